@@ -0,0 +1,236 @@
+//! agentflow 的派生宏，通过 `agentflow::AgentPayload` 使用
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::ParseStream;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Expr, Fields, Lit, LitStr, Meta};
+
+/// 为带命名字段的结构体生成 `PayloadSchema`、`AgentPayload`、`TryFrom<AgentMessage>`
+/// 和 `From<T> for StructuredMessage<T>`
+///
+/// - `#[payload(schema = "name")]` 指定 Schema 名称，默认为类型名
+/// - 文档注释作为 Schema 的 description
+/// - 识别 serde 的 `rename`、`rename_all`、`default`、`skip`；`Option<T>` 和带 `default` 的字段不是必填
+#[proc_macro_derive(AgentPayload, attributes(payload))]
+pub fn derive_agent_payload(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "AgentPayload does not support generic types",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "AgentPayload requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "AgentPayload can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut schema_name = ident.to_string();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("payload"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("schema") {
+                schema_name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("expected `schema = \"...\"`"))
+            }
+        })?;
+    }
+    let container = SerdeAttrs::parse(&input.attrs)?;
+
+    let mut properties = Vec::new();
+    for field in fields {
+        let serde = SerdeAttrs::parse(&field.attrs)?;
+        if serde.skip {
+            continue;
+        }
+        let name = match serde.rename {
+            Some(name) => name,
+            None => {
+                let name = field.ident.as_ref().expect("named field").to_string();
+                let name = name.trim_start_matches("r#");
+                match &container.rename_all {
+                    Some(rule) => rename(name, rule, field)?,
+                    None => name.to_string(),
+                }
+            }
+        };
+        let ty = &field.ty;
+        let description = doc(&field.attrs).map(|doc| quote!(.with_description(#doc)));
+        let has_default = serde.default || container.default;
+        properties.push(quote! {
+            if !#has_default && !<#ty as ::agentflow::schema::PayloadSchema>::optional() {
+                required.push(#name.to_string());
+            }
+            properties.insert(
+                #name.to_string(),
+                <#ty as ::agentflow::schema::PayloadSchema>::payload_schema() #description,
+            );
+        });
+    }
+    let description = doc(&input.attrs).map(|doc| quote!(.with_description(#doc)));
+
+    Ok(quote! {
+        impl ::agentflow::schema::PayloadSchema for #ident {
+            fn payload_schema() -> ::agentflow::schema::Schema {
+                let mut properties = ::std::collections::HashMap::new();
+                let mut required: ::std::vec::Vec<::std::string::String> = ::std::vec::Vec::new();
+                #(#properties)*
+                ::agentflow::schema::Schema::new(::agentflow::schema::SchemaKind::Object {
+                    properties,
+                    required,
+                    additional: true,
+                })
+                #description
+            }
+        }
+
+        impl ::agentflow::schema::AgentPayload for #ident {
+            const SCHEMA_NAME: &'static str = #schema_name;
+        }
+
+        impl ::std::convert::TryFrom<::agentflow::agent::AgentMessage> for #ident {
+            type Error = ::agentflow::error::AgentFlowError;
+
+            fn try_from(
+                message: ::agentflow::agent::AgentMessage,
+            ) -> ::std::result::Result<Self, Self::Error> {
+                <Self as ::agentflow::schema::AgentPayload>::from_message(&message)
+            }
+        }
+
+        impl ::std::convert::From<#ident> for ::agentflow::message::StructuredMessage<#ident> {
+            fn from(payload: #ident) -> Self {
+                <#ident as ::agentflow::schema::AgentPayload>::into_structured(payload)
+            }
+        }
+    })
+}
+
+/// 影响 JSON 结构的 serde 属性
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<LitStr>,
+    default: bool,
+    skip: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") && meta.input.peek(syn::Token![=]) {
+                    parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("rename_all") && meta.input.peek(syn::Token![=]) {
+                    parsed.rename_all = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("default") {
+                    parsed.default = true;
+                    skip_value(meta.input)?;
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                    parsed.skip = true;
+                } else {
+                    skip_value(meta.input)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+/// 跳过不关心的属性值：`= expr` 或 `(...)`
+fn skip_value(input: ParseStream) -> syn::Result<()> {
+    if input.peek(syn::Token![=]) {
+        input.parse::<syn::Token![=]>()?;
+        input.parse::<Expr>()?;
+    } else if input.peek(syn::token::Paren) {
+        let _content;
+        syn::parenthesized!(_content in input);
+    }
+    Ok(())
+}
+
+fn rename(name: &str, rule: &LitStr, field: &syn::Field) -> syn::Result<String> {
+    let words: Vec<&str> = name.split('_').filter(|word| !word.is_empty()).collect();
+    let capitalize = |word: &str| {
+        let mut chars = word.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default()
+    };
+    Ok(match rule.value().as_str() {
+        "snake_case" | "lowercase" => name.to_string(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => name.to_uppercase(),
+        "kebab-case" => words.join("-"),
+        "SCREAMING-KEBAB-CASE" => words.join("-").to_uppercase(),
+        "PascalCase" => words.iter().map(|word| capitalize(word)).collect(),
+        "camelCase" => words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| {
+                if index == 0 {
+                    word.to_string()
+                } else {
+                    capitalize(word)
+                }
+            })
+            .collect(),
+        other => {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!("unsupported serde rename_all rule `{}`", other),
+            ))
+        }
+    })
+}
+
+/// 合并文档注释
+fn doc(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(lit) => Some(lit.value().trim().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join(" "))
+    }
+}
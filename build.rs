@@ -0,0 +1,12 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/agentflow.proto");
+        let descriptors = protox::compile(["proto/agentflow.proto"], ["proto"])
+            .expect("failed to compile proto/agentflow.proto");
+        tonic_build::configure()
+            .build_client(false)
+            .compile_fds(descriptors)
+            .expect("failed to generate gRPC service");
+    }
+}
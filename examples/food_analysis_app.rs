@@ -49,11 +49,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     )?;
 
     println!("📷 使用图片: {}", image_path);
-    if image_base64.is_some() {
-        println!(
-            "✅ 图片已加载 (Base64 长度: {} 字符)",
-            image_base64.as_ref().unwrap().len()
-        );
+    if let Some(encoded) = image_base64.as_ref() {
+        println!("✅ 图片已加载 (Base64 长度: {} 字符)", encoded.len());
     }
 
     println!("\n{}", "=".repeat(80));
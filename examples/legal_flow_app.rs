@@ -62,7 +62,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 将案件详情合并到 goal 字段，确保被首个 Agent (Intake Specialist) 准确识别
     let goal_prompt = format!(
         "START_LEGAL_INTAKE_WORKFLOW\n\nCASE DATA:\n{}", 
-        case_statement.to_string()
+        case_statement
     );
 
     let input_data = serde_json::json!({
@@ -100,10 +100,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let evidence_dir = "legal_evidence";
     if let Ok(entries) = fs::read_dir(evidence_dir) {
         println!("\n🖼️  生成的法庭证据可视化文件:");
-        for entry in entries {
-            if let Ok(entry) = entry {
-                println!("   - {:?}", entry.path());
-            }
+        for entry in entries.flatten() {
+            println!("   - {:?}", entry.path());
         }
     }
 
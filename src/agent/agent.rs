@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -5,9 +7,10 @@ use serde_json::Value;
 
 use crate::error::Result;
 use crate::state::{FlowContext, FlowScopeGuard, FlowScopeKind, FlowVariables, SessionContext};
-use crate::tools::ToolInvocation;
+use crate::tools::{ToolInvocation, ToolManifest};
 
 use super::message::{AgentMessage, MessageRole};
+use super::pipeline::UpstreamStream;
 
 #[derive(Clone)]
 pub struct AgentContext<'a> {
@@ -37,6 +40,11 @@ impl<'a> AgentContext<'a> {
 pub trait AgentRuntime: Send + Sync {
     async fn call_tool(&self, name: &str, invocation: ToolInvocation) -> Result<AgentMessage>;
     async fn emit_message(&self, message: AgentMessage) -> Result<()>;
+
+    /// 可用工具的清单，用于构建函数调用的工具描述；默认没有
+    fn tool_manifests(&self) -> Vec<Arc<ToolManifest>> {
+        Vec::new()
+    }
 }
 
 #[async_trait]
@@ -53,6 +61,22 @@ pub trait Agent: Send + Sync {
     async fn on_finish(&self, _ctx: &AgentContext<'_>) -> Result<()> {
         Ok(())
     }
+
+    /// 作为流水线转移的目标时是否提前启动、通过 `on_stream` 消费上游输出；默认否
+    fn accepts_stream(&self) -> bool {
+        false
+    }
+
+    /// 流水线模式的入口：上游 Agent 仍在生成时即被调用，代替 `on_message`
+    ///
+    /// 默认读完上游输出后交给 `on_message`。
+    async fn on_stream(
+        &self,
+        upstream: UpstreamStream,
+        ctx: &AgentContext<'_>,
+    ) -> Result<AgentAction> {
+        self.on_message(upstream.into_message().await, ctx).await
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -75,6 +99,11 @@ pub enum AgentAction {
     Continue {
         message: Option<AgentMessage>,
     },
+    /// LLM 拒答（内容过滤或模型拒绝），结束流程并携带拒答信息
+    Refused {
+        refusal: crate::llm::LlmRefusal,
+        message: AgentMessage,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -117,6 +146,7 @@ where
             to: self.to,
             content,
             metadata: self.metadata,
+            attachments: Vec::new(),
         })
     }
 }
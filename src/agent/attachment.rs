@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{AgentFlowError, Result};
+use crate::state::BlobStore;
+
+/// 消息附件
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Attachment {
+    ImageUrl {
+        url: String,
+    },
+    ImageBytes {
+        mime_type: String,
+        data: BlobData,
+    },
+    File {
+        name: String,
+        mime_type: String,
+        data: BlobData,
+    },
+    Audio {
+        mime_type: String,
+        data: BlobData,
+    },
+}
+
+/// 附件内容：内联字节（序列化为 base64）或 `BlobStore` 中的 id
+///
+/// 内联字节以 `Arc` 共享，消息在入队、分支、汇合和写入历史时克隆不复制附件内容。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlobData {
+    Inline(#[serde(with = "base64_bytes")] Arc<[u8]>),
+    Blob(String),
+}
+
+impl BlobData {
+    /// 读取字节，引用 blob 时需要提供存储
+    pub async fn resolve(&self, store: Option<&dyn BlobStore>) -> Result<Vec<u8>> {
+        match self {
+            BlobData::Inline(bytes) => Ok(bytes.to_vec()),
+            BlobData::Blob(id) => match store {
+                Some(store) => store.get(id).await,
+                None => Err(AgentFlowError::Context(format!(
+                    "blob `{id}` requires a blob store"
+                ))),
+            },
+        }
+    }
+}
+
+impl Attachment {
+    pub fn image_url(url: impl Into<String>) -> Self {
+        Attachment::ImageUrl { url: url.into() }
+    }
+
+    /// `bytes` 可传 `Vec<u8>` 或已共享的 `Arc<[u8]>`
+    pub fn image_bytes(mime_type: impl Into<String>, bytes: impl Into<Arc<[u8]>>) -> Self {
+        Attachment::ImageBytes {
+            mime_type: mime_type.into(),
+            data: BlobData::Inline(bytes.into()),
+        }
+    }
+
+    pub fn file(
+        name: impl Into<String>,
+        mime_type: impl Into<String>,
+        bytes: impl Into<Arc<[u8]>>,
+    ) -> Self {
+        Attachment::File {
+            name: name.into(),
+            mime_type: mime_type.into(),
+            data: BlobData::Inline(bytes.into()),
+        }
+    }
+
+    pub fn audio(mime_type: impl Into<String>, bytes: impl Into<Arc<[u8]>>) -> Self {
+        Attachment::Audio {
+            mime_type: mime_type.into(),
+            data: BlobData::Inline(bytes.into()),
+        }
+    }
+
+    pub fn mime_type(&self) -> Option<&str> {
+        match self {
+            Attachment::ImageUrl { .. } => None,
+            Attachment::ImageBytes { mime_type, .. }
+            | Attachment::File { mime_type, .. }
+            | Attachment::Audio { mime_type, .. } => Some(mime_type),
+        }
+    }
+
+    pub fn data(&self) -> Option<&BlobData> {
+        match self {
+            Attachment::ImageUrl { .. } => None,
+            Attachment::ImageBytes { data, .. }
+            | Attachment::File { data, .. }
+            | Attachment::Audio { data, .. } => Some(data),
+        }
+    }
+
+    /// 把内联字节写入 `store`，改为按 id 引用，避免大附件随消息复制和持久化
+    pub async fn offload(mut self, store: &dyn BlobStore) -> Result<Self> {
+        let data = match &mut self {
+            Attachment::ImageUrl { .. } => return Ok(self),
+            Attachment::ImageBytes { data, .. }
+            | Attachment::File { data, .. }
+            | Attachment::Audio { data, .. } => data,
+        };
+        if let BlobData::Inline(bytes) = data {
+            *data = BlobData::Blob(store.put(bytes).await?);
+        }
+        Ok(self)
+    }
+
+    /// 读取附件字节；URL 图片不下载，返回错误
+    pub async fn load(&self, store: Option<&dyn BlobStore>) -> Result<Vec<u8>> {
+        match self.data() {
+            Some(data) => data.resolve(store).await,
+            None => Err(AgentFlowError::Context(
+                "image url attachments have no inline content".to_string(),
+            )),
+        }
+    }
+}
+
+mod base64_bytes {
+    use super::*;
+    use std::result::Result;
+
+    pub fn serialize<S: Serializer>(bytes: &Arc<[u8]>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arc<[u8]>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map(Arc::from)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentMessage;
+    use crate::state::LocalBlobStore;
+
+    #[tokio::test]
+    async fn test_attachment_roundtrip_and_offload() {
+        let message = AgentMessage::user("what is this?").with_attachment(Attachment::image_bytes(
+            "image/png",
+            vec![0x89, b'P', b'N', b'G'],
+        ));
+        let raw = serde_json::to_string(&message).unwrap();
+        assert!(raw.contains(r#""type":"image_bytes""#));
+        assert!(raw.contains(r#""inline":"iVBORw==""#));
+        let decoded: AgentMessage = serde_json::from_str(&raw).unwrap();
+        assert_eq!(decoded.attachments, message.attachments);
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalBlobStore::new(dir.path());
+        let stored = message.attachments[0]
+            .clone()
+            .offload(&store)
+            .await
+            .unwrap();
+        let Some(BlobData::Blob(id)) = stored.data() else {
+            panic!("expected blob reference");
+        };
+        assert!(dir.path().join(id).exists());
+        assert_eq!(
+            stored.load(Some(&store)).await.unwrap(),
+            vec![0x89, b'P', b'N', b'G']
+        );
+        assert!(stored.load(None).await.is_err());
+    }
+
+    #[test]
+    fn test_inline_bytes_shared_across_clones() {
+        let bytes: Arc<[u8]> = vec![0u8; 1024].into();
+        let message = AgentMessage::user("describe")
+            .with_attachment(Attachment::image_bytes("image/png", Arc::clone(&bytes)));
+        let branches: Vec<AgentMessage> = (0..3).map(|_| message.clone()).collect();
+        for branch in &branches {
+            let Some(BlobData::Inline(shared)) = branch.attachments[0].data() else {
+                panic!("expected inline bytes");
+            };
+            assert!(Arc::ptr_eq(shared, &bytes));
+        }
+        assert_eq!(Arc::strong_count(&bytes), 5);
+    }
+}
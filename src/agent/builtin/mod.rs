@@ -1,3 +1,7 @@
+mod plan;
+mod react;
+mod reflect;
+
 use std::sync::Arc;
 
 use anyhow::anyhow;
@@ -10,8 +14,14 @@ use crate::agent::{
     Agent, AgentAction, AgentContext, AgentFactoryRegistry, AgentMessage, MessageRole,
 };
 use crate::error::{AgentFlowError, Result};
+use crate::llm::DynLlmClient;
+use crate::runtime::RunUpdate;
 use crate::tools::ToolInvocation;
 
+pub use plan::{Plan, PlanExecutorAgent, PlanTask, PlannerAgent, TaskStatus, PLAN_STATE_KEY};
+pub use react::{ReActAgent, ReActStep};
+pub use reflect::{ReflectAgent, ReflectDecorator};
+
 pub struct UserProxyAgent {
     next: String,
 }
@@ -28,11 +38,24 @@ impl Agent for UserProxyAgent {
         "user_proxy"
     }
 
+    /// 上下文挂载了交互通道时，非用户发来的消息需要等待人工回复后再继续
     async fn on_message(
         &self,
         message: AgentMessage,
-        _ctx: &AgentContext<'_>,
+        ctx: &AgentContext<'_>,
     ) -> Result<AgentAction> {
+        let message = match ctx.flow().channel() {
+            Some(channel) if message.role != MessageRole::User => {
+                channel.publish(RunUpdate::AwaitingInput {
+                    agent: self.name().to_string(),
+                    message,
+                });
+                channel.next_input().await.ok_or_else(|| {
+                    AgentFlowError::Other(anyhow!("run channel closed while awaiting input"))
+                })?
+            }
+            _ => message,
+        };
         Ok(AgentAction::Next {
             target: self.next.clone(),
             message,
@@ -71,6 +94,7 @@ impl Agent for CoderAgent {
             to: Some(self.reviewer.clone()),
             content: code.clone(),
             metadata: Some(json!({ "source": "coder" })),
+            attachments: Vec::new(),
         };
         ctx.runtime.emit_message(reply.clone()).await?;
         Ok(AgentAction::Next {
@@ -82,13 +106,55 @@ impl Agent for CoderAgent {
 
 pub struct ReviewerAgent {
     coder: String,
+    verify_tool: Option<String>,
+    language: String,
 }
 
 impl ReviewerAgent {
     pub fn new<T: Into<String>>(coder: T) -> Self {
         Self {
             coder: coder.into(),
+            verify_tool: None,
+            language: "rust".to_string(),
+        }
+    }
+
+    /// 通过代码执行工具（如 `code.run`）实际编译运行代码进行审查
+    pub fn with_verify_tool<T: Into<String>>(mut self, tool: T) -> Self {
+        self.verify_tool = Some(tool.into());
+        self
+    }
+
+    /// 校验代码的默认语言（默认 `rust`），消息 metadata 中的 `language` 优先
+    pub fn with_language<T: Into<String>>(mut self, language: T) -> Self {
+        self.language = language.into();
+        self
+    }
+
+    /// 调用校验工具，失败时返回反馈文本
+    async fn verify(
+        &self,
+        tool: &str,
+        message: &AgentMessage,
+        ctx: &AgentContext<'_>,
+    ) -> Result<Option<String>> {
+        let language = message
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata["language"].as_str())
+            .unwrap_or(&self.language);
+        let invocation = ToolInvocation::new(
+            tool,
+            json!({ "language": language, "code": message.content }),
+        );
+        let response = ctx.runtime.call_tool(tool, invocation).await?;
+        let result: Value = serde_json::from_str(&response.content).unwrap_or(Value::Null);
+        if result["success"].as_bool().unwrap_or(false) {
+            return Ok(None);
         }
+        let stage = result["stage"].as_str().unwrap_or("run");
+        let stderr = result[stage]["stderr"].as_str().unwrap_or_default();
+        Ok(Some(format!("Code failed at {} stage:\n{}", stage, stderr)))
     }
 }
 
@@ -103,9 +169,15 @@ impl Agent for ReviewerAgent {
         message: AgentMessage,
         ctx: &AgentContext<'_>,
     ) -> Result<AgentAction> {
-        let needs_fix = !message.content.contains("println!");
+        let feedback_text = match &self.verify_tool {
+            Some(tool) => self.verify(tool, &message, ctx).await?,
+            None if !message.content.contains("println!") => {
+                Some("Please add println! call".to_string())
+            }
+            None => None,
+        };
         let store = ctx.flow_ctx.store();
-        if needs_fix {
+        if let Some(feedback_text) = feedback_text {
             if let Err(err) = store.set("review.status", "needs_fix".into()).await {
                 warn!(%err, "Failed to write review.status");
             }
@@ -114,8 +186,9 @@ impl Agent for ReviewerAgent {
                 role: MessageRole::Agent,
                 from: self.name().to_string(),
                 to: Some(self.coder.clone()),
-                content: "Please add println! call".into(),
-                metadata: Some(json!({ "needs_fix": true })),
+                content: feedback_text,
+                metadata: Some(json!({ "needs_fix": true, "approved": false })),
+                attachments: Vec::new(),
             };
             Ok(AgentAction::Continue {
                 message: Some(feedback),
@@ -124,7 +197,8 @@ impl Agent for ReviewerAgent {
             if let Err(err) = store.set("review.status", "pass".into()).await {
                 warn!(%err, "Failed to write review.status");
             }
-            let approval = AgentMessage::system("Review completed");
+            let mut approval = AgentMessage::system("Review completed");
+            approval.metadata = Some(json!({ "approved": true }));
             info!("Code review passed");
             Ok(AgentAction::Continue {
                 message: Some(approval),
@@ -152,6 +226,15 @@ fn extract_config<T: DeserializeOwned>(value: Option<Value>) -> Result<T> {
     serde_json::from_value(normalized).map_err(|e| AgentFlowError::Other(anyhow!(e)))
 }
 
+/// 按 Agent 配置创建 LLM 客户端，未配置或驱动不需要客户端时使用本地回显客户端
+fn llm_client(profile: Option<&crate::flow::config::AgentConfig>) -> Result<DynLlmClient> {
+    let client = match profile {
+        Some(profile) => crate::flow::services::LlmClientFactory::create_client(profile)?,
+        None => None,
+    };
+    Ok(client.unwrap_or_else(|| Arc::new(crate::llm::LocalEchoClient)))
+}
+
 pub fn register_builtin_agent_factories(registry: &mut AgentFactoryRegistry) {
     registry.register_factory(
         "user_proxy",
@@ -183,9 +266,20 @@ pub fn register_builtin_agent_factories(registry: &mut AgentFactoryRegistry) {
             #[derive(serde::Deserialize)]
             struct Conf {
                 coder: String,
+                #[serde(default)]
+                verify_tool: Option<String>,
+                #[serde(default)]
+                language: Option<String>,
             }
             let conf: Conf = extract_config(config)?;
-            Ok(Arc::new(ReviewerAgent::new(conf.coder)) as Arc<dyn Agent>)
+            let mut agent = ReviewerAgent::new(conf.coder);
+            if let Some(tool) = conf.verify_tool {
+                agent = agent.with_verify_tool(tool);
+            }
+            if let Some(language) = conf.language {
+                agent = agent.with_language(language);
+            }
+            Ok(Arc::new(agent) as Arc<dyn Agent>)
         }),
     );
 
@@ -202,6 +296,30 @@ pub fn register_builtin_agent_factories(registry: &mut AgentFactoryRegistry) {
             Ok(Arc::new(ToolInvokerAgent::new(conf.tool_name, conf.next)) as Arc<dyn Agent>)
         }),
     );
+
+    registry.register_factory(
+        "react_agent",
+        Arc::new(|config| {
+            let conf: react::ReActConfig = extract_config(config)?;
+            Ok(Arc::new(conf.build()?) as Arc<dyn Agent>)
+        }),
+    );
+
+    registry.register_factory(
+        "planner",
+        Arc::new(|config| {
+            let conf: plan::PlannerConfig = extract_config(config)?;
+            Ok(Arc::new(conf.build()?) as Arc<dyn Agent>)
+        }),
+    );
+
+    registry.register_factory(
+        "plan_executor",
+        Arc::new(|config| {
+            let conf: plan::PlanExecutorConfig = extract_config(config)?;
+            Ok(Arc::new(conf.build()) as Arc<dyn Agent>)
+        }),
+    );
 }
 
 #[async_trait]
@@ -230,3 +348,42 @@ impl Agent for ToolInvokerAgent {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, AgentRegistry};
+    use crate::flow::FlowBuilder;
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::{CodeRunTool, ToolRegistry};
+
+    #[tokio::test]
+    async fn test_reviewer_verifies_in_message_language() {
+        async fn review(reviewer: ReviewerAgent, message: AgentMessage) -> Value {
+            let mut agents = AgentRegistry::new();
+            register_agent("reviewer", Arc::new(reviewer), &mut agents);
+            let mut tools = ToolRegistry::new();
+            tools.register(Arc::new(CodeRunTool::new()));
+            let mut builder = FlowBuilder::new("review");
+            builder
+                .add_agent_node("reviewer", "reviewer")
+                .set_start("reviewer");
+            let executor = FlowExecutor::new(builder.build(), agents, tools);
+            let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+            let execution = executor.start(ctx, message).await.unwrap();
+            execution.last_message.unwrap().metadata.unwrap()
+        }
+
+        let mut message = AgentMessage::user("print('hi')");
+        message.metadata = Some(json!({ "language": "python" }));
+        let reviewer = ReviewerAgent::new("coder").with_verify_tool("code.run");
+        assert_eq!(review(reviewer, message).await["approved"], true);
+
+        let reviewer = ReviewerAgent::new("coder")
+            .with_verify_tool("code.run")
+            .with_language("python");
+        let verdict = review(reviewer, AgentMessage::user("print('hi')")).await;
+        assert_eq!(verdict["approved"], true);
+    }
+}
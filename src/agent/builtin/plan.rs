@@ -0,0 +1,766 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+use crate::agent::{Agent, AgentAction, AgentContext, AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::flow::services::routing::clean_response;
+use crate::llm::{DynLlmClient, LlmRequest};
+use crate::schema::{validate_value, Schema, SchemaError, SchemaKind};
+use crate::state::ContextStore;
+use crate::tools::ToolInvocation;
+
+/// 计划在状态中的默认键
+pub const PLAN_STATE_KEY: &str = "plan";
+
+const DEFAULT_EXECUTOR: &str = "plan_executor";
+const DEFAULT_MAX_ATTEMPTS: usize = 2;
+
+const INSTRUCTIONS: &str = "Break the user's goal into a short list of tasks. \
+Reply with exactly one JSON object and nothing else:\n\
+{\"goal\": \"...\", \"tasks\": [{\"id\": \"t1\", \"description\": \"...\", \"agent\": \"<agent>\"}, \
+{\"id\": \"t2\", \"description\": \"...\", \"tool\": \"<tool>\", \"input\": {...}, \"depends_on\": [\"t1\"]}]}\n\
+Each task sets exactly one of `agent` or `tool`; `depends_on` lists ids of earlier tasks whose results it needs.";
+
+/// 任务状态
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    #[default]
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    /// 依赖的任务失败，未执行
+    Skipped,
+}
+
+/// 计划中的任务，`agent` 和 `tool` 二选一
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlanTask {
+    pub id: String,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// 工具任务的输入，不设置时为 `{"content": 任务描述}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub status: TaskStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 规划结果，执行过程中保存在 FlowContext 状态中并更新各任务状态
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    #[serde(default)]
+    pub goal: String,
+    pub tasks: Vec<PlanTask>,
+}
+
+impl Plan {
+    /// 计划的结构 Schema（LLM 输出按此校验）
+    pub fn schema() -> Schema {
+        let string = || Schema::new(SchemaKind::String);
+        let task = Schema::new(SchemaKind::Object {
+            properties: HashMap::from([
+                ("id".to_string(), string()),
+                ("description".to_string(), string()),
+                ("agent".to_string(), string()),
+                ("tool".to_string(), string()),
+                ("input".to_string(), Schema::new(SchemaKind::Any)),
+                (
+                    "depends_on".to_string(),
+                    Schema::new(SchemaKind::Array {
+                        items: Box::new(string()),
+                    }),
+                ),
+            ]),
+            required: vec!["id".to_string(), "description".to_string()],
+            additional: true,
+        });
+        Schema::new(SchemaKind::Object {
+            properties: HashMap::from([
+                ("goal".to_string(), string()),
+                (
+                    "tasks".to_string(),
+                    Schema::new(SchemaKind::Array {
+                        items: Box::new(task),
+                    }),
+                ),
+            ]),
+            required: vec!["tasks".to_string()],
+            additional: true,
+        })
+        .with_name("plan")
+    }
+
+    /// 解析并校验 LLM 输出的计划
+    ///
+    /// `agents` / `tools` 非空时任务只能分配给其中的 Agent / 工具。
+    pub fn parse(output: &str, agents: &[String], tools: &[String]) -> Result<Self> {
+        let cleaned = clean_response(output, None);
+        let value: Value = serde_json::from_str(cleaned.trim())
+            .map_err(|e| invalid_plan(format!("output is not valid JSON ({})", e)))?;
+        validate_value(&Self::schema(), &value, &mut Vec::new()).map_err(|e| match e {
+            SchemaError::Validation { message, path } if !path.is_empty() => {
+                invalid_plan(format!("{} at `{}`", message, path.join(".")))
+            }
+            other => invalid_plan(other.to_string()),
+        })?;
+        let mut plan: Plan = serde_json::from_value(value)
+            .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        if plan.tasks.is_empty() {
+            return Err(invalid_plan("plan has no tasks"));
+        }
+
+        let mut seen = HashSet::new();
+        for task in &mut plan.tasks {
+            match (&task.agent, &task.tool) {
+                (Some(agent), None) if agents.is_empty() || agents.contains(agent) => {}
+                (None, Some(tool)) if tools.is_empty() || tools.contains(tool) => {}
+                (Some(name), None) | (None, Some(name)) => {
+                    return Err(invalid_plan(format!(
+                        "task `{}` is assigned to unknown `{}`",
+                        task.id, name
+                    )))
+                }
+                _ => {
+                    return Err(invalid_plan(format!(
+                        "task `{}` must set exactly one of `agent` or `tool`",
+                        task.id
+                    )))
+                }
+            }
+            if let Some(dependency) = task.depends_on.iter().find(|id| !seen.contains(*id)) {
+                return Err(invalid_plan(format!(
+                    "task `{}` depends on `{}`, which is not an earlier task",
+                    task.id, dependency
+                )));
+            }
+            if !seen.insert(task.id.clone()) {
+                return Err(invalid_plan(format!("duplicate task id `{}`", task.id)));
+            }
+            // 状态由执行器维护，忽略 LLM 输出中的值
+            task.status = TaskStatus::Pending;
+            task.result = None;
+            task.error = None;
+        }
+        Ok(plan)
+    }
+
+    /// 从状态中读取计划
+    pub async fn load(store: &dyn ContextStore, key: &str) -> Result<Option<Self>> {
+        match store.get(key).await? {
+            Some(raw) => serde_json::from_str(&raw)
+                .map(Some)
+                .map_err(|e| AgentFlowError::Serialization(e.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// 写回状态
+    pub async fn save(&self, store: &dyn ContextStore, key: &str) -> Result<()> {
+        let raw = serde_json::to_string(self)
+            .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        store.set(key, raw).await
+    }
+
+    pub fn task(&self, id: &str) -> Option<&PlanTask> {
+        self.tasks.iter().find(|task| task.id == id)
+    }
+
+    /// 所有任务都已结束（完成、失败或跳过）
+    pub fn is_done(&self) -> bool {
+        self.tasks
+            .iter()
+            .all(|task| !matches!(task.status, TaskStatus::Pending | TaskStatus::Running))
+    }
+
+    /// 下一个可执行的任务；依赖失败或被跳过的任务标记为跳过
+    fn next_ready(&mut self) -> Option<usize> {
+        loop {
+            let statuses: HashMap<String, TaskStatus> = self
+                .tasks
+                .iter()
+                .map(|task| (task.id.clone(), task.status))
+                .collect();
+            let index = self
+                .tasks
+                .iter()
+                .position(|task| task.status == TaskStatus::Pending)?;
+            let task = &mut self.tasks[index];
+            let blocked = task.depends_on.iter().find(|id| {
+                matches!(
+                    statuses.get(*id),
+                    Some(TaskStatus::Failed | TaskStatus::Skipped)
+                )
+            });
+            match blocked {
+                Some(id) => {
+                    task.status = TaskStatus::Skipped;
+                    task.error = Some(format!("dependency `{}` did not complete", id));
+                }
+                None => return Some(index),
+            }
+        }
+    }
+
+    /// 任务的输入文本：任务描述加上依赖任务的结果
+    fn task_prompt(&self, task: &PlanTask) -> String {
+        let mut prompt = task.description.clone();
+        let context: Vec<String> = task
+            .depends_on
+            .iter()
+            .filter_map(|id| self.task(id))
+            .filter_map(|dep| {
+                dep.result
+                    .as_ref()
+                    .map(|result| format!("[{}] {}", dep.id, result))
+            })
+            .collect();
+        if !context.is_empty() {
+            prompt.push_str("\n\nContext:\n");
+            prompt.push_str(&context.join("\n"));
+        }
+        prompt
+    }
+}
+
+fn invalid_plan(reason: impl Into<String>) -> AgentFlowError {
+    AgentFlowError::Other(anyhow!("Invalid plan: {}", reason.into()))
+}
+
+/// 规划器：让 LLM 把目标拆分为任务列表，校验后保存到状态并交给执行器
+///
+/// 输出不合法时把错误反馈给 LLM 重试，最多 `max_attempts` 次。
+pub struct PlannerAgent {
+    client: DynLlmClient,
+    agents: Vec<String>,
+    tools: Vec<String>,
+    executor: String,
+    state_key: String,
+    system_prompt: Option<String>,
+    temperature: f32,
+    max_attempts: usize,
+}
+
+impl PlannerAgent {
+    pub fn new(client: DynLlmClient) -> Self {
+        Self {
+            client,
+            agents: Vec::new(),
+            tools: Vec::new(),
+            executor: DEFAULT_EXECUTOR.to_string(),
+            state_key: PLAN_STATE_KEY.to_string(),
+            system_prompt: None,
+            temperature: 0.2,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    /// 可分配任务的 Agent（节点 ID）
+    pub fn with_agents<I, T>(mut self, agents: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.agents = agents.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 可分配任务的工具
+    pub fn with_tools<I, T>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.tools = tools.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 执行器节点 ID，默认 `plan_executor`
+    pub fn with_executor(mut self, executor: impl Into<String>) -> Self {
+        self.executor = executor.into();
+        self
+    }
+
+    /// 计划保存的状态键，默认 `plan`
+    pub fn with_state_key(mut self, key: impl Into<String>) -> Self {
+        self.state_key = key.into();
+        self
+    }
+
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// 输出不合法时的最大尝试次数，默认 2
+    pub fn with_max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    fn system(&self, ctx: &AgentContext<'_>) -> String {
+        let mut system = match &self.system_prompt {
+            Some(prompt) => format!("{}\n\n{}", prompt, INSTRUCTIONS),
+            None => INSTRUCTIONS.to_string(),
+        };
+        if !self.agents.is_empty() {
+            system.push_str(&format!("\n\nAgents: {}", self.agents.join(", ")));
+        }
+        let manifests = ctx.runtime.tool_manifests();
+        let tools: Vec<Value> = self
+            .tools
+            .iter()
+            .map(|name| {
+                manifests
+                    .iter()
+                    .find(|manifest| &manifest.name == name)
+                    .map(|manifest| manifest.function_schema())
+                    .unwrap_or_else(|| json!({ "name": name }))
+            })
+            .collect();
+        if !tools.is_empty() {
+            system.push_str(&format!(
+                "\n\nTools:\n{}",
+                serde_json::to_string_pretty(&tools).unwrap_or_default()
+            ));
+        }
+        system
+    }
+}
+
+#[async_trait]
+impl Agent for PlannerAgent {
+    fn name(&self) -> &'static str {
+        "planner"
+    }
+
+    async fn on_message(
+        &self,
+        message: AgentMessage,
+        ctx: &AgentContext<'_>,
+    ) -> Result<AgentAction> {
+        let mut system = self.system(ctx);
+        let mut attempt = 0;
+        let plan = loop {
+            attempt += 1;
+            let response = self
+                .client
+                .complete(LlmRequest {
+                    system: Some(system.clone()),
+                    user: message.content.clone(),
+                    temperature: self.temperature,
+                    metadata: None,
+                    content: Vec::new(),
+                    params: Default::default(),
+                })
+                .await?;
+            match Plan::parse(&response.content, &self.agents, &self.tools) {
+                Ok(plan) => break plan,
+                Err(err) if attempt < self.max_attempts => {
+                    warn!(attempt, error = %err, "Planner produced an invalid plan, retrying");
+                    system.push_str(&format!(
+                        "\n\nYour previous reply was rejected: {}. Reply again with a valid plan.",
+                        err
+                    ));
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        let mut plan = plan;
+        if plan.goal.is_empty() {
+            plan.goal = message.content.clone();
+        }
+        plan.save(ctx.flow_ctx.store().as_ref(), &self.state_key)
+            .await?;
+
+        let content = serde_json::to_string(&plan)
+            .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        let reply = AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Agent,
+            from: self.name().to_string(),
+            to: Some(self.executor.clone()),
+            content,
+            metadata: Some(json!({ "plan_key": self.state_key })),
+            attachments: Vec::new(),
+        };
+        ctx.runtime.emit_message(reply.clone()).await?;
+        Ok(AgentAction::Next {
+            target: self.executor.clone(),
+            message: reply,
+        })
+    }
+}
+
+/// 计划执行器：按顺序执行计划中的任务并更新状态中的计划
+///
+/// 工具任务直接调用工具；Agent 任务跳转到同名节点，该节点的输出需要回到执行器
+/// （流程中配置从该节点到执行器的边），执行器收到后把结果记录到正在执行的任务上。
+/// 所有任务结束后输出最后一个完成任务的结果，`metadata.plan` 为完整的计划。
+pub struct PlanExecutorAgent {
+    state_key: String,
+    next: Option<String>,
+}
+
+impl PlanExecutorAgent {
+    pub fn new() -> Self {
+        Self {
+            state_key: PLAN_STATE_KEY.to_string(),
+            next: None,
+        }
+    }
+
+    /// 计划保存的状态键，默认 `plan`
+    pub fn with_state_key(mut self, key: impl Into<String>) -> Self {
+        self.state_key = key.into();
+        self
+    }
+
+    /// 计划执行完成后跳转的节点，不设置时结束流程
+    pub fn with_next(mut self, next: impl Into<String>) -> Self {
+        self.next = Some(next.into());
+        self
+    }
+
+    fn finish(&self, plan: &Plan) -> AgentAction {
+        let content = plan
+            .tasks
+            .iter()
+            .rev()
+            .find_map(|task| task.result.clone())
+            .unwrap_or_default();
+        let message = AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Agent,
+            from: self.name().to_string(),
+            to: self.next.clone(),
+            content,
+            metadata: Some(json!({ "plan": plan })),
+            attachments: Vec::new(),
+        };
+        match &self.next {
+            Some(next) => AgentAction::Next {
+                target: next.clone(),
+                message,
+            },
+            None => AgentAction::Finish {
+                message: Some(message),
+            },
+        }
+    }
+}
+
+impl Default for PlanExecutorAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Agent for PlanExecutorAgent {
+    fn name(&self) -> &'static str {
+        "plan_executor"
+    }
+
+    async fn on_message(
+        &self,
+        message: AgentMessage,
+        ctx: &AgentContext<'_>,
+    ) -> Result<AgentAction> {
+        let store = ctx.flow_ctx.store();
+        let mut plan = match Plan::load(store.as_ref(), &self.state_key).await? {
+            Some(plan) => plan,
+            None => {
+                let plan = Plan::parse(&message.content, &[], &[])?;
+                plan.save(store.as_ref(), &self.state_key).await?;
+                plan
+            }
+        };
+
+        // Agent 任务的结果回到执行器
+        if let Some(task) = plan
+            .tasks
+            .iter_mut()
+            .find(|task| task.status == TaskStatus::Running && task.agent.is_some())
+        {
+            debug!(task = %task.id, "Plan task completed by agent");
+            task.status = TaskStatus::Completed;
+            task.result = Some(message.content.clone());
+        }
+
+        while let Some(index) = plan.next_ready() {
+            let prompt = plan.task_prompt(&plan.tasks[index]);
+            let task = &mut plan.tasks[index];
+            task.status = TaskStatus::Running;
+            if let Some(agent) = task.agent.clone() {
+                let metadata = json!({ "plan_task": task.id });
+                plan.save(store.as_ref(), &self.state_key).await?;
+                let message = AgentMessage {
+                    id: crate::agent::message::uuid(),
+                    role: MessageRole::Agent,
+                    from: self.name().to_string(),
+                    to: Some(agent.clone()),
+                    content: prompt,
+                    metadata: Some(metadata),
+                    attachments: Vec::new(),
+                };
+                return Ok(AgentAction::Next {
+                    target: agent,
+                    message,
+                });
+            }
+
+            let tool = task.tool.clone().unwrap_or_default();
+            let input = task
+                .input
+                .clone()
+                .unwrap_or_else(|| json!({ "content": prompt }));
+            match ctx
+                .runtime
+                .call_tool(&tool, ToolInvocation::new(&tool, input))
+                .await
+            {
+                Ok(response) => {
+                    task.status = TaskStatus::Completed;
+                    task.result = Some(response.content);
+                }
+                Err(err) => {
+                    warn!(task = %task.id, error = %err, "Plan task failed");
+                    task.status = TaskStatus::Failed;
+                    task.error = Some(err.to_string());
+                }
+            }
+            plan.save(store.as_ref(), &self.state_key).await?;
+        }
+
+        plan.save(store.as_ref(), &self.state_key).await?;
+        Ok(self.finish(&plan))
+    }
+}
+
+/// `planner` 工厂的配置
+#[derive(Deserialize)]
+pub(crate) struct PlannerConfig {
+    /// 使用的 LLM，格式同工作流中的 Agent 配置；不设置时使用本地回显客户端
+    #[serde(default)]
+    llm: Option<crate::flow::config::AgentConfig>,
+    #[serde(default)]
+    agents: Vec<String>,
+    #[serde(default)]
+    tools: Vec<String>,
+    #[serde(default)]
+    executor: Option<String>,
+    #[serde(default)]
+    state_key: Option<String>,
+    #[serde(default)]
+    system_prompt: Option<String>,
+    #[serde(default)]
+    temperature: Option<f32>,
+    #[serde(default)]
+    max_attempts: Option<usize>,
+}
+
+impl PlannerConfig {
+    pub(crate) fn build(self) -> Result<PlannerAgent> {
+        let mut agent = PlannerAgent::new(super::llm_client(self.llm.as_ref())?)
+            .with_agents(self.agents)
+            .with_tools(self.tools);
+        if let Some(executor) = self.executor {
+            agent = agent.with_executor(executor);
+        }
+        if let Some(key) = self.state_key {
+            agent = agent.with_state_key(key);
+        }
+        if let Some(prompt) = self.system_prompt {
+            agent = agent.with_system_prompt(prompt);
+        }
+        if let Some(temperature) = self.temperature {
+            agent = agent.with_temperature(temperature);
+        }
+        if let Some(attempts) = self.max_attempts {
+            agent = agent.with_max_attempts(attempts);
+        }
+        Ok(agent)
+    }
+}
+
+/// `plan_executor` 工厂的配置
+#[derive(Deserialize)]
+pub(crate) struct PlanExecutorConfig {
+    #[serde(default)]
+    state_key: Option<String>,
+    #[serde(default)]
+    next: Option<String>,
+}
+
+impl PlanExecutorConfig {
+    pub(crate) fn build(self) -> PlanExecutorAgent {
+        let mut agent = PlanExecutorAgent::new();
+        if let Some(key) = self.state_key {
+            agent = agent.with_state_key(key);
+        }
+        if let Some(next) = self.next {
+            agent = agent.with_next(next);
+        }
+        agent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::agent::{register_agent, AgentRegistry};
+    use crate::flow::FlowBuilder;
+    use crate::llm::MockLlmClient;
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::{Tool, ToolRegistry};
+
+    struct Search;
+
+    #[async_trait]
+    impl Tool for Search {
+        fn name(&self) -> &'static str {
+            "search"
+        }
+
+        async fn call(
+            &self,
+            invocation: ToolInvocation,
+            _ctx: &FlowContext,
+        ) -> Result<AgentMessage> {
+            let query = invocation.input["query"].as_str().unwrap_or_default();
+            Ok(AgentMessage::tool(
+                "search".to_string(),
+                format!("3 results for {query}"),
+            ))
+        }
+    }
+
+    struct Writer;
+
+    #[async_trait]
+    impl Agent for Writer {
+        fn name(&self) -> &'static str {
+            "writer"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Next {
+                target: "plan_executor".to_string(),
+                message: AgentMessage::user(format!("report based on: {}", message.content)),
+            })
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_plans() {
+        let agents = vec!["writer".to_string()];
+        let tools = vec!["search".to_string()];
+        for (output, reason) in [
+            ("{\"tasks\": \"none\"}", "expected array at `tasks`"),
+            ("{\"tasks\": [{\"id\": \"a\"}]}", "missing required property"),
+            (
+                "{\"tasks\": [{\"id\": \"a\", \"description\": \"x\", \"agent\": \"coder\"}]}",
+                "unknown `coder`",
+            ),
+            (
+                "{\"tasks\": [{\"id\": \"a\", \"description\": \"x\", \"tool\": \"search\", \"depends_on\": [\"b\"]}]}",
+                "not an earlier task",
+            ),
+            (
+                "{\"tasks\": [{\"id\": \"a\", \"description\": \"x\"}]}",
+                "exactly one of",
+            ),
+        ] {
+            let error = Plan::parse(output, &agents, &tools).unwrap_err().to_string();
+            assert!(error.contains(reason), "{error}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_planner_and_executor_run_plan() {
+        let llm = MockLlmClient::new().on_any("not a plan").then(
+            "```json\n{\"tasks\": [\
+             {\"id\": \"find\", \"description\": \"search sources\", \"tool\": \"search\", \"input\": {\"query\": \"rust\"}},\
+             {\"id\": \"missing\", \"description\": \"broken\", \"tool\": \"absent\"},\
+             {\"id\": \"draft\", \"description\": \"write report\", \"agent\": \"writer\", \"depends_on\": [\"find\"]},\
+             {\"id\": \"review\", \"description\": \"review\", \"agent\": \"writer\", \"depends_on\": [\"missing\"]}]}\n```",
+        );
+        let planner = PlannerAgent::new(Arc::new(llm))
+            .with_agents(["writer"])
+            .with_tools(["search", "absent"]);
+
+        let mut agents = AgentRegistry::new();
+        register_agent("planner", Arc::new(planner), &mut agents);
+        register_agent(
+            "plan_executor",
+            Arc::new(PlanExecutorAgent::new()),
+            &mut agents,
+        );
+        register_agent("writer", Arc::new(Writer), &mut agents);
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(Search));
+        let mut builder = FlowBuilder::new("plan");
+        builder
+            .add_agent_node("planner", "planner")
+            .add_agent_node("plan_executor", "plan_executor")
+            .add_agent_node("writer", "writer")
+            .set_start("planner");
+
+        let store = Arc::new(MemoryStore::new());
+        let ctx = Arc::new(FlowContext::new(store.clone()));
+        let execution = FlowExecutor::new(builder.build(), agents, tools)
+            .start(ctx, AgentMessage::user("write a report on rust"))
+            .await
+            .unwrap();
+        let message = execution.last_message.unwrap();
+        assert_eq!(
+            message.content,
+            "report based on: write report\n\nContext:\n[find] 3 results for rust"
+        );
+
+        let plan = Plan::load(store.as_ref(), PLAN_STATE_KEY)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(plan.goal, "write a report on rust");
+        let statuses: Vec<_> = plan.tasks.iter().map(|task| task.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                TaskStatus::Completed,
+                TaskStatus::Failed,
+                TaskStatus::Completed,
+                TaskStatus::Skipped
+            ]
+        );
+        assert!(plan.is_done());
+    }
+}
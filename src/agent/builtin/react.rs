@@ -0,0 +1,379 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::agent::{Agent, AgentAction, AgentContext, AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::llm::{DynLlmClient, LlmRequest};
+use crate::tools::ToolInvocation;
+
+const DEFAULT_MAX_STEPS: usize = 8;
+
+const INSTRUCTIONS: &str = "You solve the user's goal step by step using the tools below.\n\
+Reply with exactly one JSON object and nothing else:\n\
+- to call a tool: {\"thought\": \"...\", \"tool\": \"<tool name>\", \"arguments\": {...}}\n\
+- when the goal is reached: {\"thought\": \"...\", \"final_answer\": \"...\"}\n\
+Each tool result is returned to you as an observation.";
+
+/// ReAct 执行器：LLM 选择工具 → 调用工具 → 观察结果，循环直到给出最终答案或达到最大步数
+///
+/// 工具描述由运行时登记的 `ToolManifest` 自动生成；`tools` 为空时可使用所有登记了清单的工具。
+/// 每一步的工具、参数和观察结果记录在最终消息 `metadata.steps` 中。
+pub struct ReActAgent {
+    client: DynLlmClient,
+    tools: Vec<String>,
+    max_steps: usize,
+    system_prompt: Option<String>,
+    temperature: f32,
+    next: Option<String>,
+}
+
+/// 单步记录
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct ReActStep {
+    pub tool: String,
+    pub arguments: Value,
+    pub observation: String,
+}
+
+/// LLM 单步输出
+enum Decision {
+    Tool { name: String, arguments: Value },
+    Finish(String),
+}
+
+impl ReActAgent {
+    pub fn new(client: DynLlmClient) -> Self {
+        Self {
+            client,
+            tools: Vec::new(),
+            max_steps: DEFAULT_MAX_STEPS,
+            system_prompt: None,
+            temperature: 0.2,
+            next: None,
+        }
+    }
+
+    /// 限定可用的工具
+    pub fn with_tools<I, T>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.tools = tools.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 最大步数（工具调用次数），默认 8
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+
+    /// 追加在内置说明之前的系统提示词
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// 得到最终答案后跳转的节点，不设置时结束流程
+    pub fn with_next(mut self, next: impl Into<String>) -> Self {
+        self.next = Some(next.into());
+        self
+    }
+
+    /// 可用工具的函数描述
+    fn tool_schemas(&self, ctx: &AgentContext<'_>) -> Vec<Value> {
+        let manifests = ctx.runtime.tool_manifests();
+        if self.tools.is_empty() {
+            return manifests.iter().map(|m| m.function_schema()).collect();
+        }
+        self.tools
+            .iter()
+            .map(|name| {
+                manifests
+                    .iter()
+                    .find(|manifest| &manifest.name == name)
+                    .map(|manifest| manifest.function_schema())
+                    .unwrap_or_else(|| json!({ "name": name, "parameters": { "type": "object" } }))
+            })
+            .collect()
+    }
+
+    fn system(&self, tools: &[Value]) -> String {
+        let tools = serde_json::to_string_pretty(tools).unwrap_or_default();
+        match &self.system_prompt {
+            Some(prompt) => format!("{}\n\n{}\n\nTools:\n{}", prompt, INSTRUCTIONS, tools),
+            None => format!("{}\n\nTools:\n{}", INSTRUCTIONS, tools),
+        }
+    }
+
+    async fn decide(&self, system: &str, goal: &str, steps: &[ReActStep]) -> Result<Decision> {
+        let mut user = format!("Goal: {}", goal);
+        for (index, step) in steps.iter().enumerate() {
+            user.push_str(&format!(
+                "\n\nStep {}: called `{}` with {}\nObservation: {}",
+                index + 1,
+                step.tool,
+                step.arguments,
+                step.observation
+            ));
+        }
+        let response = self
+            .client
+            .complete(LlmRequest {
+                system: Some(system.to_string()),
+                user,
+                temperature: self.temperature,
+                metadata: None,
+                content: Vec::new(),
+                params: Default::default(),
+            })
+            .await?;
+        Ok(parse_decision(&response.content))
+    }
+}
+
+#[async_trait]
+impl Agent for ReActAgent {
+    fn name(&self) -> &'static str {
+        "react_agent"
+    }
+
+    async fn on_message(
+        &self,
+        message: AgentMessage,
+        ctx: &AgentContext<'_>,
+    ) -> Result<AgentAction> {
+        let tools = self.tool_schemas(ctx);
+        let allowed: Vec<&str> = tools
+            .iter()
+            .filter_map(|tool| tool["name"].as_str())
+            .collect();
+        let system = self.system(&tools);
+        let mut steps: Vec<ReActStep> = Vec::new();
+
+        loop {
+            let (name, arguments) = match self.decide(&system, &message.content, &steps).await? {
+                Decision::Finish(answer) => {
+                    let reply = AgentMessage {
+                        id: crate::agent::message::uuid(),
+                        role: MessageRole::Agent,
+                        from: self.name().to_string(),
+                        to: self.next.clone(),
+                        content: answer,
+                        metadata: Some(json!({ "steps": steps })),
+                        attachments: Vec::new(),
+                    };
+                    ctx.runtime.emit_message(reply.clone()).await?;
+                    return Ok(match &self.next {
+                        Some(next) => AgentAction::Next {
+                            target: next.clone(),
+                            message: reply,
+                        },
+                        None => AgentAction::Finish {
+                            message: Some(reply),
+                        },
+                    });
+                }
+                Decision::Tool { name, arguments } => (name, arguments),
+            };
+            if steps.len() >= self.max_steps {
+                return Err(AgentFlowError::Other(anyhow!(
+                    "ReAct agent did not reach a final answer within {} steps",
+                    self.max_steps
+                )));
+            }
+
+            // 工具错误作为观察结果返回给 LLM，由它决定如何继续
+            let observation = if !allowed.contains(&name.as_str()) {
+                format!(
+                    "Error: unknown tool `{}`; available tools: {}",
+                    name,
+                    allowed.join(", ")
+                )
+            } else {
+                let invocation = ToolInvocation::new(&name, arguments.clone());
+                match ctx.runtime.call_tool(&name, invocation).await {
+                    Ok(response) => response.content,
+                    Err(err) => format!("Error: {}", err),
+                }
+            };
+            debug!(step = steps.len() + 1, tool = %name, "ReAct step");
+            steps.push(ReActStep {
+                tool: name,
+                arguments,
+                observation,
+            });
+        }
+    }
+}
+
+/// 解析 LLM 输出；不是 JSON 或不含工具调用时作为最终答案
+fn parse_decision(content: &str) -> Decision {
+    let value = content
+        .find('{')
+        .zip(content.rfind('}'))
+        .filter(|(start, end)| start < end)
+        .and_then(|(start, end)| serde_json::from_str::<Value>(&content[start..=end]).ok());
+    let Some(value) = value else {
+        return Decision::Finish(content.trim().to_string());
+    };
+    if let Some(answer) = value.get("final_answer") {
+        return Decision::Finish(match answer {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        });
+    }
+    match value["tool"].as_str() {
+        Some(name) => Decision::Tool {
+            name: name.to_string(),
+            arguments: value.get("arguments").cloned().unwrap_or_else(|| json!({})),
+        },
+        None => Decision::Finish(content.trim().to_string()),
+    }
+}
+
+/// `react_agent` 工厂的配置
+#[derive(serde::Deserialize)]
+pub(crate) struct ReActConfig {
+    /// 使用的 LLM，格式同工作流中的 Agent 配置；不设置时使用本地回显客户端
+    #[serde(default)]
+    llm: Option<crate::flow::config::AgentConfig>,
+    #[serde(default)]
+    tools: Vec<String>,
+    #[serde(default)]
+    max_steps: Option<usize>,
+    #[serde(default)]
+    system_prompt: Option<String>,
+    #[serde(default)]
+    temperature: Option<f32>,
+    #[serde(default)]
+    next: Option<String>,
+}
+
+impl ReActConfig {
+    pub(crate) fn build(self) -> Result<ReActAgent> {
+        let mut agent = ReActAgent::new(super::llm_client(self.llm.as_ref())?).with_tools(self.tools);
+        if let Some(max_steps) = self.max_steps {
+            agent = agent.with_max_steps(max_steps);
+        }
+        if let Some(prompt) = self.system_prompt {
+            agent = agent.with_system_prompt(prompt);
+        }
+        if let Some(temperature) = self.temperature {
+            agent = agent.with_temperature(temperature);
+        }
+        if let Some(next) = self.next {
+            agent = agent.with_next(next);
+        }
+        Ok(agent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::llm::MockLlmClient;
+    use crate::runtime::ExecutorRuntime;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::{Tool, ToolManifest, ToolPort, ToolPortSchema, ToolRegistry};
+
+    struct Weather;
+
+    #[async_trait]
+    impl Tool for Weather {
+        fn name(&self) -> &'static str {
+            "weather"
+        }
+
+        async fn call(
+            &self,
+            invocation: ToolInvocation,
+            _ctx: &FlowContext,
+        ) -> Result<AgentMessage> {
+            let city = invocation.input["city"].as_str().unwrap_or_default();
+            Ok(AgentMessage::tool(
+                self.name().to_string(),
+                format!("{city}: sunny, 24°C"),
+            ))
+        }
+    }
+
+    async fn run(agent: ReActAgent) -> Result<AgentAction> {
+        let mut tools = ToolRegistry::new();
+        tools
+            .register_with_manifest(
+                Arc::new(Weather),
+                ToolManifest::builder("weather")
+                    .description("Current weather for a city")
+                    .input(
+                        ToolPort::new("city")
+                            .with_schema(ToolPortSchema::new().with_type("string"))
+                            .with_description("City name"),
+                    )
+                    .build(),
+            )
+            .unwrap();
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let runtime = ExecutorRuntime {
+            ctx: ctx.clone(),
+            tools: Arc::new(tools),
+        };
+        let agent_ctx = AgentContext {
+            flow_ctx: &ctx,
+            runtime: &runtime,
+        };
+        agent
+            .on_message(
+                AgentMessage::user("Should I bring an umbrella in Paris?"),
+                &agent_ctx,
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_react_loop_calls_tools_until_final_answer() {
+        let llm = MockLlmClient::new()
+            .on_any(r#"{"thought": "check weather", "tool": "forecast", "arguments": {}}"#)
+            .then("```json\n{\"tool\": \"weather\", \"arguments\": {\"city\": \"Paris\"}}\n```")
+            .then(r#"{"final_answer": "No umbrella needed."}"#);
+        let action = run(ReActAgent::new(Arc::new(llm.clone()))).await.unwrap();
+        let AgentAction::Finish {
+            message: Some(message),
+        } = action
+        else {
+            panic!("expected finish");
+        };
+        assert_eq!(message.content, "No umbrella needed.");
+        let steps = &message.metadata.unwrap()["steps"];
+        assert!(steps[0]["observation"]
+            .as_str()
+            .unwrap()
+            .starts_with("Error: unknown tool `forecast`"));
+        assert_eq!(steps[1]["observation"], "Paris: sunny, 24°C");
+
+        llm.assert_call_count(3);
+        let requests = llm.calls();
+        let system = requests[0].system.as_deref().unwrap();
+        assert!(system.contains("\"description\": \"City name\""));
+        assert!(requests[2].user.contains("Observation: Paris: sunny, 24°C"));
+    }
+
+    #[tokio::test]
+    async fn test_react_stops_at_max_steps() {
+        let llm =
+            MockLlmClient::new().on_any(r#"{"tool": "weather", "arguments": {"city": "Oslo"}}"#);
+        let agent = ReActAgent::new(Arc::new(llm)).with_max_steps(1);
+        let error = run(agent).await.unwrap_err();
+        assert!(error.to_string().contains("within 1 steps"));
+    }
+}
@@ -0,0 +1,289 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::agent::decorator::{action_message_mut, AgentDecorator, DecoratorContext};
+use crate::agent::{Agent, AgentAction, AgentContext, AgentMessage};
+use crate::error::{AgentFlowError, Result};
+use crate::llm::{DynLlmClient, LlmRequest};
+
+const CRITIQUE_PROMPT: &str =
+    "You review an assistant's answer to a task. List concrete problems: \
+factual errors, missing requirements, unclear wording. If the answer needs no changes, reply with \
+exactly NO_CHANGES.";
+
+const REVISION_PROMPT: &str =
+    "Rewrite the answer so that it addresses every point in the critique. \
+Reply with the revised answer only.";
+
+/// 评审认为无需修改时的回复
+const NO_CHANGES: &str = "NO_CHANGES";
+
+/// 自我反思：被包装的 Agent 回答后，用评审提示词检查答案，并按评审意见修改一次
+///
+/// 初稿、评审意见和是否修改记录在输出消息 `metadata.reflection` 中；没有输出消息的动作
+/// （Branch / CallTool）和拒答原样返回。
+pub struct ReflectAgent {
+    inner: Arc<dyn Agent>,
+    client: DynLlmClient,
+    critique_prompt: String,
+    revision_prompt: String,
+    revise: bool,
+    temperature: f32,
+}
+
+impl ReflectAgent {
+    pub fn new(inner: Arc<dyn Agent>, client: DynLlmClient) -> Self {
+        Self {
+            inner,
+            client,
+            critique_prompt: CRITIQUE_PROMPT.to_string(),
+            revision_prompt: REVISION_PROMPT.to_string(),
+            revise: true,
+            temperature: 0.2,
+        }
+    }
+
+    pub fn with_critique_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.critique_prompt = prompt.into();
+        self
+    }
+
+    pub fn with_revision_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.revision_prompt = prompt.into();
+        self
+    }
+
+    /// 关闭后只记录评审意见，不修改答案
+    pub fn with_revise(mut self, revise: bool) -> Self {
+        self.revise = revise;
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    async fn ask(&self, system: &str, user: String) -> Result<String> {
+        let response = self
+            .client
+            .complete(LlmRequest {
+                system: Some(system.to_string()),
+                user,
+                temperature: self.temperature,
+                metadata: None,
+                content: Vec::new(),
+                params: Default::default(),
+            })
+            .await?;
+        Ok(response.content.trim().to_string())
+    }
+}
+
+#[async_trait]
+impl Agent for ReflectAgent {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn on_start(&self, ctx: &AgentContext<'_>) -> Result<()> {
+        self.inner.on_start(ctx).await
+    }
+
+    async fn on_message(
+        &self,
+        message: AgentMessage,
+        ctx: &AgentContext<'_>,
+    ) -> Result<AgentAction> {
+        let task = message.content.clone();
+        let mut action = self.inner.on_message(message, ctx).await?;
+        if matches!(action, AgentAction::Refused { .. }) {
+            return Ok(action);
+        }
+        let Some(output) = action_message_mut(&mut action) else {
+            return Ok(action);
+        };
+
+        let draft = output.content.clone();
+        let exchange = format!("Task:\n{}\n\nAnswer:\n{}", task, draft);
+        let critique = self.ask(&self.critique_prompt, exchange.clone()).await?;
+        let needs_revision = self.revise && !critique.contains(NO_CHANGES);
+        if needs_revision {
+            output.content = self
+                .ask(
+                    &self.revision_prompt,
+                    format!("{}\n\nCritique:\n{}", exchange, critique),
+                )
+                .await?;
+        }
+
+        let mut metadata = match output.metadata.take() {
+            Some(Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        metadata.insert(
+            "reflection".to_string(),
+            json!({
+                "draft": draft,
+                "critique": critique,
+                "revised": needs_revision,
+            }),
+        );
+        output.metadata = Some(Value::Object(metadata));
+        Ok(action)
+    }
+
+    async fn on_finish(&self, ctx: &AgentContext<'_>) -> Result<()> {
+        self.inner.on_finish(ctx).await
+    }
+}
+
+/// 内置 `reflect` 装饰器
+///
+/// 参数：`llm`（格式同 Agent 配置，默认使用被包装 Agent 的客户端）、`critique_prompt`、
+/// `revision_prompt`、`revise`（默认 `true`）、`temperature`。
+pub struct ReflectDecorator;
+
+#[derive(serde::Deserialize, Default)]
+struct ReflectConfig {
+    #[serde(default)]
+    llm: Option<crate::flow::config::AgentConfig>,
+    #[serde(default)]
+    critique_prompt: Option<String>,
+    #[serde(default)]
+    revision_prompt: Option<String>,
+    #[serde(default)]
+    revise: Option<bool>,
+    #[serde(default)]
+    temperature: Option<f32>,
+}
+
+impl AgentDecorator for ReflectDecorator {
+    fn decorate(&self, inner: Arc<dyn Agent>, ctx: &DecoratorContext) -> Result<Arc<dyn Agent>> {
+        let conf: ReflectConfig = super::extract_config(ctx.options.clone())?;
+        let client = match &conf.llm {
+            Some(profile) => super::llm_client(Some(profile))?,
+            None => ctx.llm_client.clone().ok_or_else(|| {
+                AgentFlowError::Other(anyhow!(
+                    "reflect decorator on agent `{}` needs an LLM client",
+                    inner.name()
+                ))
+            })?,
+        };
+        let mut agent = ReflectAgent::new(inner, client);
+        if let Some(prompt) = conf.critique_prompt {
+            agent = agent.with_critique_prompt(prompt);
+        }
+        if let Some(prompt) = conf.revision_prompt {
+            agent = agent.with_revision_prompt(prompt);
+        }
+        if let Some(revise) = conf.revise {
+            agent = agent.with_revise(revise);
+        }
+        if let Some(temperature) = conf.temperature {
+            agent = agent.with_temperature(temperature);
+        }
+        Ok(Arc::new(agent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::agent::decorator::{AgentDecoratorRegistry, DecoratorSpec};
+    use crate::llm::MockLlmClient;
+    use crate::runtime::ExecutorRuntime;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+
+    struct Answer;
+
+    #[async_trait]
+    impl Agent for Answer {
+        fn name(&self) -> &'static str {
+            "answer"
+        }
+
+        async fn on_message(
+            &self,
+            _message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Continue {
+                message: Some(AgentMessage::system("Paris is in Italy.")),
+            })
+        }
+    }
+
+    async fn run(options: Value, replies: Vec<&'static str>) -> (AgentMessage, Vec<LlmRequest>) {
+        let (first, rest) = replies.split_first().expect("at least one reply");
+        let llm = rest
+            .iter()
+            .fold(MockLlmClient::new().on_any(*first), |llm, reply| {
+                llm.then(*reply)
+            });
+        let client: DynLlmClient = Arc::new(llm.clone());
+        let spec: DecoratorSpec = serde_json::from_value(options).unwrap();
+        let agent = AgentDecoratorRegistry::with_builtins()
+            .apply(Arc::new(Answer), &[spec], Some(&client))
+            .unwrap();
+        assert_eq!(agent.name(), "answer");
+
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let runtime = ExecutorRuntime {
+            ctx: ctx.clone(),
+            tools: Arc::new(ToolRegistry::new()),
+        };
+        let agent_ctx = AgentContext {
+            flow_ctx: &ctx,
+            runtime: &runtime,
+        };
+        let action = agent
+            .on_message(AgentMessage::user("Where is Paris?"), &agent_ctx)
+            .await
+            .unwrap();
+        let AgentAction::Continue {
+            message: Some(message),
+        } = action
+        else {
+            panic!("expected continue");
+        };
+        (message, llm.calls())
+    }
+
+    #[tokio::test]
+    async fn test_reflect_critiques_and_revises() {
+        let (message, requests) = run(
+            json!("reflect"),
+            vec!["Paris is in France, not Italy.", "Paris is in France."],
+        )
+        .await;
+        assert_eq!(message.content, "Paris is in France.");
+        let reflection = &message.metadata.unwrap()["reflection"];
+        assert_eq!(reflection["draft"], "Paris is in Italy.");
+        assert_eq!(reflection["revised"], true);
+        assert!(requests[0].user.contains("Task:\nWhere is Paris?"));
+        assert!(requests[1]
+            .user
+            .contains("Critique:\nParis is in France, not Italy."));
+
+        let (message, requests) = run(
+            json!({ "name": "reflect", "revise": false }),
+            vec!["Wrong country."],
+        )
+        .await;
+        assert_eq!(message.content, "Paris is in Italy.");
+        assert_eq!(
+            message.metadata.unwrap()["reflection"]["critique"],
+            "Wrong country."
+        );
+        assert_eq!(requests.len(), 1);
+
+        let (message, _) = run(json!("reflect"), vec!["NO_CHANGES"]).await;
+        assert_eq!(message.metadata.unwrap()["reflection"]["revised"], false);
+    }
+}
@@ -0,0 +1,185 @@
+//! Agent 装饰器：在不修改 Agent 实现的前提下包装其行为
+//!
+//! 配置中通过 `"decorators": ["reflect"]` 按顺序应用，列表中靠前的装饰器在内层；
+//! 对象形式（`{"name": "reflect", "revise": false}`）的其余字段作为装饰器参数。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::agent::{Agent, AgentAction};
+use super::message::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::llm::DynLlmClient;
+
+/// 装饰器配置：名称，或带参数的对象
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum DecoratorSpec {
+    Name(String),
+    Configured {
+        name: String,
+        #[serde(flatten)]
+        options: serde_json::Map<String, Value>,
+    },
+}
+
+impl DecoratorSpec {
+    pub fn name(&self) -> &str {
+        match self {
+            DecoratorSpec::Name(name) | DecoratorSpec::Configured { name, .. } => name,
+        }
+    }
+
+    /// 装饰器参数，名称形式为 `None`
+    pub fn options(&self) -> Option<Value> {
+        match self {
+            DecoratorSpec::Name(_) => None,
+            DecoratorSpec::Configured { options, .. } => Some(Value::Object(options.clone())),
+        }
+    }
+}
+
+/// 应用装饰器时可用的信息
+#[derive(Clone, Default)]
+pub struct DecoratorContext {
+    /// 被包装 Agent 配置的 LLM 客户端
+    pub llm_client: Option<DynLlmClient>,
+    pub options: Option<Value>,
+}
+
+/// Agent 装饰器
+pub trait AgentDecorator: Send + Sync {
+    fn decorate(&self, inner: Arc<dyn Agent>, ctx: &DecoratorContext) -> Result<Arc<dyn Agent>>;
+}
+
+impl<F> AgentDecorator for F
+where
+    F: Fn(Arc<dyn Agent>, &DecoratorContext) -> Result<Arc<dyn Agent>> + Send + Sync,
+{
+    fn decorate(&self, inner: Arc<dyn Agent>, ctx: &DecoratorContext) -> Result<Arc<dyn Agent>> {
+        self(inner, ctx)
+    }
+}
+
+/// 按名称登记的装饰器
+#[derive(Clone, Default)]
+pub struct AgentDecoratorRegistry {
+    decorators: HashMap<String, Arc<dyn AgentDecorator>>,
+}
+
+impl AgentDecoratorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 包含内置装饰器（`reflect`）
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register("reflect", Arc::new(super::builtin::ReflectDecorator));
+        registry
+    }
+
+    pub fn register<T: Into<String>>(&mut self, name: T, decorator: Arc<dyn AgentDecorator>) {
+        self.decorators.insert(name.into(), decorator);
+    }
+
+    pub fn has_decorator(&self, name: &str) -> bool {
+        self.decorators.contains_key(name)
+    }
+
+    /// 按顺序应用装饰器
+    pub fn apply(
+        &self,
+        agent: Arc<dyn Agent>,
+        specs: &[DecoratorSpec],
+        llm_client: Option<&DynLlmClient>,
+    ) -> Result<Arc<dyn Agent>> {
+        specs.iter().try_fold(agent, |agent, spec| {
+            let decorator = self.decorators.get(spec.name()).ok_or_else(|| {
+                AgentFlowError::Other(anyhow!("unknown agent decorator `{}`", spec.name()))
+            })?;
+            let ctx = DecoratorContext {
+                llm_client: llm_client.cloned(),
+                options: spec.options(),
+            };
+            decorator.decorate(agent, &ctx)
+        })
+    }
+}
+
+/// 动作携带的主要输出消息（Branch / CallTool 没有）
+pub fn action_message_mut(action: &mut AgentAction) -> Option<&mut AgentMessage> {
+    match action {
+        AgentAction::Next { message, .. } | AgentAction::Refused { message, .. } => Some(message),
+        AgentAction::Continue { message } | AgentAction::Finish { message } => message.as_mut(),
+        AgentAction::Branch { .. } | AgentAction::CallTool { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentContext;
+    use async_trait::async_trait;
+
+    struct Echo;
+
+    #[async_trait]
+    impl Agent for Echo {
+        fn name(&self) -> &'static str {
+            "echo"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Finish {
+                message: Some(message),
+            })
+        }
+    }
+
+    #[test]
+    fn test_apply_decorators_in_order() {
+        let specs: Vec<DecoratorSpec> =
+            serde_json::from_value(serde_json::json!(["tag", { "name": "tag", "label": "outer" }]))
+                .unwrap();
+        assert_eq!(specs[1].options().unwrap()["label"], "outer");
+
+        let mut registry = AgentDecoratorRegistry::new();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = Arc::clone(&seen);
+        registry.register(
+            "tag",
+            Arc::new(move |inner: Arc<dyn Agent>, ctx: &DecoratorContext| {
+                let label = ctx
+                    .options
+                    .as_ref()
+                    .and_then(|options| options["label"].as_str())
+                    .unwrap_or("inner");
+                record.lock().unwrap().push(label.to_string());
+                Ok(inner)
+            }),
+        );
+        registry.apply(Arc::new(Echo), &specs, None).unwrap();
+        assert_eq!(*seen.lock().unwrap(), ["inner", "outer"]);
+
+        let err = registry
+            .apply(
+                Arc::new(Echo),
+                &[DecoratorSpec::Name("missing".into())],
+                None,
+            )
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .contains("unknown agent decorator `missing`"));
+    }
+}
@@ -51,7 +51,7 @@ impl AgentPort {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct AgentPortSchema {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub type_name: Option<String>,
@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use super::attachment::Attachment;
+
+/// metadata 中幂等键的字段名
+pub const IDEMPOTENCY_KEY: &str = "idempotency_key";
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AgentMessage {
     pub id: String,
@@ -10,6 +15,9 @@ pub struct AgentMessage {
     pub content: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub metadata: Option<Value>,
+    /// 图片、文件、音频等二进制附件
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
 }
 
 impl AgentMessage {
@@ -21,6 +29,7 @@ impl AgentMessage {
             to: None,
             content: content.into(),
             metadata: None,
+            attachments: Vec::new(),
         }
     }
 
@@ -32,6 +41,7 @@ impl AgentMessage {
             to: None,
             content: content.into(),
             metadata: None,
+            attachments: Vec::new(),
         }
     }
 
@@ -43,9 +53,33 @@ impl AgentMessage {
             to: None,
             content: content.into(),
             metadata: None,
+            attachments: Vec::new(),
         }
     }
 
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// 设置幂等键：作为起始消息时，同一工作流中已使用过的键不会再次执行
+    ///
+    /// 占用记录写入上下文存储或 `FlowExecutor::with_idempotency_store`，只在共享该存储的运行之间去重。
+    /// metadata 不是对象时保持不变。
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        let metadata = self
+            .metadata
+            .get_or_insert_with(|| Value::Object(Default::default()));
+        if let Some(object) = metadata.as_object_mut() {
+            object.insert(IDEMPOTENCY_KEY.to_string(), Value::String(key.into()));
+        }
+        self
+    }
+
+    pub fn idempotency_key(&self) -> Option<&str> {
+        self.metadata.as_ref()?.get(IDEMPOTENCY_KEY)?.as_str()
+    }
+
     pub fn try_decode<T>(&self) -> Result<T, crate::error::AgentFlowError>
     where
         T: serde::de::DeserializeOwned,
@@ -72,6 +106,7 @@ impl AgentMessage {
             to,
             content,
             metadata: None,
+            attachments: Vec::new(),
         })
     }
 }
@@ -1,14 +1,21 @@
+#[allow(clippy::module_inception)]
 pub mod agent;
+pub mod attachment;
 pub mod builtin;
+pub mod decorator;
 pub mod factory;
 pub mod manifest;
 pub mod message;
+pub mod pipeline;
 pub mod registry;
 
 pub use agent::{Agent, AgentAction, AgentContext, AgentInput, AgentOutput, AgentRuntime};
+pub use attachment::{Attachment, BlobData};
+pub use decorator::{AgentDecorator, AgentDecoratorRegistry, DecoratorContext, DecoratorSpec};
 pub use factory::{AgentFactory, AgentFactoryRegistry};
 pub use manifest::{AgentManifest, AgentManifestBuilder, AgentPort, AgentPortSchema};
-pub use message::{AgentMessage, MessageRole};
+pub use message::{AgentMessage, MessageRole, IDEMPOTENCY_KEY};
+pub use pipeline::UpstreamStream;
 pub use registry::{register_agent, AgentRegistry};
 
 // Re-export uuid for backward compatibility
@@ -0,0 +1,44 @@
+//! 流水线转移中上游 Agent 的流式输出
+
+use tokio::sync::mpsc;
+
+use super::message::{AgentMessage, MessageRole};
+
+/// 上游 Agent 的输出片段，上游执行结束后关闭
+///
+/// 上游没有流式输出时（非 LLM Agent 或未启用流式），结束时一次性收到发往本节点的消息内容。
+pub struct UpstreamStream {
+    source: String,
+    receiver: mpsc::UnboundedReceiver<String>,
+}
+
+impl UpstreamStream {
+    pub fn new(source: impl Into<String>, receiver: mpsc::UnboundedReceiver<String>) -> Self {
+        Self {
+            source: source.into(),
+            receiver,
+        }
+    }
+
+    /// 上游节点名
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub async fn next(&mut self) -> Option<String> {
+        self.receiver.recv().await
+    }
+
+    /// 读取剩余片段，拼接为来自上游节点的消息
+    pub async fn into_message(mut self) -> AgentMessage {
+        let mut content = String::new();
+        while let Some(chunk) = self.receiver.recv().await {
+            content.push_str(&chunk);
+        }
+        AgentMessage {
+            role: MessageRole::Agent,
+            from: self.source,
+            ..AgentMessage::user(content)
+        }
+    }
+}
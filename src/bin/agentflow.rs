@@ -1,9 +1,17 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use agentflow::{load_plugin_manifests, schema_exports, PluginKind, PluginManifest};
+use agentflow::cli::{
+    import_workflow_files, load_plugin_manifests, load_run_snapshot, load_workflow_file,
+    migrate_workflow_file, render_graph, run_workflow, run_workflow_with_snapshot, schema_exports,
+    validate_workflow, GraphFormat, DEFAULT_SNAPSHOT_DIR,
+};
+use agentflow::{
+    workflow_schema, FlowExecution, ImportSource, PluginKind, PluginManifest, WorkflowBundle,
+};
+use anyhow::bail;
 use clap::{Parser, Subcommand};
-use serde_json::json;
+use serde_json::{json, Value};
 
 #[derive(Parser)]
 #[command(name = "agentflow", version, about = "AgentFlow CLI", author)]
@@ -14,6 +22,69 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Command {
+    /// 运行工作流
+    Run {
+        workflow: PathBuf,
+        /// GraphConfig 格式中要运行的工作流 ID（默认第一个）
+        #[arg(long = "id")]
+        workflow_id: Option<String>,
+        /// 输入 JSON 文件，字符串直接作为消息内容
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// 直接传入的输入文本
+        #[arg(long, conflicts_with = "input")]
+        text: Option<String>,
+        /// 运行结束后把上下文快照写入该目录，供 `inspect` 查看
+        #[arg(long, num_args = 0..=1, default_missing_value = DEFAULT_SNAPSHOT_DIR)]
+        snapshot_dir: Option<PathBuf>,
+    },
+    /// 查看 `run --snapshot-dir` 保存的上下文快照（存储、变量、消息历史）
+    Inspect {
+        #[arg(long = "run")]
+        run_id: String,
+        #[arg(long, default_value = DEFAULT_SNAPSHOT_DIR)]
+        dir: PathBuf,
+    },
+    /// 检查工作流配置
+    Validate {
+        #[arg(required = true)]
+        workflows: Vec<PathBuf>,
+    },
+    /// 把旧版格式（AgentSpec/FlowSpec、引入 driver 之前的配置）转换为当前格式
+    Migrate {
+        workflow: PathBuf,
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// 把 AutoGen / CrewAI / LangGraph 配置转换为工作流配置
+    Import {
+        /// 来源框架：autogen、crewai、langgraph
+        #[arg(long)]
+        from: ImportSource,
+        /// 配置文件；CrewAI 可以是 crew 文件，或 `agents.yaml` 和 `tasks.yaml`
+        #[arg(required = true, num_args = 1..=2)]
+        files: Vec<PathBuf>,
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// 导出流程图
+    Graph {
+        workflow: PathBuf,
+        #[arg(long = "id")]
+        workflow_id: Option<String>,
+        #[arg(long, default_value = "mermaid")]
+        format: GraphFormat,
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// 启动 gRPC 服务（需启用 `grpc` feature）
+    #[cfg(feature = "grpc")]
+    Serve {
+        /// 工作流 JSON 文件或目录
+        workflows: PathBuf,
+        #[arg(long, default_value = "127.0.0.1:50051")]
+        addr: std::net::SocketAddr,
+    },
     Plugins {
         #[command(subcommand)]
         command: PluginCommand,
@@ -44,6 +115,11 @@ enum SchemaCommand {
         #[arg(long, default_value_t = true)]
         pretty: bool,
     },
+    /// 导出工作流配置的 JSON Schema
+    Workflow {
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -51,7 +127,8 @@ enum FlowCommand {
     Trace { id: String },
 }
 
-fn main() -> anyhow::Result<()> {
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
     tracing_subscriber::FmtSubscriber::builder()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .with_target(false)
@@ -60,11 +137,35 @@ fn main() -> anyhow::Result<()> {
 
     let cli = Cli::parse();
     match cli.command {
+        Command::Run {
+            workflow,
+            workflow_id,
+            input,
+            text,
+            snapshot_dir,
+        } => handle_run(workflow, workflow_id, input, text, snapshot_dir).await?,
+        Command::Inspect { run_id, dir } => handle_inspect(run_id, dir)?,
+        Command::Validate { workflows } => handle_validate(workflows)?,
+        Command::Migrate { workflow, output } => handle_migrate(workflow, output)?,
+        Command::Import {
+            from,
+            files,
+            output,
+        } => handle_import(from, files, output)?,
+        Command::Graph {
+            workflow,
+            workflow_id,
+            format,
+            output,
+        } => handle_graph(workflow, workflow_id, format, output)?,
+        #[cfg(feature = "grpc")]
+        Command::Serve { workflows, addr } => handle_serve(workflows, addr).await?,
         Command::Plugins { command } => match command {
             PluginCommand::List { dir } => handle_plugins_list(dir)?,
         },
         Command::Schema { command } => match command {
             SchemaCommand::Export { output, pretty } => handle_schema_export(output, pretty)?,
+            SchemaCommand::Workflow { output } => handle_schema_workflow(output)?,
         },
         Command::Flow { command } => match command {
             FlowCommand::Trace { id } => handle_flow_trace(id)?,
@@ -73,6 +174,176 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn load_bundle(path: &Path, workflow_id: Option<&str>) -> anyhow::Result<WorkflowBundle> {
+    load_workflow_file(path, workflow_id)
+        .map_err(|e| anyhow::anyhow!("invalid workflow `{}`: {e}", path.display()))
+}
+
+async fn handle_run(
+    workflow: PathBuf,
+    workflow_id: Option<String>,
+    input: Option<PathBuf>,
+    text: Option<String>,
+    snapshot_dir: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let bundle = load_bundle(&workflow, workflow_id.as_deref())?;
+    let report = validate_workflow(&bundle);
+    if !report.is_ok() {
+        bail!(
+            "workflow `{}` is invalid:\n  {}",
+            report.flow,
+            report.errors.join("\n  ")
+        );
+    }
+    let payload = match (input, text) {
+        (Some(path), _) => serde_json::from_str(&fs::read_to_string(&path)?)?,
+        (None, Some(text)) => Value::String(text),
+        (None, None) => Value::String(String::new()),
+    };
+
+    let Some(dir) = snapshot_dir else {
+        let execution = run_workflow(bundle, payload).await?;
+        println!("{}", serde_json::to_string_pretty(&run_output(&execution))?);
+        return Ok(());
+    };
+    let run = run_workflow_with_snapshot(bundle, payload, &dir).await?;
+    let execution = match run.result {
+        Ok(execution) => execution,
+        Err(err) => bail!(
+            "run `{}` failed: {err}\n  snapshot: {} (agentflow inspect --run {} --dir {})",
+            run.run_id,
+            run.snapshot.display(),
+            run.run_id,
+            dir.display()
+        ),
+    };
+    let mut result = run_output(&execution);
+    result["run_id"] = json!(run.run_id);
+    result["snapshot"] = json!(run.snapshot);
+    println!("{}", serde_json::to_string_pretty(&result)?);
+    Ok(())
+}
+
+fn run_output(execution: &FlowExecution) -> Value {
+    let output = execution.last_message.as_ref().map(|message| {
+        serde_json::from_str::<Value>(&message.content)
+            .unwrap_or_else(|_| Value::String(message.content.clone()))
+    });
+    json!({
+        "flow": execution.flow_name,
+        "last_node": execution.last_node,
+        "output": output,
+        "errors": execution.errors,
+    })
+}
+
+fn handle_inspect(run_id: String, dir: PathBuf) -> anyhow::Result<()> {
+    let dump = load_run_snapshot(&dir, &run_id)?;
+    println!("{}", dump.to_json()?);
+    Ok(())
+}
+
+fn handle_validate(workflows: Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut failed = 0;
+    for path in &workflows {
+        let report = match load_bundle(path, None) {
+            Ok(bundle) => validate_workflow(&bundle),
+            Err(err) => {
+                failed += 1;
+                println!("✗ {}: {err}", path.display());
+                continue;
+            }
+        };
+        if report.is_ok() {
+            println!("✓ {} ({})", path.display(), report.flow);
+        } else {
+            failed += 1;
+            println!("✗ {} ({})", path.display(), report.flow);
+        }
+        for error in &report.errors {
+            println!("    error: {error}");
+        }
+        for warning in &report.warnings {
+            println!("    warning: {warning}");
+        }
+        for component in &report.analysis.strongly_connected_components {
+            println!("    cycle: {}", component.join(", "));
+        }
+        match report.analysis.max_depth {
+            Some(depth) => println!("    max depth: {depth}"),
+            None => println!("    max depth: unbounded"),
+        }
+    }
+    if failed > 0 {
+        bail!(
+            "{failed} of {} workflow(s) failed validation",
+            workflows.len()
+        );
+    }
+    Ok(())
+}
+
+fn handle_migrate(workflow: PathBuf, output: Option<PathBuf>) -> anyhow::Result<()> {
+    let config = migrate_workflow_file(&workflow)?;
+    let content = serde_json::to_string_pretty(&config)?;
+    if let Some(path) = output {
+        fs::write(&path, content)?;
+        println!("Migrated workflow written to `{}`", path.display());
+    } else {
+        println!("{content}");
+    }
+    Ok(())
+}
+
+fn handle_import(
+    from: ImportSource,
+    files: Vec<PathBuf>,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let config = import_workflow_files(from, &files)?;
+    let content = serde_json::to_string_pretty(&config)?;
+    if let Some(path) = output {
+        fs::write(&path, content)?;
+        println!("Imported workflow written to `{}`", path.display());
+    } else {
+        println!("{content}");
+    }
+    Ok(())
+}
+
+fn handle_graph(
+    workflow: PathBuf,
+    workflow_id: Option<String>,
+    format: GraphFormat,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let bundle = load_bundle(&workflow, workflow_id.as_deref())?;
+    let content = render_graph(&bundle.flow, format);
+    if let Some(path) = output {
+        fs::write(&path, content)?;
+        println!("Graph exported to `{}`", path.display());
+    } else {
+        print!("{content}");
+    }
+    Ok(())
+}
+
+#[cfg(feature = "grpc")]
+async fn handle_serve(workflows: PathBuf, addr: std::net::SocketAddr) -> anyhow::Result<()> {
+    let mut registry = agentflow::FlowRegistry::new();
+    let names = registry.reload_from(&workflows)?;
+    println!(
+        "Serving {} workflow(s) on {addr}: {}",
+        names.len(),
+        names.join(", ")
+    );
+    tonic::transport::Server::builder()
+        .add_service(agentflow::grpc::GrpcFlowService::new(registry).into_server())
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
 fn handle_plugins_list(dir: PathBuf) -> anyhow::Result<()> {
     let manifests = load_plugin_manifests(&dir)?;
     if manifests.is_empty() {
@@ -84,10 +355,7 @@ fn handle_plugins_list(dir: PathBuf) -> anyhow::Result<()> {
 }
 
 fn render_plugin_table(manifests: &[PluginManifest]) {
-    println!(
-        "{:<32} {:<10} {:<10} {}",
-        "Name", "Version", "Kind", "Description"
-    );
+    println!("{:<32} {:<10} {:<10} Description", "Name", "Version", "Kind");
     for manifest in manifests {
         let description = manifest.description.clone().unwrap_or_default();
         println!(
@@ -128,6 +396,17 @@ fn handle_schema_export(output: Option<PathBuf>, pretty: bool) -> anyhow::Result
     Ok(())
 }
 
+fn handle_schema_workflow(output: Option<PathBuf>) -> anyhow::Result<()> {
+    let content = serde_json::to_string_pretty(workflow_schema())?;
+    if let Some(path) = output {
+        fs::write(&path, content)?;
+        println!("Workflow schema exported to `{}`", path.display());
+    } else {
+        println!("{content}");
+    }
+    Ok(())
+}
+
 fn handle_flow_trace(id: String) -> anyhow::Result<()> {
     println!(
         "Flow trace `{}` is not persisted yet. Please enable event storage before querying.",
@@ -0,0 +1,42 @@
+use std::io::Write;
+
+use parking_lot::Mutex;
+
+use crate::runtime::TokenSink;
+
+/// 命令行的流式输出：把 LLM 输出片段写到标准错误，标准输出只保留运行结果
+///
+/// 每个 Agent 的输出前加 `[agent]` 前缀，一次调用结束后换行。
+#[derive(Default)]
+pub struct ConsoleTokenSink {
+    /// 正在输出的 Agent
+    current: Mutex<Option<String>>,
+}
+
+impl ConsoleTokenSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TokenSink for ConsoleTokenSink {
+    fn on_token(&self, agent: &str, token: &str) {
+        let mut current = self.current.lock();
+        let mut stderr = std::io::stderr().lock();
+        if current.as_deref() != Some(agent) {
+            if current.is_some() {
+                let _ = writeln!(stderr);
+            }
+            let _ = write!(stderr, "[{}] ", agent);
+            *current = Some(agent.to_string());
+        }
+        let _ = write!(stderr, "{}", token);
+        let _ = stderr.flush();
+    }
+
+    fn on_done(&self, _agent: &str) {
+        if self.current.lock().take().is_some() {
+            let _ = writeln!(std::io::stderr());
+        }
+    }
+}
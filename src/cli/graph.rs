@@ -0,0 +1,199 @@
+//! 流程图导出（Mermaid / Graphviz DOT）
+
+use std::fmt::Write;
+
+use crate::flow::analysis::flow_edges;
+use crate::flow::{Flow, FlowNodeKind, WaitNode};
+
+/// 流程图格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    Mermaid,
+    Dot,
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            "dot" | "graphviz" => Ok(GraphFormat::Dot),
+            other => Err(format!("unknown graph format `{other}`")),
+        }
+    }
+}
+
+fn sorted_nodes(flow: &Flow) -> Vec<&String> {
+    let mut names: Vec<&String> = flow.nodes.keys().collect();
+    names.sort();
+    names
+}
+
+fn node_detail(kind: &FlowNodeKind) -> Option<String> {
+    match kind {
+        FlowNodeKind::Agent(agent) => Some(format!("agent: {agent}")),
+        FlowNodeKind::Tool(tool) => Some(match &tool.compensate {
+            Some(compensate) => format!("pipeline: {}, compensate: {}", tool.pipeline, compensate),
+            None => format!("pipeline: {}", tool.pipeline),
+        }),
+        FlowNodeKind::SubFlow(subflow) => Some(format!("flow: {}", subflow.flow)),
+        FlowNodeKind::ImageGen(image) => Some(format!("image: {}", image.config.model)),
+        FlowNodeKind::Experiment(experiment) => {
+            Some(format!("experiment: {}", experiment.experiment))
+        }
+        FlowNodeKind::Join(join) => Some(format!("join: {:?}", join.strategy).to_lowercase()),
+        FlowNodeKind::Loop(loop_node) => loop_node.max_iterations.map(|max| format!("max: {max}")),
+        FlowNodeKind::Map(map) => Some(format!("map: {}", map.over)),
+        FlowNodeKind::Wait(WaitNode::Delay(duration)) => Some(format!("wait: {:?}", duration)),
+        FlowNodeKind::Wait(WaitNode::UntilState(until)) => Some(match &until.value {
+            Some(value) => format!("wait: {} == {}", until.key, value),
+            None => format!("wait: {}", until.key),
+        }),
+        FlowNodeKind::AwaitEvent(await_event) => Some(format!(
+            "await event: {}",
+            await_event.token_key.as_deref().unwrap_or("resume_token")
+        )),
+        FlowNodeKind::Transform(transform) => {
+            Some(format!("transform: {} ops", transform.ops.len()))
+        }
+        FlowNodeKind::Debate(debate) => Some(format!(
+            "debate: {} / {} (max {})",
+            debate.proposer, debate.critic, debate.max_rounds
+        )),
+        FlowNodeKind::GroupChat(chat) => Some(format!(
+            "group chat: {} (max {})",
+            chat.participants.join(", "),
+            chat.max_turns
+        )),
+        FlowNodeKind::Terminal | FlowNodeKind::Decision(_) | FlowNodeKind::LlmDecision(_) => None,
+    }
+}
+
+/// 渲染流程图
+pub fn render_graph(flow: &Flow, format: GraphFormat) -> String {
+    match format {
+        GraphFormat::Mermaid => render_mermaid(flow),
+        GraphFormat::Dot => render_dot(flow),
+    }
+}
+
+fn mermaid_id(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn mermaid_text(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+fn render_mermaid(flow: &Flow) -> String {
+    let mut out = String::from("flowchart TD\n");
+    for name in sorted_nodes(flow) {
+        let node = &flow.nodes[name];
+        let label = match node_detail(&node.kind) {
+            Some(detail) => format!("{}<br/>{}", mermaid_text(name), mermaid_text(&detail)),
+            None => mermaid_text(name),
+        };
+        let id = mermaid_id(name);
+        let _ = match node.kind {
+            FlowNodeKind::Decision(_)
+            | FlowNodeKind::LlmDecision(_)
+            | FlowNodeKind::Experiment(_) => {
+                writeln!(out, "    {id}{{\"{label}\"}}")
+            }
+            FlowNodeKind::Terminal => writeln!(out, "    {id}((\"{label}\"))"),
+            FlowNodeKind::Join(_) | FlowNodeKind::Loop(_) | FlowNodeKind::Map(_) => {
+                writeln!(out, "    {id}[/\"{label}\"/]")
+            }
+            _ => writeln!(out, "    {id}[\"{label}\"]"),
+        };
+    }
+    if flow.nodes.contains_key(&flow.start) {
+        let _ = writeln!(out, "    __start((start)) --> {}", mermaid_id(&flow.start));
+    }
+    for (from, to, label) in flow_edges(flow) {
+        let _ = match label {
+            Some(label) => writeln!(
+                out,
+                "    {} -->|\"{}\"| {}",
+                mermaid_id(&from),
+                mermaid_text(&label),
+                mermaid_id(&to)
+            ),
+            None => writeln!(out, "    {} --> {}", mermaid_id(&from), mermaid_id(&to)),
+        };
+    }
+    out
+}
+
+fn dot_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn render_dot(flow: &Flow) -> String {
+    let mut out = format!("digraph \"{}\" {{\n    rankdir=TB;\n", dot_text(&flow.name));
+    for name in sorted_nodes(flow) {
+        let node = &flow.nodes[name];
+        let shape = match node.kind {
+            FlowNodeKind::Decision(_)
+            | FlowNodeKind::LlmDecision(_)
+            | FlowNodeKind::Experiment(_) => "diamond",
+            FlowNodeKind::Terminal => "doublecircle",
+            FlowNodeKind::Join(_) | FlowNodeKind::Loop(_) | FlowNodeKind::Map(_) => "parallelogram",
+            _ => "box",
+        };
+        let label = match node_detail(&node.kind) {
+            Some(detail) => format!("{}\\n{}", dot_text(name), dot_text(&detail)),
+            None => dot_text(name),
+        };
+        let _ = writeln!(
+            out,
+            "    \"{}\" [shape={shape}, label=\"{label}\"];",
+            dot_text(name)
+        );
+    }
+    for (from, to, label) in flow_edges(flow) {
+        let attrs = label
+            .map(|label| format!(" [label=\"{}\"]", dot_text(&label)))
+            .unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "    \"{}\" -> \"{}\"{attrs};",
+            dot_text(&from),
+            dot_text(&to)
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::{condition_state_equals, FlowBuilder};
+
+    #[test]
+    fn test_render_mermaid() {
+        let mut builder = FlowBuilder::new("review");
+        builder
+            .add_agent_node("draft", "writer")
+            .add_terminal_node("done")
+            .set_start("draft")
+            .connect_conditional_described(
+                "draft",
+                "done",
+                None,
+                condition_state_equals("status", "ok"),
+                crate::flow::ConditionInfo::new("status == \"ok\""),
+            );
+        let mermaid = render_graph(&builder.build(), GraphFormat::Mermaid);
+        assert!(mermaid.starts_with("flowchart TD\n"));
+        assert!(mermaid.contains("draft[\"draft<br/>agent: writer\"]"));
+        assert!(mermaid.contains("done((\"done\"))"));
+        assert!(mermaid.contains("__start((start)) --> draft"));
+        assert!(mermaid.contains("draft -->|\"status == #quot;ok#quot;\"| done"));
+        assert_eq!("graphviz".parse::<GraphFormat>(), Ok(GraphFormat::Dot));
+    }
+}
@@ -1,9 +1,30 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use serde::Serialize;
+use serde_json::Value;
 
+use anyhow::anyhow;
+
+use crate::agent::AgentMessage;
+use crate::config::migrate::migrate_workflow;
+use crate::config::GraphConfig;
+use crate::error::AgentFlowError;
+use crate::flow::config::validate_workflow_config;
+use crate::flow::import::ImportSource;
+use crate::flow::loader::{load_workflow_from_value, WorkflowBundle};
 use crate::plugin::{PluginError, PluginManifest, PluginRegistry};
+use crate::runtime::{FlowExecution, FlowExecutor};
 use crate::schema::{schemas_snapshot, Schema};
+use crate::state::{FlowContext, FlowContextDump, MemoryStore};
+
+mod console;
+mod graph;
+mod validate;
+
+pub use console::ConsoleTokenSink;
+pub use graph::{render_graph, GraphFormat};
+pub use validate::{validate_workflow, ValidationReport};
 
 #[derive(Clone, Debug, Serialize)]
 pub struct SchemaExportEntry {
@@ -23,3 +44,182 @@ pub fn schema_exports() -> Vec<SchemaExportEntry> {
         .map(|(name, schema)| SchemaExportEntry { name, schema })
         .collect()
 }
+
+/// 读取工作流文件
+///
+/// 支持 `{"agents", "tools", "flow"}` 格式和 `GraphConfig`（`nodes` / `edges`）格式；
+/// 后者包含多个工作流时用 `workflow_id` 指定，未指定时取第一个。
+pub fn load_workflow_file(
+    path: &Path,
+    workflow_id: Option<&str>,
+) -> Result<WorkflowBundle, AgentFlowError> {
+    let value = read_workflow_value(path)?;
+    if value.get("flow").is_some() {
+        return load_workflow_from_value(&value);
+    }
+
+    let graph = GraphConfig::from_value(value)?;
+    let workflow_id = match workflow_id {
+        Some(id) => id.to_string(),
+        None => graph
+            .get_workflows()
+            .first()
+            .map(|node| node.id.clone())
+            .ok_or_else(|| {
+                AgentFlowError::Other(anyhow!("No workflow found in `{}`", path.display()))
+            })?,
+    };
+    graph.load_workflow(&workflow_id)
+}
+
+/// 把旧版格式的工作流文件转换为当前的 `WorkflowConfig`，转换结果会通过 Schema 校验
+pub fn migrate_workflow_file(path: &Path) -> Result<Value, AgentFlowError> {
+    let value = read_workflow_value(path)?;
+    let migrated = migrate_workflow(&value)?.into_owned();
+    validate_workflow_config(&migrated)?;
+    Ok(migrated)
+}
+
+/// 把其他框架（AutoGen、CrewAI、LangGraph）的配置文件转换为 `WorkflowConfig`，转换结果会通过 Schema 校验
+///
+/// CrewAI 可以传入一个包含 `agents` / `tasks` 的文件，也可以依次传入 `agents.yaml` 和 `tasks.yaml`。
+pub fn import_workflow_files<P: AsRef<Path>>(
+    source: ImportSource,
+    paths: &[P],
+) -> Result<Value, AgentFlowError> {
+    let value = match (source, paths) {
+        (_, [path]) => read_workflow_value(path.as_ref())?,
+        (ImportSource::Crewai, [agents, tasks]) => {
+            read_crew_files(agents.as_ref(), tasks.as_ref())?
+        }
+        (ImportSource::Crewai, _) => {
+            return Err(AgentFlowError::Other(anyhow!(
+                "CrewAI import expects a crew file or `agents.yaml` and `tasks.yaml`"
+            )))
+        }
+        (source, _) => {
+            return Err(AgentFlowError::Other(anyhow!(
+                "{:?} import expects a single config file",
+                source
+            )))
+        }
+    };
+    let workflow = source.to_workflow(&value)?;
+    validate_workflow_config(&workflow)?;
+    Ok(workflow)
+}
+
+/// 读取 CrewAI 的 `agents` / `tasks` 文件；两个都是 YAML 时保留任务的书写顺序
+fn read_crew_files(agents: &Path, tasks: &Path) -> Result<Value, AgentFlowError> {
+    #[cfg(feature = "yaml")]
+    if is_yaml(agents) && is_yaml(tasks) {
+        return crate::flow::import::parse_crewai_yaml(&read_text(agents)?, &read_text(tasks)?);
+    }
+    Ok(serde_json::json!({
+        "agents": read_workflow_value(agents)?,
+        "tasks": read_workflow_value(tasks)?,
+    }))
+}
+
+fn read_text(path: &Path) -> Result<String, AgentFlowError> {
+    std::fs::read_to_string(path)
+        .map_err(|e| AgentFlowError::Other(anyhow!("Failed to read `{}`: {}", path.display(), e)))
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "yaml" || ext == "yml")
+}
+
+/// 读取 JSON 或 YAML（`.yaml` / `.yml`，需启用 `yaml` feature）文件
+fn read_workflow_value(path: &Path) -> Result<Value, AgentFlowError> {
+    let content = read_text(path)?;
+    if is_yaml(path) {
+        #[cfg(feature = "yaml")]
+        return crate::config::migrate::parse_yaml(&content);
+        #[cfg(not(feature = "yaml"))]
+        return Err(AgentFlowError::Other(anyhow!(
+            "`{}` is a YAML file, enable the `yaml` feature to load it",
+            path.display()
+        )));
+    }
+    serde_json::from_str(&content).map_err(|e| AgentFlowError::Serialization(e.to_string()))
+}
+
+/// `agentflow run --snapshot-dir` / `agentflow inspect` 的默认快照目录
+pub const DEFAULT_SNAPSHOT_DIR: &str = ".agentflow/runs";
+
+/// 用内存存储运行一次工作流
+///
+/// 字符串输入包装为 `{"raw": ..., "steps": []}`，对象输入缺少 `steps` 时自动补上，
+/// 与配置驱动 Agent 的输入格式一致。LLM 流式输出通过 `ConsoleTokenSink` 写到标准错误。
+pub async fn run_workflow(
+    bundle: WorkflowBundle,
+    input: Value,
+) -> Result<FlowExecution, AgentFlowError> {
+    let executor = console_executor(bundle);
+    let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+    executor.start(ctx, input_message(input)).await
+}
+
+/// 保存了上下文快照的一次运行
+pub struct SnapshotRun {
+    pub run_id: String,
+    /// 快照文件路径
+    pub snapshot: PathBuf,
+    pub result: Result<FlowExecution, AgentFlowError>,
+}
+
+/// 与 `run_workflow` 相同，运行结束（成功或失败）后把上下文快照写入 `{dir}/{run_id}.json`
+pub async fn run_workflow_with_snapshot(
+    bundle: WorkflowBundle,
+    input: Value,
+    dir: &Path,
+) -> Result<SnapshotRun, AgentFlowError> {
+    let executor = console_executor(bundle);
+    let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+    let run_id = crate::agent::message::uuid();
+    let result = executor
+        .start_with_run_id(Arc::clone(&ctx), input_message(input), &run_id)
+        .await;
+
+    let snapshot = snapshot_path(dir, &run_id);
+    std::fs::create_dir_all(dir).map_err(|e| {
+        AgentFlowError::Other(anyhow!("Failed to create `{}`: {}", dir.display(), e))
+    })?;
+    std::fs::write(&snapshot, ctx.dump().await?.to_json()?).map_err(|e| {
+        AgentFlowError::Other(anyhow!("Failed to write `{}`: {}", snapshot.display(), e))
+    })?;
+    Ok(SnapshotRun {
+        run_id,
+        snapshot,
+        result,
+    })
+}
+
+/// 读取 `run_workflow_with_snapshot` 保存的上下文快照
+pub fn load_run_snapshot(dir: &Path, run_id: &str) -> Result<FlowContextDump, AgentFlowError> {
+    FlowContextDump::from_json(&read_text(&snapshot_path(dir, run_id))?)
+}
+
+fn console_executor(bundle: WorkflowBundle) -> FlowExecutor {
+    FlowExecutor::new(bundle.flow, bundle.agents, bundle.tools)
+        .with_token_sink(Arc::new(ConsoleTokenSink::new()))
+}
+
+fn snapshot_path(dir: &Path, run_id: &str) -> PathBuf {
+    dir.join(format!("{}.json", run_id))
+}
+
+fn input_message(input: Value) -> AgentMessage {
+    let payload = match input {
+        Value::String(text) => serde_json::json!({ "raw": text, "steps": [] }),
+        Value::Object(mut map) => {
+            map.entry("steps")
+                .or_insert_with(|| Value::Array(Vec::new()));
+            Value::Object(map)
+        }
+        other => other,
+    };
+    AgentMessage::user(payload.to_string())
+}
@@ -0,0 +1,156 @@
+//! 工作流静态检查
+
+use serde::Serialize;
+
+use crate::flow::analysis::flow_edges;
+use crate::flow::loader::WorkflowBundle;
+use crate::flow::{FlowAnalysis, FlowNodeKind};
+
+/// 检查结果：`errors` 会导致运行失败，`warnings` 仅提示
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ValidationReport {
+    pub flow: String,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    /// 环、可达性和最大执行深度
+    pub analysis: FlowAnalysis,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// 检查起始节点、边的目标、Agent 引用，并附带图分析结果
+pub fn validate_workflow(bundle: &WorkflowBundle) -> ValidationReport {
+    let flow = &bundle.flow;
+    let mut report = ValidationReport {
+        flow: flow.name.clone(),
+        ..Default::default()
+    };
+
+    if !flow.nodes.contains_key(&flow.start) {
+        report
+            .errors
+            .push(format!("start node `{}` does not exist", flow.start));
+    }
+
+    let mut names: Vec<&String> = flow.nodes.keys().collect();
+    names.sort();
+    for name in &names {
+        match &flow.nodes[*name].kind {
+            FlowNodeKind::Agent(agent) if !bundle.agents.contains_key(agent) => {
+                report
+                    .errors
+                    .push(format!("node `{name}` references unknown agent `{agent}`"));
+            }
+            FlowNodeKind::Debate(debate) => {
+                for agent in [&debate.proposer, &debate.critic] {
+                    if !bundle.agents.contains_key(agent) {
+                        report.errors.push(format!(
+                            "debate `{name}` references unknown agent `{agent}`"
+                        ));
+                    }
+                }
+            }
+            FlowNodeKind::GroupChat(chat) => {
+                for agent in &chat.participants {
+                    if !bundle.agents.contains_key(agent) {
+                        report.errors.push(format!(
+                            "group chat `{name}` references unknown agent `{agent}`"
+                        ));
+                    }
+                }
+            }
+            FlowNodeKind::Join(join) => {
+                for inbound in &join.inbound {
+                    if !flow.nodes.contains_key(inbound) {
+                        report
+                            .errors
+                            .push(format!("join `{name}` waits for unknown node `{inbound}`"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for name in flow.transitions.keys() {
+        if !flow.nodes.contains_key(name) {
+            report
+                .errors
+                .push(format!("transition starts from unknown node `{name}`"));
+        }
+    }
+
+    let edges = flow_edges(flow);
+    for (from, to, _) in &edges {
+        if !flow.nodes.contains_key(to) {
+            report
+                .errors
+                .push(format!("`{from}` points to unknown node `{to}`"));
+        }
+    }
+
+    // Agent 可以通过输出中的 `route` 动态跳转，图分析的结果只作为警告
+    let analysis = flow.analyze();
+    for name in &analysis.unreachable {
+        report.warnings.push(format!(
+            "node `{name}` is not reachable from `{}`",
+            flow.start
+        ));
+    }
+    for name in &analysis.dead_ends {
+        report
+            .warnings
+            .push(format!("node `{name}` has no outgoing edges"));
+    }
+    for cycle in &analysis.unbounded_cycles {
+        report.warnings.push(format!(
+            "cycle `{}` has no `max_iterations` bound",
+            cycle.join(" -> ")
+        ));
+    }
+    report.analysis = analysis;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::loader::load_workflow_from_value;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_reports_broken_references() {
+        let bundle = load_workflow_from_value(&json!({
+            "flow": {
+                "name": "broken",
+                "start": "draft",
+                "nodes": [
+                    { "kind": "agent", "name": "draft", "agent": "writer" },
+                    { "kind": "terminal", "name": "done" },
+                    { "kind": "terminal", "name": "orphan" }
+                ],
+                "transitions": [
+                    { "from": "draft", "to": "done" },
+                    { "from": "draft", "to": "missing" }
+                ]
+            }
+        }))
+        .unwrap();
+
+        let report = validate_workflow(&bundle);
+        assert!(!report.is_ok());
+        assert!(report
+            .errors
+            .contains(&"node `draft` references unknown agent `writer`".to_string()));
+        assert!(report
+            .errors
+            .contains(&"`draft` points to unknown node `missing`".to_string()));
+        assert_eq!(
+            report.warnings,
+            vec!["node `orphan` is not reachable from `draft`".to_string()]
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use super::agent_rules::AgentRules;
 use super::graph::GraphNode;
 use crate::error::{AgentFlowError, Result};
+use crate::llm::LlmParams;
 use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -38,6 +39,21 @@ pub struct AgentConfig {
     /// 业务规则配置
     #[serde(default)]
     pub rules: Option<AgentRules>,
+    /// 输出 Schema（Schema 名称或内联定义）
+    #[serde(default)]
+    pub output_schema: Option<Value>,
+    /// 输出校验失败时的处理策略
+    #[serde(default)]
+    pub on_invalid_output: Option<Value>,
+    /// 提供商拒答时的处理策略
+    #[serde(default)]
+    pub on_refusal: Option<Value>,
+    /// 知识库检索配置（rag 驱动）
+    #[serde(default)]
+    pub retrieval: Option<Value>,
+    /// 采样参数（`top_p`、`max_tokens`、`stop`、`seed` 等）
+    #[serde(flatten)]
+    pub params: LlmParams,
 }
 
 impl GraphNode {
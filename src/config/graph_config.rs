@@ -10,7 +10,8 @@ pub use super::agent_rules::{
 pub use super::conditions::Condition;
 pub use super::graph::{GraphConfig, GraphEdge, GraphNode};
 pub use super::nodes::{
-    DecisionBranchConfig, DecisionNodeConfig, JoinNodeConfig, LoopNodeConfig, WorkflowConfig,
+    DecisionBranchConfig, DecisionNodeConfig, JoinNodeConfig, LoopNodeConfig, SubFlowNodeConfig,
+    WorkflowConfig,
 };
 
 #[cfg(test)]
@@ -1,6 +1,7 @@
 use super::conditions::Condition;
 use super::graph::{GraphConfig, GraphNode};
 use crate::error::{AgentFlowError, Result};
+use crate::flow::constants::fields;
 use crate::flow::loader::WorkflowBundle;
 use anyhow::anyhow;
 use serde_json::{json, Value};
@@ -47,16 +48,16 @@ impl GraphConfig {
                     }
                     
                     if let Some(route_mode) = &agent_config.route_mode {
-                        agent_json["route_mode"] = json!(route_mode);
+                        agent_json[fields::ROUTE_MODE] = json!(route_mode);
                     }
                     if let Some(route_targets) = &agent_config.route_targets {
-                        agent_json["route_targets"] = json!(route_targets);
+                        agent_json[fields::ROUTE_TARGETS] = json!(route_targets);
                     }
                     if let Some(route_prompt) = &agent_config.route_prompt {
-                        agent_json["route_prompt"] = json!(route_prompt);
+                        agent_json[fields::ROUTE_PROMPT] = json!(route_prompt);
                     }
                     if let Some(default_route) = &agent_config.default_route {
-                        agent_json["default_route"] = json!(default_route);
+                        agent_json[fields::DEFAULT_ROUTE] = json!(default_route);
                     }
                     
                     if let Some(rules) = &agent_config.rules {
@@ -64,6 +65,21 @@ impl GraphConfig {
                             agent_json["rules"] = rules_value;
                         }
                     }
+                    if let Some(output_schema) = &agent_config.output_schema {
+                        agent_json["output_schema"] = output_schema.clone();
+                    }
+                    if let Some(policy) = &agent_config.on_invalid_output {
+                        agent_json["on_invalid_output"] = policy.clone();
+                    }
+                    if let Some(policy) = &agent_config.on_refusal {
+                        agent_json["on_refusal"] = policy.clone();
+                    }
+                    if let Some(retrieval) = &agent_config.retrieval {
+                        agent_json["retrieval"] = retrieval.clone();
+                    }
+                    if let Ok(Value::Object(params)) = serde_json::to_value(&agent_config.params) {
+                        agent_json.as_object_mut().unwrap().extend(params);
+                    }
                     
                     agent_json
                 })
@@ -157,6 +173,19 @@ impl GraphConfig {
                         nodes.push(tool_json);
                     }
                 }
+                "subflow_node" => {
+                    if let Ok(subflow_config) = node.as_subflow_node() {
+                        let mut subflow_json = json!({
+                            "kind": "sub_flow",
+                            "name": node.id,
+                            "flow": subflow_config.flow
+                        });
+                        if let Some(memoize) = &subflow_config.memoize {
+                            subflow_json["memoize"] = memoize.clone();
+                        }
+                        nodes.push(subflow_json);
+                    }
+                }
                 _ => {
                 }
             }
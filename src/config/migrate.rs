@@ -0,0 +1,382 @@
+//! 旧版工作流配置迁移
+//!
+//! 识别两种旧格式并转换为当前的 `WorkflowConfig`（`{"agents", "tools", "flow"}`）：
+//!
+//! - `AgentSpec` / `FlowSpec`：早期仿照 AutoGen 的写法，Agent 用 `system_message` 和
+//!   `llm_config` 描述，流程用 `steps` 列出步骤，`next` 指定后继（可带 `when` 条件）
+//! - 引入 `driver` 之前的配置：Agent 用 `provider` / `base_url` / `system_prompt`，
+//!   节点用 `type` 而不是 `kind`，类型名带 `_node` 后缀
+//!
+//! ```yaml
+//! agents:
+//!   - name: writer
+//!     system_message: You write drafts.
+//!     llm_config: { provider: qwen, model: qwen-max, api_key: "${QWEN_API_KEY}" }
+//! flow:
+//!   name: review
+//!   entry: draft
+//!   steps:
+//!     - { name: draft, agent: writer, next: check }
+//!     - name: check
+//!       agent: writer
+//!       next:
+//!         - { to: draft, when: { state_equals: { key: verdict, value: revise } } }
+//!         - done
+//!   end: [done]
+//! ```
+
+use std::borrow::Cow;
+
+use anyhow::anyhow;
+use serde_json::{json, Map, Value};
+
+use crate::error::{AgentFlowError, Result};
+
+/// 工作流配置的格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// 当前的 `WorkflowConfig`
+    Current,
+    /// `AgentSpec` / `FlowSpec`
+    AgentSpec,
+    /// 引入 `driver` 之前的配置
+    PreDriver,
+}
+
+/// 识别配置格式
+pub fn detect_format(value: &Value) -> ConfigFormat {
+    let flow = &value["flow"];
+    if flow.get("nodes").is_none() && (flow.get("steps").is_some() || flow.get("entry").is_some()) {
+        return ConfigFormat::AgentSpec;
+    }
+    let agents = value["agents"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    if agents
+        .iter()
+        .any(|agent| agent.get("system_message").is_some() || agent.get("llm_config").is_some())
+    {
+        return ConfigFormat::AgentSpec;
+    }
+    let legacy_agent = agents.iter().any(|agent| {
+        agent.get("driver").is_none()
+            && ["provider", "base_url", "system_prompt"]
+                .iter()
+                .any(|key| agent.get(key).is_some())
+    });
+    let legacy_node = flow["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|node| node.get("kind").is_none() && node.get("type").is_some());
+    if legacy_agent || legacy_node {
+        ConfigFormat::PreDriver
+    } else {
+        ConfigFormat::Current
+    }
+}
+
+/// 把旧格式的配置转换为当前格式，已是当前格式时原样返回
+pub fn migrate_workflow(value: &Value) -> Result<Cow<'_, Value>> {
+    let format = detect_format(value);
+    let migrated = match format {
+        ConfigFormat::Current => return Ok(Cow::Borrowed(value)),
+        ConfigFormat::AgentSpec => from_agent_spec(value)?,
+        ConfigFormat::PreDriver => from_pre_driver(value),
+    };
+    tracing::info!(
+        flow = %migrated["flow"]["name"],
+        ?format,
+        "migrated legacy workflow config"
+    );
+    Ok(Cow::Owned(migrated))
+}
+
+/// 解析 YAML 格式的工作流配置（需启用 `yaml` feature）
+#[cfg(feature = "yaml")]
+pub fn parse_yaml(text: &str) -> Result<Value> {
+    serde_yaml::from_str(text).map_err(|e| AgentFlowError::Serialization(e.to_string()))
+}
+
+fn from_agent_spec(value: &Value) -> Result<Value> {
+    let agents: Vec<Value> = value["agents"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(agent_from_spec)
+        .collect();
+
+    let spec = &value["flow"];
+    let name = spec["name"]
+        .as_str()
+        .ok_or_else(|| legacy_error("flow.name is required"))?;
+    let steps = spec["steps"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    let mut nodes = Vec::new();
+    let mut transitions = Vec::new();
+    for (index, step) in steps.iter().enumerate() {
+        let step_name = step["name"]
+            .as_str()
+            .or_else(|| step["agent"].as_str())
+            .ok_or_else(|| legacy_error(format!("flow.steps[{index}] needs `name` or `agent`")))?;
+        nodes.push(match (step["agent"].as_str(), step["tool"].as_str()) {
+            (_, Some(pipeline)) => {
+                let mut node = json!({ "kind": "tool", "name": step_name, "pipeline": pipeline });
+                if let Some(params) = step.get("params") {
+                    node["params"] = params.clone();
+                }
+                node
+            }
+            (Some(agent), None) => json!({ "kind": "agent", "name": step_name, "agent": agent }),
+            (None, None) => json!({ "kind": "terminal", "name": step_name }),
+        });
+        transitions.extend(next_transitions(step_name, &step["next"])?);
+    }
+
+    // `end` 中的节点以及转换指向但未定义的节点作为终止节点
+    let mut defined: Vec<String> = nodes
+        .iter()
+        .filter_map(|node| node["name"].as_str().map(str::to_string))
+        .collect();
+    let targets = spec["end"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .chain(
+            transitions
+                .iter()
+                .filter_map(|transition| transition["to"].as_str()),
+        )
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    for target in targets {
+        if !defined.contains(&target) {
+            nodes.push(json!({ "kind": "terminal", "name": target }));
+            defined.push(target);
+        }
+    }
+
+    let start = spec["entry"]
+        .as_str()
+        .or_else(|| spec["start"].as_str())
+        .or_else(|| defined.first().map(String::as_str))
+        .ok_or_else(|| legacy_error("flow.entry is required"))?;
+    Ok(json!({
+        "agents": agents,
+        "tools": value.get("tools").cloned().unwrap_or_else(|| json!([])),
+        "flow": {
+            "name": name,
+            "start": start,
+            "nodes": nodes,
+            "transitions": transitions
+        }
+    }))
+}
+
+/// `AgentSpec`：`llm_config` 中的字段提到顶层
+fn agent_from_spec(spec: &Value) -> Value {
+    let mut agent = Map::new();
+    let llm = &spec["llm_config"];
+    for (to, from) in [
+        ("name", &spec["name"]),
+        ("role", &spec["description"]),
+        ("prompt", &spec["system_message"]),
+        ("model", &llm["model"]),
+        ("endpoint", &llm["base_url"]),
+        ("api_key", &llm["api_key"]),
+        ("temperature", &llm["temperature"]),
+        ("tools", &spec["tools"]),
+    ] {
+        if !from.is_null() {
+            agent.insert(to.to_string(), from.clone());
+        }
+    }
+    let driver = match llm["provider"].as_str() {
+        Some(provider) => driver_for(provider),
+        None if llm.get("base_url").is_some() => "generic".to_string(),
+        None if llm.get("model").is_some() => "chatgpt".to_string(),
+        None => "echo".to_string(),
+    };
+    agent.insert("driver".to_string(), Value::String(driver));
+    Value::Object(agent)
+}
+
+/// `next` 可以是节点名、节点名列表或 `{to, when}` 列表
+fn next_transitions(from: &str, next: &Value) -> Result<Vec<Value>> {
+    let items = match next {
+        Value::Null => return Ok(Vec::new()),
+        Value::Array(items) => items.clone(),
+        other => vec![other.clone()],
+    };
+    items
+        .iter()
+        .map(|item| {
+            let (to, when) = match item {
+                Value::String(to) => (to.as_str(), &Value::Null),
+                Value::Object(object) => (
+                    object
+                        .get("to")
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| legacy_error(format!("next of step `{from}` needs `to`")))?,
+                    object.get("when").unwrap_or(&Value::Null),
+                ),
+                _ => return Err(legacy_error(format!("invalid next of step `{from}`"))),
+            };
+            let mut transition = json!({ "from": from, "to": to });
+            if !when.is_null() {
+                transition["condition"] = condition_from_when(from, when)?;
+            }
+            Ok(transition)
+        })
+        .collect()
+}
+
+/// `when: {state_equals: {key, value}}` / `{state_exists: key}` 等转换为 `GraphCondition`
+fn condition_from_when(from: &str, when: &Value) -> Result<Value> {
+    let Some((kind, body)) = when.as_object().and_then(|object| object.iter().next()) else {
+        return Err(legacy_error(format!("invalid `when` of step `{from}`")));
+    };
+    let mut condition = match body {
+        Value::Object(fields) => Value::Object(fields.clone()),
+        Value::String(key) => json!({ "key": key }),
+        _ => json!({}),
+    };
+    condition["type"] = Value::String(kind.clone());
+    Ok(condition)
+}
+
+fn from_pre_driver(value: &Value) -> Value {
+    let mut migrated = value.clone();
+    if let Some(agents) = migrated["agents"].as_array_mut() {
+        for agent in agents.iter_mut().filter_map(Value::as_object_mut) {
+            if agent.contains_key("driver") {
+                continue;
+            }
+            for (from, to) in [("base_url", "endpoint"), ("system_prompt", "prompt")] {
+                if let Some(field) = agent.remove(from) {
+                    agent.entry(to).or_insert(field);
+                }
+            }
+            let driver = agent
+                .remove("provider")
+                .and_then(|provider| provider.as_str().map(driver_for))
+                .unwrap_or_else(|| "echo".to_string());
+            agent.insert("driver".to_string(), Value::String(driver));
+        }
+    }
+    if let Some(nodes) = migrated["flow"]["nodes"].as_array_mut() {
+        for node in nodes.iter_mut().filter_map(Value::as_object_mut) {
+            if node.contains_key("kind") {
+                continue;
+            }
+            if let Some(Value::String(kind)) = node.remove("type") {
+                let kind = match kind.trim_end_matches("_node") {
+                    "subflow" => "sub_flow",
+                    other => other,
+                };
+                node.insert("kind".to_string(), Value::String(kind.to_string()));
+            }
+        }
+    }
+    migrated
+}
+
+/// 旧版提供商名称对应的驱动
+pub(crate) fn driver_for(provider: &str) -> String {
+    let provider = provider.to_ascii_lowercase();
+    match provider.as_str() {
+        "openai" | "azure" => "chatgpt",
+        "anthropic" => "claude",
+        "dashscope" | "tongyi" => "qwen",
+        "zhipu" | "glm" => "bigmodel",
+        "kimi" => "moonshot",
+        "openai_compatible" => "generic",
+        "openai_assistants" | "assistants" => "openai_assistant",
+        "aws_bedrock" | "amazon_bedrock" => "bedrock",
+        other => other,
+    }
+    .to_string()
+}
+
+fn legacy_error(message: impl Into<String>) -> AgentFlowError {
+    AgentFlowError::Other(anyhow!("legacy workflow config: {}", message.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::loader::load_workflow_from_value;
+
+    #[test]
+    fn test_migrate_agent_spec() {
+        let legacy = json!({
+            "agents": [{
+                "name": "writer",
+                "description": "Copywriter",
+                "system_message": "You write drafts.",
+                "llm_config": { "provider": "openai", "model": "gpt-4o", "temperature": 0.2 }
+            }],
+            "flow": {
+                "name": "review",
+                "entry": "draft",
+                "steps": [
+                    { "name": "draft", "agent": "writer", "next": "check" },
+                    {
+                        "name": "check",
+                        "agent": "writer",
+                        "next": [
+                            { "to": "draft", "when": { "state_equals": { "key": "verdict", "value": "revise" } } },
+                            "done"
+                        ]
+                    }
+                ],
+                "end": ["done"]
+            }
+        });
+        assert_eq!(detect_format(&legacy), ConfigFormat::AgentSpec);
+        let migrated = migrate_workflow(&legacy).unwrap().into_owned();
+        assert_eq!(
+            migrated["agents"][0],
+            json!({
+                "name": "writer",
+                "role": "Copywriter",
+                "prompt": "You write drafts.",
+                "model": "gpt-4o",
+                "temperature": 0.2,
+                "driver": "chatgpt"
+            })
+        );
+        assert_eq!(migrated["flow"]["start"], "draft");
+        assert_eq!(
+            migrated["flow"]["nodes"][2],
+            json!({ "kind": "terminal", "name": "done" })
+        );
+        assert_eq!(
+            migrated["flow"]["transitions"][1]["condition"],
+            json!({ "type": "state_equals", "key": "verdict", "value": "revise" })
+        );
+        assert_eq!(detect_format(&migrated), ConfigFormat::Current);
+    }
+
+    #[test]
+    fn test_load_pre_driver_config() {
+        let legacy = json!({
+            "agents": [{ "name": "writer", "provider": "echo", "system_prompt": "Draft it." }],
+            "flow": {
+                "name": "legacy",
+                "start": "draft",
+                "nodes": [
+                    { "type": "agent", "name": "draft", "agent": "writer" },
+                    { "type": "terminal_node", "name": "done" }
+                ],
+                "transitions": [{ "from": "draft", "to": "done" }]
+            }
+        });
+        assert_eq!(detect_format(&legacy), ConfigFormat::PreDriver);
+        let migrated = migrate_workflow(&legacy).unwrap();
+        assert_eq!(migrated["agents"][0]["prompt"], "Draft it.");
+        assert_eq!(migrated["flow"]["nodes"][1]["kind"], "terminal");
+
+        let bundle = load_workflow_from_value(&legacy).unwrap();
+        assert_eq!(bundle.flow.start, "draft");
+        assert!(bundle.agents.contains_key("writer"));
+    }
+}
@@ -5,6 +5,7 @@ pub mod env;
 pub mod graph;
 pub mod graph_config;
 pub mod graph_loader;
+pub mod migrate;
 pub mod nodes;
 
 // 重新导出所有公共接口（保持向后兼容）
@@ -60,6 +60,15 @@ pub struct ToolNodeConfig {
     pub params: Option<Value>,
 }
 
+/// SubFlow Node 配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubFlowNodeConfig {
+    pub flow: String,
+    /// 缓存配置（ttl_secs、input_keys），设置后子流程视为纯函数
+    #[serde(default)]
+    pub memoize: Option<Value>,
+}
+
 impl GraphNode {
     /// 尝试解析为 Workflow 配置
     pub fn as_workflow(&self) -> Result<WorkflowConfig> {
@@ -121,4 +130,17 @@ impl GraphNode {
         serde_json::from_value(self.config.clone())
             .map_err(|e| AgentFlowError::Other(anyhow!("Failed to parse tool_node config: {}", e)))
     }
+
+    /// 尝试解析为 SubFlowNode 配置
+    pub fn as_subflow_node(&self) -> Result<SubFlowNodeConfig> {
+        if self.node_type != "subflow_node" {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Node {} is not a subflow_node",
+                self.id
+            )));
+        }
+        serde_json::from_value(self.config.clone()).map_err(|e| {
+            AgentFlowError::Other(anyhow!("Failed to parse subflow_node config: {}", e))
+        })
+    }
 }
@@ -24,12 +24,33 @@ pub enum AgentFlowError {
     DecisionNoMatch { node: String },
     #[error("join node `{node}` did not receive required inbound branches")]
     JoinIncomplete { node: String },
+    #[error("wait node `{node}` timed out")]
+    WaitTimedOut { node: String },
+    #[error("idempotency key `{key}` was already used by run `{run_id}`")]
+    DuplicateRun { key: String, run_id: String },
+    #[error("run `{run_id}` started on version {run_version} of flow `{flow}`, executor has version {flow_version}")]
+    VersionMismatch {
+        flow: String,
+        run_id: String,
+        run_version: u32,
+        flow_version: u32,
+    },
+    #[error("{path} {message}")]
+    InvalidConfig { path: String, message: String },
+    #[error("parameter `{name}` {message}")]
+    InvalidParameter { name: String, message: String },
+    #[error("flow output `{name}` {message}")]
+    InvalidOutput { name: String, message: String },
     #[error("message serialization error: {0}")]
     Serialization(String),
     #[error("{kind} manifest mismatch for `{name}`")]
     ManifestMismatch { kind: &'static str, name: String },
     #[error("context error: {0}")]
     Context(String),
+    #[error("LLM refused: {0}")]
+    Refused(crate::llm::LlmRefusal),
+    #[error("{0}")]
+    LlmHttp(crate::llm::LlmHttpError),
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
@@ -127,6 +148,40 @@ impl From<AgentFlowError> for FrameworkError {
                 "flow.join_incomplete",
                 format!("join node `{node}` did not receive required inbound branches"),
             ),
+            AgentFlowError::WaitTimedOut { node } => FrameworkError::new(
+                "flow.wait_timed_out",
+                format!("wait node `{node}` timed out"),
+            ),
+            AgentFlowError::DuplicateRun { key, run_id } => FrameworkError::new(
+                "flow.duplicate_run",
+                format!("idempotency key `{key}` was already used by run `{run_id}`"),
+            )
+            .with_severity(ErrorSeverity::Info),
+            AgentFlowError::VersionMismatch {
+                flow,
+                run_id,
+                run_version,
+                flow_version,
+            } => FrameworkError::new(
+                "flow.version_mismatch",
+                format!(
+                    "run `{run_id}` started on version {run_version} of flow `{flow}`, executor has version {flow_version}"
+                ),
+            ),
+            AgentFlowError::InvalidConfig { path, message } => {
+                FrameworkError::new("config.invalid", format!("{path} {message}"))
+                    .with_context(serde_json::json!({ "path": path }))
+            }
+            AgentFlowError::InvalidParameter { name, message } => FrameworkError::new(
+                "flow.invalid_parameter",
+                format!("parameter `{name}` {message}"),
+            )
+            .with_context(serde_json::json!({ "parameter": name })),
+            AgentFlowError::InvalidOutput { name, message } => FrameworkError::new(
+                "flow.invalid_output",
+                format!("flow output `{name}` {message}"),
+            )
+            .with_context(serde_json::json!({ "output": name })),
             AgentFlowError::Serialization(message) => {
                 FrameworkError::new("message.serialization_error", message)
             }
@@ -136,6 +191,23 @@ impl From<AgentFlowError> for FrameworkError {
             )
             .with_severity(ErrorSeverity::Error),
             AgentFlowError::Context(message) => FrameworkError::new("context.error", message),
+            AgentFlowError::Refused(refusal) => {
+                let context = serde_json::to_value(&refusal).ok();
+                let error = FrameworkError::new("llm.refused", format!("LLM refused: {refusal}"))
+                    .with_severity(ErrorSeverity::Warning);
+                match context {
+                    Some(context) => error.with_context(context),
+                    None => error,
+                }
+            }
+            AgentFlowError::LlmHttp(error) => {
+                let context = serde_json::to_value(&error).ok();
+                let error = FrameworkError::new("llm.http_error", error.to_string());
+                match context {
+                    Some(context) => error.with_context(context),
+                    None => error,
+                }
+            }
             AgentFlowError::Other(other) => {
                 FrameworkError::new("internal.error", other.to_string())
             }
@@ -0,0 +1,354 @@
+//! 离线评估：在数据集上逐条运行流程并打分，用于回归测试提示词和模型改动
+//!
+//! 数据集为 JSONL，每行一个样本（`input`、可选的 `id`、`expected`）；每个样本的流程输出
+//! 交给所有打分器，全部通过才算通过。
+
+pub mod scorers;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::runtime::{BatchOptions, BatchStatus, FlowExecutor};
+
+pub use scorers::{ExactMatch, JsonFieldMatch, LlmJudge, RegexMatch, Score, Scorer};
+
+/// 评估样本
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvalExample {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// 字符串直接作为输入消息内容，其余序列化为 JSON
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Value>,
+}
+
+impl EvalExample {
+    pub fn new(input: Value) -> Self {
+        Self {
+            input,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn with_expected(mut self, expected: Value) -> Self {
+        self.expected = Some(expected);
+        self
+    }
+
+    pub fn input_text(&self) -> String {
+        match &self.input {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// 评估数据集
+#[derive(Clone, Debug, Default)]
+pub struct EvalDataset {
+    pub name: String,
+    pub examples: Vec<EvalExample>,
+}
+
+impl EvalDataset {
+    pub fn new(name: impl Into<String>, examples: Vec<EvalExample>) -> Self {
+        Self {
+            name: name.into(),
+            examples,
+        }
+    }
+
+    /// 解析 JSONL，忽略空行；出错时报告行号
+    pub fn from_jsonl(name: impl Into<String>, content: &str) -> Result<Self> {
+        let name = name.into();
+        let mut examples = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let example = serde_json::from_str(line).map_err(|e| {
+                AgentFlowError::Serialization(format!(
+                    "dataset `{}` line {}: {}",
+                    name,
+                    index + 1,
+                    e
+                ))
+            })?;
+            examples.push(example);
+        }
+        Ok(Self { name, examples })
+    }
+
+    /// 读取 JSONL 文件，数据集名为文件名（不含扩展名）
+    pub fn load_jsonl(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| AgentFlowError::Other(anyhow!("dataset `{}`: {}", path.display(), e)))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::from_jsonl(name, &content)
+    }
+}
+
+/// 单个样本的评估结果
+#[derive(Clone, Debug, Serialize)]
+pub struct EvalResult {
+    /// 样本 id，未设置时为序号
+    pub id: String,
+    pub passed: bool,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// 按打分器名索引
+    pub scores: BTreeMap<String, Score>,
+    /// 流程运行失败的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 评估汇总
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EvalSummary {
+    pub total: usize,
+    pub passed: usize,
+    /// 流程运行失败（未打分）的样本数
+    pub errors: usize,
+    pub pass_rate: f64,
+    /// 各打分器在已打分样本上的平均分
+    pub mean_scores: BTreeMap<String, f64>,
+    pub duration_ms: u64,
+}
+
+/// 评估报告
+#[derive(Clone, Debug, Serialize)]
+pub struct EvalReport {
+    pub dataset: String,
+    pub flow: String,
+    pub summary: EvalSummary,
+    pub results: Vec<EvalResult>,
+}
+
+impl EvalReport {
+    pub fn failures(&self) -> impl Iterator<Item = &EvalResult> {
+        self.results.iter().filter(|result| !result.passed)
+    }
+}
+
+/// 评估器：批量运行流程后对每个样本执行所有打分器
+#[derive(Clone, Default)]
+pub struct Evaluator {
+    scorers: Vec<Arc<dyn Scorer>>,
+    batch: BatchOptions,
+}
+
+impl Evaluator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_scorer(mut self, scorer: impl Scorer + 'static) -> Self {
+        self.scorers.push(Arc::new(scorer));
+        self
+    }
+
+    /// 并发度和每个样本的上下文，见 `FlowExecutor::run_batch`
+    pub fn with_batch_options(mut self, options: BatchOptions) -> Self {
+        self.batch = options;
+        self
+    }
+
+    /// 打分器出错时该分数记为失败，理由为错误信息
+    pub async fn run(&self, executor: &FlowExecutor, dataset: &EvalDataset) -> EvalReport {
+        let inputs = dataset
+            .examples
+            .iter()
+            .map(|example| AgentMessage::user(example.input_text()))
+            .collect();
+        let batch = executor.run_batch(inputs, self.batch.clone()).await;
+
+        let mut results = Vec::with_capacity(dataset.examples.len());
+        for (example, item) in dataset.examples.iter().zip(batch.items) {
+            let id = example.id.clone().unwrap_or_else(|| item.index.to_string());
+            let mut result = EvalResult {
+                id,
+                passed: false,
+                duration_ms: item.duration_ms,
+                output: item.output.map(|message| message.content),
+                scores: BTreeMap::new(),
+                error: None,
+            };
+            let Some(output) = result
+                .output
+                .as_deref()
+                .filter(|_| item.status == BatchStatus::Succeeded)
+            else {
+                result.error = Some(match item.errors.first() {
+                    Some(error) => error.message.clone(),
+                    None => "run did not produce an output".to_string(),
+                });
+                results.push(result);
+                continue;
+            };
+            for scorer in &self.scorers {
+                let score = scorer.score(example, output).await.unwrap_or_else(|e| {
+                    tracing::warn!(scorer = scorer.name(), example = %result.id, error = %e, "scorer failed");
+                    Score::fail(format!("scorer error: {}", e))
+                });
+                result.scores.insert(scorer.name().to_string(), score);
+            }
+            result.passed = result.scores.values().all(|score| score.passed);
+            results.push(result);
+        }
+
+        EvalReport {
+            dataset: dataset.name.clone(),
+            flow: executor.flow().name.clone(),
+            summary: summarize(&results, batch.duration_ms),
+            results,
+        }
+    }
+}
+
+fn summarize(results: &[EvalResult], duration_ms: u64) -> EvalSummary {
+    let mut totals: BTreeMap<String, (f64, usize)> = BTreeMap::new();
+    for score in results.iter().flat_map(|result| &result.scores) {
+        let entry = totals.entry(score.0.clone()).or_default();
+        entry.0 += score.1.value;
+        entry.1 += 1;
+    }
+    let passed = results.iter().filter(|result| result.passed).count();
+    EvalSummary {
+        total: results.len(),
+        passed,
+        errors: results
+            .iter()
+            .filter(|result| result.error.is_some())
+            .count(),
+        pass_rate: if results.is_empty() {
+            0.0
+        } else {
+            passed as f64 / results.len() as f64
+        },
+        mean_scores: totals
+            .into_iter()
+            .map(|(name, (sum, count))| (name, sum / count as f64))
+            .collect(),
+        duration_ms,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentRegistry};
+    use crate::flow::FlowBuilder;
+    use crate::llm::MockLlmClient;
+    use crate::tools::ToolRegistry;
+    use async_trait::async_trait;
+
+    /// 把问题分类为 JSON，`refund` 故意分错，`crash` 失败
+    struct ClassifierAgent;
+
+    #[async_trait]
+    impl Agent for ClassifierAgent {
+        fn name(&self) -> &'static str {
+            "classifier"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let category = match message.content.as_str() {
+                "crash" => return Err(AgentFlowError::Other(anyhow!("model unavailable"))),
+                "invoice" | "refund" => "billing",
+                _ => "other",
+            };
+            Ok(AgentAction::Next {
+                target: "done".into(),
+                message: AgentMessage::user(
+                    serde_json::json!({ "category": category }).to_string(),
+                ),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_dataset() {
+        let dataset = EvalDataset::from_jsonl(
+            "tickets",
+            r#"{"id": "a", "input": "invoice", "expected": {"category": "billing"}}
+
+{"input": "refund", "expected": {"category": "refund"}}
+{"id": "c", "input": "crash", "expected": {"category": "other"}}
+"#,
+        )
+        .unwrap();
+        assert_eq!(dataset.examples.len(), 3);
+        let err = EvalDataset::from_jsonl("broken", "{\"input\": 1}\nnot json")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("line 2"), "{}", err);
+
+        let mut agents = AgentRegistry::new();
+        register_agent("classifier", Arc::new(ClassifierAgent), &mut agents);
+        let mut builder = FlowBuilder::new("triage");
+        builder
+            .add_agent_node("classify", "classifier")
+            .add_terminal_node("done")
+            .set_start("classify");
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new());
+
+        // 对包含 `billing` 的回答给 9 分
+        let judge = MockLlmClient::new()
+            .on_contains("billing", "9\nlooks fine")
+            .fallback("2\nlooks fine");
+        let report = Evaluator::new()
+            .with_scorer(JsonFieldMatch::new(["$.category"]).unwrap())
+            .with_scorer(RegexMatch::new("^\\{").unwrap())
+            .with_scorer(LlmJudge::new(
+                Arc::new(judge.clone()),
+                "Is the category right?",
+            ))
+            .run(&executor, &dataset)
+            .await;
+
+        assert_eq!(report.flow, "triage");
+        let ids: Vec<_> = report.results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "1", "c"]);
+        assert!(report.results[0].passed);
+        let refund = &report.results[1];
+        assert!(!refund.passed);
+        assert!(!refund.scores["json_fields"].passed);
+        assert!(refund.scores["regex"].passed);
+        assert_eq!(refund.scores["llm_judge"].value, 0.9);
+        judge.assert_call_count(2);
+        assert_eq!(
+            report.results[2].error.as_deref(),
+            Some("model unavailable")
+        );
+
+        let summary = &report.summary;
+        assert_eq!((summary.total, summary.passed, summary.errors), (3, 1, 1));
+        assert!((summary.pass_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(summary.mean_scores["json_fields"], 0.5);
+        assert_eq!(summary.mean_scores["regex"], 1.0);
+        assert_eq!(report.failures().count(), 2);
+    }
+}
@@ -0,0 +1,326 @@
+//! 评估打分器
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::EvalExample;
+use crate::error::{AgentFlowError, Result};
+use crate::llm::{DynLlmClient, LlmRequest};
+use crate::utils::JsonPath;
+
+/// 单个打分器的结果
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    /// 0.0 ~ 1.0
+    pub value: f64,
+    pub passed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl Score {
+    pub fn pass() -> Self {
+        Self {
+            value: 1.0,
+            passed: true,
+            reason: None,
+        }
+    }
+
+    pub fn fail(reason: impl Into<String>) -> Self {
+        Self {
+            value: 0.0,
+            passed: false,
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+/// 打分器：比较流程输出与样本期望
+#[async_trait]
+pub trait Scorer: Send + Sync {
+    /// 报告中的分数名
+    fn name(&self) -> &str;
+
+    /// `output` 为流程最终消息的内容
+    async fn score(&self, example: &EvalExample, output: &str) -> Result<Score>;
+}
+
+fn expected(example: &EvalExample) -> Result<&Value> {
+    example
+        .expected
+        .as_ref()
+        .ok_or_else(|| AgentFlowError::Other(anyhow!("example has no expected value")))
+}
+
+/// 输出与期望完全一致；期望不是字符串时按 JSON 比较
+#[derive(Clone, Debug, Default)]
+pub struct ExactMatch {
+    ignore_case: bool,
+}
+
+impl ExactMatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ignore_case(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+}
+
+#[async_trait]
+impl Scorer for ExactMatch {
+    fn name(&self) -> &str {
+        "exact_match"
+    }
+
+    async fn score(&self, example: &EvalExample, output: &str) -> Result<Score> {
+        let matched = match expected(example)? {
+            Value::String(expected) if self.ignore_case => {
+                output.trim().to_lowercase() == expected.trim().to_lowercase()
+            }
+            Value::String(expected) => output.trim() == expected.trim(),
+            expected => serde_json::from_str::<Value>(output).ok().as_ref() == Some(expected),
+        };
+        Ok(if matched {
+            Score::pass()
+        } else {
+            Score::fail("output differs from expected")
+        })
+    }
+}
+
+/// 输出匹配正则表达式
+#[derive(Clone, Debug)]
+pub struct RegexMatch {
+    pattern: Regex,
+}
+
+impl RegexMatch {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let pattern = Regex::new(pattern)
+            .map_err(|e| AgentFlowError::Other(anyhow!("Invalid regex `{}`: {}", pattern, e)))?;
+        Ok(Self { pattern })
+    }
+}
+
+#[async_trait]
+impl Scorer for RegexMatch {
+    fn name(&self) -> &str {
+        "regex"
+    }
+
+    async fn score(&self, _example: &EvalExample, output: &str) -> Result<Score> {
+        Ok(if self.pattern.is_match(output) {
+            Score::pass()
+        } else {
+            Score::fail(format!("output does not match `{}`", self.pattern))
+        })
+    }
+}
+
+/// 把输出解析为 JSON，逐个比较指定字段与期望中的同名字段，分数为一致字段的比例
+#[derive(Clone, Debug)]
+pub struct JsonFieldMatch {
+    fields: Vec<JsonPath>,
+    names: Vec<String>,
+}
+
+impl JsonFieldMatch {
+    /// `fields` 为 JSONPath，如 `$.category`
+    pub fn new<I, S>(fields: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut parsed = Self {
+            fields: Vec::new(),
+            names: Vec::new(),
+        };
+        for field in fields {
+            parsed.fields.push(JsonPath::parse(field.as_ref())?);
+            parsed.names.push(field.as_ref().to_string());
+        }
+        if parsed.fields.is_empty() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "JsonFieldMatch requires at least one field"
+            )));
+        }
+        Ok(parsed)
+    }
+}
+
+#[async_trait]
+impl Scorer for JsonFieldMatch {
+    fn name(&self) -> &str {
+        "json_fields"
+    }
+
+    async fn score(&self, example: &EvalExample, output: &str) -> Result<Score> {
+        let expected = expected(example)?;
+        let Ok(actual) = serde_json::from_str::<Value>(output) else {
+            return Ok(Score::fail("output is not valid JSON"));
+        };
+        let mismatched: Vec<&str> = self
+            .fields
+            .iter()
+            .zip(&self.names)
+            .filter(|(path, _)| path.extract(&actual) != path.extract(expected))
+            .map(|(_, name)| name.as_str())
+            .collect();
+        let value = 1.0 - mismatched.len() as f64 / self.fields.len() as f64;
+        Ok(Score {
+            value,
+            passed: mismatched.is_empty(),
+            reason: (!mismatched.is_empty())
+                .then(|| format!("mismatched fields: {}", mismatched.join(", "))),
+        })
+    }
+}
+
+const JUDGE_PROMPT: &str = "You are grading the answer of an AI assistant. \
+Compare the answer with the reference (if any) using the criteria below. \
+Reply with the score from 0 to 10 on the first line, then a one-sentence reason.\n\nCriteria: ";
+
+/// 由 LLM 按评分标准给出 0~10 分，归一化后不低于阈值即通过
+#[derive(Clone)]
+pub struct LlmJudge {
+    client: DynLlmClient,
+    criteria: String,
+    threshold: f64,
+}
+
+impl LlmJudge {
+    /// 默认阈值 0.7
+    pub fn new(client: DynLlmClient, criteria: impl Into<String>) -> Self {
+        Self {
+            client,
+            criteria: criteria.into(),
+            threshold: 0.7,
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+}
+
+/// 解析评分：首个数字为 0~10 分，其后的文本为理由
+fn parse_judgement(response: &str) -> Option<(f64, Option<String>)> {
+    let cleaned = crate::flow::services::routing::strip_reasoning(response);
+    let start = cleaned.find(|c: char| c.is_ascii_digit())?;
+    let rest = &cleaned[start..];
+    let end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(rest.len());
+    let score: f64 = rest[..end].trim_end_matches('.').parse().ok()?;
+    let reason = rest[end..]
+        .trim_start_matches(|c: char| c == '/' || c.is_ascii_digit())
+        .trim_matches(|c: char| c.is_whitespace() || c == ':' || c == '-')
+        .to_string();
+    Some((
+        (score / 10.0).clamp(0.0, 1.0),
+        (!reason.is_empty()).then_some(reason),
+    ))
+}
+
+#[async_trait]
+impl Scorer for LlmJudge {
+    fn name(&self) -> &str {
+        "llm_judge"
+    }
+
+    async fn score(&self, example: &EvalExample, output: &str) -> Result<Score> {
+        let mut user = format!("Question:\n{}\n\n", example.input_text());
+        if let Some(expected) = &example.expected {
+            let reference = match expected {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            user.push_str(&format!("Reference:\n{}\n\n", reference));
+        }
+        user.push_str(&format!("Answer:\n{}", output));
+        let response = self
+            .client
+            .complete(LlmRequest {
+                system: Some(format!("{}{}", JUDGE_PROMPT, self.criteria)),
+                user,
+                temperature: 0.0,
+                metadata: None,
+                content: Vec::new(),
+                params: Default::default(),
+            })
+            .await?;
+        let (value, reason) = parse_judgement(&response.content).ok_or_else(|| {
+            AgentFlowError::Other(anyhow!("LLM judge returned no score: {}", response.content))
+        })?;
+        Ok(Score {
+            value,
+            passed: value >= self.threshold,
+            reason,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_judgement() {
+        assert_eq!(
+            parse_judgement("8\nCovers every step."),
+            Some((0.8, Some("Covers every step.".into())))
+        );
+        assert_eq!(
+            parse_judgement("Score: 10/10 - perfect"),
+            Some((1.0, Some("perfect".into())))
+        );
+        assert_eq!(parse_judgement("no idea"), None);
+    }
+
+    #[tokio::test]
+    async fn test_field_and_exact_scorers() {
+        let example = EvalExample::new(json!("ticket")).with_expected(json!({
+            "category": "billing",
+            "priority": "high"
+        }));
+        let fields = JsonFieldMatch::new(["$.category", "$.priority"]).unwrap();
+        let score = fields
+            .score(&example, r#"{"category":"billing","priority":"low"}"#)
+            .await
+            .unwrap();
+        assert_eq!(score.value, 0.5);
+        assert!(!score.passed);
+        assert_eq!(
+            score.reason.as_deref(),
+            Some("mismatched fields: $.priority")
+        );
+        assert!(!fields.score(&example, "billing").await.unwrap().passed);
+
+        let exact = ExactMatch::new();
+        let json_output = r#"{ "priority": "high", "category": "billing" }"#;
+        assert!(exact.score(&example, json_output).await.unwrap().passed);
+        let text = EvalExample::new(json!("q")).with_expected(json!("Paris"));
+        assert!(!exact.score(&text, "paris").await.unwrap().passed);
+        assert!(
+            ExactMatch::new()
+                .ignore_case()
+                .score(&text, " paris\n")
+                .await
+                .unwrap()
+                .passed
+        );
+        assert!(exact
+            .score(&EvalExample::new(json!("q")), "x")
+            .await
+            .is_err());
+    }
+}
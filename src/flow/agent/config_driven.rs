@@ -3,17 +3,24 @@ use serde_json::{json, Value};
 use std::sync::Arc;
 
 use crate::agent::{Agent, AgentAction, AgentContext, AgentMessage, MessageRole};
-use crate::error::Result;
-use crate::llm::DynLlmClient;
-use crate::tools::Tool;
+use crate::error::{AgentFlowError, Result};
+use crate::llm::{DynLlmClient, LlmRefusal};
+use crate::runtime::stream_to_sink;
+use crate::tools::{RagRetrieveTool, Tool, VectorMatch};
 use crate::FlowContext;
 use crate::{StructuredMessage, ToolInvocation};
 
 use crate::flow::config::agent::ToolDriverKind;
-use crate::flow::config::{AgentConfig, ToolConfig};
-use crate::flow::constants::{fields, routing as routing_consts};
+use crate::flow::config::{
+    AgentConfig, AgentDriverKind, FieldExtractionRules, InvalidOutputAction,
+    InvalidOutputExhausted, PromptBuildingRules, RefusalAction, RefusalExhausted, RoutingRules,
+    ToolConfig,
+};
+use crate::flow::constants::{fields, prompt as prompt_consts, routing as routing_consts};
 use crate::flow::services::llm_caller::LlmCaller;
+use crate::flow::services::llm_client_factory::LlmClientFactory;
 use crate::flow::services::message_parser::MessageParser;
+use crate::flow::services::output_validator::OutputValidator;
 use crate::flow::services::routing::{clean_response, RouteMatcher};
 
 /// 配置驱动的 Agent 实现
@@ -21,8 +28,12 @@ use crate::flow::services::routing::{clean_response, RouteMatcher};
 pub struct ConfigDrivenAgent {
     pub profile: Arc<AgentConfig>,
     pub name: &'static str,
-    #[cfg(feature = "openai-client")]
     pub llm_client: Option<DynLlmClient>,
+    /// `rag` 驱动的知识库检索工具
+    pub retriever: Option<Arc<RagRetrieveTool>>,
+    /// `openai_assistant` 驱动的 Assistants 客户端
+    #[cfg(feature = "openai-client")]
+    pub assistant: Option<Arc<crate::llm::OpenAiAssistantClient>>,
 }
 
 #[async_trait]
@@ -36,6 +47,34 @@ impl Agent for ConfigDrivenAgent {
         message: AgentMessage,
         ctx: &AgentContext<'_>,
     ) -> Result<AgentAction> {
+        if let Some(retriever) = &self.retriever {
+            return self.answer_with_knowledge(retriever, message, ctx).await;
+        }
+        #[cfg(feature = "openai-client")]
+        if let Some(assistant) = &self.assistant {
+            return self.answer_with_assistant(assistant, message, ctx).await;
+        }
+
+        // 挂载了交互通道或流式输出接收方时，LLM 输出片段同步推送给它们
+        let streaming;
+        let agent = match &self.llm_client {
+            Some(client) if ctx.flow().channel().is_some() || ctx.flow().token_sink().is_some() => {
+                let mut client = client.clone();
+                if let Some(channel) = ctx.flow().channel() {
+                    client = channel.stream_llm(client, &self.profile.name);
+                }
+                if let Some(sink) = ctx.flow().token_sink() {
+                    client = stream_to_sink(client, Arc::clone(sink), &self.profile.name);
+                }
+                streaming = Self {
+                    llm_client: Some(client),
+                    ..self.clone()
+                };
+                &streaming
+            }
+            _ => self,
+        };
+
         let history = ctx.flow().history();
 
         let rules = self.profile.rules.as_ref();
@@ -62,20 +101,68 @@ impl Agent for ConfigDrivenAgent {
             Some(&store_variables)
         };
 
-        #[cfg(feature = "openai-client")]
-        let response_content = LlmCaller::call_llm_or_get_raw(
-            self.llm_client.as_ref(),
-            &payload,
-            &history,
-            &self.profile,
-            field_extraction_rules,
-            prompt_building_rules,
-            store_variables_option,
-        )
-        .await?;
+        let response_content = match agent
+            .call_with_refusal_policy(
+                &payload,
+                &history,
+                field_extraction_rules,
+                prompt_building_rules,
+                store_variables_option,
+            )
+            .await?
+        {
+            RefusalOutcome::Answered(content) => content,
+            RefusalOutcome::RouteToHuman { target, refusal } => {
+                payload[fields::LAST_AGENT] = Value::String(self.profile.name.clone());
+                payload[fields::REFUSAL] = json!(refusal);
+                let message = StructuredMessage::new(payload).into_agent_message(
+                    MessageRole::Agent,
+                    &self.profile.name,
+                    Some(target.clone()),
+                )?;
+                return Ok(AgentAction::Next { target, message });
+            }
+            RefusalOutcome::Surface(refusal) => {
+                payload[fields::LAST_AGENT] = Value::String(self.profile.name.clone());
+                payload[fields::REFUSAL] = json!(refusal);
+                let message = StructuredMessage::new(payload).into_agent_message(
+                    MessageRole::Agent,
+                    &self.profile.name,
+                    None,
+                )?;
+                return Ok(AgentAction::Refused { refusal, message });
+            }
+        };
 
-        #[cfg(not(feature = "openai-client"))]
-        let response_content = LlmCaller::get_raw_from_payload(&payload)?;
+        let response_content = match agent
+            .enforce_output_policy(
+                response_content,
+                &payload,
+                &history,
+                field_extraction_rules,
+                prompt_building_rules,
+                routing_rules,
+                store_variables_option,
+            )
+            .await?
+        {
+            OutputOutcome::Accepted(content) => content,
+            OutputOutcome::RouteToHuman {
+                target,
+                output,
+                error,
+            } => {
+                payload[fields::LAST_AGENT] = Value::String(self.profile.name.clone());
+                payload[fields::RESPONSE] = Value::String(output);
+                payload[fields::VALIDATION_ERROR] = Value::String(error);
+                let message = StructuredMessage::new(payload).into_agent_message(
+                    MessageRole::Agent,
+                    &self.profile.name,
+                    Some(target.clone()),
+                )?;
+                return Ok(AgentAction::Next { target, message });
+            }
+        };
 
         let response_content_clean =
             if self.profile.route_mode.as_deref() == Some(routing_consts::MODE_AUTO) {
@@ -181,6 +268,385 @@ impl Agent for ConfigDrivenAgent {
     }
 }
 
+/// 输出校验结果
+enum OutputOutcome {
+    Accepted(String),
+    RouteToHuman {
+        target: String,
+        output: String,
+        error: String,
+    },
+}
+
+/// 拒答处理结果
+enum RefusalOutcome {
+    Answered(String),
+    RouteToHuman { target: String, refusal: LlmRefusal },
+    Surface(LlmRefusal),
+}
+
+impl ConfigDrivenAgent {
+    /// 检索知识库，把命中的文本块带引用标记注入 prompt 后回答，并在消息 metadata 中记录来源
+    async fn answer_with_knowledge(
+        &self,
+        retriever: &RagRetrieveTool,
+        message: AgentMessage,
+        ctx: &AgentContext<'_>,
+    ) -> Result<AgentAction> {
+        let history = ctx.flow().history();
+        let payload = MessageParser::parse_payload(&message, &history)?;
+        let user_input_fields = self
+            .profile
+            .rules
+            .as_ref()
+            .and_then(|r| r.field_extraction.as_ref())
+            .map(|r| r.user_input_fields.clone());
+        let query =
+            MessageParser::extract_user_input(&payload, &history, user_input_fields.as_deref())?;
+
+        let mut input = json!({ "query": query });
+        if let Some(filter) = self
+            .profile
+            .retrieval
+            .as_ref()
+            .and_then(|r| r.filter.as_ref())
+        {
+            input["filter"] = Value::Object(filter.clone());
+        }
+        let output = retriever
+            .call(ToolInvocation::new(retriever.name(), input), ctx.flow())
+            .await?;
+        let output: Value = serde_json::from_str(&output.content)
+            .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        let chunks: Vec<VectorMatch> =
+            serde_json::from_value(output["chunks"].clone()).unwrap_or_default();
+
+        let mut profile = (*self.profile).clone();
+        let mut sources = Vec::new();
+        if !chunks.is_empty() {
+            let mut knowledge = String::new();
+            for (index, chunk) in chunks.iter().enumerate() {
+                let marker = index + 1;
+                knowledge.push_str(&format!("[{}] {}\n", marker, chunk.content.trim()));
+                sources.push(json!({
+                    "marker": marker,
+                    "id": chunk.id,
+                    "score": chunk.score,
+                    "metadata": chunk.metadata,
+                }));
+            }
+            profile.prompt = Some(format!(
+                "{}{}{}",
+                profile.prompt.unwrap_or_default(),
+                prompt_consts::KNOWLEDGE_CONTEXT_HEADER,
+                knowledge
+            ));
+        }
+        tracing::info!(
+            agent = %self.profile.name,
+            sources = sources.len(),
+            "Retrieved knowledge for agent"
+        );
+
+        let agent = Self {
+            profile: Arc::new(profile),
+            retriever: None,
+            ..self.clone()
+        };
+        let mut action = agent.on_message(message, ctx).await?;
+        record_sources(&mut action, &Value::Array(sources));
+        Ok(action)
+    }
+
+    /// 在会话绑定的线程中运行 Assistant，工具调用交给运行时执行，线程 ID 写入会话状态
+    #[cfg(feature = "openai-client")]
+    async fn answer_with_assistant(
+        &self,
+        assistant: &Arc<crate::llm::OpenAiAssistantClient>,
+        message: AgentMessage,
+        ctx: &AgentContext<'_>,
+    ) -> Result<AgentAction> {
+        use crate::llm::AssistantThreadClient;
+
+        let session = ctx.session();
+        let key = assistant_thread_key(&self.profile.name);
+        let thread_id = session.get(&key).await?;
+        let (sender, mut tool_calls) = tokio::sync::mpsc::unbounded_channel();
+        let thread = AssistantThreadClient::new(assistant.clone(), thread_id.clone(), sender);
+
+        let agent = Self {
+            llm_client: Some(Arc::new(thread.clone())),
+            assistant: None,
+            ..self.clone()
+        };
+        let answer = agent.on_message(message, ctx);
+        tokio::pin!(answer);
+        let action = loop {
+            tokio::select! {
+                action = &mut answer => break action?,
+                Some(request) = tool_calls.recv() => {
+                    let name = request.call.name.clone();
+                    let output = ctx
+                        .runtime
+                        .call_tool(&name, ToolInvocation::new(&name, request.call.arguments))
+                        .await
+                        .map(|message| message.content);
+                    let _ = request.reply.send(output);
+                }
+            }
+        };
+
+        if let Some(id) = thread
+            .thread_id()
+            .filter(|id| thread_id.as_ref() != Some(id))
+        {
+            tracing::info!(agent = %self.profile.name, thread_id = %id, "Assistant thread created");
+            session.set(&key, id).await?;
+        }
+        Ok(action)
+    }
+
+    /// 调用 LLM，遇到提供商拒答时依次执行 `on_refusal` 中的处理步骤
+    async fn call_with_refusal_policy(
+        &self,
+        payload: &Value,
+        history: &[AgentMessage],
+        field_extraction_rules: Option<&FieldExtractionRules>,
+        prompt_building_rules: Option<&PromptBuildingRules>,
+        store_variables: Option<&std::collections::HashMap<String, String>>,
+    ) -> Result<RefusalOutcome> {
+        let mut refusal = match LlmCaller::call_llm_or_get_raw(
+            self.llm_client.as_ref(),
+            payload,
+            history,
+            &self.profile,
+            field_extraction_rules,
+            prompt_building_rules,
+            store_variables,
+        )
+        .await
+        {
+            Ok(content) => return Ok(RefusalOutcome::Answered(content)),
+            Err(AgentFlowError::Refused(refusal)) => refusal,
+            Err(e) => return Err(e),
+        };
+
+        let policy = self.profile.on_refusal.as_ref();
+        let steps = policy.map(|p| p.steps.as_slice()).unwrap_or_default();
+
+        for (attempt, step) in steps.iter().enumerate() {
+            tracing::warn!(
+                agent = %self.profile.name,
+                attempt = attempt + 1,
+                refusal = %refusal,
+                "LLM refused, applying on_refusal step"
+            );
+
+            let mut profile = (*self.profile).clone();
+            let mut llm_client = self.llm_client.clone();
+            match step {
+                RefusalAction::SoftenPrompt { prompt } => {
+                    profile.prompt = Some(match prompt {
+                        Some(prompt) => prompt.clone(),
+                        None => format!(
+                            "{}{}",
+                            profile.prompt.unwrap_or_default(),
+                            prompt_consts::REFUSAL_SOFTEN_INSTRUCTION
+                        ),
+                    });
+                }
+                RefusalAction::SwitchProvider {
+                    model,
+                    driver,
+                    endpoint,
+                    api_key,
+                } => match self.switch_model(&mut profile, model, driver, endpoint, api_key) {
+                    Some(client) => llm_client = client,
+                    None => continue,
+                },
+                RefusalAction::RouteToHuman { target } => {
+                    return Ok(RefusalOutcome::RouteToHuman {
+                        target: target.clone(),
+                        refusal,
+                    });
+                }
+            }
+
+            match LlmCaller::call_llm_or_get_raw(
+                llm_client.as_ref(),
+                payload,
+                history,
+                &profile,
+                field_extraction_rules,
+                prompt_building_rules,
+                store_variables,
+            )
+            .await
+            {
+                Ok(content) => return Ok(RefusalOutcome::Answered(content)),
+                Err(AgentFlowError::Refused(next)) => refusal = next,
+                Err(e) => return Err(e),
+            }
+        }
+
+        match policy.map(|p| &p.on_exhausted) {
+            Some(RefusalExhausted::Fail) => Err(AgentFlowError::Refused(refusal)),
+            _ => Ok(RefusalOutcome::Surface(refusal)),
+        }
+    }
+
+    /// 切换 profile 的模型/提供商并创建对应客户端
+    ///
+    /// 返回 `None` 表示客户端创建失败，应跳过当前步骤；驱动无需客户端时沿用当前客户端。
+    fn switch_model(
+        &self,
+        profile: &mut AgentConfig,
+        model: &str,
+        driver: &Option<AgentDriverKind>,
+        endpoint: &Option<String>,
+        api_key: &Option<String>,
+    ) -> Option<Option<DynLlmClient>> {
+        profile.model = Some(model.to_string());
+        if let Some(driver) = driver {
+            profile.driver = *driver;
+        }
+        if endpoint.is_some() {
+            profile.endpoint = endpoint.clone();
+        }
+        if api_key.is_some() {
+            profile.api_key = api_key.clone();
+        }
+        match LlmClientFactory::create_client(profile) {
+            Ok(Some(client)) => Some(Some(client)),
+            Ok(None) => Some(self.llm_client.clone()),
+            Err(e) => {
+                tracing::warn!(
+                    agent = %self.profile.name,
+                    model = %model,
+                    error = ?e,
+                    "Failed to create client for model switch, skipping step"
+                );
+                None
+            }
+        }
+    }
+
+    /// 按 `output_schema` 校验输出，失败时依次执行 `on_invalid_output` 中的补救步骤
+    #[allow(clippy::too_many_arguments)]
+    async fn enforce_output_policy(
+        &self,
+        mut output: String,
+        payload: &Value,
+        history: &[AgentMessage],
+        field_extraction_rules: Option<&FieldExtractionRules>,
+        prompt_building_rules: Option<&PromptBuildingRules>,
+        routing_rules: Option<&RoutingRules>,
+        store_variables: Option<&std::collections::HashMap<String, String>>,
+    ) -> Result<OutputOutcome> {
+        let Some(schema) = &self.profile.output_schema else {
+            return Ok(OutputOutcome::Accepted(output));
+        };
+        let mut error = match OutputValidator::validate(schema, &output, routing_rules) {
+            Ok(_) => return Ok(OutputOutcome::Accepted(output)),
+            Err(error) => error,
+        };
+
+        let policy = self.profile.on_invalid_output.as_ref();
+        let steps = policy.map(|p| p.steps.as_slice()).unwrap_or_default();
+
+        for (attempt, step) in steps.iter().enumerate() {
+            tracing::warn!(
+                agent = %self.profile.name,
+                attempt = attempt + 1,
+                error = %error,
+                "Agent output failed validation, applying on_invalid_output step"
+            );
+
+            let mut profile = (*self.profile).clone();
+            let mut llm_client = self.llm_client.clone();
+            match step {
+                InvalidOutputAction::RetryWithFeedback { feedback_template } => {
+                    let feedback =
+                        OutputValidator::feedback(feedback_template.as_deref(), &error, &output);
+                    profile.prompt = Some(format!(
+                        "{}{}",
+                        profile.prompt.unwrap_or_default(),
+                        feedback
+                    ));
+                }
+                InvalidOutputAction::BackupPrompt { prompt } => {
+                    profile.prompt = Some(prompt.clone());
+                }
+                InvalidOutputAction::EscalateModel {
+                    model,
+                    driver,
+                    endpoint,
+                    api_key,
+                } => match self.switch_model(&mut profile, model, driver, endpoint, api_key) {
+                    Some(client) => llm_client = client,
+                    None => continue,
+                },
+                InvalidOutputAction::RouteToHuman { target } => {
+                    return Ok(OutputOutcome::RouteToHuman {
+                        target: target.clone(),
+                        output,
+                        error,
+                    });
+                }
+            }
+
+            output = LlmCaller::call_llm_or_get_raw(
+                llm_client.as_ref(),
+                payload,
+                history,
+                &profile,
+                field_extraction_rules,
+                prompt_building_rules,
+                store_variables,
+            )
+            .await?;
+
+            match OutputValidator::validate(schema, &output, routing_rules) {
+                Ok(_) => return Ok(OutputOutcome::Accepted(output)),
+                Err(next_error) => error = next_error,
+            }
+        }
+
+        match policy.map(|p| &p.on_exhausted) {
+            Some(InvalidOutputExhausted::Accept) => Ok(OutputOutcome::Accepted(output)),
+            _ => Err(AgentFlowError::Other(anyhow::anyhow!(
+                "Agent `{}` produced invalid output: {}",
+                self.profile.name,
+                error
+            ))),
+        }
+    }
+}
+
+/// 会话状态中保存 Agent 的 Assistants 线程 ID 的键
+#[cfg(feature = "openai-client")]
+pub fn assistant_thread_key(agent: &str) -> String {
+    format!("assistant.{agent}.thread_id")
+}
+
+/// 在动作携带的消息 metadata 中记录检索来源
+fn record_sources(action: &mut AgentAction, sources: &Value) {
+    let messages: Vec<&mut AgentMessage> = match action {
+        AgentAction::Next { message, .. } | AgentAction::Refused { message, .. } => vec![message],
+        AgentAction::Branch { branches } => branches.values_mut().collect(),
+        AgentAction::Finish { message } | AgentAction::Continue { message } => {
+            message.iter_mut().collect()
+        }
+        AgentAction::CallTool { .. } => Vec::new(),
+    };
+    for message in messages {
+        let metadata = message.metadata.get_or_insert_with(|| json!({}));
+        if let Some(object) = metadata.as_object_mut() {
+            object.insert(fields::SOURCES.to_string(), sources.clone());
+        }
+    }
+}
+
 /// 配置驱动的 Tool 实现
 #[derive(Clone)]
 pub struct ConfigDrivenTool {
@@ -209,3 +675,268 @@ impl Tool for ConfigDrivenTool {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, AgentRegistry};
+    use crate::flow::FlowBuilder;
+    use crate::runtime::FlowExecutor;
+    use crate::state::MemoryStore;
+    use crate::tools::vector::{Embedder, InMemoryVectorStore, VectorRecord, VectorStore};
+    use crate::tools::ToolRegistry;
+
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl Embedder for KeywordEmbedder {
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    vec![
+                        t.matches("refund").count() as f32,
+                        t.matches("shipping").count() as f32,
+                    ]
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rag_agent_injects_knowledge_and_records_sources() {
+        let store = Arc::new(InMemoryVectorStore::default());
+        store
+            .upsert(vec![
+                VectorRecord::new("faq#0", vec![1.0, 0.0], "Refunds take 7 days.")
+                    .with_metadata(json!({ "doc_id": "faq" })),
+                VectorRecord::new("faq#1", vec![0.0, 1.0], "Shipping is free."),
+            ])
+            .await
+            .unwrap();
+        let retriever = RagRetrieveTool::with_config(
+            store,
+            Arc::new(KeywordEmbedder),
+            crate::tools::RagRetrieveConfig {
+                top_k: 1,
+                ..Default::default()
+            },
+        );
+
+        let profile: AgentConfig = serde_json::from_value(json!({
+            "name": "support",
+            "driver": "rag",
+            "prompt": "Answer support questions."
+        }))
+        .unwrap();
+        let agent = ConfigDrivenAgent {
+            profile: Arc::new(profile),
+            name: "support",
+            llm_client: None,
+            retriever: Some(Arc::new(retriever)),
+            #[cfg(feature = "openai-client")]
+            assistant: None,
+        };
+        let mut agents = AgentRegistry::new();
+        register_agent("support", Arc::new(agent), &mut agents);
+        let mut builder = FlowBuilder::new("kb");
+        builder
+            .add_agent_node("answer", "support")
+            .add_terminal_node("done")
+            .set_start("answer")
+            .connect("answer", "done");
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new());
+
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let input = json!({ "raw": "how long does a refund take?", "steps": [] }).to_string();
+        let execution = executor
+            .start(ctx, AgentMessage::user(input))
+            .await
+            .unwrap();
+
+        let message = execution.last_message.unwrap();
+        let payload: Value = serde_json::from_str(&message.content).unwrap();
+        let prompt = payload[fields::PROMPT].as_str().unwrap();
+        assert!(prompt.starts_with("Answer support questions."));
+        assert!(prompt.ends_with("[1] Refunds take 7 days.\n"));
+
+        let sources = &message.metadata.unwrap()[fields::SOURCES];
+        assert_eq!(sources.as_array().unwrap().len(), 1);
+        assert_eq!(sources[0]["marker"], 1);
+        assert_eq!(sources[0]["id"], "faq#0");
+        assert_eq!(sources[0]["metadata"]["doc_id"], "faq");
+    }
+
+    /// 模拟 Assistants API：首个运行请求工具 `lookup`，其余运行轮询一次后完成
+    #[cfg(feature = "openai-client")]
+    fn assistants_server() -> (String, std::sync::mpsc::Receiver<(String, String, Value)>) {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let mut runs = 0;
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let path = parts.next().unwrap_or_default().to_string();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((key, value)) = line.split_once(": ") {
+                        if key.eq_ignore_ascii_case("content-length") {
+                            length = value.parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+                let route = path.trim_start_matches("/v1/threads");
+                let response = match (method.as_str(), route) {
+                    ("POST", "") => json!({ "id": "thread_1" }),
+                    ("POST", "/thread_1/messages") => json!({ "id": "msg_1" }),
+                    ("POST", "/thread_1/runs") => {
+                        runs += 1;
+                        if runs == 1 {
+                            json!({
+                                "id": "run_1",
+                                "status": "requires_action",
+                                "required_action": { "submit_tool_outputs": { "tool_calls": [{
+                                    "id": "call_1",
+                                    "type": "function",
+                                    "function": { "name": "lookup", "arguments": "{\"order\":\"42\"}" }
+                                }] } }
+                            })
+                        } else {
+                            json!({ "id": format!("run_{runs}"), "status": "queued" })
+                        }
+                    }
+                    ("POST", "/thread_1/runs/run_1/submit_tool_outputs") => {
+                        json!({ "id": "run_1", "status": "in_progress" })
+                    }
+                    ("GET", run) if run.starts_with("/thread_1/runs/") => {
+                        json!({ "id": run.rsplit('/').next(), "status": "completed" })
+                    }
+                    ("GET", messages) if messages.starts_with("/thread_1/messages?run_id=") => {
+                        let run_id = messages["/thread_1/messages?run_id=".len()..]
+                            .split('&')
+                            .next()
+                            .unwrap();
+                        json!({ "data": [{
+                            "role": "assistant",
+                            "content": [{ "type": "text", "text": { "value": format!("answer from {run_id}") } }]
+                        }] })
+                    }
+                    _ => json!({ "error": "not found" }),
+                }
+                .to_string();
+                stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            response.len(),
+                            response
+                        )
+                        .as_bytes(),
+                    )
+                    .unwrap();
+                let _ = tx.send((method, path, body));
+            }
+        });
+        (url, rx)
+    }
+
+    #[cfg(feature = "openai-client")]
+    #[tokio::test]
+    async fn test_openai_assistant_reuses_session_thread_and_submits_tool_outputs() {
+        let (url, requests) = assistants_server();
+        let profile: AgentConfig = serde_json::from_value(json!({
+            "name": "support",
+            "driver": "openai_assistant",
+            "endpoint": url,
+            "api_key": "sk-test",
+            "prompt": "Answer order questions.",
+            "metadata": { "assistant_id": "asst_1", "poll_interval_ms": 1 }
+        }))
+        .unwrap();
+        let assistant = LlmClientFactory::create_assistant(&profile).unwrap();
+        let agent = ConfigDrivenAgent {
+            profile: Arc::new(profile),
+            name: "support",
+            llm_client: None,
+            retriever: None,
+            assistant,
+        };
+        let mut agents = AgentRegistry::new();
+        register_agent("support", Arc::new(agent), &mut agents);
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(ConfigDrivenTool {
+            profile: Arc::new(
+                serde_json::from_value(json!({ "name": "lookup", "driver": "echo" })).unwrap(),
+            ),
+            name: "lookup",
+        }));
+        let mut builder = FlowBuilder::new("assistant");
+        builder
+            .add_agent_node("answer", "support")
+            .add_terminal_node("done")
+            .set_start("answer")
+            .connect("answer", "done");
+        let executor = FlowExecutor::new(builder.build(), agents, tools);
+
+        let store = Arc::new(MemoryStore::new());
+        let mut responses = Vec::new();
+        for question in ["where is order 42?", "and when will it arrive?"] {
+            let ctx = Arc::new(FlowContext::new(store.clone()));
+            let input = json!({ "raw": question, "steps": [] }).to_string();
+            let execution = executor
+                .start(ctx.clone(), AgentMessage::user(input))
+                .await
+                .unwrap();
+            let payload: Value =
+                serde_json::from_str(&execution.last_message.unwrap().content).unwrap();
+            responses.push(payload[fields::RESPONSE].clone());
+            assert_eq!(
+                ctx.session()
+                    .get(&assistant_thread_key("support"))
+                    .await
+                    .unwrap()
+                    .as_deref(),
+                Some("thread_1")
+            );
+        }
+        assert_eq!(responses, ["answer from run_1", "answer from run_2"]);
+
+        let requests: Vec<_> = requests.try_iter().collect();
+        let created = requests
+            .iter()
+            .filter(|(method, path, _)| method == "POST" && path == "/v1/threads")
+            .count();
+        assert_eq!(created, 1);
+        let (_, _, run) = requests
+            .iter()
+            .find(|(_, path, _)| path == "/v1/threads/thread_1/runs")
+            .unwrap();
+        assert_eq!(run["assistant_id"], "asst_1");
+        let (_, _, submitted) = requests
+            .iter()
+            .find(|(_, path, _)| path.ends_with("/submit_tool_outputs"))
+            .unwrap();
+        assert_eq!(submitted["tool_outputs"][0]["tool_call_id"], "call_1");
+        let output: Value =
+            serde_json::from_str(submitted["tool_outputs"][0]["output"].as_str().unwrap()).unwrap();
+        assert_eq!(output["input"]["order"], "42");
+    }
+}
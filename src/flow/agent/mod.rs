@@ -1,3 +1,5 @@
 pub mod config_driven;
 
+#[cfg(feature = "openai-client")]
+pub use config_driven::assistant_thread_key;
 pub use config_driven::{ConfigDrivenAgent, ConfigDrivenTool};
@@ -0,0 +1,411 @@
+//! 流程图分析：强连通分量、不可达节点、无出边节点和最大执行深度
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde::Serialize;
+
+use super::nodes::{FlowNodeKind, JoinTimeoutPolicy, WaitNode};
+use super::types::Flow;
+
+/// `Flow::analyze` 的结果
+///
+/// 只分析静态边（转换、分支、循环、Map、Join 超时路由以及失败时转到的
+/// `dead_letter` / `on_error`），Agent 输出中 `route` 的动态跳转不在其中。
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct FlowAnalysis {
+    /// 成环的强连通分量（多个节点或有自环），分量内按名称排序
+    pub strongly_connected_components: Vec<Vec<String>>,
+    /// 没有 `max_iterations` 上限的循环，只能靠执行器的 `max_iterations` 终止
+    pub unbounded_cycles: Vec<Vec<String>>,
+    /// 从起始节点不可达的节点
+    pub unreachable: Vec<String>,
+    /// 没有出边的非终止节点
+    pub dead_ends: Vec<String>,
+    /// 从起始节点出发最多处理的事件数；存在可达的无上限循环时为 `None`
+    pub max_depth: Option<u64>,
+}
+
+impl FlowAnalysis {
+    pub fn is_bounded(&self) -> bool {
+        self.max_depth.is_some()
+    }
+}
+
+impl Flow {
+    /// 分析流程图的环、可达性和最大执行深度
+    pub fn analyze(&self) -> FlowAnalysis {
+        let graph = Graph::new(self);
+        let components = graph.components(|_| true);
+
+        let mut analysis = FlowAnalysis::default();
+        let mut component_of = vec![0; graph.names.len()];
+        let mut weights: Vec<Option<u64>> = Vec::with_capacity(components.len());
+        for (index, component) in components.iter().enumerate() {
+            for &node in component {
+                component_of[node] = index;
+            }
+            if !graph.is_cyclic(component) {
+                weights.push(Some(1));
+                continue;
+            }
+            analysis
+                .strongly_connected_components
+                .push(graph.sorted_names(component));
+            // 去掉有上限的 Loop 节点后仍然成环，说明存在不经过上限的环
+            let members: HashSet<usize> = component.iter().copied().collect();
+            let unbounded = graph
+                .components(|node| members.contains(&node) && graph.loop_bound(node).is_none())
+                .iter()
+                .any(|inner| graph.is_cyclic(inner));
+            if unbounded {
+                analysis
+                    .unbounded_cycles
+                    .push(graph.sorted_names(component));
+                weights.push(None);
+            } else {
+                let passes = component
+                    .iter()
+                    .filter_map(|&node| graph.loop_bound(node))
+                    .fold(1u64, |total, max| total.saturating_mul(u64::from(max) + 1));
+                weights.push(Some((component.len() as u64).saturating_mul(passes)));
+            }
+        }
+        analysis.strongly_connected_components.sort();
+        analysis.unbounded_cycles.sort();
+
+        // Tarjan 按逆拓扑序产出分量，后继分量总是先算完
+        let mut depths: Vec<Option<u64>> = Vec::with_capacity(components.len());
+        for (index, component) in components.iter().enumerate() {
+            let mut longest = Some(0u64);
+            for &node in component {
+                for &next in &graph.successors[node] {
+                    if component_of[next] != index {
+                        longest = match (longest, depths[component_of[next]]) {
+                            (Some(current), Some(depth)) => Some(current.max(depth)),
+                            _ => None,
+                        };
+                    }
+                }
+            }
+            depths.push(match (weights[index], longest) {
+                (Some(weight), Some(longest)) => Some(weight.saturating_add(longest)),
+                _ => None,
+            });
+        }
+
+        let start = graph.index.get(self.start.as_str()).copied();
+        analysis.max_depth = match start {
+            Some(start) => depths[component_of[start]],
+            None => Some(0),
+        };
+
+        let mut reachable = vec![false; graph.names.len()];
+        let mut queue: VecDeque<usize> = start.into_iter().collect();
+        while let Some(node) = queue.pop_front() {
+            if std::mem::replace(&mut reachable[node], true) {
+                continue;
+            }
+            queue.extend(graph.successors[node].iter().copied());
+        }
+        let map_bodies: HashSet<&str> = self
+            .nodes
+            .values()
+            .filter_map(|node| match &node.kind {
+                FlowNodeKind::Map(map) => Some(map.body.as_str()),
+                _ => None,
+            })
+            .collect();
+        for (node, name) in graph.names.iter().enumerate() {
+            if !reachable[node] {
+                analysis.unreachable.push(name.to_string());
+            }
+            // Map 的 body 输出会回到 Map 节点，不需要出边
+            let terminal = matches!(self.nodes[*name].kind, FlowNodeKind::Terminal);
+            if !terminal && graph.explicit[node] == 0 && !map_bodies.contains(name) {
+                analysis.dead_ends.push(name.to_string());
+            }
+        }
+        analysis
+    }
+}
+
+/// 流程中的一条边：起点、终点和可选标签
+pub(crate) fn flow_edges(flow: &Flow) -> Vec<(String, String, Option<String>)> {
+    let mut names: Vec<&String> = flow.nodes.keys().collect();
+    names.sort();
+    let mut edges = Vec::new();
+    for name in names {
+        let node = &flow.nodes[name];
+        match &node.kind {
+            FlowNodeKind::Decision(decision) => {
+                for branch in &decision.branches {
+                    let label = branch
+                        .description
+                        .as_ref()
+                        .map(|info| info.description.clone())
+                        .or_else(|| branch.name.clone());
+                    edges.push((name.clone(), branch.target.clone(), label));
+                }
+            }
+            FlowNodeKind::LlmDecision(decision) => {
+                for branch in &decision.branches {
+                    edges.push((
+                        name.clone(),
+                        branch.target.clone(),
+                        Some(branch.label.clone()),
+                    ));
+                }
+                edges.push((
+                    name.clone(),
+                    decision.fallback.clone(),
+                    Some("fallback".into()),
+                ));
+            }
+            FlowNodeKind::Map(map) => {
+                edges.push((name.clone(), map.body.clone(), Some("each".into())));
+            }
+            FlowNodeKind::Experiment(experiment) => {
+                for variant in &experiment.variants {
+                    edges.push((
+                        name.clone(),
+                        variant.target.clone(),
+                        Some(format!("{} ({})", variant.name, variant.weight)),
+                    ));
+                }
+            }
+            FlowNodeKind::Loop(loop_node) => {
+                edges.push((name.clone(), loop_node.entry.clone(), Some("loop".into())));
+                if let Some(exit) = &loop_node.exit {
+                    edges.push((name.clone(), exit.clone(), Some("exit".into())));
+                }
+            }
+            FlowNodeKind::Join(join) => {
+                if let JoinTimeoutPolicy::RouteTo(target) = &join.on_timeout {
+                    edges.push((name.clone(), target.clone(), Some("timeout".into())));
+                }
+            }
+            FlowNodeKind::Wait(WaitNode::UntilState(until)) => {
+                if let Some(target) = &until.on_timeout {
+                    edges.push((name.clone(), target.clone(), Some("timeout".into())));
+                }
+            }
+            FlowNodeKind::AwaitEvent(await_event) => {
+                if let Some(target) = &await_event.on_timeout {
+                    edges.push((name.clone(), target.clone(), Some("timeout".into())));
+                }
+            }
+            _ => {}
+        }
+        for transition in flow.transitions(name) {
+            let label = transition
+                .description
+                .as_ref()
+                .map(|info| info.description.clone())
+                .or_else(|| transition.name.clone())
+                .or_else(|| transition.condition.as_ref().map(|_| "condition".into()));
+            edges.push((name.clone(), transition.to.clone(), label));
+        }
+    }
+    edges
+}
+
+/// 按下标表示的流程图，节点按名称排序
+struct Graph<'a> {
+    names: Vec<&'a str>,
+    index: HashMap<&'a str, usize>,
+    successors: Vec<Vec<usize>>,
+    /// 不含失败边的出边数
+    explicit: Vec<usize>,
+    loop_bounds: Vec<Option<u32>>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(flow: &'a Flow) -> Self {
+        let mut names: Vec<&str> = flow.nodes.keys().map(String::as_str).collect();
+        names.sort();
+        let index: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (*name, index))
+            .collect();
+        let mut successors = vec![Vec::new(); names.len()];
+        let mut explicit = vec![0; names.len()];
+        for (from, to, _) in flow_edges(flow) {
+            explicit[index[from.as_str()]] += 1;
+            if let Some(&to) = index.get(to.as_str()) {
+                successors[index[from.as_str()]].push(to);
+            }
+        }
+
+        // 失败边：Agent/Tool 节点优先转到死信节点，其余非终止节点转到错误处理节点
+        let dead_letter = flow.dead_letter.as_deref().and_then(|name| index.get(name));
+        let on_error = flow.on_error.as_deref().and_then(|name| index.get(name));
+        let mut loop_bounds = Vec::with_capacity(names.len());
+        for (node, name) in names.iter().enumerate() {
+            let kind = &flow.nodes[*name].kind;
+            let target = match kind {
+                FlowNodeKind::Terminal => None,
+                FlowNodeKind::Agent(_) | FlowNodeKind::Tool(_) if dead_letter.is_some() => {
+                    dead_letter
+                }
+                _ => on_error.filter(|&&handler| handler != node),
+            };
+            if let Some(&target) = target {
+                successors[node].push(target);
+            }
+            successors[node].sort_unstable();
+            successors[node].dedup();
+            loop_bounds.push(match kind {
+                FlowNodeKind::Loop(loop_node) => loop_node.max_iterations,
+                _ => None,
+            });
+        }
+
+        Self {
+            names,
+            index,
+            successors,
+            explicit,
+            loop_bounds,
+        }
+    }
+
+    fn loop_bound(&self, node: usize) -> Option<u32> {
+        self.loop_bounds[node]
+    }
+
+    fn is_cyclic(&self, component: &[usize]) -> bool {
+        component.len() > 1 || self.successors[component[0]].contains(&component[0])
+    }
+
+    fn sorted_names(&self, component: &[usize]) -> Vec<String> {
+        let mut names: Vec<String> = component
+            .iter()
+            .map(|&node| self.names[node].to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Tarjan 算法求 `include` 选中的子图的强连通分量，按逆拓扑序返回
+    fn components(&self, include: impl Fn(usize) -> bool) -> Vec<Vec<usize>> {
+        let mut tarjan = Tarjan {
+            graph: self,
+            include: &include,
+            counter: 0,
+            indices: vec![None; self.names.len()],
+            lowlinks: vec![0; self.names.len()],
+            on_stack: vec![false; self.names.len()],
+            stack: Vec::new(),
+            components: Vec::new(),
+        };
+        for node in 0..self.names.len() {
+            if include(node) && tarjan.indices[node].is_none() {
+                tarjan.visit(node);
+            }
+        }
+        tarjan.components
+    }
+}
+
+struct Tarjan<'g, 'a, F> {
+    graph: &'g Graph<'a>,
+    include: &'g F,
+    counter: usize,
+    indices: Vec<Option<usize>>,
+    lowlinks: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    components: Vec<Vec<usize>>,
+}
+
+impl<F: Fn(usize) -> bool> Tarjan<'_, '_, F> {
+    fn visit(&mut self, node: usize) {
+        self.indices[node] = Some(self.counter);
+        self.lowlinks[node] = self.counter;
+        self.counter += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        for &next in &self.graph.successors[node] {
+            if !(self.include)(next) {
+                continue;
+            }
+            match self.indices[next] {
+                None => {
+                    self.visit(next);
+                    self.lowlinks[node] = self.lowlinks[node].min(self.lowlinks[next]);
+                }
+                Some(index) if self.on_stack[next] => {
+                    self.lowlinks[node] = self.lowlinks[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.lowlinks[node]) == self.indices[node] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flow::FlowBuilder;
+
+    fn builder() -> FlowBuilder {
+        let mut builder = FlowBuilder::new("review");
+        builder
+            .add_agent_node("intake", "writer")
+            .add_loop_node("review", "draft", None, Some(2), Some("done".into()))
+            .add_agent_node("draft", "writer")
+            .add_tool_node("stuck", "archive")
+            .add_agent_node("recover", "writer")
+            .add_terminal_node("done")
+            .add_terminal_node("orphan")
+            .set_start("intake")
+            .set_error_handler("recover")
+            .connect("intake", "review")
+            .connect("intake", "stuck")
+            .connect("draft", "review")
+            .connect("recover", "done");
+        builder
+    }
+
+    #[test]
+    fn test_analyze_cycles_reachability_and_depth() {
+        let analysis = builder().build().analyze();
+        assert_eq!(
+            analysis.strongly_connected_components,
+            vec![vec!["draft".to_string(), "review".to_string()]]
+        );
+        assert!(analysis.unbounded_cycles.is_empty());
+        assert_eq!(analysis.unreachable, vec!["orphan".to_string()]);
+        assert_eq!(analysis.dead_ends, vec!["stuck".to_string()]);
+        // intake + 循环 2 个节点 × 3 轮 + recover + done
+        assert_eq!(analysis.max_depth, Some(9));
+
+        let mut builder = builder();
+        builder
+            .add_agent_node("chat", "writer")
+            .add_agent_node("critic", "writer")
+            .connect("intake", "chat")
+            .connect("chat", "critic")
+            .connect("critic", "chat");
+        let analysis = builder.build().analyze();
+        assert_eq!(
+            analysis.unbounded_cycles,
+            vec![vec!["chat".to_string(), "critic".to_string()]]
+        );
+        assert_eq!(analysis.strongly_connected_components.len(), 2);
+        assert!(!analysis.is_bounded());
+    }
+}
@@ -1,31 +1,53 @@
-use crate::flow::conditions::{LoopContinuation, TransitionCondition};
+use crate::flow::conditions::{ConditionInfo, LoopContinuation, TransitionCondition};
 use crate::flow::nodes::{
-    DecisionBranch, DecisionNode, DecisionPolicy, FlowNode, FlowNodeKind, JoinNode, JoinStrategy,
-    LoopNode, ToolNode,
+    AwaitEventNode, DebateNode, DecisionBranch, DecisionNode, DecisionPolicy, ExperimentNode,
+    FlowNode, FlowNodeKind, GroupChatNode, ImageGenNode, JoinNode, JoinStrategy, JoinTimeoutPolicy,
+    LlmDecisionNode, LoopNode, MapNode, MemoizePolicy, SubFlowNode, ToolAggregate, ToolNode,
+    WaitNode,
 };
+use crate::flow::outputs::FlowOutput;
+use crate::flow::transform::TransformNode;
 use crate::flow::types::{Flow, FlowParameter, FlowTransition, FlowVariable};
+use crate::guardrails::{InjectionGuard, PiiRedactor};
+use crate::state::HistoryRetention;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Flow 构建器
 pub struct FlowBuilder {
     name: String,
+    version: u32,
     start: Option<String>,
     nodes: HashMap<String, FlowNode>,
     transitions: HashMap<String, Vec<FlowTransition>>,
     parameters: Vec<FlowParameter>,
     variables: Vec<FlowVariable>,
+    outputs: Vec<FlowOutput>,
+    pii_redactor: Option<Arc<PiiRedactor>>,
+    injection_guard: Option<Arc<InjectionGuard>>,
+    dead_letter: Option<String>,
+    on_error: Option<String>,
+    history_retention: Option<HistoryRetention>,
 }
 
 impl FlowBuilder {
     pub fn new<T: Into<String>>(name: T) -> Self {
         Self {
             name: name.into(),
+            version: 1,
             start: None,
             nodes: HashMap::new(),
             transitions: HashMap::new(),
             parameters: Vec::new(),
             variables: Vec::new(),
+            outputs: Vec::new(),
+            pii_redactor: None,
+            injection_guard: None,
+            dead_letter: None,
+            on_error: None,
+            history_retention: None,
         }
     }
 
@@ -80,13 +102,36 @@ impl FlowBuilder {
             name.to_string(),
             FlowNode {
                 name: name.to_string(),
-                kind: FlowNodeKind::Join(JoinNode { strategy, inbound }),
+                kind: FlowNodeKind::Join(JoinNode {
+                    strategy,
+                    inbound,
+                    timeout: None,
+                    on_timeout: JoinTimeoutPolicy::default(),
+                }),
                 metadata: None,
             },
         );
         self
     }
 
+    /// 为已添加的 Join 节点设置超时
+    pub fn set_join_timeout(
+        &mut self,
+        name: &str,
+        timeout: Duration,
+        on_timeout: JoinTimeoutPolicy,
+    ) -> &mut Self {
+        if let Some(FlowNode {
+            kind: FlowNodeKind::Join(join),
+            ..
+        }) = self.nodes.get_mut(name)
+        {
+            join.timeout = Some(timeout);
+            join.on_timeout = on_timeout;
+        }
+        self
+    }
+
     pub fn add_loop_node(
         &mut self,
         name: &str,
@@ -118,6 +163,20 @@ impl FlowBuilder {
         self
     }
 
+    /// 添加 Map 节点，并把 `body` 的输出连回 Map 节点
+    pub fn add_map_node(&mut self, name: &str, node: MapNode) -> &mut Self {
+        let body = node.body.clone();
+        self.nodes.insert(
+            name.to_string(),
+            FlowNode {
+                name: name.to_string(),
+                kind: FlowNodeKind::Map(node),
+                metadata: None,
+            },
+        );
+        self.connect_named(&body, name, Some("map_result".to_string()))
+    }
+
     pub fn add_tool_node(&mut self, name: &str, pipeline: &str) -> &mut Self {
         self.add_tool_node_with_params(name, pipeline, None)
     }
@@ -130,6 +189,9 @@ impl FlowBuilder {
                 kind: FlowNodeKind::Tool(ToolNode {
                     pipeline: pipeline.to_string(),
                     params: params.clone(),
+                    compensate: None,
+                    parallel: Vec::new(),
+                    aggregate: ToolAggregate::default(),
                 }),
                 metadata: params,
             },
@@ -137,6 +199,155 @@ impl FlowBuilder {
         self
     }
 
+    /// 为已添加的 Tool 节点设置补偿流水线：节点成功后运行失败时调用
+    pub fn set_compensation(&mut self, name: &str, pipeline: &str) -> &mut Self {
+        if let Some(FlowNode {
+            kind: FlowNodeKind::Tool(tool),
+            ..
+        }) = self.nodes.get_mut(name)
+        {
+            tool.compensate = Some(pipeline.to_string());
+        }
+        self
+    }
+
+    /// 为已添加的 Tool 节点增加并发执行的流水线，输出按 `aggregate` 合并
+    pub fn set_parallel_pipelines(
+        &mut self,
+        name: &str,
+        pipelines: &[&str],
+        aggregate: ToolAggregate,
+    ) -> &mut Self {
+        if let Some(FlowNode {
+            kind: FlowNodeKind::Tool(tool),
+            ..
+        }) = self.nodes.get_mut(name)
+        {
+            tool.parallel = pipelines
+                .iter()
+                .map(|pipeline| pipeline.to_string())
+                .collect();
+            tool.aggregate = aggregate;
+        }
+        self
+    }
+
+    pub fn add_subflow_node(
+        &mut self,
+        name: &str,
+        flow: &str,
+        memoize: Option<MemoizePolicy>,
+    ) -> &mut Self {
+        self.nodes.insert(
+            name.to_string(),
+            FlowNode {
+                name: name.to_string(),
+                kind: FlowNodeKind::SubFlow(SubFlowNode {
+                    flow: flow.to_string(),
+                    memoize,
+                }),
+                metadata: None,
+            },
+        );
+        self
+    }
+
+    pub fn add_llm_decision_node(&mut self, name: &str, node: LlmDecisionNode) -> &mut Self {
+        self.nodes.insert(
+            name.to_string(),
+            FlowNode {
+                name: name.to_string(),
+                kind: FlowNodeKind::LlmDecision(node),
+                metadata: None,
+            },
+        );
+        self
+    }
+
+    pub fn add_experiment_node(&mut self, name: &str, node: ExperimentNode) -> &mut Self {
+        self.nodes.insert(
+            name.to_string(),
+            FlowNode {
+                name: name.to_string(),
+                kind: FlowNodeKind::Experiment(node),
+                metadata: None,
+            },
+        );
+        self
+    }
+
+    pub fn add_group_chat_node(&mut self, name: &str, node: GroupChatNode) -> &mut Self {
+        self.nodes.insert(
+            name.to_string(),
+            FlowNode {
+                name: name.to_string(),
+                kind: FlowNodeKind::GroupChat(node),
+                metadata: None,
+            },
+        );
+        self
+    }
+
+    pub fn add_debate_node(&mut self, name: &str, node: DebateNode) -> &mut Self {
+        self.nodes.insert(
+            name.to_string(),
+            FlowNode {
+                name: name.to_string(),
+                kind: FlowNodeKind::Debate(node),
+                metadata: None,
+            },
+        );
+        self
+    }
+
+    pub fn add_image_gen_node(&mut self, name: &str, node: ImageGenNode) -> &mut Self {
+        self.nodes.insert(
+            name.to_string(),
+            FlowNode {
+                name: name.to_string(),
+                kind: FlowNodeKind::ImageGen(node),
+                metadata: None,
+            },
+        );
+        self
+    }
+
+    pub fn add_transform_node(&mut self, name: &str, node: TransformNode) -> &mut Self {
+        self.nodes.insert(
+            name.to_string(),
+            FlowNode {
+                name: name.to_string(),
+                kind: FlowNodeKind::Transform(node),
+                metadata: None,
+            },
+        );
+        self
+    }
+
+    pub fn add_wait_node(&mut self, name: &str, node: impl Into<WaitNode>) -> &mut Self {
+        self.nodes.insert(
+            name.to_string(),
+            FlowNode {
+                name: name.to_string(),
+                kind: FlowNodeKind::Wait(node.into()),
+                metadata: None,
+            },
+        );
+        self
+    }
+
+    pub fn add_await_event_node(&mut self, name: &str, node: AwaitEventNode) -> &mut Self {
+        self.nodes.insert(
+            name.to_string(),
+            FlowNode {
+                name: name.to_string(),
+                kind: FlowNodeKind::AwaitEvent(node),
+                metadata: None,
+            },
+        );
+        self
+    }
+
     pub fn with_parameter(&mut self, parameter: FlowParameter) -> &mut Self {
         self.parameters.push(parameter);
         self
@@ -155,11 +366,53 @@ impl FlowBuilder {
         self
     }
 
+    /// 声明输出，运行结束后提取到 `FlowExecution::outputs`
+    pub fn declare_output(&mut self, output: FlowOutput) -> &mut Self {
+        self.outputs.push(output);
+        self
+    }
+
     pub fn set_start(&mut self, name: &str) -> &mut Self {
         self.start = Some(name.to_string());
         self
     }
 
+    /// 运行时对写入历史的消息做 PII 脱敏（上下文已挂载脱敏器时以上下文为准）
+    pub fn set_pii_redactor(&mut self, redactor: Arc<PiiRedactor>) -> &mut Self {
+        self.pii_redactor = Some(redactor);
+        self
+    }
+
+    /// 用户消息判定为注入时改为进入 `guard.quarantine()` 节点；`build` 时该节点必须存在
+    pub fn set_injection_guard(&mut self, guard: Arc<InjectionGuard>) -> &mut Self {
+        self.injection_guard = Some(guard);
+        self
+    }
+
+    /// 图的版本号，默认为 1
+    pub fn set_version(&mut self, version: u32) -> &mut Self {
+        self.version = version;
+        self
+    }
+
+    /// Agent/Tool 节点失败时把失败事件转到 `name` 节点，不再终止整个运行
+    pub fn set_dead_letter(&mut self, name: &str) -> &mut Self {
+        self.dead_letter = Some(name.to_string());
+        self
+    }
+
+    /// 任意节点失败时带着 `ErrorEnvelope` 转到 `name` 节点（Agent/Tool 节点优先使用死信节点）
+    pub fn set_error_handler(&mut self, name: &str) -> &mut Self {
+        self.on_error = Some(name.to_string());
+        self
+    }
+
+    /// 运行时限制消息历史的大小（上下文已设置保留策略时以上下文为准）
+    pub fn set_history_retention(&mut self, retention: HistoryRetention) -> &mut Self {
+        self.history_retention = Some(retention);
+        self
+    }
+
     pub fn connect(&mut self, from: &str, to: &str) -> &mut Self {
         self.connect_named(from, to, None)
     }
@@ -172,10 +425,24 @@ impl FlowBuilder {
                 to: to.to_string(),
                 condition: None,
                 name,
+                description: None,
+                pipeline: false,
             });
         self
     }
 
+    /// 把 `from` 到 `to` 的无条件转移设为流水线模式，见 `Agent::on_stream`；`from` 有其他出边时不生效
+    pub fn set_pipeline(&mut self, from: &str, to: &str) -> &mut Self {
+        if let Some(transitions) = self.transitions.get_mut(from) {
+            for transition in transitions {
+                if transition.to == to && transition.condition.is_none() {
+                    transition.pipeline = true;
+                }
+            }
+        }
+        self
+    }
+
     pub fn connect_if(
         &mut self,
         from: &str,
@@ -206,6 +473,8 @@ impl FlowBuilder {
                     to: exit_target.to_string(),
                     condition: None,
                     name: Some("loop_exit".to_string()),
+                    description: None,
+                    pipeline: false,
                 });
         }
         self
@@ -226,6 +495,29 @@ impl FlowBuilder {
         to: &str,
         name: Option<String>,
         condition: TransitionCondition,
+    ) -> &mut Self {
+        self.push_conditional(from, to, name, condition, None)
+    }
+
+    /// 添加带描述的条件转换，explain 模式下据此生成判定理由
+    pub fn connect_conditional_described(
+        &mut self,
+        from: &str,
+        to: &str,
+        name: Option<String>,
+        condition: TransitionCondition,
+        description: ConditionInfo,
+    ) -> &mut Self {
+        self.push_conditional(from, to, name, condition, Some(description))
+    }
+
+    fn push_conditional(
+        &mut self,
+        from: &str,
+        to: &str,
+        name: Option<String>,
+        condition: TransitionCondition,
+        description: Option<ConditionInfo>,
     ) -> &mut Self {
         self.transitions
             .entry(from.to_string())
@@ -234,19 +526,36 @@ impl FlowBuilder {
                 to: to.to_string(),
                 condition: Some(condition),
                 name,
+                description,
+                pipeline: false,
             });
         self
     }
 
     pub fn build(self) -> Flow {
         let start = self.start.expect("Flow must have a start node");
+        if let Some(guard) = &self.injection_guard {
+            assert!(
+                self.nodes.contains_key(guard.quarantine()),
+                "quarantine node `{}` not found in flow `{}`",
+                guard.quarantine(),
+                self.name
+            );
+        }
         Flow {
             name: self.name,
+            version: self.version,
             start,
             nodes: self.nodes,
             transitions: self.transitions,
             parameters: self.parameters,
             variables: self.variables,
+            outputs: self.outputs,
+            pii_redactor: self.pii_redactor,
+            injection_guard: self.injection_guard,
+            dead_letter: self.dead_letter,
+            on_error: self.on_error,
+            history_retention: self.history_retention,
         }
     }
 }
@@ -1,9 +1,10 @@
 use crate::state::FlowContext;
+use serde::Serialize;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
-/// Flow 条件类型定义
+// Flow 条件类型定义
 
 /// 条件 Future 类型
 pub type ConditionFuture<'a> = Pin<Box<dyn Future<Output = bool> + Send + 'a>>;
@@ -11,6 +12,30 @@ pub type ConditionFuture<'a> = Pin<Box<dyn Future<Output = bool> + Send + 'a>>;
 /// 转换条件类型
 pub type TransitionCondition = Arc<dyn Fn(&FlowContext) -> ConditionFuture<'_> + Send + Sync>;
 
+/// 条件的可读描述（explain 模式下用于生成判定理由）
+///
+/// `keys` 为条件读取的状态键，判定时会记录这些键的当前值。
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ConditionInfo {
+    pub description: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<String>,
+}
+
+impl ConditionInfo {
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            keys: Vec::new(),
+        }
+    }
+
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.keys.push(key.into());
+        self
+    }
+}
+
 /// 循环继续 Future 类型
 pub type LoopContinuationFuture<'a> = Pin<Box<dyn Future<Output = bool> + Send + 'a>>;
 
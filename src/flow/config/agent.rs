@@ -1,10 +1,18 @@
 use super::driver::AgentDriverKind;
+use crate::agent::DecoratorSpec;
+use crate::error::Result;
+use crate::flow::constants::{llm as llm_consts, routing as routing_consts};
+use crate::guardrails::GuardrailsConfig;
+use crate::llm::{DynLlmClient, LlmParams};
+use crate::schema::Schema;
+use crate::tools::{Embedder, LlmEmbedder, RagRetrieveTool, RagRetrieveToolConfig};
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Agent 配置
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct AgentConfig {
     pub name: String,
     #[serde(default)]
@@ -40,9 +48,166 @@ pub struct AgentConfig {
     /// LLM 温度值（默认 0.7）
     #[serde(default)]
     pub temperature: Option<f32>,
+    /// 其余采样参数：`top_p`、`max_tokens`、`frequency_penalty`、`presence_penalty`、`stop`、`seed`
+    #[serde(flatten)]
+    pub params: LlmParams,
     /// 业务规则配置（从 graph_config 读取）
     #[serde(default)]
     pub rules: Option<AgentRulesConfig>,
+    /// 输出 Schema（已注册的 Schema 名称或内联定义）
+    #[serde(default)]
+    pub output_schema: Option<OutputSchemaRef>,
+    /// 输出校验失败时的处理策略
+    #[serde(default)]
+    pub on_invalid_output: Option<InvalidOutputPolicy>,
+    /// 提供商拒答（内容过滤）时的处理策略
+    #[serde(default)]
+    pub on_refusal: Option<RefusalPolicy>,
+    /// 知识库检索配置（`rag` 驱动必填）
+    #[serde(default)]
+    pub retrieval: Option<RetrievalConfig>,
+    /// 输入 / 输出内容安全护栏
+    #[serde(default)]
+    pub guardrails: Option<GuardrailsConfig>,
+    /// 按顺序包装 Agent 的装饰器，如 `["reflect"]`
+    #[serde(default)]
+    pub decorators: Vec<DecoratorSpec>,
+}
+
+/// 知识库检索配置
+///
+/// ```json
+/// {
+///   "store": { "backend": "qdrant", "url": "http://localhost:6333", "collection": "docs" },
+///   "embedding": { "endpoint": "https://api.openai.com/v1/embeddings", "model": "text-embedding-3-small" },
+///   "top_k": 4,
+///   "filter": { "lang": "zh" }
+/// }
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetrievalConfig {
+    #[serde(flatten)]
+    pub tool: RagRetrieveToolConfig,
+    /// 元数据等值过滤
+    #[serde(default)]
+    pub filter: Option<serde_json::Map<String, Value>>,
+}
+
+impl RetrievalConfig {
+    /// 构建检索工具；未配置 `embedding` 时使用 Agent 的 LLM 客户端计算向量
+    pub fn build(&self, llm_client: Option<&DynLlmClient>) -> Result<RagRetrieveTool> {
+        let fallback = llm_client
+            .map(|client| Arc::new(LlmEmbedder::new(client.clone())) as Arc<dyn Embedder>);
+        self.tool.clone().build_with(fallback)
+    }
+}
+
+/// 输出 Schema 引用
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum OutputSchemaRef {
+    /// 通过 `register_schema` 注册的 Schema 名称
+    Named(String),
+    /// 内联 Schema 定义
+    Inline(Schema),
+}
+
+/// 非法输出处理策略
+///
+/// `steps` 按顺序执行，每一步对应一次补救尝试；全部失败后按 `on_exhausted` 处理。
+#[derive(Debug, Deserialize, Clone)]
+pub struct InvalidOutputPolicy {
+    #[serde(default = "default_invalid_output_steps")]
+    pub steps: Vec<InvalidOutputAction>,
+    #[serde(default)]
+    pub on_exhausted: InvalidOutputExhausted,
+}
+
+fn default_invalid_output_steps() -> Vec<InvalidOutputAction> {
+    vec![InvalidOutputAction::RetryWithFeedback {
+        feedback_template: None,
+    }]
+}
+
+/// 单步补救动作
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum InvalidOutputAction {
+    /// 附加错误反馈后重试（模板支持 {error} 和 {output} 占位符）
+    RetryWithFeedback {
+        #[serde(default)]
+        feedback_template: Option<String>,
+    },
+    /// 切换到备用 prompt 重试
+    BackupPrompt { prompt: String },
+    /// 升级到更强的模型重试
+    EscalateModel {
+        model: String,
+        #[serde(default)]
+        driver: Option<AgentDriverKind>,
+        #[serde(default)]
+        endpoint: Option<String>,
+        #[serde(default)]
+        api_key: Option<String>,
+    },
+    /// 路由到人工处理节点
+    RouteToHuman { target: String },
+}
+
+/// 补救全部失败后的处理方式
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidOutputExhausted {
+    /// 返回错误
+    #[default]
+    Fail,
+    /// 接受最后一次输出并继续
+    Accept,
+}
+
+/// 拒答处理策略
+///
+/// `steps` 按顺序执行，每一步对应一次重试；全部仍被拒答后按 `on_exhausted` 处理。
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RefusalPolicy {
+    #[serde(default)]
+    pub steps: Vec<RefusalAction>,
+    #[serde(default)]
+    pub on_exhausted: RefusalExhausted,
+}
+
+/// 拒答后的单步处理动作
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RefusalAction {
+    /// 改写 prompt 后重试（未指定时追加默认的缓和指令）
+    SoftenPrompt {
+        #[serde(default)]
+        prompt: Option<String>,
+    },
+    /// 切换到其他提供商/模型重试
+    SwitchProvider {
+        model: String,
+        #[serde(default)]
+        driver: Option<AgentDriverKind>,
+        #[serde(default)]
+        endpoint: Option<String>,
+        #[serde(default)]
+        api_key: Option<String>,
+    },
+    /// 路由到人工处理节点
+    RouteToHuman { target: String },
+}
+
+/// 处理步骤用尽后的拒答处理方式
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RefusalExhausted {
+    /// 以 `AgentAction::Refused` 结束流程并携带拒答信息
+    #[default]
+    Surface,
+    /// 返回 `AgentFlowError::Refused`
+    Fail,
 }
 
 /// Agent 业务规则配置（内部使用）
@@ -157,15 +322,15 @@ fn default_target_suffixes() -> Vec<String> {
 }
 
 fn default_json_code_block_start() -> String {
-    "```json".to_string()
+    routing_consts::JSON_CODE_BLOCK_START.to_string()
 }
 
 fn default_code_block_start() -> String {
-    "```".to_string()
+    routing_consts::CODE_BLOCK_START.to_string()
 }
 
 fn default_code_block_end() -> String {
-    "```".to_string()
+    routing_consts::CODE_BLOCK_END.to_string()
 }
 
 /// Payload 构建规则
@@ -188,24 +353,22 @@ pub struct ImageProcessingRules {
 }
 
 fn default_vision_keywords() -> Vec<String> {
-    vec!["vl".to_string(), "vision".to_string()]
+    vec![
+        llm_consts::VISION_KEYWORD_VL.to_string(),
+        llm_consts::VISION_KEYWORD_VISION.to_string(),
+    ]
 }
 
 /// Tool 驱动类型
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum ToolDriverKind {
+    #[default]
     Echo,
 }
 
-impl Default for ToolDriverKind {
-    fn default() -> Self {
-        ToolDriverKind::Echo
-    }
-}
-
 /// Tool 配置
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct ToolConfig {
     pub name: String,
     #[serde(default)]
@@ -0,0 +1,412 @@
+//! 以代码构建 `WorkflowConfig`，不必手写 JSON
+
+use super::agent::{AgentConfig, ToolConfig, WorkflowConfig};
+use super::driver::AgentDriverKind;
+use super::graph::{
+    GraphCondition, GraphDecisionBranch, GraphFlow, GraphLoopCondition, GraphNode, GraphOutput,
+    GraphParameter, GraphTransition, GraphVariable,
+};
+use crate::error::{AgentFlowError, Result};
+use crate::flow::{JoinTimeoutPolicy, TransformOp};
+use serde_json::Value;
+use std::collections::HashSet;
+
+impl AgentConfig {
+    pub fn new(name: impl Into<String>, driver: AgentDriverKind) -> Self {
+        Self {
+            name: name.into(),
+            driver,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.role = Some(role.into());
+        self
+    }
+
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_tool(mut self, tool: impl Into<String>) -> Self {
+        self.tools.push(tool.into());
+        self
+    }
+}
+
+impl ToolConfig {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+impl GraphDecisionBranch {
+    pub fn new(target: impl Into<String>, condition: Option<GraphCondition>) -> Self {
+        Self {
+            target: target.into(),
+            name: None,
+            condition,
+        }
+    }
+}
+
+/// `WorkflowConfig` 构建器，与 `FlowBuilder` 对应，但产出可由加载器使用的配置
+///
+/// ```rust,ignore
+/// let mut builder = WorkflowConfigBuilder::new("support");
+/// builder
+///     .add_agent(AgentConfig::new("writer", AgentDriverKind::Echo).with_prompt("write"))
+///     .add_agent_node("write", "writer")
+///     .add_terminal_node("done")
+///     .set_start("write")
+///     .connect("write", "done");
+/// let bundle = load_workflow_from_config(&builder.build()?)?;
+/// ```
+pub struct WorkflowConfigBuilder {
+    agents: Vec<AgentConfig>,
+    tools: Vec<ToolConfig>,
+    flow: GraphFlow,
+}
+
+impl WorkflowConfigBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            agents: Vec::new(),
+            tools: Vec::new(),
+            flow: GraphFlow {
+                name: name.into(),
+                version: 1,
+                start: String::new(),
+                parameters: Vec::new(),
+                variables: Vec::new(),
+                outputs: Vec::new(),
+                nodes: Vec::new(),
+                transitions: Vec::new(),
+                pii: None,
+                injection: None,
+                dead_letter: None,
+                on_error: None,
+                history: None,
+            },
+        }
+    }
+
+    pub fn add_agent(&mut self, agent: AgentConfig) -> &mut Self {
+        self.agents.push(agent);
+        self
+    }
+
+    pub fn add_tool(&mut self, tool: ToolConfig) -> &mut Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// 添加任意节点配置
+    pub fn add_node(&mut self, node: GraphNode) -> &mut Self {
+        self.flow.nodes.push(node);
+        self
+    }
+
+    pub fn add_agent_node(&mut self, name: &str, agent: &str) -> &mut Self {
+        self.add_node(GraphNode::Agent {
+            name: name.into(),
+            agent: agent.into(),
+        })
+    }
+
+    pub fn add_tool_node(
+        &mut self,
+        name: &str,
+        pipeline: &str,
+        params: Option<Value>,
+    ) -> &mut Self {
+        self.add_node(GraphNode::Tool {
+            name: name.into(),
+            pipeline: pipeline.into(),
+            params,
+            compensate: None,
+            parallel: Vec::new(),
+            aggregate: Default::default(),
+        })
+    }
+
+    pub fn add_decision_node(
+        &mut self,
+        name: &str,
+        policy: Option<&str>,
+        branches: Vec<GraphDecisionBranch>,
+    ) -> &mut Self {
+        self.add_node(GraphNode::Decision {
+            name: name.into(),
+            policy: policy.map(Into::into),
+            branches,
+        })
+    }
+
+    /// `strategy` 为 `all` / `any` / `count:N`
+    pub fn add_join_node(&mut self, name: &str, strategy: &str, inbound: &[&str]) -> &mut Self {
+        self.add_node(GraphNode::Join {
+            name: name.into(),
+            strategy: strategy.into(),
+            inbound: inbound.iter().map(|node| node.to_string()).collect(),
+            timeout_ms: None,
+            on_timeout: JoinTimeoutPolicy::default(),
+        })
+    }
+
+    pub fn add_loop_node(
+        &mut self,
+        name: &str,
+        entry: &str,
+        condition: Option<GraphLoopCondition>,
+        max_iterations: Option<u32>,
+        exit: Option<&str>,
+    ) -> &mut Self {
+        self.add_node(GraphNode::Loop {
+            name: name.into(),
+            entry: entry.into(),
+            condition,
+            max_iterations,
+            exit: exit.map(Into::into),
+        })
+    }
+
+    pub fn add_subflow_node(&mut self, name: &str, flow: &str) -> &mut Self {
+        self.add_node(GraphNode::SubFlow {
+            name: name.into(),
+            flow: flow.into(),
+            memoize: None,
+        })
+    }
+
+    pub fn add_transform_node(&mut self, name: &str, ops: Vec<TransformOp>) -> &mut Self {
+        self.add_node(GraphNode::Transform {
+            name: name.into(),
+            ops,
+        })
+    }
+
+    pub fn add_terminal_node(&mut self, name: &str) -> &mut Self {
+        self.add_node(GraphNode::Terminal { name: name.into() })
+    }
+
+    pub fn connect(&mut self, from: &str, to: &str) -> &mut Self {
+        self.push_transition(from, to, None)
+    }
+
+    pub fn connect_if(&mut self, from: &str, to: &str, condition: GraphCondition) -> &mut Self {
+        self.push_transition(from, to, Some(condition))
+    }
+
+    fn push_transition(
+        &mut self,
+        from: &str,
+        to: &str,
+        condition: Option<GraphCondition>,
+    ) -> &mut Self {
+        self.flow.transitions.push(GraphTransition {
+            from: from.into(),
+            to: to.into(),
+            name: None,
+            condition,
+            pipeline: false,
+        });
+        self
+    }
+
+    pub fn set_start(&mut self, name: &str) -> &mut Self {
+        self.flow.start = name.into();
+        self
+    }
+
+    pub fn set_version(&mut self, version: u32) -> &mut Self {
+        self.flow.version = version;
+        self
+    }
+
+    pub fn set_dead_letter(&mut self, name: &str) -> &mut Self {
+        self.flow.dead_letter = Some(name.into());
+        self
+    }
+
+    pub fn set_error_handler(&mut self, name: &str) -> &mut Self {
+        self.flow.on_error = Some(name.into());
+        self
+    }
+
+    pub fn with_parameter(&mut self, parameter: GraphParameter) -> &mut Self {
+        self.flow.parameters.push(parameter);
+        self
+    }
+
+    pub fn declare_variable(&mut self, variable: GraphVariable) -> &mut Self {
+        self.flow.variables.push(variable);
+        self
+    }
+
+    pub fn declare_output(&mut self, output: GraphOutput) -> &mut Self {
+        self.flow.outputs.push(output);
+        self
+    }
+
+    /// 检查起始节点、节点名唯一以及转换和 Agent 节点的引用，失败时返回 `InvalidConfig`
+    pub fn build(&self) -> Result<WorkflowConfig> {
+        let mut names = HashSet::new();
+        for (index, node) in self.flow.nodes.iter().enumerate() {
+            if !names.insert(node.name()) {
+                return Err(invalid(
+                    format!("flow.nodes[{}].name", index),
+                    format!("duplicate node `{}`", node.name()),
+                ));
+            }
+            if let GraphNode::Agent { agent, .. } = node {
+                if !self.agents.iter().any(|profile| &profile.name == agent) {
+                    return Err(invalid(
+                        format!("flow.nodes[{}].agent", index),
+                        format!("unknown agent `{}`", agent),
+                    ));
+                }
+            }
+        }
+        if !names.contains(self.flow.start.as_str()) {
+            return Err(invalid(
+                "flow.start".to_string(),
+                format!("unknown start node `{}`", self.flow.start),
+            ));
+        }
+        for (index, transition) in self.flow.transitions.iter().enumerate() {
+            for (field, node) in [("from", &transition.from), ("to", &transition.to)] {
+                if !names.contains(node.as_str()) {
+                    return Err(invalid(
+                        format!("flow.transitions[{}].{}", index, field),
+                        format!("unknown node `{}`", node),
+                    ));
+                }
+            }
+        }
+        Ok(WorkflowConfig {
+            agents: self.agents.clone(),
+            tools: self.tools.clone(),
+            flow: self.flow.clone(),
+        })
+    }
+}
+
+fn invalid(path: String, message: String) -> AgentFlowError {
+    AgentFlowError::InvalidConfig { path, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentMessage;
+    use crate::flow::loader::load_workflow_from_config;
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn support() -> WorkflowConfigBuilder {
+        let mut builder = WorkflowConfigBuilder::new("support");
+        builder
+            .add_agent(AgentConfig::new("writer", AgentDriverKind::Echo).with_prompt("write"))
+            .add_agent_node("write", "writer")
+            .add_decision_node(
+                "route",
+                None,
+                vec![
+                    GraphDecisionBranch::new(
+                        "done",
+                        Some(GraphCondition::StateExists { key: "skip".into() }),
+                    ),
+                    GraphDecisionBranch::new("write", None),
+                ],
+            )
+            .add_terminal_node("done")
+            .set_start("write")
+            .connect("write", "done");
+        builder
+    }
+
+    #[tokio::test]
+    async fn test_built_config_loads_and_runs() {
+        let config = support().build().unwrap();
+        assert_eq!(config.flow.nodes.len(), 3);
+
+        let bundle = load_workflow_from_config(&config).unwrap();
+        let executor = FlowExecutor::new(bundle.flow, bundle.agents, bundle.tools);
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let input = json!({ "raw": "hello", "steps": [] }).to_string();
+        let execution = executor
+            .start(ctx, AgentMessage::user(input))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "done");
+    }
+
+    #[test]
+    fn test_build_rejects_unknown_references() {
+        let mut builder = support();
+        builder.connect("done", "missing");
+        let err = builder.build().unwrap_err();
+        assert!(matches!(
+            err,
+            AgentFlowError::InvalidConfig { ref path, .. } if path == "flow.transitions[1].to"
+        ));
+
+        let mut builder = support();
+        builder.add_agent_node("review", "reviewer");
+        let err = builder.build().unwrap_err();
+        assert!(matches!(
+            err,
+            AgentFlowError::InvalidConfig { ref path, .. } if path == "flow.nodes[3].agent"
+        ));
+    }
+
+    #[test]
+    fn test_load_rejects_duplicate_tool_pipelines() {
+        let mut builder = support();
+        builder.add_node(GraphNode::Tool {
+            name: "gather".into(),
+            pipeline: "search".into(),
+            params: None,
+            compensate: None,
+            parallel: vec!["vectors".into(), "search".into()],
+            aggregate: Default::default(),
+        });
+        let config = builder.build().unwrap();
+        let Err(err) = load_workflow_from_config(&config) else {
+            panic!("expected duplicate pipelines to be rejected");
+        };
+        assert!(matches!(
+            err,
+            AgentFlowError::InvalidConfig { ref path, ref message }
+                if path == "flow.nodes[3].parallel[1]" && message.contains("`search`")
+        ));
+    }
+}
@@ -35,17 +35,21 @@
 /// - `mistral`: Mistral AI
 /// - `yi`: 零一万物
 /// - `generic`: 通用驱动（用于任意兼容OpenAI API的服务）
+/// - `openai_assistant`: OpenAI Assistants，驱动 `metadata.assistant_id` 指定的 Assistant，线程 ID 保存在会话状态中
+/// - `bedrock`: AWS Bedrock（需启用 `bedrock` feature），凭证从 `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY` 读取
+/// - `rag`: 知识库驱动，先按 `retrieval` 配置检索知识再调用 LLM（未配置 endpoint 时只检索）
 /// 
 /// # 添加新的Driver
 /// 
 /// 只需在enum中添加新变体，无需添加任何业务逻辑：
 /// 
-/// ```rust
+/// ```ignore
 /// #[cfg(feature = "openai-client")]
 /// MyNewLLM,
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum AgentDriverKind {
+    #[default]
     Echo,
     #[cfg(feature = "openai-client")]
     Qwen,
@@ -71,13 +75,13 @@ pub enum AgentDriverKind {
     Yi,
     #[cfg(feature = "openai-client")]
     Generic,
+    #[cfg(feature = "openai-client")]
+    OpenAiAssistant,
+    #[cfg(feature = "bedrock")]
+    Bedrock,
+    Rag,
 }
 
-impl Default for AgentDriverKind {
-    fn default() -> Self {
-        AgentDriverKind::Echo
-    }
-}
 
 impl AgentDriverKind {
     /// 获取driver的字符串标识
@@ -108,6 +112,11 @@ impl AgentDriverKind {
             AgentDriverKind::Yi => "yi",
             #[cfg(feature = "openai-client")]
             AgentDriverKind::Generic => "generic",
+            #[cfg(feature = "openai-client")]
+            AgentDriverKind::OpenAiAssistant => "openai_assistant",
+            #[cfg(feature = "bedrock")]
+            AgentDriverKind::Bedrock => "bedrock",
+            AgentDriverKind::Rag => "rag",
         }
     }
 
@@ -138,6 +147,10 @@ impl AgentDriverKind {
             AgentDriverKind::Mistral => Some("MISTRAL_API_KEY"),
             AgentDriverKind::Yi => Some("YI_API_KEY"),
             AgentDriverKind::Generic => None,
+            AgentDriverKind::OpenAiAssistant => Some("OPENAI_API_KEY"),
+            #[cfg(feature = "bedrock")]
+            AgentDriverKind::Bedrock => None,
+            AgentDriverKind::Rag => Some("OPENAI_API_KEY"),
         }
     }
 }
@@ -174,6 +187,11 @@ impl<'de> serde::Deserialize<'de> for AgentDriverKind {
             "yi" => Ok(AgentDriverKind::Yi),
             #[cfg(feature = "openai-client")]
             "generic" => Ok(AgentDriverKind::Generic),
+            #[cfg(feature = "openai-client")]
+            "openai_assistant" => Ok(AgentDriverKind::OpenAiAssistant),
+            #[cfg(feature = "bedrock")]
+            "bedrock" => Ok(AgentDriverKind::Bedrock),
+            "rag" => Ok(AgentDriverKind::Rag),
             _ => Err(serde::de::Error::custom(format!("unknown driver: {}", s))),
         }
     }
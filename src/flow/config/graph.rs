@@ -1,10 +1,14 @@
 use crate::flow::{
     condition_always, condition_state_absent, condition_state_equals, condition_state_exists,
-    condition_state_not_equals, loop_condition_always, FlowParameter, FlowParameterKind,
-    FlowVariable, LoopContinuation, TransitionCondition,
+    condition_state_not_equals, loop_condition_always, ConditionExpr, ConditionInfo, FlowOutput,
+    FlowOutputSource, FlowParameter, FlowParameterKind, FlowVariable, JoinTimeoutPolicy,
+    LoopContinuation, MemoizePolicy, TransformOp, TransitionCondition, WaitNode, WaitUntilState,
 };
+use crate::llm::ImageGenConfig;
 use crate::state::FlowScopeKind;
+use crate::utils::JsonPath;
 use serde::Deserialize;
+use serde_json::Value;
 
 /// Graph 工作流参数配置
 #[derive(Debug, Deserialize, Clone)]
@@ -16,6 +20,9 @@ pub struct GraphParameter {
     pub type_name: Option<String>,
     #[serde(default)]
     pub description: Option<String>,
+    /// 输入参数是否必填，默认为 true
+    #[serde(default = "GraphParameter::default_required")]
+    pub required: bool,
 }
 
 impl GraphParameter {
@@ -23,6 +30,10 @@ impl GraphParameter {
         "input".into()
     }
 
+    fn default_required() -> bool {
+        true
+    }
+
     pub fn into_flow_param(self) -> FlowParameter {
         let mut param = match self.kind.as_str() {
             "input" => FlowParameter::new(self.name.clone(), FlowParameterKind::Input),
@@ -36,10 +47,44 @@ impl GraphParameter {
         if let Some(desc) = self.description {
             param = param.with_description(desc);
         }
+        if !self.required {
+            param = param.optional();
+        }
         param
     }
 }
 
+/// Graph 工作流输出配置，`from_state` 与 `from_message_path` 二选一
+#[derive(Debug, Deserialize, Clone)]
+pub struct GraphOutput {
+    pub name: String,
+    #[serde(default)]
+    pub from_state: Option<String>,
+    #[serde(default)]
+    pub from_message_path: Option<JsonPath>,
+    /// Schema 名称或内联 Schema
+    #[serde(default)]
+    pub schema: Option<Value>,
+}
+
+impl GraphOutput {
+    pub fn into_flow_output(self) -> FlowOutput {
+        let source = match (self.from_state, self.from_message_path) {
+            (Some(key), _) => FlowOutputSource::State(key),
+            (None, Some(path)) => FlowOutputSource::MessagePath(path),
+            // 没有来源时取整条最终消息
+            (None, None) => {
+                FlowOutputSource::MessagePath(JsonPath::parse("$").expect("root JSONPath is valid"))
+            }
+        };
+        FlowOutput {
+            name: self.name,
+            source,
+            schema: self.schema,
+        }
+    }
+}
+
 /// Graph 工作流变量配置
 #[derive(Debug, Deserialize, Clone)]
 pub struct GraphVariable {
@@ -82,6 +127,9 @@ pub struct GraphTransition {
     pub name: Option<String>,
     #[serde(default)]
     pub condition: Option<GraphCondition>,
+    /// 流水线模式，仅当转移无条件且是 `from` 唯一的出边时生效
+    #[serde(default)]
+    pub pipeline: bool,
 }
 
 /// Graph 条件配置
@@ -93,6 +141,11 @@ pub enum GraphCondition {
     StateNotEquals { key: String, value: String },
     StateExists { key: String },
     StateAbsent { key: String },
+    /// 条件表达式，如 `route == "tools" && !done`
+    Expr { expr: ConditionExpr },
+    /// rhai 脚本，如 `state.score >= 0.8`
+    #[cfg(feature = "script")]
+    Script { script: crate::flow::ConditionScript },
 }
 
 impl GraphCondition {
@@ -107,6 +160,31 @@ impl GraphCondition {
             }
             GraphCondition::StateExists { key } => condition_state_exists(key.clone()),
             GraphCondition::StateAbsent { key } => condition_state_absent(key.clone()),
+            GraphCondition::Expr { expr } => expr.to_condition(),
+            #[cfg(feature = "script")]
+            GraphCondition::Script { script } => script.to_condition(),
+        }
+    }
+
+    /// 条件的可读描述
+    pub fn describe(&self) -> ConditionInfo {
+        match self {
+            GraphCondition::Always => ConditionInfo::new("always"),
+            GraphCondition::StateEquals { key, value } => {
+                ConditionInfo::new(format!("state `{}` == \"{}\"", key, value)).with_key(key)
+            }
+            GraphCondition::StateNotEquals { key, value } => {
+                ConditionInfo::new(format!("state `{}` != \"{}\"", key, value)).with_key(key)
+            }
+            GraphCondition::StateExists { key } => {
+                ConditionInfo::new(format!("state `{}` exists", key)).with_key(key)
+            }
+            GraphCondition::StateAbsent { key } => {
+                ConditionInfo::new(format!("state `{}` is absent", key)).with_key(key)
+            }
+            GraphCondition::Expr { expr } => expr.describe(),
+            #[cfg(feature = "script")]
+            GraphCondition::Script { script } => script.describe(),
         }
     }
 }
@@ -162,10 +240,49 @@ pub enum GraphNode {
         policy: Option<String>,
         branches: Vec<GraphDecisionBranch>,
     },
+    /// 由模型按分支说明分类路由
+    LlmDecision {
+        name: String,
+        /// 复用该 Agent 的 LLM 客户端
+        agent: String,
+        #[serde(default)]
+        prompt: Option<String>,
+        branches: Vec<GraphLlmBranch>,
+        /// 标签无法匹配或调用失败时进入的节点
+        fallback: String,
+    },
+    /// 按权重分流的 A/B 实验
+    Experiment {
+        name: String,
+        /// 实验名称，默认与节点名相同
+        #[serde(default)]
+        experiment: Option<String>,
+        variants: Vec<GraphExperimentVariant>,
+    },
     Join {
         name: String,
         strategy: String,
         inbound: Vec<String>,
+        /// 从收到第一条消息开始计时的超时（毫秒）
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+        /// 超时策略：`fail`（默认）、`proceed_with_partial` 或 `{"route_to": "<节点>"}`
+        #[serde(default)]
+        on_timeout: JoinTimeoutPolicy,
+    },
+    /// 对数组逐元素执行 `body` 并合并结果
+    Map {
+        name: String,
+        /// 输入消息中数组的 JSON Pointer
+        #[serde(default)]
+        over: String,
+        body: String,
+        /// 合并策略，语法同 Join 节点（`all` / `any` / `count:N`），默认 `all`
+        #[serde(default)]
+        join: Option<String>,
+        /// 同时执行的分支数，默认 4
+        #[serde(default)]
+        concurrency: Option<usize>,
     },
     Loop {
         name: String,
@@ -182,12 +299,191 @@ pub enum GraphNode {
         pipeline: String,
         #[serde(default)]
         params: Option<serde_json::Value>,
+        /// 运行失败时调用的补偿流水线
+        #[serde(default)]
+        compensate: Option<String>,
+        /// 与 `pipeline` 并发执行的其他流水线
+        #[serde(default)]
+        parallel: Vec<String>,
+        /// 合并策略：`all`（默认）、`all_settled` 或 `first`
+        #[serde(default)]
+        aggregate: crate::flow::ToolAggregate,
+    },
+    SubFlow {
+        name: String,
+        flow: String,
+        #[serde(default)]
+        memoize: Option<GraphMemoize>,
+    },
+    /// 方案-评审迭代，评审通过或达到最大轮数后继续
+    Debate {
+        name: String,
+        proposer: String,
+        critic: String,
+        /// 最多迭代轮数，默认 3
+        #[serde(default)]
+        max_rounds: Option<u32>,
+        /// 评审通过的关键字，默认 `approved`
+        #[serde(default)]
+        approval: Option<String>,
+    },
+    /// 参与者轮流发言的群聊
+    GroupChat {
+        name: String,
+        /// 参与发言的 Agent 名称
+        participants: Vec<String>,
+        /// 最多发言轮数，默认 10
+        #[serde(default)]
+        max_turns: Option<u32>,
+        /// 由该 Agent 的 LLM 客户端选择下一位发言人，未设置时按顺序轮流
+        #[serde(default)]
+        selector: Option<String>,
+        #[serde(default)]
+        prompt: Option<String>,
+        /// 发言包含该关键字时结束群聊
+        #[serde(default)]
+        terminate_on: Option<String>,
+    },
+    ImageGen {
+        name: String,
+        #[serde(flatten)]
+        config: ImageGenConfig,
+        /// 提示词模板，`{{input}}` 替换为输入消息内容
+        #[serde(default)]
+        prompt: Option<String>,
+        #[serde(default)]
+        negative_prompt: Option<String>,
+        #[serde(default)]
+        size: Option<String>,
+        #[serde(default)]
+        style: Option<String>,
+        #[serde(default)]
+        n: Option<u32>,
+    },
+    /// 等待固定时长（`duration_ms`），或轮询状态直到条件满足（`until_state`）
+    Wait {
+        name: String,
+        #[serde(flatten)]
+        wait: GraphWait,
+    },
+    /// 签发恢复令牌并等待 `FlowExecutor::deliver_event` 投递外部事件
+    AwaitEvent {
+        name: String,
+        #[serde(default)]
+        token_key: Option<String>,
+        #[serde(default)]
+        store_as: Option<String>,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+        #[serde(default)]
+        on_timeout: Option<String>,
+    },
+    /// 按声明的操作整理 JSON 载荷
+    Transform {
+        name: String,
+        ops: Vec<TransformOp>,
+    },
+    /// 以 rhai 脚本处理消息（需要 `script` feature）
+    #[cfg(feature = "script")]
+    Script {
+        name: String,
+        script: String,
     },
     Terminal {
         name: String,
     },
 }
 
+impl GraphNode {
+    pub fn name(&self) -> &str {
+        match self {
+            GraphNode::Agent { name, .. }
+            | GraphNode::Decision { name, .. }
+            | GraphNode::LlmDecision { name, .. }
+            | GraphNode::Experiment { name, .. }
+            | GraphNode::Join { name, .. }
+            | GraphNode::Map { name, .. }
+            | GraphNode::Loop { name, .. }
+            | GraphNode::Tool { name, .. }
+            | GraphNode::SubFlow { name, .. }
+            | GraphNode::Debate { name, .. }
+            | GraphNode::GroupChat { name, .. }
+            | GraphNode::ImageGen { name, .. }
+            | GraphNode::Transform { name, .. }
+            | GraphNode::Wait { name, .. }
+            | GraphNode::AwaitEvent { name, .. }
+            | GraphNode::Terminal { name } => name,
+            #[cfg(feature = "script")]
+            GraphNode::Script { name, .. } => name,
+        }
+    }
+}
+
+/// 等待节点配置
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphWait {
+    DurationMs(u64),
+    UntilState(GraphWaitState),
+}
+
+/// 轮询等待的状态条件
+#[derive(Debug, Deserialize, Clone)]
+pub struct GraphWaitState {
+    pub key: String,
+    /// 不设置时键存在即满足
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default = "GraphWaitState::default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// 超时后转到的节点，不设置时运行失败
+    #[serde(default)]
+    pub on_timeout: Option<String>,
+}
+
+impl GraphWaitState {
+    fn default_poll_interval_ms() -> u64 {
+        1000
+    }
+}
+
+impl GraphWait {
+    pub fn build(&self) -> WaitNode {
+        match self {
+            GraphWait::DurationMs(ms) => WaitNode::delay(std::time::Duration::from_millis(*ms)),
+            GraphWait::UntilState(state) => WaitNode::UntilState(WaitUntilState {
+                key: state.key.clone(),
+                value: state.value.clone(),
+                poll_interval: std::time::Duration::from_millis(state.poll_interval_ms),
+                timeout: state.timeout_ms.map(std::time::Duration::from_millis),
+                on_timeout: state.on_timeout.clone(),
+            }),
+        }
+    }
+}
+
+/// 子流程缓存配置
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct GraphMemoize {
+    /// 缓存有效期（秒），不设置则永不过期
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    /// 参与缓存键计算的状态键
+    #[serde(default)]
+    pub input_keys: Vec<String>,
+}
+
+impl GraphMemoize {
+    pub fn build(&self) -> MemoizePolicy {
+        MemoizePolicy {
+            ttl: self.ttl_secs.map(std::time::Duration::from_secs),
+            input_keys: self.input_keys.clone(),
+        }
+    }
+}
+
 /// Graph 决策分支配置
 #[derive(Debug, Deserialize, Clone)]
 pub struct GraphDecisionBranch {
@@ -198,17 +494,68 @@ pub struct GraphDecisionBranch {
     pub condition: Option<GraphCondition>,
 }
 
+/// LLM 决策分支配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct GraphLlmBranch {
+    pub label: String,
+    pub target: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// 实验变体配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct GraphExperimentVariant {
+    pub name: String,
+    pub target: String,
+    #[serde(default = "GraphExperimentVariant::default_weight")]
+    pub weight: u32,
+}
+
+impl GraphExperimentVariant {
+    fn default_weight() -> u32 {
+        1
+    }
+}
+
 /// Graph 工作流配置
 #[derive(Debug, Deserialize, Clone)]
 pub struct GraphFlow {
     pub name: String,
+    /// 图的版本号，默认为 1
+    #[serde(default = "GraphFlow::default_version")]
+    pub version: u32,
     pub start: String,
     #[serde(default)]
     pub parameters: Vec<GraphParameter>,
     #[serde(default)]
     pub variables: Vec<GraphVariable>,
+    /// 运行结束后提取的命名输出
+    #[serde(default)]
+    pub outputs: Vec<GraphOutput>,
     #[serde(default)]
     pub nodes: Vec<GraphNode>,
     #[serde(default)]
     pub transitions: Vec<GraphTransition>,
+    /// 写入历史、会话和运行记录前的 PII 脱敏规则
+    #[serde(default)]
+    pub pii: Option<crate::guardrails::PiiConfig>,
+    /// 起始用户消息的提示词注入检测
+    #[serde(default)]
+    pub injection: Option<crate::guardrails::InjectionConfig>,
+    /// Agent/Tool 节点失败时接收死信的节点
+    #[serde(default)]
+    pub dead_letter: Option<String>,
+    /// 任意节点失败时转到的错误处理节点
+    #[serde(default)]
+    pub on_error: Option<String>,
+    /// 消息历史保留策略
+    #[serde(default)]
+    pub history: Option<crate::state::HistoryRetention>,
+}
+
+impl GraphFlow {
+    fn default_version() -> u32 {
+        1
+    }
 }
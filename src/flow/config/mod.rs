@@ -1,13 +1,20 @@
 pub mod agent;
+pub mod builder;
 pub mod driver;
 pub mod graph;
+pub mod schema;
 
 pub use agent::{
     AgentConfig, AgentRulesConfig, FieldExtractionRules, ImageProcessingRules,
-    PayloadBuildingRules, PromptBuildingRules, RoutingRules, ToolConfig, WorkflowConfig,
+    InvalidOutputAction, InvalidOutputExhausted, InvalidOutputPolicy, OutputSchemaRef,
+    PayloadBuildingRules, PromptBuildingRules, RefusalAction, RefusalExhausted, RefusalPolicy,
+    RetrievalConfig, RoutingRules, ToolConfig, WorkflowConfig,
 };
+pub use builder::WorkflowConfigBuilder;
 pub use driver::AgentDriverKind;
+pub use schema::{validate_workflow_config, workflow_schema};
 pub use graph::{
-    GraphCondition, GraphDecisionBranch, GraphFlow, GraphLoopCondition, GraphNode, GraphParameter,
-    GraphTransition, GraphVariable,
+    GraphCondition, GraphDecisionBranch, GraphExperimentVariant, GraphFlow, GraphLlmBranch,
+    GraphLoopCondition, GraphMemoize, GraphNode, GraphOutput, GraphParameter, GraphTransition,
+    GraphVariable, GraphWait, GraphWaitState,
 };
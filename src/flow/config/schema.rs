@@ -0,0 +1,921 @@
+//! 工作流配置（`WorkflowConfig`）的 JSON Schema 与校验
+//!
+//! Schema 在代码中生成，`agentflow schema workflow` 可以导出给编辑器或 CI 使用；
+//! `load_workflow_from_value` 在反序列化前先按 Schema 校验，错误带精确路径，
+//! 如 `flow.nodes[3].branches[0].condition.type unknown `equal``。
+
+use std::sync::OnceLock;
+
+use serde_json::{json, Map, Value};
+
+use crate::error::{AgentFlowError, Result};
+
+/// 所有 Agent 驱动标识（包括需要 `openai-client` feature 的驱动）
+const DRIVERS: &[&str] = &[
+    "echo",
+    "qwen",
+    "moonshot",
+    "bigmodel",
+    "deepseek",
+    "openrouter",
+    "doubao",
+    "claude",
+    "chatgpt",
+    "gemini",
+    "mistral",
+    "yi",
+    "generic",
+    "openai_assistant",
+    "bedrock",
+    "rag",
+];
+
+/// 工作流配置的 JSON Schema（draft 2020-12）
+pub fn workflow_schema() -> &'static Value {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    SCHEMA.get_or_init(build_schema)
+}
+
+/// 按 Schema 校验工作流配置，失败时返回带路径的 `InvalidConfig`
+pub fn validate_workflow_config(value: &Value) -> Result<()> {
+    let schema = workflow_schema();
+    Validator { root: schema }
+        .check(schema, value, &mut String::new())
+        .map_err(|(path, message)| AgentFlowError::InvalidConfig {
+            path: if path.is_empty() {
+                "config".to_string()
+            } else {
+                path
+            },
+            message,
+        })
+}
+
+fn build_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": "https://github.com/joel-xiao/agent-flow/schemas/workflow.json",
+        "title": "WorkflowConfig",
+        "type": "object",
+        "properties": {
+            "agents": { "type": "array", "items": { "$ref": "#/$defs/agent" } },
+            "tools": { "type": "array", "items": { "$ref": "#/$defs/tool" } },
+            "flow": { "$ref": "#/$defs/flow" }
+        },
+        "required": ["flow"],
+        "additionalProperties": false,
+        "$defs": {
+            "agent": agent_schema(),
+            "tool": object(
+                json!({
+                    "name": string(),
+                    "driver": { "enum": ["echo"] },
+                    "description": nullable("string")
+                }),
+                &["name"],
+                false,
+            ),
+            "flow": flow_schema(),
+            "node": node_schema(),
+            "transition": object(
+                json!({
+                    "from": string(),
+                    "to": string(),
+                    "name": nullable("string"),
+                    "condition": nullable_ref("condition"),
+                    "pipeline": { "type": "boolean" }
+                }),
+                &["from", "to"],
+                false,
+            ),
+            "condition": tagged(
+                "type",
+                vec![
+                    ("always", json!({}), &[]),
+                    ("state_equals", json!({ "key": string(), "value": string() }), &["key", "value"]),
+                    ("state_not_equals", json!({ "key": string(), "value": string() }), &["key", "value"]),
+                    ("state_exists", json!({ "key": string() }), &["key"]),
+                    ("state_absent", json!({ "key": string() }), &["key"]),
+                    ("expr", json!({ "expr": string() }), &["expr"]),
+                    // 需要 `script` feature
+                    ("script", json!({ "script": string() }), &["script"]),
+                ],
+                false,
+            ),
+            "invalid_output_policy": object(
+                json!({
+                    "steps": {
+                        "type": "array",
+                        "items": tagged(
+                            "action",
+                            vec![
+                                ("retry_with_feedback", json!({ "feedback_template": nullable("string") }), &[]),
+                                ("backup_prompt", json!({ "prompt": string() }), &["prompt"]),
+                                ("escalate_model", escalation(), &["model"]),
+                                ("route_to_human", json!({ "target": string() }), &["target"]),
+                            ],
+                            false,
+                        )
+                    },
+                    "on_exhausted": { "enum": ["fail", "accept"] }
+                }),
+                &[],
+                false,
+            ),
+            "refusal_policy": object(
+                json!({
+                    "steps": {
+                        "type": "array",
+                        "items": tagged(
+                            "action",
+                            vec![
+                                ("soften_prompt", json!({ "prompt": nullable("string") }), &[]),
+                                ("switch_provider", escalation(), &["model"]),
+                                ("route_to_human", json!({ "target": string() }), &["target"]),
+                            ],
+                            false,
+                        )
+                    },
+                    "on_exhausted": { "enum": ["surface", "fail"] }
+                }),
+                &[],
+                false,
+            )
+        }
+    })
+}
+
+/// Agent 配置；图配置（`GraphConfig`）转换而来的 Agent 带有额外字段，因此不限制未知字段
+fn agent_schema() -> Value {
+    object(
+        json!({
+            "name": string(),
+            "driver": { "enum": DRIVERS },
+            "role": nullable("string"),
+            "prompt": nullable("string"),
+            "model": nullable("string"),
+            "endpoint": nullable("string"),
+            "api_key": nullable("string"),
+            "intent": nullable("string"),
+            "tools": { "type": "array", "items": string() },
+            "metadata": {},
+            "route_mode": nullable("string"),
+            "route_targets": { "type": ["array", "null"], "items": string() },
+            "route_prompt": nullable("string"),
+            "default_route": nullable("string"),
+            "temperature": nullable("number"),
+            "top_p": nullable("number"),
+            "max_tokens": nullable_uint(),
+            "frequency_penalty": nullable("number"),
+            "presence_penalty": nullable("number"),
+            "stop": { "type": "array", "items": string() },
+            "seed": nullable_uint(),
+            "rules": nullable("object"),
+            "output_schema": { "type": ["string", "object", "null"] },
+            "on_invalid_output": nullable_ref("invalid_output_policy"),
+            "on_refusal": nullable_ref("refusal_policy"),
+            "retrieval": nullable("object"),
+            "guardrails": nullable("object"),
+            "decorators": { "type": "array", "items": { "type": ["string", "object"] } }
+        }),
+        &["name"],
+        true,
+    )
+}
+
+fn flow_schema() -> Value {
+    object(
+        json!({
+            "name": string(),
+            "version": uint(),
+            "start": string(),
+            "parameters": {
+                "type": "array",
+                "items": object(
+                    json!({
+                        "name": string(),
+                        "kind": { "enum": ["input", "output", "inout"] },
+                        "type_name": nullable("string"),
+                        "description": nullable("string"),
+                        "required": { "type": "boolean" }
+                    }),
+                    &["name"],
+                    false,
+                )
+            },
+            "outputs": {
+                "type": "array",
+                "items": object(
+                    json!({
+                        "name": string(),
+                        "from_state": nullable("string"),
+                        "from_message_path": nullable("string"),
+                        "schema": { "type": ["string", "object", "null"] }
+                    }),
+                    &["name"],
+                    false,
+                )
+            },
+            "variables": {
+                "type": "array",
+                "items": object(
+                    json!({
+                        "name": string(),
+                        "scope": string(),
+                        "default": nullable("string"),
+                        "description": nullable("string")
+                    }),
+                    &["name"],
+                    false,
+                )
+            },
+            "nodes": { "type": "array", "items": { "$ref": "#/$defs/node" } },
+            "transitions": { "type": "array", "items": { "$ref": "#/$defs/transition" } },
+            "pii": nullable("object"),
+            "injection": nullable("object"),
+            "dead_letter": nullable("string"),
+            "on_error": nullable("string"),
+            "history": nullable("object")
+        }),
+        &["name", "start"],
+        false,
+    )
+}
+
+fn node_schema() -> Value {
+    tagged(
+        "kind",
+        vec![
+            (
+                "agent",
+                json!({ "name": string(), "agent": string() }),
+                &["name", "agent"],
+            ),
+            (
+                "decision",
+                json!({
+                    "name": string(),
+                    "policy": { "enum": ["first_match", "all_matches", null] },
+                    "branches": {
+                        "type": "array",
+                        "items": object(
+                            json!({
+                                "target": string(),
+                                "name": nullable("string"),
+                                "condition": nullable_ref("condition")
+                            }),
+                            &["target"],
+                            false,
+                        )
+                    }
+                }),
+                &["name", "branches"],
+            ),
+            (
+                "llm_decision",
+                json!({
+                    "name": string(),
+                    "agent": string(),
+                    "prompt": nullable("string"),
+                    "branches": {
+                        "type": "array",
+                        "items": object(
+                            json!({
+                                "label": string(),
+                                "target": string(),
+                                "description": nullable("string")
+                            }),
+                            &["label", "target"],
+                            false,
+                        )
+                    },
+                    "fallback": string()
+                }),
+                &["name", "agent", "branches", "fallback"],
+            ),
+            (
+                "experiment",
+                json!({
+                    "name": string(),
+                    "experiment": nullable("string"),
+                    "variants": {
+                        "type": "array",
+                        "items": object(
+                            json!({ "name": string(), "target": string(), "weight": uint() }),
+                            &["name", "target"],
+                            false,
+                        )
+                    }
+                }),
+                &["name", "variants"],
+            ),
+            (
+                "join",
+                json!({
+                    "name": string(),
+                    "strategy": string(),
+                    "inbound": { "type": "array", "items": string() },
+                    "timeout_ms": nullable_uint(),
+                    "on_timeout": {
+                        "oneOf": [
+                            { "enum": ["fail", "proceed_with_partial"] },
+                            object(json!({ "route_to": string() }), &["route_to"], false)
+                        ]
+                    }
+                }),
+                &["name", "strategy", "inbound"],
+            ),
+            (
+                "map",
+                json!({
+                    "name": string(),
+                    "over": string(),
+                    "body": string(),
+                    "join": nullable("string"),
+                    "concurrency": nullable_uint()
+                }),
+                &["name", "body"],
+            ),
+            (
+                "loop",
+                json!({
+                    "name": string(),
+                    "entry": string(),
+                    // 图配置转换时会带入转换条件的字段，因此不限制未知字段
+                    "condition": {
+                        "type": ["object", "null"],
+                        "properties": {
+                            "state_equals": nullable_object(
+                                json!({ "key": string(), "value": string() }),
+                                &["key", "value"],
+                            )
+                        }
+                    },
+                    "max_iterations": nullable_uint(),
+                    "exit": nullable("string")
+                }),
+                &["name", "entry"],
+            ),
+            (
+                "tool",
+                json!({
+                    "name": string(),
+                    "pipeline": string(),
+                    "params": {},
+                    "compensate": nullable("string"),
+                    "parallel": { "type": "array", "items": string() },
+                    "aggregate": { "enum": ["all", "all_settled", "first"] }
+                }),
+                &["name", "pipeline"],
+            ),
+            (
+                "sub_flow",
+                json!({
+                    "name": string(),
+                    "flow": string(),
+                    "memoize": nullable_object(
+                        json!({
+                            "ttl_secs": nullable_uint(),
+                            "input_keys": { "type": "array", "items": string() }
+                        }),
+                        &[],
+                    )
+                }),
+                &["name", "flow"],
+            ),
+            (
+                "debate",
+                json!({
+                    "name": string(),
+                    "proposer": string(),
+                    "critic": string(),
+                    "max_rounds": nullable_uint(),
+                    "approval": nullable("string")
+                }),
+                &["name", "proposer", "critic"],
+            ),
+            (
+                "group_chat",
+                json!({
+                    "name": string(),
+                    "participants": { "type": "array", "items": string() },
+                    "max_turns": nullable_uint(),
+                    "selector": nullable("string"),
+                    "prompt": nullable("string"),
+                    "terminate_on": nullable("string")
+                }),
+                &["name", "participants"],
+            ),
+            (
+                "image_gen",
+                json!({
+                    "name": string(),
+                    "model": string(),
+                    "api_key": nullable("string"),
+                    "endpoint": nullable("string"),
+                    "task_endpoint": nullable("string"),
+                    "poll_interval_ms": uint(),
+                    "max_wait_secs": uint(),
+                    "prompt": nullable("string"),
+                    "negative_prompt": nullable("string"),
+                    "size": nullable("string"),
+                    "style": nullable("string"),
+                    "n": nullable_uint()
+                }),
+                &["name", "model"],
+            ),
+            (
+                "wait",
+                json!({
+                    "name": string(),
+                    "duration_ms": uint(),
+                    "until_state": object(
+                        json!({
+                            "key": string(),
+                            "value": nullable("string"),
+                            "poll_interval_ms": uint(),
+                            "timeout_ms": nullable_uint(),
+                            "on_timeout": nullable("string")
+                        }),
+                        &["key"],
+                        false,
+                    )
+                }),
+                &["name"],
+            ),
+            (
+                "await_event",
+                json!({
+                    "name": string(),
+                    "token_key": nullable("string"),
+                    "store_as": nullable("string"),
+                    "timeout_ms": nullable_uint(),
+                    "on_timeout": nullable("string")
+                }),
+                &["name"],
+            ),
+            (
+                "transform",
+                json!({
+                    "name": string(),
+                    "ops": { "type": "array", "items": transform_op() }
+                }),
+                &["name", "ops"],
+            ),
+            // 需要 `script` feature
+            (
+                "script",
+                json!({ "name": string(), "script": string() }),
+                &["name", "script"],
+            ),
+            ("terminal", json!({ "name": string() }), &["name"]),
+        ],
+        false,
+    )
+}
+
+/// 升级模型 / 切换提供商的字段
+/// 对应 `TransformOp`
+fn transform_op() -> Value {
+    let moved = || json!({ "from": string(), "to": string() });
+    tagged(
+        "op",
+        vec![
+            (
+                "query",
+                json!({ "expr": string(), "to": nullable("string") }),
+                &["expr"],
+            ),
+            ("get", json!({ "path": string() }), &["path"]),
+            (
+                "set",
+                json!({ "path": string(), "value": {} }),
+                &["path", "value"],
+            ),
+            ("copy", moved(), &["from", "to"]),
+            ("rename", moved(), &["from", "to"]),
+            ("remove", json!({ "path": string() }), &["path"]),
+            (
+                "merge",
+                json!({ "path": string(), "value": { "type": "object" } }),
+                &["value"],
+            ),
+            (
+                "pick",
+                json!({ "path": string(), "keys": { "type": "array", "items": string() } }),
+                &["keys"],
+            ),
+            (
+                "map",
+                json!({ "path": string(), "expr": string() }),
+                &["path", "expr"],
+            ),
+            (
+                "filter",
+                json!({ "path": string(), "expr": string(), "equals": {} }),
+                &["path", "expr"],
+            ),
+            ("flatten", json!({ "path": string() }), &["path"]),
+            (
+                "slice",
+                json!({ "path": string(), "start": uint(), "end": nullable_uint() }),
+                &["path"],
+            ),
+        ],
+        false,
+    )
+}
+
+fn escalation() -> Value {
+    json!({
+        "model": string(),
+        "driver": { "enum": DRIVERS.iter().map(|driver| Value::from(*driver)).chain([Value::Null]).collect::<Vec<_>>() },
+        "endpoint": nullable("string"),
+        "api_key": nullable("string")
+    })
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn uint() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+fn nullable(kind: &str) -> Value {
+    json!({ "type": [kind, "null"] })
+}
+
+fn nullable_uint() -> Value {
+    json!({ "type": ["integer", "null"], "minimum": 0 })
+}
+
+fn nullable_ref(name: &str) -> Value {
+    json!({ "oneOf": [{ "type": "null" }, { "$ref": format!("#/$defs/{name}") }] })
+}
+
+fn object(properties: Value, required: &[&str], additional: bool) -> Value {
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": additional
+    })
+}
+
+fn nullable_object(properties: Value, required: &[&str]) -> Value {
+    let mut schema = object(properties, required, false);
+    schema["type"] = json!(["object", "null"]);
+    schema
+}
+
+/// 按 `tag` 字段区分的对象（对应 serde 的 `#[serde(tag = "...")]`）
+fn tagged(tag: &str, variants: Vec<(&str, Value, &[&str])>, additional: bool) -> Value {
+    let variants: Vec<Value> = variants
+        .into_iter()
+        .map(|(name, mut properties, required)| {
+            properties[tag] = json!({ "const": name });
+            let mut required = required.to_vec();
+            required.insert(0, tag);
+            object(properties, &required, additional)
+        })
+        .collect();
+    json!({
+        "type": "object",
+        "discriminator": { "propertyName": tag },
+        "oneOf": variants
+    })
+}
+
+/// 只支持 Schema 中用到的关键字：`$ref`、`type`、`enum`、`const`、`minimum`、
+/// `properties`、`required`、`additionalProperties`、`items` 和 `oneOf`（含 `discriminator`）
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+type Failure = (String, String);
+
+impl Validator<'_> {
+    fn check(
+        &self,
+        schema: &Value,
+        value: &Value,
+        path: &mut String,
+    ) -> std::result::Result<(), Failure> {
+        let fail = |path: &str, message: String| Err((path.to_string(), message));
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference.trim_start_matches("#/$defs/");
+            return self.check(&self.root["$defs"][name], value, path);
+        }
+        if let Some(kinds) = schema.get("type") {
+            let kinds: Vec<&str> = match kinds {
+                Value::Array(kinds) => kinds.iter().filter_map(Value::as_str).collect(),
+                kind => kind.as_str().into_iter().collect(),
+            };
+            if !kinds.iter().any(|kind| type_matches(kind, value)) {
+                return fail(
+                    path,
+                    format!("expected {}, got {}", kinds.join(" or "), type_name(value)),
+                );
+            }
+        }
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            if !options.contains(value) {
+                return fail(path, unknown(value, options));
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                return fail(path, format!("expected {expected}"));
+            }
+        }
+        if let (Some(minimum), Some(number)) = (
+            schema.get("minimum").and_then(Value::as_f64),
+            value.as_f64(),
+        ) {
+            if number < minimum {
+                return fail(path, format!("must be at least {minimum}"));
+            }
+        }
+
+        if let Value::Object(object) = value {
+            self.check_object(schema, object, path)?;
+        }
+        if let (Some(items), Value::Array(array)) = (schema.get("items"), value) {
+            for (index, item) in array.iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{index}]"));
+                self.check(items, item, path)?;
+                path.truncate(len);
+            }
+        }
+        if let Some(variants) = schema.get("oneOf").and_then(Value::as_array) {
+            return self.check_one_of(schema, variants, value, path);
+        }
+        Ok(())
+    }
+
+    fn check_object(
+        &self,
+        schema: &Value,
+        object: &Map<String, Value>,
+        path: &mut String,
+    ) -> std::result::Result<(), Failure> {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for key in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(key) {
+                return Err((child(path, key), "is required".to_string()));
+            }
+        }
+        for (key, field) in object {
+            let len = path.len();
+            *path = child(path, key);
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(property) => self.check(property, field, path)?,
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    return Err((path.clone(), "is not allowed".to_string()));
+                }
+                None => {}
+            }
+            path.truncate(len);
+        }
+        Ok(())
+    }
+
+    fn check_one_of(
+        &self,
+        schema: &Value,
+        variants: &[Value],
+        value: &Value,
+        path: &mut String,
+    ) -> std::result::Result<(), Failure> {
+        if let Some(tag) = schema
+            .pointer("/discriminator/propertyName")
+            .and_then(Value::as_str)
+        {
+            let actual = &value[tag];
+            let variant = variants
+                .iter()
+                .find(|variant| &variant["properties"][tag]["const"] == actual);
+            return match variant {
+                Some(variant) => self.check(variant, value, path),
+                None => {
+                    let options: Vec<Value> = variants
+                        .iter()
+                        .map(|variant| variant["properties"][tag]["const"].clone())
+                        .collect();
+                    Err((child(path, tag), unknown(actual, &options)))
+                }
+            };
+        }
+
+        // 没有区分字段时取第一个匹配的分支；都不匹配时只报告类型相符的分支中最深的错误
+        let candidates: Vec<&Value> = variants
+            .iter()
+            .filter(|variant| {
+                self.declared_types(variant)
+                    .is_none_or(|kinds| kinds.iter().any(|kind| type_matches(kind, value)))
+            })
+            .collect();
+        if candidates.is_empty() {
+            let kinds: Vec<&str> = variants
+                .iter()
+                .filter_map(|variant| self.declared_types(variant))
+                .flatten()
+                .collect();
+            return Err((
+                path.clone(),
+                format!("expected {}, got {}", kinds.join(" or "), type_name(value)),
+            ));
+        }
+        let mut deepest: Option<Failure> = None;
+        for variant in candidates {
+            let mut attempt = path.clone();
+            match self.check(variant, value, &mut attempt) {
+                Ok(()) => return Ok(()),
+                Err(failure) => {
+                    if deepest
+                        .as_ref()
+                        .is_none_or(|(deepest, _)| failure.0.len() > deepest.len())
+                    {
+                        deepest = Some(failure);
+                    }
+                }
+            }
+        }
+        deepest.map_or(Ok(()), Err)
+    }
+
+    /// 分支声明的类型，未声明时为 None
+    fn declared_types<'s>(&'s self, schema: &'s Value) -> Option<Vec<&'s str>> {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference.trim_start_matches("#/$defs/");
+            return self.declared_types(&self.root["$defs"][name]);
+        }
+        match schema.get("type")? {
+            Value::Array(kinds) => Some(kinds.iter().filter_map(Value::as_str).collect()),
+            kind => Some(kind.as_str().into_iter().collect()),
+        }
+    }
+}
+
+fn child(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn unknown(value: &Value, options: &[Value]) -> String {
+    let value = match value {
+        Value::String(text) => text.clone(),
+        Value::Null => "null".to_string(),
+        other => other.to_string(),
+    };
+    let options: Vec<String> = options
+        .iter()
+        .filter_map(|option| option.as_str().map(str::to_string))
+        .collect();
+    format!("unknown `{value}`, expected one of: {}", options.join(", "))
+}
+
+fn type_matches(kind: &str, value: &Value) -> bool {
+    match kind {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(config: Value) -> String {
+        validate_workflow_config(&config).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_workflow_schema_reports_paths() {
+        let config = json!({
+            "agents": [{ "name": "writer", "driver": "echo", "service": "legacy" }],
+            "flow": {
+                "name": "review",
+                "start": "route",
+                "nodes": [
+                    { "kind": "agent", "name": "draft", "agent": "writer" },
+                    {
+                        "kind": "decision",
+                        "name": "route",
+                        "branches": [
+                            { "target": "draft", "condition": { "type": "state_equals", "key": "k", "value": "v" } }
+                        ]
+                    },
+                    { "kind": "join", "name": "merge", "strategy": "all", "inbound": ["draft"], "on_timeout": { "route_to": "done" } },
+                    { "kind": "terminal", "name": "done" }
+                ],
+                "transitions": [{ "from": "draft", "to": "done", "condition": null }]
+            }
+        });
+        validate_workflow_config(&config).unwrap();
+
+        let mut broken = config.clone();
+        broken["flow"]["nodes"][1]["branches"][0]["condition"]["type"] = json!("equal");
+        assert!(error(broken).starts_with(
+            "flow.nodes[1].branches[0].condition.type unknown `equal`, expected one of: always"
+        ));
+
+        let mut broken = config.clone();
+        broken["flow"]["nodes"][3]["kind"] = json!("finish");
+        assert!(error(broken).starts_with("flow.nodes[3].kind unknown `finish`"));
+
+        let mut broken = config.clone();
+        broken["flow"]["nodes"][2]["on_timeout"] = json!({ "route": "done" });
+        assert_eq!(
+            error(broken),
+            "flow.nodes[2].on_timeout.route_to is required"
+        );
+
+        let mut broken = config.clone();
+        broken["flow"]["nodes"][0]["agnet"] = json!("writer");
+        assert_eq!(error(broken), "flow.nodes[0].agnet is not allowed");
+
+        let mut broken = config.clone();
+        broken["flow"]["version"] = json!("2");
+        assert_eq!(error(broken), "flow.version expected integer, got string");
+
+        let mut broken = config.clone();
+        broken["flow"]["transitions"][0]["condition"] = json!("always");
+        assert_eq!(
+            error(broken),
+            "flow.transitions[0].condition expected null or object, got string"
+        );
+
+        let mut broken = config.clone();
+        broken["agents"][0]["max_tokens"] = json!(-1);
+        assert!(error(broken).starts_with("agents[0].max_tokens"));
+
+        let mut tuned = config.clone();
+        tuned["agents"][0]["top_p"] = json!(0.9);
+        tuned["agents"][0]["max_tokens"] = json!(4096);
+        tuned["agents"][0]["stop"] = json!(["END"]);
+        tuned["agents"][0]["seed"] = json!(42);
+        validate_workflow_config(&tuned).unwrap();
+        let agent: crate::flow::config::AgentConfig =
+            serde_json::from_value(tuned["agents"][0].clone()).unwrap();
+        assert_eq!(agent.params.max_tokens, Some(4096));
+        assert_eq!(agent.params.seed, Some(42));
+        assert_eq!(agent.params.stop, ["END"]);
+
+        let wait = json!({
+            "kind": "wait",
+            "name": "approval",
+            "until_state": { "key": "approved", "value": "yes", "timeout_ms": 60000, "on_timeout": "done" }
+        });
+        let mut waiting = config.clone();
+        waiting["flow"]["nodes"]
+            .as_array_mut()
+            .unwrap()
+            .push(wait.clone());
+        validate_workflow_config(&waiting).unwrap();
+        let node: crate::flow::config::GraphNode = serde_json::from_value(wait).unwrap();
+        let crate::flow::config::GraphNode::Wait { wait, .. } = node else {
+            panic!("expected wait node");
+        };
+        let crate::flow::WaitNode::UntilState(until) = wait.build() else {
+            panic!("expected state wait");
+        };
+        assert_eq!(until.poll_interval, std::time::Duration::from_secs(1));
+        assert_eq!(until.on_timeout.as_deref(), Some("done"));
+
+        let mut broken = config;
+        broken["flow"].as_object_mut().unwrap().remove("start");
+        assert_eq!(error(broken), "flow.start is required");
+    }
+}
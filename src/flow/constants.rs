@@ -1,6 +1,6 @@
-/// 流程相关的常量定义
-///
-/// 统一管理所有硬编码的字符串常量、魔法值等
+//! 流程相关的常量定义
+//!
+//! 统一管理所有硬编码的字符串常量、魔法值等
 
 /// Payload 字段名常量
 pub mod fields {
@@ -17,6 +17,9 @@ pub mod fields {
     pub const INTENT: &str = "intent";
     pub const DRIVER: &str = "driver";
     pub const AGENT: &str = "agent";
+    pub const VALIDATION_ERROR: &str = "validation_error";
+    pub const REFUSAL: &str = "refusal";
+    pub const SOURCES: &str = "sources";
 
     // 路由相关字段
     pub const ROUTE: &str = "route";
@@ -29,9 +32,12 @@ pub mod fields {
     pub const DEFAULT: &str = "default";
     pub const BRANCHES: &str = "branches";
 
-    // 图像相关字段
+    // 图像相关字段（crate 内未使用，供 `unstable` 使用方构建 payload）
+    #[cfg_attr(not(feature = "unstable"), allow(dead_code))]
     pub const IMAGE_URL: &str = "image_url";
+    #[cfg_attr(not(feature = "unstable"), allow(dead_code))]
     pub const IMAGE_BASE64: &str = "image_base64";
+    #[cfg_attr(not(feature = "unstable"), allow(dead_code))]
     pub const IMAGE_PATH: &str = "image_path";
 }
 
@@ -50,7 +56,8 @@ pub mod routing {
     /// 自动路由模式
     pub const MODE_AUTO: &str = "auto";
 
-    /// 手动路由模式
+    /// 手动路由模式（未设置 `route_mode` 时的默认行为，crate 内不需要比较）
+    #[cfg_attr(not(feature = "unstable"), allow(dead_code))]
     pub const MODE_MANUAL: &str = "manual";
 
     /// 路由目标分割时跳过的前缀/后缀
@@ -84,4 +91,13 @@ pub mod prompt {
 
     pub const DEFAULT_ROUTE_REASON: &str = "No matching route found, using default";
     pub const EXTRACTED_ROUTE_REASON: &str = "Extracted from response text";
+
+    pub const INVALID_OUTPUT_FEEDBACK: &str =
+        "\n\nYour previous response was rejected because it failed validation: {error}\nPrevious response:\n{output}\nRespond again and make sure the output satisfies the required format.";
+
+    pub const KNOWLEDGE_CONTEXT_HEADER: &str =
+        "\n\nAnswer using the knowledge below. Cite the supporting passages with their markers, e.g. [1]. If the knowledge does not cover the question, say so.\n\nKnowledge:\n";
+
+    pub const REFUSAL_SOFTEN_INSTRUCTION: &str =
+        "\n\nKeep the answer neutral, factual and within safe-use guidelines. If part of the request cannot be answered, answer the remaining parts and briefly note what was omitted.";
 }
@@ -0,0 +1,385 @@
+use crate::error::{AgentFlowError, Result};
+use crate::flow::conditions::{ConditionInfo, TransitionCondition};
+use anyhow::anyhow;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// 表达式语法树
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    /// 状态键，不存在时为 `null`
+    Key(String),
+    Literal(Option<String>),
+    Not(Box<Expr>),
+    Eq(Box<Expr>, Box<Expr>),
+    Ne(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// 转换条件表达式
+///
+/// 标识符读取同名状态键，字面量支持字符串、数字、`true` / `false` 和 `null`，
+/// 运算符支持 `==`、`!=`、`!`、`&&`、`||` 和括号。状态值按字符串比较，
+/// 单独的值非空且不为 `"false"` 时为真。
+///
+/// ```text
+/// route == "tools" && !done
+/// status != "failed" || retries == 0
+/// ```
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ConditionExpr {
+    source: String,
+    expr: Expr,
+}
+
+impl ConditionExpr {
+    /// 解析表达式
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source).map_err(|reason| invalid(source, &reason))?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or().map_err(|reason| invalid(source, &reason))?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(invalid(source, &format!("unexpected `{}`", token)));
+        }
+        Ok(Self {
+            source: source.trim().to_string(),
+            expr,
+        })
+    }
+
+    /// 表达式原文
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// 表达式读取的状态键
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys = Vec::new();
+        collect_keys(&self.expr, &mut keys);
+        keys
+    }
+
+    /// 按给定的状态值求值
+    pub fn evaluate(&self, state: &HashMap<String, String>) -> bool {
+        truthy(&self.expr, state)
+    }
+
+    /// 可读描述
+    pub fn describe(&self) -> ConditionInfo {
+        self.keys()
+            .into_iter()
+            .fold(ConditionInfo::new(&self.source), ConditionInfo::with_key)
+    }
+
+    /// 转换为流程转换条件，求值前读取表达式用到的状态键
+    pub fn to_condition(&self) -> TransitionCondition {
+        let expr = Arc::new(self.clone());
+        Arc::new(move |ctx| {
+            let store = ctx.store();
+            let expr = Arc::clone(&expr);
+            Box::pin(async move {
+                let mut state = HashMap::new();
+                for key in expr.keys() {
+                    if let Ok(Some(value)) = store.get(&key).await {
+                        state.insert(key, value);
+                    }
+                }
+                expr.evaluate(&state)
+            })
+        })
+    }
+}
+
+impl fmt::Debug for ConditionExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ConditionExpr").field(&self.source).finish()
+    }
+}
+
+impl fmt::Display for ConditionExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for ConditionExpr {
+    type Err = AgentFlowError;
+
+    fn from_str(source: &str) -> Result<Self> {
+        Self::parse(source)
+    }
+}
+
+impl TryFrom<String> for ConditionExpr {
+    type Error = AgentFlowError;
+
+    fn try_from(source: String) -> Result<Self> {
+        Self::parse(&source)
+    }
+}
+
+/// 从表达式创建转换条件
+pub fn condition_expr(source: &str) -> Result<TransitionCondition> {
+    Ok(ConditionExpr::parse(source)?.to_condition())
+}
+
+fn invalid(source: &str, reason: &str) -> AgentFlowError {
+    AgentFlowError::Other(anyhow!(
+        "Invalid condition expression `{}`: {}",
+        source,
+        reason
+    ))
+}
+
+fn collect_keys(expr: &Expr, keys: &mut Vec<String>) {
+    match expr {
+        Expr::Key(key) if !keys.contains(key) => keys.push(key.clone()),
+        Expr::Key(_) | Expr::Literal(_) => {}
+        Expr::Not(inner) => collect_keys(inner, keys),
+        Expr::Eq(left, right)
+        | Expr::Ne(left, right)
+        | Expr::And(left, right)
+        | Expr::Or(left, right) => {
+            collect_keys(left, keys);
+            collect_keys(right, keys);
+        }
+    }
+}
+
+fn value(expr: &Expr, state: &HashMap<String, String>) -> Option<String> {
+    match expr {
+        Expr::Key(key) => state.get(key).cloned(),
+        Expr::Literal(literal) => literal.clone(),
+        other => Some(truthy(other, state).to_string()),
+    }
+}
+
+fn truthy(expr: &Expr, state: &HashMap<String, String>) -> bool {
+    match expr {
+        Expr::Key(_) | Expr::Literal(_) => {
+            value(expr, state).is_some_and(|value| !value.is_empty() && value != "false")
+        }
+        Expr::Not(inner) => !truthy(inner, state),
+        Expr::Eq(left, right) => value(left, state) == value(right, state),
+        Expr::Ne(left, right) => value(left, state) != value(right, state),
+        Expr::And(left, right) => truthy(left, state) && truthy(right, state),
+        Expr::Or(left, right) => truthy(left, state) || truthy(right, state),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(String),
+    Op(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(text) | Token::Number(text) => f.write_str(text),
+            Token::Str(text) => write!(f, "\"{}\"", text),
+            Token::Op(op) => f.write_str(op),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> std::result::Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if let Some(op) = ["==", "!=", "&&", "||"]
+            .into_iter()
+            .find(|op| source[start..].starts_with(op))
+        {
+            tokens.push(Token::Op(op));
+            chars.nth(1);
+            continue;
+        }
+        match c {
+            '!' | '(' | ')' => {
+                tokens.push(Token::Op(match c {
+                    '!' => "!",
+                    '(' => "(",
+                    _ => ")",
+                }));
+                chars.next();
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => return Err("unterminated string".to_string()),
+                        },
+                        Some((_, ch)) if ch == c => break,
+                        Some((_, ch)) => text.push(ch),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Str(text));
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut text = String::new();
+                while let Some(&(_, ch)) = chars.peek() {
+                    if ch.is_ascii_digit() || ch == '.' || (ch == '-' && text.is_empty()) {
+                        text.push(ch);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Number(text));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut text = String::new();
+                while let Some(&(_, ch)) = chars.peek() {
+                    if ch.is_alphanumeric() || matches!(ch, '_' | '.' | ':' | '-') {
+                        text.push(ch);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Ident(text));
+            }
+            other => return Err(format!("unexpected character `{}`", other)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+type ParseResult = std::result::Result<Expr, String>;
+
+impl Parser {
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.tokens.get(self.pos), Some(Token::Op(current)) if *current == op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> ParseResult {
+        let mut left = self.and()?;
+        while self.eat("||") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> ParseResult {
+        let mut left = self.not()?;
+        while self.eat("&&") {
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> ParseResult {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        let left = self.primary()?;
+        if self.eat("==") {
+            Ok(Expr::Eq(Box::new(left), Box::new(self.primary()?)))
+        } else if self.eat("!=") {
+            Ok(Expr::Ne(Box::new(left), Box::new(self.primary()?)))
+        } else {
+            Ok(left)
+        }
+    }
+
+    fn primary(&mut self) -> ParseResult {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| "unexpected end of expression".to_string())?;
+        self.pos += 1;
+        match token {
+            Token::Op("(") => {
+                let inner = self.or()?;
+                if !self.eat(")") {
+                    return Err("missing `)`".to_string());
+                }
+                Ok(inner)
+            }
+            Token::Str(text) | Token::Number(text) => Ok(Expr::Literal(Some(text))),
+            Token::Ident(ident) => Ok(match ident.as_str() {
+                "true" | "false" => Expr::Literal(Some(ident)),
+                "null" => Expr::Literal(None),
+                _ => Expr::Key(ident),
+            }),
+            Token::Op(op) => Err(format!("unexpected `{}`", op)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_evaluate_condition_expr() {
+        let expr = ConditionExpr::parse(r#"route == "tools" && !done"#).unwrap();
+        assert_eq!(expr.keys(), ["route", "done"]);
+        assert!(expr.evaluate(&state(&[("route", "tools")])));
+        assert!(!expr.evaluate(&state(&[("route", "tools"), ("done", "true")])));
+        assert!(expr.evaluate(&state(&[("route", "tools"), ("done", "false")])));
+
+        let expr =
+            ConditionExpr::parse("(status != 'failed' || retries == 0) && user.tier == null")
+                .unwrap();
+        assert!(expr.evaluate(&state(&[("status", "ok")])));
+        assert!(expr.evaluate(&state(&[("status", "failed"), ("retries", "0")])));
+        assert!(!expr.evaluate(&state(&[("status", "ok"), ("user.tier", "gold")])));
+    }
+
+    #[test]
+    fn test_invalid_condition_expr() {
+        for source in ["route ==", "(a", "a b", "route = 'x'", "'open"] {
+            let error = ConditionExpr::parse(source).unwrap_err();
+            assert!(error
+                .to_string()
+                .starts_with(&format!("Invalid condition expression `{}`", source)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expr_condition_reads_store() {
+        use crate::state::{ContextStore, FlowContext, MemoryStore};
+
+        let store = Arc::new(MemoryStore::new());
+        let ctx = FlowContext::new(store.clone());
+        let condition = condition_expr("route == 'tools'").unwrap();
+        assert!(!condition(&ctx).await);
+        store.set("route", "tools".to_string()).await.unwrap();
+        assert!(condition(&ctx).await);
+    }
+}
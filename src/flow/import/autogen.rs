@@ -0,0 +1,486 @@
+//! AutoGen 群聊配置导入
+//!
+//! 支持两种写法：
+//!
+//! - 0.2：`agents` 列表（`system_message`、`description`、`llm_config.config_list`）加
+//!   `group_chat`（`speaker_selection_method`、`max_round`），`llm_config: false` 的
+//!   UserProxy 由流程输入代替，不生成节点
+//! - 0.4：`provider` / `config` 组件配置，支持 `RoundRobinGroupChat`、`SelectorGroupChat`
+//!   和单个 `AssistantAgent`
+//!
+//! 群聊按轮次展开为显式的图：`round_robin` 依次生成 `{agent}_{turn}` 节点；`auto` 在每轮前
+//! 插入 `select_{turn}` LLM 决策节点选择发言者，输出 `TERMINATE` 时提前结束。所有路径最终
+//! 进入 `done` 终止节点。
+//!
+//! ```json
+//! {
+//!   "name": "research",
+//!   "agents": [
+//!     { "name": "user_proxy", "llm_config": false },
+//!     { "name": "planner", "system_message": "Plan the work.",
+//!       "llm_config": { "config_list": [{ "model": "gpt-4o", "api_type": "openai" }] } },
+//!     { "name": "critic", "system_message": "Review the plan." }
+//!   ],
+//!   "group_chat": { "speaker_selection_method": "round_robin", "max_round": 4 }
+//! }
+//! ```
+
+use serde_json::{json, Map, Value};
+
+use super::{apply_llm, import_error, string_field, ImportedTools, LlmSettings};
+use crate::error::Result;
+use crate::flow::loader::{load_workflow_from_value, WorkflowBundle};
+
+const DEFAULT_MAX_ROUND: usize = 10;
+const DONE: &str = "done";
+
+/// 发言者选择方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Selection {
+    RoundRobin,
+    Auto,
+}
+
+struct Participant {
+    name: String,
+    description: Option<String>,
+    system_message: Option<String>,
+    llm: LlmSettings,
+    tools: Vec<String>,
+}
+
+struct Team {
+    name: String,
+    participants: Vec<Participant>,
+    selection: Selection,
+    max_round: usize,
+    selector: Option<LlmSettings>,
+    selector_prompt: Option<String>,
+    tools: ImportedTools,
+}
+
+/// 导入 AutoGen 配置并构建工作流
+pub fn import_autogen(value: &Value) -> Result<WorkflowBundle> {
+    load_workflow_from_value(&autogen_to_workflow(value)?)
+}
+
+/// 把 AutoGen 配置转换为 `WorkflowConfig` JSON
+pub fn autogen_to_workflow(value: &Value) -> Result<Value> {
+    let team = if value.get("provider").is_some() && value.get("config").is_some() {
+        team_from_component(value)?
+    } else {
+        team_from_legacy(value)?
+    };
+    if team.participants.is_empty() {
+        return Err(import_error(
+            "agents",
+            "no AutoGen agent with an LLM to import",
+        ));
+    }
+    Ok(build_workflow(team))
+}
+
+/// 0.2：`agents` + `group_chat`
+fn team_from_legacy(value: &Value) -> Result<Team> {
+    let mut tools = ImportedTools::default();
+    let mut participants = Vec::new();
+    for (index, agent) in value["agents"].as_array().into_iter().flatten().enumerate() {
+        let llm_config = &agent["llm_config"];
+        if llm_config == &Value::Bool(false) {
+            continue;
+        }
+        let name = string_field(agent, "name")
+            .ok_or_else(|| import_error(format!("agents[{index}].name"), "is required"))?;
+        let agent_tools = ["functions", "tools"]
+            .iter()
+            .flat_map(|key| llm_config[*key].as_array().into_iter().flatten())
+            .chain(agent["tools"].as_array().into_iter().flatten())
+            .filter_map(|tool| register_tool(&mut tools, tool))
+            .collect();
+        participants.push(Participant {
+            name,
+            description: string_field(agent, "description"),
+            system_message: string_field(agent, "system_message"),
+            llm: legacy_llm(llm_config),
+            tools: agent_tools,
+        });
+    }
+
+    let chat = value
+        .get("group_chat")
+        .or_else(|| value.get("groupchat"))
+        .unwrap_or(&Value::Null);
+    let (selection, max_round) = if chat.is_null() {
+        (Selection::RoundRobin, participants.len())
+    } else {
+        let selection = match chat["speaker_selection_method"].as_str().unwrap_or("auto") {
+            "round_robin" => Selection::RoundRobin,
+            "auto" => Selection::Auto,
+            other => {
+                return Err(import_error(
+                    "group_chat.speaker_selection_method",
+                    format!("`{other}` is not supported, expected `auto` or `round_robin`"),
+                ))
+            }
+        };
+        let max_round = chat["max_round"]
+            .as_u64()
+            .map_or(DEFAULT_MAX_ROUND, |max| max as usize);
+        (selection, max_round)
+    };
+    let manager = chat
+        .get("llm_config")
+        .or_else(|| value["manager"].get("llm_config"))
+        .filter(|config| config.is_object());
+
+    Ok(Team {
+        name: string_field(value, "name").unwrap_or_else(|| "autogen_group_chat".to_string()),
+        participants,
+        selection,
+        max_round,
+        selector: manager.map(legacy_llm),
+        selector_prompt: string_field(chat, "select_speaker_message_template"),
+        tools,
+    })
+}
+
+/// 0.2 的 `llm_config`：取 `config_list` 的第一项
+fn legacy_llm(llm_config: &Value) -> LlmSettings {
+    let entry = llm_config["config_list"]
+        .as_array()
+        .and_then(|list| list.first())
+        .unwrap_or(llm_config);
+    LlmSettings {
+        provider: string_field(entry, "api_type"),
+        model: string_field(entry, "model"),
+        base_url: string_field(entry, "base_url"),
+        api_key: string_field(entry, "api_key"),
+        temperature: llm_config.get("temperature").cloned(),
+    }
+}
+
+/// 0.4：`{"provider": "...RoundRobinGroupChat", "config": {...}}`
+fn team_from_component(value: &Value) -> Result<Team> {
+    let provider = value["provider"].as_str().unwrap_or_default();
+    let config = &value["config"];
+    let mut tools = ImportedTools::default();
+    let name = string_field(value, "label")
+        .or_else(|| string_field(config, "name"))
+        .unwrap_or_else(|| "autogen_group_chat".to_string());
+
+    if provider.ends_with("AssistantAgent") {
+        let participant = component_participant(value, &mut tools)
+            .ok_or_else(|| import_error("config.name", "is required"))?;
+        return Ok(Team {
+            name,
+            participants: vec![participant],
+            selection: Selection::RoundRobin,
+            max_round: 1,
+            selector: None,
+            selector_prompt: None,
+            tools,
+        });
+    }
+
+    let selection = if provider.ends_with("RoundRobinGroupChat") {
+        Selection::RoundRobin
+    } else if provider.ends_with("SelectorGroupChat") {
+        Selection::Auto
+    } else {
+        return Err(import_error(
+            "provider",
+            format!("`{provider}` is not supported, expected RoundRobinGroupChat, SelectorGroupChat or AssistantAgent"),
+        ));
+    };
+    let participants = config["participants"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|participant| {
+            !participant["provider"]
+                .as_str()
+                .unwrap_or_default()
+                .ends_with("UserProxyAgent")
+        })
+        .filter_map(|participant| component_participant(participant, &mut tools))
+        .collect();
+    let max_round = config["max_turns"]
+        .as_u64()
+        .or_else(|| max_messages(&config["termination_condition"]))
+        .map_or(DEFAULT_MAX_ROUND, |max| max as usize);
+
+    Ok(Team {
+        name,
+        participants,
+        selection,
+        max_round,
+        selector: config
+            .get("model_client")
+            .filter(|client| client.is_object())
+            .map(component_llm),
+        selector_prompt: string_field(config, "selector_prompt"),
+        tools,
+    })
+}
+
+fn component_participant(component: &Value, tools: &mut ImportedTools) -> Option<Participant> {
+    let config = &component["config"];
+    Some(Participant {
+        name: string_field(config, "name")?,
+        description: string_field(config, "description"),
+        system_message: string_field(config, "system_message"),
+        llm: component_llm(&config["model_client"]),
+        tools: config["tools"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|tool| register_tool(tools, tool))
+            .collect(),
+    })
+}
+
+/// 0.4 的 `model_client`：按组件名识别提供商
+fn component_llm(client: &Value) -> LlmSettings {
+    let provider = client["provider"]
+        .as_str()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let config = &client["config"];
+    let provider = ["anthropic", "azure", "openai"]
+        .into_iter()
+        .find(|name| provider.contains(name))
+        .map(str::to_string);
+    LlmSettings {
+        provider,
+        model: string_field(config, "model"),
+        base_url: string_field(config, "base_url"),
+        api_key: string_field(config, "api_key"),
+        temperature: config.get("temperature").cloned(),
+    }
+}
+
+/// 在（可能组合的）终止条件中查找 `MaxMessageTermination`
+fn max_messages(condition: &Value) -> Option<u64> {
+    let config = &condition["config"];
+    config["max_messages"].as_u64().or_else(|| {
+        config["conditions"]
+            .as_array()?
+            .iter()
+            .find_map(max_messages)
+    })
+}
+
+/// 工具可以是 `{"name", "description"}`、OpenAI 的 `{"type": "function", "function": {...}}`
+/// 或 0.4 的 `{"provider", "config": {...}}`
+fn register_tool(tools: &mut ImportedTools, tool: &Value) -> Option<String> {
+    let spec = ["function", "config"]
+        .iter()
+        .find_map(|key| tool.get(*key).filter(|spec| spec.is_object()))
+        .unwrap_or(tool);
+    let name = spec["name"].as_str()?;
+    Some(tools.add(name, spec.get("description")))
+}
+
+fn build_workflow(team: Team) -> Value {
+    let mut agents: Vec<Value> = team.participants.iter().map(participant_agent).collect();
+    let mut nodes = Vec::new();
+    let mut transitions = Vec::new();
+    let count = team.participants.len();
+    let max_round = team.max_round.max(1);
+    let turn_node = |participant: &Participant, turn: usize| format!("{}_{turn}", participant.name);
+    let next_node = |turn: usize, first: String| {
+        if turn == max_round {
+            DONE.to_string()
+        } else {
+            first
+        }
+    };
+
+    let start = match team.selection {
+        Selection::RoundRobin => {
+            for turn in 1..=max_round {
+                let participant = &team.participants[(turn - 1) % count];
+                let name = turn_node(participant, turn);
+                let next = next_node(turn, turn_node(&team.participants[turn % count], turn + 1));
+                nodes.push(json!({ "kind": "agent", "name": name, "agent": participant.name }));
+                transitions.push(json!({ "from": name, "to": next }));
+            }
+            turn_node(&team.participants[0], 1)
+        }
+        Selection::Auto => {
+            let selector = match team.selector {
+                Some(llm) => {
+                    let mut agent = Map::new();
+                    agent.insert("name".to_string(), json!("speaker_selector"));
+                    apply_llm(&mut agent, &llm);
+                    agents.push(Value::Object(agent));
+                    "speaker_selector".to_string()
+                }
+                None => team.participants[0].name.clone(),
+            };
+            for turn in 1..=max_round {
+                let mut branches: Vec<Value> = team
+                    .participants
+                    .iter()
+                    .map(|participant| {
+                        json!({
+                            "label": participant.name,
+                            "target": turn_node(participant, turn),
+                            "description": participant.description,
+                        })
+                    })
+                    .collect();
+                branches.push(json!({
+                    "label": "TERMINATE",
+                    "target": DONE,
+                    "description": "The conversation is complete",
+                }));
+                let mut select = json!({
+                    "kind": "llm_decision",
+                    "name": format!("select_{turn}"),
+                    "agent": selector,
+                    "branches": branches,
+                    "fallback": turn_node(&team.participants[(turn - 1) % count], turn),
+                });
+                if let Some(prompt) = &team.selector_prompt {
+                    select["prompt"] = json!(prompt);
+                }
+                nodes.push(select);
+                for participant in &team.participants {
+                    let name = turn_node(participant, turn);
+                    nodes.push(json!({ "kind": "agent", "name": name, "agent": participant.name }));
+                    transitions.push(json!({
+                        "from": name,
+                        "to": next_node(turn, format!("select_{}", turn + 1)),
+                    }));
+                }
+            }
+            "select_1".to_string()
+        }
+    };
+    nodes.push(json!({ "kind": "terminal", "name": DONE }));
+
+    json!({
+        "agents": agents,
+        "tools": team.tools.into_value(),
+        "flow": {
+            "name": team.name,
+            "start": start,
+            "nodes": nodes,
+            "transitions": transitions,
+        }
+    })
+}
+
+fn participant_agent(participant: &Participant) -> Value {
+    let mut agent = Map::new();
+    agent.insert("name".to_string(), json!(participant.name));
+    if let Some(description) = &participant.description {
+        agent.insert("role".to_string(), json!(description));
+    }
+    if let Some(system_message) = &participant.system_message {
+        agent.insert("prompt".to_string(), json!(system_message));
+    }
+    if !participant.tools.is_empty() {
+        agent.insert("tools".to_string(), json!(participant.tools));
+    }
+    apply_llm(&mut agent, &participant.llm);
+    Value::Object(agent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::config::{validate_workflow_config, GraphFlow};
+    use crate::flow::loader::build_flow_from_graph;
+    use crate::flow::FlowNodeKind;
+
+    #[test]
+    fn test_import_round_robin_group_chat() {
+        let config = json!({
+            "name": "research",
+            "agents": [
+                { "name": "user_proxy", "llm_config": false },
+                {
+                    "name": "planner",
+                    "system_message": "Plan the work.",
+                    "llm_config": { "functions": [{ "name": "search", "description": "Web search" }] }
+                },
+                { "name": "critic", "description": "Reviews plans" }
+            ],
+            "group_chat": { "speaker_selection_method": "round_robin", "max_round": 3 }
+        });
+
+        let workflow = autogen_to_workflow(&config).unwrap();
+        assert_eq!(workflow["agents"][0]["tools"], json!(["search"]));
+        assert_eq!(workflow["tools"][0]["description"], "Web search");
+
+        let bundle = import_autogen(&config).unwrap();
+        let flow = &bundle.flow;
+        assert_eq!(flow.start, "planner_1");
+        for node in ["planner_1", "critic_2", "planner_3", "done"] {
+            assert!(flow.node(node).is_some(), "missing node {node}");
+        }
+        assert!(flow.node("user_proxy_1").is_none());
+        assert!(flow.analyze().is_bounded());
+        assert!(bundle.agents.contains_key("critic"));
+        assert!(bundle.tools.get("search").is_some());
+    }
+
+    #[test]
+    fn test_import_selector_group_chat_component() {
+        let config = json!({
+            "provider": "autogen_agentchat.teams.SelectorGroupChat",
+            "label": "support",
+            "config": {
+                "participants": [
+                    {
+                        "provider": "autogen_agentchat.agents.AssistantAgent",
+                        "config": { "name": "triage", "description": "Classifies requests" }
+                    },
+                    {
+                        "provider": "autogen_agentchat.agents.AssistantAgent",
+                        "config": { "name": "writer", "description": "Drafts replies" }
+                    },
+                    { "provider": "autogen_agentchat.agents.UserProxyAgent", "config": { "name": "user" } }
+                ],
+                "termination_condition": {
+                    "provider": "autogen_agentchat.base.OrTerminationCondition",
+                    "config": { "conditions": [
+                        { "provider": "autogen_agentchat.conditions.TextMentionTermination", "config": { "text": "TERMINATE" } },
+                        { "provider": "autogen_agentchat.conditions.MaxMessageTermination", "config": { "max_messages": 2 } }
+                    ] }
+                }
+            }
+        });
+
+        // 选择发言者需要真实的 LLM 客户端，这里只检查生成的图
+        let workflow = autogen_to_workflow(&config).unwrap();
+        validate_workflow_config(&workflow).unwrap();
+        let graph: GraphFlow = serde_json::from_value(workflow["flow"].clone()).unwrap();
+        let flow = build_flow_from_graph(&graph);
+        assert_eq!(flow.name, "support");
+        assert_eq!(flow.start, "select_1");
+        let Some(FlowNodeKind::LlmDecision(select)) = flow.node("select_1").map(|node| &node.kind)
+        else {
+            panic!("select_1 should be an LLM decision node");
+        };
+        let labels: Vec<_> = select.branches.iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(labels, ["triage", "writer", "TERMINATE"]);
+        assert!(flow.node("select_2").is_some());
+        assert!(flow.node("select_3").is_none());
+        assert!(flow.node("user_1").is_none());
+    }
+
+    #[test]
+    fn test_reject_unsupported_selection() {
+        let config = json!({
+            "agents": [{ "name": "a" }],
+            "group_chat": { "speaker_selection_method": "manual" }
+        });
+        let error = autogen_to_workflow(&config).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("group_chat.speaker_selection_method"));
+    }
+}
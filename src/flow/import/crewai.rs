@@ -0,0 +1,348 @@
+//! CrewAI 配置导入
+//!
+//! 输入为 `{"agents": ..., "tasks": ..., "process": "sequential"}`，`agents` / `tasks` 对应
+//! CrewAI 项目中的 `agents.yaml` 和 `tasks.yaml`，可以是以名称为键的对象，也可以是带 `name`
+//! 字段的列表。JSON 对象不保留键的顺序，需要按文件顺序执行时用列表，或用
+//! `parse_crewai_yaml`（需启用 `yaml` feature）读取 YAML。
+//!
+//! 每个任务生成一个 Agent 节点：Agent 的 `role` / `goal` / `backstory` 与任务的
+//! `description` / `expected_output` 合成该节点的 prompt。任务按顺序串联（`context`
+//! 引用的任务排在前面），最后进入 `done` 终止节点。只支持 `sequential` 流程。
+//!
+//! ```yaml
+//! # agents.yaml
+//! researcher:
+//!   role: Senior Researcher
+//!   goal: Find the latest developments in {topic}
+//!   backstory: You are known for digging up obscure sources.
+//!   llm: openai/gpt-4o
+//!   tools: [SerperDevTool]
+//! # tasks.yaml
+//! research_task:
+//!   description: Research {topic}
+//!   expected_output: A list of 10 bullet points
+//!   agent: researcher
+//! ```
+
+use serde_json::{json, Map, Value};
+
+use super::{apply_llm, import_error, string_field, ImportedTools, LlmSettings};
+use crate::error::Result;
+use crate::flow::loader::{load_workflow_from_value, WorkflowBundle};
+
+const DONE: &str = "done";
+
+/// CrewAI 内置工具对应的内置工具名
+const BUILTIN_TOOLS: &[(&str, &str)] = &[
+    ("serperdevtool", "web.search"),
+    ("bravesearchtool", "web.search"),
+    ("exasearchtool", "web.search"),
+    ("scrapewebsitetool", "web.read"),
+    ("firecrawlscrapewebsitetool", "web.read"),
+    ("firecrawlcrawlwebsitetool", "web.crawl"),
+    ("spidertool", "web.crawl"),
+    ("dalletool", "image_generator"),
+];
+
+/// 导入 CrewAI 配置并构建工作流
+pub fn import_crewai(value: &Value) -> Result<WorkflowBundle> {
+    load_workflow_from_value(&crewai_to_workflow(value)?)
+}
+
+/// 按文件顺序读取 `agents.yaml` 和 `tasks.yaml`，合并为 `crewai_to_workflow` 的输入
+#[cfg(feature = "yaml")]
+pub fn parse_crewai_yaml(agents: &str, tasks: &str) -> Result<Value> {
+    Ok(json!({
+        "agents": ordered_yaml(agents)?,
+        "tasks": ordered_yaml(tasks)?,
+    }))
+}
+
+/// 以名称为键的 YAML 映射转换为带 `name` 字段的列表，保留原有顺序
+#[cfg(feature = "yaml")]
+fn ordered_yaml(text: &str) -> Result<Value> {
+    use crate::error::AgentFlowError;
+
+    let serialization = |e: serde_yaml::Error| AgentFlowError::Serialization(e.to_string());
+    let mapping: serde_yaml::Mapping = serde_yaml::from_str(text).map_err(serialization)?;
+    mapping
+        .into_iter()
+        .map(|(key, value)| {
+            let mut entry: Value = serde_yaml::from_value(value).map_err(serialization)?;
+            if let (Some(name), Some(object)) = (key.as_str(), entry.as_object_mut()) {
+                object.insert("name".to_string(), json!(name));
+            }
+            Ok(entry)
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Value::Array)
+}
+
+/// 把 CrewAI 配置转换为 `WorkflowConfig` JSON
+pub fn crewai_to_workflow(value: &Value) -> Result<Value> {
+    if let Some(process) = value["process"].as_str() {
+        if process != "sequential" {
+            return Err(import_error(
+                "process",
+                format!("`{process}` is not supported, expected `sequential`"),
+            ));
+        }
+    }
+    let agents = named_entries(&value["agents"], "agents")?;
+    let tasks = ordered_tasks(named_entries(&value["tasks"], "tasks")?)?;
+    if tasks.is_empty() {
+        return Err(import_error("tasks", "no CrewAI task to import"));
+    }
+
+    let mut tools = ImportedTools::default();
+    let mut agent_configs = Vec::new();
+    let mut nodes = Vec::new();
+    let mut transitions = Vec::new();
+    for (index, (task_name, task)) in tasks.iter().enumerate() {
+        let agent_name = task["agent"]
+            .as_str()
+            .ok_or_else(|| import_error(format!("tasks.{task_name}.agent"), "is required"))?;
+        let agent = agents
+            .iter()
+            .find(|(name, _)| name == agent_name)
+            .map(|(_, agent)| *agent)
+            .ok_or_else(|| {
+                import_error(
+                    format!("tasks.{task_name}.agent"),
+                    format!("unknown agent `{agent_name}`"),
+                )
+            })?;
+        agent_configs.push(task_agent(task_name, task, agent_name, agent, &mut tools));
+        nodes.push(json!({ "kind": "agent", "name": task_name, "agent": task_name }));
+        let next = tasks.get(index + 1).map_or(DONE, |(next, _)| next.as_str());
+        transitions.push(json!({ "from": task_name, "to": next }));
+    }
+    nodes.push(json!({ "kind": "terminal", "name": DONE }));
+
+    Ok(json!({
+        "agents": agent_configs,
+        "tools": tools.into_value(),
+        "flow": {
+            "name": string_field(value, "name").unwrap_or_else(|| "crew".to_string()),
+            "start": tasks[0].0,
+            "nodes": nodes,
+            "transitions": transitions,
+        }
+    }))
+}
+
+/// 对象按键、列表按 `name` 字段取出条目
+fn named_entries<'a>(value: &'a Value, path: &str) -> Result<Vec<(String, &'a Value)>> {
+    match value {
+        Value::Object(entries) => Ok(entries
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry))
+            .collect()),
+        Value::Array(entries) => entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                string_field(entry, "name")
+                    .map(|name| (name, entry))
+                    .ok_or_else(|| import_error(format!("{path}[{index}].name"), "is required"))
+            })
+            .collect(),
+        Value::Null => Ok(Vec::new()),
+        _ => Err(import_error(path, "expected an object or a list")),
+    }
+}
+
+/// 保持原有顺序，`context` 引用的任务提前
+fn ordered_tasks(tasks: Vec<(String, &Value)>) -> Result<Vec<(String, &Value)>> {
+    let mut ordered: Vec<(String, &Value)> = Vec::with_capacity(tasks.len());
+    let mut pending = tasks;
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|(_, task)| {
+            task["context"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .all(|dependency| ordered.iter().any(|(name, _)| name == dependency))
+        });
+        let Some(ready) = ready else {
+            let (name, _) = &pending[0];
+            return Err(import_error(
+                format!("tasks.{name}.context"),
+                "references an unknown task or forms a cycle",
+            ));
+        };
+        ordered.push(pending.remove(ready));
+    }
+    Ok(ordered)
+}
+
+/// 任务对应的 Agent 配置
+fn task_agent(
+    task_name: &str,
+    task: &Value,
+    agent_name: &str,
+    agent: &Value,
+    tools: &mut ImportedTools,
+) -> Value {
+    let mut prompt = String::new();
+    if let Some(role) = agent["role"].as_str() {
+        prompt.push_str(&format!("You are {}.", role.trim()));
+    }
+    if let Some(backstory) = agent["backstory"].as_str() {
+        prompt.push_str(&format!(" {}", backstory.trim()));
+    }
+    if let Some(goal) = agent["goal"].as_str() {
+        prompt.push_str(&format!("\nYour personal goal is: {}", goal.trim()));
+    }
+    if let Some(description) = task["description"].as_str() {
+        prompt.push_str(&format!("\n\nCurrent Task: {}", description.trim()));
+    }
+    if let Some(expected) = task["expected_output"].as_str() {
+        prompt.push_str(&format!(
+            "\n\nThis is the expected criteria for your final answer: {}",
+            expected.trim()
+        ));
+    }
+
+    let mut config = Map::new();
+    config.insert("name".to_string(), json!(task_name));
+    if let Some(role) = agent["role"].as_str() {
+        config.insert("role".to_string(), json!(role.trim()));
+    }
+    config.insert("prompt".to_string(), json!(prompt.trim_start()));
+    let task_tools: Vec<String> = task["tools"]
+        .as_array()
+        .or_else(|| agent["tools"].as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(|tool| builtin_tool(tool).unwrap_or_else(|| tools.add(tool, None)))
+        .collect();
+    if !task_tools.is_empty() {
+        config.insert("tools".to_string(), json!(task_tools));
+    }
+    config.insert(
+        "metadata".to_string(),
+        json!({ "crewai": { "agent": agent_name, "context": task["context"] } }),
+    );
+    apply_llm(&mut config, &crewai_llm(&agent["llm"]));
+    Value::Object(config)
+}
+
+/// `llm` 可以是 `provider/model` 字符串或 `{model, base_url, api_key, temperature}`
+fn crewai_llm(llm: &Value) -> LlmSettings {
+    match llm {
+        Value::String(model) => match model.split_once('/') {
+            Some((provider, model)) => LlmSettings {
+                provider: Some(provider.to_string()),
+                model: Some(model.to_string()),
+                ..LlmSettings::default()
+            },
+            None => LlmSettings {
+                model: Some(model.clone()),
+                ..LlmSettings::default()
+            },
+        },
+        Value::Object(_) => {
+            let mut settings = crewai_llm(&llm["model"]);
+            settings.provider = string_field(llm, "provider").or(settings.provider);
+            settings.base_url = string_field(llm, "base_url");
+            settings.api_key = string_field(llm, "api_key");
+            settings.temperature = llm.get("temperature").cloned();
+            settings
+        }
+        _ => LlmSettings::default(),
+    }
+}
+
+fn builtin_tool(name: &str) -> Option<String> {
+    let normalized: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    BUILTIN_TOOLS
+        .iter()
+        .find(|(crewai, _)| *crewai == normalized)
+        .map(|(_, builtin)| builtin.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crew() -> Value {
+        json!({
+            "name": "blog",
+            "agents": [
+                {
+                    "name": "researcher",
+                    "role": "Senior Researcher",
+                    "goal": "Find facts",
+                    "backstory": "You dig deep.",
+                    "tools": ["SerperDevTool", "notes"]
+                },
+                { "name": "writer", "role": "Writer", "goal": "Write posts" }
+            ],
+            "tasks": [
+                { "name": "write", "description": "Write the post", "agent": "writer", "context": ["research"] },
+                { "name": "research", "description": "Research the topic", "expected_output": "Bullet points", "agent": "researcher" }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_import_crewai_sequential() {
+        let workflow = crewai_to_workflow(&crew()).unwrap();
+        let research = &workflow["agents"][0];
+        assert_eq!(research["name"], "research");
+        assert_eq!(research["driver"], "echo");
+        assert_eq!(research["tools"], json!(["web.search", "notes"]));
+        let prompt = research["prompt"].as_str().unwrap();
+        assert!(prompt.starts_with("You are Senior Researcher. You dig deep."));
+        assert!(prompt.contains("Current Task: Research the topic"));
+        assert_eq!(
+            workflow["tools"],
+            json!([{ "name": "notes", "driver": "echo" }])
+        );
+
+        let bundle = import_crewai(&crew()).unwrap();
+        assert_eq!(bundle.flow.start, "research");
+        assert!(bundle.flow.node("write").is_some());
+        assert!(bundle.agents.contains_key("write"));
+        assert!(bundle.tools.get("notes").is_some());
+    }
+
+    #[test]
+    fn test_crewai_llm_and_errors() {
+        let mut crew = crew();
+        crew["agents"][1]["llm"] = json!("anthropic/claude-3-5-sonnet");
+        let workflow = crewai_to_workflow(&crew).unwrap();
+        assert_eq!(workflow["agents"][1]["driver"], "claude");
+        assert_eq!(workflow["agents"][1]["model"], "claude-3-5-sonnet");
+
+        crew["process"] = json!("hierarchical");
+        let error = crewai_to_workflow(&crew).unwrap_err();
+        assert!(error.to_string().starts_with("process "));
+
+        crew["process"] = json!("sequential");
+        crew["tasks"][0]["agent"] = json!("editor");
+        let error = crewai_to_workflow(&crew).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "tasks.write.agent unknown agent `editor`"
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_parse_crewai_yaml_keeps_order() {
+        let agents = "writer:\n  role: Writer\nanalyst:\n  role: Analyst\n";
+        let tasks = "outline:\n  description: Outline\n  agent: writer\nanalyze:\n  description: Analyze\n  agent: analyst\n";
+        let crew = parse_crewai_yaml(agents, tasks).unwrap();
+        let workflow = crewai_to_workflow(&crew).unwrap();
+        assert_eq!(workflow["flow"]["start"], "outline");
+        assert_eq!(workflow["flow"]["transitions"][0]["to"], "analyze");
+    }
+}
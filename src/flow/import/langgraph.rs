@@ -0,0 +1,206 @@
+//! LangGraph 图导入
+//!
+//! 读取 `graph.get_graph().to_json()` 导出的 JSON（`nodes` + `edges`）：
+//!
+//! - `__start__` 的出边确定起始节点，`__end__` 转换为终止节点
+//! - `ToolNode`（`data.id` 以 `ToolNode` 结尾）转换为同名流水线的工具节点，
+//!   其余节点转换为同名 Agent 节点，并生成 `echo` 驱动的占位 Agent
+//! - 条件边转换为 `expr` 条件 `route == "<label>"`：Python 中的路由函数无法导出，
+//!   需要由上游节点把路由标签写入状态键 `route`
+//!
+//! ```json
+//! {
+//!   "nodes": [
+//!     { "id": "__start__", "type": "schema" },
+//!     { "id": "agent", "type": "runnable", "data": { "id": ["langgraph", "utils", "RunnableCallable"], "name": "agent" } },
+//!     { "id": "tools", "type": "runnable", "data": { "id": ["langgraph", "prebuilt", "tool_node", "ToolNode"], "name": "tools" } },
+//!     { "id": "__end__", "type": "schema" }
+//!   ],
+//!   "edges": [
+//!     { "source": "__start__", "target": "agent" },
+//!     { "source": "agent", "target": "tools", "data": "continue", "conditional": true },
+//!     { "source": "agent", "target": "__end__", "data": "end", "conditional": true },
+//!     { "source": "tools", "target": "agent" }
+//!   ]
+//! }
+//! ```
+
+use serde_json::{json, Value};
+
+use super::{import_error, string_field};
+use crate::error::{AgentFlowError, Result};
+use crate::flow::config::GraphFlow;
+use crate::flow::loader::{load_workflow_from_value, WorkflowBundle};
+
+/// 条件边读取的路由状态键
+pub const ROUTE_KEY: &str = "route";
+
+const START: &str = "__start__";
+const END: &str = "__end__";
+
+/// 导入 LangGraph 图并构建工作流
+pub fn import_langgraph(value: &Value) -> Result<WorkflowBundle> {
+    load_workflow_from_value(&langgraph_to_workflow(value)?)
+}
+
+/// 把 LangGraph 图转换为 `GraphFlow`
+pub fn langgraph_to_graph_flow(value: &Value) -> Result<GraphFlow> {
+    serde_json::from_value(graph_flow_value(value)?)
+        .map_err(|e| AgentFlowError::Serialization(e.to_string()))
+}
+
+/// 把 LangGraph 图转换为 `WorkflowConfig` JSON，Agent 节点对应 `echo` 驱动的占位 Agent
+pub fn langgraph_to_workflow(value: &Value) -> Result<Value> {
+    let flow = graph_flow_value(value)?;
+    let agents: Vec<Value> = flow["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|node| node["kind"] == "agent")
+        .map(|node| json!({ "name": node["agent"], "driver": "echo" }))
+        .collect();
+    Ok(json!({ "agents": agents, "tools": [], "flow": flow }))
+}
+
+fn graph_flow_value(value: &Value) -> Result<Value> {
+    let mut nodes = Vec::new();
+    let mut has_end = false;
+    for (index, node) in value["nodes"].as_array().into_iter().flatten().enumerate() {
+        let id = node_id(&node["id"])
+            .ok_or_else(|| import_error(format!("nodes[{index}].id"), "is required"))?;
+        match id.as_str() {
+            START => {}
+            END => has_end = true,
+            _ if is_tool_node(&node["data"]) => {
+                nodes.push(json!({ "kind": "tool", "name": id, "pipeline": id }));
+            }
+            _ => nodes.push(json!({ "kind": "agent", "name": id, "agent": id })),
+        }
+    }
+
+    let mut start_edges = Vec::new();
+    let mut transitions = Vec::new();
+    for (index, edge) in value["edges"].as_array().into_iter().flatten().enumerate() {
+        let source = node_id(&edge["source"])
+            .ok_or_else(|| import_error(format!("edges[{index}].source"), "is required"))?;
+        let target = node_id(&edge["target"])
+            .ok_or_else(|| import_error(format!("edges[{index}].target"), "is required"))?;
+        has_end |= target == END;
+        let condition = edge["conditional"].as_bool().unwrap_or(false).then(|| {
+            let label = string_field(edge, "data").unwrap_or_else(|| target.clone());
+            json!({
+                "type": "expr",
+                "expr": format!("{ROUTE_KEY} == {}", Value::String(label)),
+            })
+        });
+        if source == START {
+            start_edges.push((target, condition));
+        } else {
+            let mut transition = json!({ "from": source, "to": target });
+            if let Some(condition) = condition {
+                transition["condition"] = condition;
+            }
+            transitions.push(transition);
+        }
+    }
+    if has_end {
+        nodes.push(json!({ "kind": "terminal", "name": END }));
+    }
+
+    // 条件入口用决策节点 `__start__` 表示
+    let start = match start_edges.as_slice() {
+        [] => return Err(import_error("edges", "no edge from `__start__`")),
+        [(target, None)] => target.clone(),
+        _ => {
+            let branches: Vec<Value> = start_edges
+                .iter()
+                .map(|(target, condition)| json!({ "target": target, "condition": condition }))
+                .collect();
+            nodes.push(json!({ "kind": "decision", "name": START, "branches": branches }));
+            START.to_string()
+        }
+    };
+
+    Ok(json!({
+        "name": string_field(value, "name").unwrap_or_else(|| "langgraph".to_string()),
+        "start": start,
+        "nodes": nodes,
+        "transitions": transitions,
+    }))
+}
+
+/// 节点 ID 可能是字符串或数字
+fn node_id(value: &Value) -> Option<String> {
+    match value {
+        Value::String(id) => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+fn is_tool_node(data: &Value) -> bool {
+    data["id"]
+        .as_array()
+        .and_then(|path| path.last())
+        .and_then(Value::as_str)
+        .is_some_and(|class| class == "ToolNode")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::config::{GraphCondition, GraphNode};
+
+    fn react_graph() -> Value {
+        json!({
+            "nodes": [
+                { "id": "__start__", "type": "schema", "data": "__start__" },
+                { "id": "agent", "type": "runnable", "data": { "id": ["langgraph", "utils", "RunnableCallable"], "name": "agent" } },
+                { "id": "tools", "type": "runnable", "data": { "id": ["langgraph", "prebuilt", "tool_node", "ToolNode"], "name": "tools" } },
+                { "id": "__end__", "type": "schema", "data": "__end__" }
+            ],
+            "edges": [
+                { "source": "__start__", "target": "agent" },
+                { "source": "agent", "target": "tools", "data": "continue", "conditional": true },
+                { "source": "agent", "target": "__end__", "data": "end", "conditional": true },
+                { "source": "tools", "target": "agent" }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_langgraph_to_graph_flow() {
+        let graph = langgraph_to_graph_flow(&react_graph()).unwrap();
+        assert_eq!(graph.start, "agent");
+        assert!(graph
+            .nodes
+            .iter()
+            .any(|node| matches!(node, GraphNode::Tool { name, pipeline, .. } if name == "tools" && pipeline == "tools")));
+        let conditional = graph
+            .transitions
+            .iter()
+            .find(|transition| transition.to == "tools")
+            .unwrap();
+        let Some(GraphCondition::Expr { expr }) = &conditional.condition else {
+            panic!("conditional edge should map to an expr condition");
+        };
+        assert_eq!(expr.source(), r#"route == "continue""#);
+    }
+
+    #[test]
+    fn test_import_langgraph_workflow() {
+        let bundle = import_langgraph(&react_graph()).unwrap();
+        assert!(bundle.agents.contains_key("agent"));
+        assert!(bundle.flow.node("__end__").is_some());
+
+        let mut graph = react_graph();
+        graph["edges"][0] =
+            json!({ "source": "__start__", "target": "agent", "conditional": true });
+        graph["edges"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "source": "__start__", "target": "tools", "conditional": true }));
+        let flow = langgraph_to_graph_flow(&graph).unwrap();
+        assert_eq!(flow.start, "__start__");
+    }
+}
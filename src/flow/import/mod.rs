@@ -0,0 +1,123 @@
+//! 从其他 Agent 框架（AutoGen、CrewAI、LangGraph）的配置导入工作流
+//!
+//! 每个导入器先把外部配置转换为当前的 `WorkflowConfig` JSON（可以用 `agentflow import`
+//! 导出后再手工调整），再通过 `load_workflow_from_value` 构建 `WorkflowBundle`。
+
+pub mod autogen;
+pub mod crewai;
+pub mod langgraph;
+
+use serde_json::{json, Map, Value};
+
+use crate::error::{AgentFlowError, Result};
+
+pub use autogen::{autogen_to_workflow, import_autogen};
+#[cfg(feature = "yaml")]
+pub use crewai::parse_crewai_yaml;
+pub use crewai::{crewai_to_workflow, import_crewai};
+pub use langgraph::{import_langgraph, langgraph_to_graph_flow, langgraph_to_workflow};
+
+/// 支持导入的外部框架
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportSource {
+    /// Microsoft AutoGen（0.2 的 `agents` + `group_chat`，或 0.4 的组件配置）
+    Autogen,
+    /// CrewAI 的 `agents.yaml` + `tasks.yaml`
+    Crewai,
+    /// LangGraph 导出的图 JSON
+    Langgraph,
+}
+
+impl std::str::FromStr for ImportSource {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "autogen" => Ok(ImportSource::Autogen),
+            "crewai" | "crew" => Ok(ImportSource::Crewai),
+            "langgraph" | "langchain" => Ok(ImportSource::Langgraph),
+            other => Err(format!("unknown import source `{other}`")),
+        }
+    }
+}
+
+impl ImportSource {
+    /// 把外部配置转换为 `WorkflowConfig` JSON
+    pub fn to_workflow(self, value: &Value) -> Result<Value> {
+        match self {
+            ImportSource::Autogen => autogen_to_workflow(value),
+            ImportSource::Crewai => crewai_to_workflow(value),
+            ImportSource::Langgraph => langgraph_to_workflow(value),
+        }
+    }
+}
+
+/// 导入过程中收集的工具，按名称去重
+#[derive(Default)]
+struct ImportedTools {
+    tools: Vec<Value>,
+}
+
+impl ImportedTools {
+    /// 登记为回显驱动的 `ToolConfig`，返回工具名
+    fn add(&mut self, name: &str, description: Option<&Value>) -> String {
+        if !self.tools.iter().any(|tool| tool["name"] == name) {
+            let mut tool = json!({ "name": name, "driver": "echo" });
+            if let Some(description) = description.filter(|value| value.is_string()) {
+                tool["description"] = description.clone();
+            }
+            self.tools.push(tool);
+        }
+        name.to_string()
+    }
+
+    fn into_value(self) -> Value {
+        Value::Array(self.tools)
+    }
+}
+
+/// 按 LLM 设置填充 Agent 的 `driver` / `model` / `endpoint` / `api_key` / `temperature`
+///
+/// 未指定模型时使用 `echo` 驱动，导入后再按需修改。
+fn apply_llm(agent: &mut Map<String, Value>, llm: &LlmSettings) {
+    let driver = match (&llm.provider, &llm.base_url, &llm.model) {
+        (Some(provider), _, _) => crate::config::migrate::driver_for(provider),
+        (None, Some(_), _) => "generic".to_string(),
+        (None, None, Some(_)) => "chatgpt".to_string(),
+        (None, None, None) => "echo".to_string(),
+    };
+    agent.insert("driver".to_string(), Value::String(driver));
+    for (key, value) in [
+        ("model", &llm.model),
+        ("endpoint", &llm.base_url),
+        ("api_key", &llm.api_key),
+    ] {
+        if let Some(value) = value {
+            agent.insert(key.to_string(), Value::String(value.clone()));
+        }
+    }
+    if let Some(temperature) = &llm.temperature {
+        agent.insert("temperature".to_string(), temperature.clone());
+    }
+}
+
+/// 外部配置中的 LLM 设置
+#[derive(Default)]
+struct LlmSettings {
+    provider: Option<String>,
+    model: Option<String>,
+    base_url: Option<String>,
+    api_key: Option<String>,
+    temperature: Option<Value>,
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn import_error(path: impl Into<String>, message: impl Into<String>) -> AgentFlowError {
+    AgentFlowError::InvalidConfig {
+        path: path.into(),
+        message: message.into(),
+    }
+}
@@ -1,5 +1,6 @@
 pub mod workflow_loader;
 
 pub use workflow_loader::{
-    build_flow_from_graph, load_workflow_from_str, load_workflow_from_value, WorkflowBundle,
+    build_flow_from_graph, load_workflow_from_config, load_workflow_from_str,
+    load_workflow_from_value, load_workflow_with_llm, WorkflowBundle,
 };
@@ -1,13 +1,24 @@
+use anyhow::anyhow;
 use serde_json::Value;
+use std::collections::HashSet;
 use std::sync::Arc;
 
-use crate::agent::{register_agent, AgentRegistry};
+use crate::agent::{register_agent, Agent, AgentDecoratorRegistry, AgentRegistry};
+use crate::config::migrate::migrate_workflow;
 use crate::error::{AgentFlowError, Result};
-use crate::flow::{DecisionBranch, DecisionPolicy, Flow, FlowBuilder, JoinStrategy};
+use crate::flow::{
+    AwaitEventNode, DebateNode, DecisionBranch, DecisionPolicy, ExperimentNode, ExperimentVariant,
+    Flow, FlowBuilder, FlowNodeKind, GroupChatNode, ImageGenNode, JoinStrategy, LlmDecisionBranch,
+    LlmDecisionNode, MapNode, TransformNode,
+};
+use crate::guardrails::GuardedAgent;
+use crate::llm::{DynLlmClient, ImageGenRequest};
 use crate::tools::ToolRegistry;
 
 use crate::flow::agent::{ConfigDrivenAgent, ConfigDrivenTool};
-use crate::flow::config::{GraphFlow, GraphNode, WorkflowConfig};
+use crate::flow::config::{
+    validate_workflow_config, AgentDriverKind, GraphFlow, GraphNode, WorkflowConfig,
+};
 use crate::flow::services::llm_client_factory::LlmClientFactory;
 
 /// 工作流包，包含流程、Agent 注册表和工具注册表
@@ -17,10 +28,44 @@ pub struct WorkflowBundle {
     pub tools: ToolRegistry,
 }
 
+/// 解析合并策略：`any`、`count:N`，其余为 `all`
+fn join_strategy(strategy: &str) -> JoinStrategy {
+    match strategy {
+        "any" => JoinStrategy::Any,
+        other => {
+            if other.starts_with("count:") {
+                let parts: Vec<_> = other.split(':').collect();
+                let count = parts
+                    .get(1)
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .expect("Invalid join count in strategy");
+                JoinStrategy::Count(count)
+            } else {
+                JoinStrategy::All
+            }
+        }
+    }
+}
+
+/// 脚本节点在 Agent 注册表中的名称
+#[cfg(feature = "script")]
+fn script_agent_name(node: &str) -> String {
+    format!("script:{}", node)
+}
+
 /// 从 GraphFlow 构建 Flow
 pub fn build_flow_from_graph(graph: &GraphFlow) -> Flow {
     let mut builder = FlowBuilder::new(graph.name.clone());
-    builder.set_start(&graph.start);
+    builder.set_start(&graph.start).set_version(graph.version);
+    if let Some(dead_letter) = &graph.dead_letter {
+        builder.set_dead_letter(dead_letter);
+    }
+    if let Some(on_error) = &graph.on_error {
+        builder.set_error_handler(on_error);
+    }
+    if let Some(history) = &graph.history {
+        builder.set_history_retention(history.clone());
+    }
 
     for parameter in graph.parameters.clone() {
         builder.with_parameter(parameter.into_flow_param());
@@ -30,6 +75,10 @@ pub fn build_flow_from_graph(graph: &GraphFlow) -> Flow {
         builder.declare_variable(variable.into_flow_variable());
     }
 
+    for output in graph.outputs.clone() {
+        builder.declare_output(output.into_flow_output());
+    }
+
     for node in &graph.nodes {
         match node {
             GraphNode::Agent { name, agent } => {
@@ -50,31 +99,89 @@ pub fn build_flow_from_graph(graph: &GraphFlow) -> Flow {
                         name: branch.name.clone(),
                         condition: branch.condition.as_ref().map(|c| c.build()),
                         target: branch.target.clone(),
+                        description: branch.condition.as_ref().map(|c| c.describe()),
                     })
                     .collect::<Vec<_>>();
                 builder.add_decision_node(name, policy, branches);
             }
+            GraphNode::LlmDecision {
+                name,
+                prompt,
+                branches,
+                fallback,
+                ..
+            } => {
+                let branches = branches
+                    .iter()
+                    .map(|branch| LlmDecisionBranch {
+                        label: branch.label.clone(),
+                        target: branch.target.clone(),
+                        description: branch.description.clone(),
+                    })
+                    .collect();
+                builder.add_llm_decision_node(
+                    name,
+                    LlmDecisionNode {
+                        client: None,
+                        branches,
+                        fallback: fallback.clone(),
+                        prompt: prompt.clone(),
+                    },
+                );
+            }
+            GraphNode::Experiment {
+                name,
+                experiment,
+                variants,
+            } => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| ExperimentVariant {
+                        name: variant.name.clone(),
+                        target: variant.target.clone(),
+                        weight: variant.weight,
+                    })
+                    .collect();
+                builder.add_experiment_node(
+                    name,
+                    ExperimentNode {
+                        experiment: experiment.clone().unwrap_or_else(|| name.clone()),
+                        variants,
+                    },
+                );
+            }
             GraphNode::Join {
                 name,
                 strategy,
                 inbound,
+                timeout_ms,
+                on_timeout,
             } => {
-                let strategy = match strategy.as_str() {
-                    "any" => JoinStrategy::Any,
-                    other => {
-                        if other.starts_with("count:") {
-                            let parts: Vec<_> = other.split(':').collect();
-                            let count = parts
-                                .get(1)
-                                .and_then(|v| v.parse::<usize>().ok())
-                                .expect("Invalid join count in strategy");
-                            JoinStrategy::Count(count)
-                        } else {
-                            JoinStrategy::All
-                        }
-                    }
-                };
-                builder.add_join_node(name, strategy, inbound.clone());
+                builder.add_join_node(name, join_strategy(strategy), inbound.clone());
+                if let Some(timeout_ms) = timeout_ms {
+                    builder.set_join_timeout(
+                        name,
+                        std::time::Duration::from_millis(*timeout_ms),
+                        on_timeout.clone(),
+                    );
+                }
+            }
+            GraphNode::Map {
+                name,
+                over,
+                body,
+                join,
+                concurrency,
+            } => {
+                builder.add_map_node(
+                    name,
+                    MapNode {
+                        over: over.clone(),
+                        body: body.clone(),
+                        strategy: join.as_deref().map_or(JoinStrategy::All, join_strategy),
+                        concurrency: concurrency.unwrap_or(4),
+                    },
+                );
             }
             GraphNode::Loop {
                 name,
@@ -86,8 +193,120 @@ pub fn build_flow_from_graph(graph: &GraphFlow) -> Flow {
                 let continuation = condition.as_ref().map(|c| c.build());
                 builder.add_loop_node(name, entry, continuation, *max_iterations, exit.clone());
             }
-            GraphNode::Tool { name, pipeline, params } => {
+            GraphNode::Tool {
+                name,
+                pipeline,
+                params,
+                compensate,
+                parallel,
+                aggregate,
+            } => {
                 builder.add_tool_node_with_params(name, pipeline, params.clone());
+                if let Some(compensate) = compensate {
+                    builder.set_compensation(name, compensate);
+                }
+                if !parallel.is_empty() {
+                    let parallel: Vec<&str> = parallel.iter().map(String::as_str).collect();
+                    builder.set_parallel_pipelines(name, &parallel, *aggregate);
+                }
+            }
+            GraphNode::SubFlow {
+                name,
+                flow,
+                memoize,
+            } => {
+                builder.add_subflow_node(name, flow, memoize.as_ref().map(|m| m.build()));
+            }
+            GraphNode::Debate {
+                name,
+                proposer,
+                critic,
+                max_rounds,
+                approval,
+            } => {
+                let mut debate = DebateNode::new(proposer.clone(), critic.clone());
+                if let Some(max_rounds) = max_rounds {
+                    debate = debate.with_max_rounds(*max_rounds);
+                }
+                if let Some(approval) = approval {
+                    debate = debate.with_approval(approval.clone());
+                }
+                builder.add_debate_node(name, debate);
+            }
+            GraphNode::GroupChat {
+                name,
+                participants,
+                max_turns,
+                prompt,
+                terminate_on,
+                ..
+            } => {
+                builder.add_group_chat_node(
+                    name,
+                    GroupChatNode {
+                        participants: participants.clone(),
+                        max_turns: max_turns.unwrap_or(GroupChatNode::DEFAULT_MAX_TURNS),
+                        selector: None,
+                        prompt: prompt.clone(),
+                        terminate_on: terminate_on.clone(),
+                    },
+                );
+            }
+            GraphNode::ImageGen {
+                name,
+                config,
+                prompt,
+                negative_prompt,
+                size,
+                style,
+                n,
+            } => {
+                let mut request = ImageGenRequest::new("");
+                request.negative_prompt = negative_prompt.clone();
+                if let Some(size) = size {
+                    request = request.with_size(size.clone());
+                }
+                if let Some(style) = style {
+                    request = request.with_style(style.clone());
+                }
+                if let Some(n) = n {
+                    request = request.with_count(*n);
+                }
+                builder.add_image_gen_node(
+                    name,
+                    ImageGenNode {
+                        config: config.clone(),
+                        prompt: prompt.clone(),
+                        request,
+                    },
+                );
+            }
+            GraphNode::AwaitEvent {
+                name,
+                token_key,
+                store_as,
+                timeout_ms,
+                on_timeout,
+            } => {
+                builder.add_await_event_node(
+                    name,
+                    AwaitEventNode {
+                        token_key: token_key.clone(),
+                        store_as: store_as.clone(),
+                        timeout: timeout_ms.map(std::time::Duration::from_millis),
+                        on_timeout: on_timeout.clone(),
+                    },
+                );
+            }
+            GraphNode::Wait { name, wait } => {
+                builder.add_wait_node(name, wait.build());
+            }
+            GraphNode::Transform { name, ops } => {
+                builder.add_transform_node(name, TransformNode::new(ops.clone()));
+            }
+            #[cfg(feature = "script")]
+            GraphNode::Script { name, .. } => {
+                builder.add_agent_node(name, &script_agent_name(name));
             }
             GraphNode::Terminal { name } => {
                 builder.add_terminal_node(name);
@@ -97,44 +316,158 @@ pub fn build_flow_from_graph(graph: &GraphFlow) -> Flow {
 
     for transition in &graph.transitions {
         if let Some(condition) = &transition.condition {
-            builder.connect_if_named(
+            builder.connect_conditional_described(
                 &transition.from,
                 &transition.to,
                 transition.name.clone(),
                 condition.build(),
+                condition.describe(),
             );
         } else if let Some(name) = &transition.name {
             builder.connect_named(&transition.from, &transition.to, Some(name.clone()));
         } else {
             builder.connect(&transition.from, &transition.to);
         }
+        if transition.pipeline {
+            builder.set_pipeline(&transition.from, &transition.to);
+        }
     }
 
     builder.build()
 }
 
 /// 从 JSON Value 加载工作流
+///
+/// 旧版格式（`AgentSpec` / `FlowSpec`、引入 `driver` 之前的配置）先转换为当前格式。
 pub fn load_workflow_from_value(value: &Value) -> Result<WorkflowBundle> {
-    let config: WorkflowConfig = serde_json::from_value(value.clone())
+    load_workflow(value, None)
+}
+
+/// 加载工作流，所有 Agent（只检索的 `rag` 驱动除外）使用 `llm` 而不按驱动创建客户端，
+/// 不读取 API Key；用于以模拟 LLM 测试流程
+pub fn load_workflow_with_llm(value: &Value, llm: DynLlmClient) -> Result<WorkflowBundle> {
+    load_workflow(value, Some(llm))
+}
+
+/// 加载以 `WorkflowConfigBuilder` 等方式在代码中构建的配置，不经过 JSON Schema 校验
+pub fn load_workflow_from_config(config: &WorkflowConfig) -> Result<WorkflowBundle> {
+    build_workflow(config, None)
+}
+
+fn load_workflow(value: &Value, llm_override: Option<DynLlmClient>) -> Result<WorkflowBundle> {
+    let value = migrate_workflow(value)?;
+    validate_workflow_config(&value)?;
+    let config: WorkflowConfig = serde_json::from_value(value.into_owned())
         .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+    build_workflow(&config, llm_override)
+}
 
+/// Tool 节点的并发流水线按名称合并输出，名称重复时拒绝加载
+fn check_tool_pipelines(flow: &GraphFlow) -> Result<()> {
+    for (index, node) in flow.nodes.iter().enumerate() {
+        let GraphNode::Tool {
+            pipeline, parallel, ..
+        } = node
+        else {
+            continue;
+        };
+        let mut names = HashSet::from([pipeline.as_str()]);
+        for (position, name) in parallel.iter().enumerate() {
+            if !names.insert(name.as_str()) {
+                return Err(AgentFlowError::InvalidConfig {
+                    path: format!("flow.nodes[{}].parallel[{}]", index, position),
+                    message: format!("duplicate pipeline `{}`", name),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn build_workflow(
+    config: &WorkflowConfig,
+    llm_override: Option<DynLlmClient>,
+) -> Result<WorkflowBundle> {
+    check_tool_pipelines(&config.flow)?;
     let mut agents = AgentRegistry::new();
+    let mut llm_clients = std::collections::HashMap::new();
+    let decorators = AgentDecoratorRegistry::with_builtins();
     for profile in &config.agents {
-        let llm_client = LlmClientFactory::create_client(profile)?;
+        let llm_client = match &llm_override {
+            Some(_) if profile.driver == AgentDriverKind::Rag && profile.endpoint.is_none() => None,
+            Some(llm) => Some(llm.clone()),
+            None => LlmClientFactory::create_client(profile)?,
+        };
+        if let Some(client) = &llm_client {
+            llm_clients.insert(profile.name.clone(), client.clone());
+        }
+        let retriever = match (&profile.driver, &profile.retrieval) {
+            (AgentDriverKind::Rag, Some(retrieval)) => {
+                Some(Arc::new(retrieval.build(llm_client.as_ref())?))
+            }
+            (AgentDriverKind::Rag, None) => {
+                return Err(AgentFlowError::Other(anyhow!(
+                    "Agent `{}` uses the rag driver but has no `retrieval` config",
+                    profile.name
+                )));
+            }
+            _ => None,
+        };
 
         let agent = ConfigDrivenAgent {
             profile: Arc::new(profile.clone()),
             name: Box::leak(profile.name.clone().into_boxed_str()),
+            llm_client: llm_client.clone(),
+            retriever,
             #[cfg(feature = "openai-client")]
-            llm_client,
+            assistant: match &llm_override {
+                Some(_) => None,
+                None => LlmClientFactory::create_assistant(profile)?,
+            },
         };
-        register_agent(&profile.name, Arc::new(agent), &mut agents);
+        // 护栏在最外层，检查装饰器修改后的输出
+        let agent = decorators.apply(Arc::new(agent), &profile.decorators, llm_client.as_ref())?;
+        let agent: Arc<dyn Agent> = match &profile.guardrails {
+            Some(guardrails) => Arc::new(GuardedAgent::new(agent, guardrails.build()?)),
+            None => agent,
+        };
+        register_agent(&profile.name, agent, &mut agents);
+    }
+
+    #[cfg(feature = "script")]
+    for node in &config.flow.nodes {
+        if let GraphNode::Script { name, script } = node {
+            let agent_name = script_agent_name(name);
+            let agent = crate::flow::ScriptAgent::new(agent_name.clone(), script)?;
+            register_agent(&agent_name, Arc::new(agent), &mut agents);
+        }
     }
 
     let mut tools = ToolRegistry::new();
     
     tools.register(Arc::new(crate::tools::DownloaderTool::new()));
     tools.register(Arc::new(crate::tools::ImageGeneratorTool::new()));
+    let http_tool = crate::tools::HttpRequestTool::new();
+    let http_manifest = http_tool.manifest();
+    tools.register_with_manifest(Arc::new(http_tool), http_manifest)?;
+    tools.register(Arc::new(crate::tools::WebCrawlerTool::new()));
+    tools.register(Arc::new(crate::tools::WebReadTool::new()));
+    if let Some(search_config) = crate::tools::WebSearchConfig::from_env() {
+        tools.register(Arc::new(crate::tools::WebSearchTool::new(search_config)));
+    }
+    if let Some(video_config) = crate::media::VideoGenConfig::from_env() {
+        tools.register(Arc::new(crate::tools::VideoGenerateTool::new(
+            video_config.build_client()?,
+        )));
+    }
+    #[cfg(feature = "openai-client")]
+    if let Some(audio_config) = crate::tools::AudioToolConfig::from_env() {
+        let client = audio_config.build_client()?;
+        tools.register(Arc::new(crate::tools::AudioTranscribeTool::new(
+            client.clone(),
+        )));
+        tools.register(Arc::new(crate::tools::AudioSpeakTool::new(client)));
+    }
     
     for profile in &config.tools {
         let tool = ConfigDrivenTool {
@@ -144,7 +477,62 @@ pub fn load_workflow_from_value(value: &Value) -> Result<WorkflowBundle> {
         tools.register(Arc::new(tool));
     }
 
-    let flow = build_flow_from_graph(&config.flow);
+    let mut flow = build_flow_from_graph(&config.flow);
+    if let Some(pii) = &config.flow.pii {
+        flow.pii_redactor = Some(Arc::new(pii.build()?));
+    }
+    for node in &config.flow.nodes {
+        let GraphNode::LlmDecision { name, agent, .. } = node else {
+            continue;
+        };
+        let client = llm_clients.get(agent).cloned().ok_or_else(|| {
+            AgentFlowError::Other(anyhow!(
+                "LLM decision node `{}` references agent `{}` without an LLM client",
+                name,
+                agent
+            ))
+        })?;
+        if let Some(FlowNodeKind::LlmDecision(decision)) =
+            flow.nodes.get_mut(name).map(|node| &mut node.kind)
+        {
+            decision.client = Some(client);
+        }
+    }
+    for node in &config.flow.nodes {
+        let GraphNode::GroupChat {
+            name,
+            selector: Some(agent),
+            ..
+        } = node
+        else {
+            continue;
+        };
+        let client = llm_clients.get(agent).cloned().ok_or_else(|| {
+            AgentFlowError::Other(anyhow!(
+                "group chat node `{}` selects speakers with agent `{}` without an LLM client",
+                name,
+                agent
+            ))
+        })?;
+        if let Some(FlowNodeKind::GroupChat(chat)) =
+            flow.nodes.get_mut(name).map(|node| &mut node.kind)
+        {
+            chat.selector = Some(client);
+        }
+    }
+    if let Some(injection) = &config.flow.injection {
+        if flow.node(&injection.quarantine).is_none() {
+            return Err(AgentFlowError::InvalidConfig {
+                path: "flow.injection.quarantine".to_string(),
+                message: format!("quarantine node `{}` not found", injection.quarantine),
+            });
+        }
+        let classifier = injection
+            .classifier_agent
+            .as_ref()
+            .and_then(|agent| llm_clients.get(agent).cloned());
+        flow.injection_guard = Some(Arc::new(injection.build(classifier)?));
+    }
 
     Ok(WorkflowBundle {
         flow,
@@ -1,27 +1,51 @@
 // Flow 模块 - 工作流定义和执行
 
+#[cfg(feature = "unstable")]
 pub mod agent;
+#[cfg(not(feature = "unstable"))]
+pub(crate) mod agent;
+pub mod analysis;
 pub mod builder;
 pub mod conditions;
 pub mod config;
+#[cfg(feature = "unstable")]
 pub mod constants;
+#[cfg(not(feature = "unstable"))]
+pub(crate) mod constants;
+pub mod expr;
+pub mod import;
 pub mod loader;
 pub mod nodes;
+pub mod outputs;
 pub mod registry;
+#[cfg(feature = "script")]
+pub mod script;
+#[cfg(feature = "unstable")]
 pub mod services;
+#[cfg(not(feature = "unstable"))]
+pub(crate) mod services;
+pub mod transform;
 pub mod types;
 
 // 重新导出核心类型
+pub use analysis::FlowAnalysis;
 pub use builder::FlowBuilder;
 pub use conditions::{
     condition_always, condition_from_fn, condition_state_absent, condition_state_equals,
     condition_state_exists, condition_state_not_equals, loop_condition_always,
-    loop_condition_from_fn, ConditionFuture, LoopContinuation, LoopContinuationFuture,
-    TransitionCondition,
+    loop_condition_from_fn, ConditionFuture, ConditionInfo, LoopContinuation,
+    LoopContinuationFuture, TransitionCondition,
 };
+pub use expr::{condition_expr, ConditionExpr};
 pub use nodes::{
-    DecisionBranch, DecisionNode, DecisionPolicy, FlowNode, FlowNodeKind, JoinNode, JoinStrategy,
-    LoopNode, ToolNode,
+    AwaitEventNode, DebateNode, DecisionBranch, DecisionNode, DecisionPolicy, ExperimentNode,
+    ExperimentVariant, FlowNode, FlowNodeKind, GroupChatNode, ImageGenNode, JoinNode, JoinStrategy,
+    JoinTimeoutPolicy, LlmDecisionBranch, LlmDecisionNode, LoopNode, MapNode, MemoizePolicy,
+    SubFlowNode, ToolAggregate, ToolNode, WaitNode, WaitUntilState,
 };
+pub use outputs::{FlowOutput, FlowOutputSource};
 pub use registry::FlowRegistry;
+#[cfg(feature = "script")]
+pub use script::{condition_script, ConditionScript, ScriptAgent};
+pub use transform::{TransformNode, TransformOp};
 pub use types::{Flow, FlowParameter, FlowParameterKind, FlowTransition, FlowVariable};
@@ -1,7 +1,11 @@
-use crate::flow::conditions::{LoopContinuation, TransitionCondition};
+use crate::agent::AgentMessage;
+use crate::flow::conditions::{ConditionInfo, LoopContinuation, TransitionCondition};
+use crate::flow::transform::TransformNode;
+use crate::llm::{DynLlmClient, ImageGenConfig, ImageGenRequest};
+use serde::Deserialize;
 use serde_json::Value;
 
-/// Flow 节点类型定义
+// Flow 节点类型定义
 
 /// Flow 节点
 #[derive(Clone, Debug)]
@@ -17,9 +21,19 @@ pub enum FlowNodeKind {
     Agent(String),
     Terminal,
     Decision(DecisionNode),
+    LlmDecision(LlmDecisionNode),
+    Experiment(ExperimentNode),
     Join(JoinNode),
     Loop(LoopNode),
+    Map(MapNode),
     Tool(ToolNode),
+    SubFlow(SubFlowNode),
+    ImageGen(ImageGenNode),
+    Transform(TransformNode),
+    Wait(WaitNode),
+    AwaitEvent(AwaitEventNode),
+    GroupChat(GroupChatNode),
+    Debate(DebateNode),
 }
 
 /// 决策节点
@@ -42,6 +56,79 @@ pub struct DecisionBranch {
     pub name: Option<String>,
     pub condition: Option<TransitionCondition>,
     pub target: String,
+    /// 条件描述（explain 模式使用）
+    pub description: Option<ConditionInfo>,
+}
+
+/// LLM 决策节点
+///
+/// 把输入消息和各分支说明交给模型分类，按返回的标签路由；
+/// 标签无法匹配或模型调用失败时进入 `fallback`。
+#[derive(Clone)]
+pub struct LlmDecisionNode {
+    /// 从配置加载时使用 `agent` 对应的 LLM 客户端
+    pub client: Option<DynLlmClient>,
+    pub branches: Vec<LlmDecisionBranch>,
+    pub fallback: String,
+    /// 自定义系统提示词，分支列表和回复格式要求追加在其后
+    pub prompt: Option<String>,
+}
+
+/// LLM 决策分支
+#[derive(Clone, Debug)]
+pub struct LlmDecisionBranch {
+    pub label: String,
+    pub target: String,
+    /// 提供给模型的分支说明
+    pub description: Option<String>,
+}
+
+/// 实验分流节点
+///
+/// 按权重把流量分到各变体；上下文绑定会话时分配结果写入会话存储，同一会话始终进入同一变体。
+#[derive(Clone, Debug)]
+pub struct ExperimentNode {
+    /// 实验名称，用作会话存储键和 metadata 中的标识
+    pub experiment: String,
+    pub variants: Vec<ExperimentVariant>,
+}
+
+/// 实验变体，权重为 0 的变体不再分配新流量
+#[derive(Clone, Debug)]
+pub struct ExperimentVariant {
+    pub name: String,
+    pub target: String,
+    pub weight: u32,
+}
+
+impl ExperimentNode {
+    pub fn variant(&self, name: &str) -> Option<&ExperimentVariant> {
+        self.variants
+            .iter()
+            .find(|variant| variant.name == name && variant.weight > 0)
+    }
+
+    /// 按 `seed` 的哈希落桶，相同 seed 总是得到相同变体
+    pub fn assign(&self, seed: &str) -> Option<&ExperimentVariant> {
+        use sha2::{Digest, Sha256};
+
+        let total: u64 = self.variants.iter().map(|v| u64::from(v.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let digest = Sha256::digest(format!("{}:{}", self.experiment, seed).as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        let mut bucket = u64::from_be_bytes(bytes) % total;
+        self.variants.iter().find(|variant| {
+            let weight = u64::from(variant.weight);
+            if bucket < weight {
+                return true;
+            }
+            bucket -= weight;
+            false
+        })
+    }
 }
 
 /// 合并节点
@@ -49,6 +136,22 @@ pub struct DecisionBranch {
 pub struct JoinNode {
     pub strategy: JoinStrategy,
     pub inbound: Vec<String>,
+    /// 从收到第一条消息开始计时，超时后按 `on_timeout` 处理
+    pub timeout: Option<std::time::Duration>,
+    pub on_timeout: JoinTimeoutPolicy,
+}
+
+/// Join 超时策略
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinTimeoutPolicy {
+    /// 运行失败（`JoinIncomplete`）
+    #[default]
+    Fail,
+    /// 用已收到的消息合并后继续
+    ProceedWithPartial,
+    /// 把部分合并结果发送到指定节点，不走 Join 节点的转换
+    RouteTo(String),
 }
 
 /// 合并策略
@@ -68,11 +171,304 @@ pub struct LoopNode {
     pub exit: Option<String>,
 }
 
+/// Map 节点
+///
+/// 对输入中 `over` 指向的数组逐个元素派发到 `body`，最多 `concurrency` 个同时执行；
+/// `body` 的输出回到 Map 节点，按 `strategy` 收齐后合并为一条消息继续执行。
+#[derive(Clone, Debug)]
+pub struct MapNode {
+    /// JSON Pointer（如 `/documents`），为空时输入本身就是数组
+    pub over: String,
+    pub body: String,
+    pub strategy: JoinStrategy,
+    pub concurrency: usize,
+}
+
 /// 工具节点
 #[derive(Clone, Debug)]
 pub struct ToolNode {
     pub pipeline: String,
     pub params: Option<serde_json::Value>,
+    /// 运行失败时按完成顺序倒序调用的补偿流水线
+    pub compensate: Option<String>,
+    /// 与 `pipeline` 并发执行的其他流水线，参数相同
+    pub parallel: Vec<String>,
+    /// 并发执行多条流水线时的合并策略
+    pub aggregate: ToolAggregate,
+}
+
+/// Tool 节点并发执行多条流水线时的合并策略
+///
+/// `all` / `all_settled` 的输出为 `{"tool_node": <节点>, "results": {<流水线>: <输出>}}`，
+/// 输出能解析为 JSON 时按 JSON 合并。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolAggregate {
+    /// 全部成功后合并，任一失败则节点失败
+    #[default]
+    All,
+    /// 等待全部完成，失败的流水线记为 `{"error": ...}`；全部失败时节点失败
+    AllSettled,
+    /// 采用最先成功的输出，取消其余流水线；全部失败时节点失败
+    First,
+}
+
+/// 子流程节点
+#[derive(Clone, Debug)]
+pub struct SubFlowNode {
+    pub flow: String,
+    pub memoize: Option<MemoizePolicy>,
+}
+
+/// 图片生成节点
+///
+/// 以输入消息（或 `prompt` 模板，`{{input}}` 替换为输入内容）为提示词生成图片，
+/// 轮询期间向运行通道推送进度。
+#[derive(Clone, Debug)]
+pub struct ImageGenNode {
+    pub config: ImageGenConfig,
+    pub prompt: Option<String>,
+    /// 尺寸、风格等参数，`prompt` 字段在执行时替换
+    pub request: ImageGenRequest,
+}
+
+/// 群聊节点
+///
+/// 在 `participants`（Agent 名称）之间轮流发言，最多 `max_turns` 轮，不需要为每轮展开图。
+/// 每位发言人收到此前的完整对话记录，发言写入运行历史；发言包含 `terminate_on`
+/// 或 Agent 返回 Finish 时提前结束。结束后最后一条发言携带完整记录
+/// （`metadata.group_chat`）沿转换继续执行。
+#[derive(Clone)]
+pub struct GroupChatNode {
+    pub participants: Vec<String>,
+    pub max_turns: u32,
+    /// 设置后由模型根据对话记录选择下一位发言人，否则按顺序轮流
+    pub selector: Option<DynLlmClient>,
+    /// 自定义选择发言人的系统提示词，参与者列表和回复格式要求追加在其后
+    pub prompt: Option<String>,
+    pub terminate_on: Option<String>,
+}
+
+impl GroupChatNode {
+    pub const DEFAULT_MAX_TURNS: u32 = 10;
+
+    /// 按顺序轮流发言的群聊
+    pub fn round_robin<I, S>(participants: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            participants: participants.into_iter().map(Into::into).collect(),
+            max_turns: Self::DEFAULT_MAX_TURNS,
+            selector: None,
+            prompt: None,
+            terminate_on: None,
+        }
+    }
+
+    pub fn with_max_turns(mut self, max_turns: u32) -> Self {
+        self.max_turns = max_turns;
+        self
+    }
+
+    pub fn with_selector(mut self, selector: DynLlmClient) -> Self {
+        self.selector = Some(selector);
+        self
+    }
+
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn with_terminate_on(mut self, keyword: impl Into<String>) -> Self {
+        self.terminate_on = Some(keyword.into());
+        self
+    }
+}
+
+/// 辩论 / 评审节点
+///
+/// `proposer` 给出方案，`critic` 评审；未通过时把上一版方案和评审意见交回 `proposer` 修改，
+/// 直到评审通过或达到 `max_rounds`。结束后最后一版方案携带每轮记录（`metadata.debate`）
+/// 沿转换继续执行。
+#[derive(Clone, Debug)]
+pub struct DebateNode {
+    pub proposer: String,
+    pub critic: String,
+    pub max_rounds: u32,
+    /// 评审内容包含该关键字（忽略大小写）即视为通过
+    pub approval: String,
+}
+
+impl DebateNode {
+    pub const DEFAULT_MAX_ROUNDS: u32 = 3;
+    pub const DEFAULT_APPROVAL: &'static str = "approved";
+
+    pub fn new(proposer: impl Into<String>, critic: impl Into<String>) -> Self {
+        Self {
+            proposer: proposer.into(),
+            critic: critic.into(),
+            max_rounds: Self::DEFAULT_MAX_ROUNDS,
+            approval: Self::DEFAULT_APPROVAL.to_string(),
+        }
+    }
+
+    pub fn with_max_rounds(mut self, max_rounds: u32) -> Self {
+        self.max_rounds = max_rounds;
+        self
+    }
+
+    pub fn with_approval(mut self, keyword: impl Into<String>) -> Self {
+        self.approval = keyword.into();
+        self
+    }
+
+    /// 评审是否通过：`metadata.approved` 为布尔值时以其为准，否则查找通过关键字
+    pub fn is_approved(&self, critique: &AgentMessage) -> bool {
+        if let Some(approved) = critique
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("approved"))
+            .and_then(Value::as_bool)
+        {
+            return approved;
+        }
+        critique
+            .content
+            .to_lowercase()
+            .contains(&self.approval.to_lowercase())
+    }
+}
+
+/// 等待节点
+///
+/// 固定延迟后转发输入消息，或轮询状态直到条件满足后转发；等待只挂起节点所在的任务，
+/// 不占用 Agent 或工具。
+#[derive(Clone, Debug)]
+pub enum WaitNode {
+    Delay(std::time::Duration),
+    UntilState(WaitUntilState),
+}
+
+impl WaitNode {
+    pub fn delay(duration: std::time::Duration) -> Self {
+        Self::Delay(duration)
+    }
+}
+
+impl From<WaitUntilState> for WaitNode {
+    fn from(wait: WaitUntilState) -> Self {
+        Self::UntilState(wait)
+    }
+}
+
+/// 轮询等待状态：`key` 存在（设置 `value` 时须相等）即满足
+#[derive(Clone, Debug)]
+pub struct WaitUntilState {
+    pub key: String,
+    pub value: Option<String>,
+    pub poll_interval: std::time::Duration,
+    pub timeout: Option<std::time::Duration>,
+    /// 超时后把输入消息转到该节点；未设置时运行失败（`WaitTimedOut`）
+    pub on_timeout: Option<String>,
+}
+
+impl WaitUntilState {
+    pub const DEFAULT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            value: None,
+            poll_interval: Self::DEFAULT_POLL_INTERVAL,
+            timeout: None,
+            on_timeout: None,
+        }
+    }
+
+    pub fn with_value(mut self, value: impl Into<String>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    pub fn with_poll_interval(mut self, interval: std::time::Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_on_timeout(mut self, target: impl Into<String>) -> Self {
+        self.on_timeout = Some(target.into());
+        self
+    }
+
+    /// 状态值是否满足条件
+    pub fn is_satisfied(&self, current: Option<&str>) -> bool {
+        match (current, &self.value) {
+            (Some(current), Some(expected)) => current == expected,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// 外部事件节点
+///
+/// 签发恢复令牌后挂起当前 trace，`FlowExecutor::deliver_event(token, payload)` 投递后以载荷为输出继续；
+/// 令牌写入状态 `token_key`（默认 `<节点名>.resume_token`），并通过运行通道推送 `awaiting_event`。
+#[derive(Clone, Debug, Default)]
+pub struct AwaitEventNode {
+    pub token_key: Option<String>,
+    /// 载荷另存到该状态键
+    pub store_as: Option<String>,
+    pub timeout: Option<std::time::Duration>,
+    /// 超时后把输入消息转到该节点；未设置时运行失败（`WaitTimedOut`）
+    pub on_timeout: Option<String>,
+}
+
+impl AwaitEventNode {
+    pub fn with_token_key(mut self, key: impl Into<String>) -> Self {
+        self.token_key = Some(key.into());
+        self
+    }
+
+    pub fn with_store_as(mut self, key: impl Into<String>) -> Self {
+        self.store_as = Some(key.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_on_timeout(mut self, target: impl Into<String>) -> Self {
+        self.on_timeout = Some(target.into());
+        self
+    }
+
+    /// 保存恢复令牌的状态键
+    pub fn token_key(&self, node: &str) -> String {
+        self.token_key
+            .clone()
+            .unwrap_or_else(|| format!("{}.resume_token", node))
+    }
+}
+
+/// 子流程缓存策略
+///
+/// 标记子流程对输入是纯函数：相同输入（消息内容 + `input_keys` 对应的状态值）
+/// 在 TTL 内直接复用之前的结果。
+#[derive(Clone, Debug, Default)]
+pub struct MemoizePolicy {
+    pub ttl: Option<std::time::Duration>,
+    pub input_keys: Vec<String>,
 }
 
 use std::fmt;
@@ -92,6 +488,30 @@ impl fmt::Debug for DecisionBranch {
             .field("name", &self.name)
             .field("target", &self.target)
             .field("has_condition", &self.condition.as_ref().map(|_| true))
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+impl fmt::Debug for LlmDecisionNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LlmDecisionNode")
+            .field("has_client", &self.client.is_some())
+            .field("branches", &self.branches)
+            .field("fallback", &self.fallback)
+            .field("prompt", &self.prompt)
+            .finish()
+    }
+}
+
+impl fmt::Debug for GroupChatNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GroupChatNode")
+            .field("participants", &self.participants)
+            .field("max_turns", &self.max_turns)
+            .field("has_selector", &self.selector.is_some())
+            .field("prompt", &self.prompt)
+            .field("terminate_on", &self.terminate_on)
             .finish()
     }
 }
@@ -106,3 +526,217 @@ impl fmt::Debug for LoopNode {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debate_approval() {
+        let debate = DebateNode::new("writer", "critic").with_approval("LGTM");
+        assert!(debate.is_approved(&AgentMessage::system("lgtm, ship it")));
+        assert!(!debate.is_approved(&AgentMessage::system("needs work")));
+
+        let mut critique = AgentMessage::system("LGTM but");
+        critique.metadata = Some(serde_json::json!({ "approved": false }));
+        assert!(!debate.is_approved(&critique));
+        critique.metadata = Some(serde_json::json!({ "approved": true }));
+        assert!(debate.is_approved(&critique));
+    }
+
+    #[tokio::test]
+    async fn test_debate_until_approved() {
+        use crate::agent::builtin::{CoderAgent, ReviewerAgent};
+        use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentRegistry};
+        use crate::flow::FlowBuilder;
+        use crate::runtime::FlowExecutor;
+        use crate::state::{FlowContext, MemoryStore};
+        use crate::tools::ToolRegistry;
+        use std::sync::Arc;
+
+        /// 按轮次写草稿，第二轮起带上收到的评审意见
+        struct Writer;
+
+        #[async_trait::async_trait]
+        impl Agent for Writer {
+            fn name(&self) -> &'static str {
+                "writer"
+            }
+
+            async fn on_message(
+                &self,
+                message: AgentMessage,
+                _ctx: &AgentContext<'_>,
+            ) -> crate::error::Result<AgentAction> {
+                let round = &message.metadata.unwrap()["debate"]["round"];
+                let fixed = message.content.contains("Critique:\ntoo short");
+                Ok(AgentAction::Continue {
+                    message: Some(AgentMessage::system(format!("draft {round} fixed={fixed}"))),
+                })
+            }
+        }
+
+        /// 草稿到第 `approve_at` 轮才通过
+        struct Critic(u32);
+
+        #[async_trait::async_trait]
+        impl Agent for Critic {
+            fn name(&self) -> &'static str {
+                "critic"
+            }
+
+            async fn on_message(
+                &self,
+                message: AgentMessage,
+                _ctx: &AgentContext<'_>,
+            ) -> crate::error::Result<AgentAction> {
+                let reply = if message.content.starts_with(&format!("draft {}", self.0)) {
+                    "Approved"
+                } else {
+                    "too short"
+                };
+                Ok(AgentAction::Continue {
+                    message: Some(AgentMessage::system(reply)),
+                })
+            }
+        }
+
+        async fn run(debate: DebateNode, approve_at: u32) -> AgentMessage {
+            let mut agents = AgentRegistry::new();
+            register_agent("writer", Arc::new(Writer), &mut agents);
+            register_agent("critic", Arc::new(Critic(approve_at)), &mut agents);
+            register_agent("coder", Arc::new(CoderAgent::new("reviewer")), &mut agents);
+            register_agent(
+                "reviewer",
+                Arc::new(ReviewerAgent::new("coder")),
+                &mut agents,
+            );
+            let mut builder = FlowBuilder::new("essay");
+            builder
+                .add_debate_node("debate", debate)
+                .add_terminal_node("done")
+                .connect("debate", "done")
+                .set_start("debate");
+            let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new());
+            let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+            let execution = executor
+                .start(ctx, AgentMessage::user("hello"))
+                .await
+                .unwrap();
+            assert_eq!(execution.last_node, "done");
+            execution.last_message.unwrap()
+        }
+
+        let message = run(DebateNode::new("writer", "critic"), 2).await;
+        assert_eq!(message.content, "draft 2 fixed=true");
+        let debate = &message.metadata.unwrap()["debate"];
+        assert_eq!(debate["rounds"], 2);
+        assert_eq!(debate["converged"], true);
+        assert_eq!(debate["history"][0]["critique"], "too short");
+
+        let message = run(DebateNode::new("writer", "critic").with_max_rounds(2), 5).await;
+        assert_eq!(message.content, "draft 2 fixed=true");
+        assert_eq!(message.metadata.unwrap()["debate"]["converged"], false);
+
+        // 内置的 coder / reviewer 也可以直接作为一对参与者
+        let message = run(DebateNode::new("coder", "reviewer"), 0).await;
+        assert!(message.content.contains("println!(\"hello\")"));
+        assert_eq!(message.metadata.unwrap()["debate"]["converged"], true);
+    }
+
+    #[test]
+    fn test_experiment_assignment() {
+        let variant = |name: &str, weight| ExperimentVariant {
+            name: name.to_string(),
+            target: format!("{}_agent", name),
+            weight,
+        };
+        let experiment = ExperimentNode {
+            experiment: "prompt_v2".to_string(),
+            variants: vec![
+                variant("control", 3),
+                variant("treatment", 1),
+                variant("off", 0),
+            ],
+        };
+        let mut counts = std::collections::HashMap::new();
+        for i in 0..2000 {
+            let chosen = experiment.assign(&format!("session-{}", i)).unwrap();
+            *counts.entry(chosen.name.as_str()).or_insert(0) += 1;
+        }
+        assert!(!counts.contains_key("off"));
+        assert!((1300..1700).contains(&counts["control"]), "{:?}", counts);
+        assert_eq!(
+            experiment.assign("session-7").unwrap().name,
+            experiment.assign("session-7").unwrap().name
+        );
+        assert!(experiment.variant("off").is_none());
+
+        let disabled = ExperimentNode {
+            experiment: "none".to_string(),
+            variants: vec![variant("off", 0)],
+        };
+        assert!(disabled.assign("s").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_session_assignment_is_sticky() {
+        use crate::agent::{AgentMessage, AgentRegistry};
+        use crate::flow::FlowBuilder;
+        use crate::runtime::FlowExecutor;
+        use crate::state::{FlowContext, MemoryStore};
+        use crate::tools::ToolRegistry;
+        use std::sync::Arc;
+
+        let executor = |weights: [u32; 2]| {
+            let mut builder = FlowBuilder::new("chat");
+            builder
+                .add_experiment_node(
+                    "split",
+                    ExperimentNode {
+                        experiment: "prompt_v2".to_string(),
+                        variants: vec![
+                            ExperimentVariant {
+                                name: "a".to_string(),
+                                target: "prompt_a".to_string(),
+                                weight: weights[0],
+                            },
+                            ExperimentVariant {
+                                name: "b".to_string(),
+                                target: "prompt_b".to_string(),
+                                weight: weights[1],
+                            },
+                        ],
+                    },
+                )
+                .add_terminal_node("prompt_a")
+                .add_terminal_node("prompt_b")
+                .set_start("split");
+            FlowExecutor::new(builder.build(), AgentRegistry::new(), ToolRegistry::new())
+        };
+        let store = Arc::new(MemoryStore::new());
+        let ctx = || Arc::new(FlowContext::new(store.clone()).with_session("user-42"));
+
+        let first = executor([1, 1])
+            .start(ctx(), AgentMessage::user("hi"))
+            .await
+            .unwrap();
+        let assigned = first.last_message.unwrap().metadata.unwrap()["experiment"].clone();
+        assert_eq!(assigned["sticky"], false);
+
+        // 调整权重后同一会话仍进入原变体
+        let weights = if assigned["variant"] == "a" {
+            [1, 99]
+        } else {
+            [99, 1]
+        };
+        let second = executor(weights)
+            .start(ctx(), AgentMessage::user("again"))
+            .await
+            .unwrap();
+        assert_eq!(second.last_node, first.last_node);
+        let experiment = &second.last_message.unwrap().metadata.unwrap()["experiment"];
+        assert_eq!(experiment["variant"], assigned["variant"]);
+        assert_eq!(experiment["sticky"], true);
+    }
+}
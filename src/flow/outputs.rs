@@ -0,0 +1,185 @@
+//! Flow 输出声明：运行结束后从状态或最终消息中提取命名输出，并按 Schema 校验
+
+use serde_json::{Map, Value};
+
+use super::types::Flow;
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::schema::{validate_schema, validate_value, Schema, SchemaError};
+use crate::state::FlowContext;
+use crate::utils::JsonPath;
+
+/// 输出的取值来源
+#[derive(Clone, Debug)]
+pub enum FlowOutputSource {
+    /// 状态键；值能解析为 JSON 时按 JSON 返回，否则为字符串
+    State(String),
+    /// 最终消息内容（解析为 JSON）中的 JSONPath
+    MessagePath(JsonPath),
+}
+
+/// Flow 输出声明
+#[derive(Clone, Debug)]
+pub struct FlowOutput {
+    pub name: String,
+    pub source: FlowOutputSource,
+    /// 为字符串时引用 `register_schema` 注册的 Schema，为对象时按内联 Schema 校验
+    pub schema: Option<Value>,
+}
+
+impl FlowOutput {
+    pub fn from_state(name: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: FlowOutputSource::State(key.into()),
+            schema: None,
+        }
+    }
+
+    pub fn from_message_path(name: impl Into<String>, path: &str) -> Result<Self> {
+        Ok(Self {
+            name: name.into(),
+            source: FlowOutputSource::MessagePath(JsonPath::parse(path)?),
+            schema: None,
+        })
+    }
+
+    pub fn with_schema(mut self, schema: Value) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// 提取输出值，找不到时为 null
+    async fn extract(&self, ctx: &FlowContext, message: Option<&AgentMessage>) -> Result<Value> {
+        let value = match &self.source {
+            FlowOutputSource::State(key) => ctx
+                .store()
+                .get(key)
+                .await?
+                .map(|raw| serde_json::from_str(&raw).unwrap_or(Value::String(raw))),
+            FlowOutputSource::MessagePath(path) => message.and_then(|message| {
+                let content = serde_json::from_str(&message.content)
+                    .unwrap_or_else(|_| Value::String(message.content.clone()));
+                path.extract(&content)
+            }),
+        };
+        Ok(value.unwrap_or(Value::Null))
+    }
+
+    fn validate(&self, value: &Value) -> Result<()> {
+        let result = match &self.schema {
+            None => return Ok(()),
+            Some(Value::String(name)) => validate_schema(name, value),
+            Some(inline) => {
+                let schema: Schema = serde_json::from_value(inline.clone())
+                    .map_err(|e| self.invalid(format!("has an invalid schema: {}", e)))?;
+                validate_value(&schema, value, &mut Vec::new())
+            }
+        };
+        result.map_err(|e| match e {
+            SchemaError::Validation { message, path } if !path.is_empty() => {
+                self.invalid(format!("{} at `{}`", message, path.join(".")))
+            }
+            other => self.invalid(other.to_string()),
+        })
+    }
+
+    fn invalid(&self, message: String) -> AgentFlowError {
+        AgentFlowError::InvalidOutput {
+            name: self.name.clone(),
+            message,
+        }
+    }
+}
+
+impl Flow {
+    pub fn outputs(&self) -> &[FlowOutput] {
+        &self.outputs
+    }
+
+    /// 按声明提取输出并校验 Schema，`message` 为运行的最终消息
+    pub async fn extract_outputs(
+        &self,
+        ctx: &FlowContext,
+        message: Option<&AgentMessage>,
+    ) -> Result<Map<String, Value>> {
+        let mut outputs = Map::new();
+        for output in &self.outputs {
+            let value = output.extract(ctx, message).await?;
+            output.validate(&value)?;
+            outputs.insert(output.name.clone(), value);
+        }
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryStore;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_extract_outputs_from_state_and_message() {
+        let mut flow = crate::flow::FlowBuilder::new("report");
+        flow.add_terminal_node("done")
+            .set_start("done")
+            .declare_output(FlowOutput::from_state("score", "review.score"))
+            .declare_output(
+                FlowOutput::from_message_path("title", "$.draft.title")
+                    .unwrap()
+                    .with_schema(json!({ "type": "string" })),
+            )
+            .declare_output(FlowOutput::from_state("missing", "nothing"));
+        let flow = flow.build();
+
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+        ctx.store().set("review.score", "0.9".into()).await.unwrap();
+        let message = AgentMessage::system(json!({ "draft": { "title": "Q3" } }).to_string());
+        let outputs = flow.extract_outputs(&ctx, Some(&message)).await.unwrap();
+        assert_eq!(
+            Value::Object(outputs),
+            json!({ "score": 0.9, "title": "Q3", "missing": null })
+        );
+
+        let err = flow.extract_outputs(&ctx, None).await.unwrap_err();
+        assert!(matches!(
+            err,
+            AgentFlowError::InvalidOutput { ref name, .. } if name == "title"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execution_carries_configured_outputs() {
+        let config = json!({
+            "agents": [{ "name": "writer", "driver": "echo", "prompt": "write" }],
+            "flow": {
+                "name": "draft",
+                "start": "write",
+                "outputs": [
+                    { "name": "draft", "from_message_path": "$.response", "schema": { "type": "string" } },
+                    { "name": "input", "from_message_path": "$.raw" }
+                ],
+                "nodes": [
+                    { "kind": "agent", "name": "write", "agent": "writer" },
+                    { "kind": "terminal", "name": "done" }
+                ],
+                "transitions": [{ "from": "write", "to": "done" }]
+            }
+        });
+        let bundle = crate::flow::loader::load_workflow_from_value(&config).unwrap();
+        let executor = crate::runtime::FlowExecutor::new(bundle.flow, bundle.agents, bundle.tools);
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let input = json!({ "raw": "hello", "steps": [] }).to_string();
+        let execution = executor
+            .start(ctx, AgentMessage::user(input))
+            .await
+            .unwrap();
+        assert_eq!(execution.outputs["input"], "hello");
+        assert!(execution.outputs["draft"]
+            .as_str()
+            .unwrap()
+            .contains("hello"));
+    }
+}
@@ -1,28 +1,230 @@
+use crate::error::{AgentFlowError, Result};
+use crate::flow::loader::{load_workflow_from_str, WorkflowBundle};
 use crate::flow::types::Flow;
-use std::collections::HashMap;
+use anyhow::anyhow;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Flow 注册表
+///
+/// 同名工作流可以注册多个版本，默认使用最新版本，`pin` 可以固定到指定版本（如回滚或灰度）。
 #[derive(Default)]
 pub struct FlowRegistry {
-    flows: HashMap<String, Flow>,
+    flows: HashMap<String, BTreeMap<u32, Flow>>,
+    workflows: HashMap<String, BTreeMap<u32, Arc<WorkflowBundle>>>,
+    pins: HashMap<String, u32>,
 }
 
 impl FlowRegistry {
     pub fn new() -> Self {
         Self {
             flows: HashMap::new(),
+            workflows: HashMap::new(),
+            pins: HashMap::new(),
         }
     }
 
+    /// 注册工作流（同名同版本时替换）
     pub fn register(&mut self, flow: Flow) {
-        self.flows.insert(flow.name.clone(), flow);
+        self.flows
+            .entry(flow.name.clone())
+            .or_default()
+            .insert(flow.version, flow);
     }
 
+    /// 当前版本：固定的版本，否则为最新版本
     pub fn get(&self, name: &str) -> Option<&Flow> {
-        self.flows.get(name)
+        self.get_version(name, self.current_version(name)?)
     }
 
+    pub fn get_version(&self, name: &str, version: u32) -> Option<&Flow> {
+        self.flows.get(name)?.get(&version)
+    }
+
+    /// 已注册的版本，从小到大
+    pub fn versions(&self, name: &str) -> Vec<u32> {
+        self.flows
+            .get(name)
+            .map(|versions| versions.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// 每个工作流的当前版本
     pub fn list(&self) -> impl Iterator<Item = &Flow> {
-        self.flows.values()
+        self.flows.keys().filter_map(|name| self.get(name))
+    }
+
+    /// 从 JSON 配置加载的工作流（包含 Agent 与工具注册表），取当前版本
+    pub fn workflow(&self, name: &str) -> Option<Arc<WorkflowBundle>> {
+        self.workflow_version(name, self.current_version(name)?)
+    }
+
+    pub fn workflow_version(&self, name: &str, version: u32) -> Option<Arc<WorkflowBundle>> {
+        self.workflows.get(name)?.get(&version).cloned()
+    }
+
+    /// 注册工作流（同名同版本时替换）
+    pub fn register_workflow(&mut self, bundle: WorkflowBundle) {
+        let name = bundle.flow.name.clone();
+        let version = bundle.flow.version;
+        self.register(bundle.flow.clone());
+        self.workflows
+            .entry(name)
+            .or_default()
+            .insert(version, Arc::new(bundle));
+    }
+
+    /// 把 `get`/`workflow` 固定到指定版本，版本未注册时返回错误
+    pub fn pin(&mut self, name: &str, version: u32) -> Result<()> {
+        if self.get_version(name, version).is_none() {
+            return Err(AgentFlowError::FlowNotRegistered(format!(
+                "{name}@{version}"
+            )));
+        }
+        self.pins.insert(name.to_string(), version);
+        Ok(())
+    }
+
+    /// 取消固定，恢复使用最新版本
+    pub fn unpin(&mut self, name: &str) {
+        self.pins.remove(name);
+    }
+
+    fn current_version(&self, name: &str) -> Option<u32> {
+        match self.pins.get(name) {
+            Some(version) => Some(*version),
+            None => self.flows.get(name)?.keys().next_back().copied(),
+        }
+    }
+
+    /// 从工作流 JSON 文件或目录（读取其中所有 `.json` 文件）加载并替换同名同版本的工作流
+    ///
+    /// 任一文件加载失败时返回错误且不修改注册表。返回加载的工作流名称。
+    pub fn reload_from(&mut self, path: impl AsRef<Path>) -> Result<Vec<String>> {
+        let bundles = Self::load_bundles(path.as_ref())?;
+        let mut names = Vec::with_capacity(bundles.len());
+        for bundle in bundles {
+            names.push(bundle.flow.name.clone());
+            self.register_workflow(bundle);
+        }
+        Ok(names)
+    }
+
+    /// 监听工作流文件或目录，变化时重新加载（需启用 `hot-reload` feature）
+    ///
+    /// 重新加载会构建全新的注册表，已删除的文件对应的工作流随之移除；配置有误时保留旧版本。
+    #[cfg(feature = "hot-reload")]
+    pub fn watch(
+        path: impl AsRef<Path>,
+    ) -> Result<crate::utils::watch::ReloadHandle<FlowRegistry>> {
+        let load = |path: &Path| {
+            let mut registry = FlowRegistry::new();
+            registry.reload_from(path)?;
+            Ok(registry)
+        };
+        let initial = load(path.as_ref())?;
+        crate::utils::watch::watch_path(path.as_ref(), initial, load)
+    }
+
+    fn load_bundles(path: &Path) -> Result<Vec<WorkflowBundle>> {
+        let read_error = |path: &Path, e: std::io::Error| {
+            AgentFlowError::Other(anyhow!("Failed to read `{}`: {}", path.display(), e))
+        };
+        let files: Vec<PathBuf> = if path.is_dir() {
+            let mut files = Vec::new();
+            for entry in fs::read_dir(path).map_err(|e| read_error(path, e))? {
+                let file = entry.map_err(|e| read_error(path, e))?.path();
+                if file.extension().is_some_and(|ext| ext == "json") {
+                    files.push(file);
+                }
+            }
+            files.sort();
+            files
+        } else {
+            vec![path.to_path_buf()]
+        };
+
+        files
+            .iter()
+            .map(|file| {
+                let content = fs::read_to_string(file).map_err(|e| read_error(file, e))?;
+                load_workflow_from_str(&content).map_err(|e| {
+                    AgentFlowError::Other(anyhow!("Invalid workflow `{}`: {}", file.display(), e))
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workflow(name: &str) -> String {
+        format!(
+            r#"{{ "flow": {{ "name": "{name}", "start": "done",
+                "nodes": [{{ "kind": "terminal", "name": "done" }}] }} }}"#
+        )
+    }
+
+    #[test]
+    fn test_reload_from_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.json"), workflow("alpha")).unwrap();
+        fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let mut registry = FlowRegistry::new();
+        assert_eq!(registry.reload_from(dir.path()).unwrap(), vec!["alpha"]);
+        assert!(registry.get("alpha").is_some());
+        assert!(registry.workflow("alpha").is_some());
+
+        fs::write(dir.path().join("b.json"), "{ broken").unwrap();
+        assert!(registry.reload_from(dir.path()).is_err());
+        assert!(registry.get("alpha").is_some());
+    }
+
+    #[test]
+    fn test_versions_and_pin() {
+        let mut registry = FlowRegistry::new();
+        for version in [1, 3, 2] {
+            let bundle = load_workflow_from_str(&workflow("alpha").replacen(
+                r#""start""#,
+                &format!(r#""version": {version}, "start""#),
+                1,
+            ))
+            .unwrap();
+            registry.register_workflow(bundle);
+        }
+        assert_eq!(registry.versions("alpha"), vec![1, 2, 3]);
+        assert_eq!(registry.get("alpha").unwrap().version, 3);
+
+        registry.pin("alpha", 2).unwrap();
+        assert_eq!(registry.workflow("alpha").unwrap().flow.version, 2);
+        assert_eq!(registry.list().count(), 1);
+        assert!(registry.pin("alpha", 4).is_err());
+
+        registry.unpin("alpha");
+        assert_eq!(registry.get("alpha").unwrap().version, 3);
+        assert_eq!(registry.get_version("alpha", 1).unwrap().version, 1);
+    }
+
+    #[cfg(feature = "hot-reload")]
+    #[tokio::test]
+    async fn test_watch_picks_up_new_workflow() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.json"), workflow("alpha")).unwrap();
+        let handle = FlowRegistry::watch(dir.path()).unwrap();
+        let mut changes = handle.subscribe();
+
+        fs::write(dir.path().join("b.json"), workflow("beta")).unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), changes.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        let registry = handle.registry();
+        assert!(registry.read().get("beta").is_some());
+        assert!(registry.read().get("alpha").is_some());
     }
 }
@@ -0,0 +1,415 @@
+//! rhai 脚本（需要 `script` feature）：转换条件和脚本节点
+
+use crate::agent::{Agent, AgentAction, AgentContext, AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::flow::conditions::{ConditionInfo, TransitionCondition};
+use crate::tools::ToolInvocation;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use rhai::{Dynamic, EvalAltResult};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::mpsc;
+
+/// 单次求值允许的最大操作数，防止死循环阻塞执行器
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// 转换条件脚本
+///
+/// 脚本的最后一个表达式须为布尔值。`state` 是求值时状态的只读快照，值能解析为 JSON 时按 JSON 转换，
+/// 否则为字符串；不存在的键为 `()`，可以配合 `??` 给出默认值。脚本错误或结果不是布尔值时条件不成立。
+///
+/// ```text
+/// state.score >= 0.8 && state.route != "manual"
+/// (state.retries ?? 0) < 3
+/// ```
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ConditionScript {
+    source: String,
+    ast: Arc<rhai::AST>,
+}
+
+fn engine() -> &'static rhai::Engine {
+    static ENGINE: OnceLock<rhai::Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut engine = rhai::Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine
+    })
+}
+
+impl ConditionScript {
+    /// 编译脚本，语法错误在加载配置时报告
+    pub fn parse(source: &str) -> Result<Self> {
+        let ast = engine().compile(source).map_err(|e| {
+            AgentFlowError::Other(anyhow!("Invalid condition script `{}`: {}", source, e))
+        })?;
+        Ok(Self {
+            source: source.trim().to_string(),
+            ast: Arc::new(ast),
+        })
+    }
+
+    /// 脚本原文
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// 以状态快照求值
+    pub fn evaluate(&self, state: &HashMap<String, String>) -> Result<bool> {
+        let state: rhai::Map = state
+            .iter()
+            .map(|(key, raw)| {
+                let value = serde_json::from_str::<Value>(raw)
+                    .ok()
+                    .and_then(|value| rhai::serde::to_dynamic(value).ok())
+                    .unwrap_or_else(|| raw.clone().into());
+                (key.as_str().into(), value)
+            })
+            .collect();
+        let mut scope = rhai::Scope::new();
+        scope.push_constant("state", state);
+        engine()
+            .eval_ast_with_scope::<bool>(&mut scope, &self.ast)
+            .map_err(|e| {
+                AgentFlowError::Other(anyhow!("Condition script `{}` failed: {}", self.source, e))
+            })
+    }
+
+    pub fn describe(&self) -> ConditionInfo {
+        ConditionInfo::new(format!("script `{}`", self.source))
+    }
+
+    /// 转换为转换条件，求值前读取全部状态键
+    pub fn to_condition(&self) -> TransitionCondition {
+        let script = Arc::new(self.clone());
+        Arc::new(move |ctx| {
+            let store = ctx.store();
+            let script = Arc::clone(&script);
+            Box::pin(async move {
+                let mut state = HashMap::new();
+                for key in store.keys("").await.unwrap_or_default() {
+                    if let Ok(Some(value)) = store.get(&key).await {
+                        state.insert(key, value);
+                    }
+                }
+                script.evaluate(&state).unwrap_or_else(|error| {
+                    tracing::warn!(%error, "condition script evaluated to false");
+                    false
+                })
+            })
+        })
+    }
+}
+
+impl fmt::Debug for ConditionScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ConditionScript")
+            .field(&self.source)
+            .finish()
+    }
+}
+
+impl TryFrom<String> for ConditionScript {
+    type Error = AgentFlowError;
+
+    fn try_from(source: String) -> Result<Self> {
+        Self::parse(&source)
+    }
+}
+
+/// 从脚本创建转换条件
+pub fn condition_script(source: &str) -> Result<TransitionCondition> {
+    Ok(ConditionScript::parse(source)?.to_condition())
+}
+
+/// 脚本向节点所在任务发出的请求
+enum HostCall {
+    StateGet(String),
+    StateSet(String, String),
+    CallTool(String, Value),
+}
+
+type HostReply = std::result::Result<Value, String>;
+type HostSender = mpsc::UnboundedSender<(HostCall, std::sync::mpsc::Sender<HostReply>)>;
+
+/// 脚本节点：以 rhai 脚本作为 `on_message`，用于整理载荷、计算汇总等不需要 LLM 的衔接逻辑
+///
+/// 脚本中可用：
+/// - `message`：输入消息内容，能解析为 JSON 时按 JSON 转换，否则为字符串
+/// - `state_get(key)` / `state_set(key, value)`：读写状态，非字符串的值按 JSON 保存
+/// - `call_tool(name, input)`：调用已注册的工具，返回响应内容
+///
+/// 脚本的结果作为输出消息内容（字符串原样，其余序列化为 JSON），结果为 `()` 时原样转发输入消息。
+/// 脚本在阻塞线程池中执行，状态和工具调用交回节点所在的异步任务完成。
+pub struct ScriptAgent {
+    name: &'static str,
+    source: String,
+    ast: Arc<rhai::AST>,
+}
+
+impl ScriptAgent {
+    pub fn new(name: impl Into<String>, source: &str) -> Result<Self> {
+        let name = name.into();
+        let ast = engine().compile(source).map_err(|e| {
+            AgentFlowError::Other(anyhow!("Invalid script for node `{}`: {}", name, e))
+        })?;
+        Ok(Self {
+            name: Box::leak(name.into_boxed_str()),
+            source: source.to_string(),
+            ast: Arc::new(ast),
+        })
+    }
+
+    /// 脚本原文
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    async fn host_call(&self, call: HostCall, ctx: &AgentContext<'_>) -> HostReply {
+        let store = ctx.flow().store();
+        let result = match call {
+            HostCall::StateGet(key) => store
+                .get(&key)
+                .await
+                .map(|value| value.map(|raw| parse_content(&raw)).unwrap_or(Value::Null)),
+            HostCall::StateSet(key, value) => store.set(&key, value).await.map(|_| Value::Null),
+            HostCall::CallTool(name, input) => ctx
+                .runtime
+                .call_tool(&name, ToolInvocation::new(name.clone(), input))
+                .await
+                .map(|response| parse_content(&response.content)),
+        };
+        result.map_err(|e| e.to_string())
+    }
+}
+
+#[async_trait]
+impl Agent for ScriptAgent {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn on_message(
+        &self,
+        message: AgentMessage,
+        ctx: &AgentContext<'_>,
+    ) -> Result<AgentAction> {
+        let input = parse_content(&message.content);
+        let ast = Arc::clone(&self.ast);
+        let (host, mut calls) = mpsc::unbounded_channel();
+        let mut script = tokio::task::spawn_blocking(move || {
+            let engine = script_engine(host);
+            let mut scope = rhai::Scope::new();
+            scope.push_constant(
+                "message",
+                rhai::serde::to_dynamic(input).map_err(|e| e.to_string())?,
+            );
+            let result = engine
+                .eval_ast_with_scope::<Dynamic>(&mut scope, &ast)
+                .map_err(|e| e.to_string())?;
+            rhai::serde::from_dynamic::<Value>(&result).map_err(|e| e.to_string())
+        });
+        // 脚本等待每个请求的回复，因此脚本结束时不会有未处理的请求
+        let result = loop {
+            tokio::select! {
+                Some((call, reply)) = calls.recv() => {
+                    let _ = reply.send(self.host_call(call, ctx).await);
+                }
+                result = &mut script => break result,
+            }
+        };
+        let output = result
+            .map_err(|e| {
+                AgentFlowError::Other(anyhow!("Script node `{}` panicked: {}", self.name, e))
+            })?
+            .map_err(|e| {
+                AgentFlowError::Other(anyhow!("Script node `{}` failed: {}", self.name, e))
+            })?;
+
+        let content = match output {
+            Value::Null => message.content,
+            Value::String(text) => text,
+            other => other.to_string(),
+        };
+        Ok(AgentAction::Continue {
+            message: Some(AgentMessage {
+                id: crate::agent::message::uuid(),
+                role: MessageRole::Agent,
+                from: self.name.to_string(),
+                to: None,
+                content,
+                metadata: message.metadata,
+                attachments: message.attachments,
+            }),
+        })
+    }
+}
+
+/// 为一次脚本运行创建引擎，宿主函数通过 `host` 把请求交给节点任务
+fn script_engine(host: HostSender) -> rhai::Engine {
+    let mut engine = rhai::Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    let call = Arc::new(
+        move |request: HostCall| -> std::result::Result<Dynamic, Box<EvalAltResult>> {
+            let (reply, response) = std::sync::mpsc::channel();
+            host.send((request, reply))
+                .map_err(|_| "script node is no longer running")?;
+            let value = response
+                .recv()
+                .map_err(|_| "script node is no longer running")??;
+            rhai::serde::to_dynamic(value)
+        },
+    );
+
+    let state_get = Arc::clone(&call);
+    engine.register_fn("state_get", move |key: &str| {
+        state_get(HostCall::StateGet(key.to_string()))
+    });
+    let state_set = Arc::clone(&call);
+    engine.register_fn(
+        "state_set",
+        move |key: &str, value: Dynamic| -> std::result::Result<(), Box<EvalAltResult>> {
+            let raw = match rhai::serde::from_dynamic::<Value>(&value)? {
+                Value::String(text) => text,
+                other => other.to_string(),
+            };
+            state_set(HostCall::StateSet(key.to_string(), raw)).map(|_| ())
+        },
+    );
+    engine.register_fn("call_tool", move |name: &str, input: Dynamic| {
+        let input = rhai::serde::from_dynamic::<Value>(&input)?;
+        call(HostCall::CallTool(name.to_string(), input))
+    });
+    engine
+}
+
+/// 能解析为 JSON 的内容按 JSON 返回，否则为字符串
+fn parse_content(content: &str) -> Value {
+    serde_json::from_str(content).unwrap_or_else(|_| Value::String(content.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{FlowContext, MemoryStore};
+
+    #[test]
+    fn test_script_reads_typed_state() {
+        let script = ConditionScript::parse(
+            r#"state.score >= 0.8 && state.route != "manual" && (state.retries ?? 0) < 3"#,
+        )
+        .unwrap();
+        let state = HashMap::from([
+            ("score".to_string(), "0.9".to_string()),
+            ("route".to_string(), "auto".to_string()),
+        ]);
+        assert!(script.evaluate(&state).unwrap());
+
+        let state = HashMap::from([
+            ("score".to_string(), "0.9".to_string()),
+            ("route".to_string(), "auto".to_string()),
+            ("retries".to_string(), "3".to_string()),
+        ]);
+        assert!(!script.evaluate(&state).unwrap());
+
+        assert!(ConditionScript::parse("state.score >=").is_err());
+        let not_bool = ConditionScript::parse("state.route").unwrap();
+        assert!(not_bool.evaluate(&state).is_err());
+        let endless = ConditionScript::parse("loop {}").unwrap();
+        assert!(endless.evaluate(&state).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_script_condition_uses_context_state() {
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+        let condition = condition_script(r#"state.review.approved == true"#).unwrap();
+        assert!(!condition(&ctx).await);
+        ctx.store()
+            .set("review", r#"{"approved":true}"#.into())
+            .await
+            .unwrap();
+        assert!(condition(&ctx).await);
+    }
+
+    #[tokio::test]
+    async fn test_config_routes_with_script_condition() {
+        let config = serde_json::json!({
+            "agents": [{ "name": "writer", "driver": "echo", "prompt": "write" }],
+            "flow": {
+                "name": "review",
+                "start": "route",
+                "nodes": [
+                    { "kind": "decision", "name": "route", "branches": [
+                        { "target": "fast", "condition": { "type": "script", "script": "state.priority > 5" } },
+                        { "target": "slow" }
+                    ] },
+                    { "kind": "agent", "name": "fast", "agent": "writer" },
+                    { "kind": "agent", "name": "slow", "agent": "writer" }
+                ]
+            }
+        });
+        let bundle = crate::flow::loader::load_workflow_from_value(&config).unwrap();
+        let executor = crate::runtime::FlowExecutor::new(bundle.flow, bundle.agents, bundle.tools);
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        ctx.store().set("priority", "8".into()).await.unwrap();
+        let input = serde_json::json!({ "raw": "hello", "steps": [] }).to_string();
+        let execution = executor
+            .start(ctx, crate::agent::AgentMessage::user(input))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "fast");
+
+        let mut invalid = config.clone();
+        invalid["flow"]["nodes"][0]["branches"][0]["condition"]["script"] =
+            "state.priority >".into();
+        assert!(crate::flow::loader::load_workflow_from_value(&invalid).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_script_node_uses_state_and_tools() {
+        let config = serde_json::json!({
+            "tools": [{ "name": "price" }],
+            "flow": {
+                "name": "quote",
+                "start": "quote",
+                "nodes": [
+                    { "kind": "script", "name": "quote", "script": r#"
+                        let response = call_tool("price", #{ item: message.item });
+                        let count = (state_get("quotes") ?? 0) + 1;
+                        state_set("quotes", count);
+                        #{ item: response.input.item, tool: response.tool, count: count }
+                    "# },
+                    { "kind": "terminal", "name": "done" }
+                ],
+                "transitions": [{ "from": "quote", "to": "done" }]
+            }
+        });
+        let bundle = crate::flow::loader::load_workflow_from_value(&config).unwrap();
+        let executor = crate::runtime::FlowExecutor::new(bundle.flow, bundle.agents, bundle.tools);
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        ctx.store().set("quotes", "2".into()).await.unwrap();
+        let input = serde_json::json!({ "item": "tea" }).to_string();
+        let execution = executor
+            .start(ctx.clone(), crate::agent::AgentMessage::user(input))
+            .await
+            .unwrap();
+        let output: Value = serde_json::from_str(&execution.last_message.unwrap().content).unwrap();
+        assert_eq!(
+            output,
+            serde_json::json!({ "item": "tea", "tool": "price", "count": 3 })
+        );
+        assert_eq!(
+            ctx.store().get("quotes").await.unwrap().as_deref(),
+            Some("3")
+        );
+
+        let mut invalid = config.clone();
+        invalid["flow"]["nodes"][0]["script"] = "let x = ;".into();
+        assert!(crate::flow::loader::load_workflow_from_value(&invalid).is_err());
+    }
+}
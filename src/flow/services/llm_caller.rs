@@ -9,7 +9,6 @@ use crate::LlmRequest;
 use futures::StreamExt;
 use serde_json::Value;
 use std::collections::HashMap;
-use std::io::{self, Write};
 
 /// LLM 调用服务
 ///
@@ -86,63 +85,24 @@ impl LlmCaller {
             user: user_input.to_string(),
             temperature,
             metadata: None,
-            image_url: None,
-            image_base64: None,
+            content: Vec::new(),
+            params: profile.params.clone(),
         };
 
-        let role_name = profile.role.as_deref().unwrap_or(&profile.name);
-
-        eprintln!("\n[{}] ⏳ 正在调用 LLM，等待响应...", role_name);
-        io::stderr().flush().ok();
-        io::stdout().flush().ok();
-
-        println!("\n[{}] 📝 响应内容:", role_name);
-        io::stdout().flush().ok();
-
-        print!("  ");
-        io::stdout().flush().ok();
-
         let mut stream = llm_client.complete_stream(llm_request);
         let mut full_response = String::new();
-        let mut has_output = false;
-
-        while let Some(chunk_result) = stream.next().await {
-            match chunk_result {
-                Ok(chunk) => {
-                    if !chunk.content.is_empty() {
-                        print!("{}", chunk.content);
-                        io::stdout().flush().map_err(|e| {
-                            AgentFlowError::Other(anyhow::anyhow!("Failed to flush stdout: {}", e))
-                        })?;
-                        full_response.push_str(&chunk.content);
-                        has_output = true;
-                    }
-                    if chunk.done {
-                        if has_output {
-                            println!();
-                            io::stdout().flush().ok();
-                        }
-                        eprintln!("[{}] ✅ 响应完成", role_name);
-                        io::stderr().flush().ok();
-                        break;
-                    }
-                }
-                Err(e) => {
-                    eprintln!("\n[{}] ❌ 流式输出错误: {}", role_name, e);
-                    io::stderr().flush().ok();
-                    return Err(e);
-                }
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            full_response.push_str(&chunk.content);
+            if chunk.done {
+                break;
             }
         }
 
-        if !has_output {
-            eprintln!("[{}] ⚠️  警告: 没有接收到任何响应内容", role_name);
-            io::stderr().flush().ok();
+        if full_response.is_empty() {
+            tracing::warn!(agent = %profile.name, "LLM returned an empty response");
         }
 
-        io::stdout().flush().ok();
-        io::stderr().flush().ok();
-
         Ok(full_response)
     }
 
@@ -1,13 +1,23 @@
+#[cfg(feature = "openai-client")]
 use crate::config::EnvConfig;
-use crate::error::{AgentFlowError, Result};
+#[cfg(any(feature = "openai-client", feature = "bedrock"))]
+use crate::error::AgentFlowError;
+use crate::error::Result;
 use crate::flow::config::AgentConfig;
 use crate::flow::config::AgentDriverKind;
 #[cfg(feature = "openai-client")]
 use crate::llm::ApiFormat;
-use crate::llm::DynLlmClient;
+#[cfg(feature = "openai-client")]
+use crate::llm::OpenAiAssistantClient;
+#[cfg(feature = "openai-client")]
+use crate::llm::{shared_http_client, HttpPoolConfig};
+use crate::llm::{DynLlmClient, FixtureMode, LlmFixtures, RecordingLlmClient};
 #[cfg(feature = "openai-client")]
 use crate::GenericHttpClient;
+#[cfg(any(feature = "openai-client", feature = "bedrock"))]
 use anyhow::anyhow;
+#[cfg(feature = "openai-client")]
+use serde::Deserialize;
 use std::sync::Arc;
 
 /// LLM 客户端工厂
@@ -25,8 +35,21 @@ use std::sync::Arc;
 /// - `api_key`: API密钥（支持环境变量引用 ${VAR_NAME}）
 ///
 /// **可选字段**:
-/// - `api_format`: API格式（"openai", "qwen", "qwenvision"），不指定则自动推断
+/// - `api_format`: API格式（"openai", "qwen", "qwenvision", "gemini", "azure_openai"），不指定则自动推断
+/// - `metadata.api_version`: Azure OpenAI 的 `api-version`
 /// - `metadata.auth_header`: 自定义认证header（如 "Bearer", "X-API-Key"）
+/// - `metadata.embedding_model` / `metadata.embedding_endpoint`: `embed` 使用的向量模型和端点
+/// - `metadata.max_request_bytes` / `metadata.max_response_bytes`: 请求体和响应体的大小上限（字节）
+/// - `metadata.http`: 连接池大小、超时、代理、CA 证书和 TLS 校验（`HttpPoolConfig`），配置相同的 Agent 共用一个连接池
+///
+/// **`openai_assistant` 驱动**：`endpoint` 为 API 根地址，`metadata.assistant_id` 必填，
+/// `model` 可选（覆盖 Assistant 的模型）；`metadata.poll_interval_ms` / `metadata.run_timeout_secs`
+/// 控制运行状态的轮询间隔和超时。
+///
+/// **`bedrock` 驱动**（需启用 `bedrock` feature）：`model` 为 Bedrock 模型 ID，凭证从
+/// `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY` / `AWS_SESSION_TOKEN` 读取；`metadata.region`
+/// 缺省时读取 `AWS_REGION`，`endpoint` 可选（VPC 终端节点等），`metadata.max_tokens` /
+/// `metadata.embedding_model` 可选。
 ///
 /// ## 示例配置
 ///
@@ -59,7 +82,6 @@ use std::sync::Arc;
 /// - **无法推断格式**: 在metadata中添加 "api_format" 字段
 pub struct LlmClientFactory;
 
-#[cfg(feature = "openai-client")]
 impl LlmClientFactory {
     /// 创建 LLM 客户端
     ///
@@ -70,11 +92,48 @@ impl LlmClientFactory {
     /// ## 返回值
     ///
     /// - `Ok(Some(client))`: 成功创建客户端
-    /// - `Ok(None)`: Echo 驱动，不需要真实客户端
+    /// - `Ok(None)`: Echo 驱动或未配置 endpoint 的 Rag 驱动，不需要真实客户端
     /// - `Err(_)`: 配置错误或创建失败
+    ///
+    /// 设置 `AGENTFLOW_LLM_FIXTURES` 时客户端被 `RecordingLlmClient` 替换：回放模式（默认）
+    /// 只读取录制文件，不需要 API Key；`AGENTFLOW_LLM_FIXTURE_MODE=record` 时调用真实客户端并录制。
     pub fn create_client(profile: &AgentConfig) -> Result<Option<DynLlmClient>> {
+        let Some(fixtures) = LlmFixtures::from_env()? else {
+            return Self::create_provider_client(profile);
+        };
+        match fixtures.mode {
+            FixtureMode::Replay if Self::uses_llm(profile) => {
+                Ok(Some(Arc::new(RecordingLlmClient::replay(fixtures.dir))))
+            }
+            FixtureMode::Replay => Ok(None),
+            FixtureMode::Record => Ok(Self::create_provider_client(profile)?.map(|inner| {
+                Arc::new(RecordingLlmClient::record(inner, fixtures.dir)) as DynLlmClient
+            })),
+        }
+    }
+
+    /// 驱动是否需要 LLM 客户端
+    fn uses_llm(profile: &AgentConfig) -> bool {
+        match profile.driver {
+            AgentDriverKind::Echo => false,
+            AgentDriverKind::Rag => cfg!(feature = "openai-client") && profile.endpoint.is_some(),
+            #[allow(unreachable_patterns)]
+            _ => true,
+        }
+    }
+}
+
+#[cfg(feature = "openai-client")]
+impl LlmClientFactory {
+    fn create_provider_client(profile: &AgentConfig) -> Result<Option<DynLlmClient>> {
         match profile.driver {
             AgentDriverKind::Echo => Ok(None),
+            AgentDriverKind::Rag if profile.endpoint.is_none() => Ok(None),
+            AgentDriverKind::OpenAiAssistant => {
+                Ok(Self::create_assistant(profile)?.map(|client| client as DynLlmClient))
+            }
+            #[cfg(feature = "bedrock")]
+            AgentDriverKind::Bedrock => Self::create_bedrock(profile).map(Some),
             _ => {
                 let api_key = Self::get_api_key(profile)?;
 
@@ -115,7 +174,7 @@ impl LlmClientFactory {
                     .and_then(|v| v.as_str())
                     .map(|s| s.to_string());
 
-                let client = if let Some(auth_header) = auth_header {
+                let mut client = if let Some(auth_header) = auth_header {
                     GenericHttpClient::with_auth_header(
                         endpoint,
                         api_key,
@@ -125,13 +184,106 @@ impl LlmClientFactory {
                     )
                 } else {
                     GenericHttpClient::new(endpoint, api_key, model, format)
+                }
+                .with_http_client(Self::http_client(profile)?);
+
+                let metadata_str = |key: &str| {
+                    profile
+                        .metadata
+                        .as_ref()
+                        .and_then(|m| m.get(key))
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string())
+                };
+                if let Some(embedding_model) = metadata_str("embedding_model") {
+                    client = client.with_embedding_model(embedding_model);
+                }
+                if let Some(embedding_endpoint) = metadata_str("embedding_endpoint") {
+                    client = client.with_embedding_endpoint(embedding_endpoint);
+                }
+                if let Some(api_version) = metadata_str("api_version") {
+                    client = client.with_api_version(api_version);
+                }
+                let metadata_usize = |key: &str| {
+                    profile
+                        .metadata
+                        .as_ref()
+                        .and_then(|m| m.get(key))
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as usize)
                 };
+                if let Some(limit) = metadata_usize("max_request_bytes") {
+                    client = client.with_max_request_bytes(limit);
+                }
+                if let Some(limit) = metadata_usize("max_response_bytes") {
+                    client = client.with_max_response_bytes(limit);
+                }
 
                 Ok(Some(Arc::new(client)))
             }
         }
     }
 
+    /// 创建 `openai_assistant` 驱动的 Assistants 客户端，其他驱动返回 `None`
+    pub fn create_assistant(profile: &AgentConfig) -> Result<Option<Arc<OpenAiAssistantClient>>> {
+        if profile.driver != AgentDriverKind::OpenAiAssistant {
+            return Ok(None);
+        }
+        let metadata = |key: &str| profile.metadata.as_ref().and_then(|m| m.get(key));
+        let assistant_id = metadata("assistant_id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| {
+                AgentFlowError::Other(anyhow!(
+                    "Missing 'metadata.assistant_id' in agent config for driver 'openai_assistant'"
+                ))
+            })?;
+        let endpoint = profile.endpoint.clone().ok_or_else(|| {
+            AgentFlowError::Other(anyhow!(
+                "Missing 'endpoint' field in agent config for driver 'openai_assistant'.\n\
+                 Please add: \"endpoint\": \"https://api.openai.com/v1\""
+            ))
+        })?;
+        let api_key = Self::get_api_key(profile)?;
+        let http = match metadata("auth_header").and_then(|v| v.as_str()) {
+            Some(auth_header) => GenericHttpClient::with_auth_header(
+                endpoint,
+                api_key,
+                "",
+                ApiFormat::OpenAI,
+                auth_header,
+            ),
+            None => GenericHttpClient::new(endpoint, api_key, "", ApiFormat::OpenAI),
+        }
+        .with_http_client(Self::http_client(profile)?);
+
+        let mut client = OpenAiAssistantClient::new(http, assistant_id);
+        if let Some(model) = &profile.model {
+            client = client.with_model(model.clone());
+        }
+        if let Some(interval) = metadata("poll_interval_ms").and_then(|v| v.as_u64()) {
+            client = client.with_poll_interval(std::time::Duration::from_millis(interval));
+        }
+        if let Some(timeout) = metadata("run_timeout_secs").and_then(|v| v.as_u64()) {
+            client = client.with_run_timeout(std::time::Duration::from_secs(timeout));
+        }
+        Ok(Some(Arc::new(client)))
+    }
+
+    /// 按 `metadata.http` 取得共享的 HTTP 客户端，未配置时使用默认连接池
+    fn http_client(profile: &AgentConfig) -> Result<reqwest::Client> {
+        let config = match profile.metadata.as_ref().and_then(|m| m.get("http")) {
+            Some(value) => HttpPoolConfig::deserialize(value).map_err(|e| {
+                AgentFlowError::Other(anyhow!(
+                    "Invalid 'metadata.http' in agent config '{}': {}",
+                    profile.name,
+                    e
+                ))
+            })?,
+            None => HttpPoolConfig::default(),
+        };
+        shared_http_client(&config)
+    }
+
     /// 确定 API 格式
     ///
     /// 优先级：
@@ -155,7 +307,7 @@ impl LlmClientFactory {
                         return Ok(format);
                     } else {
                         return Err(AgentFlowError::Other(anyhow!(
-                            "Invalid api_format '{}'. Supported formats: 'openai', 'qwen', 'qwenvision'",
+                            "Invalid api_format '{}'. Supported formats: 'openai', 'qwen', 'qwenvision', 'gemini', 'azure_openai'",
                             format_str
                         )));
                     }
@@ -178,13 +330,15 @@ impl LlmClientFactory {
              \n\
              Please explicitly specify the format in your agent config's metadata:\n\
              \"metadata\": {{\n\
-               \"api_format\": \"openai\"  // or \"qwen\", \"qwenvision\"\n\
+               \"api_format\": \"openai\"  // or \"qwen\", \"qwenvision\", \"gemini\", \"azure_openai\"\n\
              }}\n\
              \n\
              Supported formats:\n\
              - \"openai\": OpenAI-compatible API (most common)\n\
              - \"qwen\": 通义千问原生API\n\
-             - \"qwenvision\": 通义千问视觉模型",
+             - \"qwenvision\": 通义千问视觉模型\n\
+             - \"gemini\": Google Gemini generateContent\n\
+             - \"azure_openai\": Azure OpenAI（model 为部署名）",
             endpoint,
             model
         )))
@@ -228,7 +382,45 @@ impl LlmClientFactory {
 
 #[cfg(not(feature = "openai-client"))]
 impl LlmClientFactory {
-    pub fn create_client(_profile: &AgentConfig) -> Result<Option<DynLlmClient>> {
+    #[cfg_attr(not(feature = "bedrock"), allow(unused_variables))]
+    fn create_provider_client(profile: &AgentConfig) -> Result<Option<DynLlmClient>> {
+        #[cfg(feature = "bedrock")]
+        if profile.driver == AgentDriverKind::Bedrock {
+            return Self::create_bedrock(profile).map(Some);
+        }
         Ok(None)
     }
 }
+
+#[cfg(feature = "bedrock")]
+impl LlmClientFactory {
+    /// 创建 `bedrock` 驱动的 AWS Bedrock 客户端
+    pub fn create_bedrock(profile: &AgentConfig) -> Result<DynLlmClient> {
+        use crate::llm::BedrockClient;
+        use crate::utils::AwsCredentials;
+
+        let model = profile.model.clone().ok_or_else(|| {
+            AgentFlowError::Other(anyhow!(
+                "Missing 'model' field in agent config for driver 'bedrock'.\n\
+                 Please add a Bedrock model id, e.g. \"model\": \"anthropic.claude-3-5-sonnet-20240620-v1:0\""
+            ))
+        })?;
+        let metadata = |key: &str| profile.metadata.as_ref().and_then(|m| m.get(key));
+        let region = match metadata("region").and_then(|v| v.as_str()) {
+            Some(region) => region.to_string(),
+            None => std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+        };
+
+        let mut client = BedrockClient::new(region, model, AwsCredentials::from_env()?);
+        if let Some(endpoint) = &profile.endpoint {
+            client = client.with_endpoint(endpoint.clone());
+        }
+        if let Some(max_tokens) = metadata("max_tokens").and_then(|v| v.as_u64()) {
+            client = client.with_max_tokens(max_tokens as u32);
+        }
+        if let Some(model) = metadata("embedding_model").and_then(|v| v.as_str()) {
+            client = client.with_embedding_model(model);
+        }
+        Ok(Arc::new(client))
+    }
+}
@@ -111,12 +111,7 @@ mod tests {
 
     #[test]
     fn test_parse_payload_from_message() {
-        let message = AgentMessage {
-            role: MessageRole::User,
-            content: json!({"user": "test"}).to_string(),
-            agent: None,
-            target: None,
-        };
+        let message = AgentMessage::user(json!({"user": "test"}).to_string());
 
         let payload = MessageParser::parse_payload(&message, &[]).unwrap();
         assert_eq!(payload["user"], "test");
@@ -124,18 +119,11 @@ mod tests {
 
     #[test]
     fn test_parse_payload_from_history() {
-        let message = AgentMessage {
-            role: MessageRole::User,
-            content: "invalid json".to_string(),
-            agent: None,
-            target: None,
-        };
+        let message = AgentMessage::user("invalid json");
 
         let history = vec![AgentMessage {
             role: MessageRole::Agent,
-            content: json!({"user": "from history"}).to_string(),
-            agent: None,
-            target: None,
+            ..AgentMessage::user(json!({"user": "from history"}).to_string())
         }];
 
         let payload = MessageParser::parse_payload(&message, &history).unwrap();
@@ -1,13 +1,24 @@
+//! Agent 执行使用的内部服务（未启用 `unstable` feature 时仅 crate 内可见）
+
+// 通用工具函数，crate 内未使用，只面向 `unstable` 使用方
+#[cfg_attr(not(feature = "unstable"), allow(dead_code))]
 pub mod helpers;
 pub mod llm_caller;
 pub mod llm_client_factory;
 pub mod message_parser;
+pub mod output_validator;
 pub mod prompt_builder;
 pub mod routing;
 
-pub use helpers::{FileHelper, JsonHelper, StringHelper, TimeHelper};
-pub use llm_caller::LlmCaller;
 pub use llm_client_factory::LlmClientFactory;
-pub use message_parser::MessageParser;
-pub use prompt_builder::PromptBuilder;
-pub use routing::RouteMatcher;
+
+// crate 内通过子模块路径使用，以下导出只面向 `unstable` 使用方
+#[cfg_attr(not(feature = "unstable"), allow(unused_imports))]
+pub use self::{
+    helpers::{FileHelper, JsonHelper, StringHelper, TimeHelper},
+    llm_caller::LlmCaller,
+    message_parser::MessageParser,
+    output_validator::OutputValidator,
+    prompt_builder::PromptBuilder,
+    routing::RouteMatcher,
+};
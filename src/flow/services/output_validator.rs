@@ -0,0 +1,83 @@
+use crate::flow::config::{OutputSchemaRef, RoutingRules};
+use crate::flow::constants::prompt as prompt_consts;
+use crate::flow::services::routing::clean_response;
+use crate::schema::{validate_schema, validate_value, SchemaError};
+use serde_json::Value;
+
+/// 输出校验服务
+///
+/// 按 Agent 配置的 `output_schema` 校验 LLM 输出，供 `on_invalid_output` 策略使用
+pub struct OutputValidator;
+
+impl OutputValidator {
+    /// 校验输出，成功返回解析后的 JSON，失败返回错误描述
+    pub fn validate(
+        schema: &OutputSchemaRef,
+        output: &str,
+        routing_rules: Option<&RoutingRules>,
+    ) -> std::result::Result<Value, String> {
+        let cleaned = clean_response(output, routing_rules);
+        let value: Value = serde_json::from_str(cleaned.trim())
+            .map_err(|e| format!("output is not valid JSON ({})", e))?;
+
+        let result = match schema {
+            OutputSchemaRef::Named(name) => validate_schema(name, &value),
+            OutputSchemaRef::Inline(schema) => validate_value(schema, &value, &mut Vec::new()),
+        };
+
+        result.map(|_| value).map_err(Self::describe_error)
+    }
+
+    /// 构建附加到系统 prompt 的错误反馈
+    pub fn feedback(template: Option<&str>, error: &str, output: &str) -> String {
+        template
+            .unwrap_or(prompt_consts::INVALID_OUTPUT_FEEDBACK)
+            .replace("{error}", error)
+            .replace("{output}", output)
+    }
+
+    fn describe_error(error: SchemaError) -> String {
+        match error {
+            SchemaError::Validation { message, path } if !path.is_empty() => {
+                format!("{} at `{}`", message, path.join("."))
+            }
+            other => other.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Schema, SchemaKind};
+    use std::collections::HashMap;
+
+    fn schema() -> OutputSchemaRef {
+        let mut properties = HashMap::new();
+        properties.insert("score".to_string(), Schema::new(SchemaKind::Number));
+        OutputSchemaRef::Inline(Schema::new(SchemaKind::Object {
+            properties,
+            required: vec!["score".to_string()],
+            additional: true,
+        }))
+    }
+
+    #[test]
+    fn test_validate_output() {
+        let value = OutputValidator::validate(&schema(), "```json\n{\"score\": 0.9}\n```", None)
+            .unwrap();
+        assert_eq!(value["score"], 0.9);
+
+        let error = OutputValidator::validate(&schema(), "not json", None).unwrap_err();
+        assert!(error.contains("not valid JSON"));
+
+        let error = OutputValidator::validate(&schema(), "{\"score\": \"high\"}", None).unwrap_err();
+        assert!(error.contains("score"));
+    }
+
+    #[test]
+    fn test_feedback_template() {
+        let feedback = OutputValidator::feedback(Some("E={error};O={output}"), "bad", "x");
+        assert_eq!(feedback, "E=bad;O=x");
+    }
+}
@@ -173,6 +173,7 @@ impl PromptBuilder {
     /// 构建包含历史上下文的系统 prompt
     ///
     /// 在原有的系统 prompt 基础上，附加前序 Agent 的输出作为上下文
+    #[allow(clippy::too_many_arguments)]
     pub fn build_system_prompt_with_history(
         role: Option<&str>,
         prompt: Option<&str>,
@@ -278,7 +279,7 @@ mod tests {
             Some("Router"),
             Some("Route requests."),
             Some("auto"),
-            Some(&vec!["node_urgent".to_string()]),
+            Some(&["node_urgent".to_string()]),
             None,
             None,
         )
@@ -0,0 +1,194 @@
+//! 群聊节点：构建选择发言人的提示词并把模型回复匹配到参与者
+
+use serde_json::Value;
+
+use super::{clean_response, strip_reasoning};
+use crate::flow::GroupChatNode;
+
+const SPEAKER_PROMPT: &str =
+    "You are coordinating a group chat. Read the conversation and pick the participant who should speak next.";
+
+const SPEAKER_FORMAT: &str = "Respond with JSON only: {\"speaker\": \"<participant name>\"}";
+
+/// 系统提示词：说明 + 参与者列表 + 回复格式
+pub fn speaker_prompt(node: &GroupChatNode) -> String {
+    let mut prompt = node
+        .prompt
+        .clone()
+        .unwrap_or_else(|| SPEAKER_PROMPT.to_string());
+    prompt.push_str("\n\nParticipants:\n");
+    for participant in &node.participants {
+        prompt.push_str(&format!("- {}\n", participant));
+    }
+    prompt.push('\n');
+    prompt.push_str(SPEAKER_FORMAT);
+    prompt
+}
+
+/// 解析模型回复：JSON 中的 `speaker` 优先，否则取文本中最先出现的参与者名称
+pub fn select_speaker(node: &GroupChatNode, response: &str) -> Option<usize> {
+    let answer = strip_reasoning(response);
+    let cleaned = clean_response(answer, None);
+    if let Some(speaker) = serde_json::from_str::<Value>(&cleaned)
+        .ok()
+        .and_then(|value| {
+            value
+                .get("speaker")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+    {
+        return node
+            .participants
+            .iter()
+            .position(|participant| participant.eq_ignore_ascii_case(speaker.trim()));
+    }
+
+    let lower = answer.to_lowercase();
+    node.participants
+        .iter()
+        .enumerate()
+        .filter_map(|(index, participant)| {
+            lower
+                .find(&participant.to_lowercase())
+                .map(|offset| (offset, index))
+        })
+        .min()
+        .map(|(_, index)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{
+        register_agent, Agent, AgentAction, AgentContext, AgentMessage, AgentRegistry,
+    };
+    use crate::error::Result;
+    use crate::flow::FlowBuilder;
+    use crate::llm::{DynLlmClient, LlmClient, LlmRequest, LlmResponse};
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+    use std::sync::Arc;
+
+    /// 回复自己看到的对话条数，`critic` 在第二条发言后批准
+    struct Speaker(&'static str);
+
+    #[async_trait::async_trait]
+    impl Agent for Speaker {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let seen = message.content.split("\n\n").count();
+            let mut content = format!("{} saw {}", self.0, seen);
+            if self.0 == "critic" && seen >= 4 {
+                content.push_str(" APPROVED");
+            }
+            Ok(AgentAction::Continue {
+                message: Some(AgentMessage::system(content)),
+            })
+        }
+    }
+
+    /// 依次返回预设的发言人
+    struct SelectorClient(Arc<std::sync::Mutex<Vec<&'static str>>>);
+
+    #[async_trait::async_trait]
+    impl LlmClient for SelectorClient {
+        async fn complete(&self, _request: LlmRequest) -> Result<LlmResponse> {
+            let next = self.0.lock().unwrap().remove(0);
+            Ok(LlmResponse {
+                content: format!("{{\"speaker\": \"{}\"}}", next),
+                metadata: None,
+            })
+        }
+
+        fn clone_dyn(&self) -> DynLlmClient {
+            Arc::new(SelectorClient(Arc::clone(&self.0)))
+        }
+    }
+
+    async fn run_chat(chat: GroupChatNode) -> (AgentMessage, Vec<AgentMessage>) {
+        let mut agents = AgentRegistry::new();
+        for name in ["writer", "critic", "editor"] {
+            register_agent(name, Arc::new(Speaker(name)), &mut agents);
+        }
+        let mut builder = FlowBuilder::new("review");
+        builder
+            .add_group_chat_node("chat", chat)
+            .add_terminal_node("done")
+            .connect("chat", "done")
+            .set_start("chat");
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let execution = executor
+            .start(Arc::clone(&ctx), AgentMessage::user("draft a slogan"))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "done");
+        (execution.last_message.unwrap(), ctx.history())
+    }
+
+    fn speakers(message: &AgentMessage) -> Vec<String> {
+        message.metadata.as_ref().unwrap()["group_chat"]["transcript"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["speaker"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_until_keyword() {
+        let chat = GroupChatNode::round_robin(["writer", "critic"]).with_max_turns(3);
+        let (message, history) = run_chat(chat).await;
+        assert_eq!(speakers(&message), ["user", "writer", "critic", "writer"]);
+        assert_eq!(message.content, "writer saw 3");
+        assert_eq!(
+            message.metadata.as_ref().unwrap()["group_chat"]["terminated"],
+            false
+        );
+        assert!(history.iter().any(|m| m.content == "critic saw 2"));
+
+        let chat = GroupChatNode::round_robin(["writer", "critic"]).with_terminate_on("APPROVED");
+        let (message, _) = run_chat(chat).await;
+        assert_eq!(message.content, "critic saw 4 APPROVED");
+        assert_eq!(message.metadata.as_ref().unwrap()["group_chat"]["turns"], 4);
+        assert_eq!(
+            message.metadata.as_ref().unwrap()["group_chat"]["terminated"],
+            true
+        );
+    }
+
+    #[tokio::test]
+    async fn test_llm_selects_speaker() {
+        let picks = Arc::new(std::sync::Mutex::new(vec!["editor", "editor", "nobody"]));
+        let chat = GroupChatNode::round_robin(["writer", "critic", "editor"])
+            .with_selector(Arc::new(SelectorClient(picks)))
+            .with_max_turns(3);
+        let (message, _) = run_chat(chat).await;
+        // 无法识别的回复按轮询顺序取上一位发言人的下一位
+        assert_eq!(speakers(&message), ["user", "editor", "editor", "writer"]);
+    }
+
+    #[test]
+    fn test_select_speaker() {
+        let node = GroupChatNode::round_robin(["writer", "critic"]);
+        assert!(speaker_prompt(&node).contains("- critic\n"));
+
+        let json = "<think>needs review</think>```json\n{\"speaker\": \"Critic\"}\n```";
+        assert_eq!(select_speaker(&node, json), Some(1));
+        assert_eq!(select_speaker(&node, "{\"speaker\": \"editor\"}"), None);
+        assert_eq!(
+            select_speaker(&node, "The writer should revise, then the critic."),
+            Some(0)
+        );
+        assert_eq!(select_speaker(&node, "nobody"), None);
+    }
+}
@@ -0,0 +1,193 @@
+//! LLM 决策节点：构建分类提示词并把模型返回的标签匹配到分支
+
+use serde_json::{json, Value};
+
+use super::{clean_response, strip_reasoning, RouteMatcher};
+use crate::error::Result;
+use crate::flow::constants::{fields, prompt as prompt_consts};
+use crate::flow::LlmDecisionNode;
+
+const DECISION_PROMPT: &str =
+    "You are a request router. Classify the user message into the most appropriate route below.";
+
+const DECISION_FORMAT: &str =
+    "Respond with JSON only: {\"route\": \"<route label>\", \"reason\": \"<one-sentence reason>\"}";
+
+/// 分类结果
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecisionOutcome {
+    /// 命中的分支下标（按配置顺序），为空表示走 fallback
+    pub branches: Vec<usize>,
+    pub reason: Option<String>,
+}
+
+/// 系统提示词：说明 + 分支列表 + 回复格式
+pub fn decision_prompt(node: &LlmDecisionNode) -> String {
+    let mut prompt = node
+        .prompt
+        .clone()
+        .unwrap_or_else(|| DECISION_PROMPT.to_string());
+    prompt.push_str("\n\nRoutes:\n");
+    for branch in &node.branches {
+        match &branch.description {
+            Some(description) => prompt.push_str(&format!("- {}: {}\n", branch.label, description)),
+            None => prompt.push_str(&format!("- {}\n", branch.label)),
+        }
+    }
+    prompt.push('\n');
+    prompt.push_str(DECISION_FORMAT);
+    prompt
+}
+
+/// 解析模型回复：JSON 中的 `route` 优先，否则在文本中查找分支标签
+pub fn classify_decision(
+    node: &LlmDecisionNode,
+    response: &str,
+    node_name: &str,
+) -> Result<DecisionOutcome> {
+    let answer = strip_reasoning(response);
+    let cleaned = clean_response(answer, None);
+    let labels = node
+        .branches
+        .iter()
+        .map(|branch| branch.label.clone())
+        .collect();
+    let matched = RouteMatcher::new(labels, None, None)
+        .match_route(answer, &cleaned, &json!({}), node_name)?
+        .unwrap_or_default();
+    let branches: Vec<usize> = node
+        .branches
+        .iter()
+        .enumerate()
+        .filter(|(_, branch)| matched.contains_key(&branch.label))
+        .map(|(index, _)| index)
+        .collect();
+
+    let parsed = serde_json::from_str::<Value>(&cleaned).ok();
+    let reason = parsed
+        .as_ref()
+        .and_then(|value| {
+            value
+                .get("reason")
+                .or_else(|| value.get(fields::ROUTE_REASON))
+        })
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .or_else(|| {
+            (parsed.is_none() && !branches.is_empty())
+                .then(|| prompt_consts::EXTRACTED_ROUTE_REASON.to_string())
+        });
+    Ok(DecisionOutcome { branches, reason })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{AgentMessage, AgentRegistry};
+    use crate::error::AgentFlowError;
+    use crate::flow::{FlowBuilder, LlmDecisionBranch};
+    use crate::llm::{DynLlmClient, LlmClient, LlmRequest, LlmResponse};
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+    use std::sync::Arc;
+
+    fn node() -> LlmDecisionNode {
+        let branch = |label: &str, target: &str| LlmDecisionBranch {
+            label: label.to_string(),
+            target: target.to_string(),
+            description: Some(format!("{} questions", label)),
+        };
+        LlmDecisionNode {
+            client: None,
+            branches: vec![
+                branch("billing", "billing_agent"),
+                branch("tech", "tech_agent"),
+            ],
+            fallback: "human".to_string(),
+            prompt: None,
+        }
+    }
+
+    #[test]
+    fn test_classify_decision() {
+        let node = node();
+        assert!(decision_prompt(&node).contains("- tech: tech questions"));
+
+        let json = "<think>about invoices</think>```json\n{\"route\": \"Billing\", \"reason\": \"invoice question\"}\n```";
+        assert_eq!(
+            classify_decision(&node, json, "router").unwrap(),
+            DecisionOutcome {
+                branches: vec![0],
+                reason: Some("invoice question".to_string()),
+            }
+        );
+
+        let text = classify_decision(&node, "This is a tech issue.", "router").unwrap();
+        assert_eq!(text.branches, vec![1]);
+
+        let unknown = classify_decision(&node, "{\"route\": \"sales\"}", "router").unwrap();
+        assert!(unknown.branches.is_empty());
+    }
+
+    /// `None` 模拟调用失败
+    struct ReplyClient(Option<&'static str>);
+
+    #[async_trait::async_trait]
+    impl LlmClient for ReplyClient {
+        async fn complete(&self, _request: LlmRequest) -> Result<LlmResponse> {
+            match self.0 {
+                Some(reply) => Ok(LlmResponse {
+                    content: reply.to_string(),
+                    metadata: None,
+                }),
+                None => Err(AgentFlowError::Other(anyhow::anyhow!("unavailable"))),
+            }
+        }
+
+        fn clone_dyn(&self) -> DynLlmClient {
+            Arc::new(ReplyClient(self.0))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_executor_routes_by_label_with_fallback() {
+        async fn route(reply: Option<&'static str>) -> (String, Value) {
+            let mut node = node();
+            node.client = Some(Arc::new(ReplyClient(reply)));
+            let mut builder = FlowBuilder::new("support");
+            builder
+                .add_llm_decision_node("router", node)
+                .add_terminal_node("billing_agent")
+                .add_terminal_node("tech_agent")
+                .add_terminal_node("human")
+                .set_start("router");
+            let executor =
+                FlowExecutor::new(builder.build(), AgentRegistry::new(), ToolRegistry::new());
+            let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+            let execution = executor
+                .start(ctx, AgentMessage::user("my app crashes"))
+                .await
+                .unwrap();
+            let message = execution.last_message.unwrap();
+            assert_eq!(message.content, "my app crashes");
+            (
+                execution.last_node,
+                message.metadata.unwrap()["decision"].clone(),
+            )
+        }
+
+        let (node, decision) = route(Some("{\"route\": \"tech\", \"reason\": \"crash\"}")).await;
+        assert_eq!(node, "tech_agent");
+        assert_eq!(decision["branch"], "tech");
+        assert_eq!(decision["reason"], "crash");
+
+        let (node, decision) = route(Some("not sure")).await;
+        assert_eq!(node, "human");
+        assert_eq!(decision["fallback"], true);
+
+        let (node, decision) = route(None).await;
+        assert_eq!(node, "human");
+        assert!(decision["reason"].as_str().unwrap().contains("unavailable"));
+    }
+}
@@ -1,20 +1,23 @@
 // 路由服务模块
 
+mod group_chat;
+mod llm_decision;
 mod matcher;
 mod message_builder;
 mod response_cleaner;
 mod route_extractor;
 mod route_matcher_utils;
 
+pub use group_chat::{select_speaker, speaker_prompt};
+pub use llm_decision::{classify_decision, decision_prompt, DecisionOutcome};
 pub use matcher::RouteMatcher;
-pub use response_cleaner::clean_response;
+pub use response_cleaner::{clean_response, strip_reasoning};
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use route_extractor::extract_route_from_text;
     use route_matcher_utils::is_route_match;
-    use serde_json::json;
 
     #[test]
     fn test_is_route_match() {
@@ -1,19 +1,27 @@
 use crate::flow::config::RoutingRules;
+use crate::flow::constants::routing as routing_consts;
+
+/// 去掉推理模型在结论前输出的 `<think>…</think>` 段
+pub fn strip_reasoning(response: &str) -> &str {
+    response
+        .rsplit_once("</think>")
+        .map_or(response, |(_, rest)| rest)
+}
 
 /// 清理响应内容，提取 JSON（处理代码块包裹的情况）
 pub fn clean_response(response: &str, routing_rules: Option<&RoutingRules>) -> String {
     let json_code_block_start = routing_rules
         .as_ref()
         .map(|r| r.json_code_block_start.as_str())
-        .unwrap_or("```json");
+        .unwrap_or(routing_consts::JSON_CODE_BLOCK_START);
     let code_block_start = routing_rules
         .as_ref()
         .map(|r| r.code_block_start.as_str())
-        .unwrap_or("```");
+        .unwrap_or(routing_consts::CODE_BLOCK_START);
     let code_block_end = routing_rules
         .as_ref()
         .map(|r| r.code_block_end.as_str())
-        .unwrap_or("```");
+        .unwrap_or(routing_consts::CODE_BLOCK_END);
 
     if response.contains(json_code_block_start) {
         if let Some(start) = response.find(json_code_block_start) {
@@ -0,0 +1,370 @@
+//! 数据转换节点：按声明的操作整理 JSON 载荷，不调用模型
+
+use crate::error::{AgentFlowError, Result};
+use crate::utils::JsonPath;
+use anyhow::anyhow;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// 转换操作
+///
+/// `path` / `from` / `to` 为 JSON Pointer（如 `/data/items`），空字符串表示整个载荷；
+/// `expr` 为 JSONPath（如 `$.items[*].name`），相对于当前值求值。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransformOp {
+    /// 以 JSONPath 的结果替换载荷，`to` 设置时写入该位置；含通配符时为数组，无匹配为 `null`
+    Query {
+        expr: JsonPath,
+        #[serde(default)]
+        to: Option<String>,
+    },
+    /// 以指定位置的值替换整个载荷，不存在时为 `null`
+    Get {
+        path: String,
+    },
+    /// 写入值，自动创建中间对象；数组位置可用 `-` 追加
+    Set {
+        path: String,
+        value: Value,
+    },
+    Copy {
+        from: String,
+        to: String,
+    },
+    /// 移动字段
+    Rename {
+        from: String,
+        to: String,
+    },
+    Remove {
+        path: String,
+    },
+    /// 把对象浅合并到指定位置的对象中，同名字段覆盖
+    Merge {
+        #[serde(default)]
+        path: String,
+        value: Value,
+    },
+    /// 只保留对象的指定字段
+    Pick {
+        #[serde(default)]
+        path: String,
+        keys: Vec<String>,
+    },
+    /// 对数组每个元素求 JSONPath，以结果替换元素
+    Map {
+        path: String,
+        expr: JsonPath,
+    },
+    /// 保留 JSONPath 结果等于 `equals` 的元素；未设置 `equals` 时保留结果非空且不为 `false` 的元素
+    Filter {
+        path: String,
+        expr: JsonPath,
+        #[serde(default)]
+        equals: Option<Value>,
+    },
+    /// 展开一层嵌套数组
+    Flatten {
+        path: String,
+    },
+    /// 截取数组 `[start, end)`，越界时截断
+    Slice {
+        path: String,
+        #[serde(default)]
+        start: usize,
+        #[serde(default)]
+        end: Option<usize>,
+    },
+}
+
+/// 数据转换节点
+///
+/// 按顺序对输入消息的载荷执行 `ops`：内容能解析为 JSON 时按 JSON 处理，否则视为字符串；
+/// 结果为字符串时原样输出，其余序列化为 JSON。
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TransformNode {
+    pub ops: Vec<TransformOp>,
+}
+
+impl TransformNode {
+    pub fn new(ops: Vec<TransformOp>) -> Self {
+        Self { ops }
+    }
+
+    pub fn with_op(mut self, op: TransformOp) -> Self {
+        self.ops.push(op);
+        self
+    }
+
+    /// 依次执行所有操作
+    pub fn apply(&self, mut value: Value) -> Result<Value> {
+        for (index, op) in self.ops.iter().enumerate() {
+            value = apply_op(op, value).map_err(|reason| {
+                AgentFlowError::Other(anyhow!("Transform op #{} failed: {}", index, reason))
+            })?;
+        }
+        Ok(value)
+    }
+
+    /// 转换消息内容
+    pub fn transform(&self, content: &str) -> Result<String> {
+        let input =
+            serde_json::from_str(content).unwrap_or_else(|_| Value::String(content.to_string()));
+        Ok(match self.apply(input)? {
+            Value::String(text) => text,
+            other => other.to_string(),
+        })
+    }
+}
+
+fn apply_op(op: &TransformOp, mut value: Value) -> std::result::Result<Value, String> {
+    match op {
+        TransformOp::Query { expr, to } => {
+            let result = expr.extract(&value).unwrap_or(Value::Null);
+            match to {
+                Some(to) => set_pointer(&mut value, to, result)?,
+                None => return Ok(result),
+            }
+        }
+        TransformOp::Get { path } => {
+            return Ok(value.pointer(path).cloned().unwrap_or(Value::Null));
+        }
+        TransformOp::Set { path, value: new } => set_pointer(&mut value, path, new.clone())?,
+        TransformOp::Copy { from, to } => {
+            let copied = value
+                .pointer(from)
+                .cloned()
+                .ok_or_else(|| format!("`{}` not found", from))?;
+            set_pointer(&mut value, to, copied)?;
+        }
+        TransformOp::Rename { from, to } => {
+            let moved = take_pointer(&mut value, from)?;
+            set_pointer(&mut value, to, moved)?;
+        }
+        TransformOp::Remove { path } => {
+            take_pointer(&mut value, path)?;
+        }
+        TransformOp::Merge { path, value: patch } => {
+            let Value::Object(patch) = patch else {
+                return Err("merge value must be an object".to_string());
+            };
+            let target = object_at(&mut value, path)?;
+            target.extend(patch.clone());
+        }
+        TransformOp::Pick { path, keys } => {
+            let target = object_at(&mut value, path)?;
+            target.retain(|key, _| keys.contains(key));
+        }
+        TransformOp::Map { path, expr } => {
+            let items = array_at(&mut value, path)?;
+            for item in items.iter_mut() {
+                *item = expr.extract(item).unwrap_or(Value::Null);
+            }
+        }
+        TransformOp::Filter { path, expr, equals } => {
+            let items = array_at(&mut value, path)?;
+            items.retain(|item| {
+                let found = expr.extract(item).unwrap_or(Value::Null);
+                match equals {
+                    Some(expected) => &found == expected,
+                    None => !matches!(found, Value::Null | Value::Bool(false)),
+                }
+            });
+        }
+        TransformOp::Flatten { path } => {
+            let items = array_at(&mut value, path)?;
+            *items = std::mem::take(items)
+                .into_iter()
+                .flat_map(|item| match item {
+                    Value::Array(nested) => nested,
+                    other => vec![other],
+                })
+                .collect();
+        }
+        TransformOp::Slice { path, start, end } => {
+            let items = array_at(&mut value, path)?;
+            let end = end.unwrap_or(items.len()).min(items.len());
+            let start = (*start).min(end);
+            *items = items.drain(start..end).collect();
+        }
+    }
+    Ok(value)
+}
+
+fn object_at<'a>(
+    value: &'a mut Value,
+    path: &str,
+) -> std::result::Result<&'a mut serde_json::Map<String, Value>, String> {
+    value
+        .pointer_mut(path)
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| format!("`{}` is not an object", path))
+}
+
+fn array_at<'a>(
+    value: &'a mut Value,
+    path: &str,
+) -> std::result::Result<&'a mut Vec<Value>, String> {
+    value
+        .pointer_mut(path)
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| format!("`{}` is not an array", path))
+}
+
+/// 拆分 JSON Pointer，返回父路径和转义还原后的末段
+fn split_pointer(path: &str) -> std::result::Result<(&str, String), String> {
+    if !path.starts_with('/') {
+        return Err(format!("invalid JSON pointer `{}`", path));
+    }
+    let index = path.rfind('/').unwrap_or(0);
+    let token = path[index + 1..].replace("~1", "/").replace("~0", "~");
+    Ok((&path[..index], token))
+}
+
+fn set_pointer(root: &mut Value, path: &str, new: Value) -> std::result::Result<(), String> {
+    if path.is_empty() {
+        *root = new;
+        return Ok(());
+    }
+    let (parent, token) = split_pointer(path)?;
+    if root.pointer(parent).is_none_or(Value::is_null) {
+        set_pointer(root, parent, Value::Object(serde_json::Map::new()))?;
+    }
+    match root.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(token, new);
+        }
+        Some(Value::Array(items)) => {
+            if token == "-" {
+                items.push(new);
+            } else {
+                let slot = token
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get_mut(index))
+                    .ok_or_else(|| format!("index `{}` out of bounds at `{}`", token, parent))?;
+                *slot = new;
+            }
+        }
+        _ => {
+            return Err(format!(
+                "cannot set `{}`: parent is not an object or array",
+                path
+            ))
+        }
+    }
+    Ok(())
+}
+
+fn take_pointer(root: &mut Value, path: &str) -> std::result::Result<Value, String> {
+    if path.is_empty() {
+        return Ok(std::mem::take(root));
+    }
+    let (parent, token) = split_pointer(path)?;
+    let removed = match root.pointer_mut(parent) {
+        Some(Value::Object(map)) => map.remove(&token),
+        Some(Value::Array(items)) => token
+            .parse::<usize>()
+            .ok()
+            .filter(|index| *index < items.len())
+            .map(|index| items.remove(index)),
+        _ => None,
+    };
+    removed.ok_or_else(|| format!("`{}` not found", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_transform_ops() {
+        let node: TransformNode = serde_json::from_value(json!({
+            "ops": [
+                { "op": "rename", "from": "/data/results", "to": "/items" },
+                { "op": "filter", "path": "/items", "expr": "$.score" },
+                { "op": "set", "path": "/summary/count", "value": 0 },
+                { "op": "copy", "from": "/data/query", "to": "/summary/query" },
+                { "op": "remove", "path": "/data" },
+                { "op": "merge", "path": "/summary", "value": { "source": "search" } },
+                { "op": "query", "expr": "$.items[*].tags", "to": "/tags" },
+                { "op": "flatten", "path": "/tags" },
+                { "op": "slice", "path": "/tags", "end": 2 },
+                { "op": "map", "path": "/items", "expr": "$.title" }
+            ]
+        }))
+        .unwrap();
+        let input = json!({
+            "data": {
+                "query": "rust",
+                "results": [
+                    { "title": "a", "score": 0.9, "tags": ["x", "y"] },
+                    { "title": "b", "score": null, "tags": ["z"] },
+                    { "title": "c", "score": 0.5, "tags": ["w"] }
+                ]
+            }
+        });
+        assert_eq!(
+            node.apply(input).unwrap(),
+            json!({
+                "items": ["a", "c"],
+                "summary": { "count": 0, "query": "rust", "source": "search" },
+                "tags": ["x", "y"]
+            })
+        );
+
+        let pick = TransformNode::default()
+            .with_op(TransformOp::Pick {
+                path: String::new(),
+                keys: vec!["name".into()],
+            })
+            .with_op(TransformOp::Get {
+                path: "/name".into(),
+            });
+        assert_eq!(pick.transform(r#"{"name":"tea","id":1}"#).unwrap(), "tea");
+
+        let missing = TransformNode::new(vec![TransformOp::Remove {
+            path: "/missing".into(),
+        }]);
+        let err = missing.transform("{}").unwrap_err().to_string();
+        assert!(err.contains("op #0"), "{}", err);
+        assert!(missing.transform("plain text").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transform_node_in_config() {
+        let config = json!({
+            "flow": {
+                "name": "reshape",
+                "start": "reshape",
+                "nodes": [
+                    { "kind": "transform", "name": "reshape", "ops": [
+                        { "op": "query", "expr": "$.hits[*].name", "to": "/names" },
+                        { "op": "pick", "keys": ["names"] }
+                    ] },
+                    { "kind": "terminal", "name": "done" }
+                ],
+                "transitions": [{ "from": "reshape", "to": "done" }]
+            }
+        });
+        let bundle = crate::flow::loader::load_workflow_from_value(&config).unwrap();
+        let executor = crate::runtime::FlowExecutor::new(bundle.flow, bundle.agents, bundle.tools);
+        let ctx = std::sync::Arc::new(crate::state::FlowContext::new(std::sync::Arc::new(
+            crate::state::MemoryStore::new(),
+        )));
+        let input = json!({ "hits": [{ "name": "a" }, { "name": "b" }], "total": 2 });
+        let execution = executor
+            .start(ctx, crate::agent::AgentMessage::user(input.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "done");
+        let output: Value = serde_json::from_str(&execution.last_message.unwrap().content).unwrap();
+        assert_eq!(output, json!({ "names": ["a", "b"] }));
+
+        let mut invalid = config.clone();
+        invalid["flow"]["nodes"][0]["ops"][0]["op"] = "sort".into();
+        assert!(crate::flow::loader::load_workflow_from_value(&invalid).is_err());
+    }
+}
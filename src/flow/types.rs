@@ -1,17 +1,35 @@
-use crate::state::FlowScopeKind;
+use crate::error::{AgentFlowError, Result};
+use crate::guardrails::{InjectionGuard, PiiRedactor};
+use crate::state::{FlowScopeKind, HistoryRetention};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-/// Flow 核心类型定义
+// Flow 核心类型定义
 
 /// Flow 工作流
 #[derive(Clone)]
 pub struct Flow {
     pub name: String,
+    /// 图的版本号，同名工作流的不同版本可以同时注册
+    pub version: u32,
     pub start: String,
     pub nodes: HashMap<String, crate::flow::nodes::FlowNode>,
     pub transitions: HashMap<String, Vec<FlowTransition>>,
     pub parameters: Vec<FlowParameter>,
     pub variables: Vec<FlowVariable>,
+    /// 运行结束后提取的命名输出
+    pub outputs: Vec<crate::flow::outputs::FlowOutput>,
+    /// 运行时挂载到上下文的 PII 脱敏器
+    pub pii_redactor: Option<Arc<PiiRedactor>>,
+    /// 派发用户消息前的注入检测
+    pub injection_guard: Option<Arc<InjectionGuard>>,
+    /// Agent/Tool 节点失败时接收死信的节点
+    pub dead_letter: Option<String>,
+    /// 任意节点失败时转到的错误处理节点
+    pub on_error: Option<String>,
+    /// 运行时挂载到上下文的消息历史保留策略
+    pub history_retention: Option<HistoryRetention>,
 }
 
 impl Flow {
@@ -33,6 +51,44 @@ impl Flow {
     pub fn variables(&self) -> &[FlowVariable] {
         &self.variables
     }
+
+    /// 按声明的输入参数（`Input` / `InOut`）校验运行参数：缺少必填参数、类型不符或传入未声明的参数时返回错误
+    pub fn validate_params(&self, params: &HashMap<String, Value>) -> Result<()> {
+        let inputs = || {
+            self.parameters
+                .iter()
+                .filter(|param| param.kind != FlowParameterKind::Output)
+        };
+        let mut keys: Vec<&String> = params.keys().collect();
+        keys.sort();
+        for key in keys {
+            let Some(param) = inputs().find(|param| &param.name == key) else {
+                return Err(AgentFlowError::InvalidParameter {
+                    name: key.clone(),
+                    message: format!("is not an input parameter of flow `{}`", self.name),
+                });
+            };
+            if !param.accepts(&params[key]) {
+                return Err(AgentFlowError::InvalidParameter {
+                    name: key.clone(),
+                    message: format!(
+                        "expected {}, got {}",
+                        param.type_name.as_deref().unwrap_or_default(),
+                        params[key]
+                    ),
+                });
+            }
+        }
+        if let Some(missing) =
+            inputs().find(|param| param.required && !params.contains_key(&param.name))
+        {
+            return Err(AgentFlowError::InvalidParameter {
+                name: missing.name.clone(),
+                message: "is required".into(),
+            });
+        }
+        Ok(())
+    }
 }
 
 /// Flow 转换
@@ -41,6 +97,10 @@ pub struct FlowTransition {
     pub to: String,
     pub condition: Option<crate::flow::conditions::TransitionCondition>,
     pub name: Option<String>,
+    /// 条件描述（explain 模式使用）
+    pub description: Option<crate::flow::conditions::ConditionInfo>,
+    /// 流水线模式：目标 Agent 在源 Agent 生成时即开始消费其流式输出，仅对无条件转移生效
+    pub pipeline: bool,
 }
 
 /// Flow 参数类型
@@ -58,6 +118,8 @@ pub struct FlowParameter {
     pub kind: FlowParameterKind,
     pub type_name: Option<String>,
     pub description: Option<String>,
+    /// 输入参数是否必须在 `start_with_params` 中提供，默认为 true
+    pub required: bool,
 }
 
 impl FlowParameter {
@@ -67,6 +129,7 @@ impl FlowParameter {
             kind,
             type_name: None,
             description: None,
+            required: true,
         }
     }
 
@@ -97,6 +160,50 @@ impl FlowParameter {
         self.description = Some(description.into());
         self
     }
+
+    pub fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+
+    /// 按 `type_name` 检查 JSON 值的类型
+    ///
+    /// 识别 JSON 类型名（`string` / `integer` / `number` / `boolean` / `array` / `object`）和
+    /// `FlowParameter::input::<T>()` 记录的 Rust 类型名，`Option<T>` 同时接受 null；
+    /// 未声明或无法识别的类型接受任意值。
+    pub fn accepts(&self, value: &Value) -> bool {
+        let Some(type_name) = &self.type_name else {
+            return true;
+        };
+        let mut type_name = type_name.trim();
+        if let Some(inner) = type_name
+            .strip_suffix('>')
+            .and_then(|name| name.split_once("Option<"))
+            .filter(|(path, _)| path.is_empty() || path.ends_with("::"))
+            .map(|(_, inner)| inner)
+        {
+            if value.is_null() {
+                return true;
+            }
+            type_name = inner;
+        }
+        let base = type_name.split('<').next().unwrap_or_default();
+        let base = base
+            .rsplit("::")
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches('&');
+        match base.to_ascii_lowercase().as_str() {
+            "string" | "str" => value.is_string(),
+            "integer" | "int" | "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16"
+            | "u32" | "u64" | "u128" | "usize" => value.is_i64() || value.is_u64(),
+            "number" | "float" | "f32" | "f64" => value.is_number(),
+            "boolean" | "bool" => value.is_boolean(),
+            "array" | "list" | "vec" => value.is_array(),
+            "object" | "map" | "hashmap" | "btreemap" => value.is_object(),
+            _ => true,
+        }
+    }
 }
 
 /// Flow 变量
@@ -130,3 +237,94 @@ impl FlowVariable {
 }
 
 // FlowNode 在 nodes 模块中定义
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentRegistry;
+    use crate::flow::FlowBuilder;
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+    use serde_json::json;
+
+    fn flow() -> Flow {
+        let mut builder = FlowBuilder::new("report");
+        builder
+            .add_terminal_node("done")
+            .set_start("done")
+            .with_parameters([
+                FlowParameter::input::<String>("city"),
+                FlowParameter::input::<Option<u32>>("days").optional(),
+                FlowParameter::new("tags", FlowParameterKind::InOut)
+                    .with_type("array")
+                    .optional(),
+                FlowParameter::output::<String>("summary"),
+            ]);
+        builder.build()
+    }
+
+    fn params(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_validate_params() {
+        let flow = flow();
+        assert!(flow
+            .validate_params(&params(
+                json!({ "city": "Paris", "days": 3, "tags": ["a"] })
+            ))
+            .is_ok());
+        assert!(flow
+            .validate_params(&params(json!({ "city": "Paris", "days": null })))
+            .is_ok());
+
+        let error = |value| {
+            flow.validate_params(&params(value))
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(error(json!({})), "parameter `city` is required");
+        assert_eq!(
+            error(json!({ "city": "Paris", "days": "3" })),
+            "parameter `days` expected core::option::Option<u32>, got \"3\""
+        );
+        assert_eq!(
+            error(json!({ "city": "Paris", "summary": "x" })),
+            "parameter `summary` is not an input parameter of flow `report`"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_with_params_writes_state() {
+        let executor = FlowExecutor::new(flow(), AgentRegistry::new(), ToolRegistry::new());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let execution = executor
+            .start_with_params(
+                Arc::clone(&ctx),
+                params(json!({ "city": "Paris", "days": 3 })),
+            )
+            .await
+            .unwrap();
+        let store = ctx.store();
+        assert_eq!(
+            store.get("params.city").await.unwrap().as_deref(),
+            Some("Paris")
+        );
+        assert_eq!(
+            store.get("params.days").await.unwrap().as_deref(),
+            Some("3")
+        );
+        let message: Value =
+            serde_json::from_str(&execution.last_message.unwrap().content).unwrap();
+        assert_eq!(message, json!({ "city": "Paris", "days": 3 }));
+
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        assert!(executor
+            .start_with_params(Arc::clone(&ctx), params(json!({ "days": 3 })))
+            .await
+            .is_err());
+        assert_eq!(ctx.store().get("params.days").await.unwrap(), None);
+    }
+}
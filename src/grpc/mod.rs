@@ -0,0 +1,440 @@
+//! gRPC 远程执行服务（需启用 `grpc` feature）
+//!
+//! 协议定义见 `proto/agentflow.proto`。
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::{stream, Stream, StreamExt};
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::broadcast;
+use tokio::task::AbortHandle;
+use tonic::{Request, Response, Status};
+
+use crate::agent::AgentMessage;
+use crate::flow::FlowRegistry;
+use crate::runtime::{FlowExecutor, RunChannel, RunUpdate};
+use crate::state::{FlowContext, MemoryStore};
+
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("agentflow.v1");
+}
+
+use proto::flow_service_server::{FlowService, FlowServiceServer};
+use proto::{
+    CancelFlowRequest, CancelFlowResponse, GetRunRequest, Run, RunEvent, RunStatus,
+    StartFlowRequest, StartFlowResponse, StreamEventsRequest,
+};
+
+/// 事件缓冲大小，订阅方落后超过该数量时丢弃较早的实时事件
+const EVENT_CAPACITY: usize = 1024;
+
+/// 默认最多保留的已结束运行数
+pub const DEFAULT_MAX_FINISHED_RUNS: usize = 1024;
+
+/// 已结束运行的默认保留时间
+pub const DEFAULT_FINISHED_RUN_TTL: Duration = Duration::from_secs(3600);
+
+struct RunState {
+    run: Run,
+    events: Vec<RunEvent>,
+    tasks: Vec<AbortHandle>,
+    /// 运行结束（成功、失败或取消）的时间
+    finished_at: Option<Instant>,
+}
+
+/// 一次运行的状态和事件记录
+///
+/// 事件在持有锁时追加并广播，订阅时先复制已有事件再订阅，保证回放和实时事件不重不漏。
+struct RunEntry {
+    state: Mutex<RunState>,
+    live: broadcast::Sender<RunEvent>,
+}
+
+impl RunEntry {
+    fn record(&self, state: &mut RunState, kind: &str, payload_json: String) {
+        let event = RunEvent {
+            run_id: state.run.run_id.clone(),
+            sequence: state.events.len() as u64 + 1,
+            r#type: kind.to_string(),
+            payload_json,
+        };
+        state.events.push(event.clone());
+        let _ = self.live.send(event);
+    }
+
+    fn apply(&self, update: &RunUpdate) {
+        let payload = serde_json::to_value(update).unwrap_or_default();
+        let kind = payload["type"].as_str().unwrap_or_default();
+        let mut state = self.state.lock();
+        match update {
+            RunUpdate::Finished { node, message } => {
+                state.run.set_status(RunStatus::Succeeded);
+                state.run.last_node = node.clone();
+                state.run.output = message
+                    .as_ref()
+                    .map(|m| m.content.clone())
+                    .unwrap_or_default();
+            }
+            RunUpdate::Failed { error } => {
+                state.run.set_status(RunStatus::Failed);
+                state.run.error = error.clone();
+            }
+            _ => {}
+        }
+        if update.is_final() {
+            state.finished_at = Some(Instant::now());
+        }
+        self.record(&mut state, kind, payload.to_string());
+    }
+
+    /// 运行中时中止任务并记录取消事件，返回是否取消成功
+    fn cancel(&self) -> bool {
+        let mut state = self.state.lock();
+        if state.run.status() != RunStatus::Running {
+            return false;
+        }
+        for task in state.tasks.drain(..) {
+            task.abort();
+        }
+        state.run.set_status(RunStatus::Cancelled);
+        state.finished_at = Some(Instant::now());
+        let payload = serde_json::json!({ "type": "cancelled" }).to_string();
+        self.record(&mut state, "cancelled", payload);
+        true
+    }
+}
+
+/// 包装 `FlowExecutor` 的 gRPC 服务
+///
+/// 工作流从 `FlowRegistry` 中按名称查找，可以与 `FlowRegistry::watch` 共享注册表实现热加载。
+/// 每次运行使用独立的内存存储，运行记录保存在服务内存中。
+/// 已结束的运行超过保留时间或数量上限后被清除，之后查询返回 `NotFound`。
+#[derive(Clone)]
+pub struct GrpcFlowService {
+    registry: Arc<RwLock<FlowRegistry>>,
+    runs: Arc<Mutex<HashMap<String, Arc<RunEntry>>>>,
+    max_finished_runs: usize,
+    finished_run_ttl: Duration,
+}
+
+impl GrpcFlowService {
+    pub fn new(registry: FlowRegistry) -> Self {
+        Self::with_shared_registry(Arc::new(RwLock::new(registry)))
+    }
+
+    pub fn with_shared_registry(registry: Arc<RwLock<FlowRegistry>>) -> Self {
+        Self {
+            registry,
+            runs: Arc::new(Mutex::new(HashMap::new())),
+            max_finished_runs: DEFAULT_MAX_FINISHED_RUNS,
+            finished_run_ttl: DEFAULT_FINISHED_RUN_TTL,
+        }
+    }
+
+    /// 设置最多保留的已结束运行数，超出时先清除最早结束的运行
+    pub fn with_max_finished_runs(mut self, max: usize) -> Self {
+        self.max_finished_runs = max;
+        self
+    }
+
+    /// 设置已结束运行的保留时间
+    pub fn with_finished_run_ttl(mut self, ttl: Duration) -> Self {
+        self.finished_run_ttl = ttl;
+        self
+    }
+
+    /// 转换为可添加到 `tonic::transport::Server` 的服务
+    pub fn into_server(self) -> FlowServiceServer<Self> {
+        FlowServiceServer::new(self)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn entry(&self, run_id: &str) -> Result<Arc<RunEntry>, Status> {
+        self.runs
+            .lock()
+            .get(run_id)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("run `{run_id}` not found")))
+    }
+
+    /// 清除过期的已结束运行，并把剩余的已结束运行数限制在上限以内
+    fn evict_finished(&self, runs: &mut HashMap<String, Arc<RunEntry>>) {
+        let now = Instant::now();
+        let mut finished: Vec<(Instant, String)> = runs
+            .iter()
+            .filter_map(|(id, entry)| Some((entry.state.lock().finished_at?, id.clone())))
+            .collect();
+        finished.sort();
+        let expired = finished
+            .iter()
+            .take_while(|(at, _)| now.duration_since(*at) >= self.finished_run_ttl)
+            .count();
+        let excess = finished.len().saturating_sub(self.max_finished_runs);
+        for (_, id) in finished.drain(..expired.max(excess)) {
+            runs.remove(&id);
+        }
+    }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<RunEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl FlowService for GrpcFlowService {
+    async fn start_flow(
+        &self,
+        request: Request<StartFlowRequest>,
+    ) -> Result<Response<StartFlowResponse>, Status> {
+        let request = request.into_inner();
+        let bundle = self
+            .registry
+            .read()
+            .workflow(&request.workflow)
+            .ok_or_else(|| {
+                Status::not_found(format!("workflow `{}` not found", request.workflow))
+            })?;
+        let executor = FlowExecutor::new(
+            bundle.flow.clone(),
+            bundle.agents.clone(),
+            bundle.tools.clone(),
+        );
+
+        let run_id = crate::agent::message::uuid();
+        let mut run = Run {
+            run_id: run_id.clone(),
+            workflow: request.workflow,
+            ..Default::default()
+        };
+        run.set_status(RunStatus::Running);
+        let entry = Arc::new(RunEntry {
+            state: Mutex::new(RunState {
+                run,
+                events: Vec::new(),
+                tasks: Vec::new(),
+                finished_at: None,
+            }),
+            live: broadcast::channel(EVENT_CAPACITY).0,
+        });
+
+        let channel = RunChannel::new(EVENT_CAPACITY);
+        let mut updates = channel.subscribe();
+        let recorder = Arc::clone(&entry);
+        let collector = tokio::spawn(async move {
+            loop {
+                match updates.recv().await {
+                    Ok(update) => {
+                        recorder.apply(&update);
+                        if update.is_final() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "grpc run events lagging; dropped updates");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())).with_channel(channel));
+        let input = AgentMessage::user(request.input);
+        let runner = tokio::spawn(async move {
+            // 结果通过交互通道记录
+            let _ = executor.start(ctx, input).await;
+        });
+        entry.state.lock().tasks = vec![collector.abort_handle(), runner.abort_handle()];
+
+        let mut runs = self.runs.lock();
+        self.evict_finished(&mut runs);
+        runs.insert(run_id.clone(), entry);
+        drop(runs);
+        tracing::info!(run_id = %run_id, "grpc flow run started");
+        Ok(Response::new(StartFlowResponse { run_id }))
+    }
+
+    type StreamEventsStream = EventStream;
+
+    async fn stream_events(
+        &self,
+        request: Request<StreamEventsRequest>,
+    ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        let entry = self.entry(&request.into_inner().run_id)?;
+        let (history, live, running) = {
+            let state = entry.state.lock();
+            (
+                state.events.clone(),
+                entry.live.subscribe(),
+                state.run.status() == RunStatus::Running,
+            )
+        };
+
+        let replay = stream::iter(history.into_iter().map(Ok));
+        if !running {
+            return Ok(Response::new(Box::pin(replay)));
+        }
+        let live = stream::unfold(Some(live), |receiver| async move {
+            let mut receiver = receiver?;
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        let last =
+                            matches!(event.r#type.as_str(), "finished" | "failed" | "cancelled");
+                        return Some((Ok(event), (!last).then_some(receiver)));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(replay.chain(live))))
+    }
+
+    async fn cancel_flow(
+        &self,
+        request: Request<CancelFlowRequest>,
+    ) -> Result<Response<CancelFlowResponse>, Status> {
+        let entry = self.entry(&request.into_inner().run_id)?;
+        let cancelled = entry.cancel();
+        if cancelled {
+            tracing::info!(run_id = %entry.state.lock().run.run_id, "grpc flow run cancelled");
+        }
+        Ok(Response::new(CancelFlowResponse { cancelled }))
+    }
+
+    async fn get_run(&self, request: Request<GetRunRequest>) -> Result<Response<Run>, Status> {
+        let entry = self.entry(&request.into_inner().run_id)?;
+        let run = entry.state.lock().run.clone();
+        Ok(Response::new(run))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, AgentRegistry};
+    use crate::flow::loader::WorkflowBundle;
+    use crate::flow::FlowBuilder;
+    use crate::testing::EchoAgent;
+    use crate::tools::ToolRegistry;
+
+    fn service() -> GrpcFlowService {
+        let mut agents = AgentRegistry::new();
+        register_agent("echo", Arc::new(EchoAgent), &mut agents);
+        let mut builder = FlowBuilder::new("echo");
+        builder
+            .add_agent_node("reply", "echo")
+            .add_terminal_node("done")
+            .set_start("reply");
+        let mut registry = FlowRegistry::new();
+        registry.register_workflow(WorkflowBundle {
+            flow: builder.build(),
+            agents,
+            tools: ToolRegistry::new(),
+        });
+        GrpcFlowService::new(registry)
+    }
+
+    async fn start(service: &GrpcFlowService, input: &str) -> String {
+        service
+            .start_flow(Request::new(StartFlowRequest {
+                workflow: "echo".into(),
+                input: input.into(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .run_id
+    }
+
+    async fn events(service: &GrpcFlowService, run_id: &str) -> Vec<RunEvent> {
+        service
+            .stream_events(Request::new(StreamEventsRequest {
+                run_id: run_id.into(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .map(Result::unwrap)
+            .collect()
+            .await
+    }
+
+    async fn get_run(service: &GrpcFlowService, run_id: &str) -> Result<Run, Status> {
+        service
+            .get_run(Request::new(GetRunRequest {
+                run_id: run_id.into(),
+            }))
+            .await
+            .map(Response::into_inner)
+    }
+
+    #[tokio::test]
+    async fn test_start_stream_and_get_run() {
+        let service = service();
+        let run_id = start(&service, "hello").await;
+
+        let events = events(&service, &run_id).await;
+        let types: Vec<_> = events.iter().map(|e| e.r#type.as_str()).collect();
+        assert_eq!(types, vec!["node_started", "node_started", "finished"]);
+        assert_eq!(events.last().unwrap().sequence, 3);
+
+        let run = get_run(&service, &run_id).await.unwrap();
+        assert_eq!(run.status(), RunStatus::Succeeded);
+        assert_eq!(run.last_node, "done");
+        assert_eq!(run.output, "hello");
+        assert_eq!(
+            get_run(&service, "missing").await.unwrap_err().code(),
+            tonic::Code::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_flow() {
+        let service = service();
+        let run_id = start(&service, "wait").await;
+        let cancel = || {
+            service.cancel_flow(Request::new(CancelFlowRequest {
+                run_id: run_id.clone(),
+            }))
+        };
+        assert!(cancel().await.unwrap().into_inner().cancelled);
+        assert!(!cancel().await.unwrap().into_inner().cancelled);
+
+        let events = events(&service, &run_id).await;
+        assert_eq!(events.last().unwrap().r#type, "cancelled");
+        let run = get_run(&service, &run_id).await.unwrap();
+        assert_eq!(run.status(), RunStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_finished_runs_are_evicted() {
+        let service = service().with_max_finished_runs(1);
+        let first = start(&service, "one").await;
+        events(&service, &first).await;
+        let second = start(&service, "two").await;
+        events(&service, &second).await;
+        assert!(get_run(&service, &first).await.is_ok());
+
+        // 第三次运行开始时已有两个已结束运行，超出上限的最早一个被清除
+        let third = start(&service, "three").await;
+        assert_eq!(
+            get_run(&service, &first).await.unwrap_err().code(),
+            tonic::Code::NotFound
+        );
+        assert!(get_run(&service, &second).await.is_ok());
+
+        let service = service.with_finished_run_ttl(Duration::ZERO);
+        events(&service, &third).await;
+        start(&service, "four").await;
+        assert_eq!(
+            get_run(&service, &second).await.unwrap_err().code(),
+            tonic::Code::NotFound
+        );
+        assert_eq!(
+            get_run(&service, &third).await.unwrap_err().code(),
+            tonic::Code::NotFound
+        );
+    }
+}
@@ -0,0 +1,630 @@
+//! 内容安全护栏：在 Agent 处理输入前、产出结果后检查消息内容
+//!
+//! 每条规则由护栏（正则黑名单、审核接口、自定义谓词）和处置动作组成：
+//! `block` 拦截并以拒答结束，`redact` 脱敏命中内容，`flag` 仅记录。
+//! 处置记录写入消息 metadata 的 `guardrails` 字段。
+
+pub mod injection;
+pub mod pii;
+pub mod rules;
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::agent::{Agent, AgentAction, AgentContext, AgentMessage, MessageRole};
+use crate::config::EnvConfig;
+use crate::error::Result;
+use crate::llm::{LlmRefusal, RefusalKind};
+
+pub use injection::{
+    HeuristicDetector, InjectionConfig, InjectionDetector, InjectionGuard, InjectionVerdict,
+    LlmInjectionClassifier,
+};
+pub use pii::{PiiConfig, PiiKind, PiiMatch, PiiRedactor, PiiRuleConfig, RedactingStore};
+pub use rules::{parse_moderation, ModerationGuardrail, PredicateGuardrail, RegexDenyList};
+
+/// 消息 metadata 中记录处置结果的字段
+pub const GUARDRAILS_METADATA_KEY: &str = "guardrails";
+/// 脱敏后的占位文本
+pub const REDACTED: &str = "[REDACTED]";
+
+/// 检查阶段
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailStage {
+    /// Agent 处理前的输入消息
+    Input,
+    /// Agent 产出的消息
+    Output,
+}
+
+/// 命中后的处置动作
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailAction {
+    #[default]
+    Block,
+    Redact,
+    Flag,
+}
+
+/// 单条违规
+#[derive(Clone, Debug, PartialEq)]
+pub struct GuardrailViolation {
+    pub category: String,
+    pub reason: String,
+    /// 命中的字节范围；为空时脱敏整条内容
+    pub span: Option<Range<usize>>,
+}
+
+/// 内容检查器
+#[async_trait]
+pub trait Guardrail: Send + Sync {
+    fn name(&self) -> &str;
+    async fn check(&self, stage: GuardrailStage, content: &str) -> Result<Vec<GuardrailViolation>>;
+}
+
+/// 写入 metadata 的处置记录
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GuardrailRecord {
+    pub stage: GuardrailStage,
+    pub guardrail: String,
+    pub category: String,
+    pub action: GuardrailAction,
+    pub reason: String,
+}
+
+/// 检查结果
+#[derive(Clone, Debug)]
+pub enum GuardrailDecision {
+    /// 放行（可能已脱敏或标记）
+    Allowed(AgentMessage),
+    /// 被拦截，`records` 中最后一条为触发拦截的规则
+    Blocked {
+        message: AgentMessage,
+        records: Vec<GuardrailRecord>,
+    },
+}
+
+#[derive(Clone)]
+struct GuardrailRule {
+    guardrail: Arc<dyn Guardrail>,
+    /// 为空时输入输出都检查
+    stage: Option<GuardrailStage>,
+    action: GuardrailAction,
+}
+
+/// 按顺序执行的一组护栏规则
+#[derive(Clone, Default)]
+pub struct GuardrailSet {
+    rules: Vec<GuardrailRule>,
+}
+
+impl GuardrailSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 检查输入消息
+    pub fn with_input(self, guardrail: Arc<dyn Guardrail>, action: GuardrailAction) -> Self {
+        self.with_rule(guardrail, Some(GuardrailStage::Input), action)
+    }
+
+    /// 检查输出消息
+    pub fn with_output(self, guardrail: Arc<dyn Guardrail>, action: GuardrailAction) -> Self {
+        self.with_rule(guardrail, Some(GuardrailStage::Output), action)
+    }
+
+    /// 输入输出都检查
+    pub fn with_both(self, guardrail: Arc<dyn Guardrail>, action: GuardrailAction) -> Self {
+        self.with_rule(guardrail, None, action)
+    }
+
+    fn with_rule(
+        mut self,
+        guardrail: Arc<dyn Guardrail>,
+        stage: Option<GuardrailStage>,
+        action: GuardrailAction,
+    ) -> Self {
+        self.rules.push(GuardrailRule {
+            guardrail,
+            stage,
+            action,
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 依次执行该阶段的规则；遇到 `block` 立即停止
+    pub async fn apply(
+        &self,
+        stage: GuardrailStage,
+        mut message: AgentMessage,
+    ) -> Result<GuardrailDecision> {
+        let mut records = Vec::new();
+        let mut blocked = false;
+        for rule in &self.rules {
+            if rule.stage.is_some_and(|s| s != stage) {
+                continue;
+            }
+            let violations = rule.guardrail.check(stage, &message.content).await?;
+            if violations.is_empty() {
+                continue;
+            }
+            records.extend(violations.iter().map(|violation| GuardrailRecord {
+                stage,
+                guardrail: rule.guardrail.name().to_string(),
+                category: violation.category.clone(),
+                action: rule.action,
+                reason: violation.reason.clone(),
+            }));
+            match rule.action {
+                GuardrailAction::Block => {
+                    blocked = true;
+                    break;
+                }
+                GuardrailAction::Redact => {
+                    message.content = redact(&message.content, &violations);
+                }
+                GuardrailAction::Flag => {}
+            }
+        }
+        if records.is_empty() {
+            return Ok(GuardrailDecision::Allowed(message));
+        }
+        record(&mut message, &records);
+        if blocked {
+            tracing::warn!(
+                stage = ?stage,
+                from = %message.from,
+                guardrail = %records.last().map(|r| r.guardrail.as_str()).unwrap_or_default(),
+                "message blocked by guardrail"
+            );
+            Ok(GuardrailDecision::Blocked { message, records })
+        } else {
+            Ok(GuardrailDecision::Allowed(message))
+        }
+    }
+}
+
+/// 替换命中范围；任一违规没有范围时整条替换
+fn redact(content: &str, violations: &[GuardrailViolation]) -> String {
+    let mut spans = Vec::with_capacity(violations.len());
+    for violation in violations {
+        match &violation.span {
+            Some(span) => spans.push(span.clone()),
+            None => return REDACTED.to_string(),
+        }
+    }
+    spans.sort_by_key(|span| span.start);
+    let mut redacted = String::with_capacity(content.len());
+    let mut cursor = 0;
+    for span in spans {
+        if span.end <= cursor {
+            continue;
+        }
+        if span.start >= cursor {
+            redacted.push_str(&content[cursor..span.start]);
+            redacted.push_str(REDACTED);
+        }
+        cursor = span.end;
+    }
+    redacted.push_str(&content[cursor..]);
+    redacted
+}
+
+fn record(message: &mut AgentMessage, records: &[GuardrailRecord]) {
+    let metadata = message.metadata.get_or_insert_with(|| json!({}));
+    let Some(object) = metadata.as_object_mut() else {
+        return;
+    };
+    let entry = object
+        .entry(GUARDRAILS_METADATA_KEY)
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Some(list) = entry.as_array_mut() {
+        list.extend(records.iter().map(|record| json!(record)));
+    }
+}
+
+/// 为 Agent 挂载护栏：处理前检查输入，处理后检查动作携带的消息和工具调用参数中的字符串值
+///
+/// 被拦截时返回 `AgentAction::Refused`（`content_filter`），流程按拒答结束。
+#[derive(Clone)]
+pub struct GuardedAgent {
+    inner: Arc<dyn Agent>,
+    guardrails: GuardrailSet,
+}
+
+impl GuardedAgent {
+    pub fn new(inner: Arc<dyn Agent>, guardrails: GuardrailSet) -> Self {
+        Self { inner, guardrails }
+    }
+
+    fn refuse(&self, stage: GuardrailStage, records: Vec<GuardrailRecord>) -> AgentAction {
+        let trigger = records.last().cloned();
+        let reason = trigger
+            .as_ref()
+            .map(|r| format!("{} {}: {}", r.guardrail, r.category, r.reason))
+            .unwrap_or_default();
+        let mut message = AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Agent,
+            from: self.inner.name().to_string(),
+            to: None,
+            content: format!("Blocked by guardrail ({:?}): {}", stage, reason),
+            metadata: None,
+            attachments: Vec::new(),
+        };
+        record(&mut message, &records);
+        AgentAction::Refused {
+            refusal: LlmRefusal {
+                kind: RefusalKind::ContentFilter,
+                reason,
+                code: Some("guardrail".to_string()),
+            },
+            message,
+        }
+    }
+}
+
+#[async_trait]
+impl Agent for GuardedAgent {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn on_start(&self, ctx: &AgentContext<'_>) -> Result<()> {
+        self.inner.on_start(ctx).await
+    }
+
+    async fn on_message(
+        &self,
+        message: AgentMessage,
+        ctx: &AgentContext<'_>,
+    ) -> Result<AgentAction> {
+        let message = match self
+            .guardrails
+            .apply(GuardrailStage::Input, message)
+            .await?
+        {
+            GuardrailDecision::Allowed(message) => message,
+            GuardrailDecision::Blocked { records, .. } => {
+                return Ok(self.refuse(GuardrailStage::Input, records))
+            }
+        };
+        let mut action = self.inner.on_message(message, ctx).await?;
+        let messages: Vec<&mut AgentMessage> = match &mut action {
+            AgentAction::Next { message, .. } | AgentAction::Refused { message, .. } => {
+                vec![message]
+            }
+            AgentAction::Branch { branches } => branches.values_mut().collect(),
+            AgentAction::Finish { message } | AgentAction::Continue { message } => {
+                message.iter_mut().collect()
+            }
+            AgentAction::CallTool { invocation, .. } => {
+                for value in string_values(&mut invocation.input) {
+                    let checked = AgentMessage::system(std::mem::take(value));
+                    match self
+                        .guardrails
+                        .apply(GuardrailStage::Output, checked)
+                        .await?
+                    {
+                        GuardrailDecision::Allowed(checked) => *value = checked.content,
+                        GuardrailDecision::Blocked { records, .. } => {
+                            return Ok(self.refuse(GuardrailStage::Output, records))
+                        }
+                    }
+                }
+                Vec::new()
+            }
+        };
+        for message in messages {
+            let checked = std::mem::replace(message, AgentMessage::system(""));
+            match self
+                .guardrails
+                .apply(GuardrailStage::Output, checked)
+                .await?
+            {
+                GuardrailDecision::Allowed(checked) => *message = checked,
+                GuardrailDecision::Blocked { records, .. } => {
+                    return Ok(self.refuse(GuardrailStage::Output, records))
+                }
+            }
+        }
+        Ok(action)
+    }
+
+    async fn on_finish(&self, ctx: &AgentContext<'_>) -> Result<()> {
+        self.inner.on_finish(ctx).await
+    }
+}
+
+/// JSON 中的所有字符串值（不含对象键）
+fn string_values(value: &mut Value) -> Vec<&mut String> {
+    match value {
+        Value::String(text) => vec![text],
+        Value::Array(items) => items.iter_mut().flat_map(string_values).collect(),
+        Value::Object(object) => object.values_mut().flat_map(string_values).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// JSON 配置中的护栏规则
+///
+/// ```json
+/// {
+///   "input": [{ "type": "deny_list", "patterns": ["(?i)password"], "action": "redact" }],
+///   "output": [{ "type": "moderation", "api_key": "${OPENAI_API_KEY}", "action": "block" }]
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct GuardrailsConfig {
+    #[serde(default)]
+    pub input: Vec<GuardrailRuleConfig>,
+    #[serde(default)]
+    pub output: Vec<GuardrailRuleConfig>,
+}
+
+/// 单条规则配置
+#[derive(Clone, Debug, Deserialize)]
+pub struct GuardrailRuleConfig {
+    #[serde(flatten)]
+    pub kind: GuardrailKindConfig,
+    #[serde(default)]
+    pub action: GuardrailAction,
+}
+
+/// 护栏类型
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GuardrailKindConfig {
+    DenyList {
+        #[serde(default)]
+        name: Option<String>,
+        patterns: Vec<String>,
+    },
+    /// OpenAI 兼容审核接口；`api_key` 支持 `${VAR_NAME}`，为空时读取 `OPENAI_API_KEY`
+    Moderation {
+        #[serde(default)]
+        endpoint: Option<String>,
+        #[serde(default)]
+        api_key: Option<String>,
+        #[serde(default)]
+        model: Option<String>,
+        /// 请求超时秒数，默认 `ModerationGuardrail::DEFAULT_TIMEOUT`
+        #[serde(default)]
+        timeout_secs: Option<u64>,
+    },
+}
+
+impl GuardrailKindConfig {
+    pub fn build(&self) -> Result<Arc<dyn Guardrail>> {
+        match self {
+            GuardrailKindConfig::DenyList { name, patterns } => Ok(Arc::new(RegexDenyList::new(
+                name.as_deref().unwrap_or("deny_list"),
+                patterns,
+            )?)),
+            GuardrailKindConfig::Moderation {
+                endpoint,
+                api_key,
+                model,
+                timeout_secs,
+            } => {
+                let api_key = match api_key {
+                    Some(value) if value.starts_with("${") && value.ends_with('}') => {
+                        EnvConfig::get_env(&value[2..value.len() - 1])?
+                    }
+                    Some(value) => value.clone(),
+                    None => EnvConfig::get_env("OPENAI_API_KEY")?,
+                };
+                let mut guardrail = ModerationGuardrail::new(api_key);
+                if let Some(endpoint) = endpoint {
+                    guardrail = guardrail.with_endpoint(endpoint.clone());
+                }
+                if let Some(model) = model {
+                    guardrail = guardrail.with_model(model.clone());
+                }
+                if let Some(secs) = timeout_secs {
+                    guardrail = guardrail.with_timeout(std::time::Duration::from_secs(*secs));
+                }
+                Ok(Arc::new(guardrail))
+            }
+        }
+    }
+}
+
+impl GuardrailsConfig {
+    pub fn build(&self) -> Result<GuardrailSet> {
+        let mut set = GuardrailSet::new();
+        for rule in &self.input {
+            set = set.with_input(rule.kind.build()?, rule.action);
+        }
+        for rule in &self.output {
+            set = set.with_output(rule.kind.build()?, rule.action);
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::testing::{self, EchoAgent};
+    use crate::tools::ToolRegistry;
+
+    fn guarded(guardrails: GuardrailSet) -> FlowExecutor {
+        let agent = Arc::new(GuardedAgent::new(Arc::new(EchoAgent), guardrails));
+        testing::executor("guarded", vec![("echo", agent)], |builder| {
+            builder
+                .add_agent_node("echo", "echo")
+                .add_terminal_node("done")
+                .set_start("echo");
+        })
+    }
+
+    fn ctx() -> Arc<FlowContext> {
+        Arc::new(FlowContext::new(Arc::new(MemoryStore::new())))
+    }
+
+    #[test]
+    fn test_redact_merges_overlapping_spans() {
+        let violation = |span: Range<usize>| GuardrailViolation {
+            category: "deny_list".into(),
+            reason: String::new(),
+            span: Some(span),
+        };
+        let redacted = redact(
+            "card 1234 5678 end",
+            &[violation(5..9), violation(7..14), violation(15..18)],
+        );
+        assert_eq!(redacted, "card [REDACTED] [REDACTED]");
+        let whole = GuardrailViolation {
+            span: None,
+            ..violation(0..1)
+        };
+        assert_eq!(redact("anything", &[violation(0..3), whole]), REDACTED);
+    }
+
+    #[tokio::test]
+    async fn test_input_redact_and_output_flag_are_recorded() {
+        let deny = RegexDenyList::new("secrets", [r"sk-[a-z0-9]+"]).unwrap();
+        let shouting = PredicateGuardrail::new("shouting", |_, content| {
+            content.contains('!').then(|| "exclamation".to_string())
+        });
+        let guardrails = GuardrailSet::new()
+            .with_input(Arc::new(deny), GuardrailAction::Redact)
+            .with_output(Arc::new(shouting), GuardrailAction::Flag);
+
+        let execution = guarded(guardrails)
+            .start(ctx(), AgentMessage::user("my key is sk-abc123!"))
+            .await
+            .unwrap();
+        let reply = execution.last_message.unwrap();
+        assert_eq!(reply.content, "my key is [REDACTED]!");
+        let records: Vec<GuardrailRecord> =
+            serde_json::from_value(reply.metadata.unwrap()[GUARDRAILS_METADATA_KEY].clone())
+                .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].stage, GuardrailStage::Input);
+        assert_eq!(records[0].action, GuardrailAction::Redact);
+        assert_eq!(records[1].guardrail, "shouting");
+        assert_eq!(records[1].action, GuardrailAction::Flag);
+    }
+
+    #[tokio::test]
+    async fn test_output_block_stops_flow() {
+        let config: GuardrailsConfig = serde_json::from_value(json!({
+            "output": [{ "type": "deny_list", "patterns": ["(?i)forbidden"] }]
+        }))
+        .unwrap();
+        let execution = guarded(config.build().unwrap())
+            .start(ctx(), AgentMessage::user("Forbidden topic"))
+            .await
+            .unwrap();
+        let message = execution.last_message.unwrap();
+        assert!(message.content.starts_with("Blocked by guardrail (Output)"));
+        assert_eq!(message.from, "echo");
+        let record = &message.metadata.unwrap()[GUARDRAILS_METADATA_KEY][0];
+        assert_eq!(record["stage"], "output");
+        assert_eq!(record["action"], "block");
+    }
+
+    /// 把输入作为 `lookup` 工具的 `query` 参数
+    struct LookupAgent;
+
+    #[async_trait]
+    impl Agent for LookupAgent {
+        fn name(&self) -> &'static str {
+            "lookup"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let input = json!({ "query": message.content, "filters": [message.content] });
+            Ok(AgentAction::CallTool {
+                tool: "lookup".into(),
+                invocation: crate::tools::ToolInvocation::new("lookup", input),
+                on_complete: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_output_rules_check_tool_arguments() {
+        let deny = RegexDenyList::new("secrets", [r"sk-[a-z0-9]+"]).unwrap();
+        let ctx = ctx();
+        let runtime = crate::runtime::ExecutorRuntime {
+            ctx: ctx.clone(),
+            tools: Arc::new(ToolRegistry::new()),
+        };
+        let agent_ctx = &AgentContext {
+            flow_ctx: &ctx,
+            runtime: &runtime,
+        };
+        let call = |action| {
+            let guardrails = GuardrailSet::new().with_output(Arc::new(deny.clone()), action);
+            let agent = GuardedAgent::new(Arc::new(LookupAgent), guardrails);
+            async move {
+                agent
+                    .on_message(AgentMessage::user("key sk-abc123"), agent_ctx)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let AgentAction::CallTool { invocation, .. } = call(GuardrailAction::Redact).await else {
+            panic!("expected the tool call to proceed");
+        };
+        assert_eq!(
+            invocation.input,
+            json!({ "query": "key [REDACTED]", "filters": ["key [REDACTED]"] })
+        );
+        let AgentAction::Refused { refusal, .. } = call(GuardrailAction::Block).await else {
+            panic!("expected the tool call to be refused");
+        };
+        assert_eq!(refusal.kind, RefusalKind::ContentFilter);
+    }
+
+    #[tokio::test]
+    async fn test_moderation_request_times_out() {
+        // 接受连接但不响应
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/moderations", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let _held: Vec<_> = listener.incoming().take(1).collect();
+            std::thread::sleep(std::time::Duration::from_secs(5));
+        });
+        let guardrail = ModerationGuardrail::new("key")
+            .with_endpoint(endpoint)
+            .with_timeout(std::time::Duration::from_millis(100));
+        let error = guardrail
+            .check(GuardrailStage::Output, "hello")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Moderation request failed"));
+    }
+
+    #[test]
+    fn test_parse_moderation_categories() {
+        let body = json!({ "results": [{
+            "flagged": true,
+            "categories": { "violence": true, "hate": false }
+        }] });
+        let violations = parse_moderation(&body);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].category, "violence");
+        assert!(parse_moderation(&json!({ "results": [{ "flagged": false }] })).is_empty());
+    }
+}
@@ -0,0 +1,545 @@
+//! 个人敏感信息（PII）识别与脱敏
+//!
+//! 挂载到 `FlowContext` 后，写入历史（及会话持久化、运行记录）的消息内容会先脱敏，
+//! 写入 `ContextStore` 的值、交互通道推送的事件和生命周期 webhook 同样脱敏；
+//! `LoggingConfig::init_with_redactor` 对日志输出做同样处理。
+
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{Guardrail, GuardrailStage, GuardrailViolation};
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::state::{ContextStore, StateChange, UpdateFn};
+
+/// 内置的 PII 类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Email,
+    /// 中国大陆手机号（可带 `+86`）与带国家码的国际号码
+    Phone,
+    /// 18 位居民身份证号
+    IdNumber,
+}
+
+impl PiiKind {
+    pub const ALL: [PiiKind; 3] = [PiiKind::IdNumber, PiiKind::Email, PiiKind::Phone];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "email",
+            PiiKind::Phone => "phone",
+            PiiKind::IdNumber => "id_number",
+        }
+    }
+
+    fn pattern(&self) -> &'static str {
+        match self {
+            PiiKind::Email => r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}",
+            PiiKind::Phone => r"(?:\+86[- ]?|\b)1[3-9]\d{9}\b|\+\d{1,3}(?:[- ]\d{2,5}){2,4}\b",
+            PiiKind::IdNumber => r"\b\d{17}[\dXx]\b",
+        }
+    }
+
+    fn replacement(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "[EMAIL]",
+            PiiKind::Phone => "[PHONE]",
+            PiiKind::IdNumber => "[ID_NUMBER]",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PiiRule {
+    name: String,
+    pattern: Regex,
+    replacement: String,
+}
+
+/// 命中的 PII 片段
+#[derive(Clone, Debug, PartialEq)]
+pub struct PiiMatch {
+    pub rule: String,
+    pub span: Range<usize>,
+}
+
+/// 按规则顺序替换 PII，先执行的规则优先
+#[derive(Clone, Debug, Default)]
+pub struct PiiRedactor {
+    rules: Vec<PiiRule>,
+}
+
+impl PiiRedactor {
+    /// 空的脱敏器，通过 `with_builtin` / `with_rule` 添加规则
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 启用全部内置规则（身份证号、邮箱、手机号）
+    pub fn builtin() -> Self {
+        PiiKind::ALL
+            .into_iter()
+            .fold(Self::new(), |redactor, kind| redactor.with_builtin(kind))
+    }
+
+    pub fn with_builtin(mut self, kind: PiiKind) -> Self {
+        if self.rules.iter().all(|rule| rule.name != kind.name()) {
+            self.rules.push(PiiRule {
+                name: kind.name().to_string(),
+                pattern: Regex::new(kind.pattern()).expect("builtin PII pattern"),
+                replacement: kind.replacement().to_string(),
+            });
+        }
+        self
+    }
+
+    /// 自定义规则，`replacement` 支持 `$1` 等捕获组引用
+    pub fn with_rule(
+        mut self,
+        name: impl Into<String>,
+        pattern: &str,
+        replacement: impl Into<String>,
+    ) -> Result<Self> {
+        let name = name.into();
+        let pattern = Regex::new(pattern)
+            .map_err(|e| AgentFlowError::Other(anyhow!("Invalid PII rule `{}`: {}", name, e)))?;
+        self.rules.push(PiiRule {
+            name,
+            pattern,
+            replacement: replacement.into(),
+        });
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 查找原文中的 PII 片段
+    pub fn detect(&self, text: &str) -> Vec<PiiMatch> {
+        let mut matches: Vec<PiiMatch> = Vec::new();
+        for rule in &self.rules {
+            for found in rule.pattern.find_iter(text) {
+                let span = found.range();
+                if matches
+                    .iter()
+                    .all(|m| span.end <= m.span.start || span.start >= m.span.end)
+                {
+                    matches.push(PiiMatch {
+                        rule: rule.name.clone(),
+                        span,
+                    });
+                }
+            }
+        }
+        matches.sort_by_key(|m| m.span.start);
+        matches
+    }
+
+    /// 脱敏文本，没有命中时不分配新字符串
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut redacted = Cow::Borrowed(text);
+        for rule in &self.rules {
+            if let Cow::Owned(replaced) = rule
+                .pattern
+                .replace_all(&redacted, rule.replacement.as_str())
+            {
+                redacted = Cow::Owned(replaced);
+            }
+        }
+        redacted
+    }
+
+    /// 脱敏消息内容与 metadata 中的字符串
+    pub fn redact_message(&self, message: &mut AgentMessage) {
+        if let Cow::Owned(content) = self.redact(&message.content) {
+            message.content = content;
+        }
+        if let Some(metadata) = &mut message.metadata {
+            self.redact_value(metadata);
+        }
+    }
+
+    /// 脱敏写入存储的原始值；JSON 对象或数组只替换其中的字符串，保持结构有效
+    pub fn redact_raw(&self, raw: String) -> String {
+        if let Cow::Borrowed(_) = self.redact(&raw) {
+            return raw;
+        }
+        match serde_json::from_str::<serde_json::Value>(&raw) {
+            Ok(mut value) if value.is_object() || value.is_array() => {
+                self.redact_value(&mut value);
+                value.to_string()
+            }
+            _ => self.redact(&raw).into_owned(),
+        }
+    }
+
+    fn redact_value(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => {
+                if let Cow::Owned(redacted) = self.redact(text) {
+                    *text = redacted;
+                }
+            }
+            serde_json::Value::Array(items) => {
+                items.iter_mut().for_each(|item| self.redact_value(item))
+            }
+            serde_json::Value::Object(map) => {
+                map.values_mut().for_each(|item| self.redact_value(item))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 写入前脱敏的存储包装，`FlowContext::with_redactor` 自动挂载
+pub struct RedactingStore {
+    inner: Arc<dyn ContextStore>,
+    redactor: Arc<PiiRedactor>,
+}
+
+impl RedactingStore {
+    pub fn new(inner: Arc<dyn ContextStore>, redactor: Arc<PiiRedactor>) -> Self {
+        Self { inner, redactor }
+    }
+}
+
+#[async_trait]
+impl ContextStore for RedactingStore {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        self.inner.get(key).await
+    }
+
+    async fn set(&self, key: &str, value: String) -> Result<()> {
+        self.inner.set(key, self.redactor.redact_raw(value)).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.inner.delete(key).await
+    }
+
+    async fn update(&self, key: &str, f: UpdateFn<'_>) -> Result<Option<String>> {
+        let redacted =
+            |current: Option<String>| Ok(f(current)?.map(|value| self.redactor.redact_raw(value)));
+        self.inner.update(key, &redacted).await
+    }
+
+    async fn compare_and_set(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        new: Option<String>,
+    ) -> Result<bool> {
+        let new = new.map(|value| self.redactor.redact_raw(value));
+        self.inner.compare_and_set(key, expected, new).await
+    }
+
+    async fn watch(&self, prefix: &str) -> Result<BoxStream<'static, StateChange>> {
+        self.inner.watch(prefix).await
+    }
+
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>> {
+        self.inner.keys(prefix).await
+    }
+}
+
+/// 作为护栏使用时，每个命中片段产生一条违规记录
+#[async_trait]
+impl Guardrail for PiiRedactor {
+    fn name(&self) -> &str {
+        "pii"
+    }
+
+    async fn check(
+        &self,
+        _stage: GuardrailStage,
+        content: &str,
+    ) -> Result<Vec<GuardrailViolation>> {
+        Ok(self
+            .detect(content)
+            .into_iter()
+            .map(|m| GuardrailViolation {
+                reason: format!("detected {}", m.rule),
+                category: m.rule,
+                span: Some(m.span),
+            })
+            .collect())
+    }
+}
+
+/// Flow 级 PII 配置
+///
+/// ```json
+/// {
+///   "builtin": ["email", "phone"],
+///   "rules": [{ "name": "order_id", "pattern": "ORD-\\d{8}", "replacement": "[ORDER]" }]
+/// }
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct PiiConfig {
+    /// 启用的内置规则，默认全部启用
+    #[serde(default = "PiiConfig::default_builtin")]
+    pub builtin: Vec<PiiKind>,
+    #[serde(default)]
+    pub rules: Vec<PiiRuleConfig>,
+}
+
+/// 自定义正则规则
+#[derive(Clone, Debug, Deserialize)]
+pub struct PiiRuleConfig {
+    pub name: String,
+    pub pattern: String,
+    /// 替换文本，默认 `[REDACTED]`
+    #[serde(default)]
+    pub replacement: Option<String>,
+}
+
+impl PiiConfig {
+    fn default_builtin() -> Vec<PiiKind> {
+        PiiKind::ALL.to_vec()
+    }
+
+    pub fn build(&self) -> Result<PiiRedactor> {
+        let mut redactor = self
+            .builtin
+            .iter()
+            .fold(PiiRedactor::new(), |redactor, kind| {
+                redactor.with_builtin(*kind)
+            });
+        for rule in &self.rules {
+            redactor = redactor.with_rule(
+                rule.name.clone(),
+                &rule.pattern,
+                rule.replacement.as_deref().unwrap_or(super::REDACTED),
+            )?;
+        }
+        Ok(redactor)
+    }
+}
+
+impl Default for PiiConfig {
+    fn default() -> Self {
+        Self {
+            builtin: Self::default_builtin(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_builtin_patterns() {
+        let redactor = PiiRedactor::builtin();
+        let text = "联系 alice.w@example.com.cn 或 13812345678，+86 13987654321，\
+                    +1 415 555 0100，身份证 11010519491231002X，订单 2024010112345";
+        assert_eq!(
+            redactor.redact(text),
+            "联系 [EMAIL] 或 [PHONE]，[PHONE]，[PHONE]，身份证 [ID_NUMBER]，订单 2024010112345"
+        );
+        assert!(matches!(redactor.redact("nothing here"), Cow::Borrowed(_)));
+
+        let matches = redactor.detect("id 110105194912310021 tel 13812345678");
+        let rules: Vec<_> = matches.iter().map(|m| m.rule.as_str()).collect();
+        assert_eq!(rules, vec!["id_number", "phone"]);
+    }
+
+    #[tokio::test]
+    async fn test_flow_redactor_applies_to_history_and_run_record() {
+        use crate::agent::AgentRegistry;
+        use crate::flow::FlowBuilder;
+        use crate::runtime::{FlowExecutor, HistoryFilter, MemoryRunStore};
+        use crate::state::{FlowContext, MemoryStore};
+        use crate::tools::ToolRegistry;
+        use std::sync::Arc;
+
+        let mut builder = FlowBuilder::new("support");
+        builder
+            .add_terminal_node("done")
+            .set_start("done")
+            .set_pii_redactor(Arc::new(PiiRedactor::builtin()));
+        let executor =
+            FlowExecutor::new(builder.build(), AgentRegistry::new(), ToolRegistry::new())
+                .with_run_store(Arc::new(MemoryRunStore::new()));
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        executor
+            .start(ctx.clone(), AgentMessage::user("call me at 13812345678"))
+            .await
+            .unwrap();
+
+        assert!(ctx
+            .history()
+            .iter()
+            .all(|message| !message.content.contains("13812345678")));
+        assert_eq!(ctx.history()[0].content, "call me at [PHONE]");
+        let record = &executor.history(HistoryFilter::default()).await.unwrap()[0];
+        assert_eq!(
+            record.final_message.as_ref().unwrap().content,
+            "call me at [PHONE]"
+        );
+    }
+
+    #[test]
+    fn test_config_rules_and_message_metadata() {
+        let config: PiiConfig = serde_json::from_value(json!({
+            "builtin": ["email"],
+            "rules": [{ "name": "order", "pattern": "ORD-(\\d{4})\\d{4}", "replacement": "ORD-$1****" }]
+        }))
+        .unwrap();
+        let redactor = config.build().unwrap();
+        let mut message = AgentMessage::user("ORD-12345678 from bob@example.com, 13812345678");
+        message.metadata = Some(json!({ "contact": { "email": "bob@example.com" } }));
+        redactor.redact_message(&mut message);
+        assert_eq!(message.content, "ORD-1234**** from [EMAIL], 13812345678");
+        assert_eq!(message.metadata.unwrap()["contact"]["email"], "[EMAIL]");
+
+        let invalid: PiiConfig =
+            serde_json::from_value(json!({ "rules": [{ "name": "bad", "pattern": "(" }] }))
+                .unwrap();
+        assert!(invalid.build().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_redacting_store() {
+        use crate::state::{ContextStore, MemoryStore};
+        use std::sync::Arc;
+
+        let inner = Arc::new(MemoryStore::new());
+        let store = RedactingStore::new(inner.clone(), Arc::new(PiiRedactor::builtin()));
+        store
+            .set("note", "mail bob@example.com".into())
+            .await
+            .unwrap();
+        assert_eq!(inner.get("note").await.unwrap().unwrap(), "mail [EMAIL]");
+
+        // JSON 值只替换字符串，结构和数字保持不变
+        let raw = json!({ "to": "bob@example.com", "at": 110105194912310021u64 }).to_string();
+        store.set("state", raw).await.unwrap();
+        let stored: serde_json::Value =
+            serde_json::from_str(&inner.get("state").await.unwrap().unwrap()).unwrap();
+        assert_eq!(
+            stored,
+            json!({ "to": "[EMAIL]", "at": 110105194912310021u64 })
+        );
+
+        let clean = r#"{ "b": 1, "a": 2 }"#;
+        store.set("clean", clean.into()).await.unwrap();
+        assert_eq!(inner.get("clean").await.unwrap().unwrap(), clean);
+
+        assert!(store
+            .compare_and_set("cas", None, Some("13812345678".into()))
+            .await
+            .unwrap());
+        store
+            .update("cas", &|old| Ok(old.map(|v| format!("{v} / 13987654321"))))
+            .await
+            .unwrap();
+        assert_eq!(
+            inner.get("cas").await.unwrap().unwrap(),
+            "[PHONE] / [PHONE]"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_flow_redactor_applies_to_run_visits_and_store() {
+        use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentRegistry};
+        use crate::flow::FlowBuilder;
+        use crate::runtime::{FlowExecutor, HistoryFilter, MemoryRunStore};
+        use crate::state::{FlowContext, MemoryStore};
+        use crate::tools::ToolRegistry;
+        use std::sync::Arc;
+
+        /// 把收到的消息原样写入存储
+        struct Remember;
+
+        #[async_trait]
+        impl Agent for Remember {
+            fn name(&self) -> &'static str {
+                "remember"
+            }
+
+            async fn on_message(
+                &self,
+                message: AgentMessage,
+                ctx: &AgentContext<'_>,
+            ) -> Result<AgentAction> {
+                ctx.flow().store().set("last", message.content).await?;
+                Ok(AgentAction::Finish { message: None })
+            }
+        }
+
+        let mut agents = AgentRegistry::new();
+        register_agent("remember", Arc::new(Remember), &mut agents);
+        let mut builder = FlowBuilder::new("support");
+        builder
+            .add_agent_node("remember", "remember")
+            .set_start("remember")
+            .set_pii_redactor(Arc::new(PiiRedactor::builtin()));
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_run_store(Arc::new(MemoryRunStore::new()));
+        let store = Arc::new(MemoryStore::new());
+        let ctx = Arc::new(FlowContext::new(store.clone()));
+        executor
+            .start(ctx, AgentMessage::user("mail bob@example.com"))
+            .await
+            .unwrap();
+
+        assert_eq!(store.get("last").await.unwrap().unwrap(), "mail [EMAIL]");
+        let record = &executor.history(HistoryFilter::default()).await.unwrap()[0];
+        let input = record.nodes[0].input.as_ref().unwrap();
+        assert_eq!(input.content, "mail [EMAIL]");
+    }
+
+    #[tokio::test]
+    async fn test_flow_redactor_applies_to_channel_updates() {
+        use crate::agent::AgentRegistry;
+        use crate::flow::FlowBuilder;
+        use crate::runtime::{FlowExecutor, RunChannel, RunUpdate, TokenSink};
+        use crate::state::{FlowContext, MemoryStore};
+        use crate::tools::ToolRegistry;
+        use std::sync::Arc;
+
+        let mut builder = FlowBuilder::new("support");
+        builder
+            .add_terminal_node("done")
+            .set_start("done")
+            .set_pii_redactor(Arc::new(PiiRedactor::builtin()));
+        let executor =
+            FlowExecutor::new(builder.build(), AgentRegistry::new(), ToolRegistry::new());
+        let channel = RunChannel::new(16);
+        let mut updates = channel.subscribe();
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new())).with_channel(channel);
+        executor
+            .start(Arc::new(ctx), AgentMessage::user("call 13812345678"))
+            .await
+            .unwrap();
+        let finished = std::iter::from_fn(|| updates.try_recv().ok())
+            .find(RunUpdate::is_final)
+            .unwrap();
+        assert!(matches!(
+            finished,
+            RunUpdate::Finished { message: Some(message), .. } if message.content == "call [PHONE]"
+        ));
+
+        let channel = RunChannel::new(16);
+        let mut updates = channel.subscribe();
+        let redacted = channel.with_redactor(Arc::new(PiiRedactor::builtin()));
+        redacted.on_token("writer", "bob@example.com");
+        assert!(matches!(
+            updates.try_recv().unwrap(),
+            RunUpdate::Chunk { content, .. } if content == "[EMAIL]"
+        ));
+    }
+}
@@ -0,0 +1,216 @@
+//! 内置护栏：正则黑名单、审核接口、自定义谓词
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::{json, Value};
+
+use super::{Guardrail, GuardrailStage, GuardrailViolation};
+use crate::error::{AgentFlowError, Result};
+
+/// 正则黑名单，每个匹配位置产生一条违规记录
+#[derive(Clone, Debug)]
+pub struct RegexDenyList {
+    name: String,
+    patterns: Vec<Regex>,
+}
+
+impl RegexDenyList {
+    pub fn new<I, S>(name: impl Into<String>, patterns: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns = patterns
+            .into_iter()
+            .map(|pattern| {
+                Regex::new(pattern.as_ref()).map_err(|e| {
+                    AgentFlowError::Other(anyhow!(
+                        "Invalid guardrail pattern `{}`: {}",
+                        pattern.as_ref(),
+                        e
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            name: name.into(),
+            patterns,
+        })
+    }
+}
+
+#[async_trait]
+impl Guardrail for RegexDenyList {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(
+        &self,
+        _stage: GuardrailStage,
+        content: &str,
+    ) -> Result<Vec<GuardrailViolation>> {
+        Ok(self
+            .patterns
+            .iter()
+            .flat_map(|pattern| {
+                pattern.find_iter(content).map(|found| GuardrailViolation {
+                    category: "deny_list".to_string(),
+                    reason: format!("matched `{}`", pattern.as_str()),
+                    span: Some(found.range()),
+                })
+            })
+            .collect())
+    }
+}
+
+/// 调用 OpenAI 兼容的 `/moderations` 接口，每个命中的类别产生一条违规记录
+#[derive(Clone)]
+pub struct ModerationGuardrail {
+    client: reqwest::Client,
+    endpoint: String,
+    api_key: String,
+    model: String,
+    timeout: Duration,
+}
+
+impl ModerationGuardrail {
+    pub const DEFAULT_ENDPOINT: &'static str = "https://api.openai.com/v1/moderations";
+    pub const DEFAULT_MODEL: &'static str = "omni-moderation-latest";
+    /// 单次审核请求的默认超时，超时视为检查失败
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: Self::DEFAULT_ENDPOINT.to_string(),
+            api_key: api_key.into(),
+            model: Self::DEFAULT_MODEL.to_string(),
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+}
+
+/// 解析审核结果中命中的类别
+pub fn parse_moderation(body: &Value) -> Vec<GuardrailViolation> {
+    let Some(results) = body["results"].as_array() else {
+        return Vec::new();
+    };
+    results
+        .iter()
+        .filter(|result| result["flagged"].as_bool().unwrap_or(false))
+        .flat_map(|result| {
+            let categories: Vec<String> = result["categories"]
+                .as_object()
+                .map(|categories| {
+                    categories
+                        .iter()
+                        .filter(|(_, flagged)| flagged.as_bool().unwrap_or(false))
+                        .map(|(category, _)| category.clone())
+                        .collect()
+                })
+                .unwrap_or_default();
+            if categories.is_empty() {
+                vec!["flagged".to_string()]
+            } else {
+                categories
+            }
+        })
+        .map(|category| GuardrailViolation {
+            reason: format!("moderation flagged `{}`", category),
+            category,
+            span: None,
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Guardrail for ModerationGuardrail {
+    fn name(&self) -> &str {
+        "moderation"
+    }
+
+    async fn check(
+        &self,
+        _stage: GuardrailStage,
+        content: &str,
+    ) -> Result<Vec<GuardrailViolation>> {
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .timeout(self.timeout)
+            .json(&json!({ "model": self.model, "input": content }))
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Moderation request failed: {}", e)))?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Moderation API returned {}: {}",
+                status,
+                body
+            )));
+        }
+        Ok(parse_moderation(&body))
+    }
+}
+
+type Predicate = dyn Fn(GuardrailStage, &str) -> Option<String> + Send + Sync;
+
+/// 自定义 Rust 谓词，返回 `Some(reason)` 表示违规
+#[derive(Clone)]
+pub struct PredicateGuardrail {
+    name: String,
+    predicate: Arc<Predicate>,
+}
+
+impl PredicateGuardrail {
+    pub fn new(
+        name: impl Into<String>,
+        predicate: impl Fn(GuardrailStage, &str) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            predicate: Arc::new(predicate),
+        }
+    }
+}
+
+#[async_trait]
+impl Guardrail for PredicateGuardrail {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self, stage: GuardrailStage, content: &str) -> Result<Vec<GuardrailViolation>> {
+        Ok((self.predicate)(stage, content)
+            .map(|reason| GuardrailViolation {
+                category: self.name.clone(),
+                reason,
+                span: None,
+            })
+            .into_iter()
+            .collect())
+    }
+}
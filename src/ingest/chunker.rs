@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+
+/// 分块策略
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum ChunkStrategy {
+    /// 固定字符数窗口，相邻块重叠 `overlap` 个字符
+    Fixed {
+        #[serde(default = "default_fixed_size")]
+        size: usize,
+        #[serde(default = "default_overlap")]
+        overlap: usize,
+    },
+    /// 按句子切分，再合并到不超过 `max_chars`
+    Sentence {
+        #[serde(default = "default_fixed_size")]
+        max_chars: usize,
+    },
+    /// 按 Markdown 标题切分章节，过长章节再按句子切分；代码块内的 `#` 不视为标题
+    Markdown {
+        #[serde(default = "default_markdown_size")]
+        max_chars: usize,
+    },
+}
+
+impl Default for ChunkStrategy {
+    fn default() -> Self {
+        Self::Sentence {
+            max_chars: default_fixed_size(),
+        }
+    }
+}
+
+fn default_fixed_size() -> usize {
+    800
+}
+
+fn default_overlap() -> usize {
+    100
+}
+
+fn default_markdown_size() -> usize {
+    1200
+}
+
+/// 文本块
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub index: usize,
+    pub text: String,
+    /// 所属章节标题（Markdown 策略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+}
+
+impl ChunkStrategy {
+    /// 切分文本，跳过空白块
+    pub fn chunk(&self, text: &str) -> Vec<Chunk> {
+        let pieces: Vec<(Option<String>, String)> = match self {
+            Self::Fixed { size, overlap } => split_fixed(text, *size, *overlap)
+                .into_iter()
+                .map(|piece| (None, piece))
+                .collect(),
+            Self::Sentence { max_chars } => pack_sentences(text, *max_chars)
+                .into_iter()
+                .map(|piece| (None, piece))
+                .collect(),
+            Self::Markdown { max_chars } => split_markdown(text)
+                .into_iter()
+                .flat_map(|(heading, body)| {
+                    let pieces = if body.chars().count() <= *max_chars {
+                        vec![body]
+                    } else {
+                        pack_sentences(&body, *max_chars)
+                    };
+                    pieces
+                        .into_iter()
+                        .map(move |piece| (heading.clone(), piece))
+                })
+                .collect(),
+        };
+
+        pieces
+            .into_iter()
+            .map(|(heading, text)| (heading, text.trim().to_string()))
+            .filter(|(_, text)| !text.is_empty())
+            .enumerate()
+            .map(|(index, (heading, text))| Chunk {
+                index,
+                text,
+                heading,
+            })
+            .collect()
+    }
+}
+
+fn split_fixed(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let size = size.max(1);
+    let step = size.saturating_sub(overlap).max(1);
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let end = (start + size).min(chars.len());
+        pieces.push(chars[start..end].iter().collect());
+        if end == chars.len() {
+            break;
+        }
+        start += step;
+    }
+    pieces
+}
+
+/// 按句末标点（中英文）和空行切分句子，保留标点
+fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        current.push(c);
+        let boundary = match c {
+            '。' | '！' | '？' | '；' | '\n' => true,
+            '.' | '!' | '?' => chars.peek().is_none_or(|next| next.is_whitespace()),
+            _ => false,
+        };
+        if boundary {
+            if !current.trim().is_empty() {
+                sentences.push(std::mem::take(&mut current));
+            } else {
+                current.clear();
+            }
+        }
+    }
+    if !current.trim().is_empty() {
+        sentences.push(current);
+    }
+    sentences
+}
+
+fn pack_sentences(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    for sentence in split_sentences(text) {
+        let len = sentence.chars().count();
+        if current_len + len > max_chars && current_len > 0 {
+            pieces.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        if len > max_chars {
+            // 超长句子退化为固定窗口切分
+            pieces.extend(split_fixed(&sentence, max_chars, 0));
+            continue;
+        }
+        current.push_str(&sentence);
+        current_len += len;
+    }
+    if current_len > 0 {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// 按标题切分 Markdown 章节，返回 (标题路径, 章节内容)
+fn split_markdown(text: &str) -> Vec<(Option<String>, String)> {
+    let mut sections = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut body = String::new();
+    let mut in_code_block = false;
+
+    let heading_path = |headings: &[(usize, String)]| {
+        (!headings.is_empty()).then(|| {
+            headings
+                .iter()
+                .map(|(_, title)| title.as_str())
+                .collect::<Vec<_>>()
+                .join(" > ")
+        })
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        let is_heading =
+            !in_code_block && (1..=6).contains(&level) && trimmed[level..].starts_with(' ');
+        if is_heading {
+            if !body.trim().is_empty() {
+                sections.push((heading_path(&headings), std::mem::take(&mut body)));
+            }
+            body.clear();
+            headings.retain(|(l, _)| *l < level);
+            headings.push((level, trimmed[level..].trim().to_string()));
+            body.push_str(line);
+            body.push('\n');
+            continue;
+        }
+        body.push_str(line);
+        body.push('\n');
+    }
+    if !body.trim().is_empty() {
+        sections.push((heading_path(&headings), body));
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_chunks_overlap() {
+        let chunks = ChunkStrategy::Fixed {
+            size: 4,
+            overlap: 1,
+        }
+        .chunk("abcdefghij");
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["abcd", "defg", "ghij"]);
+    }
+
+    #[test]
+    fn test_sentence_chunks_pack_sentences() {
+        let chunks = ChunkStrategy::Sentence { max_chars: 20 }
+            .chunk("First one. Second one. 第三句。Version 1.2 is out!");
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            ["First one.", "Second one. 第三句。", "Version 1.2 is out!"]
+        );
+    }
+
+    #[test]
+    fn test_markdown_chunks_keep_heading_path() {
+        let text =
+            "# Guide\nIntro.\n## Install\nRun it.\n```\n# not a heading\n```\n## Usage\nCall it.";
+        let chunks = ChunkStrategy::Markdown { max_chars: 200 }.chunk(text);
+        let headings: Vec<Option<&str>> = chunks.iter().map(|c| c.heading.as_deref()).collect();
+        assert_eq!(
+            headings,
+            [
+                Some("Guide"),
+                Some("Guide > Install"),
+                Some("Guide > Usage")
+            ]
+        );
+        assert!(chunks[1].text.contains("# not a heading"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{AgentFlowError, Result};
+use crate::tools::web_crawler::{extract_title, html_to_text};
+
+/// 待导入的数据源
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IngestSource {
+    /// 直接提供文本
+    Text {
+        id: String,
+        text: String,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        metadata: Value,
+    },
+    /// 本地文件（`.html`/`.htm` 会提取正文，其余按 UTF-8 文本读取）
+    File {
+        path: String,
+        #[serde(default)]
+        metadata: Value,
+    },
+    /// 网页或远程文本
+    Url {
+        url: String,
+        #[serde(default)]
+        metadata: Value,
+    },
+}
+
+impl IngestSource {
+    pub fn text(id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::Text {
+            id: id.into(),
+            text: text.into(),
+            title: None,
+            metadata: Value::Null,
+        }
+    }
+
+    pub fn file(path: impl Into<String>) -> Self {
+        Self::File {
+            path: path.into(),
+            metadata: Value::Null,
+        }
+    }
+
+    pub fn url(url: impl Into<String>) -> Self {
+        Self::Url {
+            url: url.into(),
+            metadata: Value::Null,
+        }
+    }
+
+    /// 数据源标识（文件路径或 URL），也用作文档 ID
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Text { id, .. } => id,
+            Self::File { path, .. } => path,
+            Self::Url { url, .. } => url,
+        }
+    }
+}
+
+/// 提取出的文档
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub id: String,
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub text: String,
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+/// 读取数据源并提取文本
+#[derive(Clone)]
+pub struct DocumentLoader {
+    client: Client,
+}
+
+impl DocumentLoader {
+    pub fn new(timeout: Duration) -> Self {
+        let client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+        Self { client }
+    }
+
+    pub async fn load(&self, source: &IngestSource) -> Result<Document> {
+        match source {
+            IngestSource::Text {
+                id,
+                text,
+                title,
+                metadata,
+            } => Ok(Document {
+                id: id.clone(),
+                source: "text".to_string(),
+                title: title.clone(),
+                text: text.clone(),
+                metadata: metadata.clone(),
+            }),
+            IngestSource::File { path, metadata } => {
+                let raw = std::fs::read_to_string(path).map_err(|e| {
+                    AgentFlowError::Other(anyhow!("Failed to read {}: {}", path, e))
+                })?;
+                let is_html = Path::new(path)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| {
+                        ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm")
+                    });
+                let (title, text) = if is_html {
+                    (extract_title(&raw), html_to_text(&raw))
+                } else {
+                    let title = Path::new(path)
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .map(str::to_string);
+                    (title, raw)
+                };
+                Ok(Document {
+                    id: path.clone(),
+                    source: path.clone(),
+                    title,
+                    text,
+                    metadata: metadata.clone(),
+                })
+            }
+            IngestSource::Url { url, metadata } => {
+                let response = self.client.get(url).send().await.map_err(|e| {
+                    AgentFlowError::Other(anyhow!("Failed to fetch {}: {}", url, e))
+                })?;
+                let status = response.status();
+                if !status.is_success() {
+                    return Err(AgentFlowError::Other(anyhow!(
+                        "Failed to fetch {}: HTTP {}",
+                        url,
+                        status
+                    )));
+                }
+                let is_html = response
+                    .headers()
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .is_some_and(|value| value.contains("html"));
+                let body = response
+                    .text()
+                    .await
+                    .map_err(|e| AgentFlowError::Other(anyhow!("Failed to read {}: {}", url, e)))?;
+                let (title, text) = if is_html {
+                    (extract_title(&body), html_to_text(&body))
+                } else {
+                    (None, body)
+                };
+                Ok(Document {
+                    id: url.clone(),
+                    source: url.clone(),
+                    title,
+                    text,
+                    metadata: metadata.clone(),
+                })
+            }
+        }
+    }
+}
+
+impl Default for DocumentLoader {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
@@ -0,0 +1,14 @@
+//! 文档导入
+//!
+//! 读取文件/URL/文本，按策略分块，计算 embedding 后写入 `VectorStore`。
+//! 既可直接调用 `IngestPipeline`，也可通过 `rag.ingest` 工具或 `ToolPipeline` 使用。
+
+pub mod chunker;
+pub mod loader;
+pub mod pipeline;
+pub mod tool;
+
+pub use chunker::{Chunk, ChunkStrategy};
+pub use loader::{Document, DocumentLoader, IngestSource};
+pub use pipeline::{DocumentReport, IngestConfig, IngestPipeline, IngestReport};
+pub use tool::IngestTool;
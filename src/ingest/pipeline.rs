@@ -0,0 +1,245 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use super::chunker::ChunkStrategy;
+use super::loader::{Document, DocumentLoader, IngestSource};
+use crate::error::{AgentFlowError, Result};
+use crate::tools::vector::{DynVectorStore, Embedder, VectorRecord};
+
+/// 导入配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IngestConfig {
+    #[serde(default)]
+    pub chunking: ChunkStrategy,
+    /// 每次 embedding 请求的文本块数量
+    #[serde(default = "IngestConfig::default_batch_size")]
+    pub batch_size: usize,
+    /// 拉取 URL 的超时时间
+    #[serde(default = "IngestConfig::default_fetch_timeout_secs")]
+    pub fetch_timeout_secs: u64,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            chunking: ChunkStrategy::default(),
+            batch_size: Self::default_batch_size(),
+            fetch_timeout_secs: Self::default_fetch_timeout_secs(),
+        }
+    }
+}
+
+impl IngestConfig {
+    fn default_batch_size() -> usize {
+        16
+    }
+
+    fn default_fetch_timeout_secs() -> u64 {
+        30
+    }
+}
+
+/// 单个数据源的导入结果
+#[derive(Clone, Debug, Serialize)]
+pub struct DocumentReport {
+    pub id: String,
+    pub chunks: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 导入报告
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct IngestReport {
+    pub documents: Vec<DocumentReport>,
+}
+
+impl IngestReport {
+    pub fn total_chunks(&self) -> usize {
+        self.documents.iter().map(|d| d.chunks).sum()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.documents.iter().filter(|d| d.error.is_some()).count()
+    }
+}
+
+/// 文档导入流水线：提取文本 → 分块 → 计算 embedding → 写入向量存储
+///
+/// 文本块 ID 为 `{文档 ID}#{序号}`，重复导入同一文档会覆盖已有文本块。
+#[derive(Clone)]
+pub struct IngestPipeline {
+    store: DynVectorStore,
+    embedder: Arc<dyn Embedder>,
+    loader: DocumentLoader,
+    config: IngestConfig,
+}
+
+impl IngestPipeline {
+    pub fn new(store: DynVectorStore, embedder: Arc<dyn Embedder>) -> Self {
+        Self::with_config(store, embedder, IngestConfig::default())
+    }
+
+    pub fn with_config(
+        store: DynVectorStore,
+        embedder: Arc<dyn Embedder>,
+        config: IngestConfig,
+    ) -> Self {
+        let loader = DocumentLoader::new(Duration::from_secs(config.fetch_timeout_secs.max(1)));
+        Self {
+            store,
+            embedder,
+            loader,
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &IngestConfig {
+        &self.config
+    }
+
+    pub fn store(&self) -> &DynVectorStore {
+        &self.store
+    }
+
+    /// 导入多个数据源；单个数据源失败不会中断其他数据源
+    pub async fn ingest(&self, sources: &[IngestSource]) -> IngestReport {
+        let mut report = IngestReport::default();
+        for source in sources {
+            let outcome = async {
+                let document = self.loader.load(source).await?;
+                self.ingest_document(&document).await
+            }
+            .await;
+            let document = match outcome {
+                Ok(chunks) => DocumentReport {
+                    id: source.id().to_string(),
+                    chunks,
+                    error: None,
+                },
+                Err(err) => {
+                    warn!(source = source.id(), %err, "Document ingestion failed");
+                    DocumentReport {
+                        id: source.id().to_string(),
+                        chunks: 0,
+                        error: Some(err.to_string()),
+                    }
+                }
+            };
+            report.documents.push(document);
+        }
+        info!(
+            store = self.store.name(),
+            documents = report.documents.len(),
+            chunks = report.total_chunks(),
+            failed = report.failed(),
+            "Ingestion completed"
+        );
+        report
+    }
+
+    /// 导入已提取的文档，返回写入的文本块数量
+    pub async fn ingest_document(&self, document: &Document) -> Result<usize> {
+        let chunks = self.config.chunking.chunk(&document.text);
+        let mut written = 0;
+        for batch in chunks.chunks(self.config.batch_size.max(1)) {
+            let texts: Vec<String> = batch.iter().map(|chunk| chunk.text.clone()).collect();
+            let vectors = self.embedder.embed(texts).await?;
+            if vectors.len() != batch.len() {
+                return Err(AgentFlowError::Other(anyhow!(
+                    "Embedder returned {} vectors for {} chunks",
+                    vectors.len(),
+                    batch.len()
+                )));
+            }
+            let records = batch
+                .iter()
+                .zip(vectors)
+                .map(|(chunk, vector)| {
+                    let mut metadata = match &document.metadata {
+                        Value::Object(map) => map.clone(),
+                        _ => Default::default(),
+                    };
+                    metadata.insert("doc_id".into(), json!(document.id));
+                    metadata.insert("source".into(), json!(document.source));
+                    metadata.insert("chunk_index".into(), json!(chunk.index));
+                    if let Some(title) = &document.title {
+                        metadata.insert("title".into(), json!(title));
+                    }
+                    if let Some(heading) = &chunk.heading {
+                        metadata.insert("heading".into(), json!(heading));
+                    }
+                    VectorRecord::new(
+                        format!("{}#{}", document.id, chunk.index),
+                        vector,
+                        chunk.text.clone(),
+                    )
+                    .with_metadata(Value::Object(metadata))
+                })
+                .collect::<Vec<_>>();
+            written += records.len();
+            self.store.upsert(records).await?;
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::vector::{InMemoryVectorStore, VectorQuery, VectorStore};
+    use async_trait::async_trait;
+
+    struct LengthEmbedder;
+
+    #[async_trait]
+    impl Embedder for LengthEmbedder {
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|t| vec![1.0, t.len() as f32]).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ingest_sources_into_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("guide.md");
+        std::fs::write(&path, "# Guide\nHello.\n## Setup\nInstall it.").unwrap();
+
+        let store = Arc::new(InMemoryVectorStore::default());
+        let pipeline = IngestPipeline::with_config(
+            store.clone(),
+            Arc::new(LengthEmbedder),
+            IngestConfig {
+                chunking: ChunkStrategy::Markdown { max_chars: 200 },
+                batch_size: 1,
+                ..Default::default()
+            },
+        );
+        let report = pipeline
+            .ingest(&[
+                IngestSource::file(path.to_string_lossy()),
+                IngestSource::text("faq", "Refunds take 7 days."),
+                IngestSource::file(dir.path().join("missing.md").to_string_lossy()),
+            ])
+            .await;
+
+        assert_eq!(report.total_chunks(), 3);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(store.len(), 3);
+
+        let hits = store
+            .query(
+                VectorQuery::new(vec![1.0, 0.0], 10).with_filter("heading", json!("Guide > Setup")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].metadata["title"], "guide.md");
+        assert!(hits[0].id.ends_with("#1"));
+    }
+}
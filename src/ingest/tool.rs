@@ -0,0 +1,113 @@
+//! 文档导入工具 - `rag.ingest`（内置工具）
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::loader::IngestSource;
+use super::pipeline::{IngestConfig, IngestPipeline};
+use crate::agent::{AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::state::FlowContext;
+use crate::tools::orchestrator::{ToolPipeline, ToolStep, ToolStrategy};
+use crate::tools::tool::{Tool, ToolInvocation};
+use crate::tools::vector::{EmbeddingConfig, HttpEmbedder, VectorStoreConfig};
+
+/// 文档导入工具
+///
+/// 输入参数（任选其一）：
+/// - sources: 数据源数组，例如 `[{"type": "file", "path": "docs/a.md"}, {"type": "url", "url": "..."}]`
+/// - path / url: 单个文件或网页
+/// - text + id: 直接导入文本
+///
+/// 输出：`documents` 数组，每项包含 `id`、`chunks`、`error`。
+#[derive(Clone)]
+pub struct IngestTool {
+    pipeline: IngestPipeline,
+}
+
+impl IngestTool {
+    pub fn new(pipeline: IngestPipeline) -> Self {
+        Self { pipeline }
+    }
+
+    /// 以 `ToolPipeline` 形式暴露导入流程，数据源通过执行参数传入：
+    /// `orchestrator.execute_pipeline_with_params(name, json!({"sources": [...]}), ctx)`
+    pub fn tool_pipeline(name: impl Into<String>) -> ToolPipeline {
+        ToolPipeline::new(
+            name,
+            ToolStrategy::Sequential(vec![ToolStep::new("rag.ingest", json!({}))]),
+        )
+    }
+
+    fn parse_sources(input: &Value) -> Result<Vec<IngestSource>> {
+        if let Some(sources) = input.get("sources") {
+            return serde_json::from_value(sources.clone()).map_err(|e| {
+                AgentFlowError::Other(anyhow::anyhow!("Invalid ingest sources: {}", e))
+            });
+        }
+        if let Some(path) = input["path"].as_str() {
+            return Ok(vec![IngestSource::file(path)]);
+        }
+        if let Some(url) = input["url"].as_str() {
+            return Ok(vec![IngestSource::url(url)]);
+        }
+        if let (Some(id), Some(text)) = (input["id"].as_str(), input["text"].as_str()) {
+            return Ok(vec![IngestSource::text(id, text)]);
+        }
+        Err(AgentFlowError::Other(anyhow::anyhow!(
+            "Missing ingest sources (sources / path / url / id + text)"
+        )))
+    }
+}
+
+#[async_trait]
+impl Tool for IngestTool {
+    fn name(&self) -> &'static str {
+        "rag.ingest"
+    }
+
+    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let sources = Self::parse_sources(&invocation.input)?;
+        let report = self.pipeline.ingest(&sources).await;
+
+        let result = json!({
+            "success": report.failed() == 0,
+            "store": self.pipeline.store().name(),
+            "chunks": report.total_chunks(),
+            "documents": report.documents,
+        });
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name().to_string(),
+            to: None,
+            content: result.to_string(),
+            metadata: invocation.metadata,
+            attachments: Vec::new(),
+        })
+    }
+}
+
+/// 工厂配置：`{"store": {...}, "embedding": {...}, "chunking": {...}, "batch_size": 16}`
+#[derive(Deserialize)]
+pub(crate) struct IngestFactoryConfig {
+    #[serde(default)]
+    pub store: VectorStoreConfig,
+    pub embedding: EmbeddingConfig,
+    #[serde(flatten)]
+    pub ingest: IngestConfig,
+}
+
+impl IngestFactoryConfig {
+    pub(crate) fn build(self) -> Result<IngestTool> {
+        Ok(IngestTool::new(IngestPipeline::with_config(
+            self.store.build()?,
+            Arc::new(HttpEmbedder::new(self.embedding)),
+            self.ingest,
+        )))
+    }
+}
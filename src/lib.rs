@@ -1,14 +1,27 @@
+// 派生宏生成的代码以 `::agentflow` 引用本 crate
+extern crate self as agentflow;
+
 pub mod agent;
 pub mod cli;
 pub mod config;
 pub mod error;
+pub mod eval;
 pub mod flow;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod guardrails;
+pub mod ingest;
 pub mod llm;
+pub mod media;
 pub mod message;
 pub mod plugin;
+pub mod prelude;
 pub mod runtime;
+pub mod scheduler;
 pub mod schema;
 pub mod state;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
 pub mod tools;
 pub mod utils;
 
@@ -17,19 +30,26 @@ pub use agent::{
     AgentManifest, AgentManifestBuilder, AgentMessage, AgentOutput, AgentPort, AgentPortSchema,
     AgentRegistry, MessageRole,
 };
+// CLI 与运行时的其余类型通过 `agentflow::cli`、`agentflow::runtime` 访问
 pub use cli::{load_plugin_manifests, schema_exports, SchemaExportEntry};
 pub use error::{AgentFlowError, Result};
-pub use flow::config::GraphFlow;
+pub use flow::config::{
+    validate_workflow_config, workflow_schema, GraphFlow, WorkflowConfigBuilder,
+};
+pub use flow::import::{import_autogen, import_crewai, import_langgraph, ImportSource};
 pub use flow::loader::{
-    build_flow_from_graph, load_workflow_from_str, load_workflow_from_value, WorkflowBundle,
+    build_flow_from_graph, load_workflow_from_config, load_workflow_from_str,
+    load_workflow_from_value, load_workflow_with_llm, WorkflowBundle,
 };
 pub use flow::{
     condition_always, condition_from_fn, condition_state_absent, condition_state_equals,
     condition_state_exists, condition_state_not_equals, loop_condition_always,
     loop_condition_from_fn, DecisionBranch, DecisionNode, DecisionPolicy, Flow, FlowBuilder,
-    FlowNode, FlowNodeKind, FlowParameter, FlowParameterKind, FlowRegistry, FlowVariable, JoinNode,
-    JoinStrategy, LoopContinuation, LoopNode,
+    FlowNode, FlowNodeKind, FlowOutput, FlowOutputSource, FlowParameter, FlowParameterKind,
+    FlowRegistry, FlowVariable, JoinNode, JoinStrategy, LoopContinuation, LoopNode,
 };
+#[cfg(feature = "test-utils")]
+pub use llm::MockLlmClient;
 pub use llm::{DynLlmClient, LlmClient, LlmRequest, LlmResponse, LocalEchoClient};
 
 pub use config::{
@@ -37,16 +57,29 @@ pub use config::{
     GraphNode, JoinNodeConfig, LoopNodeConfig, WorkflowConfig,
 };
 #[cfg(feature = "openai-client")]
-pub use llm::{ApiFormat, GenericHttpClient};
+pub use flow::agent::assistant_thread_key;
+#[cfg(feature = "openai-client")]
+pub use llm::{
+    ApiFormat, GenericHttpClient, LlmHttpRequest, LlmHttpResponse, LlmMiddleware,
+    OpenAiAssistantClient,
+};
 pub use message::StructuredMessage;
-pub use plugin::{PluginKind, PluginManifest, PluginRegistry};
+pub use plugin::{PluginKind, PluginManifest, PluginRegistry, RemotePlugin};
 pub use runtime::{FlowExecution, FlowExecutor};
-pub use schema::{register_schema, validate_schema, Schema, SchemaKind, SchemaRegistry};
+pub use scheduler::{
+    EventTrigger, OverlapPolicy, PayloadMapping, ScheduleConfig, ScheduledFlow, Scheduler,
+    SchedulerHandle, TriggerSource,
+};
+pub use schema::{
+    register_schema, validate_schema, AgentPayload, PayloadSchema, Schema, SchemaKind,
+    SchemaRegistry,
+};
 pub use state::{
-    ContextStore, FlowContext, FlowScopeGuard, FlowScopeKind, FlowVariables, SessionContext,
+    ContextStore, FlowContext, FlowContextDump, FlowScopeGuard, FlowScopeKind, FlowVariables,
+    SessionContext, SessionManager,
 };
 pub use tools::{
-    orchestrator::{ToolOrchestrator, ToolPipeline, ToolStep, ToolStrategy},
+    orchestrator::{MapStep, ToolOrchestrator, ToolPipeline, ToolStep, ToolStrategy},
     Tool, ToolFactoryRegistry, ToolInvocation, ToolManifest, ToolManifestBuilder, ToolPort,
     ToolPortSchema, ToolRegistry,
 };
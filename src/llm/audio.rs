@@ -0,0 +1,247 @@
+//! 语音识别与语音合成请求格式（OpenAI 音频接口与 DashScope 原生接口）
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::types::MediaSource;
+
+/// DashScope 原生多模态生成接口路径（Qwen ASR / TTS 共用）
+pub const DASHSCOPE_MULTIMODAL_PATH: &str = "/services/aigc/multimodal-generation/generation";
+
+/// 语音识别请求
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionRequest {
+    /// 音频来源：URL 或内联字节
+    pub audio: MediaSource,
+    /// 语言代码（如 `zh`、`en`），不设置时由模型自动识别
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// 提示词（专有名词、上文等）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+impl TranscriptionRequest {
+    pub fn new(audio: MediaSource) -> Self {
+        Self {
+            audio,
+            language: None,
+            prompt: None,
+        }
+    }
+
+    pub fn from_bytes(mime_type: &str, bytes: &[u8]) -> Self {
+        Self::new(MediaSource::from_bytes(mime_type, bytes))
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+}
+
+/// 语音合成请求
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpeechRequest {
+    pub text: String,
+    /// 音色（如 OpenAI 的 `alloy`、Qwen TTS 的 `Cherry`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    /// 输出格式（如 `mp3`、`wav`），DashScope 固定返回 wav
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+}
+
+impl SpeechRequest {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            voice: None,
+            format: None,
+        }
+    }
+
+    pub fn with_voice(mut self, voice: impl Into<String>) -> Self {
+        self.voice = Some(voice.into());
+        self
+    }
+
+    pub fn with_format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+}
+
+/// 合成得到的音频
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpeechAudio {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// OpenAI `/audio/speech` 默认音色
+pub const DEFAULT_OPENAI_VOICE: &str = "alloy";
+/// Qwen TTS 默认音色
+pub const DEFAULT_DASHSCOPE_VOICE: &str = "Cherry";
+
+/// 音频格式对应的 MIME 类型
+pub fn audio_mime_type(format: &str) -> String {
+    match format {
+        "mp3" => "audio/mpeg".to_string(),
+        "wav" => "audio/wav".to_string(),
+        "opus" => "audio/opus".to_string(),
+        "aac" => "audio/aac".to_string(),
+        "flac" => "audio/flac".to_string(),
+        "pcm" => "audio/pcm".to_string(),
+        other => format!("audio/{other}"),
+    }
+}
+
+/// MIME 类型对应的文件扩展名（multipart 上传时服务端依据文件名判断格式）
+pub fn audio_extension(mime_type: &str) -> &str {
+    match mime_type {
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        "audio/wav" | "audio/x-wav" | "audio/wave" => "wav",
+        "audio/mp4" | "audio/m4a" | "audio/x-m4a" => "m4a",
+        "audio/ogg" => "ogg",
+        "audio/webm" => "webm",
+        "audio/flac" => "flac",
+        other => other.strip_prefix("audio/").unwrap_or("bin"),
+    }
+}
+
+/// DashScope 原生语音识别请求体：音频作为多模态消息内容
+pub fn dashscope_transcription_body(model: &str, request: &TranscriptionRequest) -> Value {
+    let mut body = json!({
+        "model": model,
+        "input": {
+            "messages": [
+                { "role": "system", "content": [{ "text": request.prompt.clone().unwrap_or_default() }] },
+                { "role": "user", "content": [{ "audio": request.audio.to_url() }] }
+            ]
+        }
+    });
+    if let Some(language) = &request.language {
+        body["parameters"] = json!({ "asr_options": { "language": language } });
+    }
+    body
+}
+
+/// 解析语音识别结果：OpenAI `text` 或 DashScope `output.choices[0].message.content[].text`
+pub fn parse_transcription(body: &Value) -> Option<String> {
+    if let Some(text) = body["text"].as_str() {
+        return Some(text.to_string());
+    }
+    let content = &body["output"]["choices"][0]["message"]["content"];
+    match content {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(parts) => Some(
+            parts
+                .iter()
+                .filter_map(|part| part["text"].as_str())
+                .collect::<Vec<_>>()
+                .join(""),
+        ),
+        _ => None,
+    }
+}
+
+/// OpenAI `/audio/speech` 请求体
+pub fn openai_speech_body(model: &str, request: &SpeechRequest) -> Value {
+    json!({
+        "model": model,
+        "input": request.text,
+        "voice": request.voice.as_deref().unwrap_or(DEFAULT_OPENAI_VOICE),
+        "response_format": request.format.as_deref().unwrap_or("mp3"),
+    })
+}
+
+/// DashScope 原生语音合成请求体
+pub fn dashscope_speech_body(model: &str, request: &SpeechRequest) -> Value {
+    json!({
+        "model": model,
+        "input": {
+            "text": request.text,
+            "voice": request.voice.as_deref().unwrap_or(DEFAULT_DASHSCOPE_VOICE),
+        }
+    })
+}
+
+/// DashScope 语音合成结果：内联 base64 音频或音频下载地址
+#[derive(Clone, Debug, PartialEq)]
+pub enum DashScopeSpeech {
+    Inline(Vec<u8>),
+    Url(String),
+}
+
+/// 解析 DashScope `output.audio`，优先使用内联数据
+pub fn parse_dashscope_speech(body: &Value) -> Option<DashScopeSpeech> {
+    let audio = &body["output"]["audio"];
+    if let Some(data) = audio["data"].as_str().filter(|data| !data.is_empty()) {
+        return base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .ok()
+            .map(DashScopeSpeech::Inline);
+    }
+    audio["url"]
+        .as_str()
+        .filter(|url| !url.is_empty())
+        .map(|url| DashScopeSpeech::Url(url.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_transcription_formats() {
+        assert_eq!(
+            parse_transcription(&json!({ "text": "hello" })).as_deref(),
+            Some("hello")
+        );
+        let dashscope = json!({ "output": { "choices": [{ "message": {
+            "role": "assistant",
+            "content": [{ "text": "你好" }, { "text": "世界" }]
+        } }] } });
+        assert_eq!(parse_transcription(&dashscope).as_deref(), Some("你好世界"));
+        assert!(parse_transcription(&json!({ "error": "x" })).is_none());
+    }
+
+    #[test]
+    fn test_dashscope_speech_bodies() {
+        let request = SpeechRequest::new("hi").with_voice("Ethan");
+        let body = dashscope_speech_body("qwen-tts", &request);
+        assert_eq!(body["input"], json!({ "text": "hi", "voice": "Ethan" }));
+        assert_eq!(
+            openai_speech_body("tts-1", &SpeechRequest::new("hi"))["voice"],
+            "alloy"
+        );
+
+        let inline = json!({ "output": { "audio": { "data": "AAE=", "url": "" } } });
+        assert_eq!(
+            parse_dashscope_speech(&inline),
+            Some(DashScopeSpeech::Inline(vec![0, 1]))
+        );
+        let url = json!({ "output": { "audio": { "url": "https://x/a.wav" } } });
+        assert_eq!(
+            parse_dashscope_speech(&url),
+            Some(DashScopeSpeech::Url("https://x/a.wav".to_string()))
+        );
+
+        let asr = dashscope_transcription_body(
+            "qwen3-asr-flash",
+            &TranscriptionRequest::from_bytes("audio/wav", &[0, 1]).with_language("zh"),
+        );
+        assert_eq!(
+            asr["input"]["messages"][1]["content"][0]["audio"],
+            "data:audio/wav;base64,AAE="
+        );
+        assert_eq!(asr["parameters"]["asr_options"]["language"], "zh");
+    }
+}
@@ -0,0 +1,424 @@
+//! AWS Bedrock 客户端
+//!
+//! 通过 `InvokeModel` 接口调用 Bedrock 上的模型，请求使用 SigV4 签名：
+//!
+//! - Anthropic Claude（`anthropic.*`）：Messages API 请求体，回复从 `content[].text` 读取
+//! - Amazon Titan Text（`amazon.titan-text*`）：`inputText` + `textGenerationConfig`，
+//!   回复从 `results[0].outputText` 读取
+//! - Amazon Titan Embeddings（`amazon.titan-embed*`）：用于 `embed`，每段文本一次请求
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::{json, Value};
+
+use super::client::{DynLlmClient, LlmClient};
+use super::logging::LlmCallLogger;
+use super::refusal::detect_refusal;
+use super::types::{ContentPart, LlmRequest, LlmResponse, MediaSource};
+use crate::error::{AgentFlowError, Result};
+use crate::utils::sigv4::{uri_encode_path, AwsCredentials, SigningScope};
+
+const SERVICE: &str = "bedrock";
+const ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
+const DEFAULT_MAX_TOKENS: u32 = 2000;
+
+/// 模型系列，决定请求体和响应的格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BedrockModelFamily {
+    Claude,
+    Titan,
+}
+
+impl BedrockModelFamily {
+    /// 按模型 ID（可带 `us.` 等跨区域推理前缀）识别
+    pub fn from_model_id(model_id: &str) -> Option<Self> {
+        if model_id.contains("anthropic.") {
+            Some(Self::Claude)
+        } else if model_id.contains("amazon.titan-text") {
+            Some(Self::Titan)
+        } else {
+            None
+        }
+    }
+}
+
+/// AWS Bedrock 客户端
+#[derive(Clone)]
+pub struct BedrockClient {
+    client: reqwest::Client,
+    region: String,
+    model_id: String,
+    credentials: AwsCredentials,
+    endpoint: Option<String>,
+    max_tokens: u32,
+    embedding_model: Option<String>,
+    call_logger: LlmCallLogger,
+}
+
+impl BedrockClient {
+    pub fn new(
+        region: impl Into<String>,
+        model_id: impl Into<String>,
+        credentials: AwsCredentials,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            region: region.into(),
+            model_id: model_id.into(),
+            credentials,
+            endpoint: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            embedding_model: None,
+            call_logger: LlmCallLogger::default(),
+        }
+    }
+
+    /// 从环境变量读取凭证，`AWS_REGION` 未设置时使用 us-east-1
+    pub fn from_env(model_id: impl Into<String>) -> Result<Self> {
+        let region = std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+        Ok(Self::new(region, model_id, AwsCredentials::from_env()?))
+    }
+
+    /// 自定义运行时端点（如 VPC 终端节点），默认 `https://bedrock-runtime.{region}.amazonaws.com`
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// 单次回复的最大 token 数，默认 2000
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// 设置 `embed` 使用的 Titan Embeddings 模型（如 `amazon.titan-embed-text-v2:0`）
+    pub fn with_embedding_model(mut self, model_id: impl Into<String>) -> Self {
+        self.embedding_model = Some(model_id.into());
+        self
+    }
+
+    /// 替换请求 / 响应日志的脱敏配置
+    pub fn with_call_logger(mut self, logger: LlmCallLogger) -> Self {
+        self.call_logger = logger;
+        self
+    }
+
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn endpoint(&self) -> String {
+        self.endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://bedrock-runtime.{}.amazonaws.com", self.region))
+    }
+
+    /// 调用 `InvokeModel`
+    async fn invoke(&self, model_id: &str, body: &Value) -> Result<Value> {
+        let endpoint = self.endpoint();
+        let host = endpoint
+            .split_once("://")
+            .map_or(endpoint.as_str(), |(_, rest)| rest)
+            .to_string();
+        // 模型 ID 中的 `:` 在 URL 中编码一次，规范化路径中再编码一次
+        let path = format!("/model/{}/invoke", uri_encode_path(model_id));
+        let url = format!("{endpoint}{path}");
+        self.call_logger.log_request("POST", &url, &[], Some(body));
+        let body =
+            serde_json::to_vec(body).map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        let headers = self.credentials.sign(
+            "POST",
+            &host,
+            &uri_encode_path(&path),
+            &body,
+            &SigningScope {
+                region: &self.region,
+                service: SERVICE,
+            },
+            Utc::now(),
+        );
+
+        let mut request = self
+            .client
+            .post(&url)
+            .header("content-type", "application/json")
+            .header("accept", "application/json")
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let started = std::time::Instant::now();
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Bedrock request failed: {}", e)))?;
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        self.call_logger
+            .log_response(&url, status.as_u16(), &text, started.elapsed());
+        let payload: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Bedrock returned {} for `{}`: {}",
+                status,
+                model_id,
+                payload
+            )));
+        }
+        Ok(payload)
+    }
+}
+
+#[async_trait]
+impl LlmClient for BedrockClient {
+    async fn complete(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let family = BedrockModelFamily::from_model_id(&self.model_id).ok_or_else(|| {
+            AgentFlowError::Other(anyhow!(
+                "Unsupported Bedrock model `{}`; expected an anthropic.* or amazon.titan-text* model",
+                self.model_id
+            ))
+        })?;
+        let max_tokens = request.params.max_tokens.unwrap_or(self.max_tokens);
+        let body = match family {
+            BedrockModelFamily::Claude => claude_body(&request, max_tokens)?,
+            BedrockModelFamily::Titan => titan_body(&request, max_tokens),
+        };
+        let payload = self.invoke(&self.model_id, &body).await?;
+        if let Some(refusal) = detect_refusal(&payload) {
+            return Err(AgentFlowError::Refused(refusal));
+        }
+        let content = match family {
+            BedrockModelFamily::Claude => payload["content"].as_array().map(|parts| {
+                parts
+                    .iter()
+                    .filter_map(|part| part["text"].as_str())
+                    .collect::<String>()
+            }),
+            BedrockModelFamily::Titan => payload["results"][0]["outputText"]
+                .as_str()
+                .map(str::to_string),
+        }
+        .ok_or_else(|| {
+            AgentFlowError::Other(anyhow!("Missing content in Bedrock response: {}", payload))
+        })?;
+        Ok(LlmResponse {
+            content,
+            metadata: Some(payload),
+        })
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let model = self.embedding_model.as_deref().ok_or_else(|| {
+            AgentFlowError::Other(anyhow!(
+                "No embedding model configured; call with_embedding_model() on the client"
+            ))
+        })?;
+        let mut vectors = Vec::with_capacity(texts.len());
+        for text in texts {
+            let payload = self.invoke(model, &json!({ "inputText": text })).await?;
+            let vector = payload["embedding"]
+                .as_array()
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(Value::as_f64)
+                        .map(|v| v as f32)
+                        .collect()
+                })
+                .ok_or_else(|| {
+                    AgentFlowError::Other(anyhow!("Invalid embedding response: {}", payload))
+                })?;
+            vectors.push(vector);
+        }
+        Ok(vectors)
+    }
+
+    fn clone_dyn(&self) -> DynLlmClient {
+        Arc::new(self.clone())
+    }
+}
+
+/// Claude Messages API 请求体；图片只支持 Base64 来源
+fn claude_body(request: &LlmRequest, max_tokens: u32) -> Result<Value> {
+    let mut content = Vec::new();
+    if !request.user.is_empty() {
+        content.push(json!({ "type": "text", "text": request.user }));
+    }
+    for part in &request.content {
+        content.push(match part {
+            ContentPart::Text { text } => json!({ "type": "text", "text": text }),
+            ContentPart::Image {
+                source: MediaSource::Base64 { mime_type, data },
+            } => json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": mime_type, "data": data }
+            }),
+            _ => {
+                return Err(AgentFlowError::Other(anyhow!(
+                    "Bedrock Claude models only accept text and base64 image content"
+                )))
+            }
+        });
+    }
+    let mut body = json!({
+        "anthropic_version": ANTHROPIC_VERSION,
+        "max_tokens": max_tokens,
+        "temperature": request.temperature,
+        "messages": [{ "role": "user", "content": content }],
+    });
+    if let Some(system) = &request.system {
+        body["system"] = Value::String(system.clone());
+    }
+    if let Some(top_p) = request.params.top_p {
+        body["top_p"] = json!(top_p);
+    }
+    if !request.params.stop.is_empty() {
+        body["stop_sequences"] = json!(request.params.stop);
+    }
+    Ok(body)
+}
+
+/// Titan Text 没有独立的系统提示，系统提示拼接在输入之前
+fn titan_body(request: &LlmRequest, max_tokens: u32) -> Value {
+    let input = match &request.system {
+        Some(system) => format!("{}\n\nUser: {}\nBot:", system, request.user),
+        None => request.user.clone(),
+    };
+    let mut body = json!({
+        "inputText": input,
+        "textGenerationConfig": {
+            "maxTokenCount": max_tokens,
+            "temperature": request.temperature,
+        }
+    });
+    if let Some(top_p) = request.params.top_p {
+        body["textGenerationConfig"]["topP"] = json!(top_p);
+    }
+    if !request.params.stop.is_empty() {
+        body["textGenerationConfig"]["stopSequences"] = json!(request.params.stop);
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    /// 返回固定响应的本地服务，记录请求行、请求头和请求体
+    fn bedrock_server(
+        response: Value,
+    ) -> (String, std::sync::mpsc::Receiver<(String, String, Value)>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut headers = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                    if let Some((key, value)) = line.trim_end().split_once(": ") {
+                        if key.eq_ignore_ascii_case("content-length") {
+                            length = value.parse().unwrap();
+                        }
+                    }
+                    headers.push_str(&line.to_ascii_lowercase());
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let response = response.to_string();
+                stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            response.len(),
+                            response
+                        )
+                        .as_bytes(),
+                    )
+                    .unwrap();
+                let _ = tx.send((
+                    request_line.trim_end().to_string(),
+                    headers,
+                    serde_json::from_slice(&body).unwrap(),
+                ));
+            }
+        });
+        (url, rx)
+    }
+
+    fn request() -> LlmRequest {
+        LlmRequest {
+            system: Some("be brief".into()),
+            user: "hi".into(),
+            temperature: 0.2,
+            metadata: None,
+            content: Vec::new(),
+            params: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_claude_invoke_is_signed() {
+        let (url, requests) = bedrock_server(json!({
+            "content": [{ "type": "text", "text": "hello" }],
+            "stop_reason": "end_turn"
+        }));
+        let client = BedrockClient::new(
+            "us-west-2",
+            "anthropic.claude-3-haiku-20240307-v1:0",
+            AwsCredentials::new("AKID", "secret"),
+        )
+        .with_endpoint(url);
+        assert_eq!(client.complete(request()).await.unwrap().content, "hello");
+
+        let (line, headers, body) = requests.recv().unwrap();
+        assert_eq!(
+            line,
+            "POST /model/anthropic.claude-3-haiku-20240307-v1%3A0/invoke HTTP/1.1"
+        );
+        assert!(headers.contains("authorization: aws4-hmac-sha256 credential=akid/"));
+        assert!(headers.contains("/us-west-2/bedrock/aws4_request"));
+        assert_eq!(body["anthropic_version"], ANTHROPIC_VERSION);
+        assert_eq!(body["system"], "be brief");
+        assert_eq!(body["messages"][0]["content"][0]["text"], "hi");
+    }
+
+    #[tokio::test]
+    async fn test_titan_body_mapping() {
+        let (url, requests) = bedrock_server(json!({
+            "results": [{ "outputText": " hello", "completionReason": "FINISH" }]
+        }));
+        let client = BedrockClient::new(
+            "us-east-1",
+            "amazon.titan-text-express-v1",
+            AwsCredentials::new("AKID", "secret"),
+        )
+        .with_endpoint(url)
+        .with_max_tokens(256);
+        assert_eq!(client.complete(request()).await.unwrap().content, " hello");
+
+        let (_, _, body) = requests.recv().unwrap();
+        assert_eq!(body["inputText"], "be brief\n\nUser: hi\nBot:");
+        assert_eq!(body["textGenerationConfig"]["maxTokenCount"], 256);
+
+        let unsupported = BedrockClient::new(
+            "us-east-1",
+            "meta.llama3-8b-instruct-v1:0",
+            AwsCredentials::new("AKID", "secret"),
+        );
+        assert!(unsupported.complete(request()).await.is_err());
+    }
+}
@@ -4,6 +4,7 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use futures::{Stream, StreamExt};
 
+use super::audio::{SpeechAudio, SpeechRequest, TranscriptionRequest};
 use super::types::{LlmRequest, LlmResponse, LlmStreamChunk};
 use crate::error::{AgentFlowError, Result};
 
@@ -13,6 +14,27 @@ pub type LlmStream = Pin<Box<dyn Stream<Item = Result<LlmStreamChunk>> + Send>>;
 pub trait LlmClient: Send + Sync {
     async fn complete(&self, request: LlmRequest) -> Result<LlmResponse>;
 
+    /// 文本向量化，返回的向量与输入文本一一对应；默认不支持
+    async fn embed(&self, _texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        Err(AgentFlowError::Other(anyhow::anyhow!(
+            "Embeddings are not supported by this LLM client"
+        )))
+    }
+
+    /// 语音识别，返回识别出的文本；默认不支持
+    async fn transcribe(&self, _request: TranscriptionRequest) -> Result<String> {
+        Err(AgentFlowError::Other(anyhow::anyhow!(
+            "Speech transcription is not supported by this LLM client"
+        )))
+    }
+
+    /// 语音合成；默认不支持
+    async fn speak(&self, _request: SpeechRequest) -> Result<SpeechAudio> {
+        Err(AgentFlowError::Other(anyhow::anyhow!(
+            "Speech synthesis is not supported by this LLM client"
+        )))
+    }
+
     fn complete_stream(&self, request: LlmRequest) -> LlmStream {
         let request = Arc::new(request);
         let client = self.clone_dyn();
@@ -39,8 +61,7 @@ pub trait LlmClient: Send + Sync {
                             return Some((
                                 Err(AgentFlowError::Other(anyhow::anyhow!(
                                     "Stream response content is empty"
-                                ))
-                                .into()),
+                                ))),
                                 (req, client, full_content, pos),
                             ))
                         }
@@ -53,8 +74,7 @@ pub trait LlmClient: Send + Sync {
                                 return Some((
                                     Err(AgentFlowError::Other(anyhow::anyhow!(
                                         "Character parsing failed"
-                                    ))
-                                    .into()),
+                                    ))),
                                     (req, client, full_content, pos),
                                 ))
                             }
@@ -0,0 +1,83 @@
+//! Embedding 请求与响应格式（OpenAI 兼容接口与 DashScope 原生接口）
+
+use serde_json::{json, Value};
+
+/// DashScope 原生文本向量接口路径
+pub const DASHSCOPE_EMBEDDING_PATH: &str = "/services/embeddings/text-embedding/text-embedding";
+
+/// 构造 Embedding 请求体：OpenAI 兼容格式为 `input: [...]`，DashScope 原生格式为 `input.texts`
+pub fn embedding_request_body(model: &str, texts: &[String], dashscope_native: bool) -> Value {
+    if dashscope_native {
+        json!({ "model": model, "input": { "texts": texts } })
+    } else {
+        json!({ "model": model, "input": texts })
+    }
+}
+
+/// 解析 Embedding 响应：OpenAI `data[].embedding` 或 DashScope `output.embeddings[].embedding`
+///
+/// 结果按 `index` / `text_index` 排序，与输入文本一一对应。
+pub fn parse_embeddings(body: &Value) -> Option<Vec<Vec<f32>>> {
+    let items = body["data"]
+        .as_array()
+        .or_else(|| body["output"]["embeddings"].as_array())?;
+    let mut indexed: Vec<(u64, Vec<f32>)> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let index = item["index"]
+                .as_u64()
+                .or_else(|| item["text_index"].as_u64())
+                .unwrap_or(i as u64);
+            let vector = item["embedding"]
+                .as_array()
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(Value::as_f64)
+                        .map(|v| v as f32)
+                        .collect()
+                })
+                .unwrap_or_default();
+            (index, vector)
+        })
+        .collect();
+    indexed.sort_by_key(|(index, _)| *index);
+    Some(indexed.into_iter().map(|(_, vector)| vector).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_embeddings_formats() {
+        let openai = json!({ "data": [
+            { "index": 1, "embedding": [0.0, 1.0] },
+            { "index": 0, "embedding": [1.0, 0.0] }
+        ] });
+        assert_eq!(
+            parse_embeddings(&openai).unwrap(),
+            vec![vec![1.0, 0.0], vec![0.0, 1.0]]
+        );
+
+        let dashscope = json!({ "output": { "embeddings": [
+            { "text_index": 0, "embedding": [0.5] }
+        ] } });
+        assert_eq!(parse_embeddings(&dashscope).unwrap(), vec![vec![0.5]]);
+        assert!(parse_embeddings(&json!({ "error": "x" })).is_none());
+    }
+
+    #[test]
+    fn test_embedding_request_body() {
+        let texts = vec!["a".to_string()];
+        assert_eq!(
+            embedding_request_body("m", &texts, false),
+            json!({ "model": "m", "input": ["a"] })
+        );
+        assert_eq!(
+            embedding_request_body("m", &texts, true)["input"]["texts"],
+            json!(["a"])
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! LLM HTTP 调用失败的结构化错误
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// 响应片段的最大字节数
+pub const RESPONSE_EXCERPT_BYTES: usize = 512;
+
+/// 认证失败相关的提供商错误码（小写比较）
+const AUTH_CODES: &[&str] = &[
+    "invalid_api_key",
+    "invalidapikey",
+    "authentication_error",
+    "unauthorized",
+    "permission_denied",
+    "accessdenied",
+];
+
+/// LLM 端点返回非 2xx 状态
+///
+/// 调用方按 `status` 和 `provider_code` 区分认证失败、限流等情况，不必解析错误文本；
+/// 内容过滤的错误响应仍以 `AgentFlowError::Refused` 返回。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LlmHttpError {
+    pub status: u16,
+    pub endpoint: String,
+    /// 提供商错误码：`error.code`、`error.type` 或顶层 `code`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_code: Option<String>,
+    /// 请求体的摘要（SHA-256 前 8 字节的十六进制），用于关联日志而不暴露请求内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_digest: Option<String>,
+    /// 响应体开头最多 `RESPONSE_EXCERPT_BYTES` 字节
+    pub response_excerpt: String,
+}
+
+impl LlmHttpError {
+    pub fn new(
+        status: u16,
+        endpoint: impl Into<String>,
+        request_body: Option<&Value>,
+        response: &str,
+    ) -> Self {
+        let payload = serde_json::from_str::<Value>(response).unwrap_or(Value::Null);
+        Self {
+            status,
+            endpoint: endpoint.into(),
+            provider_code: provider_code(&payload),
+            request_digest: request_body.map(request_digest),
+            response_excerpt: excerpt(response, RESPONSE_EXCERPT_BYTES).to_string(),
+        }
+    }
+
+    /// 认证或授权失败（401 / 403，或提供商的无效密钥错误码）
+    pub fn is_auth_failure(&self) -> bool {
+        matches!(self.status, 401 | 403)
+            || self
+                .provider_code
+                .as_deref()
+                .is_some_and(|code| AUTH_CODES.contains(&code.to_lowercase().as_str()))
+    }
+
+    /// 被限流（429，或 DashScope 的 `Throttling*` 错误码）
+    pub fn is_rate_limited(&self) -> bool {
+        self.status == 429
+            || self
+                .provider_code
+                .as_deref()
+                .is_some_and(|code| code.starts_with("Throttling"))
+    }
+
+    /// 提供商侧错误（5xx），通常可以重试
+    pub fn is_server_error(&self) -> bool {
+        self.status >= 500
+    }
+}
+
+impl fmt::Display for LlmHttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LLM endpoint {} returned {}", self.endpoint, self.status)?;
+        if let Some(code) = &self.provider_code {
+            write!(f, " ({})", code)?;
+        }
+        write!(f, ": {}", self.response_excerpt)
+    }
+}
+
+fn provider_code(payload: &Value) -> Option<String> {
+    let error = payload.get("error").unwrap_or(payload);
+    [error.get("code"), error.get("type"), payload.get("code")]
+        .into_iter()
+        .flatten()
+        .find_map(|code| match code {
+            Value::String(code) if !code.is_empty() => Some(code.clone()),
+            Value::Number(code) => Some(code.to_string()),
+            _ => None,
+        })
+}
+
+fn request_digest(body: &Value) -> String {
+    Sha256::digest(body.to_string().as_bytes())[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// 截取不超过 `max` 字节的开头部分，不切断多字节字符
+pub fn excerpt(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_http_error_fields() {
+        let body = json!({ "model": "gpt-4o" });
+        let error = LlmHttpError::new(
+            401,
+            "https://api.openai.com/v1/chat/completions",
+            Some(&body),
+            r#"{"error":{"message":"Incorrect API key","type":"invalid_request_error","code":"invalid_api_key"}}"#,
+        );
+        assert_eq!(error.provider_code.as_deref(), Some("invalid_api_key"));
+        assert_eq!(error.request_digest.as_ref().unwrap().len(), 16);
+        assert!(error.is_auth_failure());
+        assert!(!error.is_rate_limited());
+
+        let throttled = LlmHttpError::new(
+            400,
+            "dashscope",
+            None,
+            r#"{"code":"Throttling.RateQuota","message":"Requests rate limit exceeded"}"#,
+        );
+        assert!(throttled.is_rate_limited());
+        assert_eq!(throttled.request_digest, None);
+
+        let long = "错".repeat(400);
+        let error = LlmHttpError::new(502, "gateway", None, &long);
+        assert!(error.is_server_error());
+        assert_eq!(error.provider_code, None);
+        assert_eq!(error.response_excerpt.len(), 510);
+        let message = error.to_string();
+        assert!(message.starts_with("LLM endpoint gateway returned 502: 错"));
+    }
+}
@@ -14,17 +14,26 @@
 //! - 已移除 `GenericApiClient` 和 `ExtendedApiClient`（使用 `GenericHttpClient` 替代）
 //! - 已移除 client 目录（包含大量厂商专用代码）
 //! - 保留JSON配置相关功能用于特殊场景
+//!
+//! 未启用 `unstable` feature 时模块路径仅 crate 内可见，常用类型通过 `crate::llm` 导出。
 
 pub mod json_config;
+#[cfg_attr(not(feature = "unstable"), allow(dead_code))]
 pub mod json_unified;
+#[cfg_attr(not(feature = "unstable"), allow(dead_code))]
 pub mod service_config;
 pub mod universal;
 
-pub use json_config::{ApiCallRequest, EndpointConfig, JsonApiClient, JsonApiConfig};
+pub use json_config::JsonApiClient;
+// 以下导出只面向 `unstable` 使用方
+#[cfg_attr(not(feature = "unstable"), allow(unused_imports))]
+pub use json_config::{ApiCallRequest, EndpointConfig, JsonApiConfig};
+#[cfg_attr(not(feature = "unstable"), allow(unused_imports))]
 pub use json_unified::{
     ApiCallConfig as UnifiedApiCallConfig, ApiProviderConfig, EndpointDefinition,
     UnifiedApiManager, UnifiedJsonConfig,
 };
+#[cfg_attr(not(feature = "unstable"), allow(unused_imports))]
 pub use service_config::{
     ApiEdge, ApiGraph, ApiNode, ServiceConfig, ServiceDefinition, ServiceManager,
 };
@@ -6,6 +6,11 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::llm::config::ApiEndpointConfig;
+use crate::llm::error::{excerpt, LlmHttpError, RESPONSE_EXCERPT_BYTES};
+use crate::llm::http::body::{read_body, read_text};
+use crate::llm::http::{
+    default_http_client, shared_http_client, HttpPoolConfig, DEFAULT_MAX_RESPONSE_BYTES,
+};
 
 pub struct ApiCallConfig {
     pub method: Method,
@@ -68,18 +73,37 @@ pub struct UniversalApiClient {
     config: ApiEndpointConfig,
     api_key: String,
     default_headers: HashMap<String, String>,
+    max_response_bytes: usize,
 }
 
 impl UniversalApiClient {
     pub fn new(config: ApiEndpointConfig, api_key: impl Into<String>) -> Self {
         Self {
-            client: Client::new(),
+            client: default_http_client(),
             config,
             api_key: api_key.into(),
             default_headers: HashMap::new(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
         }
     }
 
+    /// 替换底层 `reqwest::Client`，默认与 `GenericHttpClient` 共用连接池
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 按连接池配置（代理、CA 证书、TLS 校验等）使用共享的 `reqwest::Client`
+    pub fn with_http_config(self, config: &HttpPoolConfig) -> Result<Self> {
+        Ok(self.with_http_client(shared_http_client(config)?))
+    }
+
+    /// 响应体的大小上限（字节），超过时中止读取并返回错误
+    pub fn with_max_response_bytes(mut self, limit: usize) -> Self {
+        self.max_response_bytes = limit;
+        self
+    }
+
     pub fn with_default_header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
         self.default_headers.insert(key.into(), value.into());
         self
@@ -133,12 +157,13 @@ impl UniversalApiClient {
             request_builder = request_builder.header(key, value);
         }
 
+        let request_body = call_config.body;
         if let Some(multipart) = call_config.multipart {
             request_builder = request_builder.multipart(multipart);
-        } else if let Some(body) = call_config.body {
+        } else if let Some(body) = &request_body {
             request_builder = request_builder
                 .header("Content-Type", "application/json")
-                .json(&body);
+                .json(body);
         }
 
         let response = request_builder
@@ -147,21 +172,14 @@ impl UniversalApiClient {
             .map_err(|e| AgentFlowError::Other(anyhow!("HTTP request error: {}", e)))?;
 
         let status = response.status();
-        let response_text = response
-            .text()
-            .await
-            .map_err(|e| AgentFlowError::Other(anyhow!("Failed to read response: {}", e)))?;
+        let response_text = read_text(response, self.max_response_bytes).await?;
 
         if !status.is_success() {
-            return Err(AgentFlowError::Other(anyhow!(
-                "Request failed with status {}: {}\nEndpoint: {}",
-                status,
-                if response_text.len() > 500 {
-                    format!("{}...", &response_text[..500])
-                } else {
-                    response_text
-                },
-                url
+            return Err(AgentFlowError::LlmHttp(LlmHttpError::new(
+                status.as_u16(),
+                url,
+                request_body.as_ref(),
+                &response_text,
             )));
         }
 
@@ -169,11 +187,7 @@ impl UniversalApiClient {
             AgentFlowError::Other(anyhow!(
                 "Response parse error: {}\nResponse body: {}",
                 e,
-                if response_text.len() > 500 {
-                    format!("{}...", &response_text[..500])
-                } else {
-                    response_text
-                }
+                excerpt(&response_text, RESPONSE_EXCERPT_BYTES)
             ))
         })?;
 
@@ -222,12 +236,13 @@ impl UniversalApiClient {
             request_builder = request_builder.header(key, value);
         }
 
+        let request_body = call_config.body;
         if let Some(multipart) = call_config.multipart {
             request_builder = request_builder.multipart(multipart);
-        } else if let Some(body) = call_config.body {
+        } else if let Some(body) = &request_body {
             request_builder = request_builder
                 .header("Content-Type", "application/json")
-                .json(&body);
+                .json(body);
         }
 
         let response = request_builder
@@ -237,21 +252,16 @@ impl UniversalApiClient {
 
         let status = response.status();
         if !status.is_success() {
-            let error_text = response.text().await
-                .map_err(|e| AgentFlowError::Other(anyhow!("Failed to read error response: {}", e)))?;
-            return Err(AgentFlowError::Other(anyhow!(
-                "Request failed with status {}: {}",
-                status,
-                error_text
+            let error_text = read_text(response, self.max_response_bytes).await?;
+            return Err(AgentFlowError::LlmHttp(LlmHttpError::new(
+                status.as_u16(),
+                url,
+                request_body.as_ref(),
+                &error_text,
             )));
         }
 
-        let bytes = response
-            .bytes()
-            .await
-            .map_err(|e| AgentFlowError::Other(anyhow!("Failed to read response: {}", e)))?;
-
-        Ok(bytes.to_vec())
+        read_body(response, self.max_response_bytes).await
     }
 }
 
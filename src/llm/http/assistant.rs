@@ -0,0 +1,353 @@
+//! OpenAI Assistants API 客户端
+//!
+//! 驱动已在平台上创建好的 Assistant：创建线程、追加消息、启动运行并轮询状态，
+//! 运行进入 `requires_action` 时执行工具调用并提交输出，完成后读取本次运行的回复。
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot};
+
+use super::generic::GenericHttpClient;
+use crate::error::{AgentFlowError, Result};
+use crate::llm::client::{DynLlmClient, LlmClient};
+use crate::llm::types::{LlmRequest, LlmResponse};
+
+const BETA_HEADER: (&str, &str) = ("OpenAI-Beta", "assistants=v2");
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const DEFAULT_RUN_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Assistant 运行中请求的工具调用
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssistantToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// 一次运行的结果
+#[derive(Debug, Clone)]
+pub struct AssistantRun {
+    pub thread_id: String,
+    pub run_id: String,
+    pub content: String,
+}
+
+/// OpenAI Assistants 客户端
+#[derive(Clone)]
+pub struct OpenAiAssistantClient {
+    http: GenericHttpClient,
+    assistant_id: String,
+    model: Option<String>,
+    poll_interval: Duration,
+    run_timeout: Duration,
+}
+
+impl OpenAiAssistantClient {
+    /// `http` 的端点为 API 根地址（如 `https://api.openai.com/v1`）
+    pub fn new(http: GenericHttpClient, assistant_id: impl Into<String>) -> Self {
+        Self {
+            http,
+            assistant_id: assistant_id.into(),
+            model: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            run_timeout: DEFAULT_RUN_TIMEOUT,
+        }
+    }
+
+    /// 覆盖 Assistant 配置的模型
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// 运行状态的轮询间隔，默认 500ms
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// 单次运行的超时时间，默认 300s，超时后取消运行
+    pub fn with_run_timeout(mut self, timeout: Duration) -> Self {
+        self.run_timeout = timeout;
+        self
+    }
+
+    pub fn assistant_id(&self) -> &str {
+        &self.assistant_id
+    }
+
+    /// 创建空线程，返回线程 ID
+    pub async fn create_thread(&self) -> Result<String> {
+        let thread = self
+            .send(reqwest::Method::POST, "/threads", Some(&json!({})))
+            .await?;
+        id_of(&thread, "thread")
+    }
+
+    /// 在线程中追加用户消息并运行 Assistant
+    ///
+    /// `thread_id` 为空时创建新线程；运行请求工具调用时依次交给 `on_tool_call` 执行，
+    /// 返回的文本作为工具输出提交。
+    pub async fn run<F, Fut>(
+        &self,
+        thread_id: Option<&str>,
+        request: &LlmRequest,
+        mut on_tool_call: F,
+    ) -> Result<AssistantRun>
+    where
+        F: FnMut(AssistantToolCall) -> Fut + Send,
+        Fut: Future<Output = Result<String>> + Send,
+    {
+        let thread_id = match thread_id {
+            Some(id) => id.to_string(),
+            None => self.create_thread().await?,
+        };
+        self.send(
+            reqwest::Method::POST,
+            &format!("/threads/{thread_id}/messages"),
+            Some(&json!({ "role": "user", "content": request.user })),
+        )
+        .await?;
+
+        let mut body = json!({
+            "assistant_id": self.assistant_id,
+            "temperature": request.temperature,
+        });
+        if let Some(system) = &request.system {
+            body["additional_instructions"] = Value::String(system.clone());
+        }
+        if let Some(model) = &self.model {
+            body["model"] = Value::String(model.clone());
+        }
+        if let Some(top_p) = request.params.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(max_tokens) = request.params.max_tokens {
+            body["max_completion_tokens"] = json!(max_tokens);
+        }
+        let mut run = self
+            .send(
+                reqwest::Method::POST,
+                &format!("/threads/{thread_id}/runs"),
+                Some(&body),
+            )
+            .await?;
+        let run_id = id_of(&run, "run")?;
+        let run_path = format!("/threads/{thread_id}/runs/{run_id}");
+        let deadline = Instant::now() + self.run_timeout;
+
+        loop {
+            match run["status"].as_str().unwrap_or_default() {
+                "completed" => break,
+                "requires_action" => {
+                    let mut outputs = Vec::new();
+                    for call in tool_calls(&run) {
+                        let id = call.id.clone();
+                        let name = call.name.clone();
+                        let output = on_tool_call(call).await.unwrap_or_else(|e| {
+                            tracing::warn!(tool = %name, error = %e, "Assistant tool call failed");
+                            json!({ "error": e.to_string() }).to_string()
+                        });
+                        outputs.push(json!({ "tool_call_id": id, "output": output }));
+                    }
+                    run = self
+                        .send(
+                            reqwest::Method::POST,
+                            &format!("{run_path}/submit_tool_outputs"),
+                            Some(&json!({ "tool_outputs": outputs })),
+                        )
+                        .await?;
+                    continue;
+                }
+                "queued" | "in_progress" | "cancelling" => {}
+                status => {
+                    return Err(AgentFlowError::Other(anyhow!(
+                        "Assistant run {} ended with status `{}`: {}",
+                        run_id,
+                        status,
+                        run["last_error"]
+                    )));
+                }
+            }
+            if Instant::now() >= deadline {
+                let _ = self
+                    .send(reqwest::Method::POST, &format!("{run_path}/cancel"), None)
+                    .await;
+                return Err(AgentFlowError::Other(anyhow!(
+                    "Assistant run {} did not finish within {:?}",
+                    run_id,
+                    self.run_timeout
+                )));
+            }
+            tokio::time::sleep(self.poll_interval).await;
+            run = self.send(reqwest::Method::GET, &run_path, None).await?;
+        }
+
+        let messages = self
+            .send(
+                reqwest::Method::GET,
+                &format!("/threads/{thread_id}/messages?run_id={run_id}&order=asc"),
+                None,
+            )
+            .await?;
+        Ok(AssistantRun {
+            thread_id,
+            run_id,
+            content: assistant_text(&messages),
+        })
+    }
+
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<Value> {
+        let base = self.http.endpoint().trim_end_matches('/');
+        let base = base.strip_suffix("/chat/completions").unwrap_or(base);
+        self.http
+            .request_json(
+                method,
+                &format!("{base}{path}"),
+                body,
+                &[BETA_HEADER],
+                "Assistants API",
+            )
+            .await
+    }
+}
+
+#[async_trait]
+impl LlmClient for OpenAiAssistantClient {
+    /// 每次调用使用新线程；无法执行工具，Assistant 请求的工具调用以错误输出提交
+    async fn complete(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let run = self
+            .run(None, &request, |call| async move {
+                Err(AgentFlowError::Other(anyhow!(
+                    "Tool `{}` requested by assistant cannot run outside a flow",
+                    call.name
+                )))
+            })
+            .await?;
+        Ok(LlmResponse {
+            content: run.content,
+            metadata: Some(json!({ "thread_id": run.thread_id, "run_id": run.run_id })),
+        })
+    }
+
+    fn clone_dyn(&self) -> DynLlmClient {
+        Arc::new(self.clone())
+    }
+}
+
+/// 转交给 Agent 执行的工具调用
+pub struct ToolCallRequest {
+    pub call: AssistantToolCall,
+    pub reply: oneshot::Sender<Result<String>>,
+}
+
+/// 绑定到会话线程的 Assistants 客户端
+///
+/// 首次运行后记住线程 ID，后续调用在同一线程中继续对话；工具调用通过通道转交给持有
+/// 接收端的一方执行。
+#[derive(Clone)]
+pub struct AssistantThreadClient {
+    assistant: Arc<OpenAiAssistantClient>,
+    thread_id: Arc<Mutex<Option<String>>>,
+    tool_calls: mpsc::UnboundedSender<ToolCallRequest>,
+}
+
+impl AssistantThreadClient {
+    pub fn new(
+        assistant: Arc<OpenAiAssistantClient>,
+        thread_id: Option<String>,
+        tool_calls: mpsc::UnboundedSender<ToolCallRequest>,
+    ) -> Self {
+        Self {
+            assistant,
+            thread_id: Arc::new(Mutex::new(thread_id)),
+            tool_calls,
+        }
+    }
+
+    /// 当前线程 ID
+    pub fn thread_id(&self) -> Option<String> {
+        self.thread_id.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl LlmClient for AssistantThreadClient {
+    async fn complete(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let thread_id = self.thread_id();
+        let run = self
+            .assistant
+            .run(thread_id.as_deref(), &request, |call| {
+                let (reply, response) = oneshot::channel();
+                let sent = self.tool_calls.send(ToolCallRequest { call, reply });
+                async move {
+                    sent.map_err(|_| {
+                        AgentFlowError::Other(anyhow!("Assistant tool call handler is closed"))
+                    })?;
+                    response.await.map_err(|_| {
+                        AgentFlowError::Other(anyhow!("Assistant tool call was dropped"))
+                    })?
+                }
+            })
+            .await?;
+        *self.thread_id.lock().unwrap() = Some(run.thread_id.clone());
+        Ok(LlmResponse {
+            content: run.content,
+            metadata: Some(json!({ "thread_id": run.thread_id, "run_id": run.run_id })),
+        })
+    }
+
+    fn clone_dyn(&self) -> DynLlmClient {
+        Arc::new(self.clone())
+    }
+}
+
+fn id_of(value: &Value, what: &str) -> Result<String> {
+    value["id"].as_str().map(str::to_string).ok_or_else(|| {
+        AgentFlowError::Other(anyhow!("Assistants API returned a {} without id", what))
+    })
+}
+
+/// 读取 `required_action.submit_tool_outputs.tool_calls`，参数解析失败时保留原始字符串
+fn tool_calls(run: &Value) -> Vec<AssistantToolCall> {
+    run["required_action"]["submit_tool_outputs"]["tool_calls"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|call| {
+            let arguments = call["function"]["arguments"].as_str().unwrap_or("{}");
+            AssistantToolCall {
+                id: call["id"].as_str().unwrap_or_default().to_string(),
+                name: call["function"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                arguments: serde_json::from_str(arguments)
+                    .unwrap_or_else(|_| Value::String(arguments.to_string())),
+            }
+        })
+        .collect()
+}
+
+/// 拼接 Assistant 消息中的文本内容
+fn assistant_text(messages: &Value) -> String {
+    messages["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|message| message["role"] == "assistant")
+        .flat_map(|message| message["content"].as_array().into_iter().flatten())
+        .filter_map(|part| part["text"]["value"].as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
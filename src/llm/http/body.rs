@@ -0,0 +1,62 @@
+//! 限制大小的请求体与响应体
+//!
+//! 响应体逐块读取，超过上限时立即中止，异常端点返回的超大响应不会被整体读入内存。
+
+use anyhow::anyhow;
+
+use crate::error::{AgentFlowError, Result};
+
+/// 默认的响应体大小上限（32 MiB）
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 32 * 1024 * 1024;
+
+/// 逐块读取响应体；`Content-Length` 或已读取的字节数超过 `limit` 时返回错误
+pub(crate) async fn read_body(mut response: reqwest::Response, limit: usize) -> Result<Vec<u8>> {
+    let url = response.url().to_string();
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        return Err(too_large("Response", &url, limit));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| {
+        AgentFlowError::Other(anyhow!("Failed to read response from {}: {}", url, e))
+    })? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large("Response", &url, limit));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// 读取限制大小的响应体并按 UTF-8 解码（无效字节替换为 U+FFFD）
+#[cfg(feature = "openai-client")]
+pub(crate) async fn read_text(response: reqwest::Response, limit: usize) -> Result<String> {
+    let body = read_body(response, limit).await?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// 序列化请求体，超过 `limit` 字节时在发送前返回错误
+#[cfg(feature = "openai-client")]
+pub(crate) fn encode_body(
+    url: &str,
+    body: &serde_json::Value,
+    limit: Option<usize>,
+) -> Result<Vec<u8>> {
+    let bytes =
+        serde_json::to_vec(body).map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+    match limit {
+        Some(limit) if bytes.len() > limit => Err(too_large("Request", url, limit)),
+        _ => Ok(bytes),
+    }
+}
+
+fn too_large(what: &str, url: &str, limit: usize) -> AgentFlowError {
+    AgentFlowError::Other(anyhow!(
+        "{} body for {} exceeds the limit of {} bytes",
+        what,
+        url,
+        limit
+    ))
+}
@@ -75,6 +75,48 @@ pub fn build_config_from_json(value: &serde_json::Value) -> Option<ApiEndpointCo
     Some(config)
 }
 
+/// Azure OpenAI 未配置 `api_version` 时使用的 API 版本
+pub const AZURE_OPENAI_API_VERSION: &str = "2024-10-21";
+
+/// Gemini `generateContent` 端点：`{base_url}/models/{model}:generateContent`
+///
+/// `base_url` 已是完整端点时原样返回；API Key 由调用方作为 `key` 查询参数附加。
+pub fn gemini_generate_content_url(base_url: &str, model: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    if base.ends_with(":generateContent") {
+        return base.to_string();
+    }
+    let model = model.strip_prefix("models/").unwrap_or(model);
+    format!("{}/models/{}:generateContent", base, model)
+}
+
+/// Azure OpenAI 对话端点：
+/// `{base_url}/openai/deployments/{deployment}/chat/completions?api-version={api_version}`
+///
+/// `base_url` 已包含部署路径或 `api-version` 时只补全缺少的部分。
+pub fn azure_openai_chat_url(base_url: &str, deployment: &str, api_version: &str) -> String {
+    let (base, query) = base_url.split_once('?').unwrap_or((base_url, ""));
+    let base = base.trim_end_matches('/');
+    let url = if base.ends_with("/chat/completions") {
+        base.to_string()
+    } else if base.contains("/deployments/") {
+        format!("{}/chat/completions", base)
+    } else {
+        let base = base.strip_suffix("/openai").unwrap_or(base);
+        format!(
+            "{}/openai/deployments/{}/chat/completions",
+            base, deployment
+        )
+    };
+    if query.contains("api-version=") {
+        format!("{}?{}", url, query)
+    } else if query.is_empty() {
+        format!("{}?api-version={}", url, api_version)
+    } else {
+        format!("{}?{}&api-version={}", url, query, api_version)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,4 +156,24 @@ mod tests {
         assert_eq!(config.base_url, "https://api.example.com/v1");
         assert_eq!(config.auth_header, Some("Bearer".to_string()));
     }
+
+    #[test]
+    fn test_provider_endpoint_builders() {
+        assert_eq!(
+            gemini_generate_content_url("https://generativelanguage.googleapis.com/v1beta/", "gemini-1.5-pro"),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-pro:generateContent"
+        );
+        assert_eq!(
+            azure_openai_chat_url("https://res.openai.azure.com", "gpt-4o", "2024-10-21"),
+            "https://res.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version=2024-10-21"
+        );
+        assert_eq!(
+            azure_openai_chat_url(
+                "https://res.openai.azure.com/openai/deployments/prod?api-version=2024-02-01",
+                "gpt-4o",
+                "2024-10-21"
+            ),
+            "https://res.openai.azure.com/openai/deployments/prod/chat/completions?api-version=2024-02-01"
+        );
+    }
 }
@@ -9,11 +9,32 @@ use reqwest;
 #[cfg(feature = "openai-client")]
 use tracing::instrument;
 
+use super::body::{encode_body, read_body, read_text, DEFAULT_MAX_RESPONSE_BYTES};
+use super::configs::{
+    azure_openai_chat_url, gemini_generate_content_url, AZURE_OPENAI_API_VERSION,
+};
+use super::middleware::{LlmHttpRequest, LlmHttpResponse, LlmMiddleware, MiddlewareStack};
+use super::pool::{default_http_client, shared_http_client, HttpPoolConfig};
+use super::stream::SseParser;
 use crate::error::{AgentFlowError, Result};
+use crate::llm::audio::{
+    audio_extension, audio_mime_type, dashscope_speech_body, dashscope_transcription_body,
+    openai_speech_body, parse_dashscope_speech, parse_transcription, DashScopeSpeech, SpeechAudio,
+    SpeechRequest, TranscriptionRequest, DASHSCOPE_MULTIMODAL_PATH,
+};
 use crate::llm::client::{DynLlmClient, LlmClient, LlmStream};
-use crate::llm::types::{ApiFormat, LlmRequest, LlmResponse, LlmStreamChunk};
+#[cfg(feature = "openai-client")]
+use crate::llm::embedding::{embedding_request_body, parse_embeddings, DASHSCOPE_EMBEDDING_PATH};
+use crate::llm::error::{excerpt, LlmHttpError, RESPONSE_EXCERPT_BYTES};
+use crate::llm::image::{ImageGenClient, ImageGenRequest};
+use crate::llm::logging::LlmCallLogger;
+use crate::llm::refusal::detect_refusal;
+use crate::llm::types::{
+    ApiFormat, ContentPart, LlmParams, LlmRequest, LlmResponse, LlmStreamChunk, MediaSource,
+};
 use anyhow::anyhow;
-use futures::StreamExt;
+use base64::Engine;
+use futures::{StreamExt, TryStreamExt};
 
 #[cfg(feature = "openai-client")]
 #[derive(Clone)]
@@ -24,26 +45,19 @@ pub struct GenericHttpClient {
     model: String,
     format: ApiFormat,
     auth_header: Option<String>,
+    embedding_model: Option<String>,
+    embedding_endpoint: Option<String>,
+    transcription_model: Option<String>,
+    speech_model: Option<String>,
+    api_version: Option<String>,
+    middleware: MiddlewareStack,
+    call_logger: LlmCallLogger,
+    max_request_bytes: Option<usize>,
+    max_response_bytes: usize,
 }
 
 #[cfg(feature = "openai-client")]
 impl GenericHttpClient {
-    /// 创建优化的 HTTP 客户端配置
-    ///
-    /// 优化项：
-    /// - 连接池：复用连接，提高性能
-    /// - 超时设置：避免长时间等待
-    /// - HTTP/2：支持多路复用
-    fn create_optimized_client() -> reqwest::Client {
-        reqwest::Client::builder()
-            .pool_max_idle_per_host(10) // 每个主机最多保持 10 个空闲连接
-            .pool_idle_timeout(std::time::Duration::from_secs(90))
-            .connect_timeout(std::time::Duration::from_secs(10))
-            .timeout(std::time::Duration::from_secs(300))
-            .build()
-            .expect("Failed to build HTTP client with custom config")
-    }
-
     pub fn new<S1, S2, S3>(endpoint: S1, api_key: S2, model: S3, format: ApiFormat) -> Self
     where
         S1: Into<String>,
@@ -51,12 +65,21 @@ impl GenericHttpClient {
         S3: Into<String>,
     {
         Self {
-            client: Self::create_optimized_client(),
+            client: default_http_client(),
             endpoint: endpoint.into(),
             api_key: api_key.into(),
             model: model.into(),
             format,
             auth_header: None,
+            embedding_model: None,
+            embedding_endpoint: None,
+            transcription_model: None,
+            speech_model: None,
+            api_version: None,
+            middleware: MiddlewareStack::default(),
+            call_logger: LlmCallLogger::default(),
+            max_request_bytes: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
         }
     }
 
@@ -74,155 +97,218 @@ impl GenericHttpClient {
         S4: Into<String>,
     {
         Self {
-            client: Self::create_optimized_client(),
+            client: default_http_client(),
             endpoint: endpoint.into(),
             api_key: api_key.into(),
             model: model.into(),
             format,
             auth_header: Some(auth_header.into()),
+            embedding_model: None,
+            embedding_endpoint: None,
+            transcription_model: None,
+            speech_model: None,
+            api_version: None,
+            middleware: MiddlewareStack::default(),
+            call_logger: LlmCallLogger::default(),
+            max_request_bytes: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
         }
     }
 
-    /// 检查是否是图片生成模型
-    fn is_image_generation_model(&self) -> bool {
-        self.model.contains("t2i") || 
-        self.model.contains("dalle") || 
-        self.model.starts_with("wan")
+    /// 替换底层 `reqwest::Client`，默认使用进程级共享的连接池（`default_http_client`）
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
     }
 
-    /// 处理图片生成请求
-    async fn complete_image_generation(&self, request: LlmRequest) -> Result<LlmResponse> {
-        use serde::Deserialize;
-        use tokio::time::{sleep, Duration};
+    /// 按连接池配置（代理、CA 证书、TLS 校验等）使用共享的 `reqwest::Client`
+    pub fn with_http_config(self, config: &HttpPoolConfig) -> Result<Self> {
+        Ok(self.with_http_client(shared_http_client(config)?))
+    }
 
-        #[derive(Deserialize)]
-        struct WanxiangResponse {
-            output: WanxiangOutput,
-        }
+    /// 请求体的大小上限（字节），超过时不发送请求；默认不限制
+    pub fn with_max_request_bytes(mut self, limit: usize) -> Self {
+        self.max_request_bytes = Some(limit);
+        self
+    }
 
-        #[derive(Deserialize)]
-        struct WanxiangOutput {
-            task_id: String,
-            task_status: String,
-            #[serde(default)]
-            results: Vec<WanxiangResult>,
-        }
+    /// 响应体的大小上限（字节），默认 `DEFAULT_MAX_RESPONSE_BYTES`；响应逐块读取，超过时立即中止
+    pub fn with_max_response_bytes(mut self, limit: usize) -> Self {
+        self.max_response_bytes = limit;
+        self
+    }
 
-        #[derive(Deserialize)]
-        struct WanxiangResult {
-            url: String,
-        }
+    /// 设置 `embed` 使用的向量模型（如 `text-embedding-3-small`、`text-embedding-v3`）
+    pub fn with_embedding_model(mut self, model: impl Into<String>) -> Self {
+        self.embedding_model = Some(model.into());
+        self
+    }
 
-        let body = json!({
-            "model": self.model,
-            "input": {
-                "prompt": request.user
-            },
-            "parameters": {
-                "style": "<auto>",
-                "size": "1024*1024",
-                "n": 1
-            }
-        });
+    /// 设置完整的 Embedding 端点，不设置时根据对话端点推断
+    pub fn with_embedding_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.embedding_endpoint = Some(endpoint.into());
+        self
+    }
 
-        let auth_value = if let Some(header) = &self.auth_header {
-            format!("{} {}", header, self.api_key)
-        } else {
-            format!("Bearer {}", self.api_key)
-        };
+    /// 设置 `transcribe` 使用的语音识别模型（如 `whisper-1`、`qwen3-asr-flash`）
+    pub fn with_transcription_model(mut self, model: impl Into<String>) -> Self {
+        self.transcription_model = Some(model.into());
+        self
+    }
 
-        let response = self.client
-            .post(&self.endpoint)
-            .header("Authorization", auth_value.clone())
-            .header("Content-Type", "application/json")
-            .header("X-DashScope-Async", "enable")
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| AgentFlowError::Other(anyhow!("Image generation request failed: {}", e)))?;
+    /// 设置 `speak` 使用的语音合成模型（如 `tts-1`、`qwen-tts`）
+    pub fn with_speech_model(mut self, model: impl Into<String>) -> Self {
+        self.speech_model = Some(model.into());
+        self
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await
-                .map_err(|e| AgentFlowError::Other(anyhow!("Failed to read error response: {}", e)))?;
-            return Err(AgentFlowError::Other(anyhow!(
-                "Image generation API error: {}",
-                error_text
-            )));
-        }
+    /// 设置 Azure OpenAI 的 `api-version`，默认 `AZURE_OPENAI_API_VERSION`
+    pub fn with_api_version(mut self, api_version: impl Into<String>) -> Self {
+        self.api_version = Some(api_version.into());
+        self
+    }
 
-        let submit_resp: WanxiangResponse = response.json().await.map_err(|e| {
-            AgentFlowError::Other(anyhow!("Failed to parse image generation response: {}", e))
-        })?;
+    /// 追加中间件，`before_request` 按添加顺序执行，`after_response` 按相反顺序执行
+    pub fn with_middleware(mut self, middleware: impl LlmMiddleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
 
-        if submit_resp.output.task_status == "SUCCEEDED" && !submit_resp.output.results.is_empty() {
-            let image_url = &submit_resp.output.results[0].url;
-            return Ok(LlmResponse {
-                content: json!({
-                    "image_url": image_url,
-                    "task_id": submit_resp.output.task_id
-                }).to_string(),
-                metadata: None,
-            });
-        }
+    /// 追加已共享的中间件
+    pub fn with_middleware_arc(mut self, middleware: Arc<dyn LlmMiddleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
 
-        let task_id = submit_resp.output.task_id;
-        let query_endpoint = "https://dashscope.aliyuncs.com/api/v1/tasks";
-        let query_url = format!("{}/{}", query_endpoint, task_id);
+    /// 替换请求 / 响应日志的脱敏配置（日志在 `agentflow::llm` target 的 debug 级别输出）
+    pub fn with_call_logger(mut self, logger: LlmCallLogger) -> Self {
+        self.call_logger = logger;
+        self
+    }
 
-        for _ in 0..60 {
-            sleep(Duration::from_secs(2)).await;
+    /// 是否使用 DashScope 原生 Embedding 格式
+    fn is_dashscope_native(&self) -> bool {
+        matches!(self.format, ApiFormat::Qwen) && !self.endpoint.contains("compatible-mode")
+    }
 
-            let query_response = self.client
-                .get(&query_url)
-                .header("Authorization", auth_value.clone())
-                .send()
-                .await;
+    /// Embedding 端点：OpenAI 兼容接口为 `{base}/embeddings`，DashScope 原生接口为
+    /// `{base}/services/embeddings/text-embedding/text-embedding`
+    fn embedding_endpoint(&self) -> String {
+        if let Some(endpoint) = &self.embedding_endpoint {
+            return endpoint.clone();
+        }
+        let endpoint = self.endpoint.trim_end_matches('/');
+        if self.is_dashscope_native() {
+            let base = endpoint
+                .find("/services/")
+                .map_or(endpoint, |index| &endpoint[..index]);
+            return format!("{}{}", base, DASHSCOPE_EMBEDDING_PATH);
+        }
+        let base = endpoint
+            .strip_suffix("/chat/completions")
+            .unwrap_or(endpoint);
+        format!("{}/embeddings", base)
+    }
 
-            if query_response.is_err() {
-                continue;
-            }
+    /// 音频端点：OpenAI 兼容接口为 `{base}/audio/{path}`，DashScope 原生接口为多模态生成接口
+    fn audio_endpoint(&self, path: &str) -> String {
+        let endpoint = self.endpoint.trim_end_matches('/');
+        if self.is_dashscope_native() {
+            let base = endpoint
+                .find("/services/")
+                .map_or(endpoint, |index| &endpoint[..index]);
+            return format!("{}{}", base, DASHSCOPE_MULTIMODAL_PATH);
+        }
+        let base = endpoint
+            .strip_suffix("/chat/completions")
+            .unwrap_or(endpoint);
+        format!("{}/audio/{}", base, path)
+    }
 
-            let query_response = query_response.unwrap();
-            if !query_response.status().is_success() {
-                continue;
-            }
+    fn auth_value(&self) -> String {
+        match &self.auth_header {
+            Some(header) => format!("{} {}", header, self.api_key),
+            None => format!("Bearer {}", self.api_key),
+        }
+    }
 
-            let result: WanxiangResponse = match query_response.json().await {
-                Ok(r) => r,
-                Err(_) => continue,
-            };
+    /// 按格式附加认证信息：Gemini 使用 `key` 查询参数，Azure OpenAI 使用 `api-key` 请求头，
+    /// 其余（以及显式配置了 `auth_header` 时）使用 `Authorization` 请求头
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match (&self.format, &self.auth_header) {
+            (ApiFormat::Gemini, None) => request.query(&[("key", &self.api_key)]),
+            (ApiFormat::AzureOpenAI, None) => request.header("api-key", &self.api_key),
+            _ => request.header("Authorization", self.auth_value()),
+        }
+    }
 
-            match result.output.task_status.as_str() {
-                "SUCCEEDED" if !result.output.results.is_empty() => {
-                    let image_url = &result.output.results[0].url;
-                    return Ok(LlmResponse {
-                        content: json!({
-                            "image_url": image_url,
-                            "task_id": task_id
-                        }).to_string(),
-                        metadata: None,
-                    });
-                }
-                "FAILED" => {
-                    return Err(AgentFlowError::Other(anyhow!("Image generation failed")));
-                }
-                _ => continue,
+    /// 读取音频字节，URL 来源会先下载
+    async fn fetch_audio(&self, source: &MediaSource) -> Result<(String, Vec<u8>)> {
+        match source {
+            MediaSource::Base64 { mime_type, data } => {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(data)
+                    .map_err(|e| AgentFlowError::Other(anyhow!("Invalid base64 audio: {}", e)))?;
+                Ok((mime_type.clone(), bytes))
+            }
+            MediaSource::Url { url } => {
+                let response = self
+                    .client
+                    .get(url)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| AgentFlowError::Other(anyhow!("Audio download failed: {}", e)))?;
+                let mime_type = response
+                    .headers()
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or("audio/mpeg")
+                    .to_string();
+                let bytes = read_body(response, self.max_response_bytes).await?;
+                Ok((mime_type, bytes))
             }
         }
+    }
 
-        Err(AgentFlowError::Other(anyhow!("Image generation timeout")))
+    async fn post_json(&self, url: &str, body: &Value, what: &str) -> Result<Value> {
+        self.request_json(reqwest::Method::POST, url, Some(body), &[], what)
+            .await
     }
-}
 
-#[cfg(feature = "openai-client")]
-#[async_trait]
-impl LlmClient for GenericHttpClient {
-    #[instrument(skip(self))]
-    async fn complete(&self, request: LlmRequest) -> Result<LlmResponse> {
-        if self.is_image_generation_model() {
-            return self.complete_image_generation(request).await;
+    /// 对话端点（未拼接路径）
+    pub(crate) fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// 发送带认证头的 JSON 请求，`headers` 为附加的请求头
+    pub(crate) async fn request_json(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        body: Option<&Value>,
+        headers: &[(&str, &str)],
+        what: &str,
+    ) -> Result<Value> {
+        let mut request = LlmHttpRequest::new(method.as_str(), url, body.cloned());
+        for (name, value) in headers {
+            request.set_header(*name, *value);
         }
-        
+        let (_, response) = self.send_http(request, what).await?;
+        let body: Value = response.json().unwrap_or(Value::Null);
+        if !response.is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "{} endpoint returned {}: {}",
+                what,
+                response.status,
+                body
+            )));
+        }
+        Ok(body)
+    }
+
+    /// 构建对话请求：按 API 格式生成请求体并确定完整端点
+    fn chat_request(&self, request: &LlmRequest) -> LlmHttpRequest {
         let mut messages = Vec::new();
         if let Some(system) = &request.system {
             messages.push(json!({
@@ -231,37 +317,14 @@ impl LlmClient for GenericHttpClient {
             }));
         }
 
-        let mut user_content: Value = json!(request.user);
-        if request.image_url.is_some() || request.image_base64.is_some() {
-            let mut content_parts = Vec::new();
-            content_parts.push(json!({
-                "type": "text",
-                "text": request.user
-            }));
-            if let Some(image_url) = &request.image_url {
-                content_parts.push(json!({
-                    "type": "image_url",
-                    "image_url": {
-                        "url": image_url
-                    }
-                }));
-            } else if let Some(image_base64) = &request.image_base64 {
-                content_parts.push(json!({
-                    "type": "image_url",
-                    "image_url": {
-                        "url": format!("data:image/jpeg;base64,{}", image_base64)
-                    }
-                }));
-            }
-            user_content = json!(content_parts);
-        }
+        let user_content = user_content(request, &self.format);
 
         messages.push(json!({
             "role": "user",
             "content": user_content
         }));
 
-        let body = match &self.format {
+        let mut body = match &self.format {
             ApiFormat::OpenAI => {
                 let mut body = json!({
                     "model": self.model,
@@ -300,106 +363,230 @@ impl LlmClient for GenericHttpClient {
                     }
                 })
             }
+            ApiFormat::Gemini => {
+                let mut body = json!({
+                    "contents": [{ "role": "user", "parts": gemini_parts(request) }],
+                    "generationConfig": { "temperature": request.temperature },
+                });
+                if let Some(system) = &request.system {
+                    body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+                }
+                body
+            }
+            ApiFormat::AzureOpenAI => {
+                json!({
+                    "messages": messages,
+                    "temperature": request.temperature,
+                })
+            }
         };
+        sampling_params(&mut body, &request.params, &self.format);
 
-        let auth_value = if let Some(auth_header) = &self.auth_header {
-            format!("{} {}", auth_header, self.api_key)
-        } else {
-            format!("Bearer {}", self.api_key)
+        let full_endpoint = match &self.format {
+            ApiFormat::Gemini => gemini_generate_content_url(&self.endpoint, &self.model),
+            ApiFormat::AzureOpenAI => azure_openai_chat_url(
+                &self.endpoint,
+                &self.model,
+                self.api_version
+                    .as_deref()
+                    .unwrap_or(AZURE_OPENAI_API_VERSION),
+            ),
+            _ if self.endpoint.contains("/chat/completions")
+                || self.endpoint.contains("/services/")
+                || self.endpoint.contains("/generation") =>
+            {
+                self.endpoint.clone()
+            }
+            _ if self.endpoint.contains("compatible-mode") => {
+                format!("{}/chat/completions", self.endpoint.trim_end_matches('/'))
+            }
+            ApiFormat::Qwen => {
+                format!(
+                    "{}/services/aigc/text-generation/generation",
+                    self.endpoint.trim_end_matches('/')
+                )
+            }
+            _ => format!("{}/chat/completions", self.endpoint.trim_end_matches('/')),
         };
 
-        let full_endpoint = if self.endpoint.contains("/chat/completions") 
-            || self.endpoint.contains("/services/")
-            || self.endpoint.contains("/generation")
+        // DashScope 原生接口的多模态输入走 multimodal-generation
+        let full_endpoint = if matches!(self.format, ApiFormat::Qwen) && !request.content.is_empty()
         {
-            self.endpoint.clone()
+            full_endpoint.replace("/text-generation/", "/multimodal-generation/")
         } else {
-            if self.endpoint.contains("compatible-mode") {
-                format!("{}/chat/completions", self.endpoint.trim_end_matches('/'))
-            } else {
-                match &self.format {
-                    ApiFormat::OpenAI => {
-                        format!("{}/chat/completions", self.endpoint.trim_end_matches('/'))
-                    }
-                    ApiFormat::QwenVision => {
-                        format!("{}/chat/completions", self.endpoint.trim_end_matches('/'))
-                    }
-                    ApiFormat::Qwen => {
-                        format!(
-                            "{}/services/aigc/text-generation/generation",
-                            self.endpoint.trim_end_matches('/')
-                        )
-                    }
-                }
-            }
+            full_endpoint
         };
 
-        let mut request_builder = self
-            .client
-            .post(&full_endpoint)
-            .header("Authorization", auth_value)
-            .header("Content-Type", "application/json");
-        
+        let mut http_request = LlmHttpRequest::new("POST", full_endpoint, Some(body));
         if self.endpoint.contains("bigmodel.cn") {
-            request_builder = request_builder
-                .header("Accept", "application/json")
-                .header("User-Agent", "agentflow/1.0.0");
+            http_request.set_header("Accept", "application/json");
+            http_request.set_header("User-Agent", "agentflow/1.0.0");
         }
+        http_request
+    }
 
-        let response = request_builder
-            .json(&body)
+    /// 把中间件处理后的请求转换为带认证信息的 reqwest 请求，并记录请求日志
+    fn build_request(&self, request: &LlmHttpRequest) -> Result<reqwest::RequestBuilder> {
+        let method = reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|_| {
+            AgentFlowError::Other(anyhow!("Invalid HTTP method `{}`", request.method))
+        })?;
+        let mut builder = self.authorize(self.client.request(method, &request.url));
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        if let Some(body) = &request.body {
+            builder = builder
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(encode_body(&request.url, body, self.max_request_bytes)?);
+        }
+        self.call_logger.log_request(
+            &request.method,
+            &request.url,
+            &request.headers,
+            request.body.as_ref(),
+        );
+        Ok(builder)
+    }
+
+    /// DashScope 原生接口的 SSE 流式输出：请求带 `X-DashScope-SSE: enable` 并开启
+    /// `incremental_output`，每个事件的 `output.text` 即新增片段；流式响应不经过 `after_response`
+    fn native_qwen_stream(&self, request: LlmRequest) -> LlmStream {
+        let client = self.clone();
+        let open = async move {
+            let mut http_request = client.chat_request(&request);
+            if let Some(body) = http_request.body.as_mut() {
+                body["parameters"]["incremental_output"] = json!(true);
+            }
+            http_request.set_header("X-DashScope-SSE", "enable");
+            client.middleware.before_request(&mut http_request).await?;
+            let response = client
+                .build_request(&http_request)?
+                .send()
+                .await
+                .map_err(|e| AgentFlowError::Other(anyhow!("HTTP request failed: {}", e)))?;
+            let status = response.status().as_u16();
+            if !(200..300).contains(&status) {
+                let body = read_text(response, client.max_response_bytes).await?;
+                return Err(AgentFlowError::LlmHttp(LlmHttpError::new(
+                    status,
+                    http_request.url,
+                    http_request.body.as_ref(),
+                    &body,
+                )));
+            }
+            Ok(response)
+        };
+        let chunks = futures::stream::once(open)
+            .map_ok(|response| {
+                futures::stream::try_unfold(
+                    Some((response, SseParser::new())),
+                    |state| async move {
+                        let Some((mut response, mut parser)) = state else {
+                            return Ok::<_, AgentFlowError>(None);
+                        };
+                        let bytes = response.chunk().await.map_err(|e| {
+                            AgentFlowError::Other(anyhow!("Failed to read SSE stream: {}", e))
+                        })?;
+                        Ok(Some(match bytes {
+                            Some(bytes) => (parser.parse_chunk(&bytes)?, Some((response, parser))),
+                            None => {
+                                let done = LlmStreamChunk {
+                                    content: String::new(),
+                                    done: true,
+                                };
+                                (vec![done], None)
+                            }
+                        }))
+                    },
+                )
+                .map_ok(|chunks| futures::stream::iter(chunks.into_iter().map(Ok)))
+                .try_flatten()
+            })
+            .try_flatten();
+        Box::pin(chunks)
+    }
+
+    /// 经过中间件栈发送 JSON 请求：先执行 `before_request`，附加认证信息后发送，
+    /// 再按相反顺序执行 `after_response`；返回中间件处理后的请求和响应
+    async fn send_http(
+        &self,
+        mut request: LlmHttpRequest,
+        what: &str,
+    ) -> Result<(LlmHttpRequest, LlmHttpResponse)> {
+        self.middleware.before_request(&mut request).await?;
+        let started = std::time::Instant::now();
+        let response = self
+            .build_request(&request)?
             .send()
             .await
-            .map_err(|e| AgentFlowError::Other(anyhow::anyhow!("HTTP request error: {}", e)))?;
+            .map_err(|e| AgentFlowError::Other(anyhow!("{} request failed: {}", what, e)))?;
+        let status = response.status().as_u16();
+        let body = read_text(response, self.max_response_bytes).await?;
+        self.call_logger
+            .log_response(&request.url, status, &body, started.elapsed());
+        let mut response = LlmHttpResponse { status, body };
+        self.middleware
+            .after_response(&request, &mut response)
+            .await?;
+        Ok((request, response))
+    }
 
-        let status = response.status();
-        let response_text = response.text().await.map_err(|e| {
-            AgentFlowError::Other(anyhow::anyhow!("Failed to read response: {}", e))
-        })?;
+    /// 检查是否是图片生成模型
+    fn is_image_generation_model(&self) -> bool {
+        self.model.contains("t2i") || 
+        self.model.contains("dalle") || 
+        self.model.starts_with("wan")
+    }
+
+    /// 图片生成模型交给 `ImageGenClient`，返回首张图片地址
+    async fn complete_image_generation(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let result = ImageGenClient::new(self.api_key.clone(), self.model.clone())
+            .with_endpoint(self.endpoint.clone())
+            .generate(&ImageGenRequest::new(request.user))
+            .await?;
+        Ok(LlmResponse {
+            content: json!({
+                "image_url": result.urls[0],
+                "task_id": result.task_id
+            })
+            .to_string(),
+            metadata: None,
+        })
+    }
+}
+
+#[cfg(feature = "openai-client")]
+#[async_trait]
+impl LlmClient for GenericHttpClient {
+    #[instrument(skip(self))]
+    async fn complete(&self, request: LlmRequest) -> Result<LlmResponse> {
+        if self.is_image_generation_model() {
+            return self.complete_image_generation(request).await;
+        }
         
-        if !status.is_success() {
-            if let Ok(body_str) = serde_json::to_string(&body) {
-                let truncated_body = if body_str.len() > 500 {
-                    if body_str.contains("base64") || body_str.len() > 1000 {
-                        format!(
-                            "{}...(请求体过长，已截断，总长度: {} 字节)",
-                            &body_str[..500],
-                            body_str.len()
-                        )
-                    } else {
-                        format!(
-                            "{}...(已截断，总长度: {} 字节)",
-                            &body_str[..500],
-                            body_str.len()
-                        )
-                    }
-                } else {
-                    body_str.clone()
-                };
-                
-                let truncated_response = if response_text.len() > 500 {
-                    format!(
-                        "{}...(响应过长，已截断，总长度: {} 字节)",
-                        &response_text[..500],
-                        response_text.len()
-                    )
-                } else {
-                    response_text.clone()
-                };
-                
-                return Err(AgentFlowError::Other(anyhow::anyhow!(
-                    "Request failed with status {}: {}\nEndpoint: {}\nRequest body (truncated): {}",
-                    status,
-                    truncated_response,
-                    full_endpoint,
-                    truncated_body
-                )));
+        let http_request = self.chat_request(&request);
+        let (http_request, response) = self.send_http(http_request, "HTTP").await?;
+        let LlmHttpRequest {
+            url: full_endpoint,
+            body,
+            ..
+        } = http_request;
+        let body = body.unwrap_or(Value::Null);
+        let status = response.status;
+        let response_text = response.body;
+
+        if !(200..300).contains(&status) {
+            if let Some(refusal) = serde_json::from_str::<Value>(&response_text)
+                .ok()
+                .and_then(|payload| detect_refusal(&payload))
+            {
+                return Err(AgentFlowError::Refused(refusal));
             }
-            return Err(AgentFlowError::Other(anyhow::anyhow!(
-                "Request failed with status {}: {}\nEndpoint: {}",
+            return Err(AgentFlowError::LlmHttp(LlmHttpError::new(
                 status,
-                response_text,
-                full_endpoint
+                full_endpoint,
+                Some(&body),
+                &response_text,
             )));
         }
         
@@ -407,18 +594,24 @@ impl LlmClient for GenericHttpClient {
             AgentFlowError::Other(anyhow::anyhow!(
                 "Response parse error: {}\nResponse body: {}",
                 e,
-                if response_text.len() > 500 {
-                    format!("{}...", &response_text[..500])
-                } else {
-                    response_text.clone()
-                }
+                excerpt(&response_text, RESPONSE_EXCERPT_BYTES)
             ))
         })?;
 
+        if let Some(refusal) = detect_refusal(&payload) {
+            return Err(AgentFlowError::Refused(refusal));
+        }
+
+        let gemini_text;
         let content = match &self.format {
             ApiFormat::OpenAI => payload["choices"][0]["message"]["content"].as_str(),
             ApiFormat::QwenVision => payload["choices"][0]["message"]["content"].as_str(),
             ApiFormat::Qwen => payload["output"]["text"].as_str(),
+            ApiFormat::AzureOpenAI => payload["choices"][0]["message"]["content"].as_str(),
+            ApiFormat::Gemini => {
+                gemini_text = gemini_content(&payload);
+                gemini_text.as_deref()
+            }
         }
         .ok_or_else(|| {
             if let Ok(payload_str) = serde_json::to_string(&payload) {
@@ -444,7 +637,174 @@ impl LlmClient for GenericHttpClient {
     /// 实现真正的 SSE 流式响应
     /// 
     /// 注意：对于不支持流式的格式（如 Qwen），会降级到默认的逐字符流式输出
+    #[instrument(skip(self, texts), fields(count = texts.len()))]
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let model = self.embedding_model.as_deref().ok_or_else(|| {
+            AgentFlowError::Other(anyhow!(
+                "No embedding model configured; call with_embedding_model() on the client"
+            ))
+        })?;
+        let auth_value = if let Some(auth_header) = &self.auth_header {
+            format!("{} {}", auth_header, self.api_key)
+        } else {
+            format!("Bearer {}", self.api_key)
+        };
+        let body = embedding_request_body(model, &texts, self.is_dashscope_native());
+        let url = self.embedding_endpoint();
+        self.call_logger.log_request("POST", &url, &[], Some(&body));
+        let started = std::time::Instant::now();
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", auth_value)
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Embedding request failed: {}", e)))?;
+        let status = response.status();
+        let text = read_text(response, self.max_response_bytes).await?;
+        self.call_logger
+            .log_response(&url, status.as_u16(), &text, started.elapsed());
+        let body: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Embedding endpoint returned {}: {}",
+                status,
+                body
+            )));
+        }
+        parse_embeddings(&body)
+            .filter(|vectors| vectors.len() == texts.len())
+            .ok_or_else(|| AgentFlowError::Other(anyhow!("Invalid embedding response: {}", body)))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn transcribe(&self, request: TranscriptionRequest) -> Result<String> {
+        let model = self.transcription_model.as_deref().ok_or_else(|| {
+            AgentFlowError::Other(anyhow!(
+                "No transcription model configured; call with_transcription_model() on the client"
+            ))
+        })?;
+        let url = self.audio_endpoint("transcriptions");
+        let body = if self.is_dashscope_native() {
+            self.post_json(
+                &url,
+                &dashscope_transcription_body(model, &request),
+                "Transcription",
+            )
+            .await?
+        } else {
+            let (mime_type, bytes) = self.fetch_audio(&request.audio).await?;
+            let file = reqwest::multipart::Part::bytes(bytes)
+                .file_name(format!("audio.{}", audio_extension(&mime_type)))
+                .mime_str(&mime_type)
+                .map_err(|e| AgentFlowError::Other(anyhow!("Invalid audio mime type: {}", e)))?;
+            let mut form = reqwest::multipart::Form::new()
+                .text("model", model.to_string())
+                .part("file", file);
+            if let Some(language) = request.language {
+                form = form.text("language", language);
+            }
+            if let Some(prompt) = request.prompt {
+                form = form.text("prompt", prompt);
+            }
+            let response = self
+                .client
+                .post(&url)
+                .header("Authorization", self.auth_value())
+                .multipart(form)
+                .send()
+                .await
+                .map_err(|e| {
+                    AgentFlowError::Other(anyhow!("Transcription request failed: {}", e))
+                })?;
+            let status = response.status();
+            let body = read_body(response, self.max_response_bytes).await?;
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            if !status.is_success() {
+                return Err(AgentFlowError::Other(anyhow!(
+                    "Transcription endpoint returned {}: {}",
+                    status,
+                    body
+                )));
+            }
+            body
+        };
+        parse_transcription(&body).ok_or_else(|| {
+            AgentFlowError::Other(anyhow!("Invalid transcription response: {}", body))
+        })
+    }
+
+    #[instrument(skip(self, request), fields(chars = request.text.chars().count()))]
+    async fn speak(&self, request: SpeechRequest) -> Result<SpeechAudio> {
+        let model = self.speech_model.as_deref().ok_or_else(|| {
+            AgentFlowError::Other(anyhow!(
+                "No speech model configured; call with_speech_model() on the client"
+            ))
+        })?;
+        let url = self.audio_endpoint("speech");
+        if self.is_dashscope_native() {
+            let body = self
+                .post_json(&url, &dashscope_speech_body(model, &request), "Speech")
+                .await?;
+            let data = match parse_dashscope_speech(&body) {
+                Some(DashScopeSpeech::Inline(data)) => data,
+                Some(DashScopeSpeech::Url(url)) => {
+                    self.fetch_audio(&MediaSource::Url { url }).await?.1
+                }
+                None => {
+                    return Err(AgentFlowError::Other(anyhow!(
+                        "Invalid speech response: {}",
+                        body
+                    )))
+                }
+            };
+            return Ok(SpeechAudio {
+                mime_type: audio_mime_type("wav"),
+                data,
+            });
+        }
+
+        let format = request.format.clone().unwrap_or_else(|| "mp3".to_string());
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", self.auth_value())
+            .header("Content-Type", "application/json")
+            .json(&openai_speech_body(model, &request))
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Speech request failed: {}", e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = read_text(response, self.max_response_bytes)
+                .await
+                .unwrap_or_default();
+            return Err(AgentFlowError::Other(anyhow!(
+                "Speech endpoint returned {}: {}",
+                status,
+                body
+            )));
+        }
+        let data = read_body(response, self.max_response_bytes).await?;
+        Ok(SpeechAudio {
+            mime_type: audio_mime_type(&format),
+            data,
+        })
+    }
+
     fn complete_stream(&self, request: LlmRequest) -> LlmStream {
+        if matches!(self.format, ApiFormat::Qwen)
+            && request.content.is_empty()
+            && !self.is_image_generation_model()
+        {
+            return self.native_qwen_stream(request);
+        }
         let request = Arc::new(request);
         let client = self.clone_dyn();
         
@@ -530,6 +890,577 @@ impl LlmClient for GenericHttpClient {
             model: self.model.clone(),
             format: self.format.clone(),
             auth_header: self.auth_header.clone(),
+            embedding_model: self.embedding_model.clone(),
+            embedding_endpoint: self.embedding_endpoint.clone(),
+            transcription_model: self.transcription_model.clone(),
+            speech_model: self.speech_model.clone(),
+            api_version: self.api_version.clone(),
+            middleware: self.middleware.clone(),
+            call_logger: self.call_logger.clone(),
+            max_request_bytes: self.max_request_bytes,
+            max_response_bytes: self.max_response_bytes,
         })
     }
 }
+
+/// 构建 user 消息的 content：没有多模态内容时为纯文本，否则按格式转换为内容数组
+#[cfg(feature = "openai-client")]
+fn user_content(request: &LlmRequest, format: &ApiFormat) -> Value {
+    if request.content.is_empty() {
+        return json!(request.user);
+    }
+    let parts = std::iter::once(ContentPart::text(request.user.clone()))
+        .filter(|_| !request.user.is_empty())
+        .chain(request.content.iter().cloned());
+    let parts: Vec<Value> = match format {
+        ApiFormat::Gemini => gemini_parts(request),
+        // DashScope 原生多模态格式：{"text"} / {"image"} / {"audio"} / {"video"}
+        ApiFormat::Qwen => parts
+            .map(|part| match part {
+                ContentPart::Text { text } => json!({ "text": text }),
+                ContentPart::Image { source } => json!({ "image": source.to_url() }),
+                ContentPart::Audio { source } => json!({ "audio": source.to_url() }),
+                ContentPart::Video { source } => json!({ "video": source.to_url() }),
+            })
+            .collect(),
+        // OpenAI 兼容格式（含 DashScope compatible-mode）
+        ApiFormat::OpenAI | ApiFormat::QwenVision | ApiFormat::AzureOpenAI => parts
+            .map(|part| match part {
+                ContentPart::Text { text } => json!({ "type": "text", "text": text }),
+                ContentPart::Image { source } => {
+                    json!({ "type": "image_url", "image_url": { "url": source.to_url() } })
+                }
+                ContentPart::Audio { source } => match source {
+                    MediaSource::Base64 { mime_type, data } => json!({
+                        "type": "input_audio",
+                        "input_audio": { "data": data, "format": audio_format(&mime_type) }
+                    }),
+                    MediaSource::Url { url } => {
+                        json!({ "type": "input_audio", "input_audio": { "data": url } })
+                    }
+                },
+                ContentPart::Video { source } => {
+                    json!({ "type": "video_url", "video_url": { "url": source.to_url() } })
+                }
+            })
+            .collect(),
+    };
+    json!(parts)
+}
+
+/// Gemini 的 `parts`：文本为 `{"text"}`，Base64 媒体为 `inline_data`，URL 媒体为 `file_data`
+#[cfg(feature = "openai-client")]
+fn gemini_parts(request: &LlmRequest) -> Vec<Value> {
+    std::iter::once(ContentPart::text(request.user.clone()))
+        .filter(|_| !request.user.is_empty() || request.content.is_empty())
+        .chain(request.content.iter().cloned())
+        .map(|part| match part {
+            ContentPart::Text { text } => json!({ "text": text }),
+            ContentPart::Image { source }
+            | ContentPart::Audio { source }
+            | ContentPart::Video { source } => match source {
+                MediaSource::Base64 { mime_type, data } => {
+                    json!({ "inline_data": { "mime_type": mime_type, "data": data } })
+                }
+                MediaSource::Url { url } => json!({ "file_data": { "file_uri": url } }),
+            },
+        })
+        .collect()
+}
+
+/// 按 API 格式写入采样参数：OpenAI 兼容格式放在顶层，通义千问原生格式放在 `parameters`
+/// （不支持 `frequency_penalty`），Gemini 放在 `generationConfig`
+#[cfg(feature = "openai-client")]
+fn sampling_params(body: &mut Value, params: &LlmParams, format: &ApiFormat) {
+    let (target, keys) = match format {
+        ApiFormat::OpenAI | ApiFormat::QwenVision | ApiFormat::AzureOpenAI => (
+            &mut *body,
+            [
+                "top_p",
+                "max_tokens",
+                "frequency_penalty",
+                "presence_penalty",
+                "stop",
+                "seed",
+            ],
+        ),
+        // 空键表示该格式不支持
+        ApiFormat::Qwen => (
+            &mut body["parameters"],
+            [
+                "top_p",
+                "max_tokens",
+                "",
+                "presence_penalty",
+                "stop",
+                "seed",
+            ],
+        ),
+        ApiFormat::Gemini => (
+            &mut body["generationConfig"],
+            [
+                "topP",
+                "maxOutputTokens",
+                "frequencyPenalty",
+                "presencePenalty",
+                "stopSequences",
+                "seed",
+            ],
+        ),
+    };
+    let values = [
+        params.top_p.map(|v| json!(v)),
+        params.max_tokens.map(|v| json!(v)),
+        params.frequency_penalty.map(|v| json!(v)),
+        params.presence_penalty.map(|v| json!(v)),
+        (!params.stop.is_empty()).then(|| json!(params.stop)),
+        params.seed.map(|v| json!(v)),
+    ];
+    for (key, value) in keys.into_iter().zip(values) {
+        if let (false, Some(value)) = (key.is_empty(), value) {
+            target[key] = value;
+        }
+    }
+}
+
+/// 拼接 Gemini 回复中第一个候选的文本片段
+#[cfg(feature = "openai-client")]
+fn gemini_content(payload: &Value) -> Option<String> {
+    let parts = payload["candidates"][0]["content"]["parts"].as_array()?;
+    Some(
+        parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect(),
+    )
+}
+
+/// `audio/mpeg` → `mp3`，其他取子类型（如 `audio/wav` → `wav`）
+#[cfg(feature = "openai-client")]
+fn audio_format(mime_type: &str) -> &str {
+    match mime_type.rsplit('/').next().unwrap_or(mime_type) {
+        "mpeg" => "mp3",
+        other => other,
+    }
+}
+
+#[cfg(all(test, feature = "openai-client"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_endpoint_derivation() {
+        let openai = GenericHttpClient::new(
+            "https://api.openai.com/v1/chat/completions",
+            "k",
+            "gpt-4o",
+            ApiFormat::OpenAI,
+        );
+        assert_eq!(
+            openai.embedding_endpoint(),
+            "https://api.openai.com/v1/embeddings"
+        );
+
+        let qwen = GenericHttpClient::new(
+            "https://dashscope.aliyuncs.com/api/v1/services/aigc/text-generation/generation",
+            "k",
+            "qwen-max",
+            ApiFormat::Qwen,
+        );
+        assert!(qwen.is_dashscope_native());
+        assert_eq!(
+            qwen.embedding_endpoint(),
+            "https://dashscope.aliyuncs.com/api/v1/services/embeddings/text-embedding/text-embedding"
+        );
+
+        let custom = openai.with_embedding_endpoint("http://localhost:8080/embed");
+        assert_eq!(custom.embedding_endpoint(), "http://localhost:8080/embed");
+    }
+
+    #[test]
+    fn test_user_content_parts_per_format() {
+        let request = LlmRequest {
+            system: None,
+            user: "compare".into(),
+            temperature: 0.2,
+            metadata: None,
+            content: vec![
+                ContentPart::image_url("https://example.com/a.png"),
+                ContentPart::image_base64("image/png", "AAAA"),
+                ContentPart::Audio {
+                    source: MediaSource::Base64 {
+                        mime_type: "audio/mpeg".into(),
+                        data: "BBBB".into(),
+                    },
+                },
+            ],
+            params: Default::default(),
+        };
+
+        let openai = user_content(&request, &ApiFormat::OpenAI);
+        assert_eq!(openai[0], json!({ "type": "text", "text": "compare" }));
+        assert_eq!(openai[1]["image_url"]["url"], "https://example.com/a.png");
+        assert_eq!(openai[2]["image_url"]["url"], "data:image/png;base64,AAAA");
+        assert_eq!(
+            openai[3],
+            json!({ "type": "input_audio", "input_audio": { "data": "BBBB", "format": "mp3" } })
+        );
+
+        let qwen = user_content(&request, &ApiFormat::Qwen);
+        assert_eq!(qwen[0], json!({ "text": "compare" }));
+        assert_eq!(qwen[2], json!({ "image": "data:image/png;base64,AAAA" }));
+
+        let plain = LlmRequest {
+            content: Vec::new(),
+            ..request
+        };
+        assert_eq!(user_content(&plain, &ApiFormat::OpenAI), json!("compare"));
+    }
+
+    /// 返回固定响应的本地 HTTP 服务，记录请求行、请求头和请求体
+    fn capture_server(
+        response: Value,
+    ) -> (String, std::sync::mpsc::Receiver<(String, String, Value)>) {
+        serve("200 OK", "application/json", response.to_string())
+    }
+
+    /// 以固定状态行、类型和内容响应每个请求，并把请求行、请求头和 JSON 请求体发回测试
+    fn serve(
+        status: &'static str,
+        content_type: &'static str,
+        response: String,
+    ) -> (String, std::sync::mpsc::Receiver<(String, String, Value)>) {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut headers = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                    if let Some((key, value)) = line.trim_end().split_once(": ") {
+                        if key.eq_ignore_ascii_case("content-length") {
+                            length = value.parse().unwrap();
+                        }
+                    }
+                    headers.push_str(&line.to_ascii_lowercase());
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            status,
+                            content_type,
+                            response.len(),
+                            response
+                        )
+                        .as_bytes(),
+                    )
+                    .unwrap();
+                let _ = tx.send((
+                    request_line.trim_end().to_string(),
+                    headers,
+                    serde_json::from_slice(&body).unwrap(),
+                ));
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn test_gemini_and_azure_requests() {
+        let request = LlmRequest {
+            system: Some("be brief".into()),
+            user: "hi".into(),
+            temperature: 0.3,
+            metadata: None,
+            content: Vec::new(),
+            params: Default::default(),
+        };
+
+        let (url, requests) = capture_server(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "hel" }, { "text": "lo" }] } }]
+        }));
+        let gemini = GenericHttpClient::new(
+            format!("{url}/v1beta"),
+            "g-key",
+            "gemini-1.5-flash",
+            ApiFormat::Gemini,
+        );
+        assert_eq!(
+            gemini.complete(request.clone()).await.unwrap().content,
+            "hello"
+        );
+        let (line, headers, body) = requests.recv().unwrap();
+        assert_eq!(
+            line,
+            "POST /v1beta/models/gemini-1.5-flash:generateContent?key=g-key HTTP/1.1"
+        );
+        assert!(!headers.contains("authorization"));
+        assert_eq!(body["contents"][0]["parts"], json!([{ "text": "hi" }]));
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "be brief");
+
+        let (url, requests) = capture_server(json!({
+            "choices": [{ "message": { "role": "assistant", "content": "hello" } }]
+        }));
+        let azure = GenericHttpClient::new(url, "az-key", "prod-gpt4o", ApiFormat::AzureOpenAI)
+            .with_api_version("2024-06-01");
+        assert_eq!(azure.complete(request).await.unwrap().content, "hello");
+        let (line, headers, body) = requests.recv().unwrap();
+        assert_eq!(
+            line,
+            "POST /openai/deployments/prod-gpt4o/chat/completions?api-version=2024-06-01 HTTP/1.1"
+        );
+        assert!(headers.contains("api-key: az-key"));
+        assert!(!headers.contains("authorization"));
+        assert_eq!(body["messages"][0]["role"], "system");
+    }
+
+    #[tokio::test]
+    async fn test_request_and_response_size_limits() {
+        let request = LlmRequest {
+            system: None,
+            user: "hi".into(),
+            temperature: 0.7,
+            metadata: None,
+            content: Vec::new(),
+            params: Default::default(),
+        };
+        let (url, requests) = capture_server(json!({
+            "choices": [{ "message": { "role": "assistant", "content": "x".repeat(4096) } }]
+        }));
+        let client = GenericHttpClient::new(url, "key", "gpt-4o", ApiFormat::OpenAI);
+        let limited = client.clone().with_max_response_bytes(1024);
+        let error = limited.complete(request.clone()).await.unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("exceeds the limit of 1024 bytes"));
+        requests.recv().unwrap();
+        let response = client.complete(request.clone()).await.unwrap();
+        assert_eq!(response.content.len(), 4096);
+        requests.recv().unwrap();
+
+        let error = client
+            .with_max_request_bytes(16)
+            .complete(request)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Request body"));
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_native_qwen_sse_stream() {
+        let events = [
+            r#"{"output":{"text":"你好","finish_reason":"null"},"request_id":"r1"}"#,
+            r#"{"output":{"text":"，世界","finish_reason":"null"},"request_id":"r1"}"#,
+            r#"{"output":{"text":"","finish_reason":"stop"},"usage":{"output_tokens":4}}"#,
+        ];
+        let body: String = events
+            .iter()
+            .enumerate()
+            .map(|(i, data)| {
+                format!(
+                    "id:{}\nevent:result\n:HTTP_STATUS/200\ndata:{}\n\n",
+                    i + 1,
+                    data
+                )
+            })
+            .collect();
+        let (url, requests) = serve("200 OK", "text/event-stream", body);
+        let client = GenericHttpClient::new(url, "key", "qwen-max", ApiFormat::Qwen);
+        let chunks: Vec<_> = client
+            .complete_stream(LlmRequest {
+                system: None,
+                user: "hi".into(),
+                temperature: 0.7,
+                metadata: None,
+                content: Vec::new(),
+                params: Default::default(),
+            })
+            .collect()
+            .await;
+        let chunks: Vec<_> = chunks.into_iter().map(|chunk| chunk.unwrap()).collect();
+        let text: Vec<_> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(text, ["你好", "，世界", ""]);
+        assert!(chunks.last().unwrap().done);
+
+        let (line, headers, body) = requests.recv().unwrap();
+        assert_eq!(
+            line,
+            "POST /services/aigc/text-generation/generation HTTP/1.1"
+        );
+        assert!(headers.contains("x-dashscope-sse: enable"));
+        assert_eq!(body["parameters"]["incremental_output"], true);
+    }
+
+    #[tokio::test]
+    async fn test_error_response_is_structured() {
+        let (url, _requests) = serve(
+            "401 Unauthorized",
+            "application/json",
+            json!({ "error": { "message": "Incorrect API key", "code": "invalid_api_key" } })
+                .to_string(),
+        );
+        let client = GenericHttpClient::new(url.clone(), "bad", "gpt-4o", ApiFormat::OpenAI);
+        let error = client
+            .complete(LlmRequest {
+                system: None,
+                user: "hi".into(),
+                temperature: 0.7,
+                metadata: None,
+                content: Vec::new(),
+                params: Default::default(),
+            })
+            .await
+            .unwrap_err();
+        let AgentFlowError::LlmHttp(error) = error else {
+            panic!("expected LlmHttp error, got {error}");
+        };
+        assert_eq!(error.status, 401);
+        assert_eq!(error.endpoint, format!("{}/chat/completions", url));
+        assert_eq!(error.provider_code.as_deref(), Some("invalid_api_key"));
+        assert!(error.request_digest.is_some());
+        assert!(error.is_auth_failure());
+    }
+
+    #[test]
+    fn test_sampling_params_per_format() {
+        let params = LlmParams {
+            top_p: Some(0.5),
+            max_tokens: Some(4096),
+            frequency_penalty: Some(0.1),
+            stop: vec!["END".into()],
+            seed: Some(7),
+            ..Default::default()
+        };
+
+        let mut openai = json!({ "model": "gpt-4o" });
+        sampling_params(&mut openai, &params, &ApiFormat::OpenAI);
+        assert_eq!(
+            openai,
+            json!({
+                "model": "gpt-4o",
+                "top_p": 0.5,
+                "max_tokens": 4096,
+                "frequency_penalty": 0.1f32,
+                "stop": ["END"],
+                "seed": 7
+            })
+        );
+
+        let mut qwen = json!({ "parameters": { "max_tokens": 2000 } });
+        sampling_params(&mut qwen, &params, &ApiFormat::Qwen);
+        assert_eq!(qwen["parameters"]["max_tokens"], 4096);
+        assert_eq!(qwen["parameters"]["seed"], 7);
+        assert!(qwen["parameters"].get("frequency_penalty").is_none());
+
+        let mut gemini = json!({ "generationConfig": { "temperature": 0.2 } });
+        sampling_params(&mut gemini, &params, &ApiFormat::Gemini);
+        assert_eq!(gemini["generationConfig"]["maxOutputTokens"], 4096);
+        assert_eq!(gemini["generationConfig"]["stopSequences"], json!(["END"]));
+        assert_eq!(gemini["generationConfig"]["topP"], 0.5);
+
+        let mut plain = json!({ "model": "gpt-4o" });
+        sampling_params(&mut plain, &LlmParams::default(), &ApiFormat::OpenAI);
+        assert_eq!(plain, json!({ "model": "gpt-4o" }));
+    }
+
+    #[tokio::test]
+    async fn test_middleware_rewrites_request_and_response() {
+        use crate::llm::http::middleware::{LlmHttpRequest, LlmHttpResponse, LlmMiddleware};
+        use std::sync::Mutex;
+
+        struct Tenant;
+
+        #[async_trait]
+        impl LlmMiddleware for Tenant {
+            async fn before_request(&self, request: &mut LlmHttpRequest) -> Result<()> {
+                request.set_header("X-Tenant", "acme");
+                if let Some(body) = request.body.as_mut() {
+                    body["temperature"] = json!(0.0);
+                }
+                Ok(())
+            }
+
+            async fn after_response(
+                &self,
+                _request: &LlmHttpRequest,
+                response: &mut LlmHttpResponse,
+            ) -> Result<()> {
+                let mut payload = response.json().unwrap();
+                let content = payload["choices"][0]["message"]["content"]
+                    .as_str()
+                    .unwrap()
+                    .to_uppercase();
+                payload["choices"][0]["message"]["content"] = json!(content);
+                response.set_json(&payload);
+                Ok(())
+            }
+        }
+
+        /// 记录调用顺序
+        struct Trace(&'static str, Arc<Mutex<Vec<String>>>);
+
+        #[async_trait]
+        impl LlmMiddleware for Trace {
+            async fn before_request(&self, request: &mut LlmHttpRequest) -> Result<()> {
+                assert!(request.header("authorization").is_none());
+                self.1.lock().unwrap().push(format!("before {}", self.0));
+                Ok(())
+            }
+
+            async fn after_response(
+                &self,
+                _request: &LlmHttpRequest,
+                response: &mut LlmHttpResponse,
+            ) -> Result<()> {
+                assert_eq!(response.status, 200);
+                self.1.lock().unwrap().push(format!("after {}", self.0));
+                Ok(())
+            }
+        }
+
+        let (url, requests) = capture_server(json!({
+            "choices": [{ "message": { "role": "assistant", "content": "hello" } }]
+        }));
+        let trace = Arc::new(Mutex::new(Vec::new()));
+        let client = GenericHttpClient::new(url, "key", "gpt-4o", ApiFormat::OpenAI)
+            .with_middleware(Trace("outer", trace.clone()))
+            .with_middleware(Tenant)
+            .with_middleware(Trace("inner", trace.clone()));
+        let response = client
+            .complete(LlmRequest {
+                system: None,
+                user: "hi".into(),
+                temperature: 0.7,
+                metadata: None,
+                content: Vec::new(),
+                params: Default::default(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.content, "HELLO");
+
+        let (_, headers, body) = requests.recv().unwrap();
+        assert!(headers.contains("x-tenant: acme"));
+        assert!(headers.contains("authorization: bearer key"));
+        assert_eq!(body["temperature"], 0.0);
+        assert_eq!(
+            *trace.lock().unwrap(),
+            ["before outer", "before inner", "after inner", "after outer"]
+        );
+    }
+}
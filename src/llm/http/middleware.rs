@@ -0,0 +1,130 @@
+//! LLM 请求/响应中间件
+//!
+//! `GenericHttpClient` 在发送 JSON 请求前依次调用中间件的 `before_request`，收到响应后
+//! 按相反顺序调用 `after_response`（先注册的中间件最外层），可用于日志、注入请求头、
+//! 改写请求体或响应。认证信息在中间件之后附加，不会暴露给中间件。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::error::Result;
+
+/// 即将发送的 HTTP 请求
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmHttpRequest {
+    pub method: String,
+    pub url: String,
+    /// 附加的请求头（不含认证头）
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Value>,
+}
+
+impl LlmHttpRequest {
+    pub fn new(method: impl Into<String>, url: impl Into<String>, body: Option<Value>) -> Self {
+        Self {
+            method: method.into(),
+            url: url.into(),
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    /// 设置请求头，同名（忽略大小写）的已有值被替换
+    pub fn set_header(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        self.headers
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+        self.headers.push((name, value.into()));
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// 收到的 HTTP 响应
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmHttpResponse {
+    pub status: u16,
+    /// 原始响应体
+    pub body: String,
+}
+
+impl LlmHttpResponse {
+    /// 按 JSON 解析响应体
+    pub fn json(&self) -> Option<Value> {
+        serde_json::from_str(&self.body).ok()
+    }
+
+    /// 用 JSON 替换响应体
+    pub fn set_json(&mut self, value: &Value) {
+        self.body = value.to_string();
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// LLM 客户端中间件
+///
+/// 两个方法都有空的默认实现；返回错误时中止本次请求，错误原样返回给调用方。
+#[async_trait]
+pub trait LlmMiddleware: Send + Sync {
+    /// 请求发送前调用，可修改 URL、请求头和请求体
+    async fn before_request(&self, _request: &mut LlmHttpRequest) -> Result<()> {
+        Ok(())
+    }
+
+    /// 收到响应后、解析前调用，可改写状态码和响应体
+    async fn after_response(
+        &self,
+        _request: &LlmHttpRequest,
+        _response: &mut LlmHttpResponse,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// 中间件栈
+#[derive(Clone, Default)]
+pub struct MiddlewareStack {
+    layers: Vec<Arc<dyn LlmMiddleware>>,
+}
+
+impl MiddlewareStack {
+    pub fn push(&mut self, middleware: Arc<dyn LlmMiddleware>) {
+        self.layers.push(middleware);
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub(crate) async fn before_request(&self, request: &mut LlmHttpRequest) -> Result<()> {
+        for layer in &self.layers {
+            layer.before_request(request).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn after_response(
+        &self,
+        request: &LlmHttpRequest,
+        response: &mut LlmHttpResponse,
+    ) -> Result<()> {
+        for layer in self.layers.iter().rev() {
+            layer.after_response(request, response).await?;
+        }
+        Ok(())
+    }
+}
@@ -3,25 +3,43 @@
 //! 此模块提供统一的 HTTP 客户端实现，用于与各种 LLM API 服务通信。
 //!
 //! 核心组件：
-//! - `GenericHttpClient`: 统一的 HTTP 客户端，支持多种 API 格式（OpenAI、Qwen、QwenVision）
+//! - `GenericHttpClient`: 统一的 HTTP 客户端，支持多种 API 格式（OpenAI、Qwen、QwenVision、Gemini、Azure OpenAI）
+//! - `OpenAiAssistantClient`: OpenAI Assistants API 客户端，驱动平台上已创建的 Assistant
+//! - `LlmMiddleware`: 请求/响应中间件，`GenericHttpClient::with_middleware` 注册
 //! - `SseParser`: SSE (Server-Sent Events) 流式响应解析器
-//! - `configs`: 各种 LLM 提供商的端点配置
+//! - `body`: 限制大小的请求体序列化和逐块读取的响应体
+//! - `pool`: 进程级共享的 `reqwest::Client`，相同 `HttpPoolConfig` 的客户端复用连接
+//! - `configs`: 各种 LLM 提供商的端点配置，以及 Gemini / Azure OpenAI 的端点构建函数
 //!
 //! **设计原则**：
 //! - 所有参数从 `AgentConfig` 和 `driver.rs` 读取，不硬编码
 //! - 统一使用 `GenericHttpClient`，不再使用特定提供商的客户端
 //! - 支持流式响应和普通响应
 
+#[cfg(feature = "openai-client")]
+pub mod assistant;
+pub mod body;
 #[cfg(feature = "openai-client")]
 pub mod configs;
 #[cfg(feature = "openai-client")]
 pub mod generic;
 #[cfg(feature = "openai-client")]
+pub mod middleware;
+pub mod pool;
+#[cfg(feature = "openai-client")]
 pub mod stream;
 
+#[cfg(feature = "openai-client")]
+pub use assistant::{
+    AssistantRun, AssistantThreadClient, AssistantToolCall, OpenAiAssistantClient, ToolCallRequest,
+};
+pub use body::DEFAULT_MAX_RESPONSE_BYTES;
 #[cfg(feature = "openai-client")]
 pub use configs::*;
 #[cfg(feature = "openai-client")]
 pub use generic::GenericHttpClient;
 #[cfg(feature = "openai-client")]
+pub use middleware::{LlmHttpRequest, LlmHttpResponse, LlmMiddleware, MiddlewareStack};
+pub use pool::{default_http_client, shared_http_client, HttpPoolConfig};
+#[cfg(feature = "openai-client")]
 pub use stream::SseParser;
@@ -0,0 +1,186 @@
+//! 进程级共享的 HTTP 连接池
+//!
+//! 配置相同的 LLM 客户端共用一个 `reqwest::Client`，Agent 较多的流程不必为每个客户端重复建立连接；
+//! 远程插件下载也使用这里的客户端，沿用相同的代理与证书配置。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AgentFlowError, Result};
+
+/// HTTP 连接池配置，对应 Agent 配置中的 `metadata.http`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpPoolConfig {
+    /// 每个主机最多保持的空闲连接数
+    pub max_idle_per_host: usize,
+    /// 空闲连接的保留时间（秒）
+    pub idle_timeout_secs: u64,
+    /// 建立连接的超时（秒）
+    pub connect_timeout_secs: u64,
+    /// 单次请求的超时（秒）
+    pub timeout_secs: u64,
+    /// HTTP(S) 代理地址，如 `http://proxy.internal:3128`
+    pub proxy: Option<String>,
+    /// 不经过代理的主机，逗号分隔，如 `localhost,.internal`
+    pub no_proxy: Option<String>,
+    /// 额外信任的 CA 证书文件（PEM，可包含多个证书），用于企业内部的 TLS 代理
+    pub ca_cert: Option<String>,
+    /// 跳过 TLS 证书校验，仅用于测试环境
+    pub accept_invalid_certs: bool,
+}
+
+impl Default for HttpPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: 10,
+            idle_timeout_secs: 90,
+            connect_timeout_secs: 10,
+            timeout_secs: 300,
+            proxy: None,
+            no_proxy: None,
+            ca_cert: None,
+            accept_invalid_certs: false,
+        }
+    }
+}
+
+impl HttpPoolConfig {
+    pub fn with_max_idle_per_host(mut self, max_idle_per_host: usize) -> Self {
+        self.max_idle_per_host = max_idle_per_host;
+        self
+    }
+
+    pub fn with_timeout_secs(mut self, timeout_secs: u64) -> Self {
+        self.timeout_secs = timeout_secs;
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    pub fn with_ca_cert(mut self, path: impl Into<String>) -> Self {
+        self.ca_cert = Some(path.into());
+        self
+    }
+
+    pub fn with_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    fn build(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .pool_max_idle_per_host(self.max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(self.idle_timeout_secs))
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy).map_err(|e| {
+                AgentFlowError::Other(anyhow!("Invalid HTTP proxy '{}': {}", proxy, e))
+            })?;
+            let no_proxy = self
+                .no_proxy
+                .as_deref()
+                .and_then(reqwest::NoProxy::from_string);
+            builder = builder.proxy(proxy.no_proxy(no_proxy));
+        }
+        if let Some(path) = &self.ca_cert {
+            let pem = std::fs::read(path).map_err(|e| {
+                AgentFlowError::Other(anyhow!("Failed to read CA certificate '{}': {}", path, e))
+            })?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem)
+                .ok()
+                .filter(|certs| !certs.is_empty())
+                .ok_or_else(|| {
+                    AgentFlowError::Other(anyhow!("Invalid CA certificate '{}'", path))
+                })?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if self.accept_invalid_certs {
+            tracing::warn!("TLS certificate verification is disabled for LLM HTTP client");
+        }
+        builder
+            .build()
+            .map_err(|e| AgentFlowError::Other(anyhow!("Failed to build HTTP client: {}", e)))
+    }
+}
+
+static POOLS: OnceLock<Mutex<HashMap<HttpPoolConfig, reqwest::Client>>> = OnceLock::new();
+
+fn pools() -> &'static Mutex<HashMap<HttpPoolConfig, reqwest::Client>> {
+    POOLS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 按配置获取共享的客户端，首次使用时创建；克隆的客户端共用同一个连接池
+pub fn shared_http_client(config: &HttpPoolConfig) -> Result<reqwest::Client> {
+    let mut pools = pools().lock().expect("http pool registry poisoned");
+    if let Some(client) = pools.get(config) {
+        return Ok(client.clone());
+    }
+    let client = config.build()?;
+    pools.insert(config.clone(), client.clone());
+    Ok(client)
+}
+
+/// 默认配置的共享客户端，未指定连接池时 LLM 客户端都使用它
+pub fn default_http_client() -> reqwest::Client {
+    shared_http_client(&HttpPoolConfig::default())
+        .expect("Failed to build HTTP client with default config")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_config_from_metadata() {
+        let config: HttpPoolConfig = serde_json::from_value(serde_json::json!({
+            "max_idle_per_host": 32,
+            "proxy": "http://proxy.internal:3128"
+        }))
+        .unwrap();
+        assert_eq!(
+            config,
+            HttpPoolConfig::default()
+                .with_max_idle_per_host(32)
+                .with_proxy("http://proxy.internal:3128")
+        );
+
+        shared_http_client(&config).unwrap();
+        assert!(pools().lock().unwrap().contains_key(&config));
+    }
+
+    #[test]
+    fn test_invalid_proxy_is_rejected() {
+        let config = HttpPoolConfig::default().with_proxy("http://bad proxy");
+        let error = shared_http_client(&config).unwrap_err();
+        assert!(error.to_string().contains("Invalid HTTP proxy"));
+    }
+
+    #[test]
+    fn test_tls_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ca.pem");
+        std::fs::write(&path, "not a certificate").unwrap();
+        let config = HttpPoolConfig::default().with_ca_cert(path.to_string_lossy());
+        let error = shared_http_client(&config).unwrap_err();
+        assert!(error.to_string().contains("Invalid CA certificate"));
+
+        let missing = HttpPoolConfig::default()
+            .with_ca_cert(dir.path().join("missing.pem").to_string_lossy());
+        assert!(shared_http_client(&missing).is_err());
+
+        let insecure = HttpPoolConfig::default().with_accept_invalid_certs(true);
+        assert!(shared_http_client(&insecure).is_ok());
+    }
+}
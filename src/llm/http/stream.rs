@@ -7,38 +7,48 @@ use serde_json::Value;
 ///
 /// 用于解析流式响应中的 SSE 格式数据
 pub struct SseParser {
-    buffer: String,
+    buffer: Vec<u8>,
 }
 
 impl SseParser {
     pub fn new() -> Self {
-        Self {
-            buffer: String::new(),
-        }
+        Self { buffer: Vec::new() }
     }
 
     /// 解析数据块，返回流式 chunk 列表
     ///
     /// SSE 格式：
-    /// ```
+    /// ```text
     /// data: {"id":"...","choices":[{"delta":{"content":"Hello"}}]}
     ///
     /// data: {"id":"...","choices":[{"delta":{"content":" world"}}]}
     ///
     /// data: [DONE]
     /// ```
+    ///
+    /// DashScope 原生接口（`X-DashScope-SSE: enable`）的事件带 `id:`、`event:` 和 `:HTTP_STATUS` 行，
+    /// 只读取其中的 `data:` 行：
+    /// ```text
+    /// id:1
+    /// event:result
+    /// :HTTP_STATUS/200
+    /// data:{"output":{"text":"Hello","finish_reason":"null"}}
+    /// ```
     pub fn parse_chunk(&mut self, data: &[u8]) -> Result<Vec<LlmStreamChunk>> {
-        let text = String::from_utf8_lossy(data);
-        self.buffer.push_str(&text);
+        // 按字节缓存，事件边界之前的多字节字符不会被截断
+        self.buffer.extend_from_slice(data);
 
         let mut chunks = Vec::new();
         let mut processed = 0;
 
-        while let Some(end_pos) = self.buffer[processed..].find("\n\n") {
+        while let Some(end_pos) = self.buffer[processed..]
+            .windows(2)
+            .position(|window| window == b"\n\n")
+        {
             let event_end = processed + end_pos;
-            let event_text = &self.buffer[processed..event_end];
+            let event_text = String::from_utf8_lossy(&self.buffer[processed..event_end]);
 
-            if let Some(chunk) = self.parse_event(event_text)? {
+            if let Some(chunk) = self.parse_event(&event_text)? {
                 chunks.push(chunk);
             }
 
@@ -54,12 +64,17 @@ impl SseParser {
 
     /// 解析单个 SSE 事件
     fn parse_event(&self, event_text: &str) -> Result<Option<LlmStreamChunk>> {
-        let data = if event_text.starts_with("data: ") {
-            &event_text[6..]
-        } else if event_text.starts_with("data:") {
-            &event_text[5..].trim_start()
+        let data_lines: Vec<&str> = event_text
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(str::trim_start)
+            .collect();
+        let data = if !data_lines.is_empty() {
+            data_lines.join("\n")
+        } else if event_text.lines().all(is_field_line) {
+            return Ok(None);
         } else {
-            event_text.trim()
+            event_text.trim().to_string()
         };
 
         if data.trim() == "[DONE]" {
@@ -69,10 +84,19 @@ impl SseParser {
             }));
         }
 
-        let json: Value = serde_json::from_str(data).map_err(|e| {
+        let json: Value = serde_json::from_str(&data).map_err(|e| {
             AgentFlowError::Other(anyhow!("Failed to parse SSE JSON: {}: {}", e, data))
         })?;
 
+        // DashScope 的 `event:error` 事件：{"code": "...", "message": "..."}
+        if let (Some(code), None) = (json["code"].as_str(), json.get("output")) {
+            return Err(AgentFlowError::Other(anyhow!(
+                "SSE stream error {}: {}",
+                code,
+                json["message"].as_str().unwrap_or_default()
+            )));
+        }
+
         let content = self.extract_content_delta(&json)?;
 
         if content.is_empty() {
@@ -121,6 +145,15 @@ impl SseParser {
     }
 }
 
+/// `id:`、`event:`、`retry:` 字段行或 `:` 开头的注释行
+fn is_field_line(line: &str) -> bool {
+    line.is_empty()
+        || line.starts_with(':')
+        || ["id:", "event:", "retry:"]
+            .iter()
+            .any(|field| line.starts_with(field))
+}
+
 impl Default for SseParser {
     fn default() -> Self {
         Self::new()
@@ -152,6 +185,23 @@ mod tests {
         assert!(chunks[0].done);
     }
 
+    #[test]
+    fn test_parse_dashscope_events() {
+        let mut parser = SseParser::new();
+        let data = "id:1\nevent:result\n:HTTP_STATUS/200\ndata:{\"output\":{\"text\":\"你\"}}\n\nid:2\nevent:result\n:HTTP_STATUS/200\ndata:{\"output\":{\"text\":\"好\"}}\n\n";
+
+        // 事件和多字节字符都可能跨数据块
+        let (head, tail) = data.as_bytes().split_at(data.find('你').unwrap() + 1);
+        assert!(parser.parse_chunk(head).unwrap().is_empty());
+        let chunks = parser.parse_chunk(tail).unwrap();
+        let text: Vec<_> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(text, ["你", "好"]);
+
+        let error = b"id:1\nevent:error\n:HTTP_STATUS/401\ndata:{\"code\":\"InvalidApiKey\",\"message\":\"Invalid API-key provided.\"}\n\n";
+        let error = parser.parse_chunk(error).unwrap_err();
+        assert!(error.to_string().contains("InvalidApiKey"));
+    }
+
     #[test]
     fn test_parse_qwen_format() {
         let mut parser = SseParser::new();
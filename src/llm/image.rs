@@ -0,0 +1,505 @@
+//! 图片生成客户端 - 通义万相异步任务接口（提交任务后轮询结果）
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::EnvConfig;
+use crate::error::{AgentFlowError, Result};
+use crate::media::poll::{PolledTask, TaskProgress};
+
+/// 默认提交任务的接口
+pub const DEFAULT_IMAGE_ENDPOINT: &str =
+    "https://dashscope.aliyuncs.com/api/v1/services/aigc/text2image/image-synthesis";
+/// 默认查询任务的接口（`{task_endpoint}/{task_id}`）
+pub const DEFAULT_TASK_ENDPOINT: &str = "https://dashscope.aliyuncs.com/api/v1/tasks";
+
+/// 图片生成请求
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImageGenRequest {
+    pub prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub negative_prompt: Option<String>,
+    /// 分辨率，如 `1024*1024`
+    #[serde(default = "ImageGenRequest::default_size")]
+    pub size: String,
+    /// 风格，如 `<auto>`、`<watercolor>`
+    #[serde(default = "ImageGenRequest::default_style")]
+    pub style: String,
+    /// 生成张数
+    #[serde(default = "ImageGenRequest::default_count")]
+    pub n: u32,
+}
+
+impl ImageGenRequest {
+    fn default_size() -> String {
+        "1024*1024".to_string()
+    }
+
+    fn default_style() -> String {
+        "<auto>".to_string()
+    }
+
+    fn default_count() -> u32 {
+        1
+    }
+
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            negative_prompt: None,
+            size: Self::default_size(),
+            style: Self::default_style(),
+            n: Self::default_count(),
+        }
+    }
+
+    pub fn with_negative_prompt(mut self, negative_prompt: impl Into<String>) -> Self {
+        self.negative_prompt = Some(negative_prompt.into());
+        self
+    }
+
+    pub fn with_size(mut self, size: impl Into<String>) -> Self {
+        self.size = size.into();
+        self
+    }
+
+    pub fn with_style(mut self, style: impl Into<String>) -> Self {
+        self.style = style.into();
+        self
+    }
+
+    pub fn with_count(mut self, n: u32) -> Self {
+        self.n = n.max(1);
+        self
+    }
+
+    fn body(&self, model: &str) -> Value {
+        let mut input = json!({ "prompt": self.prompt });
+        if let Some(negative_prompt) = &self.negative_prompt {
+            input["negative_prompt"] = json!(negative_prompt);
+        }
+        json!({
+            "model": model,
+            "input": input,
+            "parameters": {
+                "style": self.style,
+                "size": self.size,
+                "n": self.n,
+            }
+        })
+    }
+}
+
+/// 任务状态快照
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageTask {
+    pub task_id: String,
+    /// DashScope 任务状态：`PENDING` / `RUNNING` / `SUCCEEDED` / `FAILED` 等
+    pub status: String,
+    pub urls: Vec<String>,
+    /// 失败原因
+    pub message: Option<String>,
+}
+
+impl ImageTask {
+    /// 解析提交或查询任务的响应
+    pub fn parse(body: &Value) -> Option<Self> {
+        let output = body.get("output")?;
+        Some(Self {
+            task_id: output["task_id"].as_str()?.to_string(),
+            status: output["task_status"]
+                .as_str()
+                .unwrap_or("UNKNOWN")
+                .to_string(),
+            urls: output["results"]
+                .as_array()
+                .map(|results| {
+                    results
+                        .iter()
+                        .filter_map(|result| result["url"].as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            message: output["message"]
+                .as_str()
+                .or_else(|| body["message"].as_str())
+                .map(str::to_string),
+        })
+    }
+
+    pub fn is_succeeded(&self) -> bool {
+        self.status == "SUCCEEDED"
+    }
+
+    pub fn is_failed(&self) -> bool {
+        matches!(self.status.as_str(), "FAILED" | "CANCELED" | "UNKNOWN")
+    }
+}
+
+impl PolledTask for ImageTask {
+    fn task_id(&self) -> &str {
+        &self.task_id
+    }
+
+    fn status(&self) -> &str {
+        &self.status
+    }
+
+    fn outcome(&self) -> Option<Result<()>> {
+        if self.is_succeeded() {
+            return Some(if self.urls.is_empty() {
+                Err(AgentFlowError::Other(anyhow!(
+                    "Image generation task {} succeeded without results",
+                    self.task_id
+                )))
+            } else {
+                Ok(())
+            });
+        }
+        self.is_failed().then(|| {
+            Err(AgentFlowError::Other(anyhow!(
+                "Image generation task {} {}: {}",
+                self.task_id,
+                self.status.to_lowercase(),
+                self.message.as_deref().unwrap_or("no details")
+            )))
+        })
+    }
+}
+
+/// 轮询进度
+pub type ImageGenProgress = TaskProgress;
+
+/// 生成结果
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ImageGenResult {
+    pub task_id: String,
+    pub urls: Vec<String>,
+    /// 查询次数
+    pub attempts: u32,
+}
+
+/// 图片生成配置（工具与 `image_gen` 节点共用）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageGenConfig {
+    /// 模型，如 `wan2.5-t2i-preview`
+    pub model: String,
+    /// API Key，支持 `${VAR_NAME}`；为空时读取 `DASHSCOPE_API_KEY`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_endpoint: Option<String>,
+    #[serde(default = "ImageGenConfig::default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    #[serde(default = "ImageGenConfig::default_max_wait_secs")]
+    pub max_wait_secs: u64,
+}
+
+impl ImageGenConfig {
+    fn default_poll_interval_ms() -> u64 {
+        2_000
+    }
+
+    fn default_max_wait_secs() -> u64 {
+        120
+    }
+
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            api_key: None,
+            endpoint: None,
+            task_endpoint: None,
+            poll_interval_ms: Self::default_poll_interval_ms(),
+            max_wait_secs: Self::default_max_wait_secs(),
+        }
+    }
+
+    fn resolve_api_key(&self) -> Result<String> {
+        match &self.api_key {
+            Some(value) if value.starts_with("${") && value.ends_with('}') => {
+                EnvConfig::get_env(&value[2..value.len() - 1])
+            }
+            Some(value) => Ok(value.clone()),
+            None => EnvConfig::get_env("DASHSCOPE_API_KEY"),
+        }
+    }
+
+    pub fn build_client(&self) -> Result<ImageGenClient> {
+        let mut client = ImageGenClient::new(self.resolve_api_key()?, self.model.clone())
+            .with_poll_interval(Duration::from_millis(self.poll_interval_ms))
+            .with_max_wait(Duration::from_secs(self.max_wait_secs));
+        if let Some(endpoint) = &self.endpoint {
+            client = client.with_endpoint(endpoint.clone());
+        }
+        if let Some(task_endpoint) = &self.task_endpoint {
+            client = client.with_task_endpoint(task_endpoint.clone());
+        }
+        Ok(client)
+    }
+}
+
+/// 通义万相图片生成客户端
+///
+/// 以异步任务方式提交，随后每隔 `poll_interval` 查询一次，超过 `max_wait` 仍未完成则返回超时错误。
+#[derive(Clone)]
+pub struct ImageGenClient {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    endpoint: String,
+    task_endpoint: String,
+    poll_interval: Duration,
+    max_wait: Duration,
+}
+
+impl ImageGenClient {
+    pub fn new(api_key: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key: api_key.into(),
+            model: model.into(),
+            endpoint: DEFAULT_IMAGE_ENDPOINT.to_string(),
+            task_endpoint: DEFAULT_TASK_ENDPOINT.to_string(),
+            poll_interval: Duration::from_millis(ImageGenConfig::default_poll_interval_ms()),
+            max_wait: Duration::from_secs(ImageGenConfig::default_max_wait_secs()),
+        }
+    }
+
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    pub fn with_task_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.task_endpoint = endpoint.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// 提交生成任务
+    pub async fn submit(&self, request: &ImageGenRequest) -> Result<ImageTask> {
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .header("X-DashScope-Async", "enable")
+            .json(&request.body(&self.model))
+            .send()
+            .await
+            .map_err(|e| {
+                AgentFlowError::Other(anyhow!("Image generation request failed: {}", e))
+            })?;
+        Self::read_task(response).await
+    }
+
+    /// 查询任务状态
+    pub async fn task(&self, task_id: &str) -> Result<ImageTask> {
+        let response = self
+            .client
+            .get(format!("{}/{}", self.task_endpoint, task_id))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Image task query failed: {}", e)))?;
+        Self::read_task(response).await
+    }
+
+    async fn read_task(response: reqwest::Response) -> Result<ImageTask> {
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Image generation API returned {}: {}",
+                status,
+                body
+            )));
+        }
+        ImageTask::parse(&body).ok_or_else(|| {
+            AgentFlowError::Other(anyhow!("Invalid image generation response: {}", body))
+        })
+    }
+
+    pub async fn generate(&self, request: &ImageGenRequest) -> Result<ImageGenResult> {
+        self.generate_with_progress(request, |_| {}).await
+    }
+
+    /// 提交并等待任务完成，每次查询后回调 `on_progress`
+    pub async fn generate_with_progress(
+        &self,
+        request: &ImageGenRequest,
+        on_progress: impl Fn(&ImageGenProgress),
+    ) -> Result<ImageGenResult> {
+        let submitted = self.submit(request).await?;
+        poll_task(
+            submitted,
+            |task_id| async move { self.task(&task_id).await },
+            self.poll_interval,
+            self.max_wait,
+            on_progress,
+        )
+        .await
+    }
+}
+
+/// 轮询直到任务成功、失败或超时；查询失败视为暂时错误，继续轮询
+pub(crate) async fn poll_task<F, Fut>(
+    submitted: ImageTask,
+    fetch: F,
+    interval: Duration,
+    max_wait: Duration,
+    on_progress: impl Fn(&ImageGenProgress),
+) -> Result<ImageGenResult>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<ImageTask>>,
+{
+    let (task, attempts) =
+        crate::media::poll::poll_task(submitted, fetch, interval, max_wait, on_progress).await?;
+    Ok(ImageGenResult {
+        task_id: task.task_id,
+        urls: task.urls,
+        attempts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn task(status: &str, urls: &[&str]) -> ImageTask {
+        ImageTask {
+            task_id: "t1".to_string(),
+            status: status.to_string(),
+            urls: urls.iter().map(|u| u.to_string()).collect(),
+            message: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_reports_progress_until_success() {
+        let responses = Mutex::new(vec![
+            Ok(task("SUCCEEDED", &["https://img/1.png"])),
+            Err(AgentFlowError::Other(anyhow!("network"))),
+            Ok(task("RUNNING", &[])),
+        ]);
+        let progress = Mutex::new(Vec::new());
+        let result = poll_task(
+            task("PENDING", &[]),
+            |_| {
+                let next = responses.lock().unwrap().pop().unwrap();
+                async move { next }
+            },
+            Duration::from_secs(1),
+            Duration::from_secs(60),
+            |p| progress.lock().unwrap().push((p.status.clone(), p.attempt)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.urls, vec!["https://img/1.png"]);
+        assert_eq!(result.attempts, 3);
+        assert_eq!(
+            progress.into_inner().unwrap(),
+            vec![
+                ("PENDING".to_string(), 0),
+                ("RUNNING".to_string(), 1),
+                // 查询失败时沿用上一次的状态
+                ("RUNNING".to_string(), 2),
+                ("SUCCEEDED".to_string(), 3),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_times_out_and_reports_failure() {
+        let err = poll_task(
+            task("RUNNING", &[]),
+            |_| async { Ok(task("RUNNING", &[])) },
+            Duration::from_secs(2),
+            Duration::from_secs(5),
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("did not finish within 5s"));
+
+        let mut failed = task("FAILED", &[]);
+        failed.message = Some("content moderation".to_string());
+        let err = poll_task(
+            failed,
+            |_| async { unreachable!() },
+            Duration::from_secs(1),
+            Duration::from_secs(5),
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("content moderation"));
+    }
+
+    #[test]
+    fn test_image_gen_graph_node() {
+        use crate::flow::config::GraphFlow;
+        use crate::flow::FlowNodeKind;
+
+        let graph: GraphFlow = serde_json::from_value(json!({
+            "name": "poster",
+            "start": "draw",
+            "nodes": [{
+                "kind": "image_gen",
+                "name": "draw",
+                "model": "wan2.5-t2i-preview",
+                "max_wait_secs": 30,
+                "prompt": "poster: {{input}}",
+                "size": "720*1280"
+            }]
+        }))
+        .unwrap();
+        let flow = crate::flow::loader::build_flow_from_graph(&graph);
+        let FlowNodeKind::ImageGen(image) = &flow.node("draw").unwrap().kind else {
+            panic!("expected image_gen node");
+        };
+        assert_eq!(image.config.max_wait_secs, 30);
+        assert_eq!(image.config.poll_interval_ms, 2_000);
+        assert_eq!(image.request.size, "720*1280");
+        assert_eq!(image.prompt.as_deref(), Some("poster: {{input}}"));
+    }
+
+    #[test]
+    fn test_parse_task_and_request_body() {
+        let body = json!({ "output": {
+            "task_id": "abc",
+            "task_status": "SUCCEEDED",
+            "results": [{ "url": "https://img/a.png" }]
+        } });
+        let task = ImageTask::parse(&body).unwrap();
+        assert!(task.is_succeeded());
+        assert_eq!(task.urls, vec!["https://img/a.png"]);
+
+        let request = ImageGenRequest::new("a cat")
+            .with_negative_prompt("blurry")
+            .with_size("720*1280");
+        let body = request.body("wan2.5-t2i-preview");
+        assert_eq!(body["input"]["negative_prompt"], "blurry");
+        assert_eq!(body["parameters"]["size"], "720*1280");
+        assert_eq!(body["parameters"]["style"], "<auto>");
+    }
+}
@@ -0,0 +1,239 @@
+//! LLM 调用日志：在 `agentflow::llm` target 下以 debug 级别记录完整的请求 / 响应体（格式化 JSON），
+//! 输出前脱敏密钥字段并折叠 base64 数据
+
+use std::time::Duration;
+
+use serde_json::Value;
+
+/// LLM 调用日志的 tracing target，例如 `RUST_LOG=agentflow::llm=debug`
+pub const LLM_LOG_TARGET: &str = "agentflow::llm";
+
+/// 默认按密钥处理的字段名、请求头和查询参数（忽略大小写）
+const SECRET_FIELDS: &[&str] = &[
+    "api_key",
+    "api-key",
+    "apikey",
+    "x-api-key",
+    "authorization",
+    "proxy-authorization",
+    "key",
+    "token",
+    "access_token",
+    "secret",
+    "password",
+];
+
+const REDACTED: &str = "[REDACTED]";
+
+/// LLM 请求 / 响应日志
+///
+/// 默认脱敏密钥（上面的字段名、请求头和 URL 查询参数）并把长度不小于 `base64_min_len`
+/// 的 base64 字符串（含 `data:` URI）替换为长度说明；未开启 debug 级别时不做任何处理。
+#[derive(Clone, Debug)]
+pub struct LlmCallLogger {
+    redact_secrets: bool,
+    redact_base64: bool,
+    base64_min_len: usize,
+    secret_fields: Vec<String>,
+}
+
+impl Default for LlmCallLogger {
+    fn default() -> Self {
+        Self {
+            redact_secrets: true,
+            redact_base64: true,
+            base64_min_len: 256,
+            secret_fields: SECRET_FIELDS.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+}
+
+impl LlmCallLogger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_redact_secrets(mut self, redact: bool) -> Self {
+        self.redact_secrets = redact;
+        self
+    }
+
+    pub fn with_redact_base64(mut self, redact: bool) -> Self {
+        self.redact_base64 = redact;
+        self
+    }
+
+    /// 折叠 base64 字符串的最小长度（默认 256）
+    pub fn with_base64_min_len(mut self, len: usize) -> Self {
+        self.base64_min_len = len;
+        self
+    }
+
+    /// 追加按密钥处理的字段名
+    pub fn with_secret_field(mut self, name: impl Into<String>) -> Self {
+        self.secret_fields.push(name.into());
+        self
+    }
+
+    pub fn enabled(&self) -> bool {
+        tracing::enabled!(target: LLM_LOG_TARGET, tracing::Level::DEBUG)
+    }
+
+    pub fn log_request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(String, String)],
+        body: Option<&Value>,
+    ) {
+        if !self.enabled() {
+            return;
+        }
+        let headers: Vec<(&str, &str)> = headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.is_secret(name) {
+                    REDACTED
+                } else {
+                    value.as_str()
+                };
+                (name.as_str(), value)
+            })
+            .collect();
+        let body = body
+            .map(|body| pretty(&self.redact(body)))
+            .unwrap_or_default();
+        tracing::debug!(
+            target: LLM_LOG_TARGET,
+            method,
+            url = %self.redact_url(url),
+            headers = ?headers,
+            "LLM request\n{}",
+            body
+        );
+    }
+
+    /// 记录响应；JSON 响应体脱敏后格式化，其他内容原样输出
+    pub fn log_response(&self, url: &str, status: u16, body: &str, elapsed: Duration) {
+        if !self.enabled() {
+            return;
+        }
+        let body = match serde_json::from_str::<Value>(body) {
+            Ok(value) => pretty(&self.redact(&value)),
+            Err(_) => body.to_string(),
+        };
+        tracing::debug!(
+            target: LLM_LOG_TARGET,
+            url = %self.redact_url(url),
+            status,
+            elapsed_ms = elapsed.as_millis() as u64,
+            "LLM response\n{}",
+            body
+        );
+    }
+
+    /// 返回脱敏后的 JSON
+    pub fn redact(&self, value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| {
+                        let value = if self.is_secret(key) && !value.is_null() {
+                            Value::String(REDACTED.to_string())
+                        } else {
+                            self.redact(value)
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|item| self.redact(item)).collect())
+            }
+            Value::String(text) => Value::String(self.collapse_base64(text)),
+            other => other.clone(),
+        }
+    }
+
+    /// 脱敏 URL 中的密钥查询参数
+    pub fn redact_url(&self, url: &str) -> String {
+        let Some((base, query)) = url.split_once('?') else {
+            return url.to_string();
+        };
+        let query: Vec<String> = query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if self.is_secret(name) => format!("{}={}", name, REDACTED),
+                _ => pair.to_string(),
+            })
+            .collect();
+        format!("{}?{}", base, query.join("&"))
+    }
+
+    fn is_secret(&self, name: &str) -> bool {
+        self.redact_secrets
+            && self
+                .secret_fields
+                .iter()
+                .any(|field| field.eq_ignore_ascii_case(name))
+    }
+
+    fn collapse_base64(&self, text: &str) -> String {
+        if !self.redact_base64 || text.len() < self.base64_min_len {
+            return text.to_string();
+        }
+        if let Some((prefix, data)) = text.split_once(";base64,") {
+            if prefix.starts_with("data:") {
+                return format!("{};base64,<{} base64 chars>", prefix, data.len());
+            }
+        }
+        let is_base64 = text
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'=' | b'-' | b'_'));
+        if is_base64 {
+            format!("<{} base64 chars>", text.len())
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redacts_secrets_and_base64() {
+        let logger = LlmCallLogger::new().with_base64_min_len(16);
+        let image = format!("data:image/png;base64,{}", "iVBORw0KGgo".repeat(4));
+        let body = json!({
+            "model": "gpt-4o",
+            "api_key": "sk-live",
+            "messages": [{ "role": "user", "content": [{ "image_url": { "url": image } }] }],
+            "audio": "QUJDREVGR0hJSktMTU5PUA==",
+            "prompt": "describe this picture in a few words",
+        });
+        let redacted = logger.redact(&body);
+        assert_eq!(redacted["api_key"], REDACTED);
+        assert_eq!(
+            redacted["messages"][0]["content"][0]["image_url"]["url"],
+            "data:image/png;base64,<44 base64 chars>"
+        );
+        assert_eq!(redacted["audio"], "<24 base64 chars>");
+        assert_eq!(redacted["prompt"], body["prompt"]);
+        assert_eq!(
+            logger.redact_url("https://example.com/v1/models?alt=sse&key=abc"),
+            "https://example.com/v1/models?alt=sse&key=[REDACTED]"
+        );
+
+        let raw = LlmCallLogger::new()
+            .with_redact_secrets(false)
+            .with_redact_base64(false);
+        assert_eq!(raw.redact(&body), body);
+    }
+}
@@ -0,0 +1,268 @@
+//! 测试用 LLM 客户端：按提示词匹配返回预设回复，并记录调用供断言（`test-utils` feature）
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use regex::Regex;
+
+use super::client::{DynLlmClient, LlmClient};
+use super::types::{LlmRequest, LlmResponse};
+use crate::error::{AgentFlowError, Result};
+
+enum Matcher {
+    Any,
+    Contains(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn matches(&self, prompt: &str) -> bool {
+        match self {
+            Matcher::Any => true,
+            Matcher::Contains(needle) => prompt.contains(needle.as_str()),
+            Matcher::Regex(regex) => regex.is_match(prompt),
+        }
+    }
+}
+
+struct MockRule {
+    matcher: Matcher,
+    replies: Vec<String>,
+    hits: usize,
+}
+
+#[derive(Default)]
+struct MockState {
+    rules: Vec<MockRule>,
+    fallback: Option<String>,
+    calls: Vec<LlmRequest>,
+}
+
+/// 脚本化的 LLM 客户端
+///
+/// 规则按注册顺序匹配提示词（系统提示与用户输入以空行连接），第一个匹配的规则依次返回
+/// 它的回复，回复用完后重复最后一条；没有规则匹配时返回 `fallback`，未设置则报错。
+/// 克隆共享规则和调用记录。
+///
+/// ```ignore
+/// let llm = MockLlmClient::new()
+///     .on_contains("weather", "Sunny")
+///     .on_regex(r"order #\d+", "Shipped")
+///     .then("Delivered");
+/// // ... 运行流程 ...
+/// llm.assert_call_count(2);
+/// llm.assert_called_with("order #42");
+/// ```
+#[derive(Clone, Default)]
+pub struct MockLlmClient {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockLlmClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 任意提示词都回复 `reply`，配合 `then` 按调用顺序返回一组回复
+    pub fn on_any(self, reply: impl Into<String>) -> Self {
+        self.rule(Matcher::Any, reply.into())
+    }
+
+    /// 提示词包含 `needle` 时回复 `reply`
+    pub fn on_contains(self, needle: impl Into<String>, reply: impl Into<String>) -> Self {
+        self.rule(Matcher::Contains(needle.into()), reply.into())
+    }
+
+    /// 提示词匹配正则 `pattern` 时回复 `reply`；正则无效时 panic
+    pub fn on_regex(self, pattern: &str, reply: impl Into<String>) -> Self {
+        let regex = Regex::new(pattern)
+            .unwrap_or_else(|e| panic!("invalid mock regex `{}`: {}", pattern, e));
+        self.rule(Matcher::Regex(regex), reply.into())
+    }
+
+    /// 给最近注册的规则追加一条回复，用于多轮对话
+    pub fn then(self, reply: impl Into<String>) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .rules
+            .last_mut()
+            .expect("MockLlmClient::then requires a preceding rule")
+            .replies
+            .push(reply.into());
+        self
+    }
+
+    /// 没有规则匹配时的回复
+    pub fn fallback(self, reply: impl Into<String>) -> Self {
+        self.state.lock().unwrap().fallback = Some(reply.into());
+        self
+    }
+
+    fn rule(self, matcher: Matcher, reply: String) -> Self {
+        self.state.lock().unwrap().rules.push(MockRule {
+            matcher,
+            replies: vec![reply],
+            hits: 0,
+        });
+        self
+    }
+
+    /// 按顺序记录的请求
+    pub fn calls(&self) -> Vec<LlmRequest> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    pub fn call_count(&self) -> usize {
+        self.state.lock().unwrap().calls.len()
+    }
+
+    #[track_caller]
+    pub fn assert_call_count(&self, expected: usize) {
+        let count = self.call_count();
+        assert_eq!(
+            count, expected,
+            "expected {} LLM calls, got {}",
+            expected, count
+        );
+    }
+
+    /// 断言至少有一次调用的提示词包含 `needle`
+    #[track_caller]
+    pub fn assert_called_with(&self, needle: &str) {
+        let calls = self.calls();
+        assert!(
+            calls.iter().any(|request| prompt(request).contains(needle)),
+            "no LLM call contained `{}`; prompts: {:#?}",
+            needle,
+            calls.iter().map(prompt).collect::<Vec<_>>()
+        );
+    }
+
+    /// 断言每条规则都至少命中一次
+    #[track_caller]
+    pub fn assert_all_rules_used(&self) {
+        let state = self.state.lock().unwrap();
+        let unused: Vec<usize> = state
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.hits == 0)
+            .map(|(index, _)| index)
+            .collect();
+        assert!(unused.is_empty(), "mock rules never matched: {:?}", unused);
+    }
+}
+
+fn prompt(request: &LlmRequest) -> String {
+    match &request.system {
+        Some(system) => format!("{}\n\n{}", system, request.user),
+        None => request.user.clone(),
+    }
+}
+
+#[async_trait]
+impl LlmClient for MockLlmClient {
+    async fn complete(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let text = prompt(&request);
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(request);
+        let reply = match state
+            .rules
+            .iter_mut()
+            .find(|rule| rule.matcher.matches(&text))
+        {
+            Some(rule) => {
+                let index = rule.hits.min(rule.replies.len() - 1);
+                rule.hits += 1;
+                rule.replies[index].clone()
+            }
+            None => state.fallback.clone().ok_or_else(|| {
+                AgentFlowError::Other(anyhow!("no mock LLM response for prompt: {}", text))
+            })?,
+        };
+        Ok(LlmResponse {
+            content: reply,
+            metadata: None,
+        })
+    }
+
+    fn clone_dyn(&self) -> DynLlmClient {
+        Arc::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(system: Option<&str>, user: &str) -> LlmRequest {
+        LlmRequest {
+            system: system.map(str::to_string),
+            user: user.into(),
+            temperature: 0.0,
+            metadata: None,
+            content: Vec::new(),
+            params: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scripted_replies_and_assertions() {
+        let llm = MockLlmClient::new()
+            .on_regex(r"order #\d+", "Shipped")
+            .then("Delivered")
+            .on_contains("support agent", "How can I help?");
+        let client = llm.clone_dyn();
+
+        let reply = |system, user| {
+            let client = client.clone();
+            async move { client.complete(request(system, user)).await }
+        };
+        assert_eq!(
+            reply(None, "where is order #42").await.unwrap().content,
+            "Shipped"
+        );
+        assert_eq!(
+            reply(None, "and order #42?").await.unwrap().content,
+            "Delivered"
+        );
+        assert_eq!(
+            reply(None, "order #7 again").await.unwrap().content,
+            "Delivered"
+        );
+        assert_eq!(
+            reply(Some("You are a support agent"), "hi")
+                .await
+                .unwrap()
+                .content,
+            "How can I help?"
+        );
+        let err = reply(None, "unrelated").await.err().unwrap();
+        assert!(err.to_string().contains("no mock LLM response"));
+
+        llm.assert_call_count(5);
+        llm.assert_called_with("order #7");
+        llm.assert_all_rules_used();
+        assert_eq!(
+            llm.calls()[3].system.as_deref(),
+            Some("You are a support agent")
+        );
+
+        let llm = llm.fallback("I don't know");
+        assert_eq!(
+            llm.complete(request(None, "unrelated"))
+                .await
+                .unwrap()
+                .content,
+            "I don't know"
+        );
+
+        let llm = MockLlmClient::new().on_any("first").then("second");
+        for expected in ["first", "second", "second"] {
+            let reply = llm.complete(request(None, "anything")).await.unwrap();
+            assert_eq!(reply.content, expected);
+        }
+    }
+}
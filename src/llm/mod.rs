@@ -1,18 +1,44 @@
+pub mod audio;
+#[cfg(feature = "bedrock")]
+pub mod bedrock;
 pub mod client;
 #[cfg(feature = "openai-client")]
 pub mod config;
 pub mod echo;
-#[cfg(feature = "openai-client")]
+pub mod embedding;
+pub mod error;
+#[cfg(all(feature = "openai-client", feature = "unstable"))]
 pub mod extended;
-#[cfg(feature = "openai-client")]
+#[cfg(all(feature = "openai-client", not(feature = "unstable")))]
+pub(crate) mod extended;
 pub mod http;
+pub mod image;
+pub mod logging;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod mock;
+pub mod recording;
+pub mod refusal;
 pub mod types;
 
+pub use audio::{SpeechAudio, SpeechRequest, TranscriptionRequest};
+#[cfg(feature = "bedrock")]
+pub use bedrock::{BedrockClient, BedrockModelFamily};
 pub use client::{DynLlmClient, LlmClient};
 pub use echo::LocalEchoClient;
+pub use error::LlmHttpError;
+pub use image::{
+    ImageGenClient, ImageGenConfig, ImageGenProgress, ImageGenRequest, ImageGenResult, ImageTask,
+};
+pub use logging::{LlmCallLogger, LLM_LOG_TARGET};
+#[cfg(any(test, feature = "test-utils"))]
+pub use mock::MockLlmClient;
+pub use recording::{FixtureMode, LlmFixtures, RecordingLlmClient};
+pub use refusal::{detect_refusal, LlmRefusal, RefusalKind};
 #[cfg(feature = "openai-client")]
 pub use types::ApiFormat;
-pub use types::{LlmMessage, LlmRequest, LlmResponse, LlmStreamChunk};
+pub use types::{
+    ContentPart, LlmMessage, LlmParams, LlmRequest, LlmResponse, LlmStreamChunk, MediaSource,
+};
 
 #[cfg(feature = "openai-client")]
 pub use config::ApiEndpointConfig;
@@ -0,0 +1,196 @@
+//! LLM 调用录制与回放：录制模式把请求/响应写入目录，回放模式按请求哈希读取，
+//! 使依赖真实模型的集成测试可以在没有 API Key 的 CI 中确定性运行
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use super::client::{DynLlmClient, LlmClient};
+use super::types::{LlmRequest, LlmResponse};
+use crate::error::{AgentFlowError, Result};
+
+/// 录制目录的环境变量，设置后 `LlmClientFactory` 创建的客户端按模式录制或回放
+pub const FIXTURES_DIR_ENV: &str = "AGENTFLOW_LLM_FIXTURES";
+/// 录制模式的环境变量：`record` / `replay`（默认）
+pub const FIXTURE_MODE_ENV: &str = "AGENTFLOW_LLM_FIXTURE_MODE";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// 调用真实客户端并写入录制文件
+    Record,
+    /// 只读取录制文件，不发起网络请求
+    Replay,
+}
+
+/// 录制目录和模式
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LlmFixtures {
+    pub dir: PathBuf,
+    pub mode: FixtureMode,
+}
+
+impl LlmFixtures {
+    /// 从 `AGENTFLOW_LLM_FIXTURES` / `AGENTFLOW_LLM_FIXTURE_MODE` 读取，未设置目录时返回 `None`
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(dir) = std::env::var_os(FIXTURES_DIR_ENV).filter(|dir| !dir.is_empty()) else {
+            return Ok(None);
+        };
+        let mode = match std::env::var(FIXTURE_MODE_ENV).ok().as_deref() {
+            None | Some("") | Some("replay") => FixtureMode::Replay,
+            Some("record") => FixtureMode::Record,
+            Some(other) => {
+                return Err(AgentFlowError::Other(anyhow!(
+                    "invalid {} `{}`, expected `record` or `replay`",
+                    FIXTURE_MODE_ENV,
+                    other
+                )))
+            }
+        };
+        Ok(Some(Self {
+            dir: dir.into(),
+            mode,
+        }))
+    }
+}
+
+/// 录制文件内容
+#[derive(Serialize, Deserialize)]
+struct Fixture {
+    request: LlmRequest,
+    response: LlmResponse,
+}
+
+/// 录制 / 回放客户端
+///
+/// 录制文件为 `{dir}/{request_key}.json`；请求哈希覆盖系统提示、用户输入、温度、
+/// 多模态内容和采样参数，不包含 `metadata`。
+#[derive(Clone)]
+pub struct RecordingLlmClient {
+    dir: PathBuf,
+    inner: Option<DynLlmClient>,
+}
+
+impl RecordingLlmClient {
+    pub fn record(inner: DynLlmClient, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            inner: Some(inner),
+        }
+    }
+
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            inner: None,
+        }
+    }
+
+    pub fn mode(&self) -> FixtureMode {
+        match self.inner {
+            Some(_) => FixtureMode::Record,
+            None => FixtureMode::Replay,
+        }
+    }
+
+    pub fn fixture_path(&self, request: &LlmRequest) -> PathBuf {
+        self.dir.join(format!("{}.json", request_key(request)))
+    }
+}
+
+/// 请求哈希（SHA-256 前 16 字节的十六进制）
+pub fn request_key(request: &LlmRequest) -> String {
+    let canonical = json!({
+        "system": request.system,
+        "user": request.user,
+        "temperature": request.temperature,
+        "content": request.content,
+        "params": request.params,
+    });
+    Sha256::digest(canonical.to_string().as_bytes())[..16]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn fixture_error(path: &Path, e: impl std::fmt::Display) -> AgentFlowError {
+    AgentFlowError::Other(anyhow!("LLM fixture `{}`: {}", path.display(), e))
+}
+
+#[async_trait]
+impl LlmClient for RecordingLlmClient {
+    async fn complete(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let path = self.fixture_path(&request);
+        let Some(inner) = &self.inner else {
+            let content = std::fs::read_to_string(&path).map_err(|e| {
+                fixture_error(
+                    &path,
+                    format!("{} (record it with {}=record)", e, FIXTURE_MODE_ENV),
+                )
+            })?;
+            let fixture: Fixture =
+                serde_json::from_str(&content).map_err(|e| fixture_error(&path, e))?;
+            return Ok(fixture.response);
+        };
+
+        let response = inner.complete(request.clone()).await?;
+        let fixture = Fixture {
+            request,
+            response: response.clone(),
+        };
+        let content =
+            serde_json::to_string_pretty(&fixture).map_err(|e| fixture_error(&path, e))?;
+        std::fs::create_dir_all(&self.dir).map_err(|e| fixture_error(&path, e))?;
+        std::fs::write(&path, content).map_err(|e| fixture_error(&path, e))?;
+        Ok(response)
+    }
+
+    fn clone_dyn(&self) -> DynLlmClient {
+        Arc::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::LocalEchoClient;
+
+    fn request(user: &str) -> LlmRequest {
+        LlmRequest {
+            system: Some("be brief".into()),
+            user: user.into(),
+            temperature: 0.0,
+            metadata: None,
+            content: Vec::new(),
+            params: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = RecordingLlmClient::record(Arc::new(LocalEchoClient), dir.path());
+        assert_eq!(recorder.mode(), FixtureMode::Record);
+        let recorded = recorder.complete(request("hi")).await.unwrap();
+        assert!(recorder.fixture_path(&request("hi")).exists());
+
+        let replay = RecordingLlmClient::replay(dir.path());
+        let mut traced = request("hi");
+        traced.metadata = Some(json!({ "run_id": "r-1" }));
+        assert_eq!(
+            replay.complete(traced).await.unwrap().content,
+            recorded.content
+        );
+
+        let err = replay.complete(request("bye")).await.err().unwrap();
+        assert!(err.to_string().contains(FIXTURE_MODE_ENV));
+
+        let mut tuned = request("hi");
+        tuned.params.seed = Some(7);
+        assert_ne!(request_key(&tuned), request_key(&request("hi")));
+    }
+}
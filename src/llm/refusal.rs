@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// 拒答类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefusalKind {
+    /// 提供商内容过滤（输入或输出被拦截）
+    ContentFilter,
+    /// 模型主动拒答
+    Refusal,
+}
+
+/// LLM 拒答信息
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LlmRefusal {
+    pub kind: RefusalKind,
+    /// 提供商返回的原因（finish_reason / 错误码 / 拒答文本）
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+impl fmt::Display for LlmRefusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            RefusalKind::ContentFilter => "content filter",
+            RefusalKind::Refusal => "refusal",
+        };
+        match &self.code {
+            Some(code) => write!(f, "{} ({}): {}", kind, code, self.reason),
+            None => write!(f, "{}: {}", kind, self.reason),
+        }
+    }
+}
+
+/// 内容过滤相关的错误码
+const CONTENT_FILTER_CODES: &[&str] = &[
+    "content_filter",
+    "content_policy_violation",
+    "responsible_ai_policy_violation",
+    "datainspectionfailed",
+    "data_inspection_failed",
+    "1301",
+];
+
+/// 从响应（或错误响应）中识别拒答
+///
+/// - OpenAI 兼容：`choices[].finish_reason == "content_filter"`、`choices[].message.refusal`
+/// - Qwen 原生：`output.finish_reason` / `output.choices[].finish_reason`、错误码 `DataInspectionFailed`
+/// - Anthropic：`stop_reason == "refusal"`
+/// - Gemini：`candidates[].finishReason == "SAFETY"`、`promptFeedback.blockReason`
+/// - 错误响应：`error.code` / `code` 为内容过滤相关错误码（含 Azure、智谱 `1301`）
+pub fn detect_refusal(payload: &Value) -> Option<LlmRefusal> {
+    let finish_reasons = [
+        payload.pointer("/choices/0/finish_reason"),
+        payload.pointer("/output/finish_reason"),
+        payload.pointer("/output/choices/0/finish_reason"),
+    ];
+    if finish_reasons
+        .iter()
+        .flatten()
+        .any(|reason| reason.as_str() == Some("content_filter"))
+    {
+        return Some(LlmRefusal {
+            kind: RefusalKind::ContentFilter,
+            reason: "content_filter".to_string(),
+            code: None,
+        });
+    }
+
+    if let Some(text) = payload
+        .pointer("/choices/0/message/refusal")
+        .and_then(Value::as_str)
+        .filter(|text| !text.is_empty())
+    {
+        return Some(LlmRefusal {
+            kind: RefusalKind::Refusal,
+            reason: text.to_string(),
+            code: None,
+        });
+    }
+
+    let gemini_block = payload
+        .pointer("/promptFeedback/blockReason")
+        .or_else(|| {
+            payload
+                .pointer("/candidates/0/finishReason")
+                .filter(|reason| matches!(reason.as_str(), Some("SAFETY" | "PROHIBITED_CONTENT")))
+        })
+        .and_then(Value::as_str);
+    if let Some(reason) = gemini_block {
+        return Some(LlmRefusal {
+            kind: RefusalKind::ContentFilter,
+            reason: reason.to_string(),
+            code: None,
+        });
+    }
+
+    if payload.get("stop_reason").and_then(Value::as_str) == Some("refusal") {
+        return Some(LlmRefusal {
+            kind: RefusalKind::Refusal,
+            reason: "refusal".to_string(),
+            code: None,
+        });
+    }
+
+    let error = payload.get("error").unwrap_or(payload);
+    let code = match error.get("code") {
+        Some(Value::String(code)) => Some(code.clone()),
+        Some(Value::Number(code)) => Some(code.to_string()),
+        _ => None,
+    }?;
+    if CONTENT_FILTER_CODES.contains(&code.to_lowercase().as_str()) {
+        let reason = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("content_filter")
+            .to_string();
+        return Some(LlmRefusal {
+            kind: RefusalKind::ContentFilter,
+            reason,
+            code: Some(code),
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_detect_refusal_shapes() {
+        let openai = json!({ "choices": [{ "finish_reason": "content_filter", "message": { "content": null } }] });
+        assert_eq!(
+            detect_refusal(&openai).unwrap().kind,
+            RefusalKind::ContentFilter
+        );
+
+        let refusal = json!({ "choices": [{ "finish_reason": "stop", "message": { "refusal": "I can't help with that." } }] });
+        let detected = detect_refusal(&refusal).unwrap();
+        assert_eq!(detected.kind, RefusalKind::Refusal);
+        assert_eq!(detected.reason, "I can't help with that.");
+
+        let qwen_error = json!({ "code": "DataInspectionFailed", "message": "Input data may contain inappropriate content." });
+        let detected = detect_refusal(&qwen_error).unwrap();
+        assert_eq!(detected.code.as_deref(), Some("DataInspectionFailed"));
+
+        let azure_error = json!({ "error": { "code": "content_filter", "message": "filtered" } });
+        assert_eq!(detect_refusal(&azure_error).unwrap().reason, "filtered");
+
+        let gemini =
+            json!({ "candidates": [{ "finishReason": "SAFETY", "content": { "parts": [] } }] });
+        assert_eq!(detect_refusal(&gemini).unwrap().reason, "SAFETY");
+
+        let normal = json!({ "choices": [{ "finish_reason": "stop", "message": { "content": "hi", "refusal": null } }] });
+        assert!(detect_refusal(&normal).is_none());
+        assert!(detect_refusal(&json!({ "error": { "code": "rate_limit" } })).is_none());
+    }
+}
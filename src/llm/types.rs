@@ -3,7 +3,10 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::pin::Pin;
 
+use crate::agent::Attachment;
 use crate::error::Result;
+use crate::state::BlobStore;
+use base64::Engine;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LlmMessage {
@@ -20,10 +23,150 @@ pub struct LlmRequest {
     pub temperature: f32,
     #[serde(default)]
     pub metadata: Option<Value>,
-    #[serde(default)]
-    pub image_url: Option<String>,
-    #[serde(default)]
-    pub image_base64: Option<String>,
+    /// 追加在 `user` 文本之后的多模态内容（图片、音频、视频等），按顺序发送
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content: Vec<ContentPart>,
+    /// 温度以外的采样参数，由客户端按 API 格式映射
+    #[serde(default, skip_serializing_if = "LlmParams::is_empty")]
+    pub params: LlmParams,
+}
+
+impl LlmRequest {
+    pub fn with_params(mut self, params: LlmParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub fn with_part(mut self, part: ContentPart) -> Self {
+        self.content.push(part);
+        self
+    }
+
+    /// 把消息附件转换为内容部分追加到请求中（文件附件不支持，跳过）
+    pub async fn with_attachments(
+        mut self,
+        attachments: &[Attachment],
+        store: Option<&dyn BlobStore>,
+    ) -> Result<Self> {
+        for attachment in attachments {
+            if let Some(part) = ContentPart::from_attachment(attachment, store).await? {
+                self.content.push(part);
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// 采样参数，未设置的字段不发送，使用提供商默认值
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LlmParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// 最大输出 token 数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    /// 停止序列
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// 随机种子，用于可复现的输出（提供商支持时）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl LlmParams {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 多模态请求内容
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    Image { source: MediaSource },
+    Audio { source: MediaSource },
+    Video { source: MediaSource },
+}
+
+/// 媒体来源：URL 或 base64 内容
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MediaSource {
+    Url { url: String },
+    Base64 { mime_type: String, data: String },
+}
+
+impl MediaSource {
+    /// URL 原样返回，base64 内容转换为 data URL
+    pub fn to_url(&self) -> String {
+        match self {
+            MediaSource::Url { url } => url.clone(),
+            MediaSource::Base64 { mime_type, data } => format!("data:{mime_type};base64,{data}"),
+        }
+    }
+
+    pub fn from_bytes(mime_type: &str, bytes: &[u8]) -> Self {
+        MediaSource::Base64 {
+            mime_type: mime_type.to_string(),
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+}
+
+impl ContentPart {
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text { text: text.into() }
+    }
+
+    pub fn image_url(url: impl Into<String>) -> Self {
+        ContentPart::Image {
+            source: MediaSource::Url { url: url.into() },
+        }
+    }
+
+    pub fn image_base64(mime_type: impl Into<String>, data: impl Into<String>) -> Self {
+        ContentPart::Image {
+            source: MediaSource::Base64 {
+                mime_type: mime_type.into(),
+                data: data.into(),
+            },
+        }
+    }
+
+    pub fn audio_url(url: impl Into<String>) -> Self {
+        ContentPart::Audio {
+            source: MediaSource::Url { url: url.into() },
+        }
+    }
+
+    pub fn video_url(url: impl Into<String>) -> Self {
+        ContentPart::Video {
+            source: MediaSource::Url { url: url.into() },
+        }
+    }
+
+    /// 由消息附件转换，引用 blob 的附件从 `store` 读取内容
+    pub async fn from_attachment(
+        attachment: &Attachment,
+        store: Option<&dyn BlobStore>,
+    ) -> Result<Option<Self>> {
+        let part = match attachment {
+            Attachment::ImageUrl { url } => ContentPart::image_url(url.clone()),
+            Attachment::ImageBytes { mime_type, data } => ContentPart::Image {
+                source: MediaSource::from_bytes(mime_type, &data.resolve(store).await?),
+            },
+            Attachment::Audio { mime_type, data } => ContentPart::Audio {
+                source: MediaSource::from_bytes(mime_type, &data.resolve(store).await?),
+            },
+            Attachment::File { .. } => return Ok(None),
+        };
+        Ok(Some(part))
+    }
 }
 
 fn default_temperature() -> f32 {
@@ -62,6 +205,8 @@ pub type LlmStream = Pin<Box<dyn Stream<Item = Result<LlmStreamChunk>> + Send>>;
 /// 
 /// # 自动推断规则
 /// 
+/// - endpoint包含 "openai.azure.com" 或 "/openai/deployments/" → AzureOpenAI
+/// - endpoint为 "generativelanguage.googleapis.com"（非 OpenAI 兼容路径）→ Gemini
 /// - endpoint包含 "/compatible-mode/" 或 "/chat/completions" → OpenAI
 /// - endpoint包含 "/services/aigc/text-generation/" → Qwen
 /// - 同时使用Qwen且model包含"vl" → QwenVision
@@ -100,6 +245,19 @@ pub enum ApiFormat {
     /// - 通义千问的视觉模型（qwen-vl系列）
     /// - 需要处理图片输入的场景
     QwenVision,
+
+    /// Google Gemini 原生格式
+    ///
+    /// 请求发往 `{endpoint}/models/{model}:generateContent`，API Key 放在 `key` 查询参数中，
+    /// 回复从 `candidates[0].content.parts` 读取。
+    Gemini,
+
+    /// Azure OpenAI 格式
+    ///
+    /// 请求发往 `{endpoint}/openai/deployments/{model}/chat/completions?api-version=...`
+    /// （`model` 为部署名），API Key 放在 `api-key` 请求头中，请求/响应与 OpenAI 相同。
+    #[serde(alias = "azure_openai")]
+    AzureOpenAI,
 }
 
 #[cfg(feature = "openai-client")]
@@ -116,6 +274,14 @@ impl ApiFormat {
     /// - `Some(ApiFormat)`: 成功推断
     /// - `None`: 无法推断，需要手动指定
     pub fn infer_from_endpoint(endpoint: &str, model: Option<&str>) -> Option<Self> {
+        if endpoint.contains("openai.azure.com") || endpoint.contains("/openai/deployments/") {
+            return Some(ApiFormat::AzureOpenAI);
+        }
+
+        if endpoint.contains("generativelanguage.googleapis.com") && !endpoint.contains("/openai") {
+            return Some(ApiFormat::Gemini);
+        }
+
         if endpoint.contains("compatible-mode") 
             || endpoint.contains("/chat/completions")
             || endpoint.contains("openai.com")
@@ -149,6 +315,8 @@ impl ApiFormat {
             "openai" => Some(ApiFormat::OpenAI),
             "qwen" => Some(ApiFormat::Qwen),
             "qwenvision" | "qwen_vision" => Some(ApiFormat::QwenVision),
+            "gemini" => Some(ApiFormat::Gemini),
+            "azureopenai" | "azure_openai" | "azure" => Some(ApiFormat::AzureOpenAI),
             _ => None,
         }
     }
@@ -159,11 +327,13 @@ impl ApiFormat {
             ApiFormat::OpenAI => "openai",
             ApiFormat::Qwen => "qwen",
             ApiFormat::QwenVision => "qwenvision",
+            ApiFormat::Gemini => "gemini",
+            ApiFormat::AzureOpenAI => "azureopenai",
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "openai-client"))]
 mod tests {
     use super::*;
     
@@ -202,4 +372,33 @@ mod tests {
             Some(ApiFormat::QwenVision)
         );
     }
+
+    #[test]
+    fn test_infer_gemini_and_azure_format() {
+        assert_eq!(
+            ApiFormat::infer_from_endpoint(
+                "https://generativelanguage.googleapis.com/v1beta",
+                Some("gemini-1.5-pro")
+            ),
+            Some(ApiFormat::Gemini)
+        );
+        assert_eq!(
+            ApiFormat::infer_from_endpoint(
+                "https://generativelanguage.googleapis.com/v1beta/openai/chat/completions",
+                None
+            ),
+            Some(ApiFormat::OpenAI)
+        );
+        assert_eq!(
+            ApiFormat::infer_from_endpoint(
+                "https://my-resource.openai.azure.com/openai/deployments/gpt-4o/chat/completions",
+                None
+            ),
+            Some(ApiFormat::AzureOpenAI)
+        );
+        assert_eq!(
+            ApiFormat::from_str("azure_openai"),
+            Some(ApiFormat::AzureOpenAI)
+        );
+    }
 }
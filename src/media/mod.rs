@@ -0,0 +1,9 @@
+//! 媒体生成：长时间运行的异步任务（提交、轮询、产物下载）
+
+pub mod poll;
+pub mod video;
+
+pub use poll::{PolledTask, TaskProgress};
+pub use video::{
+    VideoGenClient, VideoGenConfig, VideoGenRequest, VideoGenResult, VideoProvider, VideoTask,
+};
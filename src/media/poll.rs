@@ -0,0 +1,71 @@
+//! 异步任务轮询：提交后按固定间隔查询，直到成功、失败或超时
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::error::{AgentFlowError, Result};
+
+/// 可轮询的任务状态快照
+pub trait PolledTask {
+    fn task_id(&self) -> &str;
+    fn status(&self) -> &str;
+    /// 任务结束时返回 `Some`：成功为 `Ok`，失败为错误
+    fn outcome(&self) -> Option<Result<()>>;
+}
+
+/// 轮询进度
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TaskProgress {
+    pub task_id: String,
+    pub status: String,
+    /// 第几次查询（提交时为 0）
+    pub attempt: u32,
+    pub elapsed_ms: u64,
+}
+
+/// 轮询直到任务结束，返回最终状态与查询次数；查询失败视为暂时错误，继续轮询
+pub(crate) async fn poll_task<T, F, Fut>(
+    submitted: T,
+    fetch: F,
+    interval: Duration,
+    max_wait: Duration,
+    on_progress: impl Fn(&TaskProgress),
+) -> Result<(T, u32)>
+where
+    T: PolledTask,
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let started = Instant::now();
+    let task_id = submitted.task_id().to_string();
+    let mut task = submitted;
+    let mut attempt = 0;
+    loop {
+        on_progress(&TaskProgress {
+            task_id: task_id.clone(),
+            status: task.status().to_string(),
+            attempt,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        });
+        if let Some(outcome) = task.outcome() {
+            return outcome.map(|_| (task, attempt));
+        }
+        if started.elapsed() + interval > max_wait {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Task {} did not finish within {:?}",
+                task_id,
+                max_wait
+            )));
+        }
+        tokio::time::sleep(interval).await;
+        attempt += 1;
+        match fetch(task_id.clone()).await {
+            Ok(next) => task = next,
+            Err(err) => tracing::debug!(%task_id, attempt, error = %err, "task query failed"),
+        }
+    }
+}
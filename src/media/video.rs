@@ -0,0 +1,586 @@
+//! 视频生成客户端 - DashScope 通义万相 / OpenAI Sora 异步任务接口
+//!
+//! 提交任务后轮询状态，完成后下载视频；配置了 `BlobStore` 时产物写入存储，消息附件只保存 blob id。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::agent::{Attachment, BlobData};
+use crate::config::EnvConfig;
+use crate::error::{AgentFlowError, Result};
+use crate::media::poll::{poll_task, PolledTask, TaskProgress};
+use crate::state::BlobStore;
+
+/// 视频生成服务提供商
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoProvider {
+    DashScope,
+    OpenAI,
+}
+
+impl VideoProvider {
+    /// 提交任务的接口
+    pub fn default_endpoint(&self) -> &'static str {
+        match self {
+            VideoProvider::DashScope => {
+                "https://dashscope.aliyuncs.com/api/v1/services/aigc/video-generation/video-synthesis"
+            }
+            VideoProvider::OpenAI => "https://api.openai.com/v1/videos",
+        }
+    }
+
+    /// 查询任务的接口（`{task_endpoint}/{task_id}`）
+    pub fn default_task_endpoint(&self) -> &'static str {
+        match self {
+            VideoProvider::DashScope => "https://dashscope.aliyuncs.com/api/v1/tasks",
+            VideoProvider::OpenAI => "https://api.openai.com/v1/videos",
+        }
+    }
+
+    /// 未配置 api_key 时读取的环境变量
+    pub fn api_key_env(&self) -> &'static str {
+        match self {
+            VideoProvider::DashScope => "DASHSCOPE_API_KEY",
+            VideoProvider::OpenAI => "OPENAI_API_KEY",
+        }
+    }
+
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            VideoProvider::DashScope => "wan2.1-t2v-turbo",
+            VideoProvider::OpenAI => "sora-2",
+        }
+    }
+
+    /// 默认分辨率：DashScope 使用 `宽*高`，OpenAI 使用 `宽x高`
+    pub fn default_size(&self) -> &'static str {
+        match self {
+            VideoProvider::DashScope => "1280*720",
+            VideoProvider::OpenAI => "1280x720",
+        }
+    }
+}
+
+/// 视频生成请求
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VideoGenRequest {
+    pub prompt: String,
+    /// 首帧图片地址（图生视频，仅 DashScope）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_url: Option<String>,
+    /// 分辨率，不设置时使用提供商默认值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    /// 时长（秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u32>,
+}
+
+impl VideoGenRequest {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_image_url(mut self, url: impl Into<String>) -> Self {
+        self.image_url = Some(url.into());
+        self
+    }
+
+    pub fn with_size(mut self, size: impl Into<String>) -> Self {
+        self.size = Some(size.into());
+        self
+    }
+
+    pub fn with_duration(mut self, seconds: u32) -> Self {
+        self.duration = Some(seconds);
+        self
+    }
+
+    fn body(&self, provider: VideoProvider, model: &str) -> Result<Value> {
+        let size = self
+            .size
+            .clone()
+            .unwrap_or_else(|| provider.default_size().to_string());
+        match provider {
+            VideoProvider::DashScope => {
+                let mut input = json!({ "prompt": self.prompt });
+                if let Some(url) = &self.image_url {
+                    input["img_url"] = json!(url);
+                }
+                let mut parameters = json!({ "size": size });
+                if let Some(duration) = self.duration {
+                    parameters["duration"] = json!(duration);
+                }
+                Ok(json!({ "model": model, "input": input, "parameters": parameters }))
+            }
+            VideoProvider::OpenAI => {
+                if self.image_url.is_some() {
+                    return Err(AgentFlowError::Other(anyhow!(
+                        "OpenAI video generation does not support image_url"
+                    )));
+                }
+                let mut body = json!({ "model": model, "prompt": self.prompt, "size": size });
+                if let Some(duration) = self.duration {
+                    body["seconds"] = json!(duration.to_string());
+                }
+                Ok(body)
+            }
+        }
+    }
+}
+
+/// 视频任务状态快照
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoTask {
+    pub task_id: String,
+    /// 提供商原始状态：DashScope `PENDING` / `RUNNING` / `SUCCEEDED`，OpenAI `queued` / `in_progress` / `completed` 等
+    pub status: String,
+    /// 完成后的视频地址（DashScope）；OpenAI 通过 `/videos/{id}/content` 下载
+    pub video_url: Option<String>,
+    /// 完成百分比（OpenAI）
+    pub progress: Option<u32>,
+    /// 失败原因
+    pub message: Option<String>,
+}
+
+impl VideoTask {
+    /// 解析提交或查询任务的响应
+    pub fn parse(provider: VideoProvider, body: &Value) -> Option<Self> {
+        match provider {
+            VideoProvider::DashScope => {
+                let output = body.get("output")?;
+                Some(Self {
+                    task_id: output["task_id"].as_str()?.to_string(),
+                    status: output["task_status"]
+                        .as_str()
+                        .unwrap_or("UNKNOWN")
+                        .to_string(),
+                    video_url: output["video_url"].as_str().map(str::to_string),
+                    progress: None,
+                    message: output["message"]
+                        .as_str()
+                        .or_else(|| body["message"].as_str())
+                        .map(str::to_string),
+                })
+            }
+            VideoProvider::OpenAI => Some(Self {
+                task_id: body["id"].as_str()?.to_string(),
+                status: body["status"].as_str().unwrap_or("unknown").to_string(),
+                video_url: None,
+                progress: body["progress"].as_u64().map(|p| p as u32),
+                message: body["error"]["message"].as_str().map(str::to_string),
+            }),
+        }
+    }
+
+    pub fn is_succeeded(&self) -> bool {
+        matches!(
+            self.status.to_ascii_lowercase().as_str(),
+            "succeeded" | "completed"
+        )
+    }
+
+    pub fn is_failed(&self) -> bool {
+        matches!(
+            self.status.to_ascii_lowercase().as_str(),
+            "failed" | "canceled" | "cancelled" | "unknown" | "expired"
+        )
+    }
+}
+
+impl PolledTask for VideoTask {
+    fn task_id(&self) -> &str {
+        &self.task_id
+    }
+
+    fn status(&self) -> &str {
+        &self.status
+    }
+
+    fn outcome(&self) -> Option<Result<()>> {
+        if self.is_succeeded() {
+            return Some(Ok(()));
+        }
+        self.is_failed().then(|| {
+            Err(AgentFlowError::Other(anyhow!(
+                "Video generation task {} {}: {}",
+                self.task_id,
+                self.status.to_lowercase(),
+                self.message.as_deref().unwrap_or("no details")
+            )))
+        })
+    }
+}
+
+/// 生成结果
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoGenResult {
+    pub task_id: String,
+    /// 提供商返回的视频地址（可能带有效期）
+    pub video_url: Option<String>,
+    /// 下载的视频，配置了 `BlobStore` 时为 blob 引用
+    pub attachment: Attachment,
+    /// 视频字节数
+    pub bytes: usize,
+    /// 查询次数
+    pub attempts: u32,
+}
+
+impl VideoGenResult {
+    /// 产物在 `BlobStore` 中的 id
+    pub fn blob(&self) -> Option<&str> {
+        match self.attachment.data() {
+            Some(BlobData::Blob(id)) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+/// 视频生成配置（`video.generate` 工具使用）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VideoGenConfig {
+    pub provider: VideoProvider,
+    /// 模型，不设置时使用提供商默认模型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// API Key，支持 `${VAR_NAME}`；为空时读取提供商对应的环境变量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_endpoint: Option<String>,
+    #[serde(default = "VideoGenConfig::default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    #[serde(default = "VideoGenConfig::default_max_wait_secs")]
+    pub max_wait_secs: u64,
+    /// 视频产物使用的本地 blob 目录，不设置时视频内联在消息附件中
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob_dir: Option<String>,
+}
+
+impl VideoGenConfig {
+    fn default_poll_interval_ms() -> u64 {
+        5_000
+    }
+
+    fn default_max_wait_secs() -> u64 {
+        600
+    }
+
+    pub fn new(provider: VideoProvider) -> Self {
+        Self {
+            provider,
+            model: None,
+            api_key: None,
+            endpoint: None,
+            task_endpoint: None,
+            poll_interval_ms: Self::default_poll_interval_ms(),
+            max_wait_secs: Self::default_max_wait_secs(),
+            blob_dir: None,
+        }
+    }
+
+    /// 按 DashScope / OpenAI 顺序选择已配置 API Key 的提供商
+    pub fn from_env() -> Option<Self> {
+        [VideoProvider::DashScope, VideoProvider::OpenAI]
+            .into_iter()
+            .find(|provider| EnvConfig::get_env_optional(provider.api_key_env()).is_some())
+            .map(Self::new)
+    }
+
+    fn resolve_api_key(&self) -> Result<String> {
+        match &self.api_key {
+            Some(value) if value.starts_with("${") && value.ends_with('}') => {
+                EnvConfig::get_env(&value[2..value.len() - 1])
+            }
+            Some(value) => Ok(value.clone()),
+            None => EnvConfig::get_env(self.provider.api_key_env()),
+        }
+    }
+
+    pub fn build_client(&self) -> Result<VideoGenClient> {
+        let mut client = VideoGenClient::new(self.provider, self.resolve_api_key()?)
+            .with_poll_interval(Duration::from_millis(self.poll_interval_ms))
+            .with_max_wait(Duration::from_secs(self.max_wait_secs));
+        if let Some(model) = &self.model {
+            client = client.with_model(model.clone());
+        }
+        if let Some(endpoint) = &self.endpoint {
+            client = client.with_endpoint(endpoint.clone());
+        }
+        if let Some(task_endpoint) = &self.task_endpoint {
+            client = client.with_task_endpoint(task_endpoint.clone());
+        }
+        if let Some(dir) = &self.blob_dir {
+            client = client.with_blob_store(Arc::new(crate::state::LocalBlobStore::new(dir)));
+        }
+        Ok(client)
+    }
+}
+
+/// 视频生成客户端
+///
+/// 以异步任务方式提交，每隔 `poll_interval` 查询一次，超过 `max_wait` 仍未完成则返回超时错误。
+#[derive(Clone)]
+pub struct VideoGenClient {
+    client: reqwest::Client,
+    provider: VideoProvider,
+    api_key: String,
+    model: String,
+    endpoint: String,
+    task_endpoint: String,
+    poll_interval: Duration,
+    max_wait: Duration,
+    blob_store: Option<Arc<dyn BlobStore>>,
+}
+
+impl VideoGenClient {
+    pub fn new(provider: VideoProvider, api_key: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            provider,
+            api_key: api_key.into(),
+            model: provider.default_model().to_string(),
+            endpoint: provider.default_endpoint().to_string(),
+            task_endpoint: provider.default_task_endpoint().to_string(),
+            poll_interval: Duration::from_millis(VideoGenConfig::default_poll_interval_ms()),
+            max_wait: Duration::from_secs(VideoGenConfig::default_max_wait_secs()),
+            blob_store: None,
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    pub fn with_task_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.task_endpoint = endpoint.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// 下载的视频写入该存储
+    pub fn with_blob_store(mut self, store: Arc<dyn BlobStore>) -> Self {
+        self.blob_store = Some(store);
+        self
+    }
+
+    pub fn provider(&self) -> VideoProvider {
+        self.provider
+    }
+
+    /// 提交生成任务
+    pub async fn submit(&self, request: &VideoGenRequest) -> Result<VideoTask> {
+        let mut builder = self
+            .client
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .json(&request.body(self.provider, &self.model)?);
+        if self.provider == VideoProvider::DashScope {
+            builder = builder.header("X-DashScope-Async", "enable");
+        }
+        let response = builder.send().await.map_err(|e| {
+            AgentFlowError::Other(anyhow!("Video generation request failed: {}", e))
+        })?;
+        self.read_task(response).await
+    }
+
+    /// 查询任务状态
+    pub async fn task(&self, task_id: &str) -> Result<VideoTask> {
+        let response = self
+            .client
+            .get(format!("{}/{}", self.task_endpoint, task_id))
+            .bearer_auth(&self.api_key)
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Video task query failed: {}", e)))?;
+        self.read_task(response).await
+    }
+
+    async fn read_task(&self, response: reqwest::Response) -> Result<VideoTask> {
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Video generation API returned {}: {}",
+                status,
+                body
+            )));
+        }
+        VideoTask::parse(self.provider, &body).ok_or_else(|| {
+            AgentFlowError::Other(anyhow!("Invalid video generation response: {}", body))
+        })
+    }
+
+    /// 下载已完成任务的视频
+    pub async fn download(&self, task: &VideoTask) -> Result<Vec<u8>> {
+        let builder = match (self.provider, &task.video_url) {
+            (VideoProvider::DashScope, Some(url)) => self.client.get(url),
+            (VideoProvider::DashScope, None) => {
+                return Err(AgentFlowError::Other(anyhow!(
+                    "Video generation task {} succeeded without video url",
+                    task.task_id
+                )))
+            }
+            (VideoProvider::OpenAI, _) => self
+                .client
+                .get(format!("{}/{}/content", self.task_endpoint, task.task_id))
+                .bearer_auth(&self.api_key),
+        };
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Video download failed: {}", e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Video download returned {}",
+                status
+            )));
+        }
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Video download failed: {}", e)))?;
+        Ok(bytes.to_vec())
+    }
+
+    pub async fn generate(&self, request: &VideoGenRequest) -> Result<VideoGenResult> {
+        self.generate_with_progress(request, |_| {}).await
+    }
+
+    /// 提交、等待完成并下载视频，每次查询后回调 `on_progress`
+    pub async fn generate_with_progress(
+        &self,
+        request: &VideoGenRequest,
+        on_progress: impl Fn(&TaskProgress),
+    ) -> Result<VideoGenResult> {
+        let submitted = self.submit(request).await?;
+        let (task, attempts) = poll_task(
+            submitted,
+            |task_id| async move { self.task(&task_id).await },
+            self.poll_interval,
+            self.max_wait,
+            on_progress,
+        )
+        .await?;
+        let bytes = self.download(&task).await?;
+        let size = bytes.len();
+        let mut attachment = Attachment::file(format!("{}.mp4", task.task_id), "video/mp4", bytes);
+        if let Some(store) = &self.blob_store {
+            attachment = attachment.offload(store.as_ref()).await?;
+        }
+        Ok(VideoGenResult {
+            task_id: task.task_id,
+            video_url: task.video_url,
+            attachment,
+            bytes: size,
+            attempts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_request_bodies_per_provider() {
+        let request = VideoGenRequest::new("a cat surfing").with_duration(5);
+        let body = request
+            .body(VideoProvider::DashScope, "wan2.1-t2v-turbo")
+            .unwrap();
+        assert_eq!(body["input"], json!({ "prompt": "a cat surfing" }));
+        assert_eq!(
+            body["parameters"],
+            json!({ "size": "1280*720", "duration": 5 })
+        );
+
+        let body = request.body(VideoProvider::OpenAI, "sora-2").unwrap();
+        assert_eq!(body["size"], "1280x720");
+        assert_eq!(body["seconds"], "5");
+        assert!(request
+            .with_image_url("https://img/a.png")
+            .body(VideoProvider::OpenAI, "sora-2")
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_task_statuses() {
+        let dashscope = json!({ "output": {
+            "task_id": "t1",
+            "task_status": "SUCCEEDED",
+            "video_url": "https://oss/v.mp4"
+        } });
+        let task = VideoTask::parse(VideoProvider::DashScope, &dashscope).unwrap();
+        assert!(task.is_succeeded());
+        assert_eq!(task.video_url.as_deref(), Some("https://oss/v.mp4"));
+
+        let openai =
+            json!({ "id": "video_1", "status": "failed", "error": { "message": "policy" } });
+        let task = VideoTask::parse(VideoProvider::OpenAI, &openai).unwrap();
+        let err = task.outcome().unwrap().unwrap_err();
+        assert!(err.to_string().contains("policy"));
+
+        let running = json!({ "id": "video_1", "status": "in_progress", "progress": 40 });
+        let task = VideoTask::parse(VideoProvider::OpenAI, &running).unwrap();
+        assert_eq!(task.progress, Some(40));
+        assert!(task.outcome().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_video_task_until_completed() {
+        let task = |status: &str| VideoTask {
+            task_id: "video_1".to_string(),
+            status: status.to_string(),
+            video_url: None,
+            progress: None,
+            message: None,
+        };
+        let responses = Mutex::new(vec![Ok(task("completed")), Ok(task("in_progress"))]);
+        let statuses = Mutex::new(Vec::new());
+        let (done, attempts) = poll_task(
+            task("queued"),
+            |_| {
+                let next = responses.lock().unwrap().pop().unwrap();
+                async move { next }
+            },
+            Duration::from_secs(5),
+            Duration::from_secs(60),
+            |p| statuses.lock().unwrap().push(p.status.clone()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(done.status, "completed");
+        assert_eq!(attempts, 2);
+        assert_eq!(
+            statuses.into_inner().unwrap(),
+            vec!["queued", "in_progress", "completed"]
+        );
+    }
+}
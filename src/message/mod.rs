@@ -54,6 +54,7 @@ where
             metadata: self
                 .metadata
                 .or_else(|| self.schema.map(|s| json!({ "schema": s }))),
+            attachments: Vec::new(),
         })
     }
 
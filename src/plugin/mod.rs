@@ -10,6 +10,10 @@ use crate::error::AgentFlowError;
 use crate::schema::{register_schema, Schema, SchemaKind};
 use crate::tools::ToolRegistry;
 
+mod remote;
+
+pub use remote::RemotePlugin;
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum PluginKind {
@@ -53,6 +57,14 @@ pub enum PluginError {
     ManifestParse(String),
     #[error("plugin `{name}` incompatible: {reason}")]
     Incompatible { name: String, reason: String },
+    #[error("plugin download failed: {0}")]
+    Download(String),
+    #[error("plugin checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("invalid plugin bundle: {0}")]
+    InvalidBundle(String),
+    #[error("plugin base dir not configured")]
+    BaseDirMissing,
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -92,6 +104,24 @@ impl PluginRegistry {
         Ok(())
     }
 
+    /// 监听插件目录，新增、修改或删除 `plugin.json` 后重新加载（需启用 `hot-reload` feature）
+    ///
+    /// 每次重新加载都会构建全新的注册表；清单解析失败时保留旧版本。
+    #[cfg(feature = "hot-reload")]
+    pub fn watch(
+        dir: impl AsRef<Path>,
+    ) -> Result<crate::utils::watch::ReloadHandle<PluginRegistry>, AgentFlowError> {
+        let load = |dir: &Path| {
+            let mut registry = PluginRegistry::new().with_base_dir(dir.to_path_buf());
+            registry
+                .load_directory(dir)
+                .map_err(|err| AgentFlowError::Other(err.into()))?;
+            Ok(registry)
+        };
+        let initial = load(dir.as_ref())?;
+        crate::utils::watch::watch_path(dir.as_ref(), initial, load)
+    }
+
     pub fn register_manifest(&mut self, manifest: PluginManifest) {
         self.manifests.insert(manifest.name.clone(), manifest);
     }
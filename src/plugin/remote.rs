@@ -0,0 +1,292 @@
+//! 远程插件 - 从 HTTP 制品服务器或 OCI 仓库下载插件包
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{PluginError, PluginManifest, PluginRegistry};
+use crate::config::EnvConfig;
+use crate::llm::http::body::read_body;
+use crate::llm::http::{
+    default_http_client, shared_http_client, HttpPoolConfig, DEFAULT_MAX_RESPONSE_BYTES,
+};
+
+/// 远程插件来源
+///
+/// `url` 支持 `http(s)://...` 和 `oci://<registry>/<repository>@sha256:<digest>`，
+/// OCI 地址会按 digest 拉取 blob，并自动用 digest 校验；http(s) 地址必须设置 `sha256`。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemotePlugin {
+    pub url: String,
+    /// 插件包的 sha256（十六进制），用于校验下载内容并命中本地缓存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Bearer Token（支持 `${VAR}`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+    /// 下载使用的连接池配置（代理、CA 证书等），未设置时使用默认的共享客户端
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpPoolConfig>,
+    /// 插件包的大小上限（字节），默认 `DEFAULT_MAX_RESPONSE_BYTES`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<usize>,
+}
+
+impl RemotePlugin {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            sha256: None,
+            auth_token: None,
+            http: None,
+            max_bytes: None,
+        }
+    }
+
+    pub fn with_sha256(mut self, sha256: impl Into<String>) -> Self {
+        self.sha256 = Some(sha256.into());
+        self
+    }
+
+    pub fn with_auth_token(mut self, token: impl Into<String>) -> Self {
+        self.auth_token = Some(token.into());
+        self
+    }
+
+    pub fn with_http_config(mut self, config: HttpPoolConfig) -> Self {
+        self.http = Some(config);
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// 解析实际下载地址和期望的 sha256；未固定 digest 的来源不允许安装
+    fn resolve(&self) -> Result<(String, String), PluginError> {
+        let Some(reference) = self.url.strip_prefix("oci://") else {
+            let digest = self.sha256.clone().ok_or_else(|| {
+                PluginError::Download(format!(
+                    "remote plugin `{}` must pin a sha256 digest",
+                    self.url
+                ))
+            })?;
+            return Ok((self.url.clone(), digest));
+        };
+        let (location, digest) = reference.split_once("@sha256:").ok_or_else(|| {
+            PluginError::Download(format!(
+                "OCI reference `{}` must pin a digest (`@sha256:...`)",
+                self.url
+            ))
+        })?;
+        let (registry, repository) = location.split_once('/').ok_or_else(|| {
+            PluginError::Download(format!("invalid OCI reference `{}`", self.url))
+        })?;
+        if let Some(expected) = &self.sha256 {
+            if !expected.eq_ignore_ascii_case(digest) {
+                return Err(PluginError::ChecksumMismatch {
+                    expected: expected.clone(),
+                    actual: digest.to_string(),
+                });
+            }
+        }
+        Ok((
+            format!("https://{registry}/v2/{repository}/blobs/sha256:{digest}"),
+            digest.to_string(),
+        ))
+    }
+}
+
+/// 插件包：清单加资源文件（base64 编码），也可以只有清单
+#[derive(Debug, Deserialize)]
+struct PluginBundle {
+    manifest: PluginManifest,
+    #[serde(default)]
+    assets: HashMap<String, String>,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// 资源路径必须是插件目录内的相对路径
+fn safe_asset_path(dir: &Path, name: &str) -> Result<PathBuf, PluginError> {
+    let relative = Path::new(name);
+    if name.is_empty()
+        || !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(PluginError::InvalidBundle(format!(
+            "asset path `{name}` escapes the plugin directory"
+        )));
+    }
+    Ok(dir.join(relative))
+}
+
+impl PluginRegistry {
+    /// 下载远程插件包，校验后缓存到 `base_dir` 并注册
+    ///
+    /// 插件解包到 `<base_dir>/<name>/`，之后 `load_directory(base_dir)` 也能加载到它；
+    /// 原始包缓存在 `<base_dir>/.cache/<sha256>.json`，命中缓存时不再下载。
+    pub async fn load_remote(
+        &mut self,
+        source: &RemotePlugin,
+    ) -> Result<PluginManifest, PluginError> {
+        let base_dir = self.base_dir.clone().ok_or(PluginError::BaseDirMissing)?;
+        let (url, expected) = source.resolve()?;
+        let cache_dir = base_dir.join(".cache");
+
+        let cached = fs::read(cache_dir.join(format!("{}.json", expected.to_lowercase())))
+            .ok()
+            .filter(|bytes| sha256_hex(bytes).eq_ignore_ascii_case(&expected));
+        let bytes = match cached {
+            Some(bytes) => {
+                tracing::debug!(url = %source.url, "remote plugin served from cache");
+                bytes
+            }
+            None => Self::download(&url, source).await?,
+        };
+
+        let actual = sha256_hex(&bytes);
+        if !expected.eq_ignore_ascii_case(&actual) {
+            return Err(PluginError::ChecksumMismatch { expected, actual });
+        }
+
+        let bundle: PluginBundle = serde_json::from_slice(&bytes)
+            .or_else(|_| {
+                serde_json::from_slice(&bytes).map(|manifest| PluginBundle {
+                    manifest,
+                    assets: HashMap::new(),
+                })
+            })
+            .map_err(|err| PluginError::ManifestParse(err.to_string()))?;
+
+        let plugin_dir = safe_asset_path(&base_dir, &bundle.manifest.name)?;
+        fs::create_dir_all(&plugin_dir)?;
+        for (name, encoded) in &bundle.assets {
+            let path = safe_asset_path(&plugin_dir, name)?;
+            let content = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|err| {
+                    PluginError::InvalidBundle(format!("asset `{name}` is not base64: {err}"))
+                })?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+        }
+        let manifest_json = serde_json::to_vec_pretty(&bundle.manifest)
+            .map_err(|err| PluginError::ManifestParse(err.to_string()))?;
+        fs::write(plugin_dir.join("plugin.json"), manifest_json)?;
+        fs::create_dir_all(&cache_dir)?;
+        fs::write(cache_dir.join(format!("{actual}.json")), &bytes)?;
+
+        tracing::info!(plugin = %bundle.manifest.name, url = %source.url, "remote plugin installed");
+        self.register_manifest(bundle.manifest.clone());
+        Ok(bundle.manifest)
+    }
+
+    async fn download(url: &str, source: &RemotePlugin) -> Result<Vec<u8>, PluginError> {
+        let client = match &source.http {
+            Some(config) => {
+                shared_http_client(config).map_err(|err| PluginError::Download(err.to_string()))?
+            }
+            None => default_http_client(),
+        };
+        let mut request = client.get(url);
+        if let Some(token) = source.auth_token.as_deref() {
+            let token = match token.strip_prefix("${").and_then(|t| t.strip_suffix('}')) {
+                Some(var) => {
+                    EnvConfig::get_env(var).map_err(|err| PluginError::Download(err.to_string()))?
+                }
+                None => token.to_string(),
+            };
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|err| PluginError::Download(format!("{url}: {err}")))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(PluginError::Download(format!("{url} returned {status}")));
+        }
+        let limit = source.max_bytes.unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+        read_body(response, limit)
+            .await
+            .map_err(|err| PluginError::Download(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve_oci_reference() {
+        let digest = "ab".repeat(32);
+        let (url, expected) =
+            RemotePlugin::new(format!("oci://ghcr.io/acme/packs@sha256:{digest}"))
+                .resolve()
+                .unwrap();
+        assert_eq!(
+            url,
+            format!("https://ghcr.io/v2/acme/packs/blobs/sha256:{digest}")
+        );
+        assert_eq!(expected, digest);
+        assert!(RemotePlugin::new("oci://ghcr.io/acme/packs:latest")
+            .resolve()
+            .is_err());
+        assert!(RemotePlugin::new("https://artifacts.example.com/pack.json")
+            .resolve()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_remote_from_verified_cache() {
+        let base = tempfile::tempdir().unwrap();
+        let bundle = json!({
+            "manifest": { "name": "legal-pack", "version": "1.0.0", "kind": "tool" },
+            "assets": { "prompts/intake.txt": base64::engine::general_purpose::STANDARD.encode("hi") }
+        })
+        .to_string();
+        let digest = sha256_hex(bundle.as_bytes());
+        fs::create_dir_all(base.path().join(".cache")).unwrap();
+        fs::write(base.path().join(format!(".cache/{digest}.json")), &bundle).unwrap();
+
+        // 地址不可达，只能命中缓存
+        let source = RemotePlugin::new("http://127.0.0.1:9/legal-pack.json").with_sha256(&digest);
+        let mut registry = PluginRegistry::new().with_base_dir(base.path().to_path_buf());
+        let manifest = registry.load_remote(&source).await.unwrap();
+        assert_eq!(manifest.name, "legal-pack");
+        assert_eq!(
+            fs::read_to_string(base.path().join("legal-pack/prompts/intake.txt")).unwrap(),
+            "hi"
+        );
+
+        let mut reloaded = PluginRegistry::new();
+        reloaded.load_directory(base.path()).unwrap();
+        assert_eq!(reloaded.manifests().count(), 1);
+
+        let tampered = RemotePlugin::new(source.url.clone()).with_sha256("00".repeat(32));
+        assert!(registry.load_remote(&tampered).await.is_err());
+    }
+
+    #[test]
+    fn test_asset_paths_cannot_escape() {
+        let dir = Path::new("/tmp/plugins/pack");
+        assert!(safe_asset_path(dir, "assets/a.txt").is_ok());
+        assert!(safe_asset_path(dir, "../evil").is_err());
+        assert!(safe_asset_path(dir, "/etc/passwd").is_err());
+    }
+}
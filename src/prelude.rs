@@ -0,0 +1,75 @@
+//! 稳定的公共 API
+//!
+//! `use agentflow::prelude::*;` 即可获得构建和运行工作流所需的常用类型。
+//! 这里导出的类型遵循语义化版本：在同一主版本内不会删除或改名，
+//! 内部模块重构（例如 v2.0 移除扩展客户端）不会影响只依赖 prelude 的代码。
+//!
+//! `flow::services`、`flow::agent`、`flow::constants`、`llm::extended` 等内部模块
+//! 需要启用 `unstable` feature 才能按路径访问，这些模块可能在次版本中变化。
+
+pub use crate::agent::{
+    register_agent, Agent, AgentAction, AgentContext, AgentMessage, AgentRegistry, MessageRole,
+};
+pub use crate::error::{AgentFlowError, Result};
+pub use crate::flow::loader::{load_workflow_from_str, load_workflow_from_value, WorkflowBundle};
+pub use crate::flow::{
+    condition_always, condition_from_fn, condition_state_absent, condition_state_equals,
+    condition_state_exists, condition_state_not_equals, Flow, FlowBuilder, JoinStrategy,
+    TransitionCondition,
+};
+pub use crate::llm::{DynLlmClient, LlmClient, LlmRequest, LlmResponse, LocalEchoClient};
+pub use crate::message::StructuredMessage;
+pub use crate::runtime::{FlowExecution, FlowExecutor};
+#[cfg(feature = "redis-store")]
+pub use crate::state::RedisStore;
+pub use crate::state::{ContextStore, FlowContext, MemoryStore, SessionContext};
+pub use crate::tools::orchestrator::{
+    MapStep, ToolOrchestrator, ToolPipeline, ToolStep, ToolStrategy,
+};
+pub use crate::tools::{Tool, ToolInvocation, ToolRegistry};
+pub use crate::GraphConfig;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    struct Greeter;
+
+    #[async_trait]
+    impl Agent for Greeter {
+        fn name(&self) -> &'static str {
+            "greeter"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Finish {
+                message: Some(AgentMessage::system(format!("hello, {}", message.content))),
+            })
+        }
+    }
+
+    /// 只使用 prelude 导出的类型完成一次运行
+    #[tokio::test]
+    async fn test_prelude_covers_basic_run() {
+        let mut agents = AgentRegistry::new();
+        register_agent("greeter", Arc::new(Greeter), &mut agents);
+        let mut builder = FlowBuilder::new("hello");
+        builder
+            .add_agent_node("greet", "greeter")
+            .set_start("greet");
+
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let execution: FlowExecution = executor
+            .start(ctx, AgentMessage::user("world"))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_message.unwrap().content, "hello, world");
+    }
+}
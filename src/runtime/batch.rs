@@ -0,0 +1,239 @@
+//! 批量执行：以受限并发对多条输入运行同一流程，并汇总每条输入的结果
+
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::agent::AgentMessage;
+use crate::error::FrameworkError;
+use crate::state::{FlowContext, MemoryStore};
+
+/// 为第 `index` 条输入创建上下文
+pub type BatchContextFactory = dyn Fn(usize) -> Arc<FlowContext> + Send + Sync;
+
+/// 批量执行参数
+#[derive(Clone)]
+pub struct BatchOptions {
+    /// 同时执行的输入数，默认 4
+    pub concurrency: usize,
+    /// 任一输入失败后取消执行中的输入并跳过剩余输入
+    pub abort_on_failure: bool,
+    context_factory: Option<Arc<BatchContextFactory>>,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            abort_on_failure: false,
+            context_factory: None,
+        }
+    }
+}
+
+impl BatchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_abort_on_failure(mut self, enabled: bool) -> Self {
+        self.abort_on_failure = enabled;
+        self
+    }
+
+    /// 自定义每条输入的上下文；默认每条输入使用独立的 `MemoryStore`
+    pub fn with_context_factory<F>(mut self, factory: F) -> Self
+    where
+        F: Fn(usize) -> Arc<FlowContext> + Send + Sync + 'static,
+    {
+        self.context_factory = Some(Arc::new(factory));
+        self
+    }
+
+    pub(super) fn context(&self, index: usize) -> Arc<FlowContext> {
+        match &self.context_factory {
+            Some(factory) => factory(index),
+            None => Arc::new(FlowContext::new(Arc::new(MemoryStore::new()))),
+        }
+    }
+}
+
+/// 单条输入的执行状态
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Succeeded,
+    Failed,
+    /// 提前终止时未开始或被取消
+    Skipped,
+}
+
+/// 单条输入的执行结果
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchItem {
+    /// 输入序号
+    pub index: usize,
+    pub status: BatchStatus,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_node: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<AgentMessage>,
+    /// 失败原因，以及运行中已被处理的节点错误
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FrameworkError>,
+}
+
+impl BatchItem {
+    pub(super) fn skipped(index: usize) -> Self {
+        Self {
+            index,
+            status: BatchStatus::Skipped,
+            duration_ms: 0,
+            last_node: None,
+            output: None,
+            errors: Vec::new(),
+        }
+    }
+}
+
+/// 批量执行报告
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchReport {
+    /// 按输入顺序排列
+    pub items: Vec<BatchItem>,
+    pub duration_ms: u64,
+    /// 是否因失败提前终止
+    pub aborted: bool,
+}
+
+impl BatchReport {
+    pub fn succeeded(&self) -> usize {
+        self.count(BatchStatus::Succeeded)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(BatchStatus::Failed)
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(BatchStatus::Skipped)
+    }
+
+    /// 失败的输入
+    pub fn failures(&self) -> impl Iterator<Item = &BatchItem> {
+        self.items
+            .iter()
+            .filter(|item| item.status == BatchStatus::Failed)
+    }
+
+    fn count(&self, status: BatchStatus) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.status == status)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentAction, AgentContext};
+    use crate::error::{AgentFlowError, Result};
+    use crate::testing;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// 记录同时执行的输入数，内容为 `bad` 时失败
+    #[derive(Default)]
+    struct ScoringAgent {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Agent for ScoringAgent {
+        fn name(&self) -> &'static str {
+            "scoring"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            if message.content == "bad" {
+                return Err(AgentFlowError::Other(anyhow::anyhow!("cannot score")));
+            }
+            Ok(AgentAction::Next {
+                target: "done".into(),
+                message: AgentMessage::user(message.content.to_uppercase()),
+            })
+        }
+    }
+
+    fn inputs(contents: &[&str]) -> Vec<AgentMessage> {
+        contents.iter().map(|c| AgentMessage::user(*c)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_run_batch() {
+        let agent = Arc::new(ScoringAgent::default());
+        let executor = testing::executor("score", vec![("scoring", agent.clone())], |builder| {
+            builder
+                .add_agent_node("score", "scoring")
+                .add_terminal_node("done")
+                .set_start("score");
+        });
+        let report = executor
+            .run_batch(
+                inputs(&["a", "bad", "c", "d", "e"]),
+                BatchOptions::new().with_concurrency(2),
+            )
+            .await;
+        assert_eq!(agent.peak.load(Ordering::SeqCst), 2);
+        assert_eq!((report.succeeded(), report.failed()), (4, 1));
+        assert!(!report.aborted);
+        let outputs: Vec<_> = report
+            .items
+            .iter()
+            .map(|item| item.output.as_ref().map(|m| m.content.as_str()))
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![Some("A"), None, Some("C"), Some("D"), Some("E")]
+        );
+        let failure = report.failures().next().unwrap();
+        assert_eq!(failure.index, 1);
+        assert!(failure.errors[0].message.contains("cannot score"));
+
+        let report = executor
+            .run_batch(
+                inputs(&["a", "bad", "c"]),
+                BatchOptions::new()
+                    .with_concurrency(1)
+                    .with_abort_on_failure(true),
+            )
+            .await;
+        assert!(report.aborted);
+        let statuses: Vec<_> = report.items.iter().map(|item| item.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                BatchStatus::Succeeded,
+                BatchStatus::Failed,
+                BatchStatus::Skipped
+            ]
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! 运行交互通道：向外推送节点事件和 LLM 输出片段，向内接收人工消息
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc, Mutex};
+
+use super::progress::ProgressUpdate;
+use super::token_sink::{stream_to_sink, TokenSink};
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::guardrails::PiiRedactor;
+use crate::llm::DynLlmClient;
+
+/// 推送给前端的运行事件
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RunUpdate {
+    /// 开始执行节点
+    NodeStarted { node: String, source: String },
+    /// LLM 流式输出片段
+    Chunk { agent: String, content: String },
+    /// 节点完成后的运行进度，需要配置 `FlowExecutor::with_progress_reporter`
+    Progress(ProgressUpdate),
+    /// 长时间任务（如图片生成）的轮询进度
+    TaskProgress {
+        node: String,
+        task_id: String,
+        status: String,
+        elapsed_ms: u64,
+    },
+    /// 等待人工输入，`message` 为需要人工处理的内容
+    AwaitingInput {
+        agent: String,
+        message: AgentMessage,
+    },
+    /// 外部事件节点等待投递，`token` 用于 `FlowExecutor::deliver_event`
+    AwaitingEvent { node: String, token: String },
+    /// 运行结束
+    Finished {
+        node: String,
+        message: Option<AgentMessage>,
+    },
+    /// 运行失败
+    Failed { error: String },
+}
+
+impl RunUpdate {
+    /// 是否为最后一个事件
+    pub fn is_final(&self) -> bool {
+        matches!(self, RunUpdate::Finished { .. } | RunUpdate::Failed { .. })
+    }
+}
+
+struct ChannelInner {
+    updates: broadcast::Sender<RunUpdate>,
+    /// 最近发布的事件，供晚订阅的一方回放
+    history: parking_lot::Mutex<VecDeque<RunUpdate>>,
+    capacity: usize,
+    input_tx: mpsc::UnboundedSender<AgentMessage>,
+    input_rx: Mutex<mpsc::UnboundedReceiver<AgentMessage>>,
+}
+
+/// 单次运行的双向通道
+///
+/// 通过 `FlowContext::with_channel` 挂到上下文后，执行器推送节点事件，
+/// 配置驱动 Agent 推送 LLM 片段，`user_proxy` Agent 等待人工消息。
+#[derive(Clone)]
+pub struct RunChannel {
+    inner: Arc<ChannelInner>,
+    redactor: Option<Arc<PiiRedactor>>,
+}
+
+impl RunChannel {
+    /// `capacity` 为事件缓冲大小，订阅方落后超过该数量时会丢失较早的事件，
+    /// 同时也是 `subscribe_with_history` 回放的最近事件数
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let (updates, _) = broadcast::channel(capacity);
+        let (input_tx, input_rx) = mpsc::unbounded_channel();
+        Self {
+            inner: Arc::new(ChannelInner {
+                updates,
+                history: parking_lot::Mutex::new(VecDeque::new()),
+                capacity,
+                input_tx,
+                input_rx: Mutex::new(input_rx),
+            }),
+            redactor: None,
+        }
+    }
+
+    /// 共享同一通道，但推送的事件先经过 PII 脱敏
+    ///
+    /// 流式片段逐个脱敏，跨片段的敏感信息无法识别。
+    pub fn with_redactor(&self, redactor: Arc<PiiRedactor>) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            redactor: Some(redactor),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RunUpdate> {
+        self.inner.updates.subscribe()
+    }
+
+    /// 返回最近发布的事件并订阅之后的事件，两者之间不重不漏
+    ///
+    /// 运行已结束时历史中最后一个事件为结束或失败事件。
+    pub fn subscribe_with_history(&self) -> (Vec<RunUpdate>, broadcast::Receiver<RunUpdate>) {
+        let history = self.inner.history.lock();
+        (history.iter().cloned().collect(), self.subscribe())
+    }
+
+    pub fn publish(&self, mut update: RunUpdate) {
+        if let Some(redactor) = &self.redactor {
+            match &mut update {
+                RunUpdate::Chunk { content, .. } => {
+                    if let Cow::Owned(redacted) = redactor.redact(content) {
+                        *content = redacted;
+                    }
+                }
+                RunUpdate::AwaitingInput { message, .. }
+                | RunUpdate::Finished {
+                    message: Some(message),
+                    ..
+                } => redactor.redact_message(message),
+                RunUpdate::Failed { error } => {
+                    if let Cow::Owned(redacted) = redactor.redact(error) {
+                        *error = redacted;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut history = self.inner.history.lock();
+        if history.len() == self.inner.capacity {
+            history.pop_front();
+        }
+        history.push_back(update.clone());
+        // 没有订阅者时忽略
+        let _ = self.inner.updates.send(update);
+    }
+
+    /// 提交人工消息，由等待输入的节点消费
+    pub fn send_input(&self, message: AgentMessage) -> Result<()> {
+        self.inner
+            .input_tx
+            .send(message)
+            .map_err(|_| AgentFlowError::Other(anyhow::anyhow!("run channel is closed")))
+    }
+
+    /// 等待下一条人工消息
+    pub async fn next_input(&self) -> Option<AgentMessage> {
+        self.inner.input_rx.lock().await.recv().await
+    }
+
+    /// 包装 LLM 客户端，把流式输出片段同步推送到通道
+    pub fn stream_llm(&self, client: DynLlmClient, agent: impl Into<String>) -> DynLlmClient {
+        stream_to_sink(client, Arc::new(self.clone()), agent)
+    }
+}
+
+impl TokenSink for RunChannel {
+    fn on_token(&self, agent: &str, token: &str) {
+        self.publish(RunUpdate::Chunk {
+            agent: agent.to_string(),
+            content: token.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::builtin::UserProxyAgent;
+    use crate::agent::{register_agent, AgentRegistry};
+    use crate::flow::FlowBuilder;
+    use crate::llm::{LlmRequest, LocalEchoClient};
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_channel_streams_chunks() {
+        let channel = RunChannel::new(64);
+        let mut updates = channel.subscribe();
+        let client = channel.stream_llm(Arc::new(LocalEchoClient), "writer");
+        let request = LlmRequest {
+            system: None,
+            user: "hi".into(),
+            temperature: 0.0,
+            metadata: None,
+            content: Vec::new(),
+            params: Default::default(),
+        };
+        let streamed: Vec<_> = client.complete_stream(request).collect().await;
+        assert!(streamed.iter().all(|chunk| chunk.is_ok()));
+
+        let mut content = String::new();
+        while let Ok(RunUpdate::Chunk {
+            agent,
+            content: part,
+        }) = updates.try_recv()
+        {
+            assert_eq!(agent, "writer");
+            content.push_str(&part);
+        }
+        assert!(!content.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_user_proxy_waits_for_human_input() {
+        let mut agents = AgentRegistry::new();
+        register_agent(
+            "user_proxy",
+            Arc::new(UserProxyAgent::new("done")),
+            &mut agents,
+        );
+        let mut builder = FlowBuilder::new("chat");
+        builder
+            .add_agent_node("ask", "user_proxy")
+            .add_terminal_node("done")
+            .set_start("ask");
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new());
+
+        let channel = RunChannel::new(64);
+        let mut updates = channel.subscribe();
+        let ctx =
+            Arc::new(FlowContext::new(Arc::new(MemoryStore::new())).with_channel(channel.clone()));
+        let run = tokio::spawn(async move {
+            executor
+                .start(ctx, AgentMessage::system("approve the draft?"))
+                .await
+        });
+
+        loop {
+            if let RunUpdate::AwaitingInput { message, .. } = updates.recv().await.unwrap() {
+                assert_eq!(message.content, "approve the draft?");
+                break;
+            }
+        }
+        channel.send_input(AgentMessage::user("approved")).unwrap();
+
+        let execution = run.await.unwrap().unwrap();
+        assert_eq!(execution.last_node, "done");
+        assert_eq!(execution.last_message.unwrap().content, "approved");
+        let mut last = None;
+        while let Ok(update) = updates.try_recv() {
+            last = Some(update);
+        }
+        assert!(matches!(last, Some(RunUpdate::Finished { node, .. }) if node == "done"));
+    }
+}
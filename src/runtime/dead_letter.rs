@@ -0,0 +1,183 @@
+//! 死信：Agent/Tool 节点失败时保留失败的事件和错误，转到死信节点而不是终止整个运行
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::history::now_millis;
+use super::types::FlowEvent;
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, FrameworkError, Result};
+
+/// metadata 中死信的字段名
+pub const DEAD_LETTER_FIELD: &str = "dead_letter";
+
+/// 一条死信：失败节点收到的事件和错误
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub flow: String,
+    /// 失败的节点
+    pub node: String,
+    /// 把事件发给失败节点的上游节点
+    pub source: String,
+    pub trace_id: String,
+    pub iterations: u32,
+    /// 失败节点收到的消息
+    pub message: AgentMessage,
+    pub error: FrameworkError,
+    pub failed_at: u64,
+}
+
+impl DeadLetter {
+    pub(super) fn new(flow: &str, event: FlowEvent, error: AgentFlowError) -> Self {
+        Self {
+            flow: flow.to_string(),
+            node: event.node,
+            source: event.source,
+            trace_id: event.trace_id,
+            iterations: event.iterations,
+            message: std::sync::Arc::unwrap_or_clone(event.message),
+            error: error.into(),
+            failed_at: now_millis(),
+        }
+    }
+
+    /// 发给死信节点的消息：内容为错误说明，metadata 的 `dead_letter` 字段为完整死信
+    pub fn to_message(&self) -> AgentMessage {
+        let mut message = AgentMessage::system(format!(
+            "node `{}` failed: {}",
+            self.node, self.error.message
+        ));
+        message.from = self.node.clone();
+        message.metadata = serde_json::to_value(self)
+            .ok()
+            .map(|letter| serde_json::json!({ DEAD_LETTER_FIELD: letter }));
+        message
+    }
+}
+
+/// 死信接收端，例如写入告警系统或待人工处理的队列
+#[async_trait]
+pub trait DeadLetterSink: Send + Sync {
+    async fn send(&self, letter: &DeadLetter) -> Result<()>;
+}
+
+/// 内存死信接收端
+#[derive(Default)]
+pub struct MemoryDeadLetterSink {
+    letters: Mutex<Vec<DeadLetter>>,
+}
+
+impl MemoryDeadLetterSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn letters(&self) -> Vec<DeadLetter> {
+        self.letters.lock().await.clone()
+    }
+}
+
+#[async_trait]
+impl DeadLetterSink for MemoryDeadLetterSink {
+    async fn send(&self, letter: &DeadLetter) -> Result<()> {
+        self.letters.lock().await.push(letter.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentAction, AgentContext};
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::testing;
+    use std::sync::Arc;
+
+    /// `fail` 节点调用失败，其余节点转到 `done`
+    struct PaymentAgent;
+
+    #[async_trait]
+    impl Agent for PaymentAgent {
+        fn name(&self) -> &'static str {
+            "payment"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            match ctx.flow().node() {
+                Some("split") => Ok(AgentAction::Branch {
+                    branches: [("ok".into(), message.clone()), ("fail".into(), message)].into(),
+                }),
+                Some("fail") => Err(AgentFlowError::Other(anyhow::anyhow!(
+                    "payment gateway unavailable"
+                ))),
+                _ => Ok(AgentAction::Next {
+                    target: "done".into(),
+                    message,
+                }),
+            }
+        }
+    }
+
+    fn executor(start: &str, dead_letter: Option<&str>) -> FlowExecutor {
+        testing::executor(
+            "checkout",
+            vec![("payment", Arc::new(PaymentAgent))],
+            |builder| {
+                builder
+                    .add_agent_node("split", "payment")
+                    .add_agent_node("ok", "payment")
+                    .add_agent_node("fail", "payment")
+                    .add_terminal_node("done")
+                    .add_terminal_node("alert")
+                    .set_start(start);
+                if let Some(node) = dead_letter {
+                    builder.set_dead_letter(node);
+                }
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_failed_node_routes_to_dead_letter() {
+        let ctx = || Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+
+        let execution = executor("fail", Some("alert"))
+            .start(ctx(), AgentMessage::user("order-7"))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "alert");
+        let metadata = execution.last_message.unwrap().metadata.unwrap();
+        let letter = &metadata[DEAD_LETTER_FIELD];
+        assert_eq!(letter["node"], "fail");
+        assert_eq!(letter["message"]["content"], "order-7");
+        assert!(letter["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("payment gateway unavailable"));
+        assert_eq!(execution.dead_letters.len(), 1);
+
+        let sink = Arc::new(MemoryDeadLetterSink::new());
+        let execution = executor("split", None)
+            .with_dead_letter_sink(sink.clone())
+            .start(ctx(), AgentMessage::user("order-8"))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "done");
+        assert_eq!(execution.dead_letters[0].node, "fail");
+        assert_eq!(sink.letters().await[0].message.content, "order-8");
+
+        let Err(error) = executor("fail", None)
+            .start(ctx(), AgentMessage::user("order-9"))
+            .await
+        else {
+            panic!("expected the run to fail without dead-letter handling");
+        };
+        assert!(error.to_string().contains("payment gateway unavailable"));
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::agent::{AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::flow::constants::fields;
+use crate::llm::{DynLlmClient, LlmRequest};
+use crate::state::FlowContext;
+
+use super::types::FlowExecution;
+
+const MAX_DECISIONS: usize = 10;
+const MAX_OUTPUT_CHARS: usize = 500;
+
+/// 默认摘要模板
+pub const DEFAULT_DIGEST_TEMPLATE: &str =
+    "Flow `{flow}` {outcome} at `{last_node}` after {steps} steps in {duration_ms}ms. \
+Agent turns: {agent_turns}, tool calls: {tool_calls}, tokens: {tokens}. \
+Decisions: {decisions}. Anomalies: {anomalies}. Output: {output}";
+
+const DEFAULT_LLM_PROMPT: &str = "You summarize workflow runs for operators. \
+Write a concise digest (at most 5 sentences) covering the outcome, key decisions, costs and anomalies.";
+
+/// 运行成本统计
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DigestCosts {
+    pub agent_turns: usize,
+    pub tool_calls: usize,
+    /// 来自消息元数据 `usage.total_tokens` 的累计值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_tokens: Option<u64>,
+}
+
+/// 运行摘要
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunDigest {
+    pub flow_name: String,
+    /// `completed` / `failed`
+    pub outcome: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_node: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    pub steps: usize,
+    pub key_decisions: Vec<String>,
+    pub costs: DigestCosts,
+    pub anomalies: Vec<String>,
+    pub duration_ms: u64,
+    /// 模板或 LLM 生成的可读摘要
+    pub summary: String,
+}
+
+impl RunDigest {
+    /// 从运行历史与结果构建摘要（`summary` 为空，由 `DigestMode` 填充）
+    pub fn collect(
+        flow_name: &str,
+        history: &[AgentMessage],
+        result: std::result::Result<&FlowExecution, &AgentFlowError>,
+        duration: Duration,
+    ) -> Self {
+        let mut key_decisions = Vec::new();
+        let mut costs = DigestCosts::default();
+        let mut anomalies = Vec::new();
+
+        for message in history {
+            let payload: Option<Value> = serde_json::from_str(&message.content).ok();
+            match message.role {
+                MessageRole::Tool => {
+                    costs.tool_calls += 1;
+                    if payload
+                        .as_ref()
+                        .and_then(|p| p.get("success"))
+                        .and_then(Value::as_bool)
+                        == Some(false)
+                    {
+                        anomalies.push(format!("tool `{}` reported failure", message.from));
+                    }
+                }
+                MessageRole::Agent => costs.agent_turns += 1,
+                _ => {}
+            }
+
+            if let Some(tokens) = message
+                .metadata
+                .as_ref()
+                .and_then(|m| m.pointer("/usage/total_tokens"))
+                .and_then(Value::as_u64)
+            {
+                *costs.total_tokens.get_or_insert(0) += tokens;
+            }
+
+            let Some(payload) = payload else {
+                continue;
+            };
+            if let Some(error) = payload
+                .get(fields::VALIDATION_ERROR)
+                .and_then(Value::as_str)
+            {
+                anomalies.push(format!(
+                    "`{}` produced invalid output: {}",
+                    message.from, error
+                ));
+            }
+            if let Some(reason) = payload
+                .get(fields::REFUSAL)
+                .and_then(|refusal| refusal.get("reason"))
+                .and_then(Value::as_str)
+            {
+                anomalies.push(format!("`{}` was refused: {}", message.from, reason));
+            }
+            if let Some(route) = payload.get(fields::ROUTE).and_then(Value::as_str) {
+                let decision = match payload.get(fields::ROUTE_REASON).and_then(Value::as_str) {
+                    Some(reason) => format!("{} -> {} ({})", message.from, route, reason),
+                    None => format!("{} -> {}", message.from, route),
+                };
+                if key_decisions.last() != Some(&decision) {
+                    key_decisions.push(decision);
+                }
+            }
+        }
+        key_decisions.truncate(MAX_DECISIONS);
+
+        let (outcome, last_node, output) = match result {
+            Ok(execution) => {
+                anomalies.extend(
+                    execution
+                        .errors
+                        .iter()
+                        .map(|e| format!("{}: {}", e.code, e.message)),
+                );
+                (
+                    "completed",
+                    Some(execution.last_node.clone()),
+                    execution
+                        .last_message
+                        .as_ref()
+                        .map(|m| truncate(&m.content, MAX_OUTPUT_CHARS)),
+                )
+            }
+            Err(error) => {
+                anomalies.push(error.to_string());
+                ("failed", history.last().map(|m| m.from.clone()), None)
+            }
+        };
+
+        Self {
+            flow_name: flow_name.to_string(),
+            outcome: outcome.to_string(),
+            last_node,
+            output,
+            steps: history.len(),
+            key_decisions,
+            costs,
+            anomalies,
+            duration_ms: duration.as_millis() as u64,
+            summary: String::new(),
+        }
+    }
+
+    /// 按模板渲染摘要
+    pub fn render(&self, template: &str) -> String {
+        let join = |items: &[String]| {
+            if items.is_empty() {
+                "none".to_string()
+            } else {
+                items.join("; ")
+            }
+        };
+        template
+            .replace("{flow}", &self.flow_name)
+            .replace("{outcome}", &self.outcome)
+            .replace("{last_node}", self.last_node.as_deref().unwrap_or("-"))
+            .replace("{steps}", &self.steps.to_string())
+            .replace("{duration_ms}", &self.duration_ms.to_string())
+            .replace("{agent_turns}", &self.costs.agent_turns.to_string())
+            .replace("{tool_calls}", &self.costs.tool_calls.to_string())
+            .replace(
+                "{tokens}",
+                &self
+                    .costs
+                    .total_tokens
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| "n/a".to_string()),
+            )
+            .replace("{decisions}", &join(&self.key_decisions))
+            .replace("{anomalies}", &join(&self.anomalies))
+            .replace("{output}", self.output.as_deref().unwrap_or("-"))
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}...", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+/// 摘要生成方式
+#[derive(Clone)]
+pub enum DigestMode {
+    /// 模板渲染，占位符见 `DEFAULT_DIGEST_TEMPLATE`
+    Template(String),
+    /// 调用 LLM 生成，失败时退回默认模板
+    Llm {
+        client: DynLlmClient,
+        prompt: Option<String>,
+    },
+}
+
+impl Default for DigestMode {
+    fn default() -> Self {
+        DigestMode::Template(DEFAULT_DIGEST_TEMPLATE.to_string())
+    }
+}
+
+/// 摘要发布目标
+#[async_trait]
+pub trait DigestSink: Send + Sync {
+    async fn publish(&self, digest: &RunDigest) -> Result<()>;
+}
+
+/// 事件总线发布（tokio broadcast）
+#[derive(Clone)]
+pub struct BroadcastDigestSink {
+    sender: broadcast::Sender<RunDigest>,
+}
+
+impl BroadcastDigestSink {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RunDigest> {
+        self.sender.subscribe()
+    }
+}
+
+#[async_trait]
+impl DigestSink for BroadcastDigestSink {
+    async fn publish(&self, digest: &RunDigest) -> Result<()> {
+        // 没有订阅者时忽略
+        let _ = self.sender.send(digest.clone());
+        Ok(())
+    }
+}
+
+/// Webhook 发布（POST JSON）
+#[derive(Clone)]
+pub struct WebhookDigestSink {
+    url: String,
+    headers: HashMap<String, String>,
+    client: reqwest::Client,
+}
+
+impl WebhookDigestSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            headers: HashMap::new(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(key.into(), value.into());
+        self
+    }
+}
+
+#[async_trait]
+impl DigestSink for WebhookDigestSink {
+    async fn publish(&self, digest: &RunDigest) -> Result<()> {
+        let mut request = self.client.post(&self.url).json(digest);
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Digest webhook failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Digest webhook returned {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 运行结束后的摘要钩子
+#[derive(Clone, Default)]
+pub struct RunDigestHook {
+    mode: DigestMode,
+    sinks: Vec<Arc<dyn DigestSink>>,
+}
+
+impl RunDigestHook {
+    pub fn new(mode: DigestMode) -> Self {
+        Self {
+            mode,
+            sinks: Vec::new(),
+        }
+    }
+
+    pub fn with_template(template: impl Into<String>) -> Self {
+        Self::new(DigestMode::Template(template.into()))
+    }
+
+    pub fn with_llm(client: DynLlmClient, prompt: Option<String>) -> Self {
+        Self::new(DigestMode::Llm { client, prompt })
+    }
+
+    pub fn with_sink(mut self, sink: Arc<dyn DigestSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// 生成摘要并发布；发布失败只记录日志，不影响运行结果
+    pub async fn on_finish(
+        &self,
+        flow_name: &str,
+        ctx: &FlowContext,
+        result: std::result::Result<&FlowExecution, &AgentFlowError>,
+        duration: Duration,
+    ) -> RunDigest {
+        let mut digest = RunDigest::collect(flow_name, &ctx.history(), result, duration);
+        digest.summary = self.summarize(&digest).await;
+        for sink in &self.sinks {
+            if let Err(err) = sink.publish(&digest).await {
+                warn!(flow = flow_name, %err, "Failed to publish run digest");
+            }
+        }
+        digest
+    }
+
+    async fn summarize(&self, digest: &RunDigest) -> String {
+        match &self.mode {
+            DigestMode::Template(template) => digest.render(template),
+            DigestMode::Llm { client, prompt } => {
+                let request = LlmRequest {
+                    system: Some(
+                        prompt
+                            .clone()
+                            .unwrap_or_else(|| DEFAULT_LLM_PROMPT.to_string()),
+                    ),
+                    user: serde_json::to_string_pretty(digest).unwrap_or_default(),
+                    temperature: 0.2,
+                    metadata: None,
+                    content: Vec::new(),
+                    params: Default::default(),
+                };
+                match client.complete(request).await {
+                    Ok(response) => response.content,
+                    Err(err) => {
+                        warn!(%err, "LLM digest failed, falling back to template");
+                        digest.render(DEFAULT_DIGEST_TEMPLATE)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentRegistry};
+    use crate::flow::FlowBuilder;
+    use crate::runtime::FlowExecutor;
+    use crate::state::MemoryStore;
+    use crate::tools::ToolRegistry;
+    use serde_json::json;
+
+    struct RouterAgent;
+
+    #[async_trait]
+    impl Agent for RouterAgent {
+        fn name(&self) -> &'static str {
+            "router"
+        }
+
+        async fn on_message(
+            &self,
+            _message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let mut message = AgentMessage::system(
+                json!({ "route": "done", "route_reason": "enough info" }).to_string(),
+            );
+            message.role = MessageRole::Agent;
+            message.from = "router".to_string();
+            message.metadata = Some(json!({ "usage": { "total_tokens": 42 } }));
+            Ok(AgentAction::Next {
+                target: "done".to_string(),
+                message,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_digest_published_after_run() {
+        let mut agents = AgentRegistry::new();
+        register_agent("router", Arc::new(RouterAgent), &mut agents);
+        let mut builder = FlowBuilder::new("triage");
+        builder
+            .add_agent_node("route", "router")
+            .add_terminal_node("done")
+            .set_start("route")
+            .connect("route", "done");
+
+        let bus = BroadcastDigestSink::new(8);
+        let mut receiver = bus.subscribe();
+        let hook = RunDigestHook::with_template("{flow}:{outcome}:{decisions}:{tokens}")
+            .with_sink(Arc::new(bus));
+        let executor =
+            FlowExecutor::new(builder.build(), agents, ToolRegistry::new()).with_run_digest(hook);
+
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let execution = executor
+            .start(ctx, AgentMessage::user("hello"))
+            .await
+            .unwrap();
+        let digest = execution.digest.unwrap();
+        assert_eq!(
+            digest.summary,
+            "triage:completed:router -> done (enough info):42"
+        );
+        assert_eq!(digest.costs.agent_turns, 1);
+
+        let published = receiver.recv().await.unwrap();
+        assert_eq!(published.summary, digest.summary);
+    }
+
+    #[test]
+    fn test_failed_run_digest() {
+        let error = AgentFlowError::UnknownNode("missing".to_string());
+        let history = vec![AgentMessage::user("hi")];
+        let digest = RunDigest::collect("f", &history, Err(&error), Duration::from_millis(5));
+        assert_eq!(digest.outcome, "failed");
+        assert_eq!(digest.anomalies, vec!["unknown node `missing` in flow"]);
+        assert!(digest.render(DEFAULT_DIGEST_TEMPLATE).contains("Output: -"));
+    }
+}
@@ -0,0 +1,915 @@
+//! 分布式执行：`FlowEvent` 通过队列分发给远程 worker 处理
+//!
+//! 协调方（`DistributedExecutor`）只投递起始事件并统计结果；worker（`FlowWorker`）处理事件后
+//! 把新产生的事件重新投递到任务队列，由任意 worker 继续处理。Join/Loop 协调状态保存在所有
+//! 进程共享的 `ContextStore` 中，因此需要支持原子 `update` 的存储（如 `RedisStore`）。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+
+use super::executor::FlowExecutor;
+use super::queue::{EventQueue, EventSender, MemoryEventQueue};
+use super::state::{clear_coordination, coordination_prefix, SharedState};
+use super::types::{FlowEvent, FlowExecution, TaskResult};
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::state::{ContextStore, FlowContext};
+
+/// 所有 worker 竞争消费的任务队列
+pub const TASK_TOPIC: &str = "agentflow.tasks";
+
+/// 单次运行的结果队列
+fn results_topic(run_id: &str) -> String {
+    format!("agentflow.results.{run_id}")
+}
+
+/// 运行结束标记，worker 据此丢弃该运行剩余的事件
+fn done_key(run_id: &str) -> String {
+    format!("{}done", coordination_prefix(run_id))
+}
+
+/// 分布式运行的默认超时时间
+pub const DEFAULT_RUN_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// 订阅取出的消息和确认凭据
+pub struct WorkItem {
+    pub payload: String,
+    pub receipt: String,
+}
+
+/// 消息队列
+#[async_trait]
+pub trait WorkQueue: Send + Sync {
+    async fn publish(&self, topic: &str, payload: String) -> Result<()>;
+
+    /// 订阅队列；同一 topic 的多个订阅者竞争消费，每条消息只投递给其中一个
+    async fn subscribe(&self, topic: &str) -> Result<BoxStream<'static, Result<WorkItem>>>;
+
+    /// 消息处理完成后确认；持久化队列会重新投递未确认的消息
+    async fn ack(&self, _topic: &str, _receipt: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// 清理不再使用的 topic（如单次运行的结果队列）
+    async fn remove(&self, _topic: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct MemoryTopic {
+    sender: mpsc::UnboundedSender<String>,
+    receiver: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<String>>>,
+}
+
+/// 进程内队列，适合测试和单机多 worker
+#[derive(Clone, Default)]
+pub struct MemoryWorkQueue {
+    topics: Arc<Mutex<HashMap<String, MemoryTopic>>>,
+}
+
+impl MemoryWorkQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn topic(&self, name: &str) -> MemoryTopic {
+        self.topics
+            .lock()
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                let (sender, receiver) = mpsc::unbounded_channel();
+                MemoryTopic {
+                    sender,
+                    receiver: Arc::new(tokio::sync::Mutex::new(receiver)),
+                }
+            })
+            .clone()
+    }
+}
+
+#[async_trait]
+impl WorkQueue for MemoryWorkQueue {
+    async fn publish(&self, topic: &str, payload: String) -> Result<()> {
+        self.topic(topic)
+            .sender
+            .send(payload)
+            .map_err(|_| AgentFlowError::Other(anyhow!("queue `{}` closed", topic)))
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<BoxStream<'static, Result<WorkItem>>> {
+        let receiver = self.topic(topic).receiver;
+        Ok(futures::stream::unfold(receiver, |receiver| async move {
+            let payload = receiver.lock().await.recv().await?;
+            let item = WorkItem {
+                payload,
+                receipt: String::new(),
+            };
+            Some((Ok(item), receiver))
+        })
+        .boxed())
+    }
+
+    async fn remove(&self, topic: &str) -> Result<()> {
+        self.topics.lock().remove(topic);
+        Ok(())
+    }
+}
+
+/// Redis Streams 队列（需启用 `redis-queue` feature）
+///
+/// 每个 topic 对应一个 stream，订阅者通过同一个消费组竞争消费。消息处理完成后才 XACK 并
+/// XDEL；超过 `claim_idle` 仍未确认的消息（如 worker 崩溃）由其他订阅者重新领取。
+#[cfg(feature = "redis-queue")]
+pub struct RedisStreamQueue {
+    client: ::redis::Client,
+    group: String,
+    claim_idle: Duration,
+}
+
+#[cfg(feature = "redis-queue")]
+impl RedisStreamQueue {
+    pub fn new(client: ::redis::Client) -> Self {
+        Self {
+            client,
+            group: "agentflow".to_string(),
+            claim_idle: Duration::from_secs(300),
+        }
+    }
+
+    pub fn open(url: &str) -> Result<Self> {
+        let client = ::redis::Client::open(url)
+            .map_err(|e| AgentFlowError::Other(anyhow!("Invalid Redis url `{}`: {}", url, e)))?;
+        Ok(Self::new(client))
+    }
+
+    /// 消费组名称（默认 `agentflow`）
+    pub fn with_group(mut self, group: impl Into<String>) -> Self {
+        self.group = group.into();
+        self
+    }
+
+    /// 未确认的消息超过该时间后可被其他订阅者领取（默认 5 分钟），应大于单个节点的处理时间
+    pub fn with_claim_idle(mut self, idle: Duration) -> Self {
+        self.claim_idle = idle;
+        self
+    }
+
+    async fn connection(&self) -> Result<::redis::aio::MultiplexedConnection> {
+        self.client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(redis_error)
+    }
+}
+
+/// 两次领取未确认消息之间的最小间隔
+#[cfg(feature = "redis-queue")]
+const CLAIM_INTERVAL: Duration = Duration::from_secs(5);
+
+#[cfg(feature = "redis-queue")]
+fn redis_error(error: ::redis::RedisError) -> AgentFlowError {
+    AgentFlowError::Other(anyhow!("Redis queue error: {}", error))
+}
+
+#[cfg(feature = "redis-queue")]
+#[async_trait]
+impl WorkQueue for RedisStreamQueue {
+    async fn publish(&self, topic: &str, payload: String) -> Result<()> {
+        use ::redis::AsyncCommands;
+        self.connection()
+            .await?
+            .xadd::<_, _, _, _, String>(topic, "*", &[("payload", payload)])
+            .await
+            .map_err(redis_error)?;
+        Ok(())
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<BoxStream<'static, Result<WorkItem>>> {
+        use ::redis::streams::{
+            StreamAutoClaimOptions, StreamAutoClaimReply, StreamId, StreamReadOptions,
+            StreamReadReply,
+        };
+        use ::redis::AsyncCommands;
+        use std::collections::VecDeque;
+
+        // 阻塞读取独占一个连接
+        let mut conn = self.connection().await?;
+        let created: ::redis::RedisResult<()> =
+            conn.xgroup_create_mkstream(topic, &self.group, "0").await;
+        if let Err(e) = created {
+            if e.code() != Some("BUSYGROUP") {
+                return Err(redis_error(e));
+            }
+        }
+
+        let topic = topic.to_string();
+        let consumer = crate::agent::message::uuid();
+        let group = self.group.clone();
+        let claim_idle = self.claim_idle.as_millis() as u64;
+        let state = (
+            conn,
+            VecDeque::<WorkItem>::new(),
+            None::<tokio::time::Instant>,
+        );
+        Ok(futures::stream::unfold(Some(state), move |state| {
+            let topic = topic.clone();
+            let group = group.clone();
+            let consumer = consumer.clone();
+            async move {
+                let (mut conn, mut buffer, mut last_claim) = state?;
+                loop {
+                    if let Some(item) = buffer.pop_front() {
+                        return Some((Ok(item), Some((conn, buffer, last_claim))));
+                    }
+                    // 先领取其他订阅者长时间未确认的消息，再读取新消息
+                    let entries: ::redis::RedisResult<Vec<StreamId>> =
+                        if last_claim.is_none_or(|at| at.elapsed() >= CLAIM_INTERVAL) {
+                            last_claim = Some(tokio::time::Instant::now());
+                            conn.xautoclaim_options::<_, _, _, _, _, StreamAutoClaimReply>(
+                                &topic,
+                                &group,
+                                &consumer,
+                                claim_idle,
+                                "0-0",
+                                StreamAutoClaimOptions::default().count(16),
+                            )
+                            .await
+                            .map(|reply| reply.claimed)
+                        } else {
+                            Ok(Vec::new())
+                        };
+                    let mut entries = match entries {
+                        Ok(entries) => entries,
+                        Err(e) => return Some((Err(redis_error(e)), None)),
+                    };
+                    if entries.is_empty() {
+                        let options = StreamReadOptions::default()
+                            .group(&group, &consumer)
+                            .count(16)
+                            .block(1_000);
+                        let reply: ::redis::RedisResult<Option<StreamReadReply>> =
+                            conn.xread_options(&[&topic], &[">"], &options).await;
+                        entries = match reply {
+                            Ok(reply) => reply
+                                .into_iter()
+                                .flat_map(|r| r.keys)
+                                .flat_map(|k| k.ids)
+                                .collect(),
+                            Err(e) => return Some((Err(redis_error(e)), None)),
+                        };
+                    }
+                    let mut malformed = Vec::new();
+                    for entry in entries {
+                        match entry.get::<String>("payload") {
+                            Some(payload) => buffer.push_back(WorkItem {
+                                payload,
+                                receipt: entry.id,
+                            }),
+                            None => malformed.push(entry.id),
+                        }
+                    }
+                    if !malformed.is_empty() {
+                        let removed: ::redis::RedisResult<()> = ::redis::pipe()
+                            .xack(&topic, &group, &malformed)
+                            .xdel(&topic, &malformed)
+                            .query_async(&mut conn)
+                            .await;
+                        if let Err(e) = removed {
+                            return Some((Err(redis_error(e)), None));
+                        }
+                    }
+                }
+            }
+        })
+        .boxed())
+    }
+
+    /// 确认后同时从 stream 中删除，避免任务队列无限增长
+    async fn ack(&self, topic: &str, receipt: &str) -> Result<()> {
+        let mut conn = self.connection().await?;
+        ::redis::pipe()
+            .xack(topic, &self.group, &[receipt])
+            .xdel(topic, &[receipt])
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(redis_error)
+    }
+
+    async fn remove(&self, topic: &str) -> Result<()> {
+        use ::redis::AsyncCommands;
+        self.connection()
+            .await?
+            .del::<_, ()>(topic)
+            .await
+            .map_err(redis_error)
+    }
+}
+
+/// NATS 队列（需启用 `nats-queue` feature），订阅者通过 queue group 竞争消费
+///
+/// NATS core 不持久化消息，也没有确认机制，worker 崩溃时正在处理的任务会丢失。
+#[cfg(feature = "nats-queue")]
+pub struct NatsWorkQueue {
+    client: async_nats::Client,
+    group: String,
+}
+
+#[cfg(feature = "nats-queue")]
+impl NatsWorkQueue {
+    pub fn new(client: async_nats::Client) -> Self {
+        Self {
+            client,
+            group: "agentflow".to_string(),
+        }
+    }
+
+    pub async fn connect(url: &str) -> Result<Self> {
+        let client = async_nats::connect(url)
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("NATS connect failed: {}", e)))?;
+        Ok(Self::new(client))
+    }
+
+    /// queue group 名称（默认 `agentflow`）
+    pub fn with_group(mut self, group: impl Into<String>) -> Self {
+        self.group = group.into();
+        self
+    }
+}
+
+#[cfg(feature = "nats-queue")]
+#[async_trait]
+impl WorkQueue for NatsWorkQueue {
+    async fn publish(&self, topic: &str, payload: String) -> Result<()> {
+        self.client
+            .publish(topic.to_string(), payload.into())
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("NATS publish failed: {}", e)))
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<BoxStream<'static, Result<WorkItem>>> {
+        let subscriber = self
+            .client
+            .queue_subscribe(topic.to_string(), self.group.clone())
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("NATS subscribe failed: {}", e)))?;
+        Ok(subscriber
+            .map(|message| {
+                Ok(WorkItem {
+                    payload: String::from_utf8_lossy(&message.payload).into_owned(),
+                    receipt: String::new(),
+                })
+            })
+            .boxed())
+    }
+}
+
+/// 投递给 worker 的任务
+#[derive(Serialize, Deserialize)]
+struct RemoteTask {
+    run_id: String,
+    flow: String,
+    event: FlowEvent,
+}
+
+/// worker 处理一个任务后回报的结果
+#[derive(Serialize, Deserialize)]
+struct RemoteOutcome {
+    /// 处理过程中新投递的事件数
+    spawned: usize,
+    #[serde(flatten)]
+    status: OutcomeStatus,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum OutcomeStatus {
+    Continue,
+    Finished {
+        node: String,
+        message: Option<AgentMessage>,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| AgentFlowError::Serialization(e.to_string()))
+}
+
+/// 分布式执行的协调方
+///
+/// 投递起始事件后等待 worker 回报，统计未完成的事件数；到达终点、出错或超时时标记运行结束，
+/// worker 随后丢弃该运行剩余的事件。
+pub struct DistributedExecutor {
+    executor: FlowExecutor,
+    queue: Arc<dyn WorkQueue>,
+    store: Arc<dyn ContextStore>,
+    timeout: Duration,
+}
+
+impl DistributedExecutor {
+    /// `executor` 只用于读取流程定义，各节点由 worker 上同名的流程执行
+    pub fn new(
+        executor: FlowExecutor,
+        queue: Arc<dyn WorkQueue>,
+        store: Arc<dyn ContextStore>,
+    ) -> Self {
+        Self {
+            executor,
+            queue,
+            store,
+            timeout: DEFAULT_RUN_TIMEOUT,
+        }
+    }
+
+    /// 整个运行的最长时间（默认 1 小时），超时后运行失败
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub async fn start(&self, initial: AgentMessage) -> Result<FlowExecution> {
+        let flow = self.executor.flow();
+        let run_id = crate::agent::message::uuid();
+        let results = results_topic(&run_id);
+        let mut outcomes = self.queue.subscribe(&results).await?;
+
+        let task = RemoteTask {
+            run_id: run_id.clone(),
+            flow: flow.name.clone(),
+            event: FlowEvent {
+                node: flow.start.clone(),
+                message: initial.into(),
+                iterations: 0,
+                trace_id: crate::agent::message::uuid(),
+                source: "__start__".to_string(),
+            },
+        };
+        self.queue.publish(TASK_TOPIC, encode(&task)?).await?;
+        tracing::info!(run_id = %run_id, flow = %flow.name, "distributed run started");
+
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let mut pending = 1usize;
+        let result = loop {
+            let item = match tokio::time::timeout_at(deadline, outcomes.next()).await {
+                Ok(Some(Ok(item))) => item,
+                Ok(Some(Err(err))) => break Err(err),
+                Ok(None) => {
+                    break Err(AgentFlowError::Other(anyhow!(
+                        "result queue for run `{}` closed",
+                        run_id
+                    )))
+                }
+                Err(_) => {
+                    break Err(AgentFlowError::Other(anyhow!(
+                        "distributed run `{}` timed out after {:?}",
+                        run_id,
+                        self.timeout
+                    )))
+                }
+            };
+            if let Err(err) = self.queue.ack(&results, &item.receipt).await {
+                tracing::warn!(run_id = %run_id, error = %err, "failed to ack distributed outcome");
+            }
+            let outcome: RemoteOutcome = match serde_json::from_str(&item.payload) {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    tracing::warn!(run_id = %run_id, error = %e, "ignoring malformed outcome");
+                    continue;
+                }
+            };
+            pending = (pending + outcome.spawned).saturating_sub(1);
+            match outcome.status {
+                OutcomeStatus::Finished { node, message } => {
+                    break Ok(FlowExecution {
+                        flow_name: flow.name.clone(),
+                        last_node: node,
+                        last_message: message,
+                        errors: Vec::new(),
+                        digest: None,
+                        explanations: Vec::new(),
+                        dead_letters: Vec::new(),
+                        progress: Vec::new(),
+                        outputs: Default::default(),
+                    })
+                }
+                OutcomeStatus::Failed { error } => {
+                    break Err(AgentFlowError::Other(anyhow!(
+                        "remote worker failed: {}",
+                        error
+                    )))
+                }
+                OutcomeStatus::Continue if pending == 0 => {
+                    break Err(AgentFlowError::Other(anyhow!(
+                        "flow finished without result"
+                    )))
+                }
+                OutcomeStatus::Continue => {}
+            }
+        };
+
+        self.finish(&run_id).await;
+        let mut execution = result?;
+        let ctx = FlowContext::new(Arc::clone(&self.store));
+        execution.outputs = flow
+            .extract_outputs(&ctx, execution.last_message.as_ref())
+            .await?;
+        Ok(execution)
+    }
+
+    async fn finish(&self, run_id: &str) {
+        if let Err(err) = self.store.set(&done_key(run_id), "1".to_string()).await {
+            tracing::warn!(run_id, error = %err, "failed to mark distributed run done");
+        }
+        clear_coordination(self.store.as_ref(), run_id, self.executor.flow()).await;
+        let _ = self.queue.remove(&results_topic(run_id)).await;
+    }
+}
+
+/// 远程 worker：从任务队列中取出事件并处理
+///
+/// 每个任务使用新的 `FlowContext`（消息历史不跨任务保留），节点之间只通过消息和共享的
+/// `ContextStore` 传递状态。
+pub struct FlowWorker {
+    queue: Arc<dyn WorkQueue>,
+    store: Arc<dyn ContextStore>,
+    executors: HashMap<String, FlowExecutor>,
+    max_concurrency: usize,
+}
+
+impl FlowWorker {
+    pub fn new(queue: Arc<dyn WorkQueue>, store: Arc<dyn ContextStore>) -> Self {
+        Self {
+            queue,
+            store,
+            executors: HashMap::new(),
+            max_concurrency: 8,
+        }
+    }
+
+    /// 注册可处理的流程（按流程名称匹配任务）
+    pub fn with_flow(mut self, executor: FlowExecutor) -> Self {
+        self.executors
+            .insert(executor.flow().name.clone(), executor);
+        self
+    }
+
+    /// 同时处理的最大任务数（默认 8）
+    pub fn with_max_concurrency(mut self, limit: usize) -> Self {
+        self.max_concurrency = limit.max(1);
+        self
+    }
+
+    /// 持续处理任务，直到任务队列关闭
+    ///
+    /// 任务在处理完成、结果回报之后才确认。
+    pub async fn run(self) -> Result<()> {
+        let mut tasks = self.queue.subscribe(TASK_TOPIC).await?;
+        let permits = Arc::new(Semaphore::new(self.max_concurrency));
+        let worker = Arc::new(self);
+        let mut running = JoinSet::new();
+
+        while let Some(item) = tasks.next().await {
+            let item = item?;
+            while running.try_join_next().is_some() {}
+            let permit = Arc::clone(&permits)
+                .acquire_owned()
+                .await
+                .map_err(|e| AgentFlowError::Other(e.into()))?;
+            let worker = Arc::clone(&worker);
+            running.spawn(async move {
+                let _permit = permit;
+                worker.handle(item.payload).await;
+                if let Err(err) = worker.queue.ack(TASK_TOPIC, &item.receipt).await {
+                    tracing::warn!(error = %err, "failed to ack distributed task");
+                }
+            });
+        }
+        while running.join_next().await.is_some() {}
+        Ok(())
+    }
+
+    async fn handle(&self, payload: String) {
+        let task: RemoteTask = match serde_json::from_str(&payload) {
+            Ok(task) => task,
+            Err(e) => {
+                tracing::warn!(error = %e, "ignoring malformed distributed task");
+                return;
+            }
+        };
+        match self.store.get(&done_key(&task.run_id)).await {
+            Ok(Some(_)) => return,
+            Ok(None) => {}
+            Err(err) => {
+                tracing::warn!(run_id = %task.run_id, error = %err, "failed to check run status")
+            }
+        }
+
+        let run_id = task.run_id.clone();
+        let outcome = self
+            .process(task)
+            .await
+            .unwrap_or_else(|err| RemoteOutcome {
+                spawned: 0,
+                status: OutcomeStatus::Failed {
+                    error: err.to_string(),
+                },
+            });
+        let published = match encode(&outcome) {
+            Ok(payload) => self.queue.publish(&results_topic(&run_id), payload).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = published {
+            tracing::warn!(run_id = %run_id, error = %err, "failed to report distributed outcome");
+        }
+    }
+
+    async fn process(&self, task: RemoteTask) -> Result<RemoteOutcome> {
+        let executor = self.executors.get(&task.flow).ok_or_else(|| {
+            AgentFlowError::Other(anyhow!("worker has no flow named `{}`", task.flow))
+        })?;
+        let ctx = Arc::new(FlowContext::new(Arc::clone(&self.store)));
+        let shared = Arc::new(SharedState {
+            max_concurrency: Some(executor.max_concurrency()),
+            ..SharedState::with_store(Arc::clone(&self.store), &task.run_id)
+        });
+        // 新产生的事件先收集在本地，再投递到任务队列
+        let local = Arc::new(MemoryEventQueue::unbounded());
+        let sender = EventSender::new(local.clone(), &task.run_id);
+        let result = executor.process(task.event, ctx, sender, shared).await;
+
+        // 投递中途失败时也要回报已投递的数量，否则协调方的未完成计数会偏差
+        let mut spawned = 0;
+        let forwarded = self
+            .forward(&task.run_id, &task.flow, &local, &mut spawned)
+            .await;
+
+        let status = match forwarded.and(result) {
+            Ok(TaskResult::Continue) => OutcomeStatus::Continue,
+            Ok(TaskResult::Finished(finished)) => OutcomeStatus::Finished {
+                node: finished.node,
+                message: finished.message,
+            },
+            Err(err) => OutcomeStatus::Failed {
+                error: err.to_string(),
+            },
+        };
+        Ok(RemoteOutcome { spawned, status })
+    }
+
+    async fn forward(
+        &self,
+        run_id: &str,
+        flow: &str,
+        local: &MemoryEventQueue,
+        spawned: &mut usize,
+    ) -> Result<()> {
+        while let Some(delivery) = local.pop(run_id).await? {
+            let next = RemoteTask {
+                run_id: run_id.to_string(),
+                flow: flow.to_string(),
+                event: delivery.event,
+            };
+            self.queue.publish(TASK_TOPIC, encode(&next)?).await?;
+            *spawned += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentAction, AgentContext};
+    use crate::flow::JoinStrategy;
+    use crate::state::MemoryStore;
+    use crate::testing;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct SplitAgent;
+
+    #[async_trait]
+    impl Agent for SplitAgent {
+        fn name(&self) -> &'static str {
+            "split"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let branches = ["left", "right"]
+                .into_iter()
+                .map(|target| (target.to_string(), message.clone()))
+                .collect();
+            Ok(AgentAction::Branch { branches })
+        }
+    }
+
+    #[derive(Default)]
+    struct UpperAgent {
+        starts: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Agent for UpperAgent {
+        fn name(&self) -> &'static str {
+            "upper"
+        }
+
+        async fn on_start(&self, _ctx: &AgentContext<'_>) -> Result<()> {
+            self.starts.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Next {
+                target: "merge".into(),
+                message: AgentMessage::user(message.content.to_uppercase()),
+            })
+        }
+    }
+
+    fn executor(upper: Arc<UpperAgent>) -> FlowExecutor {
+        let agents: Vec<(&str, Arc<dyn Agent>)> =
+            vec![("split", Arc::new(SplitAgent)), ("upper", upper)];
+        testing::executor("fanout", agents, |builder| {
+            builder
+                .add_agent_node("split", "split")
+                .add_agent_node("left", "upper")
+                .add_agent_node("right", "upper")
+                .add_join_node(
+                    "merge",
+                    JoinStrategy::All,
+                    vec!["left".into(), "right".into()],
+                )
+                .add_terminal_node("done")
+                .set_start("split")
+                .connect("merge", "done");
+        })
+    }
+
+    #[tokio::test]
+    async fn test_distributed_fan_out_and_join() {
+        let queue: Arc<dyn WorkQueue> = Arc::new(MemoryWorkQueue::new());
+        let store: Arc<dyn ContextStore> = Arc::new(MemoryStore::new());
+        let upper = Arc::new(UpperAgent::default());
+        for _ in 0..2 {
+            let worker = FlowWorker::new(Arc::clone(&queue), Arc::clone(&store))
+                .with_flow(executor(Arc::clone(&upper)));
+            tokio::spawn(worker.run());
+        }
+
+        let coordinator =
+            DistributedExecutor::new(executor(Arc::clone(&upper)), queue, Arc::clone(&store));
+        let execution = coordinator.start(AgentMessage::user("hi")).await.unwrap();
+        assert_eq!(execution.last_node, "done");
+        let joined = execution.last_message.unwrap().metadata.unwrap();
+        let contents: Vec<_> = joined["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["content"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(contents, vec!["HI", "HI"]);
+        assert_eq!(upper.starts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_distributed_unknown_flow_fails() {
+        let queue: Arc<dyn WorkQueue> = Arc::new(MemoryWorkQueue::new());
+        let store: Arc<dyn ContextStore> = Arc::new(MemoryStore::new());
+        tokio::spawn(FlowWorker::new(Arc::clone(&queue), Arc::clone(&store)).run());
+
+        let coordinator = DistributedExecutor::new(executor(Arc::default()), queue, store);
+        let Err(error) = coordinator.start(AgentMessage::user("hi")).await else {
+            panic!("expected the run to fail");
+        };
+        assert!(error.to_string().contains("no flow named `fanout`"));
+    }
+
+    #[tokio::test]
+    async fn test_distributed_run_times_out() {
+        let queue: Arc<dyn WorkQueue> = Arc::new(MemoryWorkQueue::new());
+        let store: Arc<dyn ContextStore> = Arc::new(MemoryStore::new());
+        let coordinator = DistributedExecutor::new(executor(Arc::default()), queue, store)
+            .with_timeout(Duration::from_millis(50));
+        let Err(error) = coordinator.start(AgentMessage::user("hi")).await else {
+            panic!("expected the run to time out");
+        };
+        assert!(error.to_string().contains("timed out"));
+    }
+
+    /// 记录确认顺序，并在投递指定数量的任务后拒绝继续投递
+    struct RecordingQueue {
+        inner: MemoryWorkQueue,
+        log: Mutex<Vec<String>>,
+        task_limit: Option<usize>,
+    }
+
+    impl RecordingQueue {
+        fn new(task_limit: Option<usize>) -> Self {
+            Self {
+                inner: MemoryWorkQueue::new(),
+                log: Mutex::new(Vec::new()),
+                task_limit,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl WorkQueue for RecordingQueue {
+        async fn publish(&self, topic: &str, payload: String) -> Result<()> {
+            {
+                let mut log = self.log.lock();
+                let tasks = log.iter().filter(|e| *e == "publish task").count();
+                if topic == TASK_TOPIC && self.task_limit.is_some_and(|limit| tasks >= limit) {
+                    return Err(AgentFlowError::Other(anyhow!("queue unavailable")));
+                }
+                log.push(if topic == TASK_TOPIC {
+                    "publish task".into()
+                } else {
+                    "publish outcome".into()
+                });
+            }
+            self.inner.publish(topic, payload).await
+        }
+
+        async fn subscribe(&self, topic: &str) -> Result<BoxStream<'static, Result<WorkItem>>> {
+            self.inner.subscribe(topic).await
+        }
+
+        async fn ack(&self, topic: &str, _receipt: &str) -> Result<()> {
+            if topic == TASK_TOPIC {
+                self.log.lock().push("ack task".into());
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_worker_acks_task_after_reporting() {
+        let queue = Arc::new(RecordingQueue::new(None));
+        let store: Arc<dyn ContextStore> = Arc::new(MemoryStore::new());
+        let worker = FlowWorker::new(queue.clone(), Arc::clone(&store))
+            .with_flow(executor(Arc::default()))
+            .with_max_concurrency(1);
+        tokio::spawn(worker.run());
+
+        DistributedExecutor::new(executor(Arc::default()), queue.clone(), store)
+            .start(AgentMessage::user("hi"))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let log = queue.log.lock().clone();
+        let acks = log.iter().filter(|e| *e == "ack task").count();
+        assert_eq!(acks, log.iter().filter(|e| *e == "publish task").count());
+        // 每次确认之前都已回报该任务的结果
+        let mut outcomes = 0;
+        for entry in &log {
+            match entry.as_str() {
+                "publish outcome" => outcomes += 1,
+                "ack task" => {
+                    assert!(outcomes > 0);
+                    outcomes -= 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_partial_publish_failure_fails_run() {
+        // 起始任务之后只允许再投递一个分支
+        let queue = Arc::new(RecordingQueue::new(Some(2)));
+        let store: Arc<dyn ContextStore> = Arc::new(MemoryStore::new());
+        let worker =
+            FlowWorker::new(queue.clone(), Arc::clone(&store)).with_flow(executor(Arc::default()));
+        tokio::spawn(worker.run());
+
+        let coordinator = DistributedExecutor::new(executor(Arc::default()), queue, store)
+            .with_timeout(Duration::from_secs(5));
+        let Err(error) = coordinator.start(AgentMessage::user("hi")).await else {
+            panic!("expected the run to fail");
+        };
+        assert!(error.to_string().contains("queue unavailable"), "{error}");
+    }
+}
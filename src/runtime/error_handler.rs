@@ -0,0 +1,161 @@
+//! 流程级错误处理节点：任意节点失败时带着 `ErrorEnvelope` 转到恢复/清理节点
+
+use serde::{Deserialize, Serialize};
+
+use super::types::FlowEvent;
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, ErrorSeverity, FrameworkError};
+
+/// metadata 中错误信封的字段名
+pub const ERROR_FIELD: &str = "error";
+
+/// 发给错误处理节点的错误信息
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ErrorEnvelope {
+    /// 失败的节点
+    pub node: String,
+    /// 把事件发给失败节点的上游节点
+    pub source: String,
+    pub trace_id: String,
+    /// 错误类型，同 `FrameworkError::code`
+    pub kind: String,
+    pub message: String,
+    pub severity: ErrorSeverity,
+    /// 该节点在同一 trace 中第几次失败（从 1 开始）
+    pub attempt: u32,
+    /// 失败节点收到的消息，处理节点可以据此重试
+    pub input: AgentMessage,
+}
+
+impl ErrorEnvelope {
+    pub(super) fn new(event: FlowEvent, error: AgentFlowError, attempt: u32) -> Self {
+        let error = FrameworkError::from(error);
+        Self {
+            node: event.node,
+            source: event.source,
+            trace_id: event.trace_id,
+            kind: error.code,
+            message: error.message,
+            severity: error.severity,
+            attempt,
+            input: std::sync::Arc::unwrap_or_clone(event.message),
+        }
+    }
+
+    /// 对应的 `FrameworkError`，记录到 `FlowExecution::errors`
+    pub fn framework_error(&self) -> FrameworkError {
+        FrameworkError::new(self.kind.clone(), self.message.clone())
+            .with_severity(self.severity)
+            .with_source(self.node.clone())
+    }
+
+    /// 发给错误处理节点的消息：内容为错误说明，metadata 的 `error` 字段为完整信封
+    pub fn to_message(&self) -> AgentMessage {
+        let mut message = AgentMessage::system(format!(
+            "node `{}` failed ({}): {}",
+            self.node, self.kind, self.message
+        ));
+        message.from = self.node.clone();
+        message.metadata = serde_json::to_value(self)
+            .ok()
+            .map(|envelope| serde_json::json!({ ERROR_FIELD: envelope }));
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentRegistry};
+    use crate::error::Result;
+    use crate::flow::{DecisionPolicy, FlowBuilder};
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    struct FailingAgent;
+
+    #[async_trait]
+    impl Agent for FailingAgent {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        async fn on_message(
+            &self,
+            _message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Err(AgentFlowError::Other(anyhow::anyhow!("inventory offline")))
+        }
+    }
+
+    /// 决策失败时转到 `check`，其余失败重试一次后放弃
+    struct RecoverAgent;
+
+    #[async_trait]
+    impl Agent for RecoverAgent {
+        fn name(&self) -> &'static str {
+            "recover"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let envelope: ErrorEnvelope =
+                serde_json::from_value(message.metadata.unwrap()[ERROR_FIELD].clone()).unwrap();
+            let target = if envelope.kind == "flow.decision_no_match" {
+                "check".to_string()
+            } else if envelope.attempt < 2 {
+                envelope.node
+            } else {
+                "give_up".to_string()
+            };
+            Ok(AgentAction::Next {
+                target,
+                message: envelope.input,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failures_route_to_error_handler() {
+        let mut agents = AgentRegistry::new();
+        register_agent("failing", Arc::new(FailingAgent), &mut agents);
+        register_agent("recover", Arc::new(RecoverAgent), &mut agents);
+        let mut builder = FlowBuilder::new("orders");
+        builder
+            .add_decision_node("route", DecisionPolicy::FirstMatch, Vec::new())
+            .add_agent_node("check", "failing")
+            .add_agent_node("recover", "recover")
+            .add_terminal_node("give_up")
+            .set_start("route")
+            .set_error_handler("recover");
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+
+        let execution = executor
+            .start(ctx, AgentMessage::user("order-3"))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "give_up");
+        assert_eq!(execution.last_message.unwrap().content, "order-3");
+        let errors: Vec<_> = execution
+            .errors
+            .iter()
+            .map(|error| (error.code.as_str(), error.source.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("flow.decision_no_match", "route"),
+                ("internal.error", "check"),
+                ("internal.error", "check"),
+            ]
+        );
+    }
+}
@@ -1,17 +1,39 @@
 use anyhow::anyhow;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::time::{Duration, Instant};
 use tokio::task::JoinSet;
 
-use crate::agent::{AgentMessage, AgentRegistry};
+use crate::agent::{AgentMessage, AgentRegistry, MessageRole};
 use crate::error::{AgentFlowError, Result};
-use crate::flow::Flow;
-use crate::state::FlowContext;
+use crate::flow::{Flow, FlowNodeKind};
+use crate::guardrails::{InjectionGuard, InjectionVerdict};
+use crate::state::{ContextStore, FlowContext};
 use crate::tools::{orchestrator::ToolOrchestrator, ToolRegistry};
 
+use super::batch::{BatchItem, BatchOptions, BatchReport, BatchStatus};
+use super::channel::RunUpdate;
+use super::dead_letter::{DeadLetter, DeadLetterSink};
+use super::digest::RunDigestHook;
+use super::error_handler::ErrorEnvelope;
+use super::explain::ExplainLog;
+use super::history::{now_millis, HistoryFilter, NodeVisit, RunRecord, RunStore, VisitLog};
+use super::interceptor::{InterceptorStack, NodeInterceptor};
+use super::memo::MemoCache;
+use super::notifier::{LifecycleEvent, LifecycleEventKind, NodeNotifier, WebhookNotifier};
 use super::processor::process_event;
-use super::state::SharedState;
-use super::types::{FlowEvent, FlowExecution, TaskResult};
+use super::progress::{ProgressReporter, RunProgress};
+use super::queue::{
+    Delivery, EventQueue, EventSender, MemoryEventQueue, QueueStats, DEFAULT_QUEUE_CAPACITY,
+};
+use super::resume::ResumeTokens;
+use super::state::{
+    claim_idempotency_key, clear_coordination, record_run_version, release_idempotency_key,
+    run_version, SharedState, DEFAULT_DEDUP_TTL,
+};
+use super::token_sink::TokenSink;
+use super::types::{FlowEvent, FlowExecution, FlowMigration, TaskResult};
 
 /// Flow 执行器
 #[derive(Clone)]
@@ -22,6 +44,47 @@ pub struct FlowExecutor {
     max_iterations: u32,
     max_concurrency: usize,
     tool_orchestrator: Option<Arc<ToolOrchestrator>>,
+    sub_flows: Arc<SubFlows>,
+    digest_hook: Option<Arc<RunDigestHook>>,
+    explain: bool,
+    notifier: Option<Arc<WebhookNotifier>>,
+    event_queue: Arc<dyn EventQueue>,
+    overflow_limit: usize,
+    run_store: Option<Arc<dyn RunStore>>,
+    dedup_events: bool,
+    dedup_ttl: Duration,
+    idempotency_store: Option<Arc<dyn ContextStore>>,
+    dead_letter_sink: Option<Arc<dyn DeadLetterSink>>,
+    /// 按旧版本号索引的迁移钩子
+    migrations: HashMap<u32, Arc<FlowMigration>>,
+    interceptors: InterceptorStack,
+    token_sink: Option<Arc<dyn TokenSink>>,
+    progress: Option<ProgressReporter>,
+    resume_tokens: ResumeTokens,
+}
+
+/// 节点失败后的处理方式
+enum Recovery {
+    DeadLetter,
+    /// 转到错误处理节点
+    ErrorHandler(String),
+}
+
+/// 子流程执行器集合与结果缓存
+#[derive(Clone, Default)]
+pub struct SubFlows {
+    executors: HashMap<String, FlowExecutor>,
+    cache: MemoCache,
+}
+
+impl SubFlows {
+    pub fn get(&self, name: &str) -> Option<&FlowExecutor> {
+        self.executors.get(name)
+    }
+
+    pub fn cache(&self) -> &MemoCache {
+        &self.cache
+    }
 }
 
 impl FlowExecutor {
@@ -33,9 +96,29 @@ impl FlowExecutor {
             max_iterations: 256,
             max_concurrency: 8,
             tool_orchestrator: None,
+            sub_flows: Arc::new(SubFlows::default()),
+            digest_hook: None,
+            explain: false,
+            notifier: None,
+            event_queue: Arc::new(MemoryEventQueue::default()),
+            overflow_limit: DEFAULT_QUEUE_CAPACITY,
+            run_store: None,
+            dedup_events: false,
+            dedup_ttl: DEFAULT_DEDUP_TTL,
+            idempotency_store: None,
+            dead_letter_sink: None,
+            migrations: HashMap::new(),
+            interceptors: InterceptorStack::default(),
+            token_sink: None,
+            progress: None,
+            resume_tokens: ResumeTokens::default(),
         }
     }
 
+    pub fn flow(&self) -> &Flow {
+        &self.flow
+    }
+
     pub fn with_max_iterations(mut self, max_iterations: u32) -> Self {
         self.max_iterations = max_iterations;
         self
@@ -46,130 +129,926 @@ impl FlowExecutor {
         self
     }
 
+    pub(super) fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
     pub fn with_tool_orchestrator(mut self, orchestrator: Arc<ToolOrchestrator>) -> Self {
         self.tool_orchestrator = Some(orchestrator);
         self
     }
 
+    /// 注册子流程（按子流程名称被 SubFlow 节点引用），子流程的恢复令牌也可以通过本执行器投递
+    pub fn with_sub_flow(mut self, executor: FlowExecutor) -> Self {
+        let executor = FlowExecutor {
+            resume_tokens: self.resume_tokens.clone(),
+            ..executor
+        };
+        let name = executor.flow.name.clone();
+        Arc::make_mut(&mut self.sub_flows)
+            .executors
+            .insert(name, executor);
+        self
+    }
+
+    /// 设置子流程结果缓存（默认内存缓存，随执行器在多次运行间共享）
+    pub fn with_memo_cache(mut self, cache: MemoCache) -> Self {
+        Arc::make_mut(&mut self.sub_flows).cache = cache;
+        self
+    }
+
+    /// 设置运行结束后的摘要钩子
+    pub fn with_run_digest(mut self, hook: RunDigestHook) -> Self {
+        self.digest_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// 开启 explain 模式：记录每次路由判定的理由
+    pub fn with_explain(mut self, enabled: bool) -> Self {
+        self.explain = enabled;
+        self
+    }
+
+    /// 设置生命周期 Webhook：运行开始、结束、失败和节点完成时推送事件
+    pub fn with_webhook_notifier(mut self, notifier: WebhookNotifier) -> Self {
+        self.notifier = Some(Arc::new(notifier));
+        self
+    }
+
+    /// 设置事件队列（默认容量 1024 的内存队列）
+    ///
+    /// 使用持久化队列时，Join/Loop 协调状态保存在上下文的 `ContextStore` 中，崩溃后可以 `resume`。
+    pub fn with_event_queue(mut self, queue: Arc<dyn EventQueue>) -> Self {
+        self.event_queue = queue;
+        self
+    }
+
+    /// 使用每次运行最多排队 `capacity` 个事件的内存队列；队列满时产生事件的节点等待空位
+    pub fn with_queue_capacity(self, capacity: usize) -> Self {
+        self.with_event_queue(Arc::new(MemoryEventQueue::new(capacity)))
+    }
+
+    /// 所有进行中的任务都阻塞在入队上时，调度器最多额外暂存的事件数（默认 1024），超过后运行失败
+    pub fn with_overflow_limit(mut self, limit: usize) -> Self {
+        self.overflow_limit = limit;
+        self
+    }
+
+    /// 事件队列的深度与背压统计，队列不支持时返回 None
+    pub fn queue_stats(&self) -> Option<QueueStats> {
+        self.event_queue.stats()
+    }
+
+    /// 设置运行历史存储：每次运行结束后保存节点、耗时、错误和最终消息
+    pub fn with_run_store(mut self, store: Arc<dyn RunStore>) -> Self {
+        self.run_store = Some(store);
+        self
+    }
+
+    /// 开启事件去重：同一事件（trace id + 节点 + 消息哈希）重复投递时只执行一次
+    ///
+    /// 配合持久化事件队列使用时，`resume` 重新投递已执行但未确认的事件不会重复调用有副作用的工具。
+    pub fn with_event_dedup(mut self, enabled: bool) -> Self {
+        self.dedup_events = enabled;
+        self
+    }
+
+    /// 幂等键与事件去重记录的有效期（默认 `DEFAULT_DEDUP_TTL`，24 小时），过期后可以再次执行
+    pub fn with_dedup_ttl(mut self, ttl: Duration) -> Self {
+        self.dedup_ttl = ttl;
+        self
+    }
+
+    /// 幂等键占用记录使用的存储，未设置时写入每次运行上下文的存储
+    ///
+    /// 幂等键只在共享同一存储的运行之间去重；每次运行使用独立 `MemoryStore` 时应设置共享存储。
+    pub fn with_idempotency_store(mut self, store: Arc<dyn ContextStore>) -> Self {
+        self.idempotency_store = Some(store);
+        self
+    }
+
+    /// 设置死信接收端：Agent/Tool 节点失败时发送死信，失败的分支结束而不是终止整个运行
+    ///
+    /// 流程配置了死信节点（`FlowBuilder::set_dead_letter`）时，死信同时转到该节点。
+    pub fn with_dead_letter_sink(mut self, sink: Arc<dyn DeadLetterSink>) -> Self {
+        self.dead_letter_sink = Some(sink);
+        self
+    }
+
+    /// 恢复在 `from` 版本上开始的运行时，用 `migrate` 改写队列中待处理的事件
+    ///
+    /// 未配置对应迁移时，恢复版本不同的运行返回 `VersionMismatch`。
+    pub fn with_migration<F>(mut self, from: u32, migrate: F) -> Self
+    where
+        F: Fn(FlowEvent) -> Result<Option<FlowEvent>> + Send + Sync + 'static,
+    {
+        self.migrations.insert(from, Arc::new(migrate));
+        self
+    }
+
+    /// 添加节点拦截器：每个事件处理前后调用，按添加顺序由外到内
+    pub fn with_interceptor(mut self, interceptor: impl NodeInterceptor + 'static) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// 设置流式输出接收方：配置驱动 Agent 的 LLM 输出片段交给它，默认不输出
+    ///
+    /// 上下文已通过 `FlowContext::with_token_sink` 挂载接收方时以上下文为准。
+    pub fn with_token_sink(mut self, sink: Arc<dyn TokenSink>) -> Self {
+        self.token_sink = Some(sink);
+        self
+    }
+
+    /// 设置进度报告：每个节点完成时按 trace 估算完成百分比并广播，见 `ProgressReporter`
+    pub fn with_progress_reporter(mut self, reporter: ProgressReporter) -> Self {
+        self.progress = Some(reporter);
+        self
+    }
+
+    /// 查询运行历史（最新的在前），需要先配置 `with_run_store`
+    pub async fn history(&self, filter: HistoryFilter) -> Result<Vec<RunRecord>> {
+        let store = self.run_store.as_ref().ok_or_else(|| {
+            AgentFlowError::Other(anyhow!(
+                "flow `{}` has no run store configured",
+                self.flow.name
+            ))
+        })?;
+        store.query(&filter).await
+    }
+
+    /// 把外部事件载荷投递给等待 `token` 的 `await_event` 节点，该 trace 以载荷为输出继续执行
+    ///
+    /// 令牌只在签发它的执行器（及其克隆、父流程执行器）中有效，节点超时或运行结束后失效。
+    pub fn deliver_event(&self, token: &str, payload: Value) -> Result<()> {
+        self.resume_tokens.deliver(token, payload)
+    }
+
+    /// 等待投递的恢复令牌
+    pub fn pending_events(&self) -> Vec<String> {
+        self.resume_tokens.pending()
+    }
+
+    /// 执行流程；上下文挂载了 `RunChannel` 时推送结束或失败事件
     pub async fn start(
         &self,
         ctx: Arc<FlowContext>,
         initial: AgentMessage,
     ) -> Result<FlowExecution> {
-        let debug_mode = std::env::var("AGENTFLOW_DEBUG").is_ok();
-        if debug_mode {
-            use std::io::{self, Write};
-            eprintln!("🚀 FlowExecutor::start() 开始");
-            eprintln!("   工作流名称: {}", self.flow.name);
-            eprintln!("   起始节点: {}", self.flow.start);
-            io::stderr().flush().ok();
-        }
-
-        let (tx, mut rx) = mpsc::unbounded_channel();
-
-        let start_node = self.flow.start.clone();
-        tx.send(FlowEvent {
-            node: start_node.clone(),
-            message: initial,
-            iterations: 0,
-            trace_id: crate::agent::message::uuid(),
-            source: "__start__".to_string(),
+        let run_id = crate::agent::message::uuid();
+        self.start_with_run_id(ctx, initial, &run_id).await
+    }
+
+    /// 使用指定的运行 id 执行，崩溃后可以用同一个 id 调用 `resume`
+    pub async fn start_with_run_id(
+        &self,
+        ctx: Arc<FlowContext>,
+        initial: AgentMessage,
+        run_id: &str,
+    ) -> Result<FlowExecution> {
+        self.execute(ctx, Some(initial), run_id).await
+    }
+
+    /// 使用命名参数执行
+    ///
+    /// 参数按流程声明的输入参数校验（必填、类型、未声明的参数），通过后写入状态 `params.<name>`
+    /// （字符串原样保存，其余保存为 JSON），参数对象序列化后作为初始用户消息。
+    pub async fn start_with_params(
+        &self,
+        ctx: Arc<FlowContext>,
+        params: HashMap<String, Value>,
+    ) -> Result<FlowExecution> {
+        self.flow.validate_params(&params)?;
+        let store = ctx.store();
+        for (name, value) in &params {
+            let value = match value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            store.set(&format!("params.{}", name), value).await?;
+        }
+        let payload = Value::Object(params.into_iter().collect());
+        self.start(ctx, AgentMessage::user(payload.to_string()))
+            .await
+    }
+
+    /// 对每条输入执行一次流程，最多同时执行 `options.concurrency` 条
+    ///
+    /// 每条输入使用独立的上下文和运行 id；设置 `abort_on_failure` 时首个失败会取消执行中的输入，
+    /// 未完成的输入记为 `Skipped`。
+    pub async fn run_batch(&self, inputs: Vec<AgentMessage>, options: BatchOptions) -> BatchReport {
+        let started = Instant::now();
+        let mut items: Vec<BatchItem> = (0..inputs.len()).map(BatchItem::skipped).collect();
+        let mut pending = inputs.into_iter().enumerate();
+        let mut running = JoinSet::new();
+        let mut indices = HashMap::new();
+        let mut aborted = false;
+
+        loop {
+            while !aborted && running.len() < options.concurrency.max(1) {
+                let Some((index, input)) = pending.next() else {
+                    break;
+                };
+                let executor = self.clone();
+                let ctx = options.context(index);
+                let handle = running.spawn(async move {
+                    let started = Instant::now();
+                    let result = executor.start(ctx, input).await;
+                    (result, started.elapsed())
+                });
+                indices.insert(handle.id(), index);
+            }
+            let Some(joined) = running.join_next_with_id().await else {
+                break;
+            };
+            let (id, result, elapsed) = match joined {
+                Ok((id, (result, elapsed))) => (id, result, elapsed),
+                Err(join_error) if join_error.is_cancelled() => continue,
+                Err(join_error) => (
+                    join_error.id(),
+                    Err(AgentFlowError::Other(join_error.into())),
+                    Duration::ZERO,
+                ),
+            };
+            let item = &mut items[indices[&id]];
+            item.duration_ms = elapsed.as_millis() as u64;
+            match result {
+                Ok(execution) => {
+                    item.status = BatchStatus::Succeeded;
+                    item.last_node = Some(execution.last_node);
+                    item.output = execution.last_message;
+                    item.errors = execution.errors;
+                }
+                Err(error) => {
+                    tracing::warn!(flow = %self.flow.name, index = item.index, error = %error, "batch input failed");
+                    item.status = BatchStatus::Failed;
+                    item.errors.push(error.into());
+                    if options.abort_on_failure && !aborted {
+                        aborted = true;
+                        running.abort_all();
+                    }
+                }
+            }
+        }
+
+        BatchReport {
+            items,
+            duration_ms: started.elapsed().as_millis() as u64,
+            aborted,
+        }
+    }
+
+    /// 恢复中断的运行：已取出但未确认的事件放回队列后继续执行，需要持久化事件队列
+    pub async fn resume(&self, ctx: Arc<FlowContext>, run_id: &str) -> Result<FlowExecution> {
+        if !self.event_queue.is_durable() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "resuming run `{}` requires a durable event queue",
+                run_id
+            )));
+        }
+        let requeued = self.event_queue.requeue_unacked(run_id).await?;
+        tracing::info!(run_id, requeued, flow = %self.flow.name, "resuming flow run");
+        self.migrate_run(&ctx, run_id).await?;
+        self.execute(ctx, None, run_id).await
+    }
+
+    /// 运行开始时的图版本与当前版本不同时，用迁移钩子改写队列中待处理的事件
+    async fn migrate_run(&self, ctx: &FlowContext, run_id: &str) -> Result<()> {
+        let store = ctx.store();
+        let Some(run_version) = run_version(store.as_ref(), run_id).await? else {
+            return Ok(());
+        };
+        if run_version == self.flow.version {
+            return Ok(());
+        }
+        let Some(migrate) = self.migrations.get(&run_version) else {
+            return Err(AgentFlowError::VersionMismatch {
+                flow: self.flow.name.clone(),
+                run_id: run_id.to_string(),
+                run_version,
+                flow_version: self.flow.version,
+            });
+        };
+
+        let mut pending = Vec::new();
+        while let Some(delivery) = self.event_queue.pop(run_id).await? {
+            pending.push(delivery);
+        }
+        let total = pending.len();
+        let mut kept = 0;
+        for delivery in pending {
+            if let Some(event) = migrate(delivery.event)? {
+                self.event_queue.push(run_id, event).await?;
+                kept += 1;
+            }
+            self.event_queue.ack(run_id, &delivery.receipt).await?;
+        }
+        record_run_version(store.as_ref(), run_id, self.flow.version).await?;
+        tracing::info!(
+            run_id,
+            from = run_version,
+            to = self.flow.version,
+            total,
+            kept,
+            "migrated pending events"
+        );
+        Ok(())
+    }
+
+    async fn execute(
+        &self,
+        ctx: Arc<FlowContext>,
+        initial: Option<AgentMessage>,
+        run_id: &str,
+    ) -> Result<FlowExecution> {
+        let idempotency_key = initial
+            .as_ref()
+            .and_then(AgentMessage::idempotency_key)
+            .map(str::to_string);
+        let claims = self
+            .idempotency_store
+            .clone()
+            .unwrap_or_else(|| ctx.store());
+        if let Some(key) = &idempotency_key {
+            claim_idempotency_key(
+                claims.as_ref(),
+                &self.flow.name,
+                key,
+                run_id,
+                self.dedup_ttl,
+            )
+            .await?;
+        }
+        if let (Some(notifier), Some(_)) = (&self.notifier, &initial) {
+            let event =
+                LifecycleEvent::new(LifecycleEventKind::FlowStarted, &self.flow.name, run_id);
+            notifier.notify_in_background(event);
+        }
+        let ctx = match (&self.flow.pii_redactor, ctx.redactor()) {
+            (Some(redactor), None) => {
+                Arc::new(ctx.as_ref().clone().with_redactor(Arc::clone(redactor)))
+            }
+            _ => ctx,
+        };
+        let ctx = match (&self.flow.history_retention, ctx.history_retention()) {
+            (Some(retention), None) => Arc::new(
+                ctx.as_ref()
+                    .clone()
+                    .with_history_retention(retention.clone()),
+            ),
+            _ => ctx,
+        };
+        let ctx = match (&self.token_sink, ctx.token_sink()) {
+            (Some(sink), None) => Arc::new(ctx.as_ref().clone().with_token_sink(Arc::clone(sink))),
+            _ => ctx,
+        };
+        let started_at = now_millis();
+        let visits = self
+            .run_store
+            .as_ref()
+            .map(|_| Arc::new(VisitLog::default()));
+        let result = self
+            .run_with_digest(Arc::clone(&ctx), initial, run_id, visits.clone())
+            .await;
+        if let (Some(key), Err(_)) = (&idempotency_key, &result) {
+            release_idempotency_key(claims.as_ref(), &self.flow.name, key, run_id).await;
+        }
+        if let (Some(store), Some(visits)) = (&self.run_store, visits) {
+            let mut record = RunRecord::from_result(
+                run_id,
+                &self.flow.name,
+                started_at,
+                visits.take().await,
+                result.as_ref(),
+            );
+            if let Some(redactor) = ctx.redactor() {
+                let inputs = record
+                    .nodes
+                    .iter_mut()
+                    .filter_map(|visit| visit.input.as_mut());
+                for message in inputs.chain(record.final_message.as_mut()) {
+                    redactor.redact_message(message);
+                }
+            }
+            if let Err(err) = store.save(&record).await {
+                tracing::warn!(run_id, error = %err, "failed to save run history");
+            }
+        }
+        if let Some(notifier) = &self.notifier {
+            let event = match &result {
+                Ok(execution) => LifecycleEvent {
+                    node: Some(execution.last_node.clone()),
+                    message: execution.last_message.clone().map(|mut message| {
+                        if let Some(redactor) = ctx.redactor() {
+                            redactor.redact_message(&mut message);
+                        }
+                        message
+                    }),
+                    ..LifecycleEvent::new(LifecycleEventKind::FlowFinished, &self.flow.name, run_id)
+                },
+                Err(err) => LifecycleEvent {
+                    error: Some(err.to_string()),
+                    ..LifecycleEvent::new(LifecycleEventKind::FlowFailed, &self.flow.name, run_id)
+                },
+            };
+            notifier.notify_in_background(event);
+        }
+        if let Some(channel) = ctx.channel() {
+            channel.publish(match &result {
+                Ok(execution) => RunUpdate::Finished {
+                    node: execution.last_node.clone(),
+                    message: execution.last_message.clone(),
+                },
+                Err(err) => RunUpdate::Failed {
+                    error: err.to_string(),
+                },
+            });
+        }
+        result
+    }
+
+    /// 处理单个事件，新产生的事件写入 `sender`（分布式 worker 使用）
+    pub(super) async fn process(
+        &self,
+        mut event: FlowEvent,
+        ctx: Arc<FlowContext>,
+        sender: EventSender,
+        shared: Arc<SharedState>,
+    ) -> Result<TaskResult> {
+        let fingerprint = self.dedup_events.then(|| event.fingerprint());
+        if let Some(fingerprint) = &fingerprint {
+            if shared.event_processed(fingerprint, self.dedup_ttl).await? {
+                tracing::debug!(
+                    node = %event.node,
+                    trace_id = %event.trace_id,
+                    "skipping duplicate event"
+                );
+                return Ok(TaskResult::Continue);
+            }
+        }
+        self.screen(&mut event, &shared).await?;
+        let intercepted = self.interceptors.before(&mut event, &ctx).await;
+        let retained = self
+            .recovery_for(&event)
+            .map(|recovery| (recovery, event.clone(), sender.clone()));
+        let visits = shared.visits.clone();
+        let node = event.node.clone();
+        let trace_id = event.trace_id.clone();
+        let visit_input = visits.as_ref().map(|_| {
+            (
+                event.source.clone(),
+                event.iterations,
+                event.message.clone(),
+            )
+        });
+        let node_ctx = Arc::clone(&ctx);
+        let started_at = now_millis();
+        let started = std::time::Instant::now();
+        let observed = (!self.interceptors.is_empty()).then(|| event.clone());
+        let result = match intercepted {
+            Ok(()) => {
+                if let Some(progress) = &shared.progress {
+                    progress.node_started(&event);
+                }
+                process_event(
+                    event,
+                    Arc::clone(&self.flow),
+                    Arc::clone(&self.agents),
+                    Arc::clone(&self.tools),
+                    Arc::clone(&ctx),
+                    sender,
+                    self.max_iterations,
+                    self.tool_orchestrator.clone(),
+                    Arc::clone(&shared),
+                    Arc::clone(&self.sub_flows),
+                )
+                .await
+            }
+            Err(error) => Err(error),
+        };
+        let result = match &observed {
+            Some(event) => self.interceptors.after(event, &ctx, result).await,
+            None => result,
+        };
+        node_ctx.clear_node_scope(&node);
+        if let Err(err) = node_ctx.flush_history().await {
+            tracing::warn!(node = %node, error = %err, "failed to spill message history");
+        }
+        if let (Some(visits), Some((source, iterations, input))) = (visits, visit_input) {
+            visits
+                .record(NodeVisit {
+                    node: node.clone(),
+                    source,
+                    trace_id: trace_id.clone(),
+                    iterations,
+                    started_at,
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    input: Some(Arc::unwrap_or_clone(input)),
+                    error: result.as_ref().err().map(|err| err.to_string()),
+                })
+                .await;
+        }
+        let result = match (result, retained) {
+            (Err(error), Some((Recovery::DeadLetter, event, sender))) => {
+                self.compensate(&ctx, &shared, sender.run_id()).await;
+                self.dead_letter(event, error, &sender, &shared).await
+            }
+            (Err(error), Some((Recovery::ErrorHandler(handler), event, sender))) => {
+                self.compensate(&ctx, &shared, sender.run_id()).await;
+                self.handle_error(handler, event, error, &sender, &shared)
+                    .await
+            }
+            (result, _) => result,
+        };
+        if let (Some(progress), Ok(task)) = (&shared.progress, &result) {
+            let finished = matches!(task, TaskResult::Finished(_));
+            let update = progress.node_finished(&trace_id, &node, finished);
+            if let Some(channel) = ctx.channel() {
+                channel.publish(RunUpdate::Progress(update));
+            }
+        }
+        if let (Ok(_), Some(fingerprint)) = (&result, &fingerprint) {
+            shared
+                .mark_event_processed(fingerprint, self.dedup_ttl)
+                .await?;
+        }
+        result
+    }
+
+    /// 事件失败时的处理方式：Agent/Tool 节点优先转为死信，其余节点转到错误处理节点；
+    /// 错误处理节点自身失败或超过最大跳数时仍终止运行
+    fn recovery_for(&self, event: &FlowEvent) -> Option<Recovery> {
+        let node = self.flow.node(&event.node)?;
+        if event.iterations >= self.max_iterations {
+            return None;
+        }
+        let is_call = matches!(node.kind, FlowNodeKind::Agent(_) | FlowNodeKind::Tool(_));
+        let dead_letters = self.flow.dead_letter.is_some() || self.dead_letter_sink.is_some();
+        if is_call && dead_letters && self.flow.dead_letter.as_deref() != Some(node.name.as_str()) {
+            return Some(Recovery::DeadLetter);
+        }
+        self.flow
+            .on_error
+            .as_ref()
+            .filter(|handler| **handler != node.name)
+            .map(|handler| Recovery::ErrorHandler(handler.clone()))
+    }
+
+    async fn handle_error(
+        &self,
+        handler: String,
+        event: FlowEvent,
+        error: AgentFlowError,
+        sender: &EventSender,
+        shared: &SharedState,
+    ) -> Result<TaskResult> {
+        let attempt = shared.record_failure(&event.trace_id, &event.node).await;
+        let iterations = event.iterations + 1;
+        let envelope = ErrorEnvelope::new(event, error, attempt);
+        tracing::warn!(
+            flow = %self.flow.name,
+            node = %envelope.node,
+            kind = %envelope.kind,
+            attempt,
+            "node failed, routing to error handler `{}`",
+            handler
+        );
+        sender
+            .send(FlowEvent {
+                node: handler,
+                message: envelope.to_message().into(),
+                iterations,
+                trace_id: envelope.trace_id.clone(),
+                source: envelope.node.clone(),
+            })
+            .await?;
+        shared.errors.lock().await.push(envelope.framework_error());
+        Ok(TaskResult::Continue)
+    }
+
+    async fn dead_letter(
+        &self,
+        event: FlowEvent,
+        error: AgentFlowError,
+        sender: &EventSender,
+        shared: &SharedState,
+    ) -> Result<TaskResult> {
+        let letter = DeadLetter::new(&self.flow.name, event, error);
+        tracing::warn!(
+            flow = %self.flow.name,
+            node = %letter.node,
+            error = %letter.error.message,
+            "node failed, recording dead letter"
+        );
+        if let Some(sink) = &self.dead_letter_sink {
+            if let Err(err) = sink.send(&letter).await {
+                tracing::warn!(node = %letter.node, error = %err, "failed to send dead letter");
+            }
+        }
+        if let Some(target) = &self.flow.dead_letter {
+            sender
+                .send(FlowEvent {
+                    node: target.clone(),
+                    message: letter.to_message().into(),
+                    iterations: letter.iterations + 1,
+                    trace_id: letter.trace_id.clone(),
+                    source: letter.node.clone(),
+                })
+                .await?;
+        }
+        let error = letter.error.clone().with_source(letter.node.clone());
+        shared.errors.lock().await.push(error);
+        shared.dead_letters.lock().await.push(letter);
+        Ok(TaskResult::Continue)
+    }
+
+    /// 运行失败或节点失败转入死信/错误处理时，倒序调用已完成 Tool 节点的补偿流水线，补偿失败只记录日志
+    async fn compensate(&self, ctx: &FlowContext, shared: &SharedState, run_id: &str) {
+        let compensations = shared.take_compensations().await;
+        let Some(orchestrator) = &self.tool_orchestrator else {
+            return;
+        };
+        for step in compensations {
+            match orchestrator
+                .execute_pipeline_with_params(&step.pipeline, step.params, ctx)
+                .await
+            {
+                Ok(_) => tracing::info!(
+                    run_id,
+                    node = %step.node,
+                    pipeline = %step.pipeline,
+                    "compensated node"
+                ),
+                Err(err) => tracing::warn!(
+                    run_id,
+                    node = %step.node,
+                    pipeline = %step.pipeline,
+                    error = %err,
+                    "compensation failed"
+                ),
+            }
+        }
+    }
+
+    /// 起始节点：注入的历史消息（如会话历史）中有用户消息被注入检测命中时，起始消息改为进入隔离节点
+    async fn entry_node(&self, ctx: &FlowContext, initial: &mut AgentMessage) -> Result<String> {
+        let Some(guard) = &self.flow.injection_guard else {
+            return Ok(self.flow.start.clone());
+        };
+        for message in ctx.history() {
+            if let Some(verdict) = guard.inspect(&message).await {
+                let quarantine = self.quarantine(guard, &verdict)?;
+                InjectionGuard::mark(initial, &verdict);
+                return Ok(quarantine);
+            }
+        }
+        Ok(self.flow.start.clone())
+    }
+
+    /// 分发前检测用户消息（起始消息、人工输入等），命中时改为进入隔离节点并在 metadata 中记录检测结果
+    async fn screen(&self, event: &mut FlowEvent, shared: &SharedState) -> Result<()> {
+        let Some(guard) = &self.flow.injection_guard else {
+            return Ok(());
+        };
+        let message = &event.message;
+        if message.role != MessageRole::User
+            || event.node == guard.quarantine()
+            // 同一条消息沿途转发时只检测一次
+            || !shared.inspected.lock().await.insert(message.id.clone())
+        {
+            return Ok(());
+        }
+        let Some(verdict) = guard.inspect(message).await else {
+            return Ok(());
+        };
+        event.node = self.quarantine(guard, &verdict)?;
+        let mut message = (*event.message).clone();
+        InjectionGuard::mark(&mut message, &verdict);
+        event.message = Arc::new(message);
+        Ok(())
+    }
+
+    fn quarantine(&self, guard: &InjectionGuard, verdict: &InjectionVerdict) -> Result<String> {
+        if self.flow.node(guard.quarantine()).is_none() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "quarantine node `{}` not found in flow `{}`",
+                guard.quarantine(),
+                self.flow.name
+            )));
+        }
+        tracing::warn!(
+            flow = %self.flow.name,
+            detector = %verdict.detector,
+            reason = %verdict.reason,
+            "possible prompt injection, routing to quarantine"
+        );
+        Ok(guard.quarantine().to_string())
+    }
+
+    /// `initial` 为 None 时从事件队列中已有的事件继续执行
+    pub(super) async fn run_with_digest(
+        &self,
+        ctx: Arc<FlowContext>,
+        initial: Option<AgentMessage>,
+        run_id: &str,
+        visits: Option<Arc<VisitLog>>,
+    ) -> Result<FlowExecution> {
+        let Some(hook) = &self.digest_hook else {
+            return self.run(ctx, initial, run_id, visits).await;
+        };
+
+        let started = std::time::Instant::now();
+        let result = self.run(Arc::clone(&ctx), initial, run_id, visits).await;
+        let digest = hook
+            .on_finish(&self.flow.name, &ctx, result.as_ref(), started.elapsed())
+            .await;
+        result.map(|mut execution| {
+            execution.digest = Some(digest);
+            execution
         })
-        .map_err(|_| AgentFlowError::Other(anyhow!("failed to enqueue initial event")))?;
+    }
+
+    async fn run(
+        &self,
+        ctx: Arc<FlowContext>,
+        initial: Option<AgentMessage>,
+        run_id: &str,
+        visits: Option<Arc<VisitLog>>,
+    ) -> Result<FlowExecution> {
+        tracing::debug!(
+            run_id,
+            flow = %self.flow.name,
+            start = %self.flow.start,
+            "flow run started"
+        );
+
+        let sender = EventSender::new(Arc::clone(&self.event_queue), run_id);
+        let started = initial.is_some();
+        if let Some(mut initial) = initial {
+            let node = self.entry_node(&ctx, &mut initial).await?;
+            sender
+                .send(FlowEvent {
+                    node,
+                    message: initial.into(),
+                    iterations: 0,
+                    trace_id: crate::agent::message::uuid(),
+                    source: "__start__".to_string(),
+                })
+                .await?;
+        }
+
+        // 持久化队列的运行可能在其他进程中恢复，协调状态需要保存在存储中
+        let durable = self.event_queue.is_durable();
+        if durable && started {
+            record_run_version(ctx.store().as_ref(), run_id, self.flow.version).await?;
+        }
+        let shared = if durable {
+            SharedState::with_store(ctx.store(), run_id)
+        } else {
+            SharedState::default()
+        };
+        let shared = Arc::new(SharedState {
+            explain: self.explain.then(ExplainLog::default),
+            node_notifier: self
+                .notifier
+                .as_ref()
+                .filter(|notifier| notifier.wants_nodes())
+                .map(|notifier| NodeNotifier::new(Arc::clone(notifier), &self.flow.name, run_id)),
+            visits,
+            progress: self
+                .progress
+                .as_ref()
+                .map(|reporter| RunProgress::new(&self.flow, run_id, reporter.clone())),
+            resume_tokens: self.resume_tokens.clone(),
+            max_concurrency: Some(self.max_concurrency),
+            ..shared
+        });
+
+        let result = self.drive(&ctx, &sender, run_id, &shared).await;
+        if result.is_err() {
+            self.compensate(&ctx, &shared, run_id).await;
+        }
+        if let Err(err) = self.event_queue.clear(run_id).await {
+            tracing::warn!(run_id, error = %err, "failed to clear event queue");
+        }
+        if durable {
+            clear_coordination(ctx.store().as_ref(), run_id, &self.flow).await;
+        }
 
+        let mut execution = result?;
+        execution.outputs = self
+            .flow
+            .extract_outputs(&ctx, execution.last_message.as_ref())
+            .await?;
+        if let Some(log) = &shared.explain {
+            execution.explanations = log.take().await;
+        }
+        if let Some(progress) = &shared.progress {
+            execution.progress = progress.take();
+        }
+        execution.dead_letters = std::mem::take(&mut *shared.dead_letters.lock().await);
+        execution.errors = std::mem::take(&mut *shared.errors.lock().await);
+        Ok(execution)
+    }
+
+    /// 从事件队列中取出事件并发处理，直到到达终点或队列为空
+    async fn drive(
+        &self,
+        ctx: &Arc<FlowContext>,
+        sender: &EventSender,
+        run_id: &str,
+        shared: &Arc<SharedState>,
+    ) -> Result<FlowExecution> {
         let mut join_set: JoinSet<Result<TaskResult>> = JoinSet::new();
-        let mut inflight = 0usize;
         let mut finished: Option<FlowExecution> = None;
-        let collected_errors: Vec<crate::error::FrameworkError> = Vec::new();
-        let shared = Arc::new(SharedState::default());
-
-        while finished.is_none() {
-            tokio::select! {
-                Some(result) = join_set.join_next(), if inflight > 0 => {
-                    inflight -= 1;
-                    match result {
-                        Ok(Ok(TaskResult::Continue)) => {},
-                        Ok(Ok(TaskResult::Finished(data))) => {
-                            finished = Some(FlowExecution {
-                                flow_name: self.flow.name.clone(),
-                                last_node: data.node,
-                                last_message: data.message,
-                                errors: collected_errors.clone(),
-                            });
-                        }
-                        Ok(Err(error)) => return Err(error),
-                        Err(join_error) => return Err(AgentFlowError::Other(join_error.into())),
-                    }
+        // 并发已满时为腾出队列空间而提前取出的事件，最多 `overflow_limit` 个
+        let mut overflow: VecDeque<Delivery> = VecDeque::new();
+
+        loop {
+            // 到达终点后不再取新事件，只等待进行中的任务
+            if finished.is_none() && join_set.len() < self.max_concurrency {
+                let next = match overflow.pop_front() {
+                    Some(delivery) => Some(delivery),
+                    None => self.event_queue.pop(run_id).await?,
+                };
+                if let Some(Delivery { event, receipt }) = next {
+                    tracing::debug!(
+                        run_id,
+                        node = %event.node,
+                        source = %event.source,
+                        inflight = join_set.len(),
+                        "dispatching event"
+                    );
+                    let executor = self.clone();
+                    let ctx = Arc::clone(ctx);
+                    let sender = sender.clone();
+                    let shared = Arc::clone(shared);
+                    let run_id = run_id.to_string();
+                    join_set.spawn(async move {
+                        let result = executor.process(event, ctx, sender, shared).await?;
+                        executor.event_queue.ack(&run_id, &receipt).await?;
+                        Ok(result)
+                    });
+                    continue;
                 }
-                Some(event) = rx.recv(), if finished.is_none() => {
-                    if debug_mode {
-                        use std::io::{self, Write};
-                        eprintln!("📥 接收到新事件: {} (来源: {})", event.node, event.source);
-                        io::stderr().flush().ok();
-                    }
-                    if inflight >= self.max_concurrency {
-                        if let Some(result) = join_set.join_next().await {
-                            inflight -= 1;
-                            match result {
-                                Ok(Ok(TaskResult::Continue)) => {}
-                                Ok(Ok(TaskResult::Finished(data))) => {
-                                    finished = Some(FlowExecution {
-                                        flow_name: self.flow.name.clone(),
-                                        last_node: data.node,
-                                        last_message: data.message,
-                                        errors: collected_errors.clone(),
-                                    });
-                                }
-                                Ok(Err(error)) => return Err(error),
-                                Err(join_error) => return Err(AgentFlowError::Other(join_error.into())),
-                            }
-                        }
+            }
+
+            // Join 超时后用部分结果结束时，不再等待未返回的分支
+            if finished.is_some() && shared.join_timed_out() {
+                join_set.abort_all();
+                break;
+            }
+
+            // 所有进行中的任务都在入队时（队列可能已满），继续取出事件暂存，
+            // 否则任务等不到空位、调度器又等不到任务完成；暂存也满时运行失败
+            let send_started = sender.send_started();
+            if finished.is_none()
+                && !join_set.is_empty()
+                && sender.pending_sends() >= join_set.len()
+            {
+                if let Some(delivery) = self.event_queue.pop(run_id).await? {
+                    if overflow.len() >= self.overflow_limit {
+                        let inflight = join_set.len();
+                        join_set.abort_all();
+                        return Err(AgentFlowError::Other(anyhow!(
+                            "event queue full and all {} in-flight tasks are blocked on it; increase the capacity or reduce fan-out",
+                            inflight
+                        )));
                     }
+                    overflow.push_back(delivery);
+                    continue;
+                }
+            }
 
-                    if finished.is_none() {
-                        let flow = Arc::clone(&self.flow);
-                        let agents = Arc::clone(&self.agents);
-                        let tools = Arc::clone(&self.tools);
-                        let ctx = Arc::clone(&ctx);
-                        let sender = tx.clone();
-                        let max_iterations = self.max_iterations;
-                        let shared = Arc::clone(&shared);
-                        let tool_orchestrator = self.tool_orchestrator.clone();
-
-                        if debug_mode {
-                            use std::io::{self, Write};
-                            eprintln!("   🔄 启动任务处理事件 (inflight: {})", inflight);
-                            io::stderr().flush().ok();
+            // 有等待中的 Join 超时时，同时等待任务完成和超时到期
+            let deadline = match finished {
+                Some(_) => None,
+                None => shared.next_join_deadline().await,
+            };
+            let result = match deadline {
+                Some(at) => {
+                    tokio::select! {
+                        result = join_set.join_next(), if !join_set.is_empty() => result,
+                        _ = send_started => continue,
+                        _ = tokio::time::sleep_until(at) => {
+                            for event in shared.take_expired_joins(at).await {
+                                sender.send(event).await?;
+                            }
+                            continue;
                         }
-                        join_set.spawn(async move {
-                            process_event(
-                                event,
-                                flow,
-                                agents,
-                                tools,
-                                ctx,
-                                sender,
-                                max_iterations,
-                                tool_orchestrator,
-                                shared,
-                            )
-                            .await
-                        });
-                        inflight += 1;
                     }
                 }
-                else => {
-                    if inflight == 0 {
-                        break;
+                None => {
+                    tokio::select! {
+                        result = join_set.join_next() => result,
+                        _ = send_started, if finished.is_none() => continue,
                     }
                 }
-            }
-        }
-
-        drop(rx);
-        drop(tx);
-
-        while let Some(result) = join_set.join_next().await {
+            };
+            // 队列为空且没有进行中的任务时结束
+            let Some(result) = result else {
+                break;
+            };
             match result {
                 Ok(Ok(TaskResult::Continue)) => {}
                 Ok(Ok(TaskResult::Finished(data))) => {
@@ -178,7 +1057,12 @@ impl FlowExecutor {
                             flow_name: self.flow.name.clone(),
                             last_node: data.node,
                             last_message: data.message,
-                            errors: collected_errors.clone(),
+                            errors: Vec::new(),
+                            digest: None,
+                            explanations: Vec::new(),
+                            dead_letters: Vec::new(),
+                            progress: Vec::new(),
+                            outputs: Default::default(),
                         });
                     }
                 }
@@ -187,6 +1071,18 @@ impl FlowExecutor {
             }
         }
 
-        finished.ok_or_else(|| AgentFlowError::Other(anyhow!("flow finished without result")))
+        if let Some(execution) = finished {
+            return Ok(execution);
+        }
+        // 所有分支都转成了死信、没有到达终点
+        let letters = shared.dead_letters.lock().await;
+        Err(AgentFlowError::Other(match letters.first() {
+            Some(letter) => anyhow!(
+                "flow finished without result; node `{}` failed: {}",
+                letter.node,
+                letter.error.message
+            ),
+            None => anyhow!("flow finished without result"),
+        }))
     }
 }
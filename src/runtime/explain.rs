@@ -0,0 +1,246 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tokio::sync::Mutex;
+use tracing::info;
+
+use crate::agent::AgentMessage;
+use crate::flow::constants::fields;
+use crate::flow::ConditionInfo;
+use crate::state::FlowContext;
+
+/// 路由决策类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExplainKind {
+    /// Decision 节点的分支判定
+    Decision,
+    /// 边上的条件转换
+    Transition,
+    /// Agent 自动路由（路由匹配结果）
+    AgentRoute,
+}
+
+/// 单次路由判定的说明
+#[derive(Clone, Debug, Serialize)]
+pub struct RouteExplanation {
+    pub node: String,
+    pub kind: ExplainKind,
+    pub target: String,
+    /// 是否走了该路由
+    pub taken: bool,
+    pub reason: String,
+    /// 判定时涉及的状态值
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub values: Map<String, Value>,
+}
+
+/// explain 模式下收集的路由说明
+#[derive(Default)]
+pub struct ExplainLog {
+    entries: Mutex<Vec<RouteExplanation>>,
+}
+
+impl ExplainLog {
+    /// 记录说明，同时输出到 trace（target: `agentflow::explain`）
+    pub async fn record(&self, explanation: RouteExplanation) {
+        info!(
+            target: "agentflow::explain",
+            node = %explanation.node,
+            kind = ?explanation.kind,
+            target_node = %explanation.target,
+            taken = explanation.taken,
+            reason = %explanation.reason,
+            "route decision"
+        );
+        self.entries.lock().await.push(explanation);
+    }
+
+    pub async fn take(&self) -> Vec<RouteExplanation> {
+        std::mem::take(&mut *self.entries.lock().await)
+    }
+}
+
+/// 说明一次条件判定：条件描述、结果以及相关状态的当前值
+pub async fn explain_condition(
+    ctx: &FlowContext,
+    description: Option<&ConditionInfo>,
+    has_condition: bool,
+    passed: bool,
+) -> (String, Map<String, Value>) {
+    let mut values = Map::new();
+    let Some(info) = description else {
+        let reason = if has_condition {
+            format!("custom condition evaluated to {}", passed)
+        } else {
+            "unconditional".to_string()
+        };
+        return (reason, values);
+    };
+
+    let mut observed = Vec::new();
+    for key in &info.keys {
+        let value = ctx.store().get(key).await.ok().flatten();
+        observed.push(match &value {
+            Some(v) => format!("`{}` = \"{}\"", key, v),
+            None => format!("`{}` is unset", key),
+        });
+        values.insert(key.clone(), json!(value));
+    }
+    let reason = if observed.is_empty() {
+        format!("{} -> {}", info.description, passed)
+    } else {
+        format!(
+            "{} -> {} ({})",
+            info.description,
+            passed,
+            observed.join(", ")
+        )
+    };
+    (reason, values)
+}
+
+/// 从 Agent 路由消息中提取路由理由（`route` / `route_reason` 字段）
+pub fn explain_agent_route(node: &str, target: &str, message: &AgentMessage) -> RouteExplanation {
+    let payload: Value = serde_json::from_str(&message.content).unwrap_or(Value::Null);
+    let route = payload.get(fields::ROUTE).and_then(Value::as_str);
+    let reason = match (
+        route,
+        payload.get(fields::ROUTE_REASON).and_then(Value::as_str),
+    ) {
+        (Some(route), Some(reason)) => format!("LLM selected route `{}`: {}", route, reason),
+        (Some(route), None) => format!("LLM selected route `{}`", route),
+        (None, _) => "agent branched without routing rationale".to_string(),
+    };
+    let mut values = Map::new();
+    if let Some(route) = route {
+        values.insert(fields::ROUTE.to_string(), json!(route));
+    }
+    RouteExplanation {
+        node: node.to_string(),
+        kind: ExplainKind::AgentRoute,
+        target: target.to_string(),
+        taken: true,
+        reason,
+        values,
+    }
+}
+
+/// 把说明追加到消息 metadata 的 `explain` 数组
+pub fn attach(message: &mut AgentMessage, explanation: &RouteExplanation) {
+    let metadata = message.metadata.get_or_insert_with(|| json!({}));
+    let Some(object) = metadata.as_object_mut() else {
+        return;
+    };
+    let entries = object
+        .entry("explain")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Some(entries) = entries.as_array_mut() {
+        entries.push(json!(explanation));
+    }
+}
+
+fn entries(message: &AgentMessage) -> Option<&Vec<Value>> {
+    message
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("explain"))
+        .and_then(Value::as_array)
+}
+
+/// 消息是否带有路由说明
+pub fn has_entries(message: &AgentMessage) -> bool {
+    entries(message).is_some()
+}
+
+/// 继承默认转换消息上的说明（实际发送的消息不是默认转换消息时使用）
+pub fn inherit(message: &mut AgentMessage, from: &AgentMessage) {
+    let Some(entries) = entries(from) else {
+        return;
+    };
+    let metadata = message.metadata.get_or_insert_with(|| json!({}));
+    if let Some(object) = metadata.as_object_mut() {
+        let target = object
+            .entry("explain")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Some(target) = target.as_array_mut() {
+            target.extend(entries.iter().cloned());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentRegistry};
+    use crate::error::Result;
+    use crate::flow::{condition_state_equals, FlowBuilder};
+    use crate::runtime::FlowExecutor;
+    use crate::state::MemoryStore;
+    use crate::tools::ToolRegistry;
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    struct PassAgent;
+
+    #[async_trait]
+    impl Agent for PassAgent {
+        fn name(&self) -> &'static str {
+            "pass"
+        }
+
+        async fn on_message(
+            &self,
+            _message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Continue { message: None })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_explain_records_transition_reasons() {
+        let mut agents = AgentRegistry::new();
+        register_agent("pass", Arc::new(PassAgent), &mut agents);
+        let mut builder = FlowBuilder::new("tiers");
+        builder
+            .add_agent_node("check", "pass")
+            .add_terminal_node("gold")
+            .add_terminal_node("silver")
+            .set_start("check")
+            .connect_conditional_described(
+                "check",
+                "gold",
+                Some("is_gold".to_string()),
+                condition_state_equals("tier", "gold"),
+                ConditionInfo::new("state `tier` == \"gold\"").with_key("tier"),
+            )
+            .connect_conditional("check", "silver", condition_state_equals("tier", "silver"));
+        let executor =
+            FlowExecutor::new(builder.build(), agents, ToolRegistry::new()).with_explain(true);
+
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        ctx.store().set("tier", "gold".to_string()).await.unwrap();
+        let execution = executor
+            .start(ctx, AgentMessage::user("hello"))
+            .await
+            .unwrap();
+
+        assert_eq!(execution.last_node, "gold");
+        assert_eq!(execution.explanations.len(), 2);
+        let taken = &execution.explanations[0];
+        assert_eq!(taken.kind, ExplainKind::Transition);
+        assert!(taken.taken);
+        assert_eq!(
+            taken.reason,
+            "transition `is_gold`: state `tier` == \"gold\" -> true (`tier` = \"gold\")"
+        );
+        assert_eq!(taken.values["tier"], "gold");
+        assert_eq!(
+            execution.explanations[1].reason,
+            "custom condition evaluated to false"
+        );
+
+        let metadata = execution.last_message.unwrap().metadata.unwrap();
+        assert_eq!(metadata["explain"][0]["target"], "gold");
+    }
+}
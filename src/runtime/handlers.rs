@@ -1,15 +1,36 @@
 use anyhow::anyhow;
-use std::io::{self, Write};
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::mpsc;
-use tracing::warn;
+use tracing::{debug, warn};
 
-use super::state::{make_join_message, SharedState};
+use super::channel::RunUpdate;
+use super::executor::SubFlows;
+use super::explain::{self, ExplainKind, RouteExplanation};
+use super::memo::{MemoCache, RecordingStore};
+use super::queue::EventSender;
+use super::state::{
+    make_join_message, make_map_message, make_partial_join_message, JoinProgress, MapProgress,
+    SharedState, JOIN_TIMEOUT_SOURCE,
+};
 use super::types::{FlowEvent, TaskFinished, TaskResult};
-use crate::agent::{AgentAction, AgentMessage, MessageRole};
+use crate::agent::{
+    AgentAction, AgentContext, AgentMessage, AgentRegistry, Attachment, MessageRole,
+};
 use crate::error::{AgentFlowError, Result};
-use crate::flow::{DecisionNode, Flow, JoinNode, LoopNode, ToolNode};
+use crate::flow::constants::prompt as prompt_consts;
+use crate::flow::services::routing::{
+    classify_decision, decision_prompt, select_speaker, speaker_prompt, DecisionOutcome,
+};
+use crate::flow::{
+    AwaitEventNode, DebateNode, DecisionNode, ExperimentNode, Flow, GroupChatNode, ImageGenNode,
+    JoinNode, JoinTimeoutPolicy, LlmDecisionNode, LoopNode, MapNode, SubFlowNode, ToolAggregate,
+    ToolNode, TransformNode, WaitNode,
+};
+use crate::llm::LlmRequest;
 use crate::state::FlowContext;
+use crate::tools::http_request::render_template;
 use crate::tools::{orchestrator::ToolOrchestrator, ToolRegistry};
 
 /// 处理 Agent Action
@@ -19,57 +40,52 @@ pub async fn handle_action(
     flow: Arc<Flow>,
     ctx: &Arc<FlowContext>,
     tools: &Arc<ToolRegistry>,
-    sender: mpsc::UnboundedSender<FlowEvent>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
 ) -> Result<TaskResult> {
     match action {
         AgentAction::Next { target, message } => {
             enqueue_event(
-                sender,
+                &sender,
                 target,
                 message,
                 event.iterations + 1,
                 &event.trace_id,
                 &event.node,
-            )?;
+            )
+            .await?;
             Ok(TaskResult::Continue)
         }
         AgentAction::Branch { branches } => {
-            let debug_mode = std::env::var("AGENTFLOW_DEBUG").is_ok();
-            if debug_mode {
-                use std::io::{self, Write};
-                eprintln!(
-                    "  🌿 Agent 返回 Branch action，路由到 {} 个目标节点",
-                    branches.len()
-                );
-                for (target, _) in &branches {
-                    if flow.node(target).is_some() {
-                        eprintln!("    ➡️  路由到节点: {}", target);
-                    }
-                }
-                io::stderr().flush().ok();
-            }
+            debug!(
+                node = %event.node,
+                targets = ?branches.keys().collect::<Vec<_>>(),
+                "agent returned branch action"
+            );
             let mut dispatched = false;
-            for (target, message) in branches {
+            for (target, mut message) in branches {
                 if flow.node(&target).is_some() {
+                    if let Some(log) = &shared.explain {
+                        let explanation =
+                            explain::explain_agent_route(&event.node, &target, &message);
+                        explain::attach(&mut message, &explanation);
+                        log.record(explanation).await;
+                    }
                     enqueue_event(
-                        sender.clone(),
+                        &sender,
                         target,
                         message,
                         event.iterations + 1,
                         &event.trace_id,
                         &event.node,
-                    )?;
+                    )
+                    .await?;
                     dispatched = true;
                 }
             }
             if dispatched {
                 Ok(TaskResult::Continue)
             } else {
-                if debug_mode {
-                    use std::io::{self, Write};
-                    eprintln!("  ❌ 没有找到有效的分支，停止工作流");
-                    io::stderr().flush().ok();
-                }
                 warn!("No valid branch found, stopping flow");
                 Ok(TaskResult::Finished(TaskFinished {
                     node: event.node.clone(),
@@ -97,13 +113,14 @@ pub async fn handle_action(
 
             if let Some(target) = on_complete {
                 enqueue_event(
-                    sender,
+                    &sender,
                     target,
                     tool_message,
                     event.iterations + 1,
                     &event.trace_id,
                     &event.node,
-                )?;
+                )
+                .await?;
                 Ok(TaskResult::Continue)
             } else {
                 Ok(TaskResult::Finished(TaskFinished {
@@ -122,48 +139,52 @@ pub async fn handle_action(
             }))
         }
         AgentAction::Continue { message } => {
-            let debug_mode = std::env::var("AGENTFLOW_DEBUG").is_ok();
-            let transitions = next_from_flow(&event.node, &flow, ctx).await?;
+            let transitions = next_from_flow(&event.node, &flow, ctx, shared).await?;
             if transitions.is_empty() {
-                if debug_mode {
-                    use std::io::{self, Write};
-                    eprintln!("  ⚠️  节点 {} 没有后续节点，工作流结束", event.node);
-                    io::stderr().flush().ok();
-                }
+                debug!(node = %event.node, "node has no outgoing transitions, finishing flow");
                 return Ok(TaskResult::Finished(TaskFinished {
                     node: event.node.clone(),
                     message,
                 }));
             }
 
-            if debug_mode {
-                use std::io::{self, Write};
-                eprintln!(
-                    "  ➡️  节点 {} 有 {} 个后续节点",
-                    event.node,
-                    transitions.len()
-                );
-                for (target, _) in &transitions {
-                    eprintln!("    → 路由到: {}", target);
-                }
-                io::stderr().flush().ok();
-            }
+            debug!(
+                node = %event.node,
+                targets = ?transitions.iter().map(|(target, _)| target).collect::<Vec<_>>(),
+                "routing to next nodes"
+            );
+            // 各分支共享同一条消息，只有需要附加路由解释时才复制
+            let message = message.map(Arc::new);
             for (target, default_message) in transitions {
-                let to_send = message
-                    .as_ref()
-                    .cloned()
-                    .unwrap_or_else(|| default_message.clone());
+                let to_send = match &message {
+                    Some(message) if explain::has_entries(&default_message) => {
+                        let mut to_send = (**message).clone();
+                        explain::inherit(&mut to_send, &default_message);
+                        Arc::new(to_send)
+                    }
+                    Some(message) => Arc::clone(message),
+                    None => Arc::new(default_message),
+                };
                 enqueue_event(
-                    sender.clone(),
+                    &sender,
                     target,
                     to_send,
                     event.iterations + 1,
                     &event.trace_id,
                     &event.node,
-                )?;
+                )
+                .await?;
             }
             Ok(TaskResult::Continue)
         }
+        AgentAction::Refused { refusal, message } => {
+            warn!(node = %event.node, %refusal, "Agent refused, stopping flow");
+            ctx.push_message(message.clone());
+            Ok(TaskResult::Finished(TaskFinished {
+                node: event.node.clone(),
+                message: Some(message),
+            }))
+        }
     }
 }
 
@@ -173,31 +194,48 @@ pub async fn handle_decision_node(
     node_name: &str,
     event: &FlowEvent,
     ctx: &Arc<FlowContext>,
-    sender: mpsc::UnboundedSender<FlowEvent>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
 ) -> Result<TaskResult> {
-    let debug_mode = std::env::var("AGENTFLOW_DEBUG").is_ok();
-    if debug_mode {
-        eprintln!("  🔀 Decision 节点: {} 开始评估分支条件", node_name);
-        io::stderr().flush().ok();
-    }
+    debug!(node = %node_name, "evaluating decision branches");
     let mut matched: Vec<crate::flow::DecisionBranch> = Vec::new();
+    let mut explanations: Vec<RouteExplanation> = Vec::new();
 
     for branch in &decision.branches {
         let passes = if let Some(condition) = &branch.condition {
             let result = (condition)(ctx).await;
-            if debug_mode {
-                eprintln!(
-                    "    🔍 检查分支条件: {:?} - {}",
-                    branch.name,
-                    if result { "✅" } else { "❌" }
-                );
-                io::stderr().flush().ok();
-            }
+            debug!(
+                node = %node_name,
+                branch = ?branch.name,
+                passed = result,
+                "checked decision branch condition"
+            );
             result
         } else {
             true
         };
 
+        if shared.explain.is_some() {
+            let (reason, values) = explain::explain_condition(
+                ctx,
+                branch.description.as_ref(),
+                branch.condition.is_some(),
+                passes,
+            )
+            .await;
+            explanations.push(RouteExplanation {
+                node: node_name.to_string(),
+                kind: ExplainKind::Decision,
+                target: branch.target.clone(),
+                taken: passes,
+                reason: match &branch.name {
+                    Some(name) => format!("branch `{}`: {}", name, reason),
+                    None => reason,
+                },
+                values,
+            });
+        }
+
         if passes {
             matched.push(branch.clone());
             if matches!(decision.policy, crate::flow::DecisionPolicy::FirstMatch) {
@@ -206,6 +244,12 @@ pub async fn handle_decision_node(
         }
     }
 
+    if let Some(log) = &shared.explain {
+        for explanation in &explanations {
+            log.record(explanation.clone()).await;
+        }
+    }
+
     if matched.is_empty() {
         warn!(node = %node_name, "Decision node had no matching branches");
         return Err(AgentFlowError::DecisionNoMatch {
@@ -213,16 +257,11 @@ pub async fn handle_decision_node(
         });
     }
 
-    if debug_mode {
-        eprintln!("  ✅ Decision 节点匹配到 {} 个分支", matched.len());
-        for branch in &matched {
-            eprintln!(
-                "    ➡️  路由到: {} (分支: {:?})",
-                branch.target, branch.name
-            );
-        }
-        io::stderr().flush().ok();
-    }
+    debug!(
+        node = %node_name,
+        targets = ?matched.iter().map(|branch| &branch.target).collect::<Vec<_>>(),
+        "decision branches matched"
+    );
     for branch in matched {
         let metadata = serde_json::json!({
             "decision": {
@@ -232,28 +271,204 @@ pub async fn handle_decision_node(
                 "source_metadata": event.message.metadata.clone(),
             }
         });
-        let message = AgentMessage {
+        let mut message = AgentMessage {
             id: crate::agent::message::uuid(),
             role: event.message.role.clone(),
             from: node_name.to_string(),
             to: Some(branch.target.clone()),
             content: event.message.content.clone(),
             metadata: Some(metadata),
+            attachments: Vec::new(),
         };
+        for explanation in explanations
+            .iter()
+            .filter(|e| e.taken && e.target == branch.target)
+        {
+            explain::attach(&mut message, explanation);
+        }
 
         enqueue_event(
-            sender.clone(),
+            &sender,
             branch.target.clone(),
             message,
             event.iterations + 1,
             &event.trace_id,
             node_name,
-        )?;
+        )
+        .await?;
     }
 
     Ok(TaskResult::Continue)
 }
 
+/// 处理 LLM 决策节点：按模型返回的标签转发输入消息，无法匹配或调用失败时进入 fallback
+pub async fn handle_llm_decision_node(
+    decision: &LlmDecisionNode,
+    node_name: &str,
+    event: &FlowEvent,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
+) -> Result<TaskResult> {
+    let client = decision.client.as_ref().ok_or_else(|| {
+        AgentFlowError::Other(anyhow!(
+            "LLM decision node `{}` has no LLM client",
+            node_name
+        ))
+    })?;
+    let request = LlmRequest {
+        system: Some(decision_prompt(decision)),
+        user: event.message.content.clone(),
+        temperature: 0.0,
+        metadata: None,
+        content: Vec::new(),
+        params: Default::default(),
+    };
+    let outcome = match client.complete(request).await {
+        Ok(response) => classify_decision(decision, &response.content, node_name)?,
+        Err(err) => {
+            warn!(node = %node_name, error = %err, "LLM decision failed, using fallback");
+            DecisionOutcome {
+                branches: Vec::new(),
+                reason: Some(format!("LLM call failed: {}", err)),
+            }
+        }
+    };
+
+    let routes: Vec<(Option<&str>, &str)> = if outcome.branches.is_empty() {
+        vec![(None, decision.fallback.as_str())]
+    } else {
+        outcome
+            .branches
+            .iter()
+            .map(|&index| {
+                let branch = &decision.branches[index];
+                (Some(branch.label.as_str()), branch.target.as_str())
+            })
+            .collect()
+    };
+    let reason = outcome
+        .reason
+        .unwrap_or_else(|| prompt_consts::DEFAULT_ROUTE_REASON.to_string());
+
+    for (label, target) in routes {
+        let metadata = serde_json::json!({
+            "decision": {
+                "node": node_name,
+                "branch": label,
+                "fallback": label.is_none(),
+                "reason": reason,
+                "source_message_id": event.message.id.clone(),
+                "source_metadata": event.message.metadata.clone(),
+            }
+        });
+        let mut message = AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: event.message.role.clone(),
+            from: node_name.to_string(),
+            to: Some(target.to_string()),
+            content: event.message.content.clone(),
+            metadata: Some(metadata),
+            attachments: event.message.attachments.clone(),
+        };
+        if let Some(log) = &shared.explain {
+            let explanation = RouteExplanation {
+                node: node_name.to_string(),
+                kind: ExplainKind::Decision,
+                target: target.to_string(),
+                taken: true,
+                reason: format!("llm label `{}`: {}", label.unwrap_or("fallback"), reason),
+                values: Default::default(),
+            };
+            explain::attach(&mut message, &explanation);
+            log.record(explanation).await;
+        }
+
+        enqueue_event(
+            &sender,
+            target.to_string(),
+            message,
+            event.iterations + 1,
+            &event.trace_id,
+            node_name,
+        )
+        .await?;
+    }
+
+    Ok(TaskResult::Continue)
+}
+
+/// 处理实验分流节点：会话内已有分配时沿用，否则按权重分配并写入会话存储
+pub async fn handle_experiment_node(
+    experiment: &ExperimentNode,
+    node_name: &str,
+    event: &FlowEvent,
+    ctx: &Arc<FlowContext>,
+    sender: EventSender,
+) -> Result<TaskResult> {
+    let key = format!("experiment:{}", experiment.experiment);
+    let session = ctx.session_id().map(|_| ctx.session());
+    let stored = match &session {
+        Some(session) => session.get(&key).await?,
+        None => None,
+    };
+    let (variant, sticky) = match stored.as_deref().and_then(|name| experiment.variant(name)) {
+        Some(variant) => (variant, true),
+        None => {
+            let seed = ctx
+                .session_id()
+                .map(str::to_string)
+                .unwrap_or_else(crate::agent::message::uuid);
+            let variant = experiment.assign(&seed).ok_or_else(|| {
+                AgentFlowError::Other(anyhow!(
+                    "experiment node `{}` has no variant with a positive weight",
+                    node_name
+                ))
+            })?;
+            if let Some(session) = &session {
+                session.set(&key, variant.name.clone()).await?;
+            }
+            (variant, false)
+        }
+    };
+    debug!(
+        node = %node_name,
+        experiment = %experiment.experiment,
+        variant = %variant.name,
+        sticky,
+        "experiment variant assigned"
+    );
+
+    let mut message = (*event.message).clone();
+    message.id = crate::agent::message::uuid();
+    message.from = node_name.to_string();
+    message.to = Some(variant.target.clone());
+    let metadata = message
+        .metadata
+        .get_or_insert_with(|| serde_json::json!({}));
+    if let Some(object) = metadata.as_object_mut() {
+        object.insert(
+            "experiment".to_string(),
+            serde_json::json!({
+                "name": experiment.experiment,
+                "variant": variant.name,
+                "node": node_name,
+                "sticky": sticky,
+            }),
+        );
+    }
+
+    enqueue_event(
+        &sender,
+        variant.target.clone(),
+        message,
+        event.iterations + 1,
+        &event.trace_id,
+        node_name,
+    )
+    .await?;
+    Ok(TaskResult::Continue)
+}
+
 /// 处理 Join 节点
 pub async fn handle_join_node(
     join: &JoinNode,
@@ -261,110 +476,295 @@ pub async fn handle_join_node(
     event: &FlowEvent,
     ctx: &Arc<FlowContext>,
     flow: &Arc<Flow>,
-    sender: mpsc::UnboundedSender<FlowEvent>,
+    sender: EventSender,
     shared: &Arc<SharedState>,
 ) -> Result<TaskResult> {
-    let debug_mode = std::env::var("AGENTFLOW_DEBUG").is_ok();
     let key = format!("{}::{}", event.trace_id, node_name);
+    debug!(node = %node_name, source = %event.source, "join node received message");
 
-    if debug_mode {
-        use std::io::{self, Write};
-        eprintln!(
-            "  🔗 Join 节点: {} 收到消息 (来源: {})",
-            node_name, event.source
-        );
-        io::stderr().flush().ok();
+    if event.source == JOIN_TIMEOUT_SOURCE {
+        return handle_join_timeout(join, node_name, &key, event, ctx, flow, sender, shared).await;
     }
 
-    let mut states = shared.join_states.lock().await;
-    let state = states.entry(key.clone()).or_insert_with(|| {
-        if debug_mode {
-            use std::io::{self, Write};
-            eprintln!(
-                "    📋 初始化 Join 状态，等待 {} 个节点",
-                join.inbound.len()
-            );
-            for inbound in &join.inbound {
-                eprintln!("      - {}", inbound);
-            }
-            io::stderr().flush().ok();
+    let progress = shared
+        .record_join(&key, join, &event.source, &event.message)
+        .await?;
+    if let (JoinProgress::Waiting, Some(timeout)) = (&progress, join.timeout) {
+        let timeout_event = FlowEvent {
+            node: node_name.to_string(),
+            message: AgentMessage::system(format!("join `{}` timed out", node_name)).into(),
+            iterations: event.iterations,
+            trace_id: event.trace_id.clone(),
+            source: JOIN_TIMEOUT_SOURCE.to_string(),
+        };
+        shared
+            .arm_join_timeout(&key, tokio::time::Instant::now() + timeout, timeout_event)
+            .await;
+    }
+    let collected = match progress {
+        JoinProgress::Ignored => {
+            debug!(node = %node_name, source = %event.source, "join ignored unexpected source");
+            return Ok(TaskResult::Continue);
         }
-        crate::runtime::state::JoinState::new(join.clone())
-    });
-
-    let source_node = event.source.clone();
-
-    if !state.expected.is_empty() && !state.expected.contains(&source_node) {
-        if debug_mode {
-            use std::io::{self, Write};
-            eprintln!("    ⚠️  来源节点 {} 不在预期列表中，忽略", source_node);
-            io::stderr().flush().ok();
+        JoinProgress::Waiting => {
+            debug!(node = %node_name, source = %event.source, "join recorded message");
+            return Ok(TaskResult::Continue);
         }
-        drop(states);
-        return Ok(TaskResult::Continue);
-    }
+        JoinProgress::Ready(collected) => collected,
+    };
 
-    if debug_mode {
-        use std::io::{self, Write};
-        eprintln!("    ✅ 来源节点 {} 匹配，记录消息", source_node);
-        io::stderr().flush().ok();
-    }
-    if let Some(collected) = state.record(source_node, event.message.clone()) {
-        if debug_mode {
-            use std::io::{self, Write};
-            eprintln!("    🎉 Join 节点已收集到所有预期消息，继续执行");
-            io::stderr().flush().ok();
-        }
-        let aggregated = make_join_message(node_name, &collected);
-        states.remove(&key);
-        drop(states);
+    debug!(node = %node_name, "join collected all expected messages");
+    shared.disarm_join_timeout(&key).await;
+    let aggregated = make_join_message(node_name, &collected);
+    forward_join(aggregated, node_name, event, ctx, flow, sender, shared).await
+}
 
-        let transitions = next_from_flow(node_name, flow, ctx).await?;
-        if transitions.is_empty() {
-            return Ok(TaskResult::Finished(TaskFinished {
-                node: node_name.to_string(),
-                message: Some(aggregated),
-            }));
+/// Join 超时：按 `on_timeout` 失败、用部分结果继续或转到指定节点
+#[allow(clippy::too_many_arguments)]
+async fn handle_join_timeout(
+    join: &JoinNode,
+    node_name: &str,
+    key: &str,
+    event: &FlowEvent,
+    ctx: &Arc<FlowContext>,
+    flow: &Arc<Flow>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
+) -> Result<TaskResult> {
+    let Some(collected) = shared.expire_join(key).await? else {
+        return Ok(TaskResult::Continue);
+    };
+    let missing: Vec<String> = join
+        .inbound
+        .iter()
+        .filter(|name| !collected.contains_key(*name))
+        .cloned()
+        .collect();
+    warn!(node = %node_name, ?missing, policy = ?join.on_timeout, "Join node timed out");
+    let aggregated = make_partial_join_message(node_name, &collected, &missing);
+    match &join.on_timeout {
+        JoinTimeoutPolicy::Fail => Err(AgentFlowError::JoinIncomplete {
+            node: node_name.to_string(),
+        }),
+        JoinTimeoutPolicy::ProceedWithPartial => {
+            forward_join(aggregated, node_name, event, ctx, flow, sender, shared).await
         }
-
-        for (target, default_message) in transitions {
-            let to_send = AgentMessage {
-                to: default_message.to,
-                ..aggregated.clone()
+        JoinTimeoutPolicy::RouteTo(target) => {
+            let message = AgentMessage {
+                to: Some(target.clone()),
+                ..aggregated
             };
             enqueue_event(
-                sender.clone(),
-                target,
-                to_send,
+                &sender,
+                target.clone(),
+                message,
                 event.iterations + 1,
                 &event.trace_id,
                 node_name,
-            )?;
+            )
+            .await?;
+            Ok(TaskResult::Continue)
         }
-        Ok(TaskResult::Continue)
-    } else {
-        Ok(TaskResult::Continue)
     }
 }
 
+/// 把合并消息发给 Join 节点的后继，没有后继时结束运行
+async fn forward_join(
+    aggregated: AgentMessage,
+    node_name: &str,
+    event: &FlowEvent,
+    ctx: &Arc<FlowContext>,
+    flow: &Arc<Flow>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
+) -> Result<TaskResult> {
+    let transitions = next_from_flow(node_name, flow, ctx, shared).await?;
+    if transitions.is_empty() {
+        return Ok(TaskResult::Finished(TaskFinished {
+            node: node_name.to_string(),
+            message: Some(aggregated),
+        }));
+    }
+
+    for (target, default_message) in transitions {
+        let mut to_send = AgentMessage {
+            to: default_message.to.clone(),
+            ..aggregated.clone()
+        };
+        explain::inherit(&mut to_send, &default_message);
+        enqueue_event(
+            &sender,
+            target,
+            to_send,
+            event.iterations + 1,
+            &event.trace_id,
+            node_name,
+        )
+        .await?;
+    }
+    Ok(TaskResult::Continue)
+}
+
+/// Map 分支事件的 trace id：在原 trace id 后追加 `#<节点>[<下标>]`
+fn map_trace_id(trace_id: &str, node_name: &str, index: usize) -> String {
+    format!("{}#{}[{}]", trace_id, node_name, index)
+}
+
+/// 解析 Map 分支的 trace id，返回原 trace id 和元素下标
+fn parse_map_trace_id<'a>(trace_id: &'a str, node_name: &str) -> Option<(&'a str, usize)> {
+    let (parent, index) = trace_id
+        .strip_suffix(']')?
+        .rsplit_once(&format!("#{}[", node_name))?;
+    Some((parent, index.parse().ok()?))
+}
+
+/// 取出 `over` 指向的数组：先按 JSON 解析消息内容，再查找 metadata
+fn map_items(map: &MapNode, node_name: &str, message: &AgentMessage) -> Result<Vec<Value>> {
+    let content = serde_json::from_str::<Value>(&message.content).ok();
+    let items = [content.as_ref(), message.metadata.as_ref()]
+        .into_iter()
+        .flatten()
+        .find_map(|value| value.pointer(&map.over).and_then(Value::as_array).cloned());
+    items.ok_or_else(|| {
+        AgentFlowError::Other(anyhow!(
+            "map node `{}`: `{}` does not point to an array in the input message",
+            node_name,
+            map.over
+        ))
+    })
+}
+
+/// 派发 Map 元素：字符串元素直接作为用户消息内容，其余序列化为 JSON
+async fn dispatch_map_items(
+    map: &MapNode,
+    node_name: &str,
+    items: Vec<(usize, Value)>,
+    total: usize,
+    iterations: u32,
+    trace_id: &str,
+    sender: &EventSender,
+) -> Result<()> {
+    for (index, item) in items {
+        let content = match item {
+            Value::String(text) => text,
+            other => other.to_string(),
+        };
+        let message = AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::User,
+            from: node_name.to_string(),
+            to: Some(map.body.clone()),
+            content,
+            metadata: Some(serde_json::json!({
+                "map": { "node": node_name, "index": index, "total": total }
+            })),
+            attachments: Vec::new(),
+        };
+        enqueue_event(
+            sender,
+            map.body.clone(),
+            message,
+            iterations + 1,
+            &map_trace_id(trace_id, node_name, index),
+            node_name,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// 处理 Map 节点：外部输入展开为分支，`body` 回传的输出按合并策略收集
+pub async fn handle_map_node(
+    map: &MapNode,
+    node_name: &str,
+    event: &FlowEvent,
+    ctx: &Arc<FlowContext>,
+    flow: &Arc<Flow>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
+) -> Result<TaskResult> {
+    let (trace_id, results, total) = match parse_map_trace_id(&event.trace_id, node_name) {
+        None => {
+            let items = map_items(map, node_name, &event.message)?;
+            let total = items.len();
+            if total == 0 {
+                (event.trace_id.as_str(), Vec::new(), 0)
+            } else {
+                let key = format!("{}::{}", event.trace_id, node_name);
+                let first = shared.start_map(&key, map, items, event.iterations).await?;
+                dispatch_map_items(
+                    map,
+                    node_name,
+                    first,
+                    total,
+                    event.iterations,
+                    &event.trace_id,
+                    &sender,
+                )
+                .await?;
+                return Ok(TaskResult::Continue);
+            }
+        }
+        Some((parent, index)) => {
+            let key = format!("{}::{}", parent, node_name);
+            match shared.record_map(&key, map, index, &event.message).await? {
+                MapProgress::Ignored => return Ok(TaskResult::Continue),
+                MapProgress::Dispatch {
+                    items,
+                    total,
+                    iterations,
+                } => {
+                    dispatch_map_items(map, node_name, items, total, iterations, parent, &sender)
+                        .await?;
+                    return Ok(TaskResult::Continue);
+                }
+                MapProgress::Ready { results, total } => (parent, results, total),
+            }
+        }
+    };
+
+    let aggregated = make_map_message(node_name, total, &results);
+    let transitions = next_from_flow(node_name, flow, ctx, shared).await?;
+    if transitions.is_empty() {
+        return Ok(TaskResult::Finished(TaskFinished {
+            node: node_name.to_string(),
+            message: Some(aggregated),
+        }));
+    }
+    for (target, default_message) in transitions {
+        let mut to_send = AgentMessage {
+            to: default_message.to.clone(),
+            ..aggregated.clone()
+        };
+        explain::inherit(&mut to_send, &default_message);
+        enqueue_event(
+            &sender,
+            target,
+            to_send,
+            event.iterations + 1,
+            trace_id,
+            node_name,
+        )
+        .await?;
+    }
+    Ok(TaskResult::Continue)
+}
+
 /// 处理 Loop 节点
 pub async fn handle_loop_node(
     loop_node: &LoopNode,
     node_name: &str,
     event: &FlowEvent,
     ctx: &Arc<FlowContext>,
-    sender: mpsc::UnboundedSender<FlowEvent>,
+    sender: EventSender,
     shared: &Arc<SharedState>,
 ) -> Result<TaskResult> {
     let key = format!("{}::{}", event.trace_id, node_name);
-    let mut loops = shared.loop_states.lock().await;
-    let state = loops
-        .entry(key.clone())
-        .or_insert_with(|| crate::runtime::state::LoopState::default());
+    let iterations = shared.loop_iterations(&key).await?;
 
     if let Some(max) = loop_node.max_iterations {
-        if state.iterations >= max {
-            loops.remove(&key);
+        if iterations >= max {
+            shared.clear_loop(&key).await?;
             return Err(AgentFlowError::LoopBoundExceeded {
                 node: node_name.to_string(),
                 max,
@@ -375,62 +775,692 @@ pub async fn handle_loop_node(
     if let Some(condition) = &loop_node.condition {
         let continue_loop = (condition)(ctx).await;
         if !continue_loop {
-            loops.remove(&key);
+            shared.clear_loop(&key).await?;
             if let Some(exit) = &loop_node.exit {
                 enqueue_event(
-                    sender,
+                    &sender,
                     exit.clone(),
                     event.message.clone(),
                     event.iterations + 1,
                     &event.trace_id,
                     node_name,
-                )?;
+                )
+                .await?;
                 return Ok(TaskResult::Continue);
             } else {
                 return Ok(TaskResult::Finished(TaskFinished {
                     node: node_name.to_string(),
-                    message: Some(event.message.clone()),
+                    message: Some((*event.message).clone()),
                 }));
             }
         }
     }
 
-    state.iterations += 1;
-    drop(loops);
+    shared.advance_loop(&key).await?;
 
     enqueue_event(
-        sender,
+        &sender,
         loop_node.entry.clone(),
         event.message.clone(),
         event.iterations + 1,
         &event.trace_id,
         node_name,
-    )?;
+    )
+    .await?;
     Ok(TaskResult::Continue)
 }
 
 /// 处理 Tool 节点
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_tool_node(
     tool_node: &ToolNode,
     node_name: &str,
     event: &FlowEvent,
     ctx: &Arc<FlowContext>,
     flow: Arc<Flow>,
-    sender: mpsc::UnboundedSender<FlowEvent>,
+    sender: EventSender,
     tool_orchestrator: Option<Arc<ToolOrchestrator>>,
+    shared: &Arc<SharedState>,
 ) -> Result<TaskResult> {
     let orchestrator = tool_orchestrator
         .ok_or_else(|| AgentFlowError::Other(anyhow!("tool orchestrator not configured")))?;
 
     let params = tool_node.params.clone().unwrap_or_else(|| serde_json::json!({}));
 
-    let message = orchestrator
-        .execute_pipeline_with_params(&tool_node.pipeline, params, ctx)
+    let message = if tool_node.parallel.is_empty() {
+        orchestrator
+            .execute_pipeline_with_params(&tool_node.pipeline, params.clone(), ctx)
+            .await?
+    } else {
+        run_parallel_pipelines(
+            tool_node,
+            node_name,
+            &orchestrator,
+            &params,
+            ctx,
+            shared.max_concurrency,
+        )
+        .await?
+    };
+    if let Some(compensate) = &tool_node.compensate {
+        shared
+            .record_compensation(node_name, compensate, params, &message)
+            .await;
+    }
+
+    forward_output(message, node_name, event, ctx, &flow, sender, shared).await
+}
+
+/// 并发执行 Tool 节点的全部流水线，按 `aggregate` 合并输出；同时执行的流水线不超过 `max_concurrency`
+async fn run_parallel_pipelines(
+    tool_node: &ToolNode,
+    node_name: &str,
+    orchestrator: &ToolOrchestrator,
+    params: &Value,
+    ctx: &FlowContext,
+    max_concurrency: Option<usize>,
+) -> Result<AgentMessage> {
+    use futures::stream::{FuturesUnordered, StreamExt};
+
+    let pipelines: Vec<&str> = std::iter::once(tool_node.pipeline.as_str())
+        .chain(tool_node.parallel.iter().map(String::as_str))
+        .collect();
+    debug!(node = %node_name, ?pipelines, "executing tool pipelines in parallel");
+    let permits = tokio::sync::Semaphore::new(max_concurrency.unwrap_or(pipelines.len()).max(1));
+    let permits = &permits;
+    let mut pending: FuturesUnordered<_> = pipelines
+        .iter()
+        .map(|pipeline| async move {
+            let result = match permits.acquire().await {
+                Ok(_permit) => {
+                    orchestrator
+                        .execute_pipeline_with_params(pipeline, params.clone(), ctx)
+                        .await
+                }
+                Err(err) => Err(AgentFlowError::Other(err.into())),
+            };
+            (*pipeline, result)
+        })
+        .collect();
+
+    let mut outputs = std::collections::HashMap::new();
+    let mut errors = Vec::new();
+    while let Some((pipeline, result)) = pending.next().await {
+        match (result, tool_node.aggregate) {
+            // 丢弃 `pending` 即取消其余流水线
+            (Ok(message), ToolAggregate::First) => return Ok(message),
+            (Ok(message), _) => {
+                let output = serde_json::from_str(&message.content)
+                    .unwrap_or_else(|_| Value::String(message.content.clone()));
+                outputs.insert(pipeline, output);
+            }
+            (Err(err), ToolAggregate::All) => return Err(err),
+            (Err(err), _) => {
+                warn!(node = %node_name, pipeline, error = %err, "tool pipeline failed");
+                errors.push((pipeline, err.to_string()));
+            }
+        }
+    }
+    if outputs.is_empty() {
+        let details: Vec<String> = errors
+            .iter()
+            .map(|(pipeline, error)| format!("{}: {}", pipeline, error))
+            .collect();
+        return Err(AgentFlowError::Other(anyhow!(
+            "all pipelines of tool node `{}` failed: {}",
+            node_name,
+            details.join("; ")
+        )));
+    }
+
+    let mut results = serde_json::Map::new();
+    for &pipeline in &pipelines {
+        let result = match outputs.remove(pipeline) {
+            Some(output) => output,
+            None => {
+                let error = errors
+                    .iter()
+                    .find(|(name, _)| *name == pipeline)
+                    .map(|(_, error)| error.clone());
+                serde_json::json!({ "error": error })
+            }
+        };
+        results.insert(pipeline.to_string(), result);
+    }
+    let payload = serde_json::json!({ "tool_node": node_name, "results": results });
+    Ok(AgentMessage {
+        role: MessageRole::Tool,
+        from: node_name.to_string(),
+        metadata: Some(payload.clone()),
+        ..AgentMessage::user(payload.to_string())
+    })
+}
+
+/// 处理群聊节点：在参与者之间轮流调用 Agent，结束后带着对话记录沿转换继续
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_group_chat_node(
+    chat: &GroupChatNode,
+    node_name: &str,
+    event: &FlowEvent,
+    ctx: &Arc<FlowContext>,
+    agents: &Arc<AgentRegistry>,
+    tools: &Arc<ToolRegistry>,
+    flow: Arc<Flow>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
+) -> Result<TaskResult> {
+    if chat.participants.is_empty() {
+        return Err(AgentFlowError::Other(anyhow!(
+            "group chat node `{}` has no participants",
+            node_name
+        )));
+    }
+    let runtime_handle = super::runtime::ExecutorRuntime {
+        ctx: Arc::clone(ctx),
+        tools: Arc::clone(tools),
+    };
+    let node_ctx = ctx.for_node(flow.name.clone(), node_name.to_string());
+    let agent_ctx = AgentContext {
+        flow_ctx: &node_ctx,
+        runtime: &runtime_handle,
+    };
+
+    let mut transcript = vec![(event.message.from.clone(), (*event.message).clone())];
+    let mut speaker: Option<usize> = None;
+    let mut terminated = false;
+    for turn in 0..chat.max_turns {
+        let index = next_speaker(chat, node_name, &transcript, speaker).await;
+        speaker = Some(index);
+        let agent_name = &chat.participants[index];
+        let input = AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::User,
+            from: node_name.to_string(),
+            to: Some(agent_name.clone()),
+            content: render_transcript(&transcript),
+            metadata: Some(serde_json::json!({
+                "group_chat": { "node": node_name, "turn": turn + 1, "speaker": agent_name }
+            })),
+            attachments: event.message.attachments.clone(),
+        };
+        let action = run_agent(agent_name, input, agents, &agent_ctx, shared).await?;
+        if matches!(action, AgentAction::Refused { .. }) {
+            return handle_action(action, event, flow, ctx, tools, sender, shared).await;
+        }
+        terminated = matches!(action, AgentAction::Finish { .. });
+        if let Some(message) = super::processor::action_output(&action) {
+            ctx.push_message(message.clone());
+            terminated |= chat
+                .terminate_on
+                .as_ref()
+                .is_some_and(|keyword| message.content.contains(keyword.as_str()));
+            transcript.push((agent_name.clone(), message));
+        }
+        if terminated {
+            break;
+        }
+    }
+
+    let turns = transcript.len() - 1;
+    let entries: Vec<Value> = transcript
+        .iter()
+        .map(|(speaker, message)| {
+            serde_json::json!({ "speaker": speaker, "content": message.content })
+        })
+        .collect();
+    let (_, last) = transcript
+        .pop()
+        .unwrap_or_else(|| (String::new(), (*event.message).clone()));
+    let mut metadata = match last.metadata.clone() {
+        Some(Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    metadata.insert(
+        "group_chat".to_string(),
+        serde_json::json!({
+            "node": node_name,
+            "turns": turns,
+            "terminated": terminated,
+            "transcript": entries,
+        }),
+    );
+    let message = AgentMessage {
+        id: crate::agent::message::uuid(),
+        role: MessageRole::Assistant,
+        from: node_name.to_string(),
+        to: None,
+        content: last.content,
+        metadata: Some(Value::Object(metadata)),
+        attachments: last.attachments,
+    };
+    handle_action(
+        AgentAction::Continue {
+            message: Some(message),
+        },
+        event,
+        flow,
+        ctx,
+        tools,
+        sender,
+        shared,
+    )
+    .await
+}
+
+/// 处理辩论节点：`proposer` 与 `critic` 交替执行，评审通过或达到最大轮数后带着最后一版方案继续
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_debate_node(
+    debate: &DebateNode,
+    node_name: &str,
+    event: &FlowEvent,
+    ctx: &Arc<FlowContext>,
+    agents: &Arc<AgentRegistry>,
+    tools: &Arc<ToolRegistry>,
+    flow: Arc<Flow>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
+) -> Result<TaskResult> {
+    let runtime_handle = super::runtime::ExecutorRuntime {
+        ctx: Arc::clone(ctx),
+        tools: Arc::clone(tools),
+    };
+    let node_ctx = ctx.for_node(flow.name.clone(), node_name.to_string());
+    let agent_ctx = AgentContext {
+        flow_ctx: &node_ctx,
+        runtime: &runtime_handle,
+    };
+    let turn_message = |role: MessageRole, to: &str, content: String, round: u32| AgentMessage {
+        id: crate::agent::message::uuid(),
+        role,
+        from: node_name.to_string(),
+        to: Some(to.to_string()),
+        content,
+        metadata: Some(serde_json::json!({ "debate": { "node": node_name, "round": round } })),
+        attachments: event.message.attachments.clone(),
+    };
+
+    let task = event.message.content.clone();
+    let mut proposal = (*event.message).clone();
+    let mut rounds = Vec::new();
+    let mut converged = false;
+    for round in 1..=debate.max_rounds {
+        let content = match rounds.last() {
+            Some((previous, critique)) => format!(
+                "{}\n\nPrevious proposal:\n{}\n\nCritique:\n{}",
+                task, previous, critique
+            ),
+            None => task.clone(),
+        };
+        let input = turn_message(MessageRole::User, &debate.proposer, content, round);
+        let action = run_agent(&debate.proposer, input, agents, &agent_ctx, shared).await?;
+        if matches!(action, AgentAction::Refused { .. }) {
+            return handle_action(action, event, flow, ctx, tools, sender, shared).await;
+        }
+        let Some(draft) = super::processor::action_output(&action) else {
+            return Err(AgentFlowError::Other(anyhow!(
+                "debate node `{}`: proposer `{}` returned no message",
+                node_name,
+                debate.proposer
+            )));
+        };
+        ctx.push_message(draft.clone());
+        proposal = draft;
+
+        let input = turn_message(
+            MessageRole::Agent,
+            &debate.critic,
+            proposal.content.clone(),
+            round,
+        );
+        let action = run_agent(&debate.critic, input, agents, &agent_ctx, shared).await?;
+        if matches!(action, AgentAction::Refused { .. }) {
+            return handle_action(action, event, flow, ctx, tools, sender, shared).await;
+        }
+        let critique =
+            super::processor::action_output(&action).unwrap_or_else(|| AgentMessage::system(""));
+        ctx.push_message(critique.clone());
+        converged = debate.is_approved(&critique);
+        rounds.push((proposal.content.clone(), critique.content));
+        if converged {
+            break;
+        }
+    }
+
+    let history: Vec<Value> = rounds
+        .iter()
+        .enumerate()
+        .map(|(index, (proposal, critique))| {
+            serde_json::json!({ "round": index + 1, "proposal": proposal, "critique": critique })
+        })
+        .collect();
+    let mut metadata = match proposal.metadata.clone() {
+        Some(Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    metadata.insert(
+        "debate".to_string(),
+        serde_json::json!({
+            "node": node_name,
+            "rounds": rounds.len(),
+            "converged": converged,
+            "history": history,
+        }),
+    );
+    let message = AgentMessage {
+        id: crate::agent::message::uuid(),
+        role: MessageRole::Assistant,
+        from: node_name.to_string(),
+        to: None,
+        content: proposal.content,
+        metadata: Some(Value::Object(metadata)),
+        attachments: proposal.attachments,
+    };
+    handle_action(
+        AgentAction::Continue {
+            message: Some(message),
+        },
+        event,
+        flow,
+        ctx,
+        tools,
+        sender,
+        shared,
+    )
+    .await
+}
+
+/// 在节点内直接调用 Agent，首次调用前执行 `on_start`，返回 Finish 时执行 `on_finish`
+async fn run_agent(
+    agent_name: &str,
+    input: AgentMessage,
+    agents: &AgentRegistry,
+    agent_ctx: &AgentContext<'_>,
+    shared: &SharedState,
+) -> Result<AgentAction> {
+    let agent = agents
+        .get(agent_name)
+        .ok_or_else(|| AgentFlowError::AgentNotRegistered(agent_name.to_string()))?;
+    if shared.mark_agent_started(agent_name).await? {
+        agent.on_start(agent_ctx).await?;
+    }
+    let action = agent.on_message(input, agent_ctx).await?;
+    if matches!(action, AgentAction::Finish { .. }) {
+        agent.on_finish(agent_ctx).await?;
+    }
+    Ok(action)
+}
+
+/// 下一位发言人：配置了模型时由模型选择，无法识别或调用失败时按顺序轮流
+async fn next_speaker(
+    chat: &GroupChatNode,
+    node_name: &str,
+    transcript: &[(String, AgentMessage)],
+    previous: Option<usize>,
+) -> usize {
+    let round_robin = previous.map_or(0, |index| (index + 1) % chat.participants.len());
+    let Some(client) = &chat.selector else {
+        return round_robin;
+    };
+    let request = LlmRequest {
+        system: Some(speaker_prompt(chat)),
+        user: render_transcript(transcript),
+        temperature: 0.0,
+        metadata: None,
+        content: Vec::new(),
+        params: Default::default(),
+    };
+    match client.complete(request).await {
+        Ok(response) => select_speaker(chat, &response.content).unwrap_or(round_robin),
+        Err(err) => {
+            warn!(node = %node_name, error = %err, "speaker selection failed, using round robin");
+            round_robin
+        }
+    }
+}
+
+fn render_transcript(transcript: &[(String, AgentMessage)]) -> String {
+    transcript
+        .iter()
+        .map(|(speaker, message)| format!("{}: {}", speaker, message.content))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// 处理图片生成节点
+pub async fn handle_image_gen_node(
+    image_node: &ImageGenNode,
+    node_name: &str,
+    event: &FlowEvent,
+    ctx: &Arc<FlowContext>,
+    flow: Arc<Flow>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
+) -> Result<TaskResult> {
+    let prompt = match &image_node.prompt {
+        Some(template) => {
+            let mut vars = serde_json::Map::new();
+            vars.insert("input".to_string(), event.message.content.clone().into());
+            render_template(template, &vars)
+        }
+        None => event.message.content.clone(),
+    };
+    let mut request = image_node.request.clone();
+    request.prompt = prompt;
+
+    let client = image_node.config.build_client()?;
+    let channel = ctx.channel().cloned();
+    let result = client
+        .generate_with_progress(&request, |progress| {
+            if let Some(channel) = &channel {
+                channel.publish(RunUpdate::TaskProgress {
+                    node: node_name.to_string(),
+                    task_id: progress.task_id.clone(),
+                    status: progress.status.clone(),
+                    elapsed_ms: progress.elapsed_ms,
+                });
+            }
+        })
+        .await?;
+
+    let mut message = AgentMessage {
+        id: crate::agent::message::uuid(),
+        role: MessageRole::Tool,
+        from: node_name.to_string(),
+        to: None,
+        content: serde_json::json!({
+            "image_urls": result.urls,
+            "task_id": result.task_id,
+        })
+        .to_string(),
+        metadata: None,
+        attachments: Vec::new(),
+    };
+    for url in &result.urls {
+        message = message.with_attachment(Attachment::image_url(url.clone()));
+    }
+    forward_output(message, node_name, event, ctx, &flow, sender, shared).await
+}
+
+/// 处理数据转换节点
+pub async fn handle_transform_node(
+    transform: &TransformNode,
+    node_name: &str,
+    event: &FlowEvent,
+    ctx: &Arc<FlowContext>,
+    flow: Arc<Flow>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
+) -> Result<TaskResult> {
+    let content = transform.transform(&event.message.content).map_err(|e| {
+        AgentFlowError::Other(anyhow!("Transform node `{}` failed: {}", node_name, e))
+    })?;
+    let message = AgentMessage {
+        id: crate::agent::message::uuid(),
+        role: MessageRole::Tool,
+        from: node_name.to_string(),
+        to: None,
+        content,
+        metadata: event.message.metadata.clone(),
+        attachments: event.message.attachments.clone(),
+    };
+    forward_output(message, node_name, event, ctx, &flow, sender, shared).await
+}
+
+/// 处理等待节点：等待结束后把输入消息原样转发给后继
+pub async fn handle_wait_node(
+    wait: &WaitNode,
+    node_name: &str,
+    event: &FlowEvent,
+    ctx: &Arc<FlowContext>,
+    flow: &Arc<Flow>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
+) -> Result<TaskResult> {
+    let until = match wait {
+        WaitNode::Delay(duration) => {
+            tokio::time::sleep(*duration).await;
+            None
+        }
+        WaitNode::UntilState(until) => Some(until),
+    };
+    if let Some(until) = until {
+        let deadline = until
+            .timeout
+            .map(|timeout| tokio::time::Instant::now() + timeout);
+        loop {
+            let current = ctx.store().get(&until.key).await?;
+            if until.is_satisfied(current.as_deref()) {
+                break;
+            }
+            let mut sleep = until.poll_interval;
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+                if remaining.is_zero() {
+                    warn!(node = %node_name, key = %until.key, "Wait node timed out");
+                    return route_wait_timeout(
+                        until.on_timeout.as_ref(),
+                        node_name,
+                        event,
+                        &sender,
+                    )
+                    .await;
+                }
+                sleep = sleep.min(remaining);
+            }
+            tokio::time::sleep(sleep).await;
+        }
+    }
+    // 输入消息已记录在历史中，直接转发
+    forward_join(
+        (*event.message).clone(),
+        node_name,
+        event,
+        ctx,
+        flow,
+        sender,
+        shared,
+    )
+    .await
+}
+
+/// 等待超时：有 `on_timeout` 时把输入消息转过去，否则运行失败
+async fn route_wait_timeout(
+    on_timeout: Option<&String>,
+    node_name: &str,
+    event: &FlowEvent,
+    sender: &EventSender,
+) -> Result<TaskResult> {
+    let Some(target) = on_timeout else {
+        return Err(AgentFlowError::WaitTimedOut {
+            node: node_name.to_string(),
+        });
+    };
+    let message = AgentMessage {
+        to: Some(target.clone()),
+        ..(*event.message).clone()
+    };
+    enqueue_event(
+        sender,
+        target.clone(),
+        message,
+        event.iterations + 1,
+        &event.trace_id,
+        node_name,
+    )
+    .await?;
+    Ok(TaskResult::Continue)
+}
+
+/// 处理外部事件节点：签发恢复令牌后等待投递，载荷作为输出消息转发给后继
+pub async fn handle_await_event_node(
+    node: &AwaitEventNode,
+    node_name: &str,
+    event: &FlowEvent,
+    ctx: &Arc<FlowContext>,
+    flow: Arc<Flow>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
+) -> Result<TaskResult> {
+    // 令牌在本函数返回、出错或任务被取消时撤销
+    let (issued, receiver) = shared.resume_tokens.issue();
+    let token = issued.as_str().to_string();
+    ctx.store()
+        .set(&node.token_key(node_name), token.clone())
         .await?;
+    if let Some(channel) = ctx.channel() {
+        channel.publish(RunUpdate::AwaitingEvent {
+            node: node_name.to_string(),
+            token: token.clone(),
+        });
+    }
+    debug!(node = %node_name, token = %token, "waiting for external event");
 
+    let delivered = match node.timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, receiver).await {
+            Ok(delivered) => delivered,
+            Err(_) => {
+                warn!(node = %node_name, token = %token, "External event wait timed out");
+                return route_wait_timeout(node.on_timeout.as_ref(), node_name, event, &sender)
+                    .await;
+            }
+        },
+        None => receiver.await,
+    };
+    let payload = delivered
+        .map_err(|_| AgentFlowError::Context(format!("resume token `{}` was dropped", token)))?;
+    let content = match payload {
+        Value::String(text) => text,
+        other => other.to_string(),
+    };
+    if let Some(key) = &node.store_as {
+        ctx.store().set(key, content.clone()).await?;
+    }
+
+    let message = AgentMessage {
+        id: crate::agent::message::uuid(),
+        role: MessageRole::User,
+        from: node_name.to_string(),
+        to: None,
+        content,
+        metadata: Some(serde_json::json!({ "resume_token": token })),
+        attachments: Vec::new(),
+    };
+    forward_output(message, node_name, event, ctx, &flow, sender, shared).await
+}
+
+/// 记录节点输出并发送到所有后继节点，没有后继时结束运行
+async fn forward_output(
+    message: AgentMessage,
+    node_name: &str,
+    event: &FlowEvent,
+    ctx: &Arc<FlowContext>,
+    flow: &Arc<Flow>,
+    sender: EventSender,
+    shared: &Arc<SharedState>,
+) -> Result<TaskResult> {
     ctx.push_message(message.clone());
 
-    let transitions = next_from_flow(node_name, &flow, ctx).await?;
+    let transitions = next_from_flow(node_name, flow, ctx, shared).await?;
     if transitions.is_empty() {
         return Ok(TaskResult::Finished(TaskFinished {
             node: node_name.to_string(),
@@ -441,42 +1471,166 @@ pub async fn handle_tool_node(
     for (target, default_message) in transitions {
         let mut to_send = message.clone();
         if to_send.to.is_none() {
-            to_send.to = default_message.to;
+            to_send.to = default_message.to.clone();
         }
+        explain::inherit(&mut to_send, &default_message);
         enqueue_event(
-            sender.clone(),
+            &sender,
             target,
             to_send,
             event.iterations + 1,
             &event.trace_id,
             node_name,
-        )?;
+        )
+        .await?;
     }
     Ok(TaskResult::Continue)
 }
 
+/// 处理子流程节点
+///
+/// 子流程执行与当前流程互相递归，因此返回装箱的 Future 以打破类型循环
+#[allow(clippy::too_many_arguments)]
+pub fn handle_subflow_node<'a>(
+    sub_flow: &'a SubFlowNode,
+    node_name: &'a str,
+    event: &'a FlowEvent,
+    ctx: &'a Arc<FlowContext>,
+    flow: Arc<Flow>,
+    sender: EventSender,
+    sub_flows: Arc<SubFlows>,
+    shared: &'a Arc<SharedState>,
+) -> Pin<Box<dyn Future<Output = Result<TaskResult>> + Send + 'a>> {
+    Box::pin(async move {
+        let executor = sub_flows
+            .get(&sub_flow.flow)
+            .ok_or_else(|| AgentFlowError::FlowNotRegistered(sub_flow.flow.clone()))?;
+
+        let memo_key = match &sub_flow.memoize {
+            Some(policy) => {
+                let mut inputs = serde_json::Map::new();
+                for key in &policy.input_keys {
+                    let value = ctx.store().get(key).await?;
+                    inputs.insert(key.clone(), serde_json::json!(value));
+                }
+                let input = serde_json::json!({
+                    "content": event.message.content,
+                    "metadata": event.message.metadata,
+                    "attachments": event.message.attachments,
+                    "state": inputs,
+                });
+                Some(MemoCache::key(&sub_flow.flow, &input))
+            }
+            None => None,
+        };
+
+        let cached = match &memo_key {
+            Some(key) => sub_flows.cache().lookup(key).await?,
+            None => None,
+        };
+
+        let message = match cached {
+            Some((mut message, writes)) => {
+                debug!(node = %node_name, flow = %sub_flow.flow, "sub-flow memo hit");
+                // 重放子流程当时的状态写入，与实际执行的效果一致
+                let store = ctx.store();
+                for (key, value) in writes {
+                    match value {
+                        Some(value) => store.set(&key, value).await?,
+                        None => store.delete(&key).await?,
+                    }
+                }
+                message.id = crate::agent::message::uuid();
+                message
+            }
+            None => {
+                let initial = AgentMessage {
+                    to: None,
+                    ..(*event.message).clone()
+                };
+                // 缓存时记录子流程的状态写入，命中时重放
+                let recorder = memo_key
+                    .as_ref()
+                    .map(|_| Arc::new(RecordingStore::new(ctx.store())));
+                let sub_ctx = match &recorder {
+                    Some(recorder) => Arc::new(ctx.with_store(recorder.clone())),
+                    None => Arc::clone(ctx),
+                };
+                // 子流程的结束事件不推送到交互通道
+                let run_id = crate::agent::message::uuid();
+                let execution = executor
+                    .run_with_digest(sub_ctx, Some(initial), &run_id, None)
+                    .await?;
+                let message = execution.last_message.ok_or_else(|| {
+                    AgentFlowError::Other(anyhow!(
+                        "sub-flow `{}` finished without a message",
+                        sub_flow.flow
+                    ))
+                })?;
+                if let (Some(key), Some(policy), Some(recorder)) =
+                    (&memo_key, &sub_flow.memoize, &recorder)
+                {
+                    let mut writes = recorder.writes();
+                    if let Some(redactor) = ctx.redactor() {
+                        for value in writes.values_mut().flatten() {
+                            *value = redactor.redact_raw(std::mem::take(value));
+                        }
+                    }
+                    sub_flows
+                        .cache()
+                        .record(key, &message, writes, policy.ttl)
+                        .await?;
+                }
+                message
+            }
+        };
+
+        ctx.push_message(message.clone());
+
+        let transitions = next_from_flow(node_name, &flow, ctx, shared).await?;
+        if transitions.is_empty() {
+            return Ok(TaskResult::Finished(TaskFinished {
+                node: node_name.to_string(),
+                message: Some(message),
+            }));
+        }
+
+        for (target, default_message) in transitions {
+            let mut to_send = message.clone();
+            to_send.to = default_message.to.clone();
+            explain::inherit(&mut to_send, &default_message);
+            enqueue_event(
+                &sender,
+                target,
+                to_send,
+                event.iterations + 1,
+                &event.trace_id,
+                node_name,
+            )
+            .await?;
+        }
+        Ok(TaskResult::Continue)
+    })
+}
+
 /// 入队事件
-fn enqueue_event(
-    sender: mpsc::UnboundedSender<FlowEvent>,
+async fn enqueue_event(
+    sender: &EventSender,
     target: String,
-    message: AgentMessage,
+    message: impl Into<Arc<AgentMessage>>,
     iterations: u32,
     trace_id: &str,
     source: &str,
 ) -> Result<()> {
-    if sender
+    sender
         .send(FlowEvent {
             node: target,
-            message,
+            message: message.into(),
             iterations,
             trace_id: trace_id.to_string(),
             source: source.to_string(),
         })
-        .is_err()
-    {
-        warn!("scheduler channel closed before event could be enqueued");
-    }
-    Ok(())
+        .await
 }
 
 /// 从 Flow 获取下一个转换
@@ -484,28 +1638,51 @@ async fn next_from_flow(
     node_name: &str,
     flow: &Arc<Flow>,
     ctx: &Arc<FlowContext>,
+    shared: &SharedState,
 ) -> Result<Vec<(String, AgentMessage)>> {
     let mut results = Vec::new();
     for transition in flow.transitions(node_name) {
+        let mut explanation = None;
         if let Some(condition) = &transition.condition {
-            if !(condition)(ctx).await {
+            let passed = (condition)(ctx).await;
+            if let Some(log) = &shared.explain {
+                let (reason, values) =
+                    explain::explain_condition(ctx, transition.description.as_ref(), true, passed)
+                        .await;
+                let entry = RouteExplanation {
+                    node: node_name.to_string(),
+                    kind: ExplainKind::Transition,
+                    target: transition.to.clone(),
+                    taken: passed,
+                    reason: match &transition.name {
+                        Some(name) => format!("transition `{}`: {}", name, reason),
+                        None => reason,
+                    },
+                    values,
+                };
+                log.record(entry.clone()).await;
+                explanation = Some(entry);
+            }
+            if !passed {
                 continue;
             }
         }
-        results.push((
-            transition.to.clone(),
-            AgentMessage {
-                id: crate::agent::message::uuid(),
-                role: MessageRole::System,
-                from: node_name.to_string(),
-                to: Some(transition.to.clone()),
-                content: transition
-                    .name
-                    .clone()
-                    .unwrap_or_else(|| "transition".to_string()),
-                metadata: None,
-            },
-        ));
+        let mut message = AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::System,
+            from: node_name.to_string(),
+            to: Some(transition.to.clone()),
+            content: transition
+                .name
+                .clone()
+                .unwrap_or_else(|| "transition".to_string()),
+            metadata: None,
+            attachments: Vec::new(),
+        };
+        if let Some(explanation) = &explanation {
+            explain::attach(&mut message, explanation);
+        }
+        results.push((transition.to.clone(), message));
     }
     Ok(results)
 }
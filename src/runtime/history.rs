@@ -0,0 +1,374 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::state::ContextStore;
+
+use super::types::FlowExecution;
+
+const INDEX_KEY: &str = "__agentflow:history:index";
+const RECORD_PREFIX: &str = "__agentflow:history:run:";
+
+/// 运行结果状态
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Succeeded,
+    Failed,
+}
+
+/// 一次节点执行
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeVisit {
+    pub node: String,
+    /// 发出该事件的节点
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub trace_id: String,
+    /// 到达该节点时的跳数
+    #[serde(default)]
+    pub iterations: u32,
+    /// 开始时间（Unix 毫秒）
+    pub started_at: u64,
+    pub duration_ms: u64,
+    /// 节点收到的消息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<AgentMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 已完成运行的历史记录
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub run_id: String,
+    pub flow: String,
+    pub status: RunStatus,
+    /// 开始与结束时间（Unix 毫秒）
+    pub started_at: u64,
+    pub finished_at: u64,
+    /// 按开始时间排序的节点执行记录
+    pub nodes: Vec<NodeVisit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_node: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_message: Option<AgentMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RunRecord {
+    pub fn duration_ms(&self) -> u64 {
+        self.finished_at.saturating_sub(self.started_at)
+    }
+
+    pub(super) fn from_result(
+        run_id: &str,
+        flow: &str,
+        started_at: u64,
+        mut nodes: Vec<NodeVisit>,
+        result: std::result::Result<&FlowExecution, &AgentFlowError>,
+    ) -> Self {
+        nodes.sort_by_key(|visit| visit.started_at);
+        let (status, last_node, final_message, error) = match result {
+            Ok(execution) => (
+                RunStatus::Succeeded,
+                Some(execution.last_node.clone()),
+                execution.last_message.clone(),
+                None,
+            ),
+            Err(err) => (
+                RunStatus::Failed,
+                nodes.last().map(|visit| visit.node.clone()),
+                None,
+                Some(err.to_string()),
+            ),
+        };
+        Self {
+            run_id: run_id.to_string(),
+            flow: flow.to_string(),
+            status,
+            started_at,
+            finished_at: now_millis(),
+            nodes,
+            last_node,
+            final_message,
+            error,
+        }
+    }
+}
+
+/// 历史查询条件；未设置的条件不参与过滤
+#[derive(Clone, Debug, Default)]
+pub struct HistoryFilter {
+    pub flow: Option<String>,
+    pub status: Option<RunStatus>,
+    /// 开始时间下限与上限（Unix 毫秒，闭区间）
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+impl HistoryFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn flow(mut self, flow: impl Into<String>) -> Self {
+        self.flow = Some(flow.into());
+        self
+    }
+
+    pub fn status(mut self, status: RunStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn since(mut self, millis: u64) -> Self {
+        self.since = Some(millis);
+        self
+    }
+
+    pub fn until(mut self, millis: u64) -> Self {
+        self.until = Some(millis);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn matches(&self, record: &RunRecord) -> bool {
+        self.flow.as_ref().is_none_or(|flow| *flow == record.flow)
+            && self.status.is_none_or(|status| status == record.status)
+            && self.since.is_none_or(|since| record.started_at >= since)
+            && self.until.is_none_or(|until| record.started_at <= until)
+    }
+
+    /// 过滤并按开始时间倒序截取
+    fn apply(&self, records: impl IntoIterator<Item = RunRecord>) -> Vec<RunRecord> {
+        let mut matched: Vec<RunRecord> = records
+            .into_iter()
+            .filter(|record| self.matches(record))
+            .collect();
+        matched.sort_by_key(|record| std::cmp::Reverse(record.started_at));
+        if let Some(limit) = self.limit {
+            matched.truncate(limit);
+        }
+        matched
+    }
+}
+
+/// 运行历史存储
+#[async_trait]
+pub trait RunStore: Send + Sync {
+    async fn save(&self, record: &RunRecord) -> Result<()>;
+    /// 返回满足条件的记录，最新的在前
+    async fn query(&self, filter: &HistoryFilter) -> Result<Vec<RunRecord>>;
+}
+
+/// 内存历史存储
+#[derive(Default)]
+pub struct MemoryRunStore {
+    records: Mutex<Vec<RunRecord>>,
+}
+
+impl MemoryRunStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RunStore for MemoryRunStore {
+    async fn save(&self, record: &RunRecord) -> Result<()> {
+        self.records.lock().await.push(record.clone());
+        Ok(())
+    }
+
+    async fn query(&self, filter: &HistoryFilter) -> Result<Vec<RunRecord>> {
+        Ok(filter.apply(self.records.lock().await.iter().cloned()))
+    }
+}
+
+/// 基于 `ContextStore` 的历史存储：记录按运行 id 保存，另维护一份运行 id 索引
+#[derive(Clone)]
+pub struct ContextRunStore {
+    store: Arc<dyn ContextStore>,
+}
+
+impl ContextRunStore {
+    pub fn new(store: Arc<dyn ContextStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl RunStore for ContextRunStore {
+    async fn save(&self, record: &RunRecord) -> Result<()> {
+        let raw = serde_json::to_string(record)
+            .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        self.store
+            .set(&format!("{}{}", RECORD_PREFIX, record.run_id), raw)
+            .await?;
+
+        let run_id = record.run_id.clone();
+        self.store
+            .update(INDEX_KEY, &move |current| {
+                let mut ids: Vec<String> = match current {
+                    Some(raw) => serde_json::from_str(&raw)
+                        .map_err(|e| AgentFlowError::Serialization(e.to_string()))?,
+                    None => Vec::new(),
+                };
+                if !ids.contains(&run_id) {
+                    ids.push(run_id.clone());
+                }
+                serde_json::to_string(&ids)
+                    .map(Some)
+                    .map_err(|e| AgentFlowError::Serialization(e.to_string()))
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn query(&self, filter: &HistoryFilter) -> Result<Vec<RunRecord>> {
+        let ids: Vec<String> = match self.store.get(INDEX_KEY).await? {
+            Some(raw) => serde_json::from_str(&raw)
+                .map_err(|e| AgentFlowError::Serialization(e.to_string()))?,
+            None => return Ok(Vec::new()),
+        };
+        let mut records = Vec::with_capacity(ids.len());
+        for id in ids {
+            // 记录可能已被保留策略清理
+            let Some(raw) = self.store.get(&format!("{}{}", RECORD_PREFIX, id)).await? else {
+                continue;
+            };
+            let record: RunRecord = serde_json::from_str(&raw)
+                .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+            records.push(record);
+        }
+        Ok(filter.apply(records))
+    }
+}
+
+/// 运行过程中收集的节点执行记录
+#[derive(Default)]
+pub struct VisitLog {
+    visits: Mutex<Vec<NodeVisit>>,
+}
+
+impl VisitLog {
+    pub async fn record(&self, visit: NodeVisit) {
+        self.visits.lock().await.push(visit);
+    }
+
+    pub async fn take(&self) -> Vec<NodeVisit> {
+        std::mem::take(&mut *self.visits.lock().await)
+    }
+}
+
+pub(super) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::testing::{self, EchoAgent};
+
+    #[tokio::test]
+    async fn test_history_records_runs() {
+        let store = Arc::new(MemoryStore::new());
+        let executor = testing::executor(
+            "echo_flow",
+            vec![("echo", Arc::new(EchoAgent))],
+            |builder| {
+                builder
+                    .add_agent_node("echo", "echo")
+                    .add_terminal_node("done")
+                    .set_start("echo")
+                    .connect("echo", "done");
+            },
+        )
+        .with_run_store(Arc::new(ContextRunStore::new(store.clone())));
+        let ctx = Arc::new(FlowContext::new(store.clone()));
+        executor
+            .start(Arc::clone(&ctx), AgentMessage::user("hi"))
+            .await
+            .unwrap();
+        assert!(executor
+            .start(ctx, AgentMessage::user("boom"))
+            .await
+            .is_err());
+
+        let all = executor.history(HistoryFilter::new()).await.unwrap();
+        assert_eq!(all.len(), 2);
+
+        let succeeded = executor
+            .history(
+                HistoryFilter::new()
+                    .flow("echo_flow")
+                    .status(RunStatus::Succeeded),
+            )
+            .await
+            .unwrap();
+        assert_eq!(succeeded.len(), 1);
+        let record = &succeeded[0];
+        let nodes: Vec<&str> = record.nodes.iter().map(|v| v.node.as_str()).collect();
+        assert_eq!(nodes, vec!["echo", "done"]);
+        assert_eq!(record.last_node.as_deref(), Some("done"));
+        assert_eq!(record.final_message.as_ref().unwrap().content, "hi");
+
+        let failed = executor
+            .history(HistoryFilter::new().status(RunStatus::Failed))
+            .await
+            .unwrap();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].error.as_deref().unwrap().contains("echo failed"));
+        assert_eq!(
+            failed[0].nodes[0].error.as_deref(),
+            failed[0].error.as_deref()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filter_time_range_and_limit() {
+        let store = MemoryRunStore::new();
+        for (id, started_at) in [("a", 100), ("b", 200), ("c", 300)] {
+            let record = RunRecord {
+                run_id: id.to_string(),
+                flow: "f".to_string(),
+                status: RunStatus::Succeeded,
+                started_at,
+                finished_at: started_at + 10,
+                nodes: Vec::new(),
+                last_node: None,
+                final_message: None,
+                error: None,
+            };
+            store.save(&record).await.unwrap();
+        }
+
+        let ranged = store
+            .query(&HistoryFilter::new().since(150).until(300))
+            .await
+            .unwrap();
+        let ids: Vec<&str> = ranged.iter().map(|r| r.run_id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "b"]);
+
+        let latest = store.query(&HistoryFilter::new().limit(1)).await.unwrap();
+        assert_eq!(latest[0].run_id, "c");
+        assert_eq!(latest[0].duration_ms(), 10);
+    }
+}
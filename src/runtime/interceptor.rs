@@ -0,0 +1,312 @@
+//! 节点拦截器：在执行器处理每个事件前后调用，用于指标、缓存、策略检查等，无需修改节点处理逻辑
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::types::{FlowEvent, TaskResult};
+use crate::error::{AgentFlowError, Result};
+use crate::state::FlowContext;
+
+/// 节点拦截器
+///
+/// `before_node` 按注册顺序调用，可以改写事件；返回错误时节点不执行，错误按节点失败处理
+/// （死信、错误处理节点）。`after_node` / `on_error` 按相反顺序调用；`after_node` 返回错误时
+/// 同样按节点失败处理，`on_error` 的错误只记录日志。
+#[async_trait]
+pub trait NodeInterceptor: Send + Sync {
+    async fn before_node(&self, _event: &mut FlowEvent, _ctx: &FlowContext) -> Result<()> {
+        Ok(())
+    }
+
+    async fn after_node(
+        &self,
+        _event: &FlowEvent,
+        _ctx: &FlowContext,
+        _result: &TaskResult,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_error(
+        &self,
+        _event: &FlowEvent,
+        _ctx: &FlowContext,
+        _error: &AgentFlowError,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// 拦截器栈
+#[derive(Clone, Default)]
+pub(super) struct InterceptorStack {
+    layers: Vec<Arc<dyn NodeInterceptor>>,
+}
+
+impl InterceptorStack {
+    pub(super) fn push(&mut self, interceptor: Arc<dyn NodeInterceptor>) {
+        self.layers.push(interceptor);
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub(super) async fn before(&self, event: &mut FlowEvent, ctx: &FlowContext) -> Result<()> {
+        for layer in &self.layers {
+            layer.before_node(event, ctx).await?;
+        }
+        Ok(())
+    }
+
+    /// 按相反顺序调用 `after_node` 或 `on_error`，返回最终结果
+    pub(super) async fn after(
+        &self,
+        event: &FlowEvent,
+        ctx: &FlowContext,
+        result: Result<TaskResult>,
+    ) -> Result<TaskResult> {
+        let mut result = result;
+        for layer in self.layers.iter().rev() {
+            result = match result {
+                Ok(task) => layer.after_node(event, ctx, &task).await.map(|()| task),
+                Err(error) => {
+                    if let Err(err) = layer.on_error(event, ctx, &error).await {
+                        tracing::warn!(
+                            node = %event.node,
+                            error = %err,
+                            "node interceptor on_error failed"
+                        );
+                    }
+                    Err(error)
+                }
+            };
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{
+        register_agent, Agent, AgentAction, AgentContext, AgentMessage, AgentRegistry,
+    };
+    use crate::flow::{FlowBuilder, JoinStrategy};
+    use crate::runtime::FlowExecutor;
+    use crate::state::MemoryStore;
+    use crate::testing;
+    use crate::tools::ToolRegistry;
+    use anyhow::anyhow;
+    use std::sync::Mutex;
+
+    struct Upper;
+
+    #[async_trait]
+    impl Agent for Upper {
+        fn name(&self) -> &'static str {
+            "upper"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Continue {
+                message: Some(AgentMessage::system(message.content.to_uppercase())),
+            })
+        }
+    }
+
+    /// 记录调用顺序，拒绝执行 `blocked` 节点，并给 `upper` 的输入加前缀
+    struct Recorder {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl NodeInterceptor for Recorder {
+        async fn before_node(&self, event: &mut FlowEvent, _ctx: &FlowContext) -> Result<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("{} before {}", self.name, event.node));
+            if event.node == "blocked" {
+                return Err(AgentFlowError::Other(anyhow!(
+                    "policy denied `{}`",
+                    event.node
+                )));
+            }
+            if event.node == "upper" && self.name == "outer" {
+                let message = Arc::make_mut(&mut event.message);
+                message.content = format!("checked: {}", message.content);
+            }
+            Ok(())
+        }
+
+        async fn after_node(
+            &self,
+            event: &FlowEvent,
+            _ctx: &FlowContext,
+            result: &TaskResult,
+        ) -> Result<()> {
+            let finished = matches!(result, TaskResult::Finished(_));
+            self.calls.lock().unwrap().push(format!(
+                "{} after {} finished={}",
+                self.name, event.node, finished
+            ));
+            Ok(())
+        }
+
+        async fn on_error(
+            &self,
+            event: &FlowEvent,
+            _ctx: &FlowContext,
+            error: &AgentFlowError,
+        ) -> Result<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("{} error {}: {}", self.name, event.node, error));
+            Ok(())
+        }
+    }
+
+    fn executor(start: &str, calls: &Arc<Mutex<Vec<String>>>) -> FlowExecutor {
+        let recorder = |name| Recorder {
+            name,
+            calls: Arc::clone(calls),
+        };
+        testing::executor("intercepted", vec![("upper", Arc::new(Upper))], |builder| {
+            builder
+                .add_agent_node("upper", "upper")
+                .add_agent_node("blocked", "upper")
+                .add_terminal_node("done")
+                .connect("upper", "done")
+                .set_start(start);
+        })
+        .with_interceptor(recorder("outer"))
+        .with_interceptor(recorder("inner"))
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_wrap_each_node() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let execution = executor("upper", &calls)
+            .start(ctx, AgentMessage::user("hi"))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_message.unwrap().content, "CHECKED: HI");
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                "outer before upper",
+                "inner before upper",
+                "inner after upper finished=false",
+                "outer after upper finished=false",
+                "outer before done",
+                "inner before done",
+                "inner after done finished=true",
+                "outer after done finished=true",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_before_node_error_blocks_node() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let error = executor("blocked", &calls)
+            .start(ctx, AgentMessage::user("hi"))
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("policy denied `blocked`"));
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                "outer before blocked",
+                "inner error blocked: policy denied `blocked`",
+                "outer error blocked: policy denied `blocked`",
+            ]
+        );
+    }
+
+    struct Pass;
+
+    #[async_trait]
+    impl Agent for Pass {
+        fn name(&self) -> &'static str {
+            "pass"
+        }
+
+        async fn on_message(
+            &self,
+            _message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Continue { message: None })
+        }
+    }
+
+    /// 记录每个节点收到的消息
+    #[derive(Default)]
+    struct Capture {
+        seen: Mutex<Vec<(String, Arc<AgentMessage>)>>,
+    }
+
+    #[async_trait]
+    impl NodeInterceptor for Arc<Capture> {
+        async fn before_node(&self, event: &mut FlowEvent, _ctx: &FlowContext) -> Result<()> {
+            self.seen
+                .lock()
+                .unwrap()
+                .push((event.node.clone(), Arc::clone(&event.message)));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_branches_share_message() {
+        let mut agents = AgentRegistry::new();
+        register_agent("upper", Arc::new(Upper), &mut agents);
+        register_agent("pass", Arc::new(Pass), &mut agents);
+        let mut builder = FlowBuilder::new("fan_out");
+        builder
+            .add_agent_node("upper", "upper")
+            .add_agent_node("a", "pass")
+            .add_agent_node("b", "pass")
+            .add_join_node("merge", JoinStrategy::All, vec!["a".into(), "b".into()])
+            .add_terminal_node("done")
+            .connect("upper", "a")
+            .connect("upper", "b")
+            .connect("a", "merge")
+            .connect("b", "merge")
+            .connect("merge", "done")
+            .set_start("upper");
+        let capture = Arc::new(Capture::default());
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_interceptor(Arc::clone(&capture));
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+
+        executor
+            .start(Arc::clone(&ctx), AgentMessage::user("x".repeat(1 << 20)))
+            .await
+            .unwrap();
+        let seen = capture.seen.lock().unwrap();
+        let branches: Vec<_> = seen
+            .iter()
+            .filter(|(node, _)| node == "a" || node == "b")
+            .map(|(_, message)| message)
+            .collect();
+        assert_eq!(branches.len(), 2);
+        assert!(Arc::ptr_eq(branches[0], branches[1]));
+        // 写入历史的也是同一条消息
+        let history = ctx.shared_history();
+        let shared = history.iter().filter(|m| Arc::ptr_eq(m, branches[0]));
+        assert_eq!(shared.count(), 2);
+    }
+}
@@ -0,0 +1,293 @@
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::agent::AgentMessage;
+use crate::error::Result;
+use crate::state::{ContextStore, MemoryStore, StateChange, UpdateFn};
+
+// 子流程结果缓存
+
+const KEY_PREFIX: &str = "memo:";
+
+/// 子流程对状态的写入，值为 None 表示删除
+pub(crate) type StateWrites = BTreeMap<String, Option<String>>;
+
+#[derive(Serialize, Deserialize)]
+struct MemoEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+    message: AgentMessage,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    writes: StateWrites,
+}
+
+/// 子流程结果缓存
+///
+/// 以 ContextStore 为后端，默认使用内存存储；换成 Redis 等持久化存储即可跨进程复用。
+#[derive(Clone)]
+pub struct MemoCache {
+    store: Arc<dyn ContextStore>,
+}
+
+impl Default for MemoCache {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl MemoCache {
+    pub fn new(store: Arc<dyn ContextStore>) -> Self {
+        Self { store }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(Arc::new(MemoryStore::new()))
+    }
+
+    /// 计算缓存键：流程名 + 输入的 SHA-256
+    pub fn key(flow: &str, input: &Value) -> String {
+        // serde_json 默认按键排序序列化对象，保证同一输入得到同一字符串
+        let canonical = input.to_string();
+        let digest = Sha256::digest(canonical.as_bytes());
+        let hex: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+        format!("{KEY_PREFIX}{flow}:{hex}")
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<AgentMessage>> {
+        Ok(self.lookup(key).await?.map(|(message, _)| message))
+    }
+
+    /// 读取缓存的结果和子流程当时的状态写入
+    pub(crate) async fn lookup(&self, key: &str) -> Result<Option<(AgentMessage, StateWrites)>> {
+        let Some(raw) = self.store.get(key).await? else {
+            return Ok(None);
+        };
+        let Ok(entry) = serde_json::from_str::<MemoEntry>(&raw) else {
+            self.store.delete(key).await?;
+            return Ok(None);
+        };
+        if let Some(expires_at) = entry.expires_at {
+            if now_secs() >= expires_at {
+                self.store.delete(key).await?;
+                return Ok(None);
+            }
+        }
+        Ok(Some((entry.message, entry.writes)))
+    }
+
+    pub async fn put(&self, key: &str, message: &AgentMessage, ttl: Option<Duration>) -> Result<()> {
+        self.record(key, message, StateWrites::new(), ttl).await
+    }
+
+    pub(crate) async fn record(
+        &self,
+        key: &str,
+        message: &AgentMessage,
+        writes: StateWrites,
+        ttl: Option<Duration>,
+    ) -> Result<()> {
+        let entry = MemoEntry {
+            expires_at: ttl.map(|ttl| now_secs() + ttl.as_secs().max(1)),
+            message: message.clone(),
+            writes,
+        };
+        let raw = serde_json::to_string(&entry)
+            .map_err(|e| crate::error::AgentFlowError::Serialization(e.to_string()))?;
+        self.store.set(key, raw).await
+    }
+
+    pub async fn invalidate(&self, key: &str) -> Result<()> {
+        self.store.delete(key).await
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// 记录子流程状态写入的存储包装，命中缓存时重放这些写入
+pub(crate) struct RecordingStore {
+    inner: Arc<dyn ContextStore>,
+    writes: Mutex<StateWrites>,
+}
+
+impl RecordingStore {
+    pub(crate) fn new(inner: Arc<dyn ContextStore>) -> Self {
+        Self {
+            inner,
+            writes: Mutex::new(StateWrites::new()),
+        }
+    }
+
+    pub(crate) fn writes(&self) -> StateWrites {
+        self.writes.lock().clone()
+    }
+
+    fn note(&self, key: &str, value: Option<String>) {
+        // 运行协调状态和历史溢出只属于子流程自身的运行
+        if !key.starts_with("__agentflow:") {
+            self.writes.lock().insert(key.to_string(), value);
+        }
+    }
+}
+
+#[async_trait]
+impl ContextStore for RecordingStore {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        self.inner.get(key).await
+    }
+
+    async fn set(&self, key: &str, value: String) -> Result<()> {
+        self.inner.set(key, value.clone()).await?;
+        self.note(key, Some(value));
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.inner.delete(key).await?;
+        self.note(key, None);
+        Ok(())
+    }
+
+    async fn update(&self, key: &str, f: UpdateFn<'_>) -> Result<Option<String>> {
+        let next = self.inner.update(key, f).await?;
+        self.note(key, next.clone());
+        Ok(next)
+    }
+
+    async fn compare_and_set(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        new: Option<String>,
+    ) -> Result<bool> {
+        let written = self
+            .inner
+            .compare_and_set(key, expected, new.clone())
+            .await?;
+        if written {
+            self.note(key, new);
+        }
+        Ok(written)
+    }
+
+    async fn watch(&self, prefix: &str) -> Result<BoxStream<'static, StateChange>> {
+        self.inner.watch(prefix).await
+    }
+
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>> {
+        self.inner.keys(prefix).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentRegistry};
+    use crate::flow::{FlowBuilder, MemoizePolicy};
+    use crate::runtime::FlowExecutor;
+    use crate::state::FlowContext;
+    use crate::tools::ToolRegistry;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingAgent {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Agent for CountingAgent {
+        fn name(&self) -> &'static str {
+            "counter"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            ctx.flow()
+                .store()
+                .set("parsed", message.content.clone())
+                .await?;
+            Ok(AgentAction::Finish {
+                message: Some(AgentMessage::system(format!("parsed:{}", message.content))),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memoized_sub_flow_runs_once() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut agents = AgentRegistry::new();
+        register_agent(
+            "counter",
+            Arc::new(CountingAgent {
+                calls: Arc::clone(&calls),
+            }),
+            &mut agents,
+        );
+
+        let mut sub = FlowBuilder::new("parse");
+        sub.add_agent_node("count", "counter").set_start("count");
+        let sub_executor = FlowExecutor::new(sub.build(), agents, ToolRegistry::new());
+
+        let mut parent = FlowBuilder::new("main");
+        parent
+            .add_subflow_node("pre", "parse", Some(MemoizePolicy::default()))
+            .add_terminal_node("done")
+            .set_start("pre")
+            .connect("pre", "done");
+        let executor = FlowExecutor::new(parent.build(), AgentRegistry::new(), ToolRegistry::new())
+            .with_sub_flow(sub_executor);
+
+        for _ in 0..2 {
+            let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+            let result = executor
+                .start(Arc::clone(&ctx), AgentMessage::user("doc"))
+                .await
+                .unwrap();
+            assert_eq!(result.last_node, "done");
+            assert_eq!(result.last_message.unwrap().content, "parsed:doc");
+            // 命中缓存时同样得到子流程写入的状态
+            let parsed = ctx.store().get("parsed").await.unwrap();
+            assert_eq!(parsed.as_deref(), Some("doc"));
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 元数据不同的输入不复用结果
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let mut message = AgentMessage::user("doc");
+        message.metadata = Some(json!({ "lang": "de" }));
+        executor.start(ctx, message).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_memo_cache_roundtrip() {
+        let cache = MemoCache::in_memory();
+        let key = MemoCache::key("chunker", &json!({ "b": 1, "a": 2 }));
+        assert_eq!(key, MemoCache::key("chunker", &json!({ "a": 2, "b": 1 })));
+        assert_ne!(key, MemoCache::key("chunker", &json!({ "a": 3, "b": 1 })));
+
+        assert!(cache.get(&key).await.unwrap().is_none());
+        let message = AgentMessage::system("chunks");
+        cache.put(&key, &message, None).await.unwrap();
+        assert_eq!(cache.get(&key).await.unwrap().unwrap().content, "chunks");
+
+        cache.invalidate(&key).await.unwrap();
+        assert!(cache.get(&key).await.unwrap().is_none());
+    }
+}
@@ -1,12 +1,76 @@
 // 运行时执行引擎模块
 
+mod batch;
+mod channel;
+mod dead_letter;
+mod digest;
+mod distributed;
+mod error_handler;
 mod executor;
+mod explain;
 mod handlers;
+mod history;
+mod interceptor;
+mod memo;
+mod notifier;
+mod pipeline;
 mod processor;
+mod progress;
+mod queue;
+mod resume;
+#[allow(clippy::module_inception)]
 mod runtime;
 mod state;
+mod token_sink;
+mod trace_export;
+mod transcript;
 mod types;
+#[cfg(feature = "websocket")]
+mod websocket;
 
-pub use executor::FlowExecutor;
+pub use batch::{BatchContextFactory, BatchItem, BatchOptions, BatchReport, BatchStatus};
+pub use channel::{RunChannel, RunUpdate};
+pub use dead_letter::{DeadLetter, DeadLetterSink, MemoryDeadLetterSink, DEAD_LETTER_FIELD};
+pub use digest::{
+    BroadcastDigestSink, DigestCosts, DigestMode, DigestSink, RunDigest, RunDigestHook,
+    WebhookDigestSink, DEFAULT_DIGEST_TEMPLATE,
+};
+#[cfg(feature = "nats-queue")]
+pub use distributed::NatsWorkQueue;
+#[cfg(feature = "redis-queue")]
+pub use distributed::RedisStreamQueue;
+pub use distributed::{
+    DistributedExecutor, FlowWorker, MemoryWorkQueue, WorkItem, WorkQueue, DEFAULT_RUN_TIMEOUT,
+    TASK_TOPIC,
+};
+pub use error_handler::{ErrorEnvelope, ERROR_FIELD};
+pub use executor::{FlowExecutor, SubFlows};
+pub use explain::{ExplainKind, RouteExplanation};
+pub use history::{
+    ContextRunStore, HistoryFilter, MemoryRunStore, NodeVisit, RunRecord, RunStatus, RunStore,
+};
+pub use interceptor::NodeInterceptor;
+pub use memo::MemoCache;
+pub use notifier::{
+    sign_payload, LifecycleEvent, LifecycleEventKind, WebhookNotifier, WebhookTarget,
+    DEFAULT_WEBHOOK_TIMEOUT, EVENT_HEADER, SIGNATURE_HEADER,
+};
+pub use progress::{LoopProgress, ProgressReporter, ProgressUpdate};
+#[cfg(feature = "redis-queue")]
+pub use queue::RedisEventQueue;
+pub use queue::{
+    Delivery, EventQueue, MemoryEventQueue, QueueStats, DEFAULT_PUSH_TIMEOUT,
+    DEFAULT_QUEUE_CAPACITY,
+};
+pub use resume::ResumeTokens;
 pub use runtime::ExecutorRuntime;
-pub use types::{FlowEvent, FlowExecution, TaskFinished, TaskResult};
+pub use state::DEFAULT_DEDUP_TTL;
+pub use token_sink::{stream_to_sink, TokenChunk, TokenSink};
+pub use trace_export::{export_trace, TraceFormat};
+pub use transcript::{
+    Transcript, TranscriptMessage, TranscriptRecorder, TranscriptStep, DEFAULT_IGNORED_FIELDS,
+    GOLDEN_MODE_ENV,
+};
+pub use types::{FlowEvent, FlowExecution, FlowMigration, TaskFinished, TaskResult};
+#[cfg(feature = "websocket")]
+pub use websocket::serve_websocket;
@@ -0,0 +1,486 @@
+//! 运行生命周期 Webhook 通知
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use hmac::{Hmac, KeyInit, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::agent::AgentMessage;
+use crate::config::EnvConfig;
+use crate::error::{AgentFlowError, Result};
+
+/// 签名请求头，值为 `sha256=<hex>`，对请求体做 HMAC-SHA256
+pub const SIGNATURE_HEADER: &str = "X-AgentFlow-Signature";
+/// 事件类型请求头
+pub const EVENT_HEADER: &str = "X-AgentFlow-Event";
+/// 单次投递的默认超时
+pub const DEFAULT_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 生命周期事件类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleEventKind {
+    FlowStarted,
+    FlowFinished,
+    FlowFailed,
+    NodeCompleted,
+}
+
+impl LifecycleEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleEventKind::FlowStarted => "flow_started",
+            LifecycleEventKind::FlowFinished => "flow_finished",
+            LifecycleEventKind::FlowFailed => "flow_failed",
+            LifecycleEventKind::NodeCompleted => "node_completed",
+        }
+    }
+}
+
+/// 推送给 Webhook 的事件内容
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub event: LifecycleEventKind,
+    pub flow: String,
+    pub run_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<AgentMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Unix 时间戳（毫秒）
+    pub timestamp: u64,
+}
+
+impl LifecycleEvent {
+    pub fn new(
+        event: LifecycleEventKind,
+        flow: impl Into<String>,
+        run_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            event,
+            flow: flow.into(),
+            run_id: run_id.into(),
+            node: None,
+            message: None,
+            error: None,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Webhook 目标
+///
+/// `events` 为空时接收全部事件；`nodes` 只对 `node_completed` 生效，为空时接收全部节点。
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WebhookTarget {
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<LifecycleEventKind>,
+    #[serde(default)]
+    pub nodes: Vec<String>,
+    /// HMAC 签名密钥（支持 `${VAR}`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl WebhookTarget {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_events(mut self, events: impl IntoIterator<Item = LifecycleEventKind>) -> Self {
+        self.events = events.into_iter().collect();
+        self
+    }
+
+    pub fn with_nodes<I, S>(mut self, nodes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.nodes = nodes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    pub fn with_header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(key.into(), value.into());
+        self
+    }
+
+    fn accepts(&self, event: &LifecycleEvent) -> bool {
+        if !self.events.is_empty() && !self.events.contains(&event.event) {
+            return false;
+        }
+        match (&event.node, event.event) {
+            (Some(node), LifecycleEventKind::NodeCompleted) => {
+                self.nodes.is_empty() || self.nodes.contains(node)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// 计算 `sha256=<hex>` 签名，接收方用同一密钥对原始请求体校验
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = <Hmac<Sha256> as KeyInit>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!("sha256={digest}")
+}
+
+/// 生命周期 Webhook 通知
+///
+/// 通过 `FlowExecutor::with_webhook_notifier` 挂载后，执行器在运行开始、结束、失败
+/// 以及节点完成时推送事件。事件在后台投递，不等待端点响应，到达顺序不保证；
+/// 投递失败或超时只记录日志，不影响运行结果。
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    targets: Vec<WebhookTarget>,
+    client: reqwest::Client,
+}
+
+impl Default for WebhookNotifier {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            client: Self::client(DEFAULT_WEBHOOK_TIMEOUT),
+        }
+    }
+}
+
+impl WebhookNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn client(timeout: Duration) -> reqwest::Client {
+        reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new())
+    }
+
+    /// 单次投递的超时，默认 `DEFAULT_WEBHOOK_TIMEOUT`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = Self::client(timeout);
+        self
+    }
+
+    /// 从 JSON 数组创建，元素为 `WebhookTarget`
+    pub fn from_value(value: Value) -> Result<Self> {
+        let targets: Vec<WebhookTarget> = serde_json::from_value(value)
+            .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        Ok(Self {
+            targets,
+            ..Default::default()
+        })
+    }
+
+    pub fn with_target(mut self, target: WebhookTarget) -> Self {
+        self.targets.push(target);
+        self
+    }
+
+    /// 是否有目标订阅了节点完成事件
+    pub(crate) fn wants_nodes(&self) -> bool {
+        self.targets.iter().any(|target| {
+            target.events.is_empty() || target.events.contains(&LifecycleEventKind::NodeCompleted)
+        })
+    }
+
+    /// 推送到所有匹配的目标
+    pub async fn notify(&self, event: &LifecycleEvent) {
+        let targets: Vec<_> = self.targets.iter().filter(|t| t.accepts(event)).collect();
+        if targets.is_empty() {
+            return;
+        }
+        let body = match serde_json::to_vec(event) {
+            Ok(body) => body,
+            Err(err) => {
+                tracing::warn!(%err, "Failed to serialize lifecycle event");
+                return;
+            }
+        };
+        let deliveries = targets.into_iter().map(|target| {
+            let body = body.clone();
+            async move {
+                if let Err(err) = self.deliver(target, event.event, body).await {
+                    tracing::warn!(url = %target.url, event = event.event.as_str(), error = %err, "Lifecycle webhook failed");
+                }
+            }
+        });
+        futures::future::join_all(deliveries).await;
+    }
+
+    /// 在后台推送，不等待结果；执行器投递所有事件都使用此方法
+    pub(crate) fn notify_in_background(self: &Arc<Self>, event: LifecycleEvent) {
+        let notifier = Arc::clone(self);
+        tokio::spawn(async move { notifier.notify(&event).await });
+    }
+
+    async fn deliver(
+        &self,
+        target: &WebhookTarget,
+        kind: LifecycleEventKind,
+        body: Vec<u8>,
+    ) -> Result<()> {
+        let mut request = self
+            .client
+            .post(&target.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, kind.as_str());
+        if let Some(secret) = &target.secret {
+            let secret = EnvConfig::get_api_key(secret, "AGENTFLOW_WEBHOOK_SECRET")?;
+            request = request.header(SIGNATURE_HEADER, sign_payload(&secret, &body));
+        }
+        for (key, value) in &target.headers {
+            request = request.header(key, value);
+        }
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Lifecycle webhook failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Lifecycle webhook returned {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 节点完成通知，绑定到一次运行
+#[derive(Clone)]
+pub(crate) struct NodeNotifier {
+    notifier: Arc<WebhookNotifier>,
+    flow: String,
+    run_id: String,
+}
+
+impl NodeNotifier {
+    pub(crate) fn new(notifier: Arc<WebhookNotifier>, flow: &str, run_id: &str) -> Self {
+        Self {
+            notifier,
+            flow: flow.to_string(),
+            run_id: run_id.to_string(),
+        }
+    }
+
+    pub(crate) fn node_completed(&self, node: &str, message: Option<AgentMessage>) {
+        let event = LifecycleEvent {
+            node: Some(node.to_string()),
+            message,
+            ..LifecycleEvent::new(LifecycleEventKind::NodeCompleted, &self.flow, &self.run_id)
+        };
+        self.notifier.notify_in_background(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentRegistry};
+    use crate::flow::FlowBuilder;
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+    use async_trait::async_trait;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    struct ReplyAgent;
+
+    #[async_trait]
+    impl Agent for ReplyAgent {
+        fn name(&self) -> &'static str {
+            "reply"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Next {
+                target: "done".into(),
+                message: AgentMessage::system(format!("re: {}", message.content)),
+            })
+        }
+    }
+
+    /// 接收 HTTP 请求，返回 (事件头, 签名头, 请求体)
+    fn webhook_server() -> (String, mpsc::Receiver<(String, String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut headers = HashMap::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((key, value)) = line.split_once(": ") {
+                        headers.insert(key.to_ascii_lowercase(), value.to_string());
+                    }
+                }
+                let length: usize = headers["content-length"].parse().unwrap();
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+                let header = |name: &str| headers.get(name).cloned().unwrap_or_default();
+                let _ = tx.send((
+                    header("x-agentflow-event"),
+                    header("x-agentflow-signature"),
+                    String::from_utf8(body).unwrap(),
+                ));
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_lifecycle_webhooks_are_filtered_and_signed() {
+        let (url, requests) = webhook_server();
+        let notifier = WebhookNotifier::from_value(serde_json::json!([{
+            "url": url,
+            "events": ["flow_started", "flow_finished", "node_completed"],
+            "nodes": ["answer"],
+            "secret": "s3cret"
+        }]))
+        .unwrap();
+
+        let mut agents = AgentRegistry::new();
+        register_agent("reply", Arc::new(ReplyAgent), &mut agents);
+        let mut builder = FlowBuilder::new("support");
+        builder
+            .add_agent_node("answer", "reply")
+            .add_terminal_node("done")
+            .set_start("answer");
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_webhook_notifier(notifier);
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        executor
+            .start(ctx, AgentMessage::user("hello"))
+            .await
+            .unwrap();
+
+        let mut received = HashMap::new();
+        for _ in 0..3 {
+            let (event, signature, body) = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(signature, sign_payload("s3cret", body.as_bytes()));
+            let payload: LifecycleEvent = serde_json::from_str(&body).unwrap();
+            assert_eq!(payload.event.as_str(), event);
+            received.insert(event, payload);
+        }
+        assert!(requests.recv_timeout(Duration::from_millis(200)).is_err());
+
+        let node = &received["node_completed"];
+        assert_eq!(node.node.as_deref(), Some("answer"));
+        assert_eq!(node.message.as_ref().unwrap().content, "re: hello");
+        let finished = &received["flow_finished"];
+        assert_eq!(finished.node.as_deref(), Some("done"));
+        assert_eq!(finished.run_id, received["flow_started"].run_id);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_webhook_messages_are_redacted() {
+        let (url, requests) = webhook_server();
+        let notifier = WebhookNotifier::from_value(serde_json::json!([{
+            "url": url,
+            "events": ["flow_finished", "node_completed"]
+        }]))
+        .unwrap();
+
+        let mut agents = AgentRegistry::new();
+        register_agent("reply", Arc::new(ReplyAgent), &mut agents);
+        let mut builder = FlowBuilder::new("support");
+        builder
+            .add_agent_node("answer", "reply")
+            .add_terminal_node("done")
+            .set_start("answer")
+            .set_pii_redactor(Arc::new(crate::guardrails::PiiRedactor::builtin()));
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_webhook_notifier(notifier);
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        executor
+            .start(ctx, AgentMessage::user("call 13812345678"))
+            .await
+            .unwrap();
+
+        let mut answered = None;
+        while let Ok((_, _, body)) = requests.recv_timeout(Duration::from_millis(500)) {
+            assert!(!body.contains("13812345678"));
+            let payload: LifecycleEvent = serde_json::from_str(&body).unwrap();
+            if payload.node.as_deref() == Some("answer") {
+                answered = payload.message;
+            }
+        }
+        assert_eq!(answered.unwrap().content, "re: call [PHONE]");
+    }
+
+    #[tokio::test]
+    async fn test_hung_endpoint_does_not_block_run() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        // 接受连接但从不响应
+        std::thread::spawn(move || {
+            let connections: Vec<_> = listener.incoming().take(2).collect();
+            std::thread::sleep(Duration::from_secs(5));
+            drop(connections);
+        });
+        let notifier = WebhookNotifier::from_value(serde_json::json!([{ "url": url }]))
+            .unwrap()
+            .with_timeout(Duration::from_millis(200));
+
+        let mut builder = FlowBuilder::new("support");
+        builder.add_terminal_node("done").set_start("done");
+        let executor =
+            FlowExecutor::new(builder.build(), AgentRegistry::new(), ToolRegistry::new())
+                .with_webhook_notifier(notifier);
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let run = executor.start(ctx, AgentMessage::user("hello"));
+        tokio::time::timeout(Duration::from_millis(100), run)
+            .await
+            .expect("run waited for the webhook")
+            .unwrap();
+    }
+}
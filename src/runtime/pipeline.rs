@@ -0,0 +1,326 @@
+//! 流水线转移：下游 Agent 在上游 Agent 生成时即开始消费其流式输出
+//!
+//! 上游节点执行时同时启动下游 Agent 的 `on_stream`；上游结束后若确实路由到下游，
+//! 下游的结果暂存在运行状态中，下游节点的事件到达时直接使用，不再调用 `on_message`。
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc;
+
+use super::token_sink::TokenSink;
+use super::types::FlowEvent;
+use crate::agent::{Agent, AgentAction, AgentRegistry, UpstreamStream};
+use crate::error::Result;
+use crate::flow::{Flow, FlowNodeKind};
+
+/// 节点的流水线目标：节点唯一的出边是无条件的 `pipeline` 转移，且指向接受流式输入的 Agent 节点
+///
+/// 节点还有其他出边时上游可能转向别的分支，提前启动的下游会在未被选中的分支上产生副作用。
+pub(super) fn target(
+    flow: &Flow,
+    node: &str,
+    agents: &AgentRegistry,
+) -> Option<(String, String, Arc<dyn Agent>)> {
+    let [transition] = flow.transitions(node) else {
+        return None;
+    };
+    if !transition.pipeline || transition.condition.is_some() {
+        return None;
+    }
+    let FlowNodeKind::Agent(agent_name) = &flow.node(&transition.to)?.kind else {
+        return None;
+    };
+    let agent = Arc::clone(agents.get(agent_name)?);
+    agent
+        .accepts_stream()
+        .then(|| (transition.to.clone(), agent_name.clone(), agent))
+}
+
+/// 下游事件的暂存键，与上游路由时生成的事件一致
+pub(super) fn key(trace_id: &str, node: &str, source: &str, iterations: u32) -> String {
+    format!("{}|{}|{}|{}", trace_id, node, source, iterations)
+}
+
+pub(super) fn event_key(event: &FlowEvent) -> String {
+    key(
+        &event.trace_id,
+        &event.node,
+        &event.source,
+        event.iterations,
+    )
+}
+
+/// 把上游的输出片段转给下游，同时保留原有的 `TokenSink`
+pub(super) struct PipeSink {
+    inner: Option<Arc<dyn TokenSink>>,
+    sender: Mutex<Option<mpsc::UnboundedSender<String>>>,
+    forwarded: AtomicBool,
+}
+
+impl PipeSink {
+    pub(super) fn new(inner: Option<Arc<dyn TokenSink>>, source: &str) -> (Self, UpstreamStream) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let sink = Self {
+            inner,
+            sender: Mutex::new(Some(sender)),
+            forwarded: AtomicBool::new(false),
+        };
+        (sink, UpstreamStream::new(source, receiver))
+    }
+
+    /// 结束下游的输入；上游没有流式输出时补发 `fallback`
+    fn close(&self, fallback: Option<&str>) {
+        let Some(sender) = self.sender.lock().expect("pipe sink poisoned").take() else {
+            return;
+        };
+        if let Some(content) = fallback.filter(|_| !self.forwarded.load(Ordering::SeqCst)) {
+            let _ = sender.send(content.to_string());
+        }
+    }
+}
+
+impl TokenSink for PipeSink {
+    fn on_token(&self, agent: &str, token: &str) {
+        if let Some(inner) = &self.inner {
+            inner.on_token(agent, token);
+        }
+        if let Some(sender) = self.sender.lock().expect("pipe sink poisoned").as_ref() {
+            self.forwarded.store(true, Ordering::SeqCst);
+            let _ = sender.send(token.to_string());
+        }
+    }
+
+    fn on_done(&self, agent: &str) {
+        if let Some(inner) = &self.inner {
+            inner.on_done(agent);
+        }
+    }
+}
+
+/// 上游动作是否路由到 `target`，是则返回发往它的消息内容
+fn routed<'a>(action: &'a AgentAction, target: &str) -> Option<Option<&'a str>> {
+    match action {
+        AgentAction::Next {
+            target: next,
+            message,
+        } if next == target => Some(Some(message.content.as_str())),
+        AgentAction::Branch { branches } => branches
+            .get(target)
+            .map(|message| Some(message.content.as_str())),
+        // 流水线转移无条件，`Continue` 总会经过它
+        AgentAction::Continue { message } => {
+            Some(message.as_ref().map(|message| message.content.as_str()))
+        }
+        _ => None,
+    }
+}
+
+/// 并发执行上游和下游；上游未路由到下游时取消下游，返回 `None`
+pub(super) async fn run<U, D>(
+    upstream: U,
+    downstream: D,
+    target: &str,
+    pipe: &PipeSink,
+) -> (Result<AgentAction>, Option<Result<AgentAction>>)
+where
+    U: Future<Output = Result<AgentAction>>,
+    D: Future<Output = Result<AgentAction>>,
+{
+    tokio::pin!(upstream);
+    tokio::pin!(downstream);
+    let mut finished = None;
+    let action = loop {
+        tokio::select! {
+            action = &mut upstream => break action,
+            result = &mut downstream, if finished.is_none() => finished = Some(result),
+        }
+    };
+    let routed = action
+        .as_ref()
+        .ok()
+        .and_then(|action| routed(action, target));
+    pipe.close(routed.flatten());
+    if routed.is_none() {
+        return (action, None);
+    }
+    let result = match finished {
+        Some(result) => result,
+        None => downstream.await,
+    };
+    (action, Some(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, AgentContext, AgentMessage};
+    use crate::flow::FlowBuilder;
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+    use async_trait::async_trait;
+    use std::time::Duration;
+
+    /// 逐词输出草稿，输出完成后才转到 `target`；`streaming` 为 false 时不输出片段
+    struct Drafter {
+        streaming: bool,
+        target: &'static str,
+        finished: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl Agent for Drafter {
+        fn name(&self) -> &'static str {
+            "drafter"
+        }
+
+        async fn on_message(
+            &self,
+            _message: AgentMessage,
+            ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let words = ["one ", "two ", "three"];
+            if let Some(sink) = ctx.flow().token_sink().filter(|_| self.streaming) {
+                for word in words {
+                    sink.on_token("drafter", word);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+            }
+            self.finished.store(true, Ordering::SeqCst);
+            Ok(AgentAction::Next {
+                target: self.target.into(),
+                message: AgentMessage::user(words.concat()),
+            })
+        }
+    }
+
+    /// 收到第一个片段时记录上游是否已结束
+    struct Shouter {
+        finished: Arc<AtomicBool>,
+        overlapped: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl Agent for Shouter {
+        fn name(&self) -> &'static str {
+            "shouter"
+        }
+
+        fn accepts_stream(&self) -> bool {
+            true
+        }
+
+        async fn on_stream(
+            &self,
+            mut upstream: UpstreamStream,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let mut text = String::new();
+            while let Some(chunk) = upstream.next().await {
+                if text.is_empty() {
+                    self.overlapped
+                        .store(!self.finished.load(Ordering::SeqCst), Ordering::SeqCst);
+                }
+                text.push_str(&chunk);
+            }
+            Ok(AgentAction::Finish {
+                message: Some(AgentMessage::user(text.to_uppercase())),
+            })
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Finish {
+                message: Some(AgentMessage::user(format!("late: {}", message.content))),
+            })
+        }
+    }
+
+    async fn run_flow(pipeline: bool, streaming: bool) -> (String, bool) {
+        let finished = Arc::new(AtomicBool::new(false));
+        let overlapped = Arc::new(AtomicBool::new(false));
+        let mut agents = AgentRegistry::new();
+        let drafter = Drafter {
+            streaming,
+            target: "shout",
+            finished: Arc::clone(&finished),
+        };
+        register_agent("drafter", Arc::new(drafter), &mut agents);
+        let shouter = Shouter {
+            finished,
+            overlapped: Arc::clone(&overlapped),
+        };
+        register_agent("shouter", Arc::new(shouter), &mut agents);
+        let mut builder = FlowBuilder::new("pipeline");
+        builder
+            .add_agent_node("draft", "drafter")
+            .add_agent_node("shout", "shouter")
+            .connect("draft", "shout")
+            .set_start("draft");
+        if pipeline {
+            builder.set_pipeline("draft", "shout");
+        }
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_token_sink(Arc::new(|_: &str, _: &str| {}));
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let execution = executor.start(ctx, AgentMessage::user("go")).await.unwrap();
+        let output = execution.last_message.unwrap().content;
+        (output, overlapped.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_streams_into_downstream() {
+        assert_eq!(
+            run_flow(true, true).await,
+            ("ONE TWO THREE".to_string(), true)
+        );
+        // 上游没有流式输出时结束后一次性收到消息
+        assert_eq!(
+            run_flow(true, false).await,
+            ("ONE TWO THREE".to_string(), false)
+        );
+        assert_eq!(
+            run_flow(false, true).await,
+            ("late: one two three".to_string(), false)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_untaken_branch_is_not_pipelined() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let overlapped = Arc::new(AtomicBool::new(false));
+        let mut agents = AgentRegistry::new();
+        let drafter = Drafter {
+            streaming: true,
+            target: "archive",
+            finished: Arc::clone(&finished),
+        };
+        register_agent("drafter", Arc::new(drafter), &mut agents);
+        let shouter = Shouter {
+            finished,
+            overlapped: Arc::clone(&overlapped),
+        };
+        register_agent("shouter", Arc::new(shouter), &mut agents);
+        let mut builder = FlowBuilder::new("pipeline");
+        builder
+            .add_agent_node("draft", "drafter")
+            .add_agent_node("shout", "shouter")
+            .add_terminal_node("archive")
+            .connect("draft", "shout")
+            .connect("draft", "archive")
+            .set_pipeline("draft", "shout")
+            .set_start("draft");
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_token_sink(Arc::new(|_: &str, _: &str| {}));
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let execution = executor.start(ctx, AgentMessage::user("go")).await.unwrap();
+        assert_eq!(execution.last_node, "archive");
+        // 上游还有其他出边时不提前启动下游，未被选中的分支没有消费任何片段
+        assert!(!overlapped.load(Ordering::SeqCst));
+    }
+}
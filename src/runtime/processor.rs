@@ -1,66 +1,64 @@
-use std::io::{self, Write};
 use std::sync::Arc;
-use tokio::sync::mpsc;
 use tracing::debug;
 
+use super::channel::RunUpdate;
+use super::executor::SubFlows;
 use super::handlers;
+use super::pipeline::{self, PipeSink};
+use super::queue::EventSender;
 use super::runtime::ExecutorRuntime;
 use super::state::SharedState;
 use super::types::{FlowEvent, TaskFinished, TaskResult};
-use crate::agent::{AgentAction, AgentContext, AgentRegistry};
+use crate::agent::{AgentAction, AgentContext, AgentMessage, AgentRegistry};
 use crate::error::{AgentFlowError, Result};
 use crate::flow::{Flow, FlowNodeKind};
 use crate::state::FlowContext;
 use crate::tools::{orchestrator::ToolOrchestrator, ToolRegistry};
 
 /// 处理单个事件
+#[allow(clippy::too_many_arguments)]
 pub async fn process_event(
     event: FlowEvent,
     flow: Arc<Flow>,
     agents: Arc<AgentRegistry>,
     tools: Arc<ToolRegistry>,
     ctx: Arc<FlowContext>,
-    sender: mpsc::UnboundedSender<FlowEvent>,
+    sender: EventSender,
     max_iterations: u32,
     tool_orchestrator: Option<Arc<ToolOrchestrator>>,
     shared: Arc<SharedState>,
+    sub_flows: Arc<SubFlows>,
 ) -> Result<TaskResult> {
     if event.iterations >= max_iterations {
         return Err(AgentFlowError::MaxIterationsExceeded(max_iterations));
     }
 
-    ctx.push_message(event.message.clone());
+    ctx.push_shared_message(Arc::clone(&event.message));
+    if let Some(channel) = ctx.channel() {
+        channel.publish(RunUpdate::NodeStarted {
+            node: event.node.clone(),
+            source: event.source.clone(),
+        });
+    }
 
     let node = flow
         .node(&event.node)
         .ok_or_else(|| AgentFlowError::UnknownNode(event.node.clone()))?;
 
-    let debug_mode = std::env::var("AGENTFLOW_DEBUG").is_ok();
-    if debug_mode {
-        eprintln!("▶️  正在执行节点: {} ({})", node.name, event.node);
-        std::io::stderr().flush().ok();
-    }
+    debug!(node = %node.name, source = %event.source, "executing node");
 
-    match &node.kind {
+    let node_name = node.name.clone();
+    let mut output = None;
+    let result = match &node.kind {
         FlowNodeKind::Terminal => {
-            if debug_mode {
-                eprintln!("🏁 到达终端节点: {}", node.name);
-                std::io::stderr().flush().ok();
-            }
             debug!("Reached terminal node `{}`", node.name);
-            return Ok(TaskResult::Finished(TaskFinished {
+            Ok(TaskResult::Finished(TaskFinished {
                 node: node.name.clone(),
-                message: Some(event.message.clone()),
-            }));
+                message: Some((*event.message).clone()),
+            }))
         }
         FlowNodeKind::Agent(agent_name) => {
-            if debug_mode {
-                eprintln!(
-                    "🤖 开始执行 Agent 节点: {} (agent: {})",
-                    node.name, agent_name
-                );
-                std::io::stderr().flush().ok();
-            }
+            debug!(node = %node.name, agent = %agent_name, "executing agent node");
             let agent = agents
                 .get(agent_name)
                 .ok_or_else(|| AgentFlowError::AgentNotRegistered(agent_name.clone()))?;
@@ -68,45 +66,97 @@ pub async fn process_event(
                 ctx: Arc::clone(&ctx),
                 tools: Arc::clone(&tools),
             };
+            let pipelined = shared.take_pipelined(&event).await;
+            let pipeline = pipelined
+                .is_none()
+                .then(|| pipeline::target(&flow, &node.name, &agents))
+                .flatten();
+            let mut node_ctx = ctx.for_node(flow.name.clone(), node.name.clone());
+            let mut upstream = None;
+            if pipeline.is_some() {
+                let (sink, stream) = PipeSink::new(ctx.token_sink().cloned(), &node.name);
+                let sink = Arc::new(sink);
+                node_ctx = node_ctx.with_token_sink(sink.clone());
+                upstream = Some((sink, stream));
+            }
             let agent_ctx = AgentContext {
-                flow_ctx: &ctx,
+                flow_ctx: &node_ctx,
                 runtime: &runtime_handle,
             };
 
-            {
-                let mut started = shared.started_agents.lock().await;
-                if started.insert(agent_name.clone()) {
-                    if debug_mode {
-                        eprintln!("  📌 首次启动 Agent: {}", agent_name);
-                        io::stderr().flush().ok();
-                    }
-                    agent.on_start(&agent_ctx).await?;
-                }
+            if shared.mark_agent_started(agent_name).await? {
+                debug!(agent = %agent_name, "starting agent");
+                agent.on_start(&agent_ctx).await?;
             }
 
-            let action = agent.on_message(event.message.clone(), &agent_ctx).await?;
+            let action = match (pipelined, pipeline, upstream) {
+                (Some(result), _, _) => {
+                    debug!(node = %node.name, "using pipelined result");
+                    result?
+                }
+                (None, Some((target, target_agent, downstream)), Some((sink, stream))) => {
+                    debug!(node = %node.name, target = %target, "starting pipelined agent");
+                    let downstream_ctx = ctx.for_node(flow.name.clone(), target.clone());
+                    let downstream_agent_ctx = AgentContext {
+                        flow_ctx: &downstream_ctx,
+                        runtime: &runtime_handle,
+                    };
+                    let (action, result) = pipeline::run(
+                        agent.on_message((*event.message).clone(), &agent_ctx),
+                        async {
+                            if shared.mark_agent_started(&target_agent).await? {
+                                downstream.on_start(&downstream_agent_ctx).await?;
+                            }
+                            downstream.on_stream(stream, &downstream_agent_ctx).await
+                        },
+                        &target,
+                        &sink,
+                    )
+                    .await;
+                    if let Some(result) = result {
+                        let key = pipeline::key(
+                            &event.trace_id,
+                            &target,
+                            &node.name,
+                            event.iterations + 1,
+                        );
+                        shared.store_pipelined(key, result).await;
+                    }
+                    action?
+                }
+                _ => {
+                    agent
+                        .on_message((*event.message).clone(), &agent_ctx)
+                        .await?
+                }
+            };
             if matches!(action, AgentAction::Finish { .. }) {
                 agent.on_finish(&agent_ctx).await?;
             }
-            handlers::handle_action(action, &event, flow, &ctx, &tools, sender).await
+            if shared.node_notifier.is_some() {
+                output = action_output(&action);
+            }
+            handlers::handle_action(action, &event, flow, &ctx, &tools, sender, &shared).await
         }
         FlowNodeKind::Decision(decision) => {
-            if debug_mode {
-                eprintln!("🔀 执行 Decision 节点: {}", node.name);
-                io::stderr().flush().ok();
-            }
-            handlers::handle_decision_node(decision, &node.name, &event, &ctx, sender).await
+            handlers::handle_decision_node(decision, &node.name, &event, &ctx, sender, &shared)
+                .await
+        }
+        FlowNodeKind::LlmDecision(decision) => {
+            handlers::handle_llm_decision_node(decision, &node.name, &event, sender, &shared).await
+        }
+        FlowNodeKind::Experiment(experiment) => {
+            handlers::handle_experiment_node(experiment, &node.name, &event, &ctx, sender).await
         }
         FlowNodeKind::Join(join) => {
-            if debug_mode {
-                eprintln!("🔗 执行 Join 节点: {}", node.name);
-                io::stderr().flush().ok();
-            }
             handlers::handle_join_node(join, &node.name, &event, &ctx, &flow, sender, &shared).await
         }
         FlowNodeKind::Loop(loop_node) => {
             handlers::handle_loop_node(loop_node, &node.name, &event, &ctx, sender, &shared).await
         }
+        FlowNodeKind::Map(map) => {
+            handlers::handle_map_node(map, &node.name, &event, &ctx, &flow, sender, &shared).await
+        }
         FlowNodeKind::Tool(tool_node) => {
             handlers::handle_tool_node(
                 tool_node,
@@ -116,8 +166,112 @@ pub async fn process_event(
                 Arc::clone(&flow),
                 sender,
                 tool_orchestrator,
+                &shared,
             )
             .await
         }
+        FlowNodeKind::SubFlow(sub_flow) => {
+            debug!(node = %node.name, flow = %sub_flow.flow, "executing sub-flow node");
+            handlers::handle_subflow_node(
+                sub_flow,
+                &node.name,
+                &event,
+                &ctx,
+                Arc::clone(&flow),
+                sender,
+                sub_flows,
+                &shared,
+            )
+            .await
+        }
+        FlowNodeKind::GroupChat(chat) => {
+            handlers::handle_group_chat_node(
+                chat,
+                &node.name,
+                &event,
+                &ctx,
+                &agents,
+                &tools,
+                Arc::clone(&flow),
+                sender,
+                &shared,
+            )
+            .await
+        }
+        FlowNodeKind::Debate(debate) => {
+            handlers::handle_debate_node(
+                debate,
+                &node.name,
+                &event,
+                &ctx,
+                &agents,
+                &tools,
+                Arc::clone(&flow),
+                sender,
+                &shared,
+            )
+            .await
+        }
+        FlowNodeKind::Transform(transform) => {
+            handlers::handle_transform_node(
+                transform,
+                &node.name,
+                &event,
+                &ctx,
+                Arc::clone(&flow),
+                sender,
+                &shared,
+            )
+            .await
+        }
+        FlowNodeKind::AwaitEvent(await_event) => {
+            handlers::handle_await_event_node(
+                await_event,
+                &node.name,
+                &event,
+                &ctx,
+                Arc::clone(&flow),
+                sender,
+                &shared,
+            )
+            .await
+        }
+        FlowNodeKind::Wait(wait) => {
+            handlers::handle_wait_node(wait, &node.name, &event, &ctx, &flow, sender, &shared).await
+        }
+        FlowNodeKind::ImageGen(image_node) => {
+            handlers::handle_image_gen_node(
+                image_node,
+                &node.name,
+                &event,
+                &ctx,
+                Arc::clone(&flow),
+                sender,
+                &shared,
+            )
+            .await
+        }
+    };
+
+    if let (Ok(_), Some(notifier)) = (&result, &shared.node_notifier) {
+        let output = output.map(|mut message| {
+            if let Some(redactor) = ctx.redactor() {
+                redactor.redact_message(&mut message);
+            }
+            message
+        });
+        notifier.node_completed(&node_name, output);
+    }
+    result
+}
+
+/// Agent 动作中携带的输出消息
+pub(super) fn action_output(action: &AgentAction) -> Option<AgentMessage> {
+    match action {
+        AgentAction::Next { message, .. } | AgentAction::Refused { message, .. } => {
+            Some(message.clone())
+        }
+        AgentAction::Continue { message } | AgentAction::Finish { message } => message.clone(),
+        AgentAction::Branch { .. } | AgentAction::CallTool { .. } => None,
     }
 }
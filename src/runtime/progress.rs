@@ -0,0 +1,302 @@
+//! 运行进度：按 trace 统计已完成节点和仍可到达的节点，估算完成百分比
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use super::types::FlowEvent;
+use crate::flow::analysis::flow_edges;
+use crate::flow::{Flow, FlowNodeKind};
+
+/// 一个 trace 的进度
+///
+/// `percent` 为已完成节点数占（已完成 + 仍可到达）节点数的比例，分支未选定前按所有可能的路径估算，
+/// 到达终点时为 100。Map 的每个元素有独立的 trace。
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ProgressUpdate {
+    pub run_id: String,
+    pub trace_id: String,
+    /// 刚完成的节点
+    pub node: String,
+    /// 已完成的不同节点数
+    pub completed: usize,
+    /// 已完成 + 仍可到达的节点数
+    pub reachable: usize,
+    pub percent: u8,
+    pub finished: bool,
+    /// 各 Loop 节点已执行的轮数
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub loops: BTreeMap<String, LoopProgress>,
+}
+
+/// Loop 节点的轮数
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct LoopProgress {
+    pub iteration: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_iterations: Option<u32>,
+}
+
+/// 进度事件的订阅入口
+///
+/// 通过 `FlowExecutor::with_progress_reporter` 挂载后，每个节点完成时广播一次 `ProgressUpdate`，
+/// 上下文挂载了 `RunChannel` 时同时推送 `RunUpdate::Progress`；运行结束时各 trace 的最终进度
+/// 保存在 `FlowExecution::progress`。
+#[derive(Clone)]
+pub struct ProgressReporter {
+    updates: broadcast::Sender<ProgressUpdate>,
+}
+
+impl ProgressReporter {
+    /// `capacity` 为事件缓冲大小，订阅方落后超过该数量时会丢失较早的事件
+    pub fn new(capacity: usize) -> Self {
+        let (updates, _) = broadcast::channel(capacity.max(1));
+        Self { updates }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ProgressUpdate> {
+        self.updates.subscribe()
+    }
+
+    fn emit(&self, update: ProgressUpdate) {
+        // 没有订阅者时忽略
+        let _ = self.updates.send(update);
+    }
+}
+
+impl Default for ProgressReporter {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+#[derive(Default)]
+struct TraceProgress {
+    completed: BTreeSet<String>,
+    /// 执行中的节点及其事件数
+    active: HashMap<String, usize>,
+    loops: BTreeMap<String, LoopProgress>,
+    last: Option<ProgressUpdate>,
+}
+
+/// 单次运行的进度状态
+pub(super) struct RunProgress {
+    run_id: String,
+    reporter: ProgressReporter,
+    successors: HashMap<String, Vec<String>>,
+    loop_bounds: HashMap<String, Option<u32>>,
+    traces: Mutex<BTreeMap<String, TraceProgress>>,
+}
+
+impl RunProgress {
+    pub(super) fn new(flow: &Flow, run_id: &str, reporter: ProgressReporter) -> Self {
+        let mut successors: HashMap<String, Vec<String>> = HashMap::new();
+        for (from, to, _) in flow_edges(flow) {
+            successors.entry(from).or_default().push(to);
+        }
+        let loop_bounds = flow
+            .nodes
+            .values()
+            .filter_map(|node| match &node.kind {
+                FlowNodeKind::Loop(loop_node) => {
+                    Some((node.name.clone(), loop_node.max_iterations))
+                }
+                _ => None,
+            })
+            .collect();
+        Self {
+            run_id: run_id.to_string(),
+            reporter,
+            successors,
+            loop_bounds,
+            traces: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub(super) fn node_started(&self, event: &FlowEvent) {
+        let mut traces = self.traces.lock();
+        let trace = traces.entry(event.trace_id.clone()).or_default();
+        *trace.active.entry(event.node.clone()).or_default() += 1;
+        if let Some(&max_iterations) = self.loop_bounds.get(&event.node) {
+            trace
+                .loops
+                .entry(event.node.clone())
+                .or_insert(LoopProgress {
+                    iteration: 0,
+                    max_iterations,
+                })
+                .iteration += 1;
+        }
+    }
+
+    /// 记录节点完成并广播进度
+    pub(super) fn node_finished(
+        &self,
+        trace_id: &str,
+        node: &str,
+        finished: bool,
+    ) -> ProgressUpdate {
+        let update = {
+            let mut traces = self.traces.lock();
+            let trace = traces.entry(trace_id.to_string()).or_default();
+            if let Some(count) = trace.active.get_mut(node) {
+                *count -= 1;
+                if *count == 0 {
+                    trace.active.remove(node);
+                }
+            }
+            trace.completed.insert(node.to_string());
+
+            let remaining = if finished {
+                0
+            } else {
+                let frontier = trace
+                    .active
+                    .keys()
+                    .chain(self.successors.get(node).into_iter().flatten());
+                self.remaining(frontier, &trace.completed)
+            };
+            let completed = trace.completed.len();
+            let reachable = completed + remaining;
+            let update = ProgressUpdate {
+                run_id: self.run_id.clone(),
+                trace_id: trace_id.to_string(),
+                node: node.to_string(),
+                completed,
+                reachable,
+                percent: (completed * 100 / reachable) as u8,
+                finished,
+                loops: trace.loops.clone(),
+            };
+            trace.last = Some(update.clone());
+            update
+        };
+        self.reporter.emit(update.clone());
+        update
+    }
+
+    /// 从 `frontier` 出发可到达、尚未完成的节点数；已完成的节点仍会继续向后搜索
+    fn remaining<'a>(
+        &'a self,
+        frontier: impl Iterator<Item = &'a String>,
+        completed: &BTreeSet<String>,
+    ) -> usize {
+        let mut seen: BTreeSet<&str> = BTreeSet::new();
+        let mut queue: VecDeque<&str> = frontier.map(String::as_str).collect();
+        while let Some(node) = queue.pop_front() {
+            if !seen.insert(node) {
+                continue;
+            }
+            queue.extend(
+                self.successors
+                    .get(node)
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str),
+            );
+        }
+        seen.iter()
+            .filter(|node| !completed.contains(**node))
+            .count()
+    }
+
+    /// 各 trace 的最终进度
+    pub(super) fn take(&self) -> Vec<ProgressUpdate> {
+        std::mem::take(&mut *self.traces.lock())
+            .into_values()
+            .filter_map(|trace| trace.last)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{
+        register_agent, Agent, AgentAction, AgentContext, AgentMessage, AgentRegistry,
+    };
+    use crate::error::Result;
+    use crate::flow::{loop_condition_from_fn, FlowBuilder};
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    struct Step;
+
+    #[async_trait]
+    impl Agent for Step {
+        fn name(&self) -> &'static str {
+            "step"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Continue {
+                message: Some(message),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_progress_counts_nodes_and_loop_iterations() {
+        let mut agents = AgentRegistry::new();
+        register_agent("step", Arc::new(Step), &mut agents);
+        let counter = Arc::new(AtomicU32::new(0));
+        let mut builder = FlowBuilder::new("progress");
+        builder
+            .add_agent_node("intake", "step")
+            .add_loop_node(
+                "review",
+                "draft",
+                Some(loop_condition_from_fn(move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst) < 2
+                })),
+                Some(5),
+                Some("publish".into()),
+            )
+            .add_agent_node("draft", "step")
+            .add_agent_node("publish", "step")
+            .add_terminal_node("done")
+            .set_start("intake")
+            .connect("intake", "review")
+            .connect("draft", "review")
+            .connect("publish", "done");
+
+        let reporter = ProgressReporter::new(64);
+        let mut updates = reporter.subscribe();
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_progress_reporter(reporter);
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let execution = executor.start(ctx, AgentMessage::user("go")).await.unwrap();
+
+        let first = updates.try_recv().unwrap();
+        assert_eq!(first.node, "intake");
+        assert_eq!(
+            (first.completed, first.reachable, first.percent),
+            (1, 5, 20)
+        );
+        let mut last = first;
+        while let Ok(update) = updates.try_recv() {
+            assert!(update.percent >= last.percent);
+            last = update;
+        }
+        assert!(last.finished);
+        assert_eq!(last.percent, 100);
+        assert_eq!(
+            last.loops["review"],
+            LoopProgress {
+                iteration: 3,
+                max_iterations: Some(5),
+            }
+        );
+        assert_eq!(execution.progress, vec![last]);
+    }
+}
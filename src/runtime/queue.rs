@@ -0,0 +1,840 @@
+//! 执行器的事件队列
+//!
+//! 默认使用有容量上限的内存队列：节点产生的事件在队列满时等待，超过 `push_timeout` 仍无空位时
+//! 返回错误，避免队列无限增长。持久化队列（如 `RedisEventQueue`）中的事件在进程崩溃后保留，
+//! 可以通过 `FlowExecutor::resume` 继续执行。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::Notify;
+
+use super::types::FlowEvent;
+use crate::error::{AgentFlowError, Result};
+
+/// 默认容量
+pub const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+/// 队列满时入队的默认等待时间
+pub const DEFAULT_PUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 取出的事件和确认凭据
+pub struct Delivery {
+    pub event: FlowEvent,
+    pub receipt: String,
+}
+
+/// 事件队列的深度与背压统计
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct QueueStats {
+    /// 当前排队的事件数（所有运行合计）
+    pub depth: usize,
+    /// 单次运行达到过的最大排队数
+    pub peak_depth: usize,
+    /// 因队列满而等待的入队次数
+    pub blocked_pushes: u64,
+    /// 等待超时被拒绝的入队次数
+    pub rejected_pushes: u64,
+}
+
+/// 事件队列，按运行 id 隔离
+#[async_trait]
+pub trait EventQueue: Send + Sync {
+    /// 入队；队列满时等待
+    async fn push(&self, run_id: &str, event: FlowEvent) -> Result<()>;
+
+    /// 取出下一个事件，队列为空时立即返回 None
+    async fn pop(&self, run_id: &str) -> Result<Option<Delivery>>;
+
+    /// 事件处理完成（后续事件已入队）后确认
+    async fn ack(&self, _run_id: &str, _receipt: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// 把已取出但未确认的事件放回队列，返回放回的数量
+    async fn requeue_unacked(&self, _run_id: &str) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// 运行结束后清理该运行的队列
+    async fn clear(&self, run_id: &str) -> Result<()>;
+
+    /// 事件是否在进程崩溃后保留
+    fn is_durable(&self) -> bool {
+        false
+    }
+
+    /// 深度与背压统计，不支持时返回 None
+    fn stats(&self) -> Option<QueueStats> {
+        None
+    }
+}
+
+/// 向某次运行的事件队列投递事件
+#[derive(Clone)]
+pub struct EventSender {
+    queue: Arc<dyn EventQueue>,
+    run_id: Arc<str>,
+    sends: Arc<PendingSends>,
+}
+
+/// 正在进行（可能因队列满而等待）的入队次数，调度器据此判断是否所有任务都卡在入队上
+#[derive(Default)]
+struct PendingSends {
+    count: AtomicUsize,
+    changed: Notify,
+}
+
+/// 入队结束（包括被取消）时减少计数
+struct SendGuard<'a>(&'a PendingSends);
+
+impl Drop for SendGuard<'_> {
+    fn drop(&mut self) {
+        self.0.count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl EventSender {
+    pub fn new(queue: Arc<dyn EventQueue>, run_id: &str) -> Self {
+        Self {
+            queue,
+            run_id: run_id.into(),
+            sends: Arc::default(),
+        }
+    }
+
+    pub async fn send(&self, event: FlowEvent) -> Result<()> {
+        self.sends.count.fetch_add(1, Ordering::SeqCst);
+        let _guard = SendGuard(&self.sends);
+        self.sends.changed.notify_waiters();
+        self.queue.push(&self.run_id, event).await
+    }
+
+    pub(crate) fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// 当前正在入队的次数
+    pub(crate) fn pending_sends(&self) -> usize {
+        self.sends.count.load(Ordering::SeqCst)
+    }
+
+    /// 有新的入队开始时完成；需在检查 `pending_sends` 之前创建
+    pub(crate) fn send_started(&self) -> tokio::sync::futures::Notified<'_> {
+        self.sends.changed.notified()
+    }
+}
+
+fn queue_full(capacity: usize) -> AgentFlowError {
+    AgentFlowError::Other(anyhow!(
+        "event queue full (capacity {}); increase the capacity or reduce fan-out",
+        capacity
+    ))
+}
+
+/// 内存事件队列（默认）
+pub struct MemoryEventQueue {
+    capacity: usize,
+    push_timeout: Duration,
+    runs: Mutex<HashMap<String, VecDeque<FlowEvent>>>,
+    space: Notify,
+    peak_depth: AtomicUsize,
+    blocked_pushes: AtomicU64,
+    rejected_pushes: AtomicU64,
+}
+
+impl MemoryEventQueue {
+    /// 每次运行最多排队 `capacity` 个事件
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            push_timeout: DEFAULT_PUSH_TIMEOUT,
+            runs: Mutex::new(HashMap::new()),
+            space: Notify::new(),
+            peak_depth: AtomicUsize::new(0),
+            blocked_pushes: AtomicU64::new(0),
+            rejected_pushes: AtomicU64::new(0),
+        }
+    }
+
+    /// 不限制容量
+    pub fn unbounded() -> Self {
+        Self::new(usize::MAX)
+    }
+
+    pub fn with_push_timeout(mut self, timeout: Duration) -> Self {
+        self.push_timeout = timeout;
+        self
+    }
+}
+
+impl Default for MemoryEventQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUEUE_CAPACITY)
+    }
+}
+
+#[async_trait]
+impl EventQueue for MemoryEventQueue {
+    async fn push(&self, run_id: &str, event: FlowEvent) -> Result<()> {
+        let deadline = tokio::time::Instant::now() + self.push_timeout;
+        let mut event = Some(event);
+        let mut blocked = false;
+        loop {
+            // 先注册等待再检查容量，避免错过 pop 的通知
+            let space = self.space.notified();
+            {
+                let mut runs = self.runs.lock();
+                let queue = runs.entry(run_id.to_string()).or_default();
+                if queue.len() < self.capacity {
+                    queue.extend(event.take());
+                    self.peak_depth.fetch_max(queue.len(), Ordering::Relaxed);
+                    return Ok(());
+                }
+            }
+            if !blocked {
+                blocked = true;
+                self.blocked_pushes.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!(
+                    run_id,
+                    capacity = self.capacity,
+                    "event queue full, waiting"
+                );
+            }
+            tokio::time::timeout_at(deadline, space)
+                .await
+                .map_err(|_| {
+                    self.rejected_pushes.fetch_add(1, Ordering::Relaxed);
+                    queue_full(self.capacity)
+                })?;
+        }
+    }
+
+    async fn pop(&self, run_id: &str) -> Result<Option<Delivery>> {
+        let event = self
+            .runs
+            .lock()
+            .get_mut(run_id)
+            .and_then(VecDeque::pop_front);
+        if event.is_some() {
+            self.space.notify_waiters();
+        }
+        Ok(event.map(|event| Delivery {
+            event,
+            receipt: String::new(),
+        }))
+    }
+
+    async fn clear(&self, run_id: &str) -> Result<()> {
+        self.runs.lock().remove(run_id);
+        self.space.notify_waiters();
+        Ok(())
+    }
+
+    fn stats(&self) -> Option<QueueStats> {
+        Some(QueueStats {
+            depth: self.runs.lock().values().map(VecDeque::len).sum(),
+            peak_depth: self.peak_depth.load(Ordering::Relaxed),
+            blocked_pushes: self.blocked_pushes.load(Ordering::Relaxed),
+            rejected_pushes: self.rejected_pushes.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// Redis 列表事件队列（需启用 `redis-queue` feature）
+///
+/// 每次运行使用 `agentflow:events:<run_id>` 和 `…:processing` 两个列表：取出时用 LMOVE
+/// 移入处理中列表，确认后删除，崩溃后可由 `requeue_unacked` 放回。需要 Redis 6.2+。
+#[cfg(feature = "redis-queue")]
+pub struct RedisEventQueue {
+    client: ::redis::Client,
+    capacity: usize,
+    push_timeout: Duration,
+}
+
+#[cfg(feature = "redis-queue")]
+impl RedisEventQueue {
+    pub fn new(client: ::redis::Client) -> Self {
+        Self {
+            client,
+            capacity: DEFAULT_QUEUE_CAPACITY,
+            push_timeout: DEFAULT_PUSH_TIMEOUT,
+        }
+    }
+
+    pub fn open(url: &str) -> Result<Self> {
+        let client = ::redis::Client::open(url)
+            .map_err(|e| AgentFlowError::Other(anyhow!("Invalid Redis url `{}`: {}", url, e)))?;
+        Ok(Self::new(client))
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    pub fn with_push_timeout(mut self, timeout: Duration) -> Self {
+        self.push_timeout = timeout;
+        self
+    }
+
+    async fn connection(&self) -> Result<::redis::aio::MultiplexedConnection> {
+        self.client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(redis_error)
+    }
+
+    fn keys(run_id: &str) -> (String, String) {
+        let pending = format!("agentflow:events:{run_id}");
+        let processing = format!("{pending}:processing");
+        (pending, processing)
+    }
+}
+
+#[cfg(feature = "redis-queue")]
+fn redis_error(error: ::redis::RedisError) -> AgentFlowError {
+    AgentFlowError::Other(anyhow!("Redis event queue error: {}", error))
+}
+
+#[cfg(feature = "redis-queue")]
+#[async_trait]
+impl EventQueue for RedisEventQueue {
+    async fn push(&self, run_id: &str, event: FlowEvent) -> Result<()> {
+        use ::redis::AsyncCommands;
+
+        let payload = serde_json::to_string(&event)
+            .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        let (pending, _) = Self::keys(run_id);
+        let mut conn = self.connection().await?;
+        let deadline = tokio::time::Instant::now() + self.push_timeout;
+        loop {
+            let len: usize = conn.llen(&pending).await.map_err(redis_error)?;
+            if len < self.capacity {
+                break;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(queue_full(self.capacity));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        conn.rpush::<_, _, ()>(&pending, payload)
+            .await
+            .map_err(redis_error)
+    }
+
+    async fn pop(&self, run_id: &str) -> Result<Option<Delivery>> {
+        use ::redis::AsyncCommands;
+
+        let (pending, processing) = Self::keys(run_id);
+        let payload: Option<String> = self
+            .connection()
+            .await?
+            .lmove(
+                &pending,
+                &processing,
+                ::redis::Direction::Left,
+                ::redis::Direction::Right,
+            )
+            .await
+            .map_err(redis_error)?;
+        payload
+            .map(|payload| {
+                let event = serde_json::from_str(&payload)
+                    .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+                Ok(Delivery {
+                    event,
+                    receipt: payload,
+                })
+            })
+            .transpose()
+    }
+
+    async fn ack(&self, run_id: &str, receipt: &str) -> Result<()> {
+        use ::redis::AsyncCommands;
+
+        let (_, processing) = Self::keys(run_id);
+        self.connection()
+            .await?
+            .lrem::<_, _, ()>(&processing, 1, receipt)
+            .await
+            .map_err(redis_error)
+    }
+
+    async fn requeue_unacked(&self, run_id: &str) -> Result<usize> {
+        use ::redis::AsyncCommands;
+
+        let (pending, processing) = Self::keys(run_id);
+        let mut conn = self.connection().await?;
+        let mut moved = 0;
+        // 放回队首，先于崩溃后新入队的事件处理
+        while conn
+            .lmove::<_, _, Option<String>>(
+                &processing,
+                &pending,
+                ::redis::Direction::Right,
+                ::redis::Direction::Left,
+            )
+            .await
+            .map_err(redis_error)?
+            .is_some()
+        {
+            moved += 1;
+        }
+        Ok(moved)
+    }
+
+    async fn clear(&self, run_id: &str) -> Result<()> {
+        use ::redis::AsyncCommands;
+
+        let (pending, processing) = Self::keys(run_id);
+        self.connection()
+            .await?
+            .del::<_, ()>(&[pending, processing])
+            .await
+            .map_err(redis_error)
+    }
+
+    fn is_durable(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{
+        register_agent, Agent, AgentAction, AgentContext, AgentMessage, AgentRegistry,
+    };
+    use crate::flow::{FlowBuilder, JoinStrategy};
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    fn event(node: &str) -> FlowEvent {
+        FlowEvent {
+            node: node.into(),
+            message: AgentMessage::user(node).into(),
+            iterations: 0,
+            trace_id: "t".into(),
+            source: "__start__".into(),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_memory_queue_backpressure() {
+        let queue = Arc::new(MemoryEventQueue::new(1).with_push_timeout(Duration::from_secs(1)));
+        queue.push("run", event("a")).await.unwrap();
+        assert!(queue.push("other", event("x")).await.is_ok());
+
+        let waiting = {
+            let queue = Arc::clone(&queue);
+            tokio::spawn(async move { queue.push("run", event("b")).await })
+        };
+        tokio::task::yield_now().await;
+        assert_eq!(queue.pop("run").await.unwrap().unwrap().event.node, "a");
+        waiting.await.unwrap().unwrap();
+        assert_eq!(queue.pop("run").await.unwrap().unwrap().event.node, "b");
+        assert!(queue.pop("run").await.unwrap().is_none());
+
+        queue.push("run", event("c")).await.unwrap();
+        let error = queue.push("run", event("d")).await.unwrap_err();
+        assert!(error.to_string().contains("event queue full"));
+        assert_eq!(
+            queue.stats(),
+            Some(QueueStats {
+                depth: 2,
+                peak_depth: 1,
+                blocked_pushes: 2,
+                rejected_pushes: 1,
+            })
+        );
+    }
+
+    const FAN_OUT: usize = 16;
+
+    /// 把消息分给 `w0`..`w15`
+    struct FanOutAgent;
+
+    #[async_trait]
+    impl Agent for FanOutAgent {
+        fn name(&self) -> &'static str {
+            "fan_out"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let branches = (0..FAN_OUT)
+                .map(|i| (format!("w{i}"), message.clone()))
+                .collect();
+            Ok(AgentAction::Branch { branches })
+        }
+    }
+
+    /// 把消息交给 Join 节点
+    struct RelayAgent;
+
+    #[async_trait]
+    impl Agent for RelayAgent {
+        fn name(&self) -> &'static str {
+            "relay"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Next {
+                target: "merge".into(),
+                message,
+            })
+        }
+    }
+
+    /// 扇出数超过队列容量与并发数之和的流程
+    fn fan_out_executor() -> FlowExecutor {
+        let mut agents = AgentRegistry::new();
+        register_agent("fan_out", Arc::new(FanOutAgent), &mut agents);
+        register_agent("relay", Arc::new(RelayAgent), &mut agents);
+        let workers: Vec<String> = (0..FAN_OUT).map(|i| format!("w{i}")).collect();
+        let mut builder = FlowBuilder::new("fan_out");
+        builder.add_agent_node("split", "fan_out");
+        for worker in &workers {
+            builder.add_agent_node(worker, "relay");
+        }
+        builder
+            .add_join_node("merge", JoinStrategy::All, workers)
+            .add_terminal_node("done")
+            .set_start("split")
+            .connect("merge", "done");
+        FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_executor_fan_out_beyond_capacity() {
+        let queue = Arc::new(MemoryEventQueue::new(2).with_push_timeout(Duration::from_secs(1)));
+        let executor = fan_out_executor()
+            .with_max_concurrency(4)
+            .with_event_queue(queue.clone());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+
+        let execution = executor
+            .start(ctx, AgentMessage::user("job"))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "done");
+        assert_eq!(queue.stats().unwrap().rejected_pushes, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_queue_capacity_below_fan_out() {
+        let executor = fan_out_executor().with_queue_capacity(2);
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+
+        let execution = executor
+            .start(ctx, AgentMessage::user("job"))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "done");
+        let stats = executor.queue_stats().unwrap();
+        assert_eq!(stats.depth, 0);
+        assert_eq!(stats.peak_depth, 2);
+        assert!(stats.blocked_pushes > 0);
+        assert_eq!(stats.rejected_pushes, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fan_out_beyond_queue_and_overflow_fails() {
+        // 单个任务扇出 16 个事件，队列容量 2、并发 1，暂存上限 4
+        let queue = Arc::new(MemoryEventQueue::new(2).with_push_timeout(Duration::from_secs(60)));
+        let executor = fan_out_executor()
+            .with_max_concurrency(1)
+            .with_overflow_limit(4)
+            .with_event_queue(queue.clone());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+
+        let started = tokio::time::Instant::now();
+        let Err(error) = executor.start(ctx, AgentMessage::user("job")).await else {
+            panic!("expected the run to fail");
+        };
+        assert!(error.to_string().contains("event queue full"), "{error}");
+        assert!(started.elapsed() < Duration::from_secs(60));
+        assert_eq!(queue.stats().unwrap().rejected_pushes, 0);
+    }
+
+    /// 模拟持久化队列：未确认的事件保留在 processing 中
+    #[derive(Default)]
+    struct DurableQueue {
+        pending: Mutex<HashMap<String, VecDeque<FlowEvent>>>,
+        processing: Mutex<HashMap<String, Vec<(String, FlowEvent)>>>,
+        next: AtomicUsize,
+        /// 模拟处理完成后、确认前崩溃
+        drop_acks: AtomicBool,
+    }
+
+    #[async_trait]
+    impl EventQueue for DurableQueue {
+        async fn push(&self, run_id: &str, event: FlowEvent) -> Result<()> {
+            self.pending
+                .lock()
+                .entry(run_id.to_string())
+                .or_default()
+                .push_back(event);
+            Ok(())
+        }
+
+        async fn pop(&self, run_id: &str) -> Result<Option<Delivery>> {
+            let Some(event) = self
+                .pending
+                .lock()
+                .get_mut(run_id)
+                .and_then(VecDeque::pop_front)
+            else {
+                return Ok(None);
+            };
+            let receipt = self.next.fetch_add(1, Ordering::SeqCst).to_string();
+            self.processing
+                .lock()
+                .entry(run_id.to_string())
+                .or_default()
+                .push((receipt.clone(), event.clone()));
+            Ok(Some(Delivery { event, receipt }))
+        }
+
+        async fn ack(&self, run_id: &str, receipt: &str) -> Result<()> {
+            if self.drop_acks.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            if let Some(items) = self.processing.lock().get_mut(run_id) {
+                items.retain(|(id, _)| id != receipt);
+            }
+            Ok(())
+        }
+
+        async fn requeue_unacked(&self, run_id: &str) -> Result<usize> {
+            let items = self.processing.lock().remove(run_id).unwrap_or_default();
+            let mut pending = self.pending.lock();
+            let queue = pending.entry(run_id.to_string()).or_default();
+            for (_, event) in items.iter().rev() {
+                queue.push_front(event.clone());
+            }
+            Ok(items.len())
+        }
+
+        async fn clear(&self, run_id: &str) -> Result<()> {
+            self.pending.lock().remove(run_id);
+            self.processing.lock().remove(run_id);
+            Ok(())
+        }
+
+        fn is_durable(&self) -> bool {
+            true
+        }
+    }
+
+    /// 第一次运行时卡住，模拟进程在处理中崩溃
+    struct GateAgent {
+        crashed: AtomicBool,
+        reached: Notify,
+    }
+
+    #[async_trait]
+    impl Agent for GateAgent {
+        fn name(&self) -> &'static str {
+            "gate"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            if !self.crashed.swap(true, Ordering::SeqCst) {
+                self.reached.notify_one();
+                std::future::pending::<()>().await;
+            }
+            Ok(AgentAction::Next {
+                target: "done".into(),
+                message,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_crash() {
+        let gate = Arc::new(GateAgent {
+            crashed: AtomicBool::new(false),
+            reached: Notify::new(),
+        });
+        let mut agents = AgentRegistry::new();
+        register_agent("gate", gate.clone(), &mut agents);
+        let mut builder = FlowBuilder::new("resumable");
+        builder
+            .add_agent_node("check", "gate")
+            .add_terminal_node("done")
+            .set_start("check");
+        let queue = Arc::new(DurableQueue::default());
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_event_queue(queue.clone());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+
+        let run = {
+            let executor = executor.clone();
+            let ctx = Arc::clone(&ctx);
+            tokio::spawn(async move {
+                executor
+                    .start_with_run_id(ctx, AgentMessage::user("order"), "run-1")
+                    .await
+            })
+        };
+        gate.reached.notified().await;
+        run.abort();
+        let _ = run.await;
+        assert_eq!(queue.processing.lock()["run-1"].len(), 1);
+
+        let execution = executor.resume(ctx, "run-1").await.unwrap();
+        assert_eq!(execution.last_node, "done");
+        assert_eq!(execution.last_message.unwrap().content, "order");
+        assert!(queue.processing.lock().get("run-1").is_none());
+
+        let mut plain = FlowBuilder::new("plain");
+        plain.add_terminal_node("done").set_start("done");
+        let in_memory = FlowExecutor::new(plain.build(), AgentRegistry::new(), ToolRegistry::new());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        assert!(in_memory.resume(ctx, "run-1").await.is_err());
+    }
+
+    /// 有副作用的 Agent：记录调用次数
+    #[derive(Default)]
+    struct ChargeAgent {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Agent for ChargeAgent {
+        fn name(&self) -> &'static str {
+            "charge"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(AgentAction::Next {
+                target: "check".into(),
+                message,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_skips_processed_events() {
+        let charge = Arc::new(ChargeAgent::default());
+        let gate = Arc::new(GateAgent {
+            crashed: AtomicBool::new(false),
+            reached: Notify::new(),
+        });
+        let mut agents = AgentRegistry::new();
+        register_agent("charge", charge.clone(), &mut agents);
+        register_agent("gate", gate.clone(), &mut agents);
+        let mut builder = FlowBuilder::new("payment");
+        builder
+            .add_agent_node("charge", "charge")
+            .add_agent_node("check", "gate")
+            .add_terminal_node("done")
+            .set_start("charge");
+        let queue = Arc::new(DurableQueue::default());
+        queue.drop_acks.store(true, Ordering::SeqCst);
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_event_queue(queue.clone())
+            .with_event_dedup(true);
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+
+        let run = {
+            let executor = executor.clone();
+            let ctx = Arc::clone(&ctx);
+            tokio::spawn(async move {
+                executor
+                    .start_with_run_id(ctx, AgentMessage::user("order"), "run-2")
+                    .await
+            })
+        };
+        gate.reached.notified().await;
+        run.abort();
+        let _ = run.await;
+        assert_eq!(queue.processing.lock()["run-2"].len(), 2);
+
+        queue.drop_acks.store(false, Ordering::SeqCst);
+        let execution = executor.resume(ctx, "run-2").await.unwrap();
+        assert_eq!(execution.last_node, "done");
+        assert_eq!(charge.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resume_migrates_events_to_new_version() {
+        let gate = Arc::new(GateAgent {
+            crashed: AtomicBool::new(false),
+            reached: Notify::new(),
+        });
+        let mut agents = AgentRegistry::new();
+        register_agent("gate", gate.clone(), &mut agents);
+        let flow = |version: u32, check: &str| {
+            let mut builder = FlowBuilder::new("orders");
+            builder
+                .set_version(version)
+                .add_agent_node(check, "gate")
+                .add_terminal_node("done")
+                .set_start(check);
+            builder.build()
+        };
+        let queue = Arc::new(DurableQueue::default());
+        let v1 = FlowExecutor::new(flow(1, "check"), agents.clone(), ToolRegistry::new())
+            .with_event_queue(queue.clone());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+
+        let run = {
+            let ctx = Arc::clone(&ctx);
+            tokio::spawn(async move {
+                v1.start_with_run_id(ctx, AgentMessage::user("order"), "run-3")
+                    .await
+            })
+        };
+        gate.reached.notified().await;
+        run.abort();
+        let _ = run.await;
+
+        let v2 = FlowExecutor::new(flow(2, "verify"), agents, ToolRegistry::new())
+            .with_event_queue(queue.clone());
+        let result = v2.resume(Arc::clone(&ctx), "run-3").await;
+        assert!(matches!(
+            result,
+            Err(AgentFlowError::VersionMismatch {
+                run_version: 1,
+                flow_version: 2,
+                ..
+            })
+        ));
+
+        let v2 = v2.with_migration(1, |mut event| {
+            if event.node == "check" {
+                event.node = "verify".into();
+            }
+            Ok(Some(event))
+        });
+        let execution = v2.resume(ctx, "run-3").await.unwrap();
+        assert_eq!(execution.last_node, "done");
+        assert_eq!(execution.last_message.unwrap().content, "order");
+    }
+}
@@ -0,0 +1,63 @@
+//! 外部事件恢复令牌：`await_event` 节点签发令牌并挂起，`FlowExecutor::deliver_event` 投递后继续
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+use crate::error::{AgentFlowError, Result};
+
+/// 等待中的外部事件，按令牌索引；同一执行器的克隆共享
+#[derive(Clone, Default)]
+pub struct ResumeTokens {
+    pending: Arc<parking_lot::Mutex<HashMap<String, oneshot::Sender<Value>>>>,
+}
+
+impl ResumeTokens {
+    /// 签发新令牌，返回令牌和等待载荷的接收端
+    ///
+    /// 返回的 `IssuedToken` 释放时撤销令牌，等待超时、出错或任务被取消时都不会遗留。
+    pub(super) fn issue(&self) -> (IssuedToken, oneshot::Receiver<Value>) {
+        let token = crate::agent::message::uuid();
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().insert(token.clone(), sender);
+        let issued = IssuedToken {
+            token,
+            tokens: self.clone(),
+        };
+        (issued, receiver)
+    }
+
+    /// 投递载荷，令牌不存在或已使用时返回错误
+    pub fn deliver(&self, token: &str, payload: Value) -> Result<()> {
+        let sender = self.pending.lock().remove(token).ok_or_else(|| {
+            AgentFlowError::Context(format!("unknown or expired resume token `{token}`"))
+        })?;
+        sender.send(payload).map_err(|_| {
+            AgentFlowError::Context(format!("run waiting on resume token `{token}` has ended"))
+        })
+    }
+
+    /// 等待中的令牌
+    pub fn pending(&self) -> Vec<String> {
+        self.pending.lock().keys().cloned().collect()
+    }
+}
+
+/// 已签发的令牌，释放时撤销
+pub(super) struct IssuedToken {
+    token: String,
+    tokens: ResumeTokens,
+}
+
+impl IssuedToken {
+    pub(super) fn as_str(&self) -> &str {
+        &self.token
+    }
+}
+
+impl Drop for IssuedToken {
+    fn drop(&mut self) {
+        self.tokens.pending.lock().remove(&self.token);
+    }
+}
@@ -4,7 +4,7 @@ use std::sync::Arc;
 use crate::agent::AgentMessage;
 use crate::error::Result;
 use crate::state::FlowContext;
-use crate::tools::{ToolInvocation, ToolRegistry};
+use crate::tools::{ToolInvocation, ToolManifest, ToolRegistry};
 
 /// Executor 运行时实现
 pub struct ExecutorRuntime {
@@ -27,4 +27,8 @@ impl crate::agent::AgentRuntime for ExecutorRuntime {
         self.ctx.push_message(message);
         Ok(())
     }
+
+    fn tool_manifests(&self) -> Vec<Arc<ToolManifest>> {
+        self.tools.manifests()
+    }
 }
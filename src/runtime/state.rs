@@ -1,53 +1,697 @@
-use crate::agent::AgentMessage;
-use crate::flow::{JoinNode, JoinStrategy};
-use std::collections::{HashMap, HashSet};
+use crate::agent::{AgentAction, AgentMessage};
+use crate::error::{AgentFlowError, FrameworkError, Result};
+use crate::flow::{Flow, FlowNodeKind, JoinNode, JoinStrategy, MapNode};
+use crate::state::ContextStore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::time::Instant;
 
-/// 运行时状态管理
+use super::dead_letter::DeadLetter;
+use super::history::now_millis;
+use super::types::FlowEvent;
+
+// 运行时状态管理
 
 /// 共享状态
 #[derive(Default)]
 pub struct SharedState {
-    pub join_states: Mutex<HashMap<String, JoinState>>,
-    pub loop_states: Mutex<HashMap<String, LoopState>>,
-    pub started_agents: Mutex<HashSet<String>>,
+    pub(super) coordination: Coordination,
+    pub(super) local: LocalState,
+    /// explain 模式下的路由说明
+    pub explain: Option<super::explain::ExplainLog>,
+    /// 节点完成时的 Webhook 通知
+    pub node_notifier: Option<super::notifier::NodeNotifier>,
+    /// 配置运行历史时的节点执行记录
+    pub visits: Option<Arc<super::history::VisitLog>>,
+    /// 配置进度报告时的进度状态
+    pub(super) progress: Option<super::progress::RunProgress>,
+    /// 等待中的 Join 超时，到期后由执行器投递超时事件
+    pub(super) join_deadlines: Mutex<HashMap<String, (Instant, FlowEvent)>>,
+    /// 本次运行是否有 Join 超时
+    pub(super) join_timed_out: AtomicBool,
+    /// 本次运行产生的死信
+    pub(super) dead_letters: Mutex<Vec<DeadLetter>>,
+    /// 已转给死信或错误处理节点的错误
+    pub(super) errors: Mutex<Vec<FrameworkError>>,
+    /// 各节点在每个 trace 中的失败次数
+    pub(super) failures: Mutex<HashMap<String, u32>>,
+    /// 已完成、带补偿流水线的 Tool 节点，按完成顺序
+    pub(super) compensations: Mutex<Vec<Compensation>>,
+    /// 外部事件节点签发的恢复令牌，与执行器共享
+    pub(super) resume_tokens: super::resume::ResumeTokens,
+    /// 流水线下游节点已算出的结果，按下游事件索引
+    pub(super) pipelined: Mutex<HashMap<String, Result<AgentAction>>>,
+    /// 执行器的最大并发数，Tool 节点的并发流水线同样受此限制（None 表示不限制）
+    pub(super) max_concurrency: Option<usize>,
+    /// 已做过注入检测的消息 id
+    pub(super) inspected: Mutex<HashSet<String>>,
 }
 
-/// Join 节点状态
-pub struct JoinState {
-    strategy: JoinStrategy,
-    pub expected: HashSet<String>,
-    received: HashMap<String, AgentMessage>,
-    triggered: bool,
+/// 一个待执行的补偿步骤
+pub(super) struct Compensation {
+    pub node: String,
+    pub pipeline: String,
+    /// 节点参数，另加 `output` 字段为节点输出
+    pub params: Value,
 }
 
-impl JoinState {
-    pub fn new(node: JoinNode) -> Self {
+/// Join 超时事件的来源标识
+pub const JOIN_TIMEOUT_SOURCE: &str = "__join_timeout__";
+
+/// 单进程运行时的协调状态
+#[derive(Default)]
+pub(super) struct LocalState {
+    join_states: Mutex<HashMap<String, JoinState>>,
+    loop_states: Mutex<HashMap<String, u32>>,
+    map_states: Mutex<HashMap<String, MapState>>,
+    started_agents: Mutex<HashSet<String>>,
+    processed_events: Mutex<HashSet<String>>,
+}
+
+/// Join/Loop 协调状态的存放位置
+#[derive(Default)]
+pub(super) enum Coordination {
+    /// 单进程运行，保存在内存中
+    #[default]
+    Local,
+    /// 分布式运行，保存在多个进程共享的 `ContextStore` 中，键按运行 id 隔离
+    Store {
+        store: Arc<dyn ContextStore>,
+        prefix: String,
+    },
+}
+
+/// Join 节点收到一条消息后的进展
+pub enum JoinProgress {
+    /// 来源不在预期列表中
+    Ignored,
+    Waiting,
+    Ready(HashMap<String, Arc<AgentMessage>>),
+}
+
+impl SharedState {
+    /// 协调状态保存在 `store` 中，供多个执行进程共享
+    pub fn with_store(store: Arc<dyn ContextStore>, run_id: &str) -> Self {
         Self {
-            strategy: node.strategy,
-            expected: node.inbound.into_iter().collect(),
-            received: HashMap::new(),
-            triggered: false,
+            coordination: Coordination::Store {
+                store,
+                prefix: coordination_prefix(run_id),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// 暂存流水线下游节点的结果，由对应的事件取用
+    pub(super) async fn store_pipelined(&self, key: String, result: Result<AgentAction>) {
+        self.pipelined.lock().await.insert(key, result);
+    }
+
+    pub(super) async fn take_pipelined(&self, event: &FlowEvent) -> Option<Result<AgentAction>> {
+        let key = super::pipeline::event_key(event);
+        self.pipelined.lock().await.remove(&key)
+    }
+
+    /// 记录 Join 节点收到的消息，满足合并策略时返回收集到的消息
+    pub async fn record_join(
+        &self,
+        key: &str,
+        join: &JoinNode,
+        source: &str,
+        message: &Arc<AgentMessage>,
+    ) -> Result<JoinProgress> {
+        if !join.inbound.is_empty() && !join.inbound.iter().any(|name| name == source) {
+            return Ok(JoinProgress::Ignored);
+        }
+
+        match &self.coordination {
+            Coordination::Local => {
+                let mut states = self.local.join_states.lock().await;
+                let state = states.entry(key.to_string()).or_default();
+                match state.record(join, source.to_string(), message.clone()) {
+                    Some(collected) => {
+                        states.remove(key);
+                        Ok(JoinProgress::Ready(collected))
+                    }
+                    None => Ok(JoinProgress::Waiting),
+                }
+            }
+            Coordination::Store { store, prefix } => {
+                let ready = parking_lot::Mutex::new(None);
+                store
+                    .update(&format!("{prefix}join:{key}"), &|current| {
+                        let mut state: JoinState = decode(current)?;
+                        let collected = state.record(join, source.to_string(), message.clone());
+                        let next = match &collected {
+                            Some(_) => None,
+                            None => Some(encode(&state)?),
+                        };
+                        *ready.lock() = collected;
+                        Ok(next)
+                    })
+                    .await?;
+                Ok(match ready.into_inner() {
+                    Some(collected) => JoinProgress::Ready(collected),
+                    None => JoinProgress::Waiting,
+                })
+            }
+        }
+    }
+
+    /// Join 超时：返回尚未合并时已收到的消息，并标记为已合并，之后到达的消息被忽略
+    pub async fn expire_join(
+        &self,
+        key: &str,
+    ) -> Result<Option<HashMap<String, Arc<AgentMessage>>>> {
+        let expire = |state: &mut JoinState| {
+            if state.triggered || state.received.is_empty() {
+                return None;
+            }
+            state.triggered = true;
+            Some(state.received.clone())
+        };
+        let expired = match &self.coordination {
+            Coordination::Local => {
+                let mut states = self.local.join_states.lock().await;
+                states.get_mut(key).and_then(expire)
+            }
+            Coordination::Store { store, prefix } => {
+                let expired = parking_lot::Mutex::new(None);
+                store
+                    .update(&format!("{prefix}join:{key}"), &|current| {
+                        let Some(raw) = current else {
+                            return Ok(None);
+                        };
+                        let mut state: JoinState = decode(Some(raw))?;
+                        *expired.lock() = expire(&mut state);
+                        encode(&state).map(Some)
+                    })
+                    .await?;
+                expired.into_inner()
+            }
+        };
+        if expired.is_some() {
+            self.join_timed_out.store(true, Ordering::SeqCst);
+        }
+        Ok(expired)
+    }
+
+    /// 首条消息到达时登记超时，已登记时不重复计时
+    pub async fn arm_join_timeout(&self, key: &str, at: Instant, event: FlowEvent) {
+        self.join_deadlines
+            .lock()
+            .await
+            .entry(key.to_string())
+            .or_insert((at, event));
+    }
+
+    pub async fn disarm_join_timeout(&self, key: &str) {
+        self.join_deadlines.lock().await.remove(key);
+    }
+
+    /// 最近的 Join 超时时间
+    pub async fn next_join_deadline(&self) -> Option<Instant> {
+        self.join_deadlines
+            .lock()
+            .await
+            .values()
+            .map(|(at, _)| *at)
+            .min()
+    }
+
+    /// 取出已到期的超时事件
+    pub async fn take_expired_joins(&self, now: Instant) -> Vec<FlowEvent> {
+        let mut deadlines = self.join_deadlines.lock().await;
+        let expired: Vec<String> = deadlines
+            .iter()
+            .filter(|(_, (at, _))| *at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|key| deadlines.remove(&key).map(|(_, event)| event))
+            .collect()
+    }
+
+    pub fn join_timed_out(&self) -> bool {
+        self.join_timed_out.load(Ordering::SeqCst)
+    }
+
+    /// 开始 Map：保存数组元素，返回首批要派发的元素
+    pub async fn start_map(
+        &self,
+        key: &str,
+        map: &MapNode,
+        items: Vec<Value>,
+        iterations: u32,
+    ) -> Result<Vec<(usize, Value)>> {
+        let concurrency = map.concurrency.max(1);
+        self.update_map(key, &|state| {
+            *state = MapState {
+                items: items.clone(),
+                next: 0,
+                iterations,
+                results: BTreeMap::new(),
+            };
+            (state.take_next(concurrency), true)
+        })
+        .await
+    }
+
+    /// 记录 Map 分支的输出，返回下一批要派发的元素或收齐的结果
+    pub async fn record_map(
+        &self,
+        key: &str,
+        map: &MapNode,
+        index: usize,
+        message: &AgentMessage,
+    ) -> Result<MapProgress> {
+        self.update_map(key, &|state| {
+            let total = state.items.len();
+            // 已合并（或从未开始）的 Map 不再接收结果
+            if total == 0 || state.results.contains_key(&index) {
+                return (MapProgress::Ignored, total > 0);
+            }
+            state.results.insert(index, message.clone());
+            let required = match map.strategy {
+                JoinStrategy::All => total,
+                JoinStrategy::Any => 1,
+                JoinStrategy::Count(count) => count.min(total),
+            };
+            if state.results.len() >= required {
+                let results = state
+                    .results
+                    .iter()
+                    .map(|(index, message)| (*index, message.clone()))
+                    .collect();
+                return (MapProgress::Ready { results, total }, false);
+            }
+            let items = state.take_next(1);
+            (
+                MapProgress::Dispatch {
+                    items,
+                    total,
+                    iterations: state.iterations,
+                },
+                true,
+            )
+        })
+        .await
+    }
+
+    /// 读改写 Map 状态，`f` 返回 false 时删除状态
+    async fn update_map<R: Send>(
+        &self,
+        key: &str,
+        f: &(dyn Fn(&mut MapState) -> (R, bool) + Send + Sync),
+    ) -> Result<R> {
+        match &self.coordination {
+            Coordination::Local => {
+                let mut states = self.local.map_states.lock().await;
+                let state = states.entry(key.to_string()).or_default();
+                let (result, keep) = f(state);
+                if !keep {
+                    states.remove(key);
+                }
+                Ok(result)
+            }
+            Coordination::Store { store, prefix } => {
+                let output = parking_lot::Mutex::new(None);
+                store
+                    .update(&format!("{prefix}map:{key}"), &|current| {
+                        let mut state: MapState = decode(current)?;
+                        let (result, keep) = f(&mut state);
+                        *output.lock() = Some(result);
+                        if keep {
+                            encode(&state).map(Some)
+                        } else {
+                            Ok(None)
+                        }
+                    })
+                    .await?;
+                output.into_inner().ok_or_else(|| {
+                    AgentFlowError::Other(anyhow::anyhow!("map state update did not run"))
+                })
+            }
+        }
+    }
+
+    /// Loop 节点已完成的迭代次数
+    pub async fn loop_iterations(&self, key: &str) -> Result<u32> {
+        match &self.coordination {
+            Coordination::Local => Ok(self
+                .local
+                .loop_states
+                .lock()
+                .await
+                .get(key)
+                .copied()
+                .unwrap_or_default()),
+            Coordination::Store { store, prefix } => {
+                decode(store.get(&format!("{prefix}loop:{key}")).await?)
+            }
+        }
+    }
+
+    pub async fn advance_loop(&self, key: &str) -> Result<()> {
+        match &self.coordination {
+            Coordination::Local => {
+                *self
+                    .local
+                    .loop_states
+                    .lock()
+                    .await
+                    .entry(key.to_string())
+                    .or_default() += 1;
+            }
+            Coordination::Store { store, prefix } => {
+                store
+                    .update(&format!("{prefix}loop:{key}"), &|current| {
+                        let iterations: u32 = decode(current)?;
+                        encode(&(iterations + 1)).map(Some)
+                    })
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn clear_loop(&self, key: &str) -> Result<()> {
+        match &self.coordination {
+            Coordination::Local => {
+                self.local.loop_states.lock().await.remove(key);
+                Ok(())
+            }
+            Coordination::Store { store, prefix } => {
+                store.delete(&format!("{prefix}loop:{key}")).await
+            }
         }
     }
 
+    /// 标记 Agent 已启动，本次运行中首次启动时返回 true
+    pub async fn mark_agent_started(&self, agent: &str) -> Result<bool> {
+        match &self.coordination {
+            Coordination::Local => Ok(self
+                .local
+                .started_agents
+                .lock()
+                .await
+                .insert(agent.to_string())),
+            Coordination::Store { store, prefix } => {
+                let first = parking_lot::Mutex::new(false);
+                store
+                    .update(&format!("{prefix}started:{agent}"), &|current| {
+                        *first.lock() = current.is_none();
+                        Ok(Some(current.unwrap_or_else(|| "1".to_string())))
+                    })
+                    .await?;
+                Ok(first.into_inner())
+            }
+        }
+    }
+
+    /// 记录成功的 Tool 节点，运行或后续节点失败时调用其补偿流水线
+    pub async fn record_compensation(
+        &self,
+        node: &str,
+        pipeline: &str,
+        mut params: Value,
+        output: &AgentMessage,
+    ) {
+        let output = serde_json::from_str(&output.content)
+            .unwrap_or_else(|_| Value::String(output.content.clone()));
+        match params.as_object_mut() {
+            Some(object) => {
+                object.insert("output".to_string(), output);
+            }
+            None => params = serde_json::json!({ "output": output }),
+        }
+        self.compensations.lock().await.push(Compensation {
+            node: node.to_string(),
+            pipeline: pipeline.to_string(),
+            params,
+        });
+    }
+
+    /// 取出待执行的补偿步骤，最后完成的在前
+    pub(super) async fn take_compensations(&self) -> Vec<Compensation> {
+        let mut compensations = std::mem::take(&mut *self.compensations.lock().await);
+        compensations.reverse();
+        compensations
+    }
+
+    /// 记录节点失败，返回该节点在同一 trace 中的失败次数
+    pub async fn record_failure(&self, trace_id: &str, node: &str) -> u32 {
+        let mut failures = self.failures.lock().await;
+        let count = failures.entry(format!("{trace_id}::{node}")).or_default();
+        *count += 1;
+        *count
+    }
+
+    /// 指纹对应的事件是否已处理过；存储中的记录超过 `ttl` 后视为未处理
+    pub async fn event_processed(&self, fingerprint: &str, ttl: Duration) -> Result<bool> {
+        match &self.coordination {
+            Coordination::Local => Ok(self
+                .local
+                .processed_events
+                .lock()
+                .await
+                .contains(fingerprint)),
+            Coordination::Store { store, prefix } => {
+                let marked_at = store
+                    .get(&format!("{prefix}processed:{fingerprint}"))
+                    .await?;
+                Ok(parse_marked_at(marked_at.as_deref())
+                    .is_some_and(|marked_at| !is_expired(marked_at, now_millis(), ttl)))
+            }
+        }
+    }
+
+    /// 记录已成功处理的事件
+    ///
+    /// 存储中每个指纹一个键，值为处理时间；已有未过期的记录时保持不变，过期的记录被覆盖。
+    pub async fn mark_event_processed(&self, fingerprint: &str, ttl: Duration) -> Result<()> {
+        match &self.coordination {
+            Coordination::Local => {
+                self.local
+                    .processed_events
+                    .lock()
+                    .await
+                    .insert(fingerprint.to_string());
+            }
+            Coordination::Store { store, prefix } => {
+                let key = format!("{prefix}processed:{fingerprint}");
+                loop {
+                    let current = store.get(&key).await?;
+                    let now = now_millis();
+                    if parse_marked_at(current.as_deref())
+                        .is_some_and(|marked_at| !is_expired(marked_at, now, ttl))
+                    {
+                        break;
+                    }
+                    // 并发写入时重新读取
+                    if store
+                        .compare_and_set(&key, current.as_deref(), Some(now.to_string()))
+                        .await?
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_marked_at(value: Option<&str>) -> Option<u64> {
+    value?.parse().ok()
+}
+
+/// 删除运行结束后残留的协调状态（Agent 启动标记、已处理事件）
+pub(super) async fn clear_coordination(store: &dyn ContextStore, run_id: &str, flow: &Flow) {
+    let prefix = coordination_prefix(run_id);
+    if let Err(err) = store.delete(&format!("{prefix}version")).await {
+        tracing::warn!(run_id, error = %err, "failed to clear coordination state");
+    }
+    // 不支持列出键的存储保留去重记录，过期后由下次写入覆盖
+    match store.keys(&format!("{prefix}processed:")).await {
+        Ok(keys) => {
+            for key in keys {
+                if let Err(err) = store.delete(&key).await {
+                    tracing::warn!(run_id, error = %err, "failed to clear coordination state");
+                }
+            }
+        }
+        Err(err) => tracing::debug!(run_id, error = %err, "cannot list processed events"),
+    }
+    for node in flow.nodes.values() {
+        let agents = match &node.kind {
+            FlowNodeKind::Agent(agent) => vec![agent.clone()],
+            FlowNodeKind::GroupChat(chat) => chat.participants.clone(),
+            FlowNodeKind::Debate(debate) => vec![debate.proposer.clone(), debate.critic.clone()],
+            _ => continue,
+        };
+        for agent in agents {
+            if let Err(err) = store.delete(&format!("{prefix}started:{agent}")).await {
+                tracing::warn!(run_id, error = %err, "failed to clear coordination state");
+            }
+        }
+    }
+}
+
+/// 记录运行使用的图版本，恢复时据此判断是否需要迁移
+pub(super) async fn record_run_version(
+    store: &dyn ContextStore,
+    run_id: &str,
+    version: u32,
+) -> Result<()> {
+    let prefix = coordination_prefix(run_id);
+    store
+        .set(&format!("{prefix}version"), version.to_string())
+        .await
+}
+
+/// 运行开始时的图版本，未记录时为 None
+pub(super) async fn run_version(store: &dyn ContextStore, run_id: &str) -> Result<Option<u32>> {
+    let prefix = coordination_prefix(run_id);
+    store
+        .get(&format!("{prefix}version"))
+        .await?
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|e| AgentFlowError::Context(format!("invalid run version `{value}`: {e}")))
+        })
+        .transpose()
+}
+
+/// 幂等键与事件去重记录的默认有效期
+pub const DEFAULT_DEDUP_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 记录时间（Unix 毫秒）距今是否已超过 `ttl`
+fn is_expired(recorded_at: u64, now: u64, ttl: Duration) -> bool {
+    now.saturating_sub(recorded_at) >= ttl.as_millis() as u64
+}
+
+/// 幂等键的占用记录
+#[derive(Default, Serialize, Deserialize)]
+struct IdempotencyClaim {
+    run_id: String,
+    /// 占用时间（Unix 毫秒）
+    claimed_at: u64,
+}
+
+/// 存储中幂等键的位置，按工作流名称隔离
+fn idempotency_location(flow: &str, key: &str) -> String {
+    format!("__agentflow:idempotency:{flow}:{key}")
+}
+
+/// 占用幂等键，已被其他运行占用且未超过 `ttl` 时返回 `DuplicateRun`
+pub(super) async fn claim_idempotency_key(
+    store: &dyn ContextStore,
+    flow: &str,
+    key: &str,
+    run_id: &str,
+    ttl: Duration,
+) -> Result<()> {
+    let location = idempotency_location(flow, key);
+    let claim = encode(&IdempotencyClaim {
+        run_id: run_id.to_string(),
+        claimed_at: now_millis(),
+    })?;
+    loop {
+        let current = store.get(&location).await?;
+        if let Some(existing) = &current {
+            let existing: IdempotencyClaim = decode(Some(existing.clone()))?;
+            if !is_expired(existing.claimed_at, now_millis(), ttl) {
+                return Err(AgentFlowError::DuplicateRun {
+                    key: key.to_string(),
+                    run_id: existing.run_id,
+                });
+            }
+        }
+        // 过期的记录直接覆盖；并发占用时重新读取
+        if store
+            .compare_and_set(&location, current.as_deref(), Some(claim.clone()))
+            .await?
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// 运行失败时释放幂等键，之后的重试可以重新执行
+pub(super) async fn release_idempotency_key(
+    store: &dyn ContextStore,
+    flow: &str,
+    key: &str,
+    run_id: &str,
+) {
+    let location = idempotency_location(flow, key);
+    let result = async {
+        let Some(current) = store.get(&location).await? else {
+            return Ok(false);
+        };
+        let claim: IdempotencyClaim = decode(Some(current.clone()))?;
+        if claim.run_id != run_id {
+            return Ok(false);
+        }
+        store.compare_and_set(&location, Some(&current), None).await
+    }
+    .await;
+    if let Err(err) = result {
+        tracing::warn!(run_id, error = %err, "failed to release idempotency key");
+    }
+}
+
+/// 存储中协调状态键的前缀
+pub(super) fn coordination_prefix(run_id: &str) -> String {
+    format!("__agentflow:run:{run_id}:")
+}
+
+fn decode<T: Default + for<'de> Deserialize<'de>>(value: Option<String>) -> Result<T> {
+    match value {
+        Some(text) => {
+            serde_json::from_str(&text).map_err(|e| AgentFlowError::Serialization(e.to_string()))
+        }
+        None => Ok(T::default()),
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| AgentFlowError::Serialization(e.to_string()))
+}
+
+/// Join 节点状态
+#[derive(Default, Serialize, Deserialize)]
+pub struct JoinState {
+    received: HashMap<String, Arc<AgentMessage>>,
+    triggered: bool,
+}
+
+impl JoinState {
     pub fn record(
         &mut self,
+        join: &JoinNode,
         source: String,
-        message: AgentMessage,
-    ) -> Option<HashMap<String, AgentMessage>> {
+        message: Arc<AgentMessage>,
+    ) -> Option<HashMap<String, Arc<AgentMessage>>> {
         if self.triggered {
             return None;
         }
 
         self.received.insert(source.clone(), message);
 
-        match &self.strategy {
+        match &join.strategy {
             JoinStrategy::All => {
-                let required = if self.expected.is_empty() {
+                let required = if join.inbound.is_empty() {
                     !self.received.is_empty()
                 } else {
-                    self.expected
+                    join.inbound
                         .iter()
                         .all(|name| self.received.contains_key(name))
                 };
@@ -76,16 +720,98 @@ impl JoinState {
     }
 }
 
-/// Loop 节点状态
-#[derive(Default)]
-pub struct LoopState {
-    pub iterations: u32,
+/// Map 节点收到一条分支输出后的进展
+pub enum MapProgress {
+    /// Map 已合并或结果重复
+    Ignored,
+    /// 继续派发的元素（分支事件沿用 Map 开始时的迭代计数）
+    Dispatch {
+        items: Vec<(usize, Value)>,
+        total: usize,
+        iterations: u32,
+    },
+    /// 已满足合并策略，按元素下标排序的结果
+    Ready {
+        results: Vec<(usize, AgentMessage)>,
+        total: usize,
+    },
+}
+
+/// Map 节点状态
+#[derive(Default, Serialize, Deserialize)]
+pub struct MapState {
+    items: Vec<Value>,
+    /// 下一个待派发元素的下标
+    next: usize,
+    iterations: u32,
+    results: BTreeMap<usize, AgentMessage>,
+}
+
+impl MapState {
+    fn take_next(&mut self, count: usize) -> Vec<(usize, Value)> {
+        let end = (self.next + count).min(self.items.len());
+        let items = (self.next..end)
+            .map(|index| (index, self.items[index].clone()))
+            .collect();
+        self.next = end;
+        items
+    }
+}
+
+/// 创建超时后的部分合并消息，`missing` 为未到达的来源节点
+pub fn make_partial_join_message(
+    node_name: &str,
+    messages: &HashMap<String, Arc<AgentMessage>>,
+    missing: &[String],
+) -> AgentMessage {
+    let mut message = make_join_message(node_name, messages);
+    if let Some(payload) = message.metadata.as_mut() {
+        payload["timed_out"] = serde_json::json!(true);
+        payload["missing"] = serde_json::json!(missing);
+        message.content = payload.to_string();
+    }
+    message
+}
+
+/// 创建 Map 合并消息
+pub fn make_map_message(
+    node_name: &str,
+    total: usize,
+    results: &[(usize, AgentMessage)],
+) -> AgentMessage {
+    let results: Vec<_> = results
+        .iter()
+        .map(|(index, message)| {
+            serde_json::json!({
+                "index": index,
+                "source": message.from.clone(),
+                "content": message.content.clone(),
+                "metadata": message.metadata.clone(),
+            })
+        })
+        .collect();
+
+    let payload = serde_json::json!({
+        "map_node": node_name,
+        "total": total,
+        "results": results,
+    });
+
+    AgentMessage {
+        id: crate::agent::message::uuid(),
+        role: crate::agent::MessageRole::System,
+        from: node_name.to_string(),
+        to: None,
+        content: payload.to_string(),
+        metadata: Some(payload),
+        attachments: Vec::new(),
+    }
 }
 
 /// 创建 Join 消息
 pub fn make_join_message(
     node_name: &str,
-    messages: &HashMap<String, AgentMessage>,
+    messages: &HashMap<String, Arc<AgentMessage>>,
 ) -> AgentMessage {
     let aggregated: Vec<_> = messages
         .iter()
@@ -112,5 +838,641 @@ pub fn make_join_message(
         to: None,
         content: payload.to_string(),
         metadata: Some(payload),
+        attachments: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::agent::AgentMessage;
+    use crate::agent::{Agent, AgentAction, AgentContext, AgentRegistry};
+    use crate::error::{AgentFlowError, Result};
+    use crate::flow::{
+        AwaitEventNode, FlowBuilder, JoinStrategy, JoinTimeoutPolicy, MapNode, WaitNode,
+        WaitUntilState,
+    };
+    use crate::runtime::FlowExecutor;
+    use crate::state::{ContextStore, FlowContext, MemoryStore};
+    use crate::testing;
+    use crate::tools::ToolRegistry;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct SummarizeAgent {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Agent for SummarizeAgent {
+        fn name(&self) -> &'static str {
+            "summarize"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(AgentAction::Continue {
+                message: Some(AgentMessage::user(message.content.to_uppercase())),
+            })
+        }
+    }
+
+    fn executor(agent: Arc<SummarizeAgent>, strategy: JoinStrategy) -> FlowExecutor {
+        testing::executor("digest", vec![("summarize", agent)], |builder| {
+            builder
+                .add_map_node(
+                    "each_doc",
+                    MapNode {
+                        over: "/docs".to_string(),
+                        body: "summarize".to_string(),
+                        strategy,
+                        concurrency: 2,
+                    },
+                )
+                .add_agent_node("summarize", "summarize")
+                .add_terminal_node("merge")
+                .set_start("each_doc")
+                .connect("each_doc", "merge");
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_map_node_fans_out_with_bounded_concurrency() {
+        let agent = Arc::new(SummarizeAgent::default());
+        let ctx = || Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let input = AgentMessage::user(r#"{"docs": ["a", "b", "c", "d", {"text": "e"}]}"#);
+
+        let execution = executor(Arc::clone(&agent), JoinStrategy::All)
+            .start(ctx(), input.clone())
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "merge");
+        let merged = execution.last_message.unwrap().metadata.unwrap();
+        assert_eq!(merged["total"], 5);
+        let contents: Vec<_> = merged["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| result["content"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(contents, vec!["A", "B", "C", "D", r#"{"TEXT":"E"}"#]);
+        assert_eq!(agent.peak.load(Ordering::SeqCst), 2);
+
+        let first = executor(Arc::clone(&agent), JoinStrategy::Any)
+            .start(ctx(), input)
+            .await
+            .unwrap();
+        let merged = first.last_message.unwrap().metadata.unwrap();
+        assert_eq!(merged["results"].as_array().unwrap().len(), 1);
+
+        let Err(error) = executor(agent, JoinStrategy::All)
+            .start(ctx(), AgentMessage::user(r#"{"docs": "a"}"#))
+            .await
+        else {
+            panic!("expected a non-array input to fail");
+        };
+        assert!(error.to_string().contains("does not point to an array"));
+    }
+
+    /// 把消息分给 `fast` 和 `slow` 两个分支
+    struct SplitAgent;
+
+    #[async_trait]
+    impl Agent for SplitAgent {
+        fn name(&self) -> &'static str {
+            "split"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let branches = ["fast", "slow"]
+                .into_iter()
+                .map(|target| (target.to_string(), message.clone()))
+                .collect();
+            Ok(AgentAction::Branch { branches })
+        }
+    }
+
+    /// 等待指定时长后把消息交给 Join 节点
+    struct DelayAgent(std::time::Duration);
+
+    #[async_trait]
+    impl Agent for DelayAgent {
+        fn name(&self) -> &'static str {
+            "delay"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            tokio::time::sleep(self.0).await;
+            Ok(AgentAction::Next {
+                target: "merge".into(),
+                message: AgentMessage::user(message.content.to_uppercase()),
+            })
+        }
+    }
+
+    fn join_executor(on_timeout: JoinTimeoutPolicy) -> FlowExecutor {
+        let fast = DelayAgent(std::time::Duration::from_millis(10));
+        let slow = DelayAgent(std::time::Duration::from_secs(3600));
+        let agents: Vec<(&str, Arc<dyn Agent>)> = vec![
+            ("split", Arc::new(SplitAgent)),
+            ("fast", Arc::new(fast)),
+            ("slow", Arc::new(slow)),
+        ];
+        testing::executor("fanout", agents, |builder| {
+            builder
+                .add_agent_node("split", "split")
+                .add_agent_node("fast", "fast")
+                .add_agent_node("slow", "slow")
+                .add_join_node(
+                    "merge",
+                    JoinStrategy::All,
+                    vec!["fast".into(), "slow".into()],
+                )
+                .set_join_timeout("merge", std::time::Duration::from_secs(5), on_timeout)
+                .add_terminal_node("done")
+                .add_terminal_node("partial")
+                .set_start("split")
+                .connect("merge", "done");
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_join_timeout_policies() {
+        let ctx = || Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let started = tokio::time::Instant::now();
+
+        let execution = join_executor(JoinTimeoutPolicy::ProceedWithPartial)
+            .start(ctx(), AgentMessage::user("hi"))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "done");
+        let merged = execution.last_message.unwrap().metadata.unwrap();
+        assert_eq!(merged["timed_out"], true);
+        assert_eq!(merged["missing"], serde_json::json!(["slow"]));
+        assert_eq!(merged["messages"][0]["content"], "HI");
+        assert!(started.elapsed() < std::time::Duration::from_secs(60));
+
+        let routed = join_executor(JoinTimeoutPolicy::RouteTo("partial".into()))
+            .start(ctx(), AgentMessage::user("hi"))
+            .await
+            .unwrap();
+        assert_eq!(routed.last_node, "partial");
+
+        let Err(error) = join_executor(JoinTimeoutPolicy::Fail)
+            .start(ctx(), AgentMessage::user("hi"))
+            .await
+        else {
+            panic!("expected the join timeout to fail the run");
+        };
+        assert!(error.to_string().contains("join node `merge`"));
+    }
+
+    fn wait_executor(until: WaitUntilState) -> FlowExecutor {
+        let mut builder = FlowBuilder::new("approval");
+        builder
+            .add_wait_node("cool_down", WaitNode::delay(Duration::from_secs(30)))
+            .add_wait_node("approval", until)
+            .add_terminal_node("done")
+            .add_terminal_node("expired")
+            .set_start("cool_down")
+            .connect("cool_down", "approval")
+            .connect("approval", "done");
+        FlowExecutor::new(builder.build(), AgentRegistry::new(), ToolRegistry::new())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_node_polls_state() {
+        let until = WaitUntilState::new("approval")
+            .with_value("yes")
+            .with_poll_interval(Duration::from_secs(5))
+            .with_timeout(Duration::from_secs(120));
+        let store = Arc::new(MemoryStore::new());
+        let ctx = Arc::new(FlowContext::new(store.clone()));
+        let writer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(40)).await;
+            store.set("approval", "no".into()).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(20)).await;
+            store.set("approval", "yes".into()).await.unwrap();
+        });
+        let started = tokio::time::Instant::now();
+        let execution = wait_executor(until.clone())
+            .start(ctx, AgentMessage::user("refund #42"))
+            .await
+            .unwrap();
+        writer.await.unwrap();
+        assert_eq!(execution.last_node, "done");
+        assert_eq!(execution.last_message.unwrap().content, "refund #42");
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(60) && elapsed < Duration::from_secs(70));
+
+        let ctx = || Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let routed = wait_executor(until.clone().with_on_timeout("expired"))
+            .start(ctx(), AgentMessage::user("refund #42"))
+            .await
+            .unwrap();
+        assert_eq!(routed.last_node, "expired");
+
+        let Err(error) = wait_executor(until)
+            .start(ctx(), AgentMessage::user("refund #42"))
+            .await
+        else {
+            panic!("expected the wait timeout to fail the run");
+        };
+        assert!(matches!(error, AgentFlowError::WaitTimedOut { ref node } if node == "approval"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_await_event_resumes_on_delivery() {
+        let executor = |node: AwaitEventNode| {
+            testing::executor("payment", Vec::new(), |builder| {
+                builder
+                    .add_await_event_node("payment", node)
+                    .add_terminal_node("paid")
+                    .add_terminal_node("expired")
+                    .set_start("payment")
+                    .connect("payment", "paid");
+            })
+        };
+
+        let paid = executor(AwaitEventNode::default().with_store_as("payment.result"));
+        let store = Arc::new(MemoryStore::new());
+        let ctx = Arc::new(FlowContext::new(store.clone()));
+        let run = tokio::spawn({
+            let paid = paid.clone();
+            async move { paid.start(ctx, AgentMessage::user("order #7")).await }
+        });
+        let token = loop {
+            if let Some(token) = paid.pending_events().pop() {
+                break token;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(
+            store.get("payment.resume_token").await.unwrap().as_deref(),
+            Some(token.as_str())
+        );
+        assert!(paid
+            .deliver_event("unknown", serde_json::json!({}))
+            .is_err());
+        paid.deliver_event(&token, serde_json::json!({ "status": "settled" }))
+            .unwrap();
+        let execution = run.await.unwrap().unwrap();
+        assert_eq!(execution.last_node, "paid");
+        let message = execution.last_message.unwrap();
+        assert_eq!(message.content, r#"{"status":"settled"}"#);
+        assert_eq!(message.metadata.unwrap()["resume_token"], token.as_str());
+        assert_eq!(
+            store.get("payment.result").await.unwrap().as_deref(),
+            Some(r#"{"status":"settled"}"#)
+        );
+        assert!(paid.deliver_event(&token, serde_json::json!({})).is_err());
+
+        let expiring = executor(
+            AwaitEventNode::default()
+                .with_timeout(Duration::from_secs(60))
+                .with_on_timeout("expired"),
+        );
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let execution = expiring
+            .start(ctx, AgentMessage::user("order #8"))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "expired");
+        assert!(expiring.pending_events().is_empty());
+
+        // 运行被取消时令牌同样撤销
+        let waiting = executor(AwaitEventNode::default());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let run = tokio::spawn({
+            let waiting = waiting.clone();
+            async move { waiting.start(ctx, AgentMessage::user("order #9")).await }
+        });
+        while waiting.pending_events().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        run.abort();
+        let Err(error) = run.await else {
+            panic!("expected the run to be cancelled");
+        };
+        assert!(error.is_cancelled());
+        assert!(waiting.pending_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_runs_once() {
+        let mut builder = FlowBuilder::new("webhook");
+        builder.add_terminal_node("done").set_start("done");
+        let executor =
+            FlowExecutor::new(builder.build(), AgentRegistry::new(), ToolRegistry::new());
+        let store = Arc::new(MemoryStore::new());
+        let ctx = || Arc::new(FlowContext::new(store.clone()));
+        let delivery = || AgentMessage::user("paid").with_idempotency_key("evt-1");
+
+        executor
+            .start_with_run_id(ctx(), delivery(), "run-1")
+            .await
+            .unwrap();
+        let Err(error) = executor.start(ctx(), delivery()).await else {
+            panic!("expected the duplicate delivery to be rejected");
+        };
+        assert!(matches!(
+            error,
+            crate::error::AgentFlowError::DuplicateRun { ref run_id, .. } if run_id == "run-1"
+        ));
+
+        // 占用记录过期后同一键可以再次执行
+        let execution = executor
+            .clone()
+            .with_dedup_ttl(Duration::ZERO)
+            .start(ctx(), delivery())
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "done");
+
+        let mut failing = FlowBuilder::new("failing");
+        failing
+            .add_agent_node("missing", "missing")
+            .set_start("missing");
+        let failing = FlowExecutor::new(failing.build(), AgentRegistry::new(), ToolRegistry::new());
+        for _ in 0..2 {
+            let Err(error) = failing.start(ctx(), delivery()).await else {
+                panic!("expected the run to fail");
+            };
+            assert!(error.to_string().contains("not registered"));
+        }
+
+        // 每次运行使用独立存储时，幂等键记录在共享的幂等存储中
+        let claims = Arc::new(MemoryStore::new());
+        let shared = executor.clone().with_idempotency_store(claims.clone());
+        let isolated = || Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let delivery = || AgentMessage::user("paid").with_idempotency_key("evt-2");
+        shared.start(isolated(), delivery()).await.unwrap();
+        assert!(shared.start(isolated(), delivery()).await.is_err());
+        assert!(claims
+            .get("__agentflow:idempotency:webhook:evt-2")
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_processed_events_expire() {
+        let store = Arc::new(MemoryStore::new());
+        let shared = super::SharedState::with_store(store.clone(), "run-1");
+        let ttl = super::DEFAULT_DEDUP_TTL;
+        shared.mark_event_processed("a", ttl).await.unwrap();
+        assert!(shared.event_processed("a", ttl).await.unwrap());
+        assert!(!shared.event_processed("a", Duration::ZERO).await.unwrap());
+
+        // 每个指纹一个键，未过期的记录不被覆盖
+        let key = "__agentflow:run:run-1:processed:a";
+        let marked_at = store.get(key).await.unwrap();
+        shared.mark_event_processed("a", ttl).await.unwrap();
+        assert_eq!(store.get(key).await.unwrap(), marked_at);
+        shared
+            .mark_event_processed("b", Duration::ZERO)
+            .await
+            .unwrap();
+        let mut keys = store.keys("__agentflow:run:run-1:").await.unwrap();
+        keys.sort();
+        assert_eq!(keys, vec![key, "__agentflow:run:run-1:processed:b"]);
+
+        // 运行结束后删除全部去重记录
+        let mut builder = FlowBuilder::new("empty");
+        builder.add_terminal_node("done").set_start("done");
+        let flow = builder.build();
+        super::clear_coordination(store.as_ref(), "run-1", &flow).await;
+        assert!(store.keys("__agentflow:run:").await.unwrap().is_empty());
+    }
+
+    /// 记录调用参数，返回 `{"id": <resource>}`
+    struct ResourceTool {
+        name: &'static str,
+        calls: Arc<parking_lot::Mutex<Vec<(&'static str, serde_json::Value)>>>,
+    }
+
+    #[async_trait]
+    impl crate::tools::Tool for ResourceTool {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn call(
+            &self,
+            invocation: crate::tools::ToolInvocation,
+            _ctx: &FlowContext,
+        ) -> Result<AgentMessage> {
+            let resource = invocation.input["resource"].clone();
+            self.calls.lock().push((self.name, invocation.input));
+            Ok(AgentMessage::tool(
+                self.name.to_string(),
+                serde_json::json!({ "id": resource }).to_string(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_run_compensates_in_reverse_order() {
+        use crate::tools::orchestrator::{ToolOrchestrator, ToolPipeline, ToolStep, ToolStrategy};
+
+        let calls = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let mut registry = ToolRegistry::new();
+        for name in ["create", "delete"] {
+            registry.register(Arc::new(ResourceTool {
+                name,
+                calls: Arc::clone(&calls),
+            }));
+        }
+        let mut orchestrator = ToolOrchestrator::new(registry);
+        for (pipeline, tool) in [("create", "create"), ("delete", "delete")] {
+            let step = ToolStep::new(tool, serde_json::json!({}));
+            let strategy = ToolStrategy::Sequential(vec![step]);
+            orchestrator
+                .register_pipeline(ToolPipeline::new(pipeline, strategy))
+                .unwrap();
+        }
+        let orchestrator = Arc::new(orchestrator);
+
+        let executor = |last: &str, dead_letter: bool| {
+            testing::executor("provision", Vec::new(), |builder| {
+                builder
+                    .add_tool_node_with_params(
+                        "bucket",
+                        "create",
+                        Some(serde_json::json!({ "resource": "bucket" })),
+                    )
+                    .set_compensation("bucket", "delete")
+                    .add_tool_node_with_params(
+                        "queue",
+                        "create",
+                        Some(serde_json::json!({ "resource": "queue" })),
+                    )
+                    .set_compensation("queue", "delete")
+                    .set_start("bucket")
+                    .connect("bucket", "queue")
+                    .connect("queue", last);
+                if last == "done" {
+                    builder.add_terminal_node("done");
+                } else {
+                    builder.add_agent_node(last, "unregistered");
+                }
+                if dead_letter {
+                    builder.add_terminal_node("dlq").set_dead_letter("dlq");
+                }
+            })
+            .with_tool_orchestrator(Arc::clone(&orchestrator))
+        };
+        let ctx = || Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+
+        let deletes = || -> Vec<_> {
+            calls
+                .lock()
+                .iter()
+                .filter(|(tool, _)| *tool == "delete")
+                .map(|(_, input)| (input["resource"].clone(), input["output"]["id"].clone()))
+                .collect()
+        };
+        let expected = vec![
+            (serde_json::json!("queue"), serde_json::json!("queue")),
+            (serde_json::json!("bucket"), serde_json::json!("bucket")),
+        ];
+
+        executor("done", false)
+            .start(ctx(), AgentMessage::user("go"))
+            .await
+            .unwrap();
+        assert_eq!(calls.lock().len(), 2);
+
+        calls.lock().clear();
+        assert!(executor("notify", false)
+            .start(ctx(), AgentMessage::user("go"))
+            .await
+            .is_err());
+        assert_eq!(deletes(), expected);
+
+        // 失败被死信节点接住、运行正常结束时同样补偿
+        calls.lock().clear();
+        let execution = executor("notify", true)
+            .start(ctx(), AgentMessage::user("go"))
+            .await
+            .unwrap();
+        assert_eq!(execution.last_node, "dlq");
+        assert_eq!(execution.dead_letters.len(), 1);
+        assert_eq!(deletes(), expected);
+    }
+
+    /// 延迟后返回固定内容，`output` 为空时失败
+    struct GatherTool {
+        name: &'static str,
+        delay_ms: u64,
+        output: Option<&'static str>,
+    }
+
+    #[async_trait]
+    impl crate::tools::Tool for GatherTool {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn call(
+            &self,
+            _invocation: crate::tools::ToolInvocation,
+            _ctx: &FlowContext,
+        ) -> Result<AgentMessage> {
+            tokio::time::sleep(Duration::from_millis(self.delay_ms)).await;
+            match self.output {
+                Some(output) => Ok(AgentMessage::tool(self.name, output)),
+                None => Err(AgentFlowError::Other(anyhow::anyhow!(
+                    "{} unavailable",
+                    self.name
+                ))),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_node_runs_pipelines_in_parallel() {
+        use crate::flow::ToolAggregate;
+        use crate::tools::orchestrator::{ToolOrchestrator, ToolPipeline, ToolStep, ToolStrategy};
+
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(GatherTool {
+            name: "search",
+            delay_ms: 60,
+            output: Some(r#"{"hits": 3}"#),
+        }));
+        registry.register(Arc::new(GatherTool {
+            name: "vectors",
+            delay_ms: 10,
+            output: Some("nearest: doc-7"),
+        }));
+        registry.register(Arc::new(GatherTool {
+            name: "calculator",
+            delay_ms: 20,
+            output: None,
+        }));
+        let mut orchestrator = ToolOrchestrator::new(registry);
+        for tool in ["search", "vectors", "calculator"] {
+            let strategy =
+                ToolStrategy::Sequential(vec![ToolStep::new(tool, serde_json::json!({}))]);
+            orchestrator
+                .register_pipeline(ToolPipeline::new(tool, strategy))
+                .unwrap();
+        }
+        let orchestrator = Arc::new(orchestrator);
+
+        let run = |aggregate: ToolAggregate, max_concurrency: usize| {
+            let mut builder = FlowBuilder::new("gather");
+            builder
+                .add_tool_node("gather", "search")
+                .set_parallel_pipelines("gather", &["vectors", "calculator"], aggregate)
+                .set_start("gather");
+            let executor =
+                FlowExecutor::new(builder.build(), AgentRegistry::new(), ToolRegistry::new())
+                    .with_tool_orchestrator(Arc::clone(&orchestrator))
+                    .with_max_concurrency(max_concurrency);
+            async move {
+                let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+                executor.start(ctx, AgentMessage::user("go")).await
+            }
+        };
+
+        let started = std::time::Instant::now();
+        let execution = run(ToolAggregate::AllSettled, 8).await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(90));
+        let payload: serde_json::Value =
+            serde_json::from_str(&execution.last_message.unwrap().content).unwrap();
+        assert_eq!(payload["tool_node"], "gather");
+        assert_eq!(payload["results"]["search"]["hits"], 3);
+        assert_eq!(payload["results"]["vectors"], "nearest: doc-7");
+        let error = payload["results"]["calculator"]["error"].as_str().unwrap();
+        assert!(error.contains("calculator unavailable"), "{}", error);
+
+        let first = run(ToolAggregate::First, 8).await.unwrap();
+        assert_eq!(first.last_message.unwrap().content, "nearest: doc-7");
+        assert!(run(ToolAggregate::All, 8).await.is_err());
+
+        // 并发流水线数受执行器的 `max_concurrency` 限制
+        let started = std::time::Instant::now();
+        run(ToolAggregate::AllSettled, 1).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(90));
     }
 }
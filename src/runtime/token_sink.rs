@@ -0,0 +1,169 @@
+//! LLM 流式输出的接收方：配置驱动 Agent 把输出片段交给 `TokenSink`，而不是直接写标准输出
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::error::Result;
+use crate::llm::client::LlmStream;
+use crate::llm::{
+    DynLlmClient, LlmClient, LlmRequest, LlmResponse, SpeechAudio, SpeechRequest,
+    TranscriptionRequest,
+};
+
+/// LLM 流式输出片段
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TokenChunk {
+    pub agent: String,
+    pub content: String,
+}
+
+/// 流式输出接收方
+///
+/// 通过 `FlowExecutor::with_token_sink` 或 `FlowContext::with_token_sink` 挂载，未挂载时不输出。
+/// 闭包 `Fn(&str, &str)`（Agent 名称、片段）和 `mpsc::UnboundedSender<TokenChunk>` 可直接使用。
+pub trait TokenSink: Send + Sync {
+    fn on_token(&self, agent: &str, token: &str);
+
+    /// 一次 LLM 调用的输出结束
+    fn on_done(&self, _agent: &str) {}
+}
+
+impl<F> TokenSink for F
+where
+    F: Fn(&str, &str) + Send + Sync,
+{
+    fn on_token(&self, agent: &str, token: &str) {
+        self(agent, token)
+    }
+}
+
+impl TokenSink for mpsc::UnboundedSender<TokenChunk> {
+    fn on_token(&self, agent: &str, token: &str) {
+        // 接收方已关闭时忽略
+        let _ = self.send(TokenChunk {
+            agent: agent.to_string(),
+            content: token.to_string(),
+        });
+    }
+}
+
+/// 包装 LLM 客户端，把流式输出片段同步交给 `sink`
+pub fn stream_to_sink(
+    client: DynLlmClient,
+    sink: Arc<dyn TokenSink>,
+    agent: impl Into<String>,
+) -> DynLlmClient {
+    Arc::new(SinkLlmClient {
+        inner: client,
+        sink,
+        agent: agent.into(),
+    })
+}
+
+struct SinkLlmClient {
+    inner: DynLlmClient,
+    sink: Arc<dyn TokenSink>,
+    agent: String,
+}
+
+#[async_trait]
+impl LlmClient for SinkLlmClient {
+    async fn complete(&self, request: LlmRequest) -> Result<LlmResponse> {
+        self.inner.complete(request).await
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        self.inner.embed(texts).await
+    }
+
+    async fn transcribe(&self, request: TranscriptionRequest) -> Result<String> {
+        self.inner.transcribe(request).await
+    }
+
+    async fn speak(&self, request: SpeechRequest) -> Result<SpeechAudio> {
+        self.inner.speak(request).await
+    }
+
+    fn complete_stream(&self, request: LlmRequest) -> LlmStream {
+        let sink = Arc::clone(&self.sink);
+        let agent = self.agent.clone();
+        Box::pin(self.inner.complete_stream(request).inspect(move |chunk| {
+            if let Ok(chunk) = chunk {
+                if !chunk.content.is_empty() {
+                    sink.on_token(&agent, &chunk.content);
+                }
+                if chunk.done {
+                    sink.on_done(&agent);
+                }
+            }
+        }))
+    }
+
+    fn clone_dyn(&self) -> DynLlmClient {
+        Arc::new(SinkLlmClient {
+            inner: self.inner.clone_dyn(),
+            sink: Arc::clone(&self.sink),
+            agent: self.agent.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, AgentMessage, AgentRegistry};
+    use crate::flow::agent::ConfigDrivenAgent;
+    use crate::flow::config::AgentConfig;
+    use crate::flow::FlowBuilder;
+    use crate::llm::LocalEchoClient;
+    use crate::runtime::FlowExecutor;
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_executor_streams_agent_tokens_to_sink() {
+        let profile: AgentConfig = serde_json::from_value(
+            json!({ "name": "writer", "driver": "echo", "prompt": "write" }),
+        )
+        .unwrap();
+        let mut agents = AgentRegistry::new();
+        let agent = ConfigDrivenAgent {
+            profile: Arc::new(profile),
+            name: "writer",
+            llm_client: Some(Arc::new(LocalEchoClient)),
+            retriever: None,
+            #[cfg(feature = "openai-client")]
+            assistant: None,
+        };
+        register_agent("writer", Arc::new(agent), &mut agents);
+        let mut builder = FlowBuilder::new("stream");
+        builder
+            .add_agent_node("write", "writer")
+            .add_terminal_node("done")
+            .connect("write", "done")
+            .set_start("write");
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_token_sink(Arc::new(tx));
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let input = json!({ "raw": "hello", "steps": [] }).to_string();
+        executor
+            .start(ctx, AgentMessage::user(input))
+            .await
+            .unwrap();
+
+        let mut streamed = String::new();
+        while let Ok(chunk) = rx.try_recv() {
+            assert_eq!(chunk.agent, "writer");
+            streamed.push_str(&chunk.content);
+        }
+        assert!(streamed.starts_with("[Echo] "), "{}", streamed);
+        assert!(streamed.contains("hello"), "{}", streamed);
+    }
+}
@@ -0,0 +1,302 @@
+//! 把运行历史导出为 LLM 观测平台的 trace JSON：LangSmith 的 run 树或 W&B Weave 的 call 列表
+//!
+//! 运行本身是根 span，每次节点执行是它的子 span；子 span 的输入是节点收到的消息，
+//! 输出是节点发给后继的消息（终点为最终消息）。
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+
+use super::history::{NodeVisit, RunRecord, RunStatus};
+use crate::agent::AgentMessage;
+
+/// 导出格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// LangSmith run：根 run 的 `child_runs` 嵌套节点 run，带 `dotted_order`
+    LangSmith,
+    /// W&B Weave call：按开始时间排列的扁平列表，以 `parent_id` 关联
+    Weave,
+}
+
+struct Span<'a> {
+    id: String,
+    name: &'a str,
+    started_at: u64,
+    ended_at: u64,
+    inputs: Value,
+    output: Option<Value>,
+    error: Option<&'a str>,
+    metadata: Value,
+}
+
+/// 导出一次运行；需要配置 `FlowExecutor::with_run_store` 记录历史
+///
+/// span id 由运行 id 和节点序号派生，同一记录多次导出结果相同。
+pub fn export_trace(record: &RunRecord, format: TraceFormat) -> Value {
+    let root = root_span(record);
+    let children: Vec<Span> = record
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(index, visit)| node_span(record, index, visit))
+        .collect();
+    match format {
+        TraceFormat::LangSmith => langsmith(&root, &children),
+        TraceFormat::Weave => weave(&root, &children),
+    }
+}
+
+fn root_span(record: &RunRecord) -> Span<'_> {
+    let input = record
+        .nodes
+        .iter()
+        .find(|visit| visit.iterations == 0)
+        .and_then(|visit| visit.input.as_ref());
+    Span {
+        id: span_id(&record.run_id, 0),
+        name: &record.flow,
+        started_at: record.started_at,
+        ended_at: record.finished_at,
+        inputs: io("input", input.map(content)),
+        output: record.final_message.as_ref().map(content),
+        error: record.error.as_deref(),
+        metadata: json!({
+            "run_id": record.run_id,
+            "status": record.status,
+            "last_node": record.last_node,
+        }),
+    }
+}
+
+fn node_span<'a>(record: &'a RunRecord, index: usize, visit: &'a NodeVisit) -> Span<'a> {
+    // 由本次执行发出的事件：来源为该节点、跳数加一、同一 trace 或其 map 子 trace
+    let emitted: Vec<Value> = record
+        .nodes
+        .iter()
+        .filter(|next| {
+            next.source == visit.node
+                && next.iterations == visit.iterations + 1
+                && (next.trace_id == visit.trace_id
+                    || next.trace_id.starts_with(&format!("{}#", visit.trace_id)))
+        })
+        .filter_map(|next| next.input.as_ref().map(content))
+        .collect();
+    let output = match emitted.len() {
+        0 if record.status == RunStatus::Succeeded
+            && record.last_node.as_deref() == Some(visit.node.as_str()) =>
+        {
+            record.final_message.as_ref().map(content)
+        }
+        0 => None,
+        1 => emitted.into_iter().next(),
+        _ => Some(Value::Array(emitted)),
+    };
+    Span {
+        id: span_id(&record.run_id, index + 1),
+        name: &visit.node,
+        started_at: visit.started_at,
+        ended_at: visit.started_at + visit.duration_ms,
+        inputs: io("input", visit.input.as_ref().map(content)),
+        output,
+        error: visit.error.as_deref(),
+        metadata: json!({
+            "source": visit.source,
+            "trace_id": visit.trace_id,
+            "iterations": visit.iterations,
+        }),
+    }
+}
+
+fn langsmith(root: &Span, children: &[Span]) -> Value {
+    let root_order = dotted_order(root);
+    let child_runs: Vec<Value> = children
+        .iter()
+        .map(|child| {
+            let mut run = langsmith_run(child, &root.id);
+            run["parent_run_id"] = json!(root.id);
+            run["dotted_order"] = json!(format!("{}.{}", root_order, dotted_order(child)));
+            run
+        })
+        .collect();
+    let mut run = langsmith_run(root, &root.id);
+    run["dotted_order"] = json!(root_order);
+    run["child_runs"] = Value::Array(child_runs);
+    run
+}
+
+fn langsmith_run(span: &Span, trace_id: &str) -> Value {
+    json!({
+        "id": span.id,
+        "trace_id": trace_id,
+        "name": span.name,
+        "run_type": "chain",
+        "start_time": timestamp(span.started_at),
+        "end_time": timestamp(span.ended_at),
+        "inputs": span.inputs,
+        "outputs": io("output", span.output.clone()),
+        "error": span.error,
+        "extra": { "metadata": span.metadata },
+    })
+}
+
+fn weave(root: &Span, children: &[Span]) -> Value {
+    let call = |span: &Span, parent: Option<&str>| {
+        json!({
+            "id": span.id,
+            "trace_id": root.id,
+            "parent_id": parent,
+            "op_name": span.name,
+            "started_at": timestamp(span.started_at),
+            "ended_at": timestamp(span.ended_at),
+            "inputs": span.inputs,
+            "output": span.output,
+            "exception": span.error,
+            "attributes": span.metadata,
+        })
+    };
+    let mut calls = vec![call(root, None)];
+    calls.extend(children.iter().map(|child| call(child, Some(&root.id))));
+    Value::Array(calls)
+}
+
+/// 消息内容能解析为 JSON 时按 JSON 导出
+fn content(message: &AgentMessage) -> Value {
+    serde_json::from_str(&message.content)
+        .unwrap_or_else(|_| Value::String(message.content.clone()))
+}
+
+fn io(key: &str, value: Option<Value>) -> Value {
+    let mut map = Map::new();
+    if let Some(value) = value {
+        map.insert(key.to_string(), value);
+    }
+    Value::Object(map)
+}
+
+fn datetime(millis: u64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(millis as i64).unwrap_or_default()
+}
+
+fn timestamp(millis: u64) -> String {
+    datetime(millis).to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// LangSmith 的排序键：开始时间 + run id
+fn dotted_order(span: &Span) -> String {
+    format!(
+        "{}{}",
+        datetime(span.started_at).format("%Y%m%dT%H%M%S%6fZ"),
+        span.id
+    )
+}
+
+/// 由运行 id 和序号派生的 UUID 格式 id
+fn span_id(run_id: &str, index: usize) -> String {
+    use sha2::{Digest, Sha256};
+
+    let digest = Sha256::new()
+        .chain_update(run_id.as_bytes())
+        .chain_update(index.to_be_bytes())
+        .finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    // 标记为 RFC 4122 v5 格式
+    bytes[6] = (bytes[6] & 0x0f) | 0x50;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentRegistry};
+    use crate::error::Result;
+    use crate::flow::FlowBuilder;
+    use crate::runtime::{FlowExecutor, HistoryFilter, MemoryRunStore};
+    use crate::state::{FlowContext, MemoryStore};
+    use crate::tools::ToolRegistry;
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    /// 把输入包装为 JSON 交给 `done`
+    struct Draft;
+
+    #[async_trait]
+    impl Agent for Draft {
+        fn name(&self) -> &'static str {
+            "draft"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            Ok(AgentAction::Next {
+                target: "done".into(),
+                message: AgentMessage::user(json!({ "draft": message.content }).to_string()),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_trace() {
+        let mut agents = AgentRegistry::new();
+        register_agent("draft", Arc::new(Draft), &mut agents);
+        let mut builder = FlowBuilder::new("writer");
+        builder
+            .add_agent_node("write", "draft")
+            .add_terminal_node("done")
+            .set_start("write");
+        let store = Arc::new(MemoryRunStore::new());
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_run_store(store.clone());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        executor
+            .start(ctx, AgentMessage::user("hello"))
+            .await
+            .unwrap();
+        let record = executor
+            .history(HistoryFilter::new())
+            .await
+            .unwrap()
+            .remove(0);
+
+        let run = export_trace(&record, TraceFormat::LangSmith);
+        assert_eq!(run["name"], "writer");
+        assert_eq!(run["inputs"], json!({ "input": "hello" }));
+        assert_eq!(run["outputs"], json!({ "output": { "draft": "hello" } }));
+        let children = run["child_runs"].as_array().unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0]["name"], "write");
+        assert_eq!(children[0]["parent_run_id"], run["id"]);
+        assert_eq!(
+            children[0]["outputs"],
+            json!({ "output": { "draft": "hello" } })
+        );
+        assert_eq!(
+            children[1]["inputs"],
+            json!({ "input": { "draft": "hello" } })
+        );
+        let order = children[1]["dotted_order"].as_str().unwrap();
+        assert!(order.starts_with(run["dotted_order"].as_str().unwrap()));
+        assert_eq!(run["id"].as_str().unwrap().len(), 36);
+        assert_eq!(export_trace(&record, TraceFormat::LangSmith), run);
+
+        let calls = export_trace(&record, TraceFormat::Weave);
+        let calls = calls.as_array().unwrap();
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0]["parent_id"], Value::Null);
+        assert_eq!(calls[2]["op_name"], "done");
+        assert_eq!(calls[2]["parent_id"], run["id"]);
+        assert_eq!(calls[2]["output"], json!({ "draft": "hello" }));
+    }
+}
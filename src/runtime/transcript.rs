@@ -0,0 +1,434 @@
+//! 运行记录（transcript）：按节点记录收到的消息，保存为黄金样本后用于检测行为漂移
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::interceptor::NodeInterceptor;
+use super::types::{FlowEvent, TaskResult};
+use crate::agent::{AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::state::FlowContext;
+
+/// 设为 `update` 时 `compare_to_golden` 用本次运行覆盖黄金样本
+pub const GOLDEN_MODE_ENV: &str = "AGENTFLOW_GOLDEN_MODE";
+
+/// 默认忽略的易变字段，在消息内容的任意层级按字段名匹配
+pub const DEFAULT_IGNORED_FIELDS: &[&str] = &[
+    "timestamp",
+    "created_at",
+    "updated_at",
+    "run_id",
+    "trace_id",
+    "duration_ms",
+    "latency_ms",
+];
+
+/// 一次节点执行
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptStep {
+    pub node: String,
+    /// 节点收到的消息
+    pub message: TranscriptMessage,
+}
+
+/// 规范化后的消息：不含 id 和 metadata，能解析为 JSON 的内容按 JSON 保存
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptMessage {
+    pub role: MessageRole,
+    pub from: String,
+    pub content: Value,
+}
+
+/// 一次运行的规范记录
+///
+/// 步骤按跳数排序，同一跳的并行分支按节点名排序，使并发执行的记录保持稳定。
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transcript {
+    pub steps: Vec<TranscriptStep>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_node: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<TranscriptMessage>,
+}
+
+impl Transcript {
+    /// 经过的节点
+    pub fn nodes(&self) -> Vec<&str> {
+        self.steps.iter().map(|step| step.node.as_str()).collect()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| golden_error(path, e))?;
+        serde_json::from_str(&content).map_err(|e| golden_error(path, e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let content = serde_json::to_string_pretty(self).map_err(|e| golden_error(path, e))?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| golden_error(path, e))?;
+        }
+        std::fs::write(path, content + "\n").map_err(|e| golden_error(path, e))
+    }
+
+    /// 与 `golden` 逐步比较，返回差异说明；为空表示一致
+    pub fn diff(&self, golden: &Transcript) -> Vec<String> {
+        let mut differences = Vec::new();
+        if self.nodes() != golden.nodes() {
+            differences.push(format!(
+                "node path: expected {:?}, got {:?}",
+                golden.nodes(),
+                self.nodes()
+            ));
+        }
+        for (index, (actual, expected)) in self.steps.iter().zip(&golden.steps).enumerate() {
+            if actual.node == expected.node {
+                let label = format!("step {} `{}`", index, actual.node);
+                diff_message(&label, &expected.message, &actual.message, &mut differences);
+            }
+        }
+        if self.last_node != golden.last_node {
+            differences.push(format!(
+                "last node: expected {:?}, got {:?}",
+                golden.last_node, self.last_node
+            ));
+        }
+        match (&golden.output, &self.output) {
+            (Some(expected), Some(actual)) => {
+                diff_message("output", expected, actual, &mut differences)
+            }
+            (None, None) => {}
+            (expected, actual) => differences.push(format!(
+                "output: expected {}, got {}",
+                presence(expected.is_some()),
+                presence(actual.is_some())
+            )),
+        }
+        differences
+    }
+
+    /// 与黄金样本文件比较，返回差异说明
+    ///
+    /// 文件不存在时返回错误；设置 `AGENTFLOW_GOLDEN_MODE=update` 时写入本次记录并返回空差异。
+    pub fn compare_to_golden(&self, path: impl AsRef<Path>) -> Result<Vec<String>> {
+        let path = path.as_ref();
+        if std::env::var(GOLDEN_MODE_ENV).ok().as_deref() == Some("update") {
+            self.save(path)?;
+            return Ok(Vec::new());
+        }
+        if !path.exists() {
+            return Err(golden_error(
+                path,
+                format!("not found (record it with {}=update)", GOLDEN_MODE_ENV),
+            ));
+        }
+        Ok(self.diff(&Transcript::load(path)?))
+    }
+}
+
+fn golden_error(path: &Path, e: impl std::fmt::Display) -> AgentFlowError {
+    AgentFlowError::Other(anyhow!("golden transcript `{}`: {}", path.display(), e))
+}
+
+fn presence(present: bool) -> &'static str {
+    if present {
+        "a message"
+    } else {
+        "none"
+    }
+}
+
+fn diff_message(
+    label: &str,
+    expected: &TranscriptMessage,
+    actual: &TranscriptMessage,
+    differences: &mut Vec<String>,
+) {
+    if expected.role != actual.role {
+        differences.push(format!(
+            "{} role: expected {:?}, got {:?}",
+            label, expected.role, actual.role
+        ));
+    }
+    if expected.from != actual.from {
+        differences.push(format!(
+            "{} from: expected `{}`, got `{}`",
+            label, expected.from, actual.from
+        ));
+    }
+    diff_value(label, "", &expected.content, &actual.content, differences);
+}
+
+/// 结构化比较 JSON，按 JSON Pointer 报告不同的位置
+fn diff_value(
+    label: &str,
+    pointer: &str,
+    expected: &Value,
+    actual: &Value,
+    differences: &mut Vec<String>,
+) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            let mut keys: Vec<&String> = expected.keys().chain(actual.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+                match (expected.get(key), actual.get(key)) {
+                    (Some(expected), Some(actual)) => {
+                        diff_value(label, &child, expected, actual, differences)
+                    }
+                    (Some(_), None) => {
+                        differences.push(format!("{} content `{}`: missing", label, child))
+                    }
+                    (None, Some(_)) => {
+                        differences.push(format!("{} content `{}`: unexpected field", label, child))
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(expected_items), Value::Array(actual_items))
+            if expected_items.len() == actual_items.len() =>
+        {
+            for (index, (expected, actual)) in expected_items.iter().zip(actual_items).enumerate() {
+                let child = format!("{}/{}", pointer, index);
+                diff_value(label, &child, expected, actual, differences);
+            }
+        }
+        _ if expected != actual => {
+            let at = if pointer.is_empty() {
+                String::new()
+            } else {
+                format!(" `{}`", pointer)
+            };
+            differences.push(format!(
+                "{} content{}: expected {}, got {}",
+                label, at, expected, actual
+            ))
+        }
+        _ => {}
+    }
+}
+
+/// 记录运行的节点拦截器，通过 `FlowExecutor::with_interceptor` 注册
+///
+/// 一个记录器只应用于一次运行；同一执行器多次运行时各次的步骤会混在一起。
+#[derive(Clone)]
+pub struct TranscriptRecorder {
+    ignored_fields: Arc<Vec<String>>,
+    state: Arc<Mutex<RecorderState>>,
+}
+
+#[derive(Default)]
+struct RecorderState {
+    steps: Vec<(u32, TranscriptStep)>,
+    finished: Option<(String, Option<TranscriptMessage>)>,
+}
+
+impl Default for TranscriptRecorder {
+    fn default() -> Self {
+        Self {
+            ignored_fields: Arc::new(
+                DEFAULT_IGNORED_FIELDS
+                    .iter()
+                    .map(|field| field.to_string())
+                    .collect(),
+            ),
+            state: Default::default(),
+        }
+    }
+}
+
+impl TranscriptRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 额外忽略的字段名
+    pub fn with_ignored_field(mut self, field: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.ignored_fields).push(field.into());
+        self
+    }
+
+    /// 当前已记录的内容
+    pub fn transcript(&self) -> Transcript {
+        let state = self.state.lock().unwrap();
+        let mut steps = state.steps.clone();
+        steps.sort_by(|(a_hops, a), (b_hops, b)| a_hops.cmp(b_hops).then(a.node.cmp(&b.node)));
+        let (last_node, output) = match state.finished.clone() {
+            Some((node, output)) => (Some(node), output),
+            None => (None, None),
+        };
+        Transcript {
+            steps: steps.into_iter().map(|(_, step)| step).collect(),
+            last_node,
+            output,
+        }
+    }
+
+    fn normalize(&self, message: &AgentMessage) -> TranscriptMessage {
+        let mut content = serde_json::from_str(&message.content)
+            .unwrap_or_else(|_| Value::String(message.content.clone()));
+        strip_fields(&mut content, &self.ignored_fields);
+        TranscriptMessage {
+            role: message.role.clone(),
+            from: message.from.clone(),
+            content,
+        }
+    }
+}
+
+fn strip_fields(value: &mut Value, fields: &[String]) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|key, _| !fields.contains(key));
+            for child in map.values_mut() {
+                strip_fields(child, fields);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                strip_fields(item, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[async_trait]
+impl NodeInterceptor for TranscriptRecorder {
+    async fn before_node(&self, event: &mut FlowEvent, _ctx: &FlowContext) -> Result<()> {
+        let step = TranscriptStep {
+            node: event.node.clone(),
+            message: self.normalize(&event.message),
+        };
+        self.state
+            .lock()
+            .unwrap()
+            .steps
+            .push((event.iterations, step));
+        Ok(())
+    }
+
+    async fn after_node(
+        &self,
+        _event: &FlowEvent,
+        _ctx: &FlowContext,
+        result: &TaskResult,
+    ) -> Result<()> {
+        if let TaskResult::Finished(finished) = result {
+            let output = finished
+                .message
+                .as_ref()
+                .map(|message| self.normalize(message));
+            self.state
+                .lock()
+                .unwrap()
+                .finished
+                .get_or_insert((finished.node.clone(), output));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentRegistry};
+    use crate::flow::FlowBuilder;
+    use crate::runtime::FlowExecutor;
+    use crate::state::MemoryStore;
+    use crate::tools::ToolRegistry;
+    use serde_json::json;
+
+    /// 输出分类结果，带每次不同的时间戳
+    struct Triage(&'static str);
+
+    #[async_trait]
+    impl Agent for Triage {
+        fn name(&self) -> &'static str {
+            "triage"
+        }
+
+        async fn on_message(
+            &self,
+            _message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let content = json!({
+                "category": self.0,
+                "tags": ["inbox"],
+                "timestamp": crate::agent::message::uuid(),
+            });
+            let mut message = AgentMessage::user(content.to_string());
+            message.from = "triage".into();
+            Ok(AgentAction::Next {
+                target: "done".into(),
+                message,
+            })
+        }
+    }
+
+    async fn record(category: &'static str) -> Transcript {
+        let mut agents = AgentRegistry::new();
+        register_agent("triage", Arc::new(Triage(category)), &mut agents);
+        let mut builder = FlowBuilder::new("support");
+        builder
+            .add_agent_node("classify", "triage")
+            .add_terminal_node("done")
+            .set_start("classify");
+        let recorder = TranscriptRecorder::new();
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new())
+            .with_interceptor(recorder.clone());
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        executor
+            .start(ctx, AgentMessage::user("my invoice is wrong"))
+            .await
+            .unwrap();
+        recorder.transcript()
+    }
+
+    #[tokio::test]
+    async fn test_compare_to_golden() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("golden/support.json");
+
+        let golden = record("billing").await;
+        assert_eq!(golden.nodes(), vec!["classify", "done"]);
+        assert_eq!(golden.last_node.as_deref(), Some("done"));
+        assert_eq!(
+            golden.steps[1].message.content,
+            json!({ "category": "billing", "tags": ["inbox"] })
+        );
+        let err = golden.compare_to_golden(&path).unwrap_err().to_string();
+        assert!(err.contains(GOLDEN_MODE_ENV), "{}", err);
+        golden.save(&path).unwrap();
+
+        let rerun = record("billing").await;
+        assert!(rerun.compare_to_golden(&path).unwrap().is_empty());
+
+        let drifted = record("refund").await;
+        assert_eq!(
+            drifted.compare_to_golden(&path).unwrap(),
+            vec![
+                r#"step 1 `done` content `/category`: expected "billing", got "refund""#,
+                r#"output content `/category`: expected "billing", got "refund""#,
+            ]
+        );
+
+        let mut shortened = golden.clone();
+        shortened.steps.pop();
+        let differences = shortened.diff(&golden);
+        assert_eq!(
+            differences[0],
+            r#"node path: expected ["classify", "done"], got ["classify"]"#
+        );
+    }
+}
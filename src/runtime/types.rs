@@ -1,17 +1,57 @@
+use std::sync::Arc;
+
 use crate::agent::AgentMessage;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
 
-/// 运行时类型定义
+// 运行时类型定义
 
 /// Flow 执行事件
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct FlowEvent {
     pub node: String,
-    pub message: AgentMessage,
+    /// 分支、Join 和历史共享同一条消息，入队时不复制内容与附件
+    pub message: Arc<AgentMessage>,
     pub iterations: u32,
     pub trace_id: String,
     pub source: String,
 }
 
+/// 版本迁移钩子：把旧版本运行中待处理的事件改写为新版本的图中的事件，返回 `None` 时丢弃
+pub type FlowMigration = dyn Fn(FlowEvent) -> Result<Option<FlowEvent>> + Send + Sync;
+
+impl FlowEvent {
+    /// 事件指纹：trace id + 节点 + 来源 + 跳数 + 消息内容的哈希，重复投递的同一事件指纹相同
+    ///
+    /// 不包含消息 id，内容相同但重新生成的消息（如恢复时重建的事件）指纹也相同。
+    pub fn fingerprint(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let AgentMessage {
+            id: _,
+            role,
+            from,
+            to,
+            content,
+            metadata,
+            attachments,
+        } = self.message.as_ref();
+        let message = serde_json::to_string(&(role, from, to, content, metadata, attachments))
+            .unwrap_or_default();
+        let mut hasher = Sha256::new();
+        for part in [&self.trace_id, &self.node, &self.source, &message] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        hasher.update(self.iterations.to_be_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
 /// 任务执行结果
 pub enum TaskResult {
     Continue,
@@ -30,4 +70,32 @@ pub struct FlowExecution {
     pub last_node: String,
     pub last_message: Option<AgentMessage>,
     pub errors: Vec<crate::error::FrameworkError>,
+    /// 配置摘要钩子时的运行摘要
+    pub digest: Option<super::digest::RunDigest>,
+    /// explain 模式下记录的路由说明
+    pub explanations: Vec<super::explain::RouteExplanation>,
+    /// 失败后转为死信的节点事件
+    pub dead_letters: Vec<super::dead_letter::DeadLetter>,
+    /// 配置进度报告时各 trace 的最终进度
+    pub progress: Vec<super::progress::ProgressUpdate>,
+    /// 按 `Flow::outputs` 声明提取的输出
+    pub outputs: serde_json::Map<String, serde_json::Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_ignores_message_id() {
+        let event = |content: &str| FlowEvent {
+            node: "charge".to_string(),
+            message: Arc::new(AgentMessage::user(content)),
+            iterations: 1,
+            trace_id: "trace-1".to_string(),
+            source: "__start__".to_string(),
+        };
+        assert_eq!(event("order").fingerprint(), event("order").fingerprint());
+        assert_ne!(event("order").fingerprint(), event("refund").fingerprint());
+    }
 }
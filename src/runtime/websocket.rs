@@ -0,0 +1,152 @@
+//! WebSocket 交互（需启用 `websocket` feature）
+
+use anyhow::anyhow;
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::tungstenite::Message;
+
+use super::channel::{RunChannel, RunUpdate};
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+
+/// 在已建立的连接上完成 WebSocket 握手，并与一次运行的交互通道双向转发
+///
+/// 出站：先回放连接前已发布的事件，之后每个 `RunUpdate` 序列化为一条 JSON 文本帧，
+/// 结束或失败事件发送后关闭连接；运行已结束时回放到最终状态后立即关闭。
+/// 入站：文本帧作为人工消息提交，可以是纯文本，也可以是 `{"content": ..., "metadata": ...}`。
+pub async fn serve_websocket<S>(stream: S, channel: RunChannel) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // 握手前订阅，握手期间发布的事件进入回放或实时事件
+    let (history, mut updates) = channel.subscribe_with_history();
+    let socket = tokio_tungstenite::accept_async(stream)
+        .await
+        .map_err(|e| AgentFlowError::Other(anyhow!("WebSocket handshake failed: {}", e)))?;
+    let (mut outbound, mut inbound) = socket.split();
+    let send_error = |e| AgentFlowError::Other(anyhow!("WebSocket send failed: {}", e));
+
+    let mut finished = false;
+    for update in history {
+        outbound.send(encode(&update)?).await.map_err(send_error)?;
+        finished |= update.is_final();
+    }
+
+    while !finished {
+        tokio::select! {
+            update = updates.recv() => {
+                let update = match update {
+                    Ok(update) => update,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "websocket client lagging; dropped run updates");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                outbound.send(encode(&update)?).await.map_err(send_error)?;
+                finished = update.is_final();
+            }
+            frame = inbound.next() => match frame {
+                Some(Ok(Message::Text(text))) => channel.send_input(parse_input(text.as_str()))?,
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    return Err(AgentFlowError::Other(anyhow!("WebSocket receive failed: {}", e)));
+                }
+            },
+        }
+    }
+
+    outbound
+        .send(Message::Close(None))
+        .await
+        .map_err(send_error)?;
+    Ok(())
+}
+
+fn encode(update: &RunUpdate) -> Result<Message> {
+    serde_json::to_string(update)
+        .map(Message::text)
+        .map_err(|e| AgentFlowError::Serialization(e.to_string()))
+}
+
+fn parse_input(text: &str) -> AgentMessage {
+    match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(mut object)) if object.get("content").is_some_and(Value::is_string) => {
+            let content = object
+                .remove("content")
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            AgentMessage {
+                metadata: object.remove("metadata"),
+                ..AgentMessage::user(content)
+            }
+        }
+        _ => AgentMessage::user(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_websocket_round_trip() {
+        let (server, client) = tokio::io::duplex(4096);
+        let channel = RunChannel::new(16);
+        let serve = tokio::spawn(serve_websocket(server, channel.clone()));
+
+        let (mut socket, _) = tokio_tungstenite::client_async("ws://localhost/runs/1", client)
+            .await
+            .unwrap();
+        socket
+            .send(Message::text(
+                r#"{"content":"approved","metadata":{"by":"alice"}}"#,
+            ))
+            .await
+            .unwrap();
+        let input = channel.next_input().await.unwrap();
+        assert_eq!(input.content, "approved");
+        assert_eq!(input.metadata.unwrap()["by"], "alice");
+
+        channel.publish(RunUpdate::Failed {
+            error: "boom".into(),
+        });
+        let frame = socket.next().await.unwrap().unwrap();
+        let update: Value = serde_json::from_str(frame.to_text().unwrap()).unwrap();
+        assert_eq!(update["type"], "failed");
+        assert_eq!(update["error"], "boom");
+        serve.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_websocket_replays_updates_before_connect() {
+        let (server, client) = tokio::io::duplex(4096);
+        let channel = RunChannel::new(16);
+        channel.publish(RunUpdate::NodeStarted {
+            node: "reply".into(),
+            source: "start".into(),
+        });
+        channel.publish(RunUpdate::Finished {
+            node: "done".into(),
+            message: None,
+        });
+        let serve = tokio::spawn(serve_websocket(server, channel.clone()));
+
+        let (socket, _) = tokio_tungstenite::client_async("ws://localhost/runs/1", client)
+            .await
+            .unwrap();
+        let frames: Vec<_> = socket
+            .filter_map(|frame| async move {
+                let frame = frame.ok()?;
+                let update: Value = serde_json::from_str(frame.to_text().ok()?).ok()?;
+                Some(update["type"].as_str()?.to_string())
+            })
+            .collect()
+            .await;
+        assert_eq!(frames, vec!["node_started", "finished"]);
+        serve.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,614 @@
+//! 事件触发：订阅消息总线，把收到的消息转换为 `AgentMessage` 并启动工作流
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+
+use super::{executor_for, run_flow};
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::flow::FlowRegistry;
+use crate::runtime::FlowExecutor;
+use crate::state::ContextStore;
+
+/// 重新订阅的初始等待时间，失败时翻倍
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// 从消息总线收到的一条消息
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TriggerEvent {
+    /// 频道或主题
+    pub subject: String,
+    pub payload: String,
+}
+
+impl TriggerEvent {
+    pub fn new(subject: impl Into<String>, payload: impl Into<String>) -> Self {
+        Self {
+            subject: subject.into(),
+            payload: payload.into(),
+        }
+    }
+}
+
+/// 事件来源
+///
+/// `subscribe` 返回的流中出现 `Err` 表示连接中断，调度器会等待后重新订阅；
+/// 流正常结束表示来源已关闭，对应的触发器随之停止。
+#[async_trait]
+pub trait TriggerSource: Send + Sync {
+    /// 用于日志的描述
+    fn describe(&self) -> String;
+
+    async fn subscribe(&self) -> Result<BoxStream<'static, Result<TriggerEvent>>>;
+}
+
+/// 进程内事件来源，适合嵌入使用和测试
+pub struct ChannelTriggerSource {
+    receiver: Mutex<Option<mpsc::UnboundedReceiver<TriggerEvent>>>,
+}
+
+impl ChannelTriggerSource {
+    /// 返回来源和发送端，所有发送端 drop 后来源关闭
+    pub fn new() -> (Self, mpsc::UnboundedSender<TriggerEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (
+            Self {
+                receiver: Mutex::new(Some(rx)),
+            },
+            tx,
+        )
+    }
+}
+
+#[async_trait]
+impl TriggerSource for ChannelTriggerSource {
+    fn describe(&self) -> String {
+        "channel".to_string()
+    }
+
+    async fn subscribe(&self) -> Result<BoxStream<'static, Result<TriggerEvent>>> {
+        let receiver = self.receiver.lock().take().ok_or_else(|| {
+            AgentFlowError::Other(anyhow!("Channel trigger source already subscribed"))
+        })?;
+        Ok(futures::stream::unfold(receiver, |mut rx| async move {
+            rx.recv().await.map(|event| (Ok(event), rx))
+        })
+        .boxed())
+    }
+}
+
+/// Redis pub/sub 事件来源（需启用 `redis-trigger` feature）
+#[cfg(feature = "redis-trigger")]
+pub struct RedisTriggerSource {
+    client: ::redis::Client,
+    channels: Vec<String>,
+    patterns: Vec<String>,
+}
+
+#[cfg(feature = "redis-trigger")]
+impl RedisTriggerSource {
+    pub fn new(client: ::redis::Client, channels: Vec<String>) -> Self {
+        Self {
+            client,
+            channels,
+            patterns: Vec::new(),
+        }
+    }
+
+    pub fn open(url: &str, channels: Vec<String>) -> Result<Self> {
+        let client = ::redis::Client::open(url)
+            .map_err(|e| AgentFlowError::Other(anyhow!("Invalid Redis url `{}`: {}", url, e)))?;
+        Ok(Self::new(client, channels))
+    }
+
+    /// 追加按模式订阅（PSUBSCRIBE）
+    pub fn with_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.patterns = patterns;
+        self
+    }
+}
+
+#[cfg(feature = "redis-trigger")]
+#[async_trait]
+impl TriggerSource for RedisTriggerSource {
+    fn describe(&self) -> String {
+        format!(
+            "redis:{}",
+            self.channels
+                .iter()
+                .chain(&self.patterns)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+
+    async fn subscribe(&self) -> Result<BoxStream<'static, Result<TriggerEvent>>> {
+        let redis_error = |e: ::redis::RedisError| {
+            AgentFlowError::Other(anyhow!("Redis subscribe failed: {}", e))
+        };
+        let mut pubsub = self.client.get_async_pubsub().await.map_err(redis_error)?;
+        for channel in &self.channels {
+            pubsub.subscribe(channel).await.map_err(redis_error)?;
+        }
+        for pattern in &self.patterns {
+            pubsub.psubscribe(pattern).await.map_err(redis_error)?;
+        }
+        let messages = pubsub.into_on_message().map(|message| {
+            let payload = message
+                .get_payload::<String>()
+                .map_err(|e| AgentFlowError::Other(anyhow!("Invalid Redis payload: {}", e)));
+            Ok(TriggerEvent::new(
+                message.get_channel_name(),
+                payload.unwrap_or_default(),
+            ))
+        });
+        // 连接断开时消息流结束，转换为错误以便重新订阅
+        let disconnected = futures::stream::once(async {
+            Err(AgentFlowError::Other(anyhow!("Redis connection closed")))
+        });
+        Ok(messages.chain(disconnected).boxed())
+    }
+}
+
+/// NATS 事件来源（需启用 `nats-trigger` feature）
+///
+/// 连接断开由 NATS 客户端自动重连。
+#[cfg(feature = "nats-trigger")]
+pub struct NatsTriggerSource {
+    url: String,
+    subjects: Vec<String>,
+}
+
+#[cfg(feature = "nats-trigger")]
+impl NatsTriggerSource {
+    pub fn new(url: impl Into<String>, subjects: Vec<String>) -> Self {
+        Self {
+            url: url.into(),
+            subjects,
+        }
+    }
+}
+
+#[cfg(feature = "nats-trigger")]
+#[async_trait]
+impl TriggerSource for NatsTriggerSource {
+    fn describe(&self) -> String {
+        format!("nats:{}", self.subjects.join(","))
+    }
+
+    async fn subscribe(&self) -> Result<BoxStream<'static, Result<TriggerEvent>>> {
+        let client = async_nats::connect(self.url.as_str())
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("NATS connect failed: {}", e)))?;
+        let mut streams = Vec::new();
+        for subject in &self.subjects {
+            let subscriber = client.subscribe(subject.clone()).await.map_err(|e| {
+                AgentFlowError::Other(anyhow!("NATS subscribe to `{}` failed: {}", subject, e))
+            })?;
+            streams.push(subscriber.map(|message| {
+                Ok(TriggerEvent::new(
+                    message.subject.as_str(),
+                    String::from_utf8_lossy(&message.payload),
+                ))
+            }));
+        }
+        Ok(futures::stream::select_all(streams).boxed())
+    }
+}
+
+/// 消息到 `AgentMessage` 的转换规则
+///
+/// - `when`：JSON pointer → 期望值，全部相等时才触发
+/// - `content`：JSON pointer，取该字段作为消息内容（非字符串值序列化为 JSON）
+/// - `template`：内容模板，支持 `{subject}`、`{payload}` 和 `{/json/pointer}`，优先于 `content`
+/// - `metadata`：JSON pointer，取该字段作为消息 metadata
+/// - `idempotency_key`：JSON pointer，取该字段作为幂等键，重复投递的同一消息只执行一次
+///
+/// 都未配置时原样使用消息内容。
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PayloadMapping {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub when: BTreeMap<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+}
+
+impl PayloadMapping {
+    fn needs_json(&self) -> bool {
+        !self.when.is_empty()
+            || self.content.is_some()
+            || self.metadata.is_some()
+            || self.idempotency_key.is_some()
+            || self.template.as_deref().is_some_and(|t| t.contains("{/"))
+    }
+
+    /// 转换一条消息；不满足 `when` 条件时返回 None
+    pub fn apply(&self, event: &TriggerEvent) -> Result<Option<AgentMessage>> {
+        let json = if self.needs_json() {
+            serde_json::from_str::<Value>(&event.payload).map_err(|e| {
+                AgentFlowError::Serialization(format!(
+                    "Trigger payload on `{}` is not JSON: {}",
+                    event.subject, e
+                ))
+            })?
+        } else {
+            Value::Null
+        };
+        if self
+            .when
+            .iter()
+            .any(|(pointer, expected)| json.pointer(pointer) != Some(expected))
+        {
+            return Ok(None);
+        }
+
+        let content = if let Some(template) = &self.template {
+            render_template(template, event, &json)
+        } else if let Some(pointer) = &self.content {
+            json.pointer(pointer).map(value_text).ok_or_else(|| {
+                AgentFlowError::Other(anyhow!(
+                    "Trigger payload on `{}` has no field `{}`",
+                    event.subject,
+                    pointer
+                ))
+            })?
+        } else {
+            event.payload.clone()
+        };
+        let message = AgentMessage {
+            metadata: self
+                .metadata
+                .as_ref()
+                .and_then(|pointer| json.pointer(pointer).cloned()),
+            ..AgentMessage::user(content)
+        };
+        let key = self
+            .idempotency_key
+            .as_ref()
+            .and_then(|pointer| json.pointer(pointer))
+            .map(value_text);
+        Ok(Some(match key {
+            Some(key) => message.with_idempotency_key(key),
+            None => message,
+        }))
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn render_template(template: &str, event: &TriggerEvent, json: &Value) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let key = &rest[start + 1..start + end];
+        match key {
+            "subject" => output.push_str(&event.subject),
+            "payload" => output.push_str(&event.payload),
+            pointer if pointer.starts_with('/') => {
+                if let Some(value) = json.pointer(pointer) {
+                    output.push_str(&value_text(value));
+                }
+            }
+            _ => output.push_str(&rest[start..=start + end]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// 一个事件触发器：来源 + 工作流 + 转换规则
+#[derive(Clone)]
+pub struct EventTrigger {
+    name: String,
+    source: Arc<dyn TriggerSource>,
+    executor: FlowExecutor,
+    mapping: PayloadMapping,
+    max_concurrency: usize,
+    store: Option<Arc<dyn ContextStore>>,
+}
+
+impl EventTrigger {
+    pub fn new(
+        name: impl Into<String>,
+        source: Arc<dyn TriggerSource>,
+        executor: FlowExecutor,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            source,
+            executor,
+            mapping: PayloadMapping::default(),
+            max_concurrency: 8,
+            store: None,
+        }
+    }
+
+    pub fn with_mapping(mut self, mapping: PayloadMapping) -> Self {
+        self.mapping = mapping;
+        self
+    }
+
+    /// 同时运行的最大数量，超出时暂停消费消息（默认 8）
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// 所有运行共享的上下文存储；默认每次运行使用新的内存存储
+    pub fn with_store(mut self, store: Arc<dyn ContextStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// JSON 中的事件来源配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TriggerSourceConfig {
+    #[cfg(feature = "redis-trigger")]
+    Redis {
+        url: String,
+        #[serde(default)]
+        channels: Vec<String>,
+        #[serde(default)]
+        patterns: Vec<String>,
+    },
+    #[cfg(feature = "nats-trigger")]
+    Nats { url: String, subjects: Vec<String> },
+}
+
+impl TriggerSourceConfig {
+    pub fn build(&self) -> Result<Arc<dyn TriggerSource>> {
+        match self {
+            #[cfg(feature = "redis-trigger")]
+            TriggerSourceConfig::Redis {
+                url,
+                channels,
+                patterns,
+            } => Ok(Arc::new(
+                RedisTriggerSource::open(url, channels.clone())?.with_patterns(patterns.clone()),
+            )),
+            #[cfg(feature = "nats-trigger")]
+            TriggerSourceConfig::Nats { url, subjects } => {
+                Ok(Arc::new(NatsTriggerSource::new(url, subjects.clone())))
+            }
+            #[cfg(not(any(feature = "redis-trigger", feature = "nats-trigger")))]
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// JSON 中的事件触发器配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TriggerConfig {
+    pub name: String,
+    /// `FlowRegistry` 中的工作流名称
+    pub workflow: String,
+    pub source: TriggerSourceConfig,
+    #[serde(default)]
+    pub mapping: PayloadMapping,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<usize>,
+}
+
+impl TriggerConfig {
+    pub fn build(&self, registry: &FlowRegistry) -> Result<EventTrigger> {
+        let executor = executor_for(
+            registry,
+            &format!("Trigger `{}`", self.name),
+            &self.workflow,
+        )?;
+        let mut trigger = EventTrigger::new(&self.name, self.source.build()?, executor)
+            .with_mapping(self.mapping.clone());
+        if let Some(max_concurrency) = self.max_concurrency {
+            trigger = trigger.with_max_concurrency(max_concurrency);
+        }
+        Ok(trigger)
+    }
+}
+
+pub(super) async fn drive_events(trigger: Arc<EventTrigger>) {
+    let permits = Arc::new(Semaphore::new(trigger.max_concurrency));
+    // drop 时中止所有运行
+    let mut runs = JoinSet::new();
+    let mut delay = RECONNECT_DELAY;
+
+    loop {
+        let mut events = match trigger.source.subscribe().await {
+            Ok(events) => {
+                tracing::info!(trigger = %trigger.name, source = %trigger.source.describe(), "trigger subscribed");
+                delay = RECONNECT_DELAY;
+                events
+            }
+            Err(err) => {
+                tracing::warn!(trigger = %trigger.name, error = %err, ?delay, "trigger subscribe failed; retrying");
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                continue;
+            }
+        };
+
+        let disconnected = loop {
+            let event = match events.next().await {
+                Some(Ok(event)) => event,
+                Some(Err(err)) => break Some(err),
+                None => break None,
+            };
+            let message = match trigger.mapping.apply(&event) {
+                Ok(Some(message)) => message,
+                Ok(None) => continue,
+                Err(err) => {
+                    tracing::warn!(trigger = %trigger.name, error = %err, "dropping trigger event");
+                    continue;
+                }
+            };
+            while runs.try_join_next().is_some() {}
+            let Ok(permit) = Arc::clone(&permits).acquire_owned().await else {
+                return;
+            };
+            let worker = Arc::clone(&trigger);
+            runs.spawn(async move {
+                let _permit = permit;
+                run_flow(
+                    "trigger",
+                    &worker.name,
+                    &worker.executor,
+                    worker.store.clone(),
+                    message,
+                )
+                .await;
+            });
+        };
+
+        match disconnected {
+            Some(err) => {
+                tracing::warn!(trigger = %trigger.name, error = %err, ?delay, "trigger disconnected; resubscribing");
+                tokio::time::sleep(delay).await;
+            }
+            None => break,
+        }
+    }
+
+    // 来源关闭后等待已开始的运行结束
+    while runs.join_next().await.is_some() {}
+    tracing::info!(trigger = %trigger.name, "trigger source closed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentRegistry};
+    use crate::flow::FlowBuilder;
+    use crate::scheduler::Scheduler;
+    use crate::tools::ToolRegistry;
+
+    #[test]
+    fn test_payload_mapping() {
+        let event = TriggerEvent::new(
+            "orders.created",
+            r#"{"kind":"order","order":{"id":42,"note":"加急"}}"#,
+        );
+
+        let raw = PayloadMapping::default().apply(&event).unwrap().unwrap();
+        assert_eq!(raw.content, event.payload);
+
+        let mapping: PayloadMapping = serde_json::from_value(serde_json::json!({
+            "when": { "/kind": "order" },
+            "template": "[{subject}] 订单 {/order/id}：{/order/note} {unknown}",
+            "metadata": "/order",
+            "idempotency_key": "/order/id"
+        }))
+        .unwrap();
+        let message = mapping.apply(&event).unwrap().unwrap();
+        assert_eq!(message.content, "[orders.created] 订单 42：加急 {unknown}");
+        assert_eq!(message.idempotency_key(), Some("42"));
+        assert_eq!(message.metadata.unwrap()["id"], 42);
+
+        let content = PayloadMapping {
+            content: Some("/order".into()),
+            ..Default::default()
+        };
+        let message = content.apply(&event).unwrap().unwrap();
+        assert_eq!(message.content, r#"{"id":42,"note":"加急"}"#);
+
+        let filtered = PayloadMapping {
+            when: BTreeMap::from([("/kind".to_string(), Value::from("refund"))]),
+            ..Default::default()
+        };
+        assert!(filtered.apply(&event).unwrap().is_none());
+        assert!(content
+            .apply(&TriggerEvent::new("orders", "not json"))
+            .is_err());
+    }
+
+    struct RecordAgent(mpsc::UnboundedSender<String>);
+
+    #[async_trait]
+    impl Agent for RecordAgent {
+        fn name(&self) -> &'static str {
+            "record"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let _ = self.0.send(message.content.clone());
+            Ok(AgentAction::Finish {
+                message: Some(message),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_event_trigger_starts_flow() {
+        let (record_tx, mut recorded) = mpsc::unbounded_channel();
+        let mut agents = AgentRegistry::new();
+        register_agent("record", Arc::new(RecordAgent(record_tx)), &mut agents);
+        let mut builder = FlowBuilder::new("orders");
+        builder
+            .add_agent_node("handle", "record")
+            .set_start("handle");
+        let executor = FlowExecutor::new(builder.build(), agents, ToolRegistry::new());
+
+        let (source, events) = ChannelTriggerSource::new();
+        let mapping = PayloadMapping {
+            content: Some("/id".into()),
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new();
+        scheduler.add_trigger(
+            EventTrigger::new("orders", Arc::new(source), executor).with_mapping(mapping),
+        );
+        let _handle = scheduler.start();
+
+        events
+            .send(TriggerEvent::new("orders", r#"{"id":"a-1"}"#))
+            .unwrap();
+        events.send(TriggerEvent::new("orders", "broken")).unwrap();
+        events
+            .send(TriggerEvent::new("orders", r#"{"id":"a-2"}"#))
+            .unwrap();
+        let mut seen = vec![
+            recorded.recv().await.unwrap(),
+            recorded.recv().await.unwrap(),
+        ];
+        seen.sort();
+        assert_eq!(seen, vec!["a-1", "a-2"]);
+    }
+}
@@ -0,0 +1,504 @@
+//! 定时触发工作流：cron 表达式或固定间隔，支持重叠策略和输入模板
+//!
+//! 事件触发（消息总线）见 [`events`]。
+
+pub mod events;
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::flow::FlowRegistry;
+use crate::runtime::FlowExecutor;
+use crate::state::{ContextStore, FlowContext, MemoryStore};
+
+#[cfg(feature = "nats-trigger")]
+pub use events::NatsTriggerSource;
+#[cfg(feature = "redis-trigger")]
+pub use events::RedisTriggerSource;
+pub use events::{
+    ChannelTriggerSource, EventTrigger, PayloadMapping, TriggerConfig, TriggerEvent, TriggerSource,
+    TriggerSourceConfig,
+};
+
+/// 触发方式
+#[derive(Clone, Debug)]
+pub enum Trigger {
+    /// cron 表达式（UTC），支持 5 段（分 时 日 月 周）或带秒的 6/7 段
+    Cron(Box<cron::Schedule>),
+    /// 固定间隔，启动后先等待一个间隔
+    Interval(Duration),
+}
+
+impl Trigger {
+    pub fn cron(expression: &str) -> Result<Self> {
+        let expression = expression.trim();
+        let normalized = if expression.split_whitespace().count() == 5 {
+            format!("0 {expression}")
+        } else {
+            expression.to_string()
+        };
+        cron::Schedule::from_str(&normalized)
+            .map(|schedule| Trigger::Cron(Box::new(schedule)))
+            .map_err(|e| {
+                AgentFlowError::Other(anyhow!("Invalid cron expression `{}`: {}", expression, e))
+            })
+    }
+
+    /// 距离下一次触发的时间，没有下一次时返回 None
+    fn next_delay(&self, now: DateTime<Utc>) -> Option<Duration> {
+        match self {
+            Trigger::Cron(schedule) => schedule
+                .after(&now)
+                .next()
+                .map(|next| (next - now).to_std().unwrap_or_default()),
+            Trigger::Interval(every) => Some(*every),
+        }
+    }
+}
+
+/// 上一次运行尚未结束时的处理方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+    /// 跳过本次触发
+    #[default]
+    Skip,
+    /// 排队，按顺序逐个运行
+    Queue,
+    /// 取消上一次运行，立即开始新的运行
+    CancelPrevious,
+}
+
+/// 一个定时任务
+///
+/// `input` 为输入模板，字符串中的 `{schedule}`、`{fired_at}`（RFC 3339）、`{run}`（第几次触发）
+/// 在触发时替换；模板为字符串时直接作为消息内容，否则序列化为 JSON。
+#[derive(Clone)]
+pub struct ScheduledFlow {
+    name: String,
+    executor: FlowExecutor,
+    trigger: Trigger,
+    overlap: OverlapPolicy,
+    input: Value,
+    store: Option<Arc<dyn ContextStore>>,
+}
+
+impl ScheduledFlow {
+    pub fn new(name: impl Into<String>, executor: FlowExecutor, trigger: Trigger) -> Self {
+        Self {
+            name: name.into(),
+            executor,
+            trigger,
+            overlap: OverlapPolicy::default(),
+            input: Value::Object(Default::default()),
+            store: None,
+        }
+    }
+
+    pub fn cron(name: impl Into<String>, expression: &str, executor: FlowExecutor) -> Result<Self> {
+        Ok(Self::new(name, executor, Trigger::cron(expression)?))
+    }
+
+    pub fn every(name: impl Into<String>, interval: Duration, executor: FlowExecutor) -> Self {
+        Self::new(name, executor, Trigger::Interval(interval))
+    }
+
+    pub fn with_overlap(mut self, overlap: OverlapPolicy) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    pub fn with_input(mut self, input: Value) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// 所有运行共享的上下文存储；默认每次运行使用新的内存存储
+    pub fn with_store(mut self, store: Arc<dyn ContextStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn render_input(&self, fired_at: DateTime<Utc>, run: u64) -> AgentMessage {
+        fn render(value: &Value, vars: &[(&str, String)]) -> Value {
+            match value {
+                Value::String(text) => Value::String(
+                    vars.iter()
+                        .fold(text.clone(), |acc, (key, val)| acc.replace(key, val)),
+                ),
+                Value::Array(items) => {
+                    Value::Array(items.iter().map(|item| render(item, vars)).collect())
+                }
+                Value::Object(map) => Value::Object(
+                    map.iter()
+                        .map(|(key, item)| (key.clone(), render(item, vars)))
+                        .collect(),
+                ),
+                other => other.clone(),
+            }
+        }
+
+        let vars = [
+            ("{schedule}", self.name.clone()),
+            ("{fired_at}", fired_at.to_rfc3339()),
+            ("{run}", run.to_string()),
+        ];
+        match render(&self.input, &vars) {
+            Value::String(text) => AgentMessage::user(text),
+            other => AgentMessage::user(other.to_string()),
+        }
+    }
+
+    async fn run_once(&self, message: AgentMessage) {
+        run_flow(
+            "schedule",
+            &self.name,
+            &self.executor,
+            self.store.clone(),
+            message,
+        )
+        .await;
+    }
+}
+
+/// 执行一次触发的运行，结果只记录日志
+async fn run_flow(
+    kind: &'static str,
+    name: &str,
+    executor: &FlowExecutor,
+    store: Option<Arc<dyn ContextStore>>,
+    message: AgentMessage,
+) {
+    let store = store.unwrap_or_else(|| Arc::new(MemoryStore::new()));
+    let ctx = Arc::new(FlowContext::new(store));
+    match executor.start(ctx, message).await {
+        Ok(execution) => {
+            tracing::info!(kind, name, last_node = %execution.last_node, "triggered run finished")
+        }
+        Err(err @ AgentFlowError::DuplicateRun { .. }) => {
+            tracing::info!(kind, name, error = %err, "skipped duplicate trigger")
+        }
+        Err(err) => tracing::warn!(kind, name, error = %err, "triggered run failed"),
+    }
+}
+
+/// 按名称从注册表中创建工作流执行器
+fn executor_for(registry: &FlowRegistry, owner: &str, workflow: &str) -> Result<FlowExecutor> {
+    let bundle = registry.workflow(workflow).ok_or_else(|| {
+        AgentFlowError::Other(anyhow!(
+            "{} references unknown workflow `{}`",
+            owner,
+            workflow
+        ))
+    })?;
+    Ok(FlowExecutor::new(
+        bundle.flow.clone(),
+        bundle.agents.clone(),
+        bundle.tools.clone(),
+    ))
+}
+
+/// JSON 中的定时任务配置，`cron` 与 `every_secs` 二选一
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    pub name: String,
+    /// `FlowRegistry` 中的工作流名称
+    pub workflow: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every_secs: Option<u64>,
+    #[serde(default)]
+    pub overlap: OverlapPolicy,
+    #[serde(default)]
+    pub input: Value,
+}
+
+impl ScheduleConfig {
+    pub fn build(&self, registry: &FlowRegistry) -> Result<ScheduledFlow> {
+        let executor = executor_for(
+            registry,
+            &format!("Schedule `{}`", self.name),
+            &self.workflow,
+        )?;
+        let trigger = match (&self.cron, self.every_secs) {
+            (Some(expression), None) => Trigger::cron(expression)?,
+            (None, Some(secs)) if secs > 0 => Trigger::Interval(Duration::from_secs(secs)),
+            _ => {
+                return Err(AgentFlowError::Other(anyhow!(
+                    "Schedule `{}` needs exactly one of `cron` or a positive `every_secs`",
+                    self.name
+                )))
+            }
+        };
+        let input = match &self.input {
+            Value::Null => Value::Object(Default::default()),
+            input => input.clone(),
+        };
+        Ok(ScheduledFlow::new(&self.name, executor, trigger)
+            .with_overlap(self.overlap)
+            .with_input(input))
+    }
+}
+
+/// 调度器，管理定时任务和事件触发器
+#[derive(Default)]
+pub struct Scheduler {
+    schedules: Vec<ScheduledFlow>,
+    triggers: Vec<EventTrigger>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, schedule: ScheduledFlow) -> &mut Self {
+        self.schedules.push(schedule);
+        self
+    }
+
+    /// 从 JSON 数组加载定时任务，元素为 `ScheduleConfig`
+    pub fn add_configs(&mut self, configs: &Value, registry: &FlowRegistry) -> Result<&mut Self> {
+        let configs: Vec<ScheduleConfig> = serde_json::from_value(configs.clone())
+            .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        for config in &configs {
+            self.schedules.push(config.build(registry)?);
+        }
+        Ok(self)
+    }
+
+    pub fn add_trigger(&mut self, trigger: EventTrigger) -> &mut Self {
+        self.triggers.push(trigger);
+        self
+    }
+
+    /// 从 JSON 数组加载事件触发器，元素为 `TriggerConfig`
+    pub fn add_trigger_configs(
+        &mut self,
+        configs: &Value,
+        registry: &FlowRegistry,
+    ) -> Result<&mut Self> {
+        let configs: Vec<TriggerConfig> = serde_json::from_value(configs.clone())
+            .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        for config in &configs {
+            self.triggers.push(config.build(registry)?);
+        }
+        Ok(self)
+    }
+
+    /// 在当前 tokio 运行时中启动所有定时任务和事件触发器
+    pub fn start(self) -> SchedulerHandle {
+        let schedules = self
+            .schedules
+            .into_iter()
+            .map(|schedule| tokio::spawn(drive(Arc::new(schedule))));
+        let triggers = self
+            .triggers
+            .into_iter()
+            .map(|trigger| tokio::spawn(events::drive_events(Arc::new(trigger))));
+        let tasks = schedules.chain(triggers).collect();
+        SchedulerHandle { tasks }
+    }
+}
+
+/// 调度器句柄，Drop 或 `shutdown` 后停止触发并中止正在进行的运行
+pub struct SchedulerHandle {
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl SchedulerHandle {
+    /// 停止调度（等同于 drop）
+    pub fn shutdown(self) {}
+}
+
+impl Drop for SchedulerHandle {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// 中止时一并中止子任务
+struct AbortOnDrop(Option<JoinHandle<()>>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        if let Some(task) = self.0.take() {
+            task.abort();
+        }
+    }
+}
+
+async fn drive(schedule: Arc<ScheduledFlow>) {
+    let mut run = 0u64;
+    let mut current = AbortOnDrop(None);
+    let mut queue: Option<mpsc::UnboundedSender<AgentMessage>> = None;
+
+    while let Some(delay) = schedule.trigger.next_delay(Utc::now()) {
+        tokio::time::sleep(delay).await;
+        run += 1;
+        let message = schedule.render_input(Utc::now(), run);
+        let running = current.0.as_ref().is_some_and(|task| !task.is_finished());
+
+        match (schedule.overlap, running) {
+            (OverlapPolicy::Skip, true) => {
+                tracing::info!(schedule = %schedule.name, run, "previous run still active; skipping");
+            }
+            (OverlapPolicy::Queue, _) => {
+                let sender = queue.get_or_insert_with(|| {
+                    let (tx, mut rx) = mpsc::unbounded_channel::<AgentMessage>();
+                    let worker = Arc::clone(&schedule);
+                    current.0 = Some(tokio::spawn(async move {
+                        while let Some(message) = rx.recv().await {
+                            worker.run_once(message).await;
+                        }
+                    }));
+                    tx
+                });
+                let _ = sender.send(message);
+            }
+            (_, running) => {
+                if running {
+                    tracing::info!(schedule = %schedule.name, run, "cancelling previous run");
+                }
+                let worker = Arc::clone(&schedule);
+                let task = tokio::spawn(async move { worker.run_once(message).await });
+                if let Some(previous) = current.0.replace(task) {
+                    previous.abort();
+                }
+            }
+        }
+    }
+
+    // 没有下一次触发时等待最后的运行结束
+    drop(queue);
+    if let Some(task) = current.0.take() {
+        let _ = task.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentAction, AgentContext};
+    use crate::testing;
+    use async_trait::async_trait;
+    use parking_lot::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct SlowAgent {
+        started: AtomicUsize,
+        finished: AtomicUsize,
+        inputs: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Agent for SlowAgent {
+        fn name(&self) -> &'static str {
+            "slow"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            _ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            self.started.fetch_add(1, Ordering::SeqCst);
+            self.inputs.lock().push(message.content.clone());
+            tokio::time::sleep(Duration::from_millis(25)).await;
+            self.finished.fetch_add(1, Ordering::SeqCst);
+            Ok(AgentAction::Finish {
+                message: Some(message),
+            })
+        }
+    }
+
+    /// 每 10ms 触发一次、每次运行 25ms，观察 92ms 内的 (开始次数, 完成次数)
+    async fn observe(overlap: OverlapPolicy) -> (usize, usize, Arc<SlowAgent>) {
+        let agent = Arc::new(SlowAgent::default());
+        let mut scheduler = Scheduler::new();
+        scheduler.add(
+            ScheduledFlow::every(
+                "nightly",
+                Duration::from_millis(10),
+                testing::executor("job", vec![("slow", agent.clone())], |builder| {
+                    builder.add_agent_node("work", "slow").set_start("work");
+                }),
+            )
+            .with_overlap(overlap)
+            .with_input(serde_json::json!({ "job": "{schedule}", "run": "{run}" })),
+        );
+        let handle = scheduler.start();
+        tokio::time::sleep(Duration::from_millis(92)).await;
+        handle.shutdown();
+        (
+            agent.started.load(Ordering::SeqCst),
+            agent.finished.load(Ordering::SeqCst),
+            agent,
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_overlap_policies() {
+        let (started, finished, agent) = observe(OverlapPolicy::Skip).await;
+        assert_eq!((started, finished), (3, 2));
+        let first: Value = serde_json::from_str(&agent.inputs.lock()[0]).unwrap();
+        assert_eq!(first, serde_json::json!({ "job": "nightly", "run": "1" }));
+
+        let (started, finished, _) = observe(OverlapPolicy::Queue).await;
+        assert_eq!((started, finished), (4, 3));
+
+        let (started, finished, _) = observe(OverlapPolicy::CancelPrevious).await;
+        assert_eq!((started, finished), (9, 0));
+    }
+
+    #[test]
+    fn test_schedule_config_validation() {
+        assert!(Trigger::cron("*/5 * * * *").is_ok());
+        assert!(Trigger::cron("0 0 9 * * Mon-Fri").is_ok());
+        assert!(Trigger::cron("every minute").is_err());
+
+        let delay = Trigger::cron("*/5 * * * *")
+            .unwrap()
+            .next_delay(Utc::now())
+            .unwrap();
+        assert!(delay <= Duration::from_secs(300));
+
+        let mut registry = FlowRegistry::new();
+        registry.register_workflow(
+            crate::flow::loader::load_workflow_from_value(&serde_json::json!({
+                "flow": { "name": "report", "start": "done",
+                          "nodes": [{ "kind": "terminal", "name": "done" }] }
+            }))
+            .unwrap(),
+        );
+        let mut scheduler = Scheduler::new();
+        let configs = serde_json::json!([
+            { "name": "daily", "workflow": "report", "cron": "0 9 * * *", "overlap": "queue" }
+        ]);
+        assert!(scheduler.add_configs(&configs, &registry).is_ok());
+        let both = serde_json::json!([
+            { "name": "bad", "workflow": "report", "cron": "0 9 * * *", "every_secs": 60 }
+        ]);
+        assert!(scheduler.add_configs(&both, &registry).is_err());
+        let unknown = serde_json::json!([{ "name": "x", "workflow": "missing", "every_secs": 60 }]);
+        assert!(scheduler.add_configs(&unknown, &registry).is_err());
+    }
+}
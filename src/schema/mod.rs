@@ -1,13 +1,18 @@
 // Schema 模块
 
 mod error;
+mod payload;
 mod registry;
+#[allow(clippy::module_inception)]
 mod schema;
 mod validation;
 
+pub use agentflow_derive::AgentPayload;
 pub use error::SchemaError;
+pub use payload::{AgentPayload, PayloadSchema};
 pub use registry::SchemaRegistry;
 pub use schema::{Schema, SchemaKind};
+pub use validation::validate_value;
 
 use serde_json::Value;
 use std::sync::{Mutex, OnceLock};
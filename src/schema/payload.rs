@@ -0,0 +1,223 @@
+use super::error::SchemaError;
+use super::schema::{Schema, SchemaKind};
+use super::validation::validate_value;
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::message::StructuredMessage;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// 可以描述自身 JSON 结构的类型，`#[derive(AgentPayload)]` 按字段类型组合生成
+pub trait PayloadSchema {
+    fn payload_schema() -> Schema;
+
+    /// 作为对象属性时能否省略（`Option<T>`）
+    fn optional() -> bool {
+        false
+    }
+}
+
+/// 结构化 Agent 消息的载荷，通常通过 `#[derive(AgentPayload)]` 实现
+///
+/// ```rust,ignore
+/// #[derive(Serialize, Deserialize, AgentPayload)]
+/// #[payload(schema = "meal_plan")]
+/// struct MealPlan {
+///     /// 每日热量（千卡）
+///     calories: u32,
+///     meals: Vec<String>,
+///     notes: Option<String>,
+/// }
+///
+/// MealPlan::register_schema();
+/// let plan = MealPlan::try_from(message)?;
+/// let structured: StructuredMessage<MealPlan> = plan.into();
+/// ```
+pub trait AgentPayload: PayloadSchema + Serialize + DeserializeOwned {
+    const SCHEMA_NAME: &'static str;
+
+    fn schema() -> Schema {
+        Self::payload_schema().with_name(Self::SCHEMA_NAME)
+    }
+
+    /// 以 `SCHEMA_NAME` 注册到全局 Schema 注册表，供 `output_schema` 等配置引用
+    fn register_schema() {
+        super::register_schema(Self::SCHEMA_NAME, Self::schema());
+    }
+
+    /// 解析消息内容并按 Schema 校验
+    fn from_message(message: &AgentMessage) -> Result<Self> {
+        let value: Value = serde_json::from_str(&message.content)
+            .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        validate_value(&Self::schema(), &value, &mut Vec::new()).map_err(|e| {
+            let reason = match e {
+                SchemaError::Validation { message, path } if !path.is_empty() => {
+                    format!("{} at `{}`", message, path.join("."))
+                }
+                other => other.to_string(),
+            };
+            AgentFlowError::Serialization(format!(
+                "payload `{}` does not match its schema: {}",
+                Self::SCHEMA_NAME,
+                reason
+            ))
+        })?;
+        serde_json::from_value(value).map_err(|e| AgentFlowError::Serialization(e.to_string()))
+    }
+
+    fn into_structured(self) -> StructuredMessage<Self> {
+        StructuredMessage::new(self).with_schema(Self::SCHEMA_NAME)
+    }
+}
+
+macro_rules! payload_schema {
+    ($kind:ident: $($ty:ty),+) => {
+        $(
+            impl PayloadSchema for $ty {
+                fn payload_schema() -> Schema {
+                    Schema::new(SchemaKind::$kind)
+                }
+            }
+        )+
+    };
+}
+
+payload_schema!(Boolean: bool);
+payload_schema!(Integer: i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+payload_schema!(Number: f32, f64);
+payload_schema!(String: String, char);
+payload_schema!(Any: Value);
+
+impl<T: PayloadSchema> PayloadSchema for Option<T> {
+    fn payload_schema() -> Schema {
+        T::payload_schema()
+    }
+
+    fn optional() -> bool {
+        true
+    }
+}
+
+impl<T: PayloadSchema> PayloadSchema for Box<T> {
+    fn payload_schema() -> Schema {
+        T::payload_schema()
+    }
+}
+
+impl<T: PayloadSchema> PayloadSchema for Vec<T> {
+    fn payload_schema() -> Schema {
+        Schema::new(SchemaKind::Array {
+            items: Box::new(T::payload_schema()),
+        })
+    }
+}
+
+impl<T: PayloadSchema> PayloadSchema for HashMap<String, T> {
+    fn payload_schema() -> Schema {
+        Schema::new(SchemaKind::Object {
+            properties: HashMap::new(),
+            required: Vec::new(),
+            additional: true,
+        })
+    }
+}
+
+impl<T: PayloadSchema> PayloadSchema for BTreeMap<String, T> {
+    fn payload_schema() -> Schema {
+        HashMap::<String, T>::payload_schema()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AgentPayload;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AgentPayload)]
+    #[payload(schema = "meal")]
+    struct Meal {
+        name: String,
+        calories: u32,
+    }
+
+    /// 一日饮食计划
+    #[derive(Debug, PartialEq, Serialize, Deserialize, AgentPayload)]
+    #[serde(rename_all = "camelCase")]
+    struct DietPlan {
+        /// 每日目标热量
+        daily_calories: u32,
+        meals: Vec<Meal>,
+        #[serde(default)]
+        tags: Vec<String>,
+        notes: Option<String>,
+        #[serde(skip)]
+        cache: Option<String>,
+    }
+
+    #[test]
+    fn test_derived_schema_and_conversions() {
+        let schema = DietPlan::schema();
+        assert_eq!(schema.name.as_deref(), Some("DietPlan"));
+        assert_eq!(schema.description.as_deref(), Some("一日饮食计划"));
+        let SchemaKind::Object {
+            properties,
+            mut required,
+            ..
+        } = schema.kind
+        else {
+            panic!("expected object schema");
+        };
+        required.sort();
+        assert_eq!(required, vec!["dailyCalories", "meals"]);
+        assert_eq!(
+            properties["dailyCalories"].description.as_deref(),
+            Some("每日目标热量")
+        );
+        assert!(!properties.contains_key("cache"));
+        assert_eq!(
+            serde_json::to_value(&properties["meals"]).unwrap(),
+            json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "calories": { "type": "integer" }
+                    },
+                    "required": ["name", "calories"],
+                    "additional": true
+                }
+            })
+        );
+
+        let message = AgentMessage::system(
+            json!({ "dailyCalories": 1800, "meals": [{ "name": "oats", "calories": 350 }] })
+                .to_string(),
+        );
+        let plan = DietPlan::try_from(message).unwrap();
+        assert_eq!(plan.meals[0].name, "oats");
+        // `notes: None` 序列化为 null，仍然符合 Schema
+        let roundtrip = AgentMessage::system(serde_json::to_string(&plan).unwrap());
+        assert_eq!(DietPlan::try_from(roundtrip).unwrap(), plan);
+
+        let invalid =
+            AgentMessage::system(json!({ "dailyCalories": "many", "meals": [] }).to_string());
+        let err = DietPlan::try_from(invalid).unwrap_err().to_string();
+        assert!(
+            err.contains("expected integer at `dailyCalories`"),
+            "{}",
+            err
+        );
+
+        let structured: StructuredMessage<Meal> = Meal {
+            name: "soup".into(),
+            calories: 200,
+        }
+        .into();
+        assert_eq!(structured.schema.as_deref(), Some("meal"));
+    }
+}
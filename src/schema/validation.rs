@@ -84,6 +84,10 @@ pub fn validate_value(
             }
 
             for (key, val) in object {
+                // 非必填属性允许为 null（如序列化后的 `Option::None`）
+                if val.is_null() && !required.contains(key) {
+                    continue;
+                }
                 if let Some(sub_schema) = properties.get(key) {
                     path.push(key.clone());
                     validate_value(sub_schema, val, path)?;
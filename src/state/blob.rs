@@ -0,0 +1,253 @@
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+
+use crate::error::{AgentFlowError, Result};
+use crate::utils::sigv4::{hex, uri_encode_path, AwsCredentials, SigningScope};
+
+/// 附件二进制存储，按 id 读写
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// 写入内容并返回 id
+    async fn put(&self, bytes: &[u8]) -> Result<String>;
+    async fn get(&self, id: &str) -> Result<Vec<u8>>;
+    async fn delete(&self, id: &str) -> Result<()>;
+}
+
+/// 内容寻址的 id（SHA-256），相同内容只存一份
+pub fn blob_id(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+/// 本地目录存储，每个 blob 一个文件
+#[derive(Clone, Debug)]
+pub struct LocalBlobStore {
+    dir: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        validate_id(id)?;
+        Ok(self.dir.join(id))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, bytes: &[u8]) -> Result<String> {
+        let id = blob_id(bytes);
+        let path = self.path(&id)?;
+        let dir = self.dir.clone();
+        let bytes = bytes.to_vec();
+        blocking(move || {
+            std::fs::create_dir_all(&dir)?;
+            if !path.exists() {
+                std::fs::write(&path, bytes)?;
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(id)
+    }
+
+    async fn get(&self, id: &str) -> Result<Vec<u8>> {
+        let path = self.path(id)?;
+        blocking(move || std::fs::read(path)).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let path = self.path(id)?;
+        blocking(move || match std::fs::remove_file(path) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        })
+        .await
+    }
+}
+
+/// S3 兼容对象存储（AWS Signature V4）
+///
+/// 默认使用 `https://{bucket}.s3.{region}.amazonaws.com`；`with_endpoint` 指定 MinIO 等服务时
+/// 使用路径风格 `{endpoint}/{bucket}/{key}`。
+#[derive(Clone)]
+pub struct S3BlobStore {
+    bucket: String,
+    region: String,
+    credentials: AwsCredentials,
+    endpoint: Option<String>,
+    prefix: String,
+    client: reqwest::Client,
+}
+
+impl S3BlobStore {
+    pub fn new(
+        bucket: impl Into<String>,
+        region: impl Into<String>,
+        access_key: impl Into<String>,
+        secret_key: impl Into<String>,
+    ) -> Self {
+        Self {
+            bucket: bucket.into(),
+            region: region.into(),
+            credentials: AwsCredentials::new(access_key, secret_key),
+            endpoint: None,
+            prefix: String::new(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// 从 `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY` / `AWS_REGION`（默认 us-east-1）
+    /// 和可选的 `AWS_SESSION_TOKEN` 读取凭证
+    pub fn from_env(bucket: impl Into<String>) -> Result<Self> {
+        let region = std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+        let mut store = Self::new(bucket, region, "", "");
+        store.credentials = AwsCredentials::from_env()?;
+        Ok(store)
+    }
+
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// 对象键前缀，如 `attachments/`
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    pub fn with_session_token(mut self, token: impl Into<String>) -> Self {
+        self.credentials.session_token = Some(token.into());
+        self
+    }
+
+    /// 返回 (url, host, 规范化路径)
+    fn locate(&self, id: &str) -> (String, String, String) {
+        let key = format!("{}{}", self.prefix, id);
+        match &self.endpoint {
+            Some(endpoint) => {
+                let host = endpoint
+                    .split_once("://")
+                    .map_or(endpoint.as_str(), |(_, rest)| rest)
+                    .to_string();
+                let path = format!("/{}/{}", self.bucket, key);
+                (format!("{endpoint}{path}"), host, path)
+            }
+            None => {
+                let host = format!("{}.s3.{}.amazonaws.com", self.bucket, self.region);
+                let path = format!("/{key}");
+                (format!("https://{host}{path}"), host, path)
+            }
+        }
+    }
+
+    async fn send(&self, method: reqwest::Method, id: &str, body: Vec<u8>) -> Result<Vec<u8>> {
+        validate_id(id)?;
+        let (url, host, path) = self.locate(id);
+        let headers = self.credentials.sign(
+            method.as_str(),
+            &host,
+            &uri_encode_path(&path),
+            &body,
+            &SigningScope {
+                region: &self.region,
+                service: "s3",
+            },
+            Utc::now(),
+        );
+
+        let mut request = self.client.request(method, &url).body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("S3 request failed: {}", e)))?;
+        let status = response.status();
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("S3 response failed: {}", e)))?;
+        if !status.is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "S3 returned {} for `{}`: {}",
+                status,
+                id,
+                String::from_utf8_lossy(&bytes)
+            )));
+        }
+        Ok(bytes.to_vec())
+    }
+}
+
+#[async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, bytes: &[u8]) -> Result<String> {
+        let id = blob_id(bytes);
+        self.send(reqwest::Method::PUT, &id, bytes.to_vec()).await?;
+        Ok(id)
+    }
+
+    async fn get(&self, id: &str) -> Result<Vec<u8>> {
+        self.send(reqwest::Method::GET, id, Vec::new()).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        self.send(reqwest::Method::DELETE, id, Vec::new()).await?;
+        Ok(())
+    }
+}
+
+/// id 只允许字母、数字、`-` 和 `_`，防止路径穿越
+fn validate_id(id: &str) -> Result<()> {
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(AgentFlowError::Context(format!("invalid blob id `{id}`")));
+    }
+    Ok(())
+}
+
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> std::io::Result<T> + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| AgentFlowError::Other(e.into()))?
+        .map_err(|e| AgentFlowError::Other(anyhow!("blob storage failed: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_blob_ids_are_validated() {
+        let store = LocalBlobStore::new("/tmp/blobs");
+        let id = blob_id(b"data");
+        assert_eq!(store.path(&id).unwrap(), Path::new("/tmp/blobs").join(&id));
+        assert!(store.path("../etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_s3_locate_path_style_endpoint() {
+        let store = S3BlobStore::new("bucket", "us-east-1", "ak", "sk")
+            .with_endpoint("http://localhost:9000/")
+            .with_prefix("attachments/");
+        let (url, host, path) = store.locate("abc");
+        assert_eq!(url, "http://localhost:9000/bucket/attachments/abc");
+        assert_eq!(host, "localhost:9000");
+        assert_eq!(path, "/bucket/attachments/abc");
+    }
+}
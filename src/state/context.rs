@@ -1,16 +1,30 @@
+use super::dump::{FlowContextDump, ScopeDump};
+use super::history::{HistoryRetention, MessageLog, HISTORY_SPILL_PREFIX};
 use super::scope::{FlowScopeKind, ScopeId, ScopeStack};
-use super::store::ContextStore;
+use super::store::{ContextStore, MemoryStore, StateChange};
+use super::transaction::{run_transaction, Transaction};
 use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::guardrails::{PiiRedactor, RedactingStore};
+use crate::runtime::{RunChannel, TokenSink};
+use futures::stream::BoxStream;
 use parking_lot::RwLock;
+use std::future::Future;
 use std::sync::Arc;
 
 /// Flow 上下文
 #[derive(Clone)]
 pub struct FlowContext {
     store: Arc<dyn ContextStore>,
-    messages: Arc<RwLock<Vec<AgentMessage>>>,
+    messages: Arc<RwLock<MessageLog>>,
     scopes: Arc<ScopeStack>,
     global_scope_id: ScopeId,
+    channel: Option<RunChannel>,
+    session_id: Option<String>,
+    flow_name: Option<String>,
+    node: Option<String>,
+    redactor: Option<Arc<PiiRedactor>>,
+    token_sink: Option<Arc<dyn TokenSink>>,
 }
 
 impl FlowContext {
@@ -19,35 +33,202 @@ impl FlowContext {
         let global_scope_id = scopes.push_scope(FlowScopeKind::Global);
         Self {
             store,
-            messages: Arc::new(RwLock::new(Vec::new())),
+            messages: Arc::new(RwLock::new(MessageLog::new())),
             scopes,
             global_scope_id,
+            channel: None,
+            session_id: None,
+            flow_name: None,
+            node: None,
+            redactor: None,
+            token_sink: None,
         }
     }
 
+    /// 挂载运行交互通道，用于推送运行事件和接收人工消息
+    pub fn with_channel(mut self, channel: RunChannel) -> Self {
+        self.channel = Some(match &self.redactor {
+            Some(redactor) => channel.with_redactor(Arc::clone(redactor)),
+            None => channel,
+        });
+        self
+    }
+
+    pub fn channel(&self) -> Option<&RunChannel> {
+        self.channel.as_ref()
+    }
+
+    /// 挂载流式输出接收方，配置驱动 Agent 的 LLM 输出片段交给它处理
+    pub fn with_token_sink(mut self, sink: Arc<dyn TokenSink>) -> Self {
+        self.token_sink = Some(sink);
+        self
+    }
+
+    pub fn token_sink(&self) -> Option<&Arc<dyn TokenSink>> {
+        self.token_sink.as_ref()
+    }
+
+    /// 绑定会话，`session()` 的键按会话 id 隔离
+    pub fn with_session(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// 绑定当前执行的 Flow 与节点，用于变量的 node / flow 作用域
+    pub fn for_node(&self, flow: impl Into<String>, node: impl Into<String>) -> Self {
+        Self {
+            flow_name: Some(flow.into()),
+            node: Some(node.into()),
+            ..self.clone()
+        }
+    }
+
+    pub fn node(&self) -> Option<&str> {
+        self.node.as_deref()
+    }
+
+    /// 清理节点作用域的变量（节点执行完成时调用）
+    pub fn clear_node_scope(&self, node: &str) {
+        self.scopes
+            .remove_kind(&FlowScopeKind::Node(node.to_string()));
+    }
+
     pub fn store(&self) -> Arc<dyn ContextStore> {
         Arc::clone(&self.store)
     }
 
+    /// 共享消息历史和作用域，状态读写改走 `store`
+    pub(crate) fn with_store(&self, store: Arc<dyn ContextStore>) -> Self {
+        Self {
+            store,
+            ..self.clone()
+        }
+    }
+
+    /// 挂载 PII 脱敏器，写入历史的消息、写入存储的值和通道推送的事件先脱敏
+    pub fn with_redactor(mut self, redactor: Arc<PiiRedactor>) -> Self {
+        self.store = Arc::new(RedactingStore::new(self.store, Arc::clone(&redactor)));
+        self.channel = self
+            .channel
+            .map(|channel| channel.with_redactor(Arc::clone(&redactor)));
+        self.redactor = Some(redactor);
+        self
+    }
+
+    pub fn redactor(&self) -> Option<&Arc<PiiRedactor>> {
+        self.redactor.as_ref()
+    }
+
+    /// 设置消息历史的保留策略，对共享同一历史的上下文克隆都生效
+    pub fn with_history_retention(self, retention: HistoryRetention) -> Self {
+        self.messages.write().retention = Some(retention);
+        self
+    }
+
+    pub fn history_retention(&self) -> Option<HistoryRetention> {
+        self.messages.read().retention.clone()
+    }
+
     pub fn push_message(&self, message: AgentMessage) {
+        self.push_shared_message(Arc::new(message));
+    }
+
+    /// 写入历史而不复制消息；挂载脱敏器时仍会复制一份脱敏后写入
+    pub fn push_shared_message(&self, message: Arc<AgentMessage>) {
+        let message = match &self.redactor {
+            Some(redactor) => {
+                let mut message = Arc::unwrap_or_clone(message);
+                redactor.redact_message(&mut message);
+                Arc::new(message)
+            }
+            None => message,
+        };
         self.messages.write().push(message);
     }
 
+    /// 内存中的消息历史；配置了保留策略时只包含未被移出的较新消息
     pub fn history(&self) -> Vec<AgentMessage> {
-        self.messages.read().clone()
+        self.shared_history()
+            .into_iter()
+            .map(Arc::unwrap_or_clone)
+            .collect()
+    }
+
+    /// 与 `history` 相同，但与事件和其他读取方共享消息，不复制内容
+    pub fn shared_history(&self) -> Vec<Arc<AgentMessage>> {
+        self.messages.read().messages()
+    }
+
+    /// 完整的消息历史：读回保留策略写入存储的旧消息，再接上内存中的消息
+    pub async fn full_history(&self) -> Result<Vec<AgentMessage>> {
+        let (id, spilled, recent) = {
+            let log = self.messages.read();
+            let mut recent = log.pending().to_vec();
+            recent.extend(log.messages());
+            let recent: Vec<AgentMessage> = recent.into_iter().map(Arc::unwrap_or_clone).collect();
+            (log.id.clone(), log.spilled, recent)
+        };
+        let mut history = Vec::with_capacity(spilled + recent.len());
+        for index in 0..spilled {
+            let key = format!("{}{}:{}", HISTORY_SPILL_PREFIX, id, index);
+            if let Some(raw) = self.store.get(&key).await? {
+                history.push(
+                    serde_json::from_str(&raw)
+                        .map_err(|e| AgentFlowError::Serialization(e.to_string()))?,
+                );
+            }
+        }
+        history.extend(recent);
+        Ok(history)
+    }
+
+    /// 把保留策略移出的消息写入存储；执行器在每个节点结束后调用
+    pub async fn flush_history(&self) -> Result<()> {
+        let (id, (start, pending)) = {
+            let mut log = self.messages.write();
+            (log.id.clone(), log.take_pending())
+        };
+        for (offset, message) in pending.iter().enumerate() {
+            let raw = serde_json::to_string(message)
+                .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+            let key = format!("{}{}:{}", HISTORY_SPILL_PREFIX, id, start + offset);
+            self.store.set(&key, raw).await?;
+        }
+        Ok(())
     }
 
     pub fn last_message(&self) -> Option<AgentMessage> {
-        self.messages.read().last().cloned()
+        self.messages.read().last().map(Arc::unwrap_or_clone)
     }
 
     pub fn clear_messages(&self) {
         self.messages.write().clear();
     }
 
+    /// 订阅存储中以 `prefix` 开头的键的变更
+    pub async fn watch(&self, prefix: &str) -> Result<BoxStream<'static, StateChange>> {
+        self.store.watch(prefix).await
+    }
+
+    /// 在乐观事务中读写 `store`，冲突时重新执行 `f`
+    ///
+    /// 并发分支修改同一组键（如 Join 前的多个 Agent 累加计数）时使用，避免丢失更新。
+    pub async fn transaction<F, Fut, T>(&self, f: F) -> Result<T>
+    where
+        F: Fn(Transaction) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        run_transaction(Arc::clone(&self.store), f).await
+    }
+
     pub fn session(&self) -> super::session::SessionContext {
         super::session::SessionContext {
             store: Arc::clone(&self.store),
+            id: self.session_id.clone(),
         }
     }
 
@@ -57,6 +238,189 @@ impl FlowContext {
     }
 
     pub fn variables(&self) -> super::scope::FlowVariables {
-        super::scope::FlowVariables::new(Arc::clone(&self.scopes), self.global_scope_id)
+        let mut variables =
+            super::scope::FlowVariables::new(Arc::clone(&self.scopes), self.global_scope_id);
+        if let Some(flow) = &self.flow_name {
+            variables = variables.with_flow(flow.clone());
+        }
+        if let Some(node) = &self.node {
+            variables = variables.with_node(node.clone());
+        }
+        if self.session_id.is_some() {
+            variables = variables.with_session(self.session());
+        }
+        variables
+    }
+
+    /// 导出存储中的全部键、各作用域变量和消息历史，存储需支持 `ContextStore::keys`
+    pub async fn dump(&self) -> Result<FlowContextDump> {
+        let store = self.store();
+        let mut entries = std::collections::BTreeMap::new();
+        for key in store.keys("").await? {
+            // 移出的历史消息已合并到 `history`
+            if key.starts_with(HISTORY_SPILL_PREFIX) {
+                continue;
+            }
+            if let Some(value) = store.get(&key).await? {
+                entries.insert(key, value);
+            }
+        }
+        let variables = self.scopes.with_frames(|frames| {
+            frames
+                .iter()
+                .map(|frame| ScopeDump {
+                    scope: frame.kind.as_str().to_string(),
+                    name: frame.kind.name().map(str::to_string),
+                    variables: frame.variables.clone().into_iter().collect(),
+                })
+                .collect()
+        });
+        Ok(FlowContextDump {
+            session_id: self.session_id.clone(),
+            flow: self.flow_name.clone(),
+            node: self.node.clone(),
+            store: entries,
+            variables,
+            history: self.full_history().await?,
+        })
+    }
+
+    /// 用内存存储还原 `dump` 导出的上下文
+    pub async fn restore(dump: &FlowContextDump) -> Result<Self> {
+        Self::restore_with_store(Arc::new(MemoryStore::new()), dump).await
+    }
+
+    /// 把快照写入 `store` 并还原上下文；历史消息按原样写入，不再脱敏
+    pub async fn restore_with_store(
+        store: Arc<dyn ContextStore>,
+        dump: &FlowContextDump,
+    ) -> Result<Self> {
+        for (key, value) in &dump.store {
+            store.set(key, value.clone()).await?;
+        }
+        let mut ctx = Self::new(store);
+        ctx.session_id = dump.session_id.clone();
+        ctx.flow_name = dump.flow.clone();
+        ctx.node = dump.node.clone();
+        for scope in &dump.variables {
+            let id = match scope.kind()? {
+                FlowScopeKind::Global => ctx.global_scope_id,
+                kind => ctx.scopes.push_scope(kind),
+            };
+            ctx.scopes.with_frame_mut(id, |frame| {
+                frame.variables.extend(scope.variables.clone());
+            });
+        }
+        let mut log = ctx.messages.write();
+        for message in &dump.history {
+            log.push(Arc::new(message.clone()));
+        }
+        drop(log);
+        Ok(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_dump_and_restore() {
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new())).with_session("s1");
+        ctx.store()
+            .set("order.status", "failed".to_string())
+            .await
+            .unwrap();
+        let node_ctx = ctx.for_node("support", "answer");
+        let vars = node_ctx.variables();
+        vars.set_global("lang", "en").await.unwrap();
+        vars.set_flow("attempt", "2").await.unwrap();
+        vars.set("draft", "hello").await.unwrap();
+        let mut message = AgentMessage::user("where is my order");
+        message.metadata = Some(json!({ "channel": "chat" }));
+        ctx.push_message(message);
+
+        let dump = node_ctx.dump().await.unwrap();
+        assert_eq!(dump.store["order.status"], "failed");
+        assert_eq!(dump.node.as_deref(), Some("answer"));
+        let scopes: Vec<(&str, Option<&str>)> = dump
+            .variables
+            .iter()
+            .map(|scope| (scope.scope.as_str(), scope.name.as_deref()))
+            .collect();
+        assert_eq!(
+            scopes,
+            [
+                ("global", None),
+                ("flow", Some("support")),
+                ("node", Some("answer"))
+            ]
+        );
+
+        let dump = FlowContextDump::from_json(&dump.to_json().unwrap()).unwrap();
+        let restored = FlowContext::restore(&dump).await.unwrap();
+        assert_eq!(restored.session_id(), Some("s1"));
+        assert_eq!(restored.node(), Some("answer"));
+        assert_eq!(
+            restored
+                .store()
+                .get("order.status")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("failed")
+        );
+        let vars = restored.variables();
+        assert_eq!(vars.get("draft").await.as_deref(), Some("hello"));
+        assert_eq!(vars.get("attempt").await.as_deref(), Some("2"));
+        assert_eq!(vars.get("lang").await.as_deref(), Some("en"));
+        let history = restored.history();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].metadata, Some(json!({ "channel": "chat" })));
+    }
+
+    #[tokio::test]
+    async fn test_history_retention() {
+        let retention = HistoryRetention::new()
+            .with_max_messages(2)
+            .with_spill(true);
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new())).with_history_retention(retention);
+        for index in 0..5 {
+            ctx.push_message(AgentMessage::user(format!("turn {}", index)));
+        }
+        let recent: Vec<String> = ctx.history().into_iter().map(|m| m.content).collect();
+        assert_eq!(recent, ["turn 3", "turn 4"]);
+        assert_eq!(ctx.last_message().unwrap().content, "turn 4");
+        assert_eq!(ctx.full_history().await.unwrap().len(), 5);
+
+        ctx.flush_history().await.unwrap();
+        ctx.push_message(AgentMessage::user("turn 5"));
+        ctx.flush_history().await.unwrap();
+        let full: Vec<String> = ctx
+            .full_history()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(full[0], "turn 0");
+        assert_eq!(full.len(), 6);
+        let dump = ctx.dump().await.unwrap();
+        assert_eq!(dump.history.len(), 6);
+        assert!(dump
+            .store
+            .keys()
+            .all(|key| !key.starts_with(HISTORY_SPILL_PREFIX)));
+
+        // 单条超出字节上限时仍保留最新一条；未开启 spill 直接丢弃
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()))
+            .with_history_retention(HistoryRetention::new().with_max_bytes(8));
+        ctx.push_message(AgentMessage::user("abcdef"));
+        ctx.push_message(AgentMessage::user("a long message"));
+        assert_eq!(ctx.history().len(), 1);
+        ctx.flush_history().await.unwrap();
+        assert_eq!(ctx.full_history().await.unwrap().len(), 1);
+        assert!(ctx.store().keys("").await.unwrap().is_empty());
     }
 }
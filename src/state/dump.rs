@@ -0,0 +1,71 @@
+use super::scope::FlowScopeKind;
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// `FlowContext` 的完整快照：存储中的键、各作用域变量和消息历史
+///
+/// 用于调试时冻结失败现场，可通过 `FlowContext::restore` 还原。
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FlowContextDump {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flow: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    #[serde(default)]
+    pub store: BTreeMap<String, String>,
+    /// 按创建顺序排列的作用域
+    #[serde(default)]
+    pub variables: Vec<ScopeDump>,
+    #[serde(default)]
+    pub history: Vec<AgentMessage>,
+}
+
+impl FlowContextDump {
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| AgentFlowError::Serialization(e.to_string()))
+    }
+
+    pub fn from_json(content: &str) -> Result<Self> {
+        serde_json::from_str(content).map_err(|e| AgentFlowError::Serialization(e.to_string()))
+    }
+}
+
+/// 一个作用域的变量
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopeDump {
+    /// `global` / `flow` / `session` / `node` / `branch` / `custom`
+    pub scope: String,
+    /// 作用域名称（global 为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+impl ScopeDump {
+    pub(super) fn kind(&self) -> Result<FlowScopeKind> {
+        let name = || {
+            self.name.clone().ok_or_else(|| {
+                AgentFlowError::Context(format!("`{}` scope in dump has no name", self.scope))
+            })
+        };
+        Ok(match self.scope.as_str() {
+            "global" => FlowScopeKind::Global,
+            "flow" => FlowScopeKind::Flow(name()?),
+            "session" => FlowScopeKind::Session(name()?),
+            "node" => FlowScopeKind::Node(name()?),
+            "branch" => FlowScopeKind::Branch(name()?),
+            "custom" => FlowScopeKind::Custom(name()?),
+            other => {
+                return Err(AgentFlowError::Context(format!(
+                    "unknown scope `{}` in dump",
+                    other
+                )))
+            }
+        })
+    }
+}
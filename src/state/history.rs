@@ -0,0 +1,137 @@
+//! 消息历史与保留策略
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::{AgentMessage, BlobData};
+
+/// 移出内存的消息在存储中的键前缀，后接上下文的历史 id 和序号
+pub const HISTORY_SPILL_PREFIX: &str = "__agentflow:history:spill:";
+
+/// 消息历史保留策略
+///
+/// 超出任一上限时从最旧的消息开始移出内存，最新的一条总会保留；
+/// 移出的消息按 `spill` 写入存储或直接丢弃。
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryRetention {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_messages: Option<usize>,
+    /// 按消息内容和内联附件的字节数计算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<usize>,
+    /// 把移出的消息写入 `ContextStore`，可用 `FlowContext::full_history` 读回
+    #[serde(default)]
+    pub spill: bool,
+}
+
+impl HistoryRetention {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_messages(mut self, max: usize) -> Self {
+        self.max_messages = Some(max);
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max: usize) -> Self {
+        self.max_bytes = Some(max);
+        self
+    }
+
+    pub fn with_spill(mut self, spill: bool) -> Self {
+        self.spill = spill;
+        self
+    }
+}
+
+/// 消息占用的字节数（估算）
+fn message_bytes(message: &AgentMessage) -> usize {
+    let attachments: usize = message
+        .attachments
+        .iter()
+        .map(|attachment| match attachment.data() {
+            Some(BlobData::Inline(bytes)) => bytes.len(),
+            Some(BlobData::Blob(id)) => id.len(),
+            None => 0,
+        })
+        .sum();
+    message.content.len() + attachments
+}
+
+/// 上下文中的消息历史
+pub(super) struct MessageLog {
+    /// 区分不同上下文写入存储的消息
+    pub(super) id: String,
+    pub(super) retention: Option<HistoryRetention>,
+    messages: VecDeque<Arc<AgentMessage>>,
+    bytes: usize,
+    /// 已移出、尚未写入存储的消息
+    pending: Vec<Arc<AgentMessage>>,
+    /// 已分配存储序号的消息数
+    pub(super) spilled: usize,
+}
+
+impl MessageLog {
+    pub(super) fn new() -> Self {
+        Self {
+            id: crate::agent::message::uuid(),
+            retention: None,
+            messages: VecDeque::new(),
+            bytes: 0,
+            pending: Vec::new(),
+            spilled: 0,
+        }
+    }
+
+    pub(super) fn push(&mut self, message: Arc<AgentMessage>) {
+        self.bytes += message_bytes(&message);
+        self.messages.push_back(message);
+        let Some(retention) = &self.retention else {
+            return;
+        };
+        while self.messages.len() > 1
+            && (retention
+                .max_messages
+                .is_some_and(|max| self.messages.len() > max)
+                || retention.max_bytes.is_some_and(|max| self.bytes > max))
+        {
+            let Some(evicted) = self.messages.pop_front() else {
+                break;
+            };
+            self.bytes -= message_bytes(&evicted);
+            if retention.spill {
+                self.pending.push(evicted);
+            }
+        }
+    }
+
+    pub(super) fn messages(&self) -> Vec<Arc<AgentMessage>> {
+        self.messages.iter().cloned().collect()
+    }
+
+    pub(super) fn last(&self) -> Option<Arc<AgentMessage>> {
+        self.messages.back().cloned()
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.messages.clear();
+        self.pending.clear();
+        self.bytes = 0;
+        self.spilled = 0;
+    }
+
+    /// 取出待写入的消息及其起始序号，序号在取出时分配
+    pub(super) fn take_pending(&mut self) -> (usize, Vec<Arc<AgentMessage>>) {
+        let start = self.spilled;
+        let pending = std::mem::take(&mut self.pending);
+        self.spilled += pending.len();
+        (start, pending)
+    }
+
+    pub(super) fn pending(&self) -> &[Arc<AgentMessage>] {
+        &self.pending
+    }
+}
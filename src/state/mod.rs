@@ -1,13 +1,30 @@
 // 状态管理模块
 
+mod blob;
 mod context;
+mod dump;
+mod history;
+mod privacy;
+mod retention;
 mod scope;
 mod session;
 mod store;
+mod transaction;
 
+pub use blob::{blob_id, BlobStore, LocalBlobStore, S3BlobStore};
 pub use context::FlowContext;
-pub use scope::{FlowScopeGuard, FlowScopeKind, FlowVariables};
-pub use session::SessionContext;
+pub use dump::{FlowContextDump, ScopeDump};
+pub use history::{HistoryRetention, HISTORY_SPILL_PREFIX};
+pub use privacy::{
+    DeletionReport, StoreDeletionReport, UserDataDeletion, UserDataRegistry, UserDataStore,
+};
+pub use retention::{
+    RecordCatalog, RecordKind, RecordMeta, RetentionManager, RetentionPolicy, RetentionReport,
+    RetentionRule,
+};
+pub use scope::{FlowScopeGuard, FlowScopeKind, FlowVariables, ScopeSnapshot};
+pub use session::{Session, SessionContext, SessionManager};
 #[cfg(feature = "redis-store")]
 pub use store::redis::RedisStore;
-pub use store::{ContextStore, MemoryStore};
+pub use store::{ChangeKind, ContextStore, MemoryStore, StateChange, UpdateFn};
+pub use transaction::{Transaction, MAX_TRANSACTION_ATTEMPTS};
@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use super::retention::RetentionManager;
+use crate::error::Result;
+
+/// 单个存储的删除结果
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct UserDataDeletion {
+    pub deleted: usize,
+    /// 因 legal hold 等原因保留的条目
+    pub retained: usize,
+}
+
+/// 持有用户数据的存储
+///
+/// 会话、长期记忆、附件、审计日志、运行记录等后端实现该 trait 后注册到
+/// `UserDataRegistry`，由 `delete_user_data` 统一清除。
+#[async_trait]
+pub trait UserDataStore: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// 删除该用户的数据
+    async fn delete_user_data(&self, user_id: &str) -> Result<UserDataDeletion>;
+
+    /// 统计该用户仍可删除的数据条目（用于删除后的校验）
+    async fn count_user_data(&self, user_id: &str) -> Result<usize>;
+}
+
+/// 单个存储的删除报告
+#[derive(Clone, Debug, Serialize)]
+pub struct StoreDeletionReport {
+    pub store: String,
+    pub deleted: usize,
+    pub retained: usize,
+    /// 删除后校验仍存在的条目
+    pub remaining: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 用户数据删除报告
+#[derive(Clone, Debug, Serialize)]
+pub struct DeletionReport {
+    pub user_id: String,
+    pub stores: Vec<StoreDeletionReport>,
+    /// 所有存储均删除成功且校验无残留
+    pub verified: bool,
+}
+
+impl DeletionReport {
+    pub fn total_deleted(&self) -> usize {
+        self.stores.iter().map(|s| s.deleted).sum()
+    }
+}
+
+/// 用户数据存储注册表
+#[derive(Clone, Default)]
+pub struct UserDataRegistry {
+    stores: Vec<Arc<dyn UserDataStore>>,
+}
+
+impl UserDataRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, store: Arc<dyn UserDataStore>) -> &mut Self {
+        self.stores.push(store);
+        self
+    }
+
+    pub fn stores(&self) -> impl Iterator<Item = &str> {
+        self.stores.iter().map(|s| s.name())
+    }
+
+    /// 清除用户在所有已注册存储中的数据并校验
+    ///
+    /// 单个存储失败不会中断其他存储的删除，错误记录在报告中。
+    pub async fn delete_user_data(&self, user_id: &str) -> DeletionReport {
+        let mut reports = Vec::with_capacity(self.stores.len());
+        for store in &self.stores {
+            let mut report = StoreDeletionReport {
+                store: store.name().to_string(),
+                deleted: 0,
+                retained: 0,
+                remaining: 0,
+                error: None,
+            };
+            let outcome = async {
+                let deletion = store.delete_user_data(user_id).await?;
+                report.deleted = deletion.deleted;
+                report.retained = deletion.retained;
+                report.remaining = store.count_user_data(user_id).await?;
+                Ok::<_, crate::error::AgentFlowError>(())
+            }
+            .await;
+            if let Err(err) = outcome {
+                warn!(store = store.name(), %err, "User data deletion failed");
+                report.error = Some(err.to_string());
+            }
+            reports.push(report);
+        }
+
+        let verified = reports
+            .iter()
+            .all(|r| r.error.is_none() && r.remaining == 0);
+        let report = DeletionReport {
+            user_id: user_id.to_string(),
+            stores: reports,
+            verified,
+        };
+        info!(
+            user_id,
+            deleted = report.total_deleted(),
+            verified,
+            "User data deletion completed"
+        );
+        report
+    }
+}
+
+/// 运行记录、会话、附件、审计日志（记录目录）中的用户数据
+///
+/// legal hold 记录保留，计入 `retained`。
+#[async_trait]
+impl UserDataStore for RetentionManager {
+    fn name(&self) -> &str {
+        "records"
+    }
+
+    async fn delete_user_data(&self, user_id: &str) -> Result<UserDataDeletion> {
+        let mut deletion = UserDataDeletion::default();
+        for meta in self.catalog().list_by_user(user_id).await? {
+            if meta.has_tag(&self.policy().legal_hold_tag) {
+                deletion.retained += 1;
+                continue;
+            }
+            if self.catalog().remove(meta.kind, &meta.id).await?.is_some() {
+                deletion.deleted += 1;
+            }
+        }
+        Ok(deletion)
+    }
+
+    async fn count_user_data(&self, user_id: &str) -> Result<usize> {
+        Ok(self
+            .catalog()
+            .list_by_user(user_id)
+            .await?
+            .iter()
+            .filter(|meta| !meta.has_tag(&self.policy().legal_hold_tag))
+            .count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AgentFlowError;
+    use crate::state::{MemoryStore, RecordCatalog, RecordKind, RecordMeta, RetentionPolicy};
+
+    struct FailingStore;
+
+    #[async_trait]
+    impl UserDataStore for FailingStore {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        async fn delete_user_data(&self, _user_id: &str) -> Result<UserDataDeletion> {
+            Err(AgentFlowError::Other(anyhow::anyhow!("backend offline")))
+        }
+
+        async fn count_user_data(&self, _user_id: &str) -> Result<usize> {
+            Ok(1)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_data_across_stores() {
+        let catalog = RecordCatalog::new(Arc::new(MemoryStore::new()));
+        for (kind, id, user) in [
+            (RecordKind::Run, "r1", "alice"),
+            (RecordKind::Session, "s1", "alice"),
+            (RecordKind::Audit, "a1", "alice"),
+            (RecordKind::Run, "r2", "bob"),
+        ] {
+            let key = format!("{}:{}", kind.as_str(), id);
+            catalog.store().set(&key, "data".into()).await.unwrap();
+            catalog
+                .track(RecordMeta::new(kind, id).with_key(key).with_user(user))
+                .await
+                .unwrap();
+        }
+        let manager = RetentionManager::new(catalog.clone(), RetentionPolicy::default());
+        manager
+            .set_legal_hold(RecordKind::Audit, "a1", true)
+            .await
+            .unwrap();
+
+        let mut registry = UserDataRegistry::new();
+        registry.register(Arc::new(manager.clone()));
+        let report = registry.delete_user_data("alice").await;
+        assert!(report.verified);
+        assert_eq!(report.stores[0].deleted, 2);
+        assert_eq!(report.stores[0].retained, 1);
+        assert!(catalog.store().get("session:s1").await.unwrap().is_none());
+        assert!(catalog.store().get("run:r2").await.unwrap().is_some());
+
+        registry.register(Arc::new(FailingStore));
+        let report = registry.delete_user_data("bob").await;
+        assert!(!report.verified);
+        assert_eq!(report.stores[0].deleted, 1);
+        assert_eq!(report.stores[1].error.as_deref(), Some("backend offline"));
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use super::store::ContextStore;
+use crate::error::{AgentFlowError, Result};
+
+const INDEX_PREFIX: &str = "retention:index";
+
+/// 持久化记录类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordKind {
+    Run,
+    Session,
+    Attachment,
+    Audit,
+}
+
+impl RecordKind {
+    pub const ALL: [RecordKind; 4] = [
+        RecordKind::Run,
+        RecordKind::Session,
+        RecordKind::Attachment,
+        RecordKind::Audit,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecordKind::Run => "run",
+            RecordKind::Session => "session",
+            RecordKind::Attachment => "attachment",
+            RecordKind::Audit => "audit",
+        }
+    }
+}
+
+/// 记录元数据
+///
+/// `keys` 为该记录在 ContextStore 中占用的键，清理时一并删除。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordMeta {
+    pub id: String,
+    pub kind: RecordKind,
+    #[serde(default)]
+    pub keys: Vec<String>,
+    pub created_at: u64,
+    #[serde(default)]
+    pub size_bytes: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl RecordMeta {
+    pub fn new(kind: RecordKind, id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            kind,
+            keys: Vec::new(),
+            created_at: now_secs(),
+            size_bytes: 0,
+            user_id: None,
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.keys.push(key.into());
+        self
+    }
+
+    pub fn with_size(mut self, size_bytes: u64) -> Self {
+        self.size_bytes = size_bytes;
+        self
+    }
+
+    pub fn with_user(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn with_created_at(mut self, created_at: u64) -> Self {
+        self.created_at = created_at;
+        self
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+/// 记录目录
+///
+/// 按类型在 ContextStore 中维护记录索引，供保留策略与数据删除使用。
+#[derive(Clone)]
+pub struct RecordCatalog {
+    store: Arc<dyn ContextStore>,
+    lock: Arc<Mutex<()>>,
+}
+
+impl RecordCatalog {
+    pub fn new(store: Arc<dyn ContextStore>) -> Self {
+        Self {
+            store,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn store(&self) -> Arc<dyn ContextStore> {
+        Arc::clone(&self.store)
+    }
+
+    fn index_key(kind: RecordKind) -> String {
+        format!("{}:{}", INDEX_PREFIX, kind.as_str())
+    }
+
+    async fn load(&self, kind: RecordKind) -> Result<Vec<RecordMeta>> {
+        match self.store.get(&Self::index_key(kind)).await? {
+            Some(raw) => {
+                serde_json::from_str(&raw).map_err(|e| AgentFlowError::Serialization(e.to_string()))
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn save(&self, kind: RecordKind, records: &[RecordMeta]) -> Result<()> {
+        let raw = serde_json::to_string(records)
+            .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
+        self.store.set(&Self::index_key(kind), raw).await
+    }
+
+    /// 登记记录（同 id 覆盖）
+    pub async fn track(&self, meta: RecordMeta) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut records = self.load(meta.kind).await?;
+        records.retain(|r| r.id != meta.id);
+        let kind = meta.kind;
+        records.push(meta);
+        self.save(kind, &records).await
+    }
+
+    pub async fn get(&self, kind: RecordKind, id: &str) -> Result<Option<RecordMeta>> {
+        Ok(self.load(kind).await?.into_iter().find(|r| r.id == id))
+    }
+
+    pub async fn list(&self, kind: RecordKind) -> Result<Vec<RecordMeta>> {
+        self.load(kind).await
+    }
+
+    /// 列出某用户的所有记录
+    pub async fn list_by_user(&self, user_id: &str) -> Result<Vec<RecordMeta>> {
+        let mut records = Vec::new();
+        for kind in RecordKind::ALL {
+            records.extend(
+                self.load(kind)
+                    .await?
+                    .into_iter()
+                    .filter(|r| r.user_id.as_deref() == Some(user_id)),
+            );
+        }
+        Ok(records)
+    }
+
+    /// 删除记录及其占用的所有键
+    pub async fn remove(&self, kind: RecordKind, id: &str) -> Result<Option<RecordMeta>> {
+        let _guard = self.lock.lock().await;
+        let mut records = self.load(kind).await?;
+        let Some(pos) = records.iter().position(|r| r.id == id) else {
+            return Ok(None);
+        };
+        let meta = records.remove(pos);
+        for key in &meta.keys {
+            self.store.delete(key).await?;
+        }
+        self.save(kind, &records).await?;
+        Ok(Some(meta))
+    }
+
+    /// 添加或移除标签，记录不存在时返回 false
+    pub async fn set_tag(&self, kind: RecordKind, id: &str, tag: &str, on: bool) -> Result<bool> {
+        let _guard = self.lock.lock().await;
+        let mut records = self.load(kind).await?;
+        let Some(meta) = records.iter_mut().find(|r| r.id == id) else {
+            return Ok(false);
+        };
+        meta.tags.retain(|t| t != tag);
+        if on {
+            meta.tags.push(tag.to_string());
+        }
+        self.save(kind, &records).await?;
+        Ok(true)
+    }
+}
+
+/// 单类记录的保留规则（任一条件超出即清理）
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RetentionRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_bytes: Option<u64>,
+}
+
+/// 保留策略
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    #[serde(default)]
+    pub rules: HashMap<RecordKind, RetentionRule>,
+    /// 带有该标签的记录不会被清理，也不计入数量/大小限制
+    #[serde(default = "RetentionPolicy::default_legal_hold_tag")]
+    pub legal_hold_tag: String,
+    #[serde(default = "RetentionPolicy::default_sweep_interval_secs")]
+    pub sweep_interval_secs: u64,
+}
+
+impl RetentionPolicy {
+    fn default_legal_hold_tag() -> String {
+        "legal_hold".to_string()
+    }
+
+    fn default_sweep_interval_secs() -> u64 {
+        300
+    }
+
+    pub fn with_rule(mut self, kind: RecordKind, rule: RetentionRule) -> Self {
+        self.rules.insert(kind, rule);
+        self
+    }
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            rules: HashMap::new(),
+            legal_hold_tag: Self::default_legal_hold_tag(),
+            sweep_interval_secs: Self::default_sweep_interval_secs(),
+        }
+    }
+}
+
+/// 清理报告
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RetentionReport {
+    pub removed: Vec<RecordMeta>,
+    pub held: usize,
+    pub retained: usize,
+}
+
+/// 保留策略执行器
+#[derive(Clone)]
+pub struct RetentionManager {
+    catalog: RecordCatalog,
+    policy: RetentionPolicy,
+}
+
+impl RetentionManager {
+    pub fn new(catalog: RecordCatalog, policy: RetentionPolicy) -> Self {
+        Self { catalog, policy }
+    }
+
+    pub fn catalog(&self) -> &RecordCatalog {
+        &self.catalog
+    }
+
+    pub fn policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    /// 设置 / 解除 legal hold
+    pub async fn set_legal_hold(&self, kind: RecordKind, id: &str, held: bool) -> Result<bool> {
+        self.catalog
+            .set_tag(kind, id, &self.policy.legal_hold_tag, held)
+            .await
+    }
+
+    /// 执行一次清理
+    pub async fn sweep(&self) -> Result<RetentionReport> {
+        self.sweep_at(now_secs()).await
+    }
+
+    async fn sweep_at(&self, now: u64) -> Result<RetentionReport> {
+        let mut report = RetentionReport::default();
+        for kind in RecordKind::ALL {
+            let Some(rule) = self.policy.rules.get(&kind) else {
+                continue;
+            };
+            let mut records = self.catalog.list(kind).await?;
+            // 从新到旧，保留最新的记录
+            records.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+
+            let mut kept = 0usize;
+            let mut kept_bytes = 0u64;
+            for meta in records {
+                if meta.has_tag(&self.policy.legal_hold_tag) {
+                    report.held += 1;
+                    continue;
+                }
+                let expired = rule
+                    .max_age_secs
+                    .is_some_and(|max| now.saturating_sub(meta.created_at) > max);
+                let over_count = rule.max_count.is_some_and(|max| kept >= max);
+                let over_size = rule
+                    .max_total_bytes
+                    .is_some_and(|max| kept_bytes + meta.size_bytes > max);
+
+                if expired || over_count || over_size {
+                    if let Some(removed) = self.catalog.remove(kind, &meta.id).await? {
+                        report.removed.push(removed);
+                    }
+                } else {
+                    kept += 1;
+                    kept_bytes += meta.size_bytes;
+                    report.retained += 1;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// 启动后台清理任务，按 `sweep_interval_secs` 周期执行
+    pub fn spawn(self) -> JoinHandle<()> {
+        let interval = Duration::from_secs(self.policy.sweep_interval_secs.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match self.sweep().await {
+                    Ok(report) if !report.removed.is_empty() => {
+                        info!(
+                            removed = report.removed.len(),
+                            held = report.held,
+                            "Retention sweep completed"
+                        );
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!(%err, "Retention sweep failed"),
+                }
+            }
+        })
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryStore;
+
+    async fn seed(catalog: &RecordCatalog, id: &str, created_at: u64, size: u64) {
+        let key = format!("run:{}", id);
+        catalog.store().set(&key, "{}".into()).await.unwrap();
+        catalog
+            .track(
+                RecordMeta::new(RecordKind::Run, id)
+                    .with_key(key)
+                    .with_size(size)
+                    .with_created_at(created_at),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sweep_by_age_count_and_size() {
+        let catalog = RecordCatalog::new(Arc::new(MemoryStore::new()));
+        seed(&catalog, "r1", 100, 10).await;
+        seed(&catalog, "r2", 900, 10).await;
+        seed(&catalog, "r3", 950, 10).await;
+        seed(&catalog, "r4", 990, 10).await;
+
+        let policy = RetentionPolicy::default().with_rule(
+            RecordKind::Run,
+            RetentionRule {
+                max_age_secs: Some(500),
+                max_count: Some(2),
+                max_total_bytes: None,
+            },
+        );
+        let manager = RetentionManager::new(catalog.clone(), policy);
+        let report = manager.sweep_at(1000).await.unwrap();
+
+        let removed: Vec<_> = report.removed.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(removed, vec!["r2", "r1"]);
+        assert_eq!(report.retained, 2);
+        assert!(catalog.store().get("run:r1").await.unwrap().is_none());
+        assert!(catalog.store().get("run:r4").await.unwrap().is_some());
+
+        let policy = RetentionPolicy::default().with_rule(
+            RecordKind::Run,
+            RetentionRule {
+                max_total_bytes: Some(15),
+                ..RetentionRule::default()
+            },
+        );
+        let report = RetentionManager::new(catalog.clone(), policy)
+            .sweep_at(1000)
+            .await
+            .unwrap();
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(catalog.list(RecordKind::Run).await.unwrap()[0].id, "r4");
+    }
+
+    #[tokio::test]
+    async fn test_legal_hold_exempts_record() {
+        let catalog = RecordCatalog::new(Arc::new(MemoryStore::new()));
+        seed(&catalog, "old", 0, 1).await;
+        let policy = RetentionPolicy::default().with_rule(
+            RecordKind::Run,
+            RetentionRule {
+                max_age_secs: Some(10),
+                ..RetentionRule::default()
+            },
+        );
+        let manager = RetentionManager::new(catalog.clone(), policy);
+        assert!(manager
+            .set_legal_hold(RecordKind::Run, "old", true)
+            .await
+            .unwrap());
+
+        let report = manager.sweep_at(1000).await.unwrap();
+        assert!(report.removed.is_empty());
+        assert_eq!(report.held, 1);
+
+        manager
+            .set_legal_hold(RecordKind::Run, "old", false)
+            .await
+            .unwrap();
+        let report = manager.sweep_at(1000).await.unwrap();
+        assert_eq!(report.removed.len(), 1);
+    }
+}
@@ -1,6 +1,8 @@
+use super::session::SessionContext;
 use crate::error::{AgentFlowError, Result};
 use parking_lot::RwLock;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
@@ -12,6 +14,10 @@ pub type ScopeId = u64;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum FlowScopeKind {
     Global,
+    /// 按 Flow 名称区分
+    Flow(String),
+    /// 按会话 id 区分
+    Session(String),
     Node(String),
     Branch(String),
     Custom(String),
@@ -21,11 +27,25 @@ impl FlowScopeKind {
     pub fn as_str(&self) -> &str {
         match self {
             FlowScopeKind::Global => "global",
+            FlowScopeKind::Flow(_) => "flow",
+            FlowScopeKind::Session(_) => "session",
             FlowScopeKind::Node(_) => "node",
             FlowScopeKind::Branch(_) => "branch",
             FlowScopeKind::Custom(_) => "custom",
         }
     }
+
+    /// 作用域名称（global 为 None）
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            FlowScopeKind::Global => None,
+            FlowScopeKind::Flow(name)
+            | FlowScopeKind::Session(name)
+            | FlowScopeKind::Node(name)
+            | FlowScopeKind::Branch(name)
+            | FlowScopeKind::Custom(name) => Some(name),
+        }
+    }
 }
 
 /// Scope 栈
@@ -41,6 +61,22 @@ impl ScopeStack {
         id
     }
 
+    /// 返回指定类型的作用域，不存在时创建
+    pub fn find_or_push(&self, kind: FlowScopeKind) -> ScopeId {
+        let mut frames = self.frames.write();
+        if let Some(frame) = frames.iter().find(|frame| frame.kind == kind) {
+            return frame.id;
+        }
+        let id = NEXT_SCOPE_ID.fetch_add(1, Ordering::Relaxed);
+        frames.push(ScopeFrame::new(id, kind));
+        id
+    }
+
+    /// 移除指定类型的全部作用域
+    pub fn remove_kind(&self, kind: &FlowScopeKind) {
+        self.frames.write().retain(|frame| frame.kind != *kind);
+    }
+
     pub fn remove(&self, id: ScopeId) {
         let mut frames = self.frames.write();
         if let Some(pos) = frames.iter().rposition(|frame| frame.id == id) {
@@ -152,10 +188,27 @@ impl Drop for FlowScopeGuard {
 }
 
 /// Flow 变量管理器
+///
+/// `get` 按 node → flow → session → global 的顺序解析，内层同名变量遮蔽外层。
+/// 通过 `ctx.scope()` 创建的 Branch/Custom 作用域与 node 同层，后创建的优先。
 #[derive(Clone)]
 pub struct FlowVariables {
     stack: Arc<ScopeStack>,
     global_scope_id: ScopeId,
+    flow: Option<String>,
+    node: Option<String>,
+    session: Option<SessionContext>,
+}
+
+/// 各层作用域变量的快照，用于调试
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ScopeSnapshot {
+    pub node: BTreeMap<String, String>,
+    pub flow: BTreeMap<String, String>,
+    pub session: BTreeMap<String, String>,
+    pub global: BTreeMap<String, String>,
+    /// 按解析链合并后的结果
+    pub resolved: BTreeMap<String, String>,
 }
 
 impl FlowVariables {
@@ -163,9 +216,28 @@ impl FlowVariables {
         Self {
             stack,
             global_scope_id,
+            flow: None,
+            node: None,
+            session: None,
         }
     }
 
+    pub fn with_flow(mut self, flow: impl Into<String>) -> Self {
+        self.flow = Some(flow.into());
+        self
+    }
+
+    pub fn with_node(mut self, node: impl Into<String>) -> Self {
+        self.node = Some(node.into());
+        self
+    }
+
+    /// session 层读写绑定会话的 `SessionContext`
+    pub fn with_session(mut self, session: SessionContext) -> Self {
+        self.session = Some(session);
+        self
+    }
+
     pub async fn set_global(&self, key: impl Into<String>, value: impl Into<String>) -> Result<()> {
         let updated = self.stack.with_frame_mut(self.global_scope_id, |frame| {
             frame.variables.insert(key.into(), value.into());
@@ -200,20 +272,65 @@ impl FlowVariables {
         }
     }
 
+    /// 写入当前节点的作用域，节点执行完成后自动清理
+    pub async fn set_node(&self, key: impl Into<String>, value: impl Into<String>) -> Result<()> {
+        let node = self
+            .node
+            .as_ref()
+            .ok_or_else(|| AgentFlowError::Context("no active node scope".to_string()))?;
+        self.insert(FlowScopeKind::Node(node.clone()), key.into(), value.into());
+        Ok(())
+    }
+
+    /// 写入当前 Flow 的作用域，同一上下文中该 Flow 的所有节点可见
+    pub async fn set_flow(&self, key: impl Into<String>, value: impl Into<String>) -> Result<()> {
+        let flow = self
+            .flow
+            .as_ref()
+            .ok_or_else(|| AgentFlowError::Context("no active flow scope".to_string()))?;
+        self.insert(FlowScopeKind::Flow(flow.clone()), key.into(), value.into());
+        Ok(())
+    }
+
+    /// 写入会话作用域（持久化到会话存储，跨运行可见）
+    pub async fn set_session(&self, key: &str, value: impl Into<String>) -> Result<()> {
+        let session = self
+            .session
+            .as_ref()
+            .ok_or_else(|| AgentFlowError::Context("no active session scope".to_string()))?;
+        session.set(key, value).await
+    }
+
     pub async fn get(&self, key: &str) -> Option<String> {
-        self.stack.with_frames(|frames| {
+        if let Some(value) = self.stack.with_frames(|frames| {
             frames
                 .iter()
                 .rev()
+                .filter(|frame| self.in_node_layer(&frame.kind))
+                .chain(self.flow_frame(frames))
                 .find_map(|frame| frame.variables.get(key).cloned())
-        })
+        }) {
+            return Some(value);
+        }
+        if let Some(session) = &self.session {
+            match session.get(key).await {
+                Ok(Some(value)) => return Some(value),
+                Ok(None) => {}
+                Err(err) => tracing::warn!(key, error = %err, "failed to read session variable"),
+            }
+        }
+        self.get_global(key).await
     }
 
+    /// 绑定节点时写入节点作用域，否则写入最内层作用域
     pub async fn set(&self, key: impl Into<String>, value: impl Into<String>) -> Result<()> {
+        if self.node.is_some() {
+            return self.set_node(key, value).await;
+        }
         let frame_id = {
             let frames = self.stack.frames.read();
             if let Some(frame) = frames.last() {
-                frame.id.clone()
+                frame.id
             } else {
                 return Err(AgentFlowError::Context("no active scope".to_string()));
             }
@@ -227,4 +344,98 @@ impl FlowVariables {
             Err(AgentFlowError::Context("no active scope".to_string()))
         }
     }
+
+    /// 各层变量的快照
+    pub async fn snapshot(&self) -> Result<ScopeSnapshot> {
+        let mut snapshot = ScopeSnapshot::default();
+        self.stack.with_frames(|frames| {
+            for frame in frames {
+                if frame.id == self.global_scope_id {
+                    snapshot.global.extend(frame.variables.clone());
+                } else if self.in_node_layer(&frame.kind) {
+                    snapshot.node.extend(frame.variables.clone());
+                }
+            }
+            if let Some(frame) = self.flow_frame(frames) {
+                snapshot.flow.extend(frame.variables.clone());
+            }
+        });
+        if let Some(session) = &self.session {
+            for key in session.keys().await? {
+                if let Some(value) = session.get(&key).await? {
+                    snapshot.session.insert(key, value);
+                }
+            }
+        }
+
+        for layer in [
+            &snapshot.global,
+            &snapshot.session,
+            &snapshot.flow,
+            &snapshot.node,
+        ] {
+            snapshot
+                .resolved
+                .extend(layer.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        Ok(snapshot)
+    }
+
+    fn insert(&self, kind: FlowScopeKind, key: String, value: String) {
+        let id = self.stack.find_or_push(kind);
+        self.stack.with_frame_mut(id, |frame| {
+            frame.variables.insert(key, value);
+        });
+    }
+
+    /// 与节点同层的作用域：当前节点（未绑定时为任意节点）以及 Branch/Custom
+    fn in_node_layer(&self, kind: &FlowScopeKind) -> bool {
+        match kind {
+            FlowScopeKind::Global | FlowScopeKind::Flow(_) | FlowScopeKind::Session(_) => false,
+            FlowScopeKind::Node(node) => self.node.as_ref().is_none_or(|current| current == node),
+            FlowScopeKind::Branch(_) | FlowScopeKind::Custom(_) => true,
+        }
+    }
+
+    fn flow_frame<'a>(&self, frames: &'a [ScopeFrame]) -> Option<&'a ScopeFrame> {
+        let flow = self.flow.as_ref()?;
+        frames
+            .iter()
+            .find(|frame| matches!(&frame.kind, FlowScopeKind::Flow(name) if name == flow))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::{FlowContext, MemoryStore};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_resolution_chain_and_shadowing() {
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new())).with_session("s1");
+        let node_ctx = ctx.for_node("support", "answer");
+        let vars = node_ctx.variables();
+
+        vars.set_global("lang", "en").await.unwrap();
+        vars.set_session("lang", "zh").await.unwrap();
+        assert_eq!(vars.get("lang").await.as_deref(), Some("zh"));
+        vars.set_flow("lang", "fr").await.unwrap();
+        assert_eq!(vars.get("lang").await.as_deref(), Some("fr"));
+        vars.set("lang", "de").await.unwrap();
+        assert_eq!(vars.get("lang").await.as_deref(), Some("de"));
+
+        // 其他节点看不到 answer 的节点变量
+        let other = ctx.for_node("support", "review").variables();
+        assert_eq!(other.get("lang").await.as_deref(), Some("fr"));
+
+        let snapshot = vars.snapshot().await.unwrap();
+        assert_eq!(snapshot.node["lang"], "de");
+        assert_eq!(snapshot.session["lang"], "zh");
+        assert_eq!(snapshot.global["lang"], "en");
+        assert_eq!(snapshot.resolved["lang"], "de");
+
+        ctx.clear_node_scope("answer");
+        assert_eq!(vars.get("lang").await.as_deref(), Some("fr"));
+        assert!(ctx.variables().set_node("x", "1").await.is_err());
+    }
 }
@@ -1,31 +1,492 @@
+use super::context::FlowContext;
 use super::store::ContextStore;
-use crate::error::Result;
+use crate::agent::AgentMessage;
+use crate::error::{AgentFlowError, Result};
+use crate::runtime::{FlowExecution, FlowExecutor};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const SESSION_PREFIX: &str = "session";
+const META_KEY: &str = "__meta";
+const TURNS_KEY: &str = "__turns";
+const KEYS_KEY: &str = "__keys";
+const DEFAULT_MAX_MESSAGES: usize = 50;
 
 /// 会话上下文
+///
+/// 绑定会话 id 时键为 `session:{id}:{key}`，否则为 `session:{key}`。`__meta`、`__turns`、
+/// `__keys` 为保留键；未绑定会话时键不能包含 `:`，避免与某个会话的键重叠。
 #[derive(Clone)]
 pub struct SessionContext {
     pub store: Arc<dyn ContextStore>,
+    pub id: Option<String>,
 }
 
 impl SessionContext {
-    fn key_with_prefix(key: &str) -> String {
-        format!("{SESSION_PREFIX}:{key}")
+    fn key_with_prefix(&self, key: &str) -> Result<String> {
+        if RESERVED_KEYS.contains(&key) {
+            return Err(invalid_key(key, "is reserved for session bookkeeping"));
+        }
+        match &self.id {
+            Some(id) => {
+                check_session_id(id)?;
+                Ok(session_key(id, key))
+            }
+            None if key.contains(':') => Err(invalid_key(
+                key,
+                "must not contain `:` outside a bound session",
+            )),
+            None => Ok(format!("{SESSION_PREFIX}:{key}")),
+        }
     }
 
     pub async fn get(&self, key: &str) -> Result<Option<String>> {
-        self.store.get(&Self::key_with_prefix(key)).await
+        self.store.get(&self.key_with_prefix(key)?).await
     }
 
     pub async fn set(&self, key: &str, value: impl Into<String>) -> Result<()> {
         self.store
-            .set(&Self::key_with_prefix(key), value.into())
-            .await
+            .set(&self.key_with_prefix(key)?, value.into())
+            .await?;
+        // 记录会话写过的键，会话过期时一并删除
+        if let Some(id) = &self.id {
+            let key = key.to_string();
+            self.store
+                .update(&session_key(id, KEYS_KEY), &move |current| {
+                    let mut keys: Vec<String> = decode_or_default(current)?;
+                    if !keys.contains(&key) {
+                        keys.push(key.clone());
+                    }
+                    encode(&keys).map(Some)
+                })
+                .await?;
+        }
+        Ok(())
     }
 
     pub async fn delete(&self, key: &str) -> Result<()> {
-        self.store.delete(&Self::key_with_prefix(key)).await
+        self.store.delete(&self.key_with_prefix(key)?).await
+    }
+
+    /// 会话内写入过的键（未绑定会话 id 时为空）
+    pub async fn keys(&self) -> Result<Vec<String>> {
+        match &self.id {
+            Some(id) => decode_or_default(self.store.get(&session_key(id, KEYS_KEY)).await?),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// 会话元数据（时间为 Unix 毫秒）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SessionMeta {
+    created_at: u64,
+    last_active_at: u64,
+}
+
+/// 会话管理：按会话 id 保存多轮对话，空闲超过 TTL 的会话自动过期
+#[derive(Clone)]
+pub struct SessionManager {
+    store: Arc<dyn ContextStore>,
+    ttl: Option<Duration>,
+    max_messages: usize,
+}
+
+impl SessionManager {
+    pub fn new(store: Arc<dyn ContextStore>) -> Self {
+        Self {
+            store,
+            ttl: None,
+            max_messages: DEFAULT_MAX_MESSAGES,
+        }
+    }
+
+    /// 空闲超过 `ttl` 的会话在下次访问或 `purge_expired` 时清空
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// 每个会话保留的历史消息数（默认 50，保留最新的）
+    pub fn with_max_messages(mut self, max_messages: usize) -> Self {
+        self.max_messages = max_messages.max(1);
+        self
+    }
+
+    /// 获取会话，不存在或已过期时创建新会话；会话 id 不能包含 `:`
+    pub async fn get_or_create(&self, session_id: &str) -> Result<Session> {
+        check_session_id(session_id)?;
+        let meta_key = session_key(session_id, META_KEY);
+        let now = now_millis();
+        let existing = match self.store.get(&meta_key).await? {
+            Some(raw) => Some(decode::<SessionMeta>(&raw)?),
+            None => None,
+        };
+        let created_at = match existing {
+            Some(meta) if !self.is_expired(&meta, now) => meta.created_at,
+            Some(_) => {
+                self.expire(session_id).await?;
+                now
+            }
+            None => now,
+        };
+        let meta = SessionMeta {
+            created_at,
+            last_active_at: now,
+        };
+        self.store.set(&meta_key, encode(&meta)?).await?;
+        Ok(Session {
+            id: session_id.to_string(),
+            manager: self.clone(),
+            created_at,
+        })
+    }
+
+    /// 删除会话的历史消息、元数据以及会话作用域内写入的键
+    pub async fn expire(&self, session_id: &str) -> Result<()> {
+        let keys_key = session_key(session_id, KEYS_KEY);
+        let keys: Vec<String> = decode_or_default(self.store.get(&keys_key).await?)?;
+        for key in keys {
+            self.store.delete(&session_key(session_id, &key)).await?;
+        }
+        for key in [KEYS_KEY, TURNS_KEY, META_KEY] {
+            self.store.delete(&session_key(session_id, key)).await?;
+        }
+        Ok(())
+    }
+
+    /// 删除所有已过期的会话，返回被删除的会话 id；需要存储支持 `keys`，适合定期调用
+    pub async fn purge_expired(&self) -> Result<Vec<String>> {
+        if self.ttl.is_none() {
+            return Ok(Vec::new());
+        }
+        let suffix = format!(":{META_KEY}");
+        let prefix = format!("{SESSION_PREFIX}:");
+        let now = now_millis();
+        let mut purged = Vec::new();
+        for key in self.store.keys(&prefix).await? {
+            let Some(id) = key
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(&suffix))
+            else {
+                continue;
+            };
+            if check_session_id(id).is_err() {
+                continue;
+            }
+            let Some(raw) = self.store.get(&key).await? else {
+                continue;
+            };
+            if self.is_expired(&decode::<SessionMeta>(&raw)?, now) {
+                self.expire(id).await?;
+                purged.push(id.to_string());
+            }
+        }
+        Ok(purged)
+    }
+
+    fn is_expired(&self, meta: &SessionMeta, now: u64) -> bool {
+        self.ttl
+            .is_some_and(|ttl| now.saturating_sub(meta.last_active_at) >= ttl.as_millis() as u64)
+    }
+}
+
+/// 一个会话：多次调用执行器时共享对话历史和会话作用域的键
+#[derive(Clone)]
+pub struct Session {
+    id: String,
+    manager: SessionManager,
+    created_at: u64,
+}
+
+impl Session {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// 创建时间（Unix 毫秒）
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    /// 会话作用域的键值存储
+    pub fn context(&self) -> SessionContext {
+        SessionContext {
+            store: Arc::clone(&self.manager.store),
+            id: Some(self.id.clone()),
+        }
+    }
+
+    /// 之前各轮的对话消息（用户输入与最终回复）
+    pub async fn history(&self) -> Result<Vec<AgentMessage>> {
+        let raw = self
+            .manager
+            .store
+            .get(&session_key(&self.id, TURNS_KEY))
+            .await?;
+        decode_or_default(raw)
+    }
+
+    /// 创建注入了历史消息、绑定会话 id 的运行上下文
+    pub async fn flow_context(&self) -> Result<FlowContext> {
+        let ctx = FlowContext::new(Arc::clone(&self.manager.store)).with_session(self.id.clone());
+        for message in self.history().await? {
+            ctx.push_message(message);
+        }
+        Ok(ctx)
+    }
+
+    /// 追加一轮对话，并刷新会话的最近活跃时间
+    pub async fn record_turn(
+        &self,
+        input: AgentMessage,
+        reply: Option<AgentMessage>,
+    ) -> Result<()> {
+        let max_messages = self.manager.max_messages;
+        let turn: Vec<AgentMessage> = std::iter::once(input).chain(reply).collect();
+        self.manager
+            .store
+            .update(&session_key(&self.id, TURNS_KEY), &move |current| {
+                let mut messages: Vec<AgentMessage> = decode_or_default(current)?;
+                messages.extend(turn.iter().cloned());
+                let overflow = messages.len().saturating_sub(max_messages);
+                messages.drain(..overflow);
+                encode(&messages).map(Some)
+            })
+            .await?;
+        self.touch().await
+    }
+
+    async fn touch(&self) -> Result<()> {
+        let created_at = self.created_at;
+        let now = now_millis();
+        self.manager
+            .store
+            .update(&session_key(&self.id, META_KEY), &move |current| {
+                let meta = match current {
+                    Some(raw) => SessionMeta {
+                        last_active_at: now,
+                        ..decode(&raw)?
+                    },
+                    None => SessionMeta {
+                        created_at,
+                        last_active_at: now,
+                    },
+                };
+                encode(&meta).map(Some)
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// 在会话中执行一轮：注入历史、运行流程并记录本轮输入与最终消息
+    pub async fn run(&self, executor: &FlowExecutor, input: AgentMessage) -> Result<FlowExecution> {
+        let ctx = Arc::new(self.flow_context().await?);
+        let execution = executor.start(ctx, input.clone()).await?;
+        self.record_turn(input, execution.last_message.clone())
+            .await?;
+        Ok(execution)
+    }
+}
+
+const RESERVED_KEYS: [&str; 3] = [META_KEY, TURNS_KEY, KEYS_KEY];
+
+fn session_key(id: &str, key: &str) -> String {
+    format!("{SESSION_PREFIX}:{id}:{key}")
+}
+
+/// 会话 id 中的 `:` 会让 `session:{id}:{key}` 与其他会话的键重叠
+fn check_session_id(id: &str) -> Result<()> {
+    if id.is_empty() || id.contains(':') {
+        return Err(AgentFlowError::InvalidParameter {
+            name: "session_id".to_string(),
+            message: format!("`{}` must be non-empty and must not contain `:`", id),
+        });
+    }
+    Ok(())
+}
+
+fn invalid_key(key: &str, reason: &str) -> AgentFlowError {
+    AgentFlowError::InvalidParameter {
+        name: "session_key".to_string(),
+        message: format!("`{}` {}", key, reason),
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| AgentFlowError::Serialization(e.to_string()))
+}
+
+fn decode<T: for<'de> Deserialize<'de>>(raw: &str) -> Result<T> {
+    serde_json::from_str(raw).map_err(|e| AgentFlowError::Serialization(e.to_string()))
+}
+
+fn decode_or_default<T: for<'de> Deserialize<'de> + Default>(raw: Option<String>) -> Result<T> {
+    raw.map(|raw| decode(&raw))
+        .transpose()
+        .map(Option::unwrap_or_default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentAction, AgentContext};
+    use crate::state::MemoryStore;
+    use crate::testing;
+    use async_trait::async_trait;
+
+    /// 回复中带上已看到的历史消息数
+    struct CountingAgent;
+
+    #[async_trait]
+    impl Agent for CountingAgent {
+        fn name(&self) -> &'static str {
+            "counter"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            ctx.session().set("last", message.content.clone()).await?;
+            let seen = ctx.flow().history().len();
+            Ok(AgentAction::Finish {
+                message: Some(AgentMessage::system(format!("seen {}", seen))),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_carries_turns_across_runs() {
+        let store = Arc::new(MemoryStore::new());
+        let manager = SessionManager::new(store.clone());
+        let executor = testing::executor(
+            "chat",
+            vec![("counter", Arc::new(CountingAgent))],
+            |builder| {
+                builder
+                    .add_agent_node("counter", "counter")
+                    .set_start("counter");
+            },
+        );
+
+        let session = manager.get_or_create("s1").await.unwrap();
+        let first = session
+            .run(&executor, AgentMessage::user("hi"))
+            .await
+            .unwrap();
+        assert_eq!(first.last_message.unwrap().content, "seen 1");
+
+        let session = manager.get_or_create("s1").await.unwrap();
+        let second = session
+            .run(&executor, AgentMessage::user("again"))
+            .await
+            .unwrap();
+        // 上一轮的输入与回复 + 本轮输入
+        assert_eq!(second.last_message.unwrap().content, "seen 3");
+        assert_eq!(session.history().await.unwrap().len(), 4);
+
+        let other = manager.get_or_create("s2").await.unwrap();
+        assert!(other.history().await.unwrap().is_empty());
+        assert_eq!(
+            session.context().get("last").await.unwrap().as_deref(),
+            Some("again")
+        );
+        assert_eq!(other.context().get("last").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_starts_fresh() {
+        let store = Arc::new(MemoryStore::new());
+        let manager = SessionManager::new(store.clone())
+            .with_ttl(Duration::from_millis(20))
+            .with_max_messages(3);
+
+        let session = manager.get_or_create("s").await.unwrap();
+        session.context().set("topic", "rust").await.unwrap();
+        for i in 0..2 {
+            session
+                .record_turn(AgentMessage::user(format!("q{i}")), None)
+                .await
+                .unwrap();
+        }
+        session
+            .record_turn(AgentMessage::user("q2"), Some(AgentMessage::system("a2")))
+            .await
+            .unwrap();
+        let contents: Vec<String> = session
+            .history()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, vec!["q1", "q2", "a2"]);
+
+        std::thread::sleep(Duration::from_millis(30));
+        let session = manager.get_or_create("s").await.unwrap();
+        assert!(session.history().await.unwrap().is_empty());
+        assert_eq!(session.context().get("topic").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_session_keys_cannot_alias() {
+        let store = Arc::new(MemoryStore::new());
+        let manager = SessionManager::new(store.clone());
+        assert!(manager.get_or_create("a:b").await.is_err());
+
+        let session = manager.get_or_create("a").await.unwrap();
+        session
+            .record_turn(AgentMessage::user("hi"), None)
+            .await
+            .unwrap();
+        let context = session.context();
+        for key in [TURNS_KEY, META_KEY, KEYS_KEY] {
+            assert!(context.set(key, "[]").await.is_err());
+        }
+        assert_eq!(session.history().await.unwrap().len(), 1);
+
+        let unbound = SessionContext { store, id: None };
+        assert!(unbound.set("a:__turns", "[]").await.is_err());
+        unbound.set("topic", "rust").await.unwrap();
+        assert_eq!(session.history().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_turns_keep_session_active_and_purge_expired() {
+        let store = Arc::new(MemoryStore::new());
+        let manager = SessionManager::new(store.clone()).with_ttl(Duration::from_millis(60));
+
+        let active = manager.get_or_create("active").await.unwrap();
+        let idle = manager.get_or_create("idle").await.unwrap();
+        idle.context().set("topic", "rust").await.unwrap();
+        for i in 0..3 {
+            std::thread::sleep(Duration::from_millis(30));
+            active
+                .record_turn(AgentMessage::user(format!("q{i}")), None)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(manager.purge_expired().await.unwrap(), vec!["idle"]);
+        assert_eq!(
+            store.get(&session_key("idle", "topic")).await.unwrap(),
+            None
+        );
+        assert_eq!(
+            store.get(&session_key("idle", META_KEY)).await.unwrap(),
+            None
+        );
+        let active = manager.get_or_create("active").await.unwrap();
+        assert_eq!(active.history().await.unwrap().len(), 3);
     }
 }
@@ -1,7 +1,51 @@
-use crate::error::Result;
+use crate::error::{AgentFlowError, Result};
 use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::StreamExt;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tokio::sync::broadcast;
+
+/// `MemoryStore` 变更通知的缓冲大小，订阅者落后超过该数量时丢弃旧通知
+const WATCH_CAPACITY: usize = 1024;
+
+/// 键变更类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Set,
+    Deleted,
+}
+
+/// 键变更通知
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StateChange {
+    pub key: String,
+    pub kind: ChangeKind,
+    /// 写入后的值（删除时为 None；Redis 下为收到通知后读取的值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+impl StateChange {
+    fn new(key: &str, value: Option<String>) -> Self {
+        Self {
+            key: key.to_string(),
+            kind: if value.is_some() {
+                ChangeKind::Set
+            } else {
+                ChangeKind::Deleted
+            },
+            value,
+        }
+    }
+}
+
+/// `ContextStore::update` 的修改函数：输入旧值，返回新值（None 表示删除）
+///
+/// 实现可能在冲突时重试，函数不应有副作用。
+pub type UpdateFn<'a> = &'a (dyn Fn(Option<String>) -> Result<Option<String>> + Send + Sync);
 
 /// 上下文存储 trait
 #[async_trait]
@@ -9,21 +53,82 @@ pub trait ContextStore: Send + Sync {
     async fn get(&self, key: &str) -> Result<Option<String>>;
     async fn set(&self, key: &str, value: String) -> Result<()>;
     async fn delete(&self, key: &str) -> Result<()>;
+
+    /// 读取并修改一个键，返回写入后的值
+    ///
+    /// 默认实现为 get + set，不保证并发安全；多个执行进程共享的存储应覆盖为原子操作。
+    async fn update(&self, key: &str, f: UpdateFn<'_>) -> Result<Option<String>> {
+        let next = f(self.get(key).await?)?;
+        match &next {
+            Some(value) => self.set(key, value.clone()).await?,
+            None => self.delete(key).await?,
+        }
+        Ok(next)
+    }
+
+    /// 当前值等于 `expected`（None 表示键不存在）时写入 `new`（None 表示删除），返回是否写入
+    ///
+    /// 默认实现为 get + set，不保证并发安全；存储应覆盖为原子操作。
+    async fn compare_and_set(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        new: Option<String>,
+    ) -> Result<bool> {
+        if self.get(key).await?.as_deref() != expected {
+            return Ok(false);
+        }
+        match new {
+            Some(value) => self.set(key, value).await?,
+            None => self.delete(key).await?,
+        }
+        Ok(true)
+    }
+
+    /// 订阅以 `prefix` 开头的键的变更，替代在循环条件中轮询
+    async fn watch(&self, prefix: &str) -> Result<BoxStream<'static, StateChange>> {
+        Err(AgentFlowError::Context(format!(
+            "store does not support watching `{prefix}`"
+        )))
+    }
+
+    /// 列出以 `prefix` 开头的键（用于导出上下文快照），顺序不保证
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>> {
+        Err(AgentFlowError::Context(format!(
+            "store does not support listing `{prefix}`"
+        )))
+    }
 }
 
 /// 内存存储实现
 pub struct MemoryStore {
     inner: RwLock<HashMap<String, String>>,
+    changes: broadcast::Sender<StateChange>,
 }
 
 impl MemoryStore {
     pub fn new() -> Self {
+        let (changes, _) = broadcast::channel(WATCH_CAPACITY);
         Self {
             inner: RwLock::new(HashMap::new()),
+            changes,
+        }
+    }
+
+    /// 在持有写锁时发送，保证通知顺序与写入顺序一致；没有订阅者时忽略
+    fn notify(&self, key: &str, value: Option<String>) {
+        if self.changes.receiver_count() > 0 {
+            let _ = self.changes.send(StateChange::new(key, value));
         }
     }
 }
 
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[async_trait]
 impl ContextStore for MemoryStore {
     async fn get(&self, key: &str) -> Result<Option<String>> {
@@ -31,22 +136,93 @@ impl ContextStore for MemoryStore {
     }
 
     async fn set(&self, key: &str, value: String) -> Result<()> {
-        self.inner.write().insert(key.to_string(), value);
+        let mut inner = self.inner.write();
+        inner.insert(key.to_string(), value.clone());
+        self.notify(key, Some(value));
         Ok(())
     }
 
     async fn delete(&self, key: &str) -> Result<()> {
-        self.inner.write().remove(key);
+        let mut inner = self.inner.write();
+        if inner.remove(key).is_some() {
+            self.notify(key, None);
+        }
         Ok(())
     }
+
+    async fn update(&self, key: &str, f: UpdateFn<'_>) -> Result<Option<String>> {
+        let mut inner = self.inner.write();
+        let next = f(inner.get(key).cloned())?;
+        match &next {
+            Some(value) => inner.insert(key.to_string(), value.clone()),
+            None => inner.remove(key),
+        };
+        self.notify(key, next.clone());
+        Ok(next)
+    }
+
+    async fn compare_and_set(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        new: Option<String>,
+    ) -> Result<bool> {
+        let mut inner = self.inner.write();
+        if inner.get(key).map(String::as_str) != expected {
+            return Ok(false);
+        }
+        match &new {
+            Some(value) => inner.insert(key.to_string(), value.clone()),
+            None => inner.remove(key),
+        };
+        self.notify(key, new);
+        Ok(true)
+    }
+
+    async fn watch(&self, prefix: &str) -> Result<BoxStream<'static, StateChange>> {
+        let receiver = self.changes.subscribe();
+        let prefix = prefix.to_string();
+        let stream = futures::stream::unfold(receiver, move |mut receiver| {
+            let prefix = prefix.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(change) if change.key.starts_with(&prefix) => {
+                            return Some((change, receiver))
+                        }
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!(skipped, prefix, "state watcher lagged behind");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+        Ok(stream.boxed())
+    }
+
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self
+            .inner
+            .read()
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
 }
 
 #[cfg(feature = "redis-store")]
 pub mod redis {
     use super::*;
-    use crate::error::AgentFlowError;
     use ::redis::AsyncCommands;
 
+    /// `update` 加锁的过期时间，防止持锁进程崩溃后死锁
+    const LOCK_TTL_MS: u64 = 5_000;
+    const LOCK_RETRY: std::time::Duration = std::time::Duration::from_millis(10);
+    const LOCK_ATTEMPTS: usize = 500;
+
     pub struct RedisStore {
         client: ::redis::Client,
     }
@@ -78,7 +254,7 @@ pub mod redis {
                 .get_multiplexed_async_connection()
                 .await
                 .map_err(|e| AgentFlowError::Context(e.to_string()))?;
-            conn.set(key, value)
+            conn.set::<_, _, ()>(key, value)
                 .await
                 .map_err(|e| AgentFlowError::Context(e.to_string()))?;
             Ok(())
@@ -90,10 +266,208 @@ pub mod redis {
                 .get_multiplexed_async_connection()
                 .await
                 .map_err(|e| AgentFlowError::Context(e.to_string()))?;
-            conn.del(key)
+            conn.del::<_, ()>(key)
                 .await
                 .map_err(|e| AgentFlowError::Context(e.to_string()))?;
             Ok(())
         }
+
+        /// 通过 `{key}:lock`（SET NX PX）加锁后读改写，多个执行进程之间互斥
+        async fn update(&self, key: &str, f: UpdateFn<'_>) -> Result<Option<String>> {
+            let mut conn = self
+                .client
+                .get_multiplexed_async_connection()
+                .await
+                .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+            let lock_key = format!("{key}:lock");
+            let token = crate::agent::message::uuid();
+            let mut acquired = false;
+            for _ in 0..LOCK_ATTEMPTS {
+                let reply: Option<String> = ::redis::cmd("SET")
+                    .arg(&lock_key)
+                    .arg(&token)
+                    .arg("NX")
+                    .arg("PX")
+                    .arg(LOCK_TTL_MS)
+                    .query_async(&mut conn)
+                    .await
+                    .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+                if reply.is_some() {
+                    acquired = true;
+                    break;
+                }
+                tokio::time::sleep(LOCK_RETRY).await;
+            }
+            if !acquired {
+                return Err(AgentFlowError::Context(format!(
+                    "timed out waiting for lock on `{key}`"
+                )));
+            }
+
+            let result = async {
+                let current: Option<String> = conn
+                    .get(key)
+                    .await
+                    .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+                let next = f(current)?;
+                match &next {
+                    Some(value) => conn.set::<_, _, ()>(key, value).await,
+                    None => conn.del::<_, ()>(key).await,
+                }
+                .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+                Ok(next)
+            }
+            .await;
+
+            let release = ::redis::Script::new(
+                "if redis.call('get', KEYS[1]) == ARGV[1] then return redis.call('del', KEYS[1]) else return 0 end",
+            );
+            release
+                .key(&lock_key)
+                .arg(&token)
+                .invoke_async::<i64>(&mut conn)
+                .await
+                .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+            result
+        }
+
+        /// Lua 脚本中比较并写入，单次往返完成
+        async fn compare_and_set(
+            &self,
+            key: &str,
+            expected: Option<&str>,
+            new: Option<String>,
+        ) -> Result<bool> {
+            let mut conn = self
+                .client
+                .get_multiplexed_async_connection()
+                .await
+                .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+            let script = ::redis::Script::new(
+                r#"
+                local current = redis.call('get', KEYS[1])
+                if ARGV[1] == '1' then
+                    if current ~= ARGV[2] then return 0 end
+                elseif current then
+                    return 0
+                end
+                if ARGV[3] == '1' then
+                    redis.call('set', KEYS[1], ARGV[4])
+                else
+                    redis.call('del', KEYS[1])
+                end
+                return 1
+                "#,
+            );
+            let swapped: i64 = script
+                .key(key)
+                .arg(if expected.is_some() { "1" } else { "0" })
+                .arg(expected.unwrap_or_default())
+                .arg(if new.is_some() { "1" } else { "0" })
+                .arg(new.unwrap_or_default())
+                .invoke_async(&mut conn)
+                .await
+                .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+            Ok(swapped == 1)
+        }
+
+        /// 基于 keyspace 通知，服务端需开启 `notify-keyspace-events`（至少 `K$g`）
+        ///
+        /// 连接断开时流结束。
+        async fn watch(&self, prefix: &str) -> Result<BoxStream<'static, StateChange>> {
+            let db = self.client.get_connection_info().redis.db;
+            let channel_prefix = format!("__keyspace@{db}__:");
+            let mut pubsub = self
+                .client
+                .get_async_pubsub()
+                .await
+                .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+            pubsub
+                .psubscribe(format!("{channel_prefix}{}*", escape_glob(prefix)))
+                .await
+                .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+            let conn = self
+                .client
+                .get_multiplexed_async_connection()
+                .await
+                .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+
+            let stream = pubsub.into_on_message().filter_map(move |message| {
+                let mut conn = conn.clone();
+                let key = message
+                    .get_channel_name()
+                    .strip_prefix(&channel_prefix)
+                    .map(str::to_string);
+                let event: Option<String> = message.get_payload().ok();
+                async move {
+                    let key = key?;
+                    match event?.as_str() {
+                        "del" | "expired" | "evicted" => Some(StateChange::new(&key, None)),
+                        _ => {
+                            let value: Option<String> = conn.get(&key).await.ok().flatten();
+                            Some(StateChange {
+                                kind: ChangeKind::Set,
+                                ..StateChange::new(&key, value)
+                            })
+                        }
+                    }
+                }
+            });
+            Ok(stream.boxed())
+        }
+
+        /// 通过 SCAN 遍历，不阻塞服务端
+        async fn keys(&self, prefix: &str) -> Result<Vec<String>> {
+            let mut conn = self
+                .client
+                .get_multiplexed_async_connection()
+                .await
+                .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+            let mut iter = conn
+                .scan_match::<_, String>(format!("{}*", escape_glob(prefix)))
+                .await
+                .map_err(|e| AgentFlowError::Context(e.to_string()))?;
+            let mut keys = Vec::new();
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+            Ok(keys)
+        }
+    }
+
+    /// 转义 PSUBSCRIBE / SCAN MATCH 模式中的通配符
+    fn escape_glob(value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len());
+        for ch in value.chars() {
+            if matches!(ch, '*' | '?' | '[' | ']' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(ch);
+        }
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_store_watch_prefix() {
+        let store = MemoryStore::new();
+        let mut changes = store.watch("order:").await.unwrap();
+
+        store.set("user:1", "ignored".to_string()).await.unwrap();
+        store.set("order:1", "paid".to_string()).await.unwrap();
+        store.delete("order:missing").await.unwrap();
+        store.delete("order:1").await.unwrap();
+
+        let first = changes.next().await.unwrap();
+        assert_eq!(first.key, "order:1");
+        assert_eq!(first.kind, ChangeKind::Set);
+        assert_eq!(first.value.as_deref(), Some("paid"));
+        let second = changes.next().await.unwrap();
+        assert_eq!(second.kind, ChangeKind::Deleted);
+        assert_eq!(second.value, None);
     }
 }
@@ -0,0 +1,220 @@
+use super::store::ContextStore;
+use crate::error::{AgentFlowError, Result};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 冲突后的最大重试次数
+pub const MAX_TRANSACTION_ATTEMPTS: usize = 16;
+
+/// 乐观事务：读取时记录看到的值，提交时逐键 compare-and-set
+///
+/// 任一键在读取后被其他分支修改则回滚已写入的键并重新执行事务函数。
+/// 提交过程中其他读者可能短暂看到部分写入。
+#[derive(Clone)]
+pub struct Transaction {
+    store: Arc<dyn ContextStore>,
+    state: Arc<Mutex<TxnState>>,
+}
+
+#[derive(Default)]
+struct TxnState {
+    /// 读取时看到的值
+    reads: HashMap<String, Option<String>>,
+    /// 待写入的值（None 表示删除），按写入顺序
+    writes: Vec<(String, Option<String>)>,
+}
+
+impl TxnState {
+    fn pending(&self, key: &str) -> Option<&Option<String>> {
+        self.writes
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+}
+
+impl Transaction {
+    fn new(store: Arc<dyn ContextStore>) -> Self {
+        Self {
+            store,
+            state: Arc::new(Mutex::new(TxnState::default())),
+        }
+    }
+
+    /// 读取键，优先返回本事务中尚未提交的写入
+    pub async fn get(&self, key: &str) -> Result<Option<String>> {
+        {
+            let state = self.state.lock();
+            if let Some(value) = state.pending(key) {
+                return Ok(value.clone());
+            }
+            if let Some(value) = state.reads.get(key) {
+                return Ok(value.clone());
+            }
+        }
+        let value = self.store.get(key).await?;
+        self.state
+            .lock()
+            .reads
+            .entry(key.to_string())
+            .or_insert_with(|| value.clone());
+        Ok(value)
+    }
+
+    pub fn set(&self, key: impl Into<String>, value: impl Into<String>) {
+        self.state
+            .lock()
+            .writes
+            .push((key.into(), Some(value.into())));
+    }
+
+    pub fn delete(&self, key: impl Into<String>) {
+        self.state.lock().writes.push((key.into(), None));
+    }
+
+    /// 提交写入，发生冲突时返回 false（已写入的键会被回滚）
+    async fn commit(&self) -> Result<bool> {
+        let (reads, writes) = {
+            let state = self.state.lock();
+            let mut writes: Vec<(String, Option<String>)> = Vec::new();
+            for (key, value) in &state.writes {
+                match writes.iter_mut().find(|(k, _)| k == key) {
+                    Some(entry) => entry.1 = value.clone(),
+                    None => writes.push((key.clone(), value.clone())),
+                }
+            }
+            (state.reads.clone(), writes)
+        };
+
+        // 只读的键提交前再校验一次
+        for (key, seen) in &reads {
+            if writes.iter().any(|(k, _)| k == key) {
+                continue;
+            }
+            if self.store.get(key).await? != *seen {
+                return Ok(false);
+            }
+        }
+
+        let mut applied: Vec<(String, Option<String>, Option<String>)> = Vec::new();
+        for (key, value) in writes {
+            // 未读取过的键为盲写，以提交时的值为准
+            let expected = match reads.get(&key) {
+                Some(seen) => seen.clone(),
+                None => self.store.get(&key).await?,
+            };
+            if self
+                .store
+                .compare_and_set(&key, expected.as_deref(), value.clone())
+                .await?
+            {
+                applied.push((key, expected, value));
+                continue;
+            }
+            for (key, previous, written) in applied.into_iter().rev() {
+                self.store
+                    .compare_and_set(&key, written.as_deref(), previous)
+                    .await?;
+            }
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+/// 执行事务，冲突时重新执行 `f`，最多 `MAX_TRANSACTION_ATTEMPTS` 次
+pub(super) async fn run_transaction<F, Fut, T>(store: Arc<dyn ContextStore>, f: F) -> Result<T>
+where
+    F: Fn(Transaction) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    for attempt in 0..MAX_TRANSACTION_ATTEMPTS {
+        let txn = Transaction::new(Arc::clone(&store));
+        let value = f(txn.clone()).await?;
+        if txn.commit().await? {
+            return Ok(value);
+        }
+        tracing::debug!(attempt, "transaction conflict, retrying");
+        tokio::time::sleep(Duration::from_millis(1 << attempt.min(6))).await;
+    }
+    Err(AgentFlowError::Context(format!(
+        "transaction aborted after {} conflicting attempts",
+        MAX_TRANSACTION_ATTEMPTS
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::{ContextStore, FlowContext, MemoryStore};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    async fn increment(ctx: &FlowContext) {
+        ctx.transaction(|txn| async move {
+            let count: u64 = txn
+                .get("counter")
+                .await?
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(0);
+            tokio::task::yield_now().await;
+            txn.set("counter", (count + 1).to_string());
+            Ok(())
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_increments_do_not_lose_updates() {
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        let mut tasks = Vec::new();
+        for _ in 0..4 {
+            let ctx = Arc::clone(&ctx);
+            tasks.push(tokio::spawn(async move {
+                for _ in 0..10 {
+                    increment(&ctx).await;
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let value = ctx.store().get("counter").await.unwrap();
+        assert_eq!(value.as_deref(), Some("40"));
+    }
+
+    #[tokio::test]
+    async fn test_conflict_rolls_back_and_retries() {
+        let store = Arc::new(MemoryStore::new());
+        store.set("a", "1".to_string()).await.unwrap();
+        store.set("b", "1".to_string()).await.unwrap();
+        let ctx = FlowContext::new(store.clone());
+        let attempts = AtomicUsize::new(0);
+
+        ctx.transaction(|txn| {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            let store = store.clone();
+            async move {
+                let a = txn.get("a").await?.unwrap();
+                let b = txn.get("b").await?.unwrap();
+                if attempt == 0 {
+                    // 其他分支在读取后修改了 b
+                    store.set("b", "5".to_string()).await?;
+                }
+                txn.set("a", format!("{a}+"));
+                txn.set("b", format!("{b}+"));
+                Ok(())
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert_eq!(store.get("a").await.unwrap().as_deref(), Some("1+"));
+        assert_eq!(store.get("b").await.unwrap().as_deref(), Some("5+"));
+    }
+}
@@ -0,0 +1,416 @@
+//! 流程测试工具（`test-utils` feature）
+//!
+//! `FlowTestHarness` 用模拟的 LLM / Agent / 工具运行流程，返回的 `FlowTestRun` 提供对
+//! 经过的节点、最终消息和状态的断言：
+//!
+//! ```ignore
+//! let llm = MockLlmClient::new().on_contains("money back", "refund");
+//! let run = FlowTestHarness::from_config(&config, Arc::new(llm.clone()))?
+//!     .with_state("user.tier", "gold")
+//!     .with_tool_response("order_lookup", json!({ "status": "shipped" }))
+//!     .send(AgentMessage::user(json!({ "raw": "I want my money back", "steps": [] }).to_string()))
+//!     .await?;
+//! run.assert_visited(&["classify", "refund", "done"]);
+//! run.assert_payload("$.response", "refund");
+//! run.assert_state("refund.approved", "true").await;
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::agent::{register_agent, Agent, AgentAction, AgentContext, AgentMessage, AgentRegistry};
+use crate::error::{AgentFlowError, Result};
+use crate::flow::loader::{load_workflow_with_llm, WorkflowBundle};
+use crate::flow::FlowBuilder;
+use crate::llm::DynLlmClient;
+use crate::runtime::{
+    FlowEvent, FlowExecution, FlowExecutor, NodeInterceptor, Transcript, TranscriptRecorder,
+};
+use crate::state::{FlowContext, MemoryStore};
+use crate::tools::{Tool, ToolInvocation, ToolRegistry};
+use crate::utils::JsonPath;
+
+pub use crate::llm::MockLlmClient;
+
+/// 流程测试脚手架
+pub struct FlowTestHarness {
+    bundle: WorkflowBundle,
+    state: Vec<(String, String)>,
+}
+
+impl FlowTestHarness {
+    pub fn new(bundle: WorkflowBundle) -> Self {
+        Self {
+            bundle,
+            state: Vec::new(),
+        }
+    }
+
+    /// 加载工作流配置，Agent 使用 `llm`（通常是 `MockLlmClient`），不需要 API Key
+    pub fn from_config(config: &Value, llm: DynLlmClient) -> Result<Self> {
+        Ok(Self::new(load_workflow_with_llm(config, llm)?))
+    }
+
+    /// 运行前写入状态
+    pub fn with_state(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.state.push((key.into(), value.into()));
+        self
+    }
+
+    /// 替换（或新增）同名 Agent
+    pub fn with_agent(mut self, name: &str, agent: Arc<dyn Agent>) -> Self {
+        register_agent(name, agent, &mut self.bundle.agents);
+        self
+    }
+
+    /// 替换（或新增）同名工具
+    pub fn with_tool(mut self, tool: Arc<dyn Tool>) -> Self {
+        self.bundle.tools.register(tool);
+        self
+    }
+
+    /// 注册总是返回 `response` 的同名工具；字符串原样作为消息内容，其余序列化为 JSON
+    pub fn with_tool_response(self, name: &str, response: Value) -> Self {
+        self.with_tool(Arc::new(CannedTool {
+            name: Box::leak(name.to_string().into_boxed_str()),
+            response,
+        }))
+    }
+
+    /// 发送消息运行流程
+    pub async fn send(self, message: AgentMessage) -> Result<FlowTestRun> {
+        let ctx = Arc::new(FlowContext::new(Arc::new(MemoryStore::new())));
+        for (key, value) in self.state {
+            ctx.store().set(&key, value).await?;
+        }
+        let visited = Arc::new(Mutex::new(Vec::new()));
+        let recorder = TranscriptRecorder::new();
+        let executor = FlowExecutor::new(self.bundle.flow, self.bundle.agents, self.bundle.tools)
+            .with_interceptor(VisitRecorder(Arc::clone(&visited)))
+            .with_interceptor(recorder.clone());
+        let execution = executor.start(Arc::clone(&ctx), message).await?;
+        let visited = visited.lock().unwrap().clone();
+        Ok(FlowTestRun {
+            execution,
+            visited,
+            transcript: recorder.transcript(),
+            ctx,
+        })
+    }
+}
+
+/// 按执行顺序记录节点
+struct VisitRecorder(Arc<Mutex<Vec<String>>>);
+
+#[async_trait]
+impl NodeInterceptor for VisitRecorder {
+    async fn before_node(&self, event: &mut FlowEvent, _ctx: &FlowContext) -> Result<()> {
+        self.0.lock().unwrap().push(event.node.clone());
+        Ok(())
+    }
+}
+
+struct CannedTool {
+    name: &'static str,
+    response: Value,
+}
+
+#[async_trait]
+impl Tool for CannedTool {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn call(&self, _invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let content = match &self.response {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        Ok(AgentMessage::tool(self.name.to_string(), content))
+    }
+}
+
+/// 一次测试运行的结果
+pub struct FlowTestRun {
+    pub execution: FlowExecution,
+    /// 按执行顺序经过的节点
+    pub visited: Vec<String>,
+    /// 规范化的运行记录，用于黄金样本比较
+    pub transcript: Transcript,
+    pub ctx: Arc<FlowContext>,
+}
+
+impl FlowTestRun {
+    /// 最终消息；流程没有输出时 panic
+    #[track_caller]
+    pub fn final_message(&self) -> &AgentMessage {
+        self.execution
+            .last_message
+            .as_ref()
+            .expect("flow finished without a message")
+    }
+
+    /// 按 JSONPath 读取最终消息内容（解析为 JSON）中的值
+    #[track_caller]
+    pub fn payload(&self, path: &str) -> Option<Value> {
+        let content: Value = serde_json::from_str(&self.final_message().content).ok()?;
+        query(path, &content)
+    }
+
+    /// 按 JSONPath 读取最终消息 `metadata` 中的值
+    #[track_caller]
+    pub fn metadata(&self, path: &str) -> Option<Value> {
+        query(path, self.final_message().metadata.as_ref()?)
+    }
+
+    pub async fn state(&self, key: &str) -> Option<String> {
+        self.ctx.store().get(key).await.ok().flatten()
+    }
+
+    /// 断言经过的节点序列
+    #[track_caller]
+    pub fn assert_visited(&self, nodes: &[&str]) {
+        assert_eq!(self.visited, nodes, "unexpected node path");
+    }
+
+    #[track_caller]
+    pub fn assert_not_visited(&self, node: &str) {
+        assert!(
+            !self.visited.iter().any(|visited| visited == node),
+            "node `{}` was visited: {:?}",
+            node,
+            self.visited
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_payload(&self, path: &str, expected: impl Into<Value>) {
+        assert_eq!(
+            self.payload(path),
+            Some(expected.into()),
+            "payload `{}` of {:?}",
+            path,
+            self.final_message().content
+        );
+    }
+
+    /// `Flow::outputs` 声明的输出
+    pub fn output(&self, name: &str) -> Option<&Value> {
+        self.execution.outputs.get(name)
+    }
+
+    #[track_caller]
+    pub fn assert_output(&self, name: &str, expected: impl Into<Value>) {
+        assert_eq!(
+            self.output(name),
+            Some(&expected.into()),
+            "output `{}` of {:?}",
+            name,
+            self.execution.outputs
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_metadata(&self, path: &str, expected: impl Into<Value>) {
+        assert_eq!(
+            self.metadata(path),
+            Some(expected.into()),
+            "metadata `{}` of {:?}",
+            path,
+            self.final_message().metadata
+        );
+    }
+
+    /// 断言与黄金样本一致，见 `Transcript::compare_to_golden`
+    #[track_caller]
+    pub fn assert_matches_golden(&self, path: impl AsRef<std::path::Path>) {
+        let path = path.as_ref();
+        let differences = self
+            .transcript
+            .compare_to_golden(path)
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert!(
+            differences.is_empty(),
+            "run drifted from golden transcript `{}`:\n{}",
+            path.display(),
+            differences.join("\n")
+        );
+    }
+
+    pub async fn assert_state(&self, key: &str, expected: &str) {
+        assert_eq!(
+            self.state(key).await.as_deref(),
+            Some(expected),
+            "state `{}`",
+            key
+        );
+    }
+}
+
+/// 注册 `agents` 并由 `build` 添加节点，构建没有工具的执行器
+pub fn executor(
+    flow: &str,
+    agents: Vec<(&str, Arc<dyn Agent>)>,
+    build: impl FnOnce(&mut FlowBuilder),
+) -> FlowExecutor {
+    let mut registry = AgentRegistry::new();
+    for (name, agent) in agents {
+        register_agent(name, agent, &mut registry);
+    }
+    let mut builder = FlowBuilder::new(flow);
+    build(&mut builder);
+    FlowExecutor::new(builder.build(), registry, ToolRegistry::new())
+}
+
+/// 把收到的消息原样转到 `done` 节点；内容为 `boom` 时返回错误，为 `wait` 时一直挂起
+pub struct EchoAgent;
+
+#[async_trait]
+impl Agent for EchoAgent {
+    fn name(&self) -> &'static str {
+        "echo"
+    }
+
+    async fn on_message(
+        &self,
+        message: AgentMessage,
+        _ctx: &AgentContext<'_>,
+    ) -> Result<AgentAction> {
+        match message.content.as_str() {
+            "boom" => Err(AgentFlowError::Other(anyhow::anyhow!("echo failed"))),
+            "wait" => std::future::pending().await,
+            _ => Ok(AgentAction::Next {
+                target: "done".to_string(),
+                message,
+            }),
+        }
+    }
+}
+
+#[track_caller]
+fn query(path: &str, value: &Value) -> Option<Value> {
+    JsonPath::parse(path)
+        .unwrap_or_else(|e| panic!("{}", e))
+        .extract(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_harness_runs_config_with_mock_llm() {
+        let config = json!({
+            "agents": [{ "name": "classifier", "driver": "echo", "prompt": "classify the request" }],
+            "flow": {
+                "name": "support",
+                "start": "classify",
+                "outputs": [{ "name": "label", "from_message_path": "$.response", "schema": { "type": "string" } }],
+                "nodes": [
+                    { "kind": "agent", "name": "classify", "agent": "classifier" },
+                    { "kind": "terminal", "name": "done" }
+                ],
+                "transitions": [{ "from": "classify", "to": "done" }]
+            }
+        });
+        let llm = MockLlmClient::new().on_contains("money back", "refund");
+        let run = FlowTestHarness::from_config(&config, Arc::new(llm.clone()))
+            .unwrap()
+            .send(AgentMessage::user(
+                json!({ "raw": "I want my money back", "steps": [] }).to_string(),
+            ))
+            .await
+            .unwrap();
+        run.assert_visited(&["classify", "done"]);
+        run.assert_payload("$.response", "refund");
+        run.assert_output("label", "refund");
+        llm.assert_call_count(1);
+        llm.assert_called_with("classify the request");
+
+        let dir = tempfile::tempdir().unwrap();
+        let golden = dir.path().join("support.json");
+        run.transcript.save(&golden).unwrap();
+        run.assert_matches_golden(&golden);
+        assert_eq!(run.transcript.nodes(), vec!["classify", "done"]);
+    }
+
+    struct Lookup;
+
+    #[async_trait]
+    impl Agent for Lookup {
+        fn name(&self) -> &'static str {
+            "lookup"
+        }
+
+        async fn on_message(
+            &self,
+            message: AgentMessage,
+            ctx: &AgentContext<'_>,
+        ) -> Result<AgentAction> {
+            let tier = ctx
+                .flow_ctx
+                .store()
+                .get("user.tier")
+                .await?
+                .unwrap_or_default();
+            let order = ctx
+                .runtime
+                .call_tool(
+                    "order_lookup",
+                    ToolInvocation::new("order_lookup", json!({})),
+                )
+                .await?;
+            ctx.flow_ctx
+                .store()
+                .set("lookup.done", "yes".into())
+                .await?;
+            let mut reply = AgentMessage::system(
+                json!({
+                    "question": message.content,
+                    "tier": tier,
+                    "order": serde_json::from_str::<Value>(&order.content).unwrap(),
+                })
+                .to_string(),
+            );
+            reply.metadata = Some(json!({ "source": "lookup" }));
+            Ok(AgentAction::Finish {
+                message: Some(reply),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_harness_assertions() {
+        let mut builder = FlowBuilder::new("orders");
+        builder
+            .add_agent_node("lookup", "lookup")
+            .add_terminal_node("done")
+            .connect("lookup", "done")
+            .set_start("lookup");
+        let bundle = WorkflowBundle {
+            flow: builder.build(),
+            agents: AgentRegistry::new(),
+            tools: ToolRegistry::new(),
+        };
+        let run = FlowTestHarness::new(bundle)
+            .with_agent("lookup", Arc::new(Lookup))
+            .with_state("user.tier", "gold")
+            .with_tool_response(
+                "order_lookup",
+                json!({ "status": "shipped", "items": [1, 2] }),
+            )
+            .send(AgentMessage::user("where is my order"))
+            .await
+            .unwrap();
+        run.assert_visited(&["lookup"]);
+        run.assert_not_visited("done");
+        run.assert_payload("$.tier", "gold");
+        run.assert_payload("$.order.items[-1]", 2);
+        run.assert_metadata("source", "lookup");
+        assert_eq!(run.payload("$.missing"), None);
+        run.assert_state("lookup.done", "yes").await;
+    }
+}
@@ -0,0 +1,396 @@
+//! 语音工具 - `audio.transcribe` / `audio.speak`（内置工具）
+//!
+//! 通过 `LlmClient::transcribe` / `LlmClient::speak` 调用 OpenAI 或 DashScope 音频接口。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::agent::{AgentMessage, Attachment, BlobData, MessageRole};
+use crate::config::EnvConfig;
+use crate::error::{AgentFlowError, Result};
+use crate::llm::{DynLlmClient, MediaSource, SpeechRequest, TranscriptionRequest};
+use crate::state::{BlobStore, FlowContext};
+use crate::tools::tool::{Tool, ToolInvocation};
+
+/// 音频服务提供商
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioProvider {
+    OpenAI,
+    DashScope,
+}
+
+impl AudioProvider {
+    pub fn default_endpoint(&self) -> &'static str {
+        match self {
+            AudioProvider::OpenAI => "https://api.openai.com/v1",
+            AudioProvider::DashScope => {
+                "https://dashscope.aliyuncs.com/api/v1/services/aigc/multimodal-generation/generation"
+            }
+        }
+    }
+
+    /// 未配置 api_key 时读取的环境变量
+    pub fn api_key_env(&self) -> &'static str {
+        match self {
+            AudioProvider::OpenAI => "OPENAI_API_KEY",
+            AudioProvider::DashScope => "DASHSCOPE_API_KEY",
+        }
+    }
+
+    pub fn default_transcription_model(&self) -> &'static str {
+        match self {
+            AudioProvider::OpenAI => "whisper-1",
+            AudioProvider::DashScope => "qwen3-asr-flash",
+        }
+    }
+
+    pub fn default_speech_model(&self) -> &'static str {
+        match self {
+            AudioProvider::OpenAI => "tts-1",
+            AudioProvider::DashScope => "qwen-tts",
+        }
+    }
+}
+
+/// 语音工具配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AudioToolConfig {
+    pub provider: AudioProvider,
+    /// API Key，支持 `${VAR_NAME}`；为空时读取提供商对应的环境变量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// 自定义接口地址（代理或私有部署）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// 模型，不设置时使用提供商的默认识别 / 合成模型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 音频附件使用的本地 blob 目录，不设置时合成的音频内联在消息附件中
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob_dir: Option<String>,
+}
+
+impl AudioToolConfig {
+    pub fn new(provider: AudioProvider) -> Self {
+        Self {
+            provider,
+            api_key: None,
+            endpoint: None,
+            model: None,
+            blob_dir: None,
+        }
+    }
+
+    /// 按 DashScope / OpenAI 顺序选择已配置 API Key 的提供商
+    pub fn from_env() -> Option<Self> {
+        [AudioProvider::DashScope, AudioProvider::OpenAI]
+            .into_iter()
+            .find(|provider| EnvConfig::get_env_optional(provider.api_key_env()).is_some())
+            .map(Self::new)
+    }
+
+    /// 解析 API Key：`${VAR_NAME}` 读取环境变量，未配置时读取提供商默认变量
+    pub fn resolve_api_key(&self) -> Result<String> {
+        match &self.api_key {
+            Some(value) if value.starts_with("${") && value.ends_with('}') => {
+                EnvConfig::get_env(&value[2..value.len() - 1])
+            }
+            Some(value) => Ok(value.clone()),
+            None => EnvConfig::get_env(self.provider.api_key_env()),
+        }
+    }
+
+    /// 按提供商创建同时配置了识别与合成模型的客户端
+    #[cfg(feature = "openai-client")]
+    pub fn build_client(&self) -> Result<DynLlmClient> {
+        use crate::llm::{ApiFormat, GenericHttpClient};
+
+        let format = match self.provider {
+            AudioProvider::OpenAI => ApiFormat::OpenAI,
+            AudioProvider::DashScope => ApiFormat::Qwen,
+        };
+        let endpoint = self
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| self.provider.default_endpoint().to_string());
+        let transcription_model = self
+            .model
+            .clone()
+            .unwrap_or_else(|| self.provider.default_transcription_model().to_string());
+        let speech_model = self
+            .model
+            .clone()
+            .unwrap_or_else(|| self.provider.default_speech_model().to_string());
+        let client = GenericHttpClient::new(endpoint, self.resolve_api_key()?, "", format)
+            .with_transcription_model(transcription_model)
+            .with_speech_model(speech_model);
+        Ok(Arc::new(client))
+    }
+
+    pub fn blob_store(&self) -> Option<Arc<dyn BlobStore>> {
+        self.blob_dir
+            .as_ref()
+            .map(|dir| Arc::new(crate::state::LocalBlobStore::new(dir)) as Arc<dyn BlobStore>)
+    }
+}
+
+/// 语音识别工具
+///
+/// 输入参数（音频来源任选其一）：
+/// - url: 音频地址
+/// - audio: base64 音频，配合 `mime_type`
+/// - blob: `BlobStore` 中的 id，配合 `mime_type`
+/// - attachment: 序列化的音频附件
+/// - language / prompt: 可选
+///
+/// 输出：`text` 为识别结果。
+#[derive(Clone)]
+pub struct AudioTranscribeTool {
+    client: DynLlmClient,
+    blob_store: Option<Arc<dyn BlobStore>>,
+}
+
+impl AudioTranscribeTool {
+    pub fn new(client: DynLlmClient) -> Self {
+        Self {
+            client,
+            blob_store: None,
+        }
+    }
+
+    /// 用于读取 `blob` 引用的音频
+    pub fn with_blob_store(mut self, store: Arc<dyn BlobStore>) -> Self {
+        self.blob_store = Some(store);
+        self
+    }
+
+    async fn source(&self, input: &Value) -> Result<MediaSource> {
+        if let Some(url) = input["url"].as_str() {
+            return Ok(MediaSource::Url {
+                url: url.to_string(),
+            });
+        }
+        let mime_type = input["mime_type"].as_str().unwrap_or("audio/mpeg");
+        if let Some(data) = input["audio"].as_str() {
+            return Ok(MediaSource::Base64 {
+                mime_type: mime_type.to_string(),
+                data: data.to_string(),
+            });
+        }
+        let attachment = if let Some(id) = input["blob"].as_str() {
+            Attachment::Audio {
+                mime_type: mime_type.to_string(),
+                data: BlobData::Blob(id.to_string()),
+            }
+        } else if !input["attachment"].is_null() {
+            serde_json::from_value(input["attachment"].clone())
+                .map_err(|e| AgentFlowError::Serialization(e.to_string()))?
+        } else {
+            return Err(AgentFlowError::Other(anyhow::anyhow!(
+                "Missing audio source (url, audio, blob or attachment)"
+            )));
+        };
+        if let Attachment::ImageUrl { .. } | Attachment::ImageBytes { .. } = attachment {
+            return Err(AgentFlowError::Other(anyhow::anyhow!(
+                "Attachment is not audio"
+            )));
+        }
+        let bytes = attachment.load(self.blob_store.as_deref()).await?;
+        Ok(MediaSource::from_bytes(
+            attachment.mime_type().unwrap_or(mime_type),
+            &bytes,
+        ))
+    }
+}
+
+#[async_trait]
+impl Tool for AudioTranscribeTool {
+    fn name(&self) -> &'static str {
+        "audio.transcribe"
+    }
+
+    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let input = &invocation.input;
+        let mut request = TranscriptionRequest::new(self.source(input).await?);
+        if let Some(language) = input["language"].as_str() {
+            request = request.with_language(language);
+        }
+        if let Some(prompt) = input["prompt"].as_str() {
+            request = request.with_prompt(prompt);
+        }
+        let text = self.client.transcribe(request).await?;
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name().to_string(),
+            to: None,
+            content: json!({ "success": true, "text": text }).to_string(),
+            metadata: invocation.metadata,
+            attachments: Vec::new(),
+        })
+    }
+}
+
+/// 语音合成工具
+///
+/// 输入参数：
+/// - text: 要合成的文本
+/// - voice / format: 可选
+///
+/// 输出：音频作为消息附件返回；配置了 `BlobStore` 时附件只保存 blob id。
+#[derive(Clone)]
+pub struct AudioSpeakTool {
+    client: DynLlmClient,
+    blob_store: Option<Arc<dyn BlobStore>>,
+}
+
+impl AudioSpeakTool {
+    pub fn new(client: DynLlmClient) -> Self {
+        Self {
+            client,
+            blob_store: None,
+        }
+    }
+
+    /// 合成的音频写入 `store`
+    pub fn with_blob_store(mut self, store: Arc<dyn BlobStore>) -> Self {
+        self.blob_store = Some(store);
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for AudioSpeakTool {
+    fn name(&self) -> &'static str {
+        "audio.speak"
+    }
+
+    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let input = &invocation.input;
+        let text = input["text"]
+            .as_str()
+            .or_else(|| input["content"].as_str())
+            .filter(|text| !text.trim().is_empty())
+            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing text")))?;
+        let mut request = SpeechRequest::new(text);
+        if let Some(voice) = input["voice"].as_str() {
+            request = request.with_voice(voice);
+        }
+        if let Some(format) = input["format"].as_str() {
+            request = request.with_format(format);
+        }
+        let audio = self.client.speak(request).await?;
+
+        let bytes = audio.data.len();
+        let mut attachment = Attachment::audio(audio.mime_type.clone(), audio.data);
+        if let Some(store) = &self.blob_store {
+            attachment = attachment.offload(store.as_ref()).await?;
+        }
+        let blob = match attachment.data() {
+            Some(BlobData::Blob(id)) => Some(id.clone()),
+            _ => None,
+        };
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name().to_string(),
+            to: None,
+            content: json!({
+                "success": true,
+                "mime_type": audio.mime_type,
+                "bytes": bytes,
+                "blob": blob,
+            })
+            .to_string(),
+            metadata: invocation.metadata,
+            attachments: vec![attachment],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{LlmClient, LlmRequest, LlmResponse, SpeechAudio};
+    use crate::state::{LocalBlobStore, MemoryStore};
+
+    /// 识别结果回显音频 data URL 与语言，合成结果为文本字节
+    struct FakeAudioClient;
+
+    #[async_trait]
+    impl LlmClient for FakeAudioClient {
+        async fn complete(&self, _request: LlmRequest) -> Result<LlmResponse> {
+            unreachable!()
+        }
+
+        async fn transcribe(&self, request: TranscriptionRequest) -> Result<String> {
+            Ok(format!(
+                "{} {}",
+                request.audio.to_url(),
+                request.language.unwrap_or_default()
+            ))
+        }
+
+        async fn speak(&self, request: SpeechRequest) -> Result<SpeechAudio> {
+            Ok(SpeechAudio {
+                mime_type: "audio/mpeg".to_string(),
+                data: request.text.into_bytes(),
+            })
+        }
+
+        fn clone_dyn(&self) -> DynLlmClient {
+            Arc::new(FakeAudioClient)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_speak_then_transcribe_via_blob_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::new(dir.path()));
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+
+        let speak = AudioSpeakTool::new(Arc::new(FakeAudioClient)).with_blob_store(store.clone());
+        let spoken = speak
+            .call(
+                ToolInvocation::new("audio.speak", json!({ "text": "hi" })),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        let result: Value = serde_json::from_str(&spoken.content).unwrap();
+        assert_eq!(result["bytes"], 2);
+        let blob = result["blob"].as_str().unwrap();
+        assert_eq!(
+            spoken.attachments[0]
+                .load(Some(store.as_ref()))
+                .await
+                .unwrap(),
+            b"hi"
+        );
+
+        let transcribe = AudioTranscribeTool::new(Arc::new(FakeAudioClient)).with_blob_store(store);
+        let heard = transcribe
+            .call(
+                ToolInvocation::new(
+                    "audio.transcribe",
+                    json!({ "blob": blob, "mime_type": "audio/mpeg", "language": "en" }),
+                ),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        let result: Value = serde_json::from_str(&heard.content).unwrap();
+        assert_eq!(result["text"], "data:audio/mpeg;base64,aGk= en");
+
+        assert!(transcribe
+            .call(ToolInvocation::new("audio.transcribe", json!({})), &ctx)
+            .await
+            .is_err());
+    }
+}
@@ -27,6 +27,7 @@ impl Tool for EchoTool {
                 .map(|s| s.to_string()),
             content: format!("Echo: {}", invocation.input),
             metadata: invocation.metadata,
+            attachments: Vec::new(),
         })
     }
 }
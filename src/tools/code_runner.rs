@@ -0,0 +1,414 @@
+//! 代码执行工具 - 在临时目录中编译/运行 Rust、Python 代码片段（内置工具）
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::process::Command;
+
+use crate::agent::{AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::state::FlowContext;
+use crate::tools::shell::{apply_env, run_process, ProcessOutput};
+use crate::tools::tool::{Tool, ToolInvocation};
+
+/// 支持的语言
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CodeLanguage {
+    #[serde(alias = "rs")]
+    Rust,
+    #[serde(alias = "py", alias = "python3")]
+    Python,
+}
+
+impl CodeLanguage {
+    fn source_file(&self) -> &'static str {
+        match self {
+            CodeLanguage::Rust => "main.rs",
+            CodeLanguage::Python => "main.py",
+        }
+    }
+}
+
+/// 代码执行工具配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CodeRunConfig {
+    #[serde(default = "CodeRunConfig::default_rustc")]
+    pub rustc: String,
+    #[serde(default = "CodeRunConfig::default_python")]
+    pub python: String,
+    /// 允许执行的语言（为空时全部允许）
+    #[serde(default)]
+    pub languages: Vec<CodeLanguage>,
+    /// 临时目录的父目录（默认系统临时目录）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub work_dir: Option<String>,
+    #[serde(default = "CodeRunConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "CodeRunConfig::default_compile_timeout_secs")]
+    pub compile_timeout_secs: u64,
+    /// 运行阶段的内存上限（MB，仅 Unix，通过 `ulimit -v` 限制；`null` 表示不限制）
+    #[serde(default = "CodeRunConfig::default_memory_limit_mb")]
+    pub memory_limit_mb: Option<u64>,
+    /// 运行阶段的 CPU 时间上限（秒，仅 Unix，通过 `ulimit -t` 限制；`null` 表示不限制）
+    #[serde(default = "CodeRunConfig::default_cpu_time_secs")]
+    pub cpu_time_secs: Option<u64>,
+    #[serde(default = "CodeRunConfig::default_max_code_bytes")]
+    pub max_code_bytes: usize,
+    #[serde(default = "CodeRunConfig::default_max_output_bytes")]
+    pub max_output_bytes: usize,
+    #[serde(default = "CodeRunConfig::default_env_whitelist")]
+    pub env_whitelist: Vec<String>,
+}
+
+impl CodeRunConfig {
+    fn default_rustc() -> String {
+        "rustc".to_string()
+    }
+
+    fn default_python() -> String {
+        "python3".to_string()
+    }
+
+    fn default_timeout_secs() -> u64 {
+        10
+    }
+
+    fn default_compile_timeout_secs() -> u64 {
+        60
+    }
+
+    fn default_memory_limit_mb() -> Option<u64> {
+        Some(512)
+    }
+
+    fn default_cpu_time_secs() -> Option<u64> {
+        Some(10)
+    }
+
+    fn default_max_code_bytes() -> usize {
+        100 * 1024
+    }
+
+    fn default_max_output_bytes() -> usize {
+        64 * 1024
+    }
+
+    fn default_env_whitelist() -> Vec<String> {
+        [
+            "PATH",
+            "HOME",
+            "LANG",
+            "RUSTUP_HOME",
+            "RUSTUP_TOOLCHAIN",
+            "CARGO_HOME",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+}
+
+impl Default for CodeRunConfig {
+    fn default() -> Self {
+        Self {
+            rustc: Self::default_rustc(),
+            python: Self::default_python(),
+            languages: Vec::new(),
+            work_dir: None,
+            timeout_secs: Self::default_timeout_secs(),
+            compile_timeout_secs: Self::default_compile_timeout_secs(),
+            memory_limit_mb: Self::default_memory_limit_mb(),
+            cpu_time_secs: Self::default_cpu_time_secs(),
+            max_code_bytes: Self::default_max_code_bytes(),
+            max_output_bytes: Self::default_max_output_bytes(),
+            env_whitelist: Self::default_env_whitelist(),
+        }
+    }
+}
+
+/// 代码执行工具
+///
+/// 输入参数：
+/// - language: `rust` / `python`
+/// - code: 源码
+/// - stdin: 标准输入（可选）
+/// - args: 程序参数（可选）
+///
+/// 输出：`success`、`stage`（`compile` / `run`）及各阶段的 `exit_code`、`stdout`、`stderr`。
+#[derive(Clone, Default)]
+pub struct CodeRunTool {
+    config: CodeRunConfig,
+}
+
+impl CodeRunTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: CodeRunConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &CodeRunConfig {
+        &self.config
+    }
+
+    fn command(&self, program: &str, dir: &Path) -> Command {
+        let mut command = Command::new(program);
+        apply_env(&mut command, &self.config.env_whitelist, &HashMap::new());
+        command.current_dir(dir);
+        command
+    }
+
+    /// 运行阶段命令：Unix 下通过 sh 设置 ulimit 后 exec 目标程序
+    fn limited_command(&self, program: &str, args: &[String], dir: &Path) -> Command {
+        let mut limits = Vec::new();
+        if cfg!(unix) {
+            if let Some(mb) = self.config.memory_limit_mb {
+                limits.push(format!("ulimit -v {}", mb * 1024));
+            }
+            if let Some(secs) = self.config.cpu_time_secs {
+                limits.push(format!("ulimit -t {}", secs));
+            }
+        }
+
+        if limits.is_empty() {
+            let mut command = self.command(program, dir);
+            command.args(args);
+            return command;
+        }
+
+        let script = format!("{}; exec \"$0\" \"$@\"", limits.join("; "));
+        let mut command = self.command("sh", dir);
+        command.arg("-c").arg(script).arg(program).args(args);
+        command
+    }
+
+    async fn execute(
+        &self,
+        language: CodeLanguage,
+        dir: &Path,
+        args: &[String],
+        stdin: Option<String>,
+    ) -> Result<(Option<ProcessOutput>, ProcessOutput)> {
+        let source = dir.join(language.source_file());
+        let run_timeout = Duration::from_secs(self.config.timeout_secs.max(1));
+
+        match language {
+            CodeLanguage::Rust => {
+                let binary = dir.join("main");
+                let mut compile = self.command(&self.config.rustc, dir);
+                compile
+                    .arg("--edition")
+                    .arg("2021")
+                    .arg("-o")
+                    .arg(&binary)
+                    .arg(&source);
+                let compiled = run_process(
+                    compile,
+                    &self.config.rustc,
+                    None,
+                    Duration::from_secs(self.config.compile_timeout_secs.max(1)),
+                    self.config.max_output_bytes,
+                )
+                .await?;
+                if !compiled.success() {
+                    return Ok((Some(compiled), ProcessOutput::default()));
+                }
+
+                let program = binary.to_string_lossy().to_string();
+                let run = self.limited_command(&program, args, dir);
+                let output = run_process(
+                    run,
+                    &program,
+                    stdin,
+                    run_timeout,
+                    self.config.max_output_bytes,
+                )
+                .await?;
+                Ok((Some(compiled), output))
+            }
+            CodeLanguage::Python => {
+                let mut run_args = vec![source.to_string_lossy().to_string()];
+                run_args.extend(args.iter().cloned());
+                let run = self.limited_command(&self.config.python, &run_args, dir);
+                let output = run_process(
+                    run,
+                    &self.config.python,
+                    stdin,
+                    run_timeout,
+                    self.config.max_output_bytes,
+                )
+                .await?;
+                Ok((None, output))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for CodeRunTool {
+    fn name(&self) -> &'static str {
+        "code.run"
+    }
+
+    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let input = &invocation.input;
+        let language: CodeLanguage = serde_json::from_value(input["language"].clone())
+            .map_err(|e| AgentFlowError::Other(anyhow::anyhow!("Invalid language: {}", e)))?;
+        if !self.config.languages.is_empty() && !self.config.languages.contains(&language) {
+            return Err(AgentFlowError::Other(anyhow::anyhow!(
+                "Language {:?} is not enabled for code.run",
+                language
+            )));
+        }
+
+        let code = input["code"]
+            .as_str()
+            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing code")))?;
+        if code.len() > self.config.max_code_bytes {
+            return Err(AgentFlowError::Other(anyhow::anyhow!(
+                "Code size {} exceeds limit {}",
+                code.len(),
+                self.config.max_code_bytes
+            )));
+        }
+        let args: Vec<String> = input["args"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .map(|v| {
+                        v.as_str()
+                            .map(|s| s.to_string())
+                            .unwrap_or_else(|| v.to_string())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let stdin = input["stdin"].as_str().map(|s| s.to_string());
+
+        let base = self
+            .config
+            .work_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        let dir = base.join(format!("agentflow-code-{}", crate::agent::message::uuid()));
+        std::fs::create_dir_all(&dir)
+            .and_then(|_| std::fs::write(dir.join(language.source_file()), code))
+            .map_err(|e| {
+                AgentFlowError::Other(anyhow::anyhow!("Failed to prepare code.run dir: {}", e))
+            })?;
+
+        let executed = self.execute(language, &dir, &args, stdin).await;
+        if let Err(err) = std::fs::remove_dir_all(&dir) {
+            tracing::warn!(%err, dir = %dir.display(), "Failed to clean up code.run dir");
+        }
+        let (compile, run) = executed?;
+
+        let compile_failed = compile.as_ref().is_some_and(|c| !c.success());
+        let result = json!({
+            "success": !compile_failed && run.success(),
+            "language": language,
+            "stage": if compile_failed { "compile" } else { "run" },
+            "compile": compile.as_ref().map(|c| c.to_json()),
+            "run": if compile_failed { Value::Null } else { run.to_json() },
+        });
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name().to_string(),
+            to: None,
+            content: result.to_string(),
+            metadata: invocation.metadata,
+            attachments: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryStore;
+    use std::sync::Arc;
+
+    async fn run(tool: &CodeRunTool, input: Value) -> Result<Value> {
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+        let message = tool
+            .call(ToolInvocation::new("code.run", input), &ctx)
+            .await?;
+        Ok(serde_json::from_str(&message.content).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_run_python_snippet() {
+        let tool = CodeRunTool::new();
+        let result = run(
+            &tool,
+            json!({
+                "language": "python",
+                "code": "import sys\nprint(sys.stdin.read().upper())\nsys.exit(2)",
+                "stdin": "hi"
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result["stage"], "run");
+        assert_eq!(result["run"]["stdout"], "HI\n");
+        assert_eq!(result["run"]["exit_code"], 2);
+        assert_eq!(result["success"], false);
+    }
+
+    #[tokio::test]
+    async fn test_rust_compile_error_and_limits() {
+        let tool = CodeRunTool::new();
+        let result = run(
+            &tool,
+            json!({ "language": "rust", "code": "fn main() { let x: u8 = \"no\"; }" }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result["stage"], "compile");
+        assert_eq!(result["success"], false);
+        assert!(result["compile"]["stderr"]
+            .as_str()
+            .unwrap()
+            .contains("mismatched types"));
+
+        let tool = CodeRunTool::with_config(CodeRunConfig {
+            languages: vec![CodeLanguage::Rust],
+            ..CodeRunConfig::default()
+        });
+        assert!(
+            run(&tool, json!({ "language": "python", "code": "print(1)" }))
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_config_defaults_keep_resource_limits() {
+        let config: CodeRunConfig = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(
+            config.memory_limit_mb,
+            CodeRunConfig::default().memory_limit_mb
+        );
+        assert_eq!(config.cpu_time_secs, CodeRunConfig::default().cpu_time_secs);
+        assert!(config.memory_limit_mb.is_some() && config.cpu_time_secs.is_some());
+
+        let config: CodeRunConfig =
+            serde_json::from_value(json!({ "memory_limit_mb": null, "cpu_time_secs": 2 })).unwrap();
+        assert_eq!(config.memory_limit_mb, None);
+        assert_eq!(config.cpu_time_secs, Some(2));
+        let round_trip: CodeRunConfig =
+            serde_json::from_value(serde_json::to_value(&config).unwrap()).unwrap();
+        assert_eq!(round_trip.memory_limit_mb, None);
+    }
+}
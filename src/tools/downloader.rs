@@ -202,6 +202,7 @@ impl Tool for DownloaderTool {
             to: None,
             content: result.to_string(),
             metadata: None,
+            attachments: Vec::new(),
         })
     }
 }
@@ -74,7 +74,7 @@ pub fn register_builtin_tool_factories(registry: &mut ToolFactoryRegistry) {
                 0.2
             }
             let conf: Conf = extract_config(config)?;
-            let client: DynLlmClient = Arc::new(LocalEchoClient::default());
+            let client: DynLlmClient = Arc::new(LocalEchoClient);
             Ok(Arc::new(LlmTool::new(
                 "llm.local_echo",
                 client,
@@ -95,8 +95,145 @@ pub fn register_builtin_tool_factories(registry: &mut ToolFactoryRegistry) {
     
     registry.register_factory(
         "image_generator",
-        Arc::new(|_config| {
-            Ok(Arc::new(crate::tools::ImageGeneratorTool::new()) as Arc<dyn Tool>)
+        Arc::new(|config| {
+            let tool = match config {
+                Some(config) => {
+                    crate::tools::ImageGeneratorTool::with_config(extract_config(Some(config))?)
+                }
+                None => crate::tools::ImageGeneratorTool::new(),
+            };
+            Ok(Arc::new(tool) as Arc<dyn Tool>)
+        }),
+    );
+
+    registry.register_factory(
+        "video.generate",
+        Arc::new(|config| {
+            let conf = match config {
+                Some(config) => extract_config(Some(config))?,
+                None => crate::media::VideoGenConfig::from_env().ok_or_else(|| {
+                    crate::error::AgentFlowError::Other(anyhow!(
+                        "video.generate requires a provider (dashscope/openai) and API key"
+                    ))
+                })?,
+            };
+            Ok(
+                Arc::new(crate::tools::VideoGenerateTool::new(conf.build_client()?))
+                    as Arc<dyn Tool>,
+            )
+        }),
+    );
+
+    #[cfg(feature = "openai-client")]
+    {
+        fn audio_config(
+            name: &str,
+            config: Option<Value>,
+        ) -> Result<crate::tools::AudioToolConfig> {
+            match config {
+                Some(config) => extract_config(Some(config)),
+                None => crate::tools::AudioToolConfig::from_env().ok_or_else(|| {
+                    crate::error::AgentFlowError::Other(anyhow!(
+                        "{} requires a provider (openai/dashscope) and API key",
+                        name
+                    ))
+                }),
+            }
+        }
+
+        registry.register_factory(
+            "audio.transcribe",
+            Arc::new(|config| {
+                let conf = audio_config("audio.transcribe", config)?;
+                let mut tool = crate::tools::AudioTranscribeTool::new(conf.build_client()?);
+                if let Some(store) = conf.blob_store() {
+                    tool = tool.with_blob_store(store);
+                }
+                Ok(Arc::new(tool) as Arc<dyn Tool>)
+            }),
+        );
+
+        registry.register_factory(
+            "audio.speak",
+            Arc::new(|config| {
+                let conf = audio_config("audio.speak", config)?;
+                let mut tool = crate::tools::AudioSpeakTool::new(conf.build_client()?);
+                if let Some(store) = conf.blob_store() {
+                    tool = tool.with_blob_store(store);
+                }
+                Ok(Arc::new(tool) as Arc<dyn Tool>)
+            }),
+        );
+    }
+
+    registry.register_factory(
+        "http.request",
+        Arc::new(|config| {
+            let conf: crate::tools::HttpRequestConfig = extract_config(config)?;
+            Ok(Arc::new(crate::tools::HttpRequestTool::with_config(conf)?) as Arc<dyn Tool>)
+        }),
+    );
+
+    registry.register_factory(
+        "web.crawl",
+        Arc::new(|config| {
+            let conf: crate::tools::WebCrawlerConfig = extract_config(config)?;
+            Ok(Arc::new(crate::tools::WebCrawlerTool::with_config(conf)) as Arc<dyn Tool>)
+        }),
+    );
+
+    registry.register_factory(
+        "web.search",
+        Arc::new(|config| {
+            let conf: crate::tools::WebSearchConfig = match config {
+                Some(config) => extract_config(Some(config))?,
+                None => crate::tools::WebSearchConfig::from_env().ok_or_else(|| {
+                    crate::error::AgentFlowError::Other(anyhow!(
+                        "web.search requires a provider (bing/serper/tavily) and API key"
+                    ))
+                })?,
+            };
+            Ok(Arc::new(crate::tools::WebSearchTool::new(conf)) as Arc<dyn Tool>)
+        }),
+    );
+
+    registry.register_factory(
+        "web.read",
+        Arc::new(|config| {
+            let conf: crate::tools::WebReadConfig = extract_config(config)?;
+            Ok(Arc::new(crate::tools::WebReadTool::with_config(conf)) as Arc<dyn Tool>)
+        }),
+    );
+
+    registry.register_factory(
+        "rag.retrieve",
+        Arc::new(|config| {
+            let conf: crate::tools::vector::RagRetrieveToolConfig = extract_config(config)?;
+            Ok(Arc::new(conf.build()?) as Arc<dyn Tool>)
+        }),
+    );
+
+    registry.register_factory(
+        "rag.ingest",
+        Arc::new(|config| {
+            let conf: crate::ingest::tool::IngestFactoryConfig = extract_config(config)?;
+            Ok(Arc::new(conf.build()?) as Arc<dyn Tool>)
+        }),
+    );
+
+    registry.register_factory(
+        "shell.exec",
+        Arc::new(|config| {
+            let conf: crate::tools::ShellExecConfig = extract_config(config)?;
+            Ok(Arc::new(crate::tools::ShellExecTool::with_config(conf)) as Arc<dyn Tool>)
+        }),
+    );
+
+    registry.register_factory(
+        "code.run",
+        Arc::new(|config| {
+            let conf: crate::tools::CodeRunConfig = extract_config(config)?;
+            Ok(Arc::new(crate::tools::CodeRunTool::with_config(conf)) as Arc<dyn Tool>)
         }),
     );
 }
@@ -128,6 +265,7 @@ impl Tool for EchoToolWithPrefix {
                 .map(|s| s.to_string()),
             content: format!("{}: {}", self.prefix, invocation.input),
             metadata: invocation.metadata,
+            attachments: Vec::new(),
         })
     }
 }
@@ -185,8 +323,8 @@ impl Tool for LlmTool {
             user: user_input,
             temperature: self.config.temperature,
             metadata: metadata.clone(),
-            image_url: None,
-            image_base64: None,
+            content: Vec::new(),
+            params: Default::default(),
         };
         let response = self.client.complete(request).await?;
         let content = response.content.clone();
@@ -201,6 +339,7 @@ impl Tool for LlmTool {
             to: None,
             content,
             metadata: response.metadata.or(metadata),
+            attachments: Vec::new(),
         })
     }
 }
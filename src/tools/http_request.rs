@@ -0,0 +1,603 @@
+//! HTTP 请求工具 - 声明式配置的通用 HTTP 调用（内置工具）
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::agent::{AgentMessage, MessageRole};
+use crate::config::EnvConfig;
+use crate::error::{AgentFlowError, Result};
+use crate::llm::http::body::read_body;
+use crate::llm::http::DEFAULT_MAX_RESPONSE_BYTES;
+use crate::state::FlowContext;
+use crate::tools::manifest::{ToolManifest, ToolPort, ToolPortSchema};
+use crate::tools::tool::{Tool, ToolInvocation};
+use crate::utils::json_path::JsonPath;
+
+/// 认证方式
+///
+/// 凭据支持 `${VAR_NAME}` 形式引用环境变量
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HttpAuth {
+    Bearer { token: String },
+    Basic { username: String, password: String },
+    Header { name: String, value: String },
+    Query { name: String, value: String },
+}
+
+/// HTTP 请求工具配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HttpRequestConfig {
+    /// 注册名（默认 `http.request`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default = "HttpRequestConfig::default_method")]
+    pub method: String,
+    /// URL 模板，支持 `{{field}}` 占位符（取自调用输入，替换时做 URL 编码）
+    ///
+    /// 配置后调用输入不能再覆盖 `url`；`auth` 只发送给与此 URL 相同的主机
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub query: HashMap<String, String>,
+    /// 请求体模板，字符串中的 `{{field}}` 会被替换
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+    /// 认证凭据，仅在请求主机与 `url` 的主机一致时附加
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<HttpAuth>,
+    /// 响应提取规则：输出字段 -> JSONPath
+    #[serde(default)]
+    pub extract: HashMap<String, String>,
+    #[serde(default = "HttpRequestConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// 非 2xx 状态码时返回错误（默认 true）
+    #[serde(default = "HttpRequestConfig::default_fail_on_status")]
+    pub fail_on_status: bool,
+    /// 响应体大小上限（字节）
+    #[serde(default = "HttpRequestConfig::default_max_response_bytes")]
+    pub max_response_bytes: usize,
+}
+
+impl HttpRequestConfig {
+    fn default_method() -> String {
+        "GET".to_string()
+    }
+
+    fn default_timeout_secs() -> u64 {
+        30
+    }
+
+    fn default_fail_on_status() -> bool {
+        true
+    }
+
+    fn default_max_response_bytes() -> usize {
+        DEFAULT_MAX_RESPONSE_BYTES
+    }
+}
+
+impl Default for HttpRequestConfig {
+    fn default() -> Self {
+        Self {
+            name: None,
+            method: Self::default_method(),
+            url: None,
+            headers: HashMap::new(),
+            query: HashMap::new(),
+            body: None,
+            auth: None,
+            extract: HashMap::new(),
+            timeout_secs: Self::default_timeout_secs(),
+            fail_on_status: Self::default_fail_on_status(),
+            max_response_bytes: Self::default_max_response_bytes(),
+        }
+    }
+}
+
+/// HTTP 请求工具
+///
+/// 输入参数（均可选，覆盖配置）：
+/// - url（仅在配置未指定 `url` 时可用）/ method / headers / query / body
+/// - 其它字段作为模板变量，用于替换 `{{field}}`
+///
+/// 输出：`status`、`headers`、`body`（JSON 或文本）以及按 `extract` 规则提取的 `extracted`。
+#[derive(Clone)]
+pub struct HttpRequestTool {
+    name: &'static str,
+    client: Client,
+    config: HttpRequestConfig,
+    extractors: Vec<(String, JsonPath)>,
+    /// 允许附加 `auth` 的主机与端口（取自配置的 `url`）
+    auth_origin: Option<(String, Option<u16>)>,
+}
+
+impl Default for HttpRequestTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpRequestTool {
+    pub fn new() -> Self {
+        Self::with_config(HttpRequestConfig::default())
+            .expect("default http.request config is valid")
+    }
+
+    pub fn with_config(config: HttpRequestConfig) -> Result<Self> {
+        let name: &'static str = match &config.name {
+            Some(name) => Box::leak(name.clone().into_boxed_str()),
+            None => "http.request",
+        };
+        let extractors = config
+            .extract
+            .iter()
+            .map(|(field, path)| Ok((field.clone(), JsonPath::parse(path)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build()
+            .map_err(|e| AgentFlowError::Other(anyhow::anyhow!(e)))?;
+        let auth_origin = config
+            .url
+            .as_deref()
+            .and_then(|url| reqwest::Url::parse(url).ok())
+            .and_then(|url| origin(&url));
+        Ok(Self {
+            name,
+            client,
+            config,
+            extractors,
+            auth_origin,
+        })
+    }
+
+    pub fn config(&self) -> &HttpRequestConfig {
+        &self.config
+    }
+
+    /// 根据配置生成工具清单
+    pub fn manifest(&self) -> ToolManifest {
+        let mut output = ToolPort::new("response").with_schema(
+            ToolPortSchema::new().with_type("object").with_format("json"),
+        );
+        if !self.config.extract.is_empty() {
+            let mut fields: Vec<&String> = self.config.extract.keys().collect();
+            fields.sort();
+            output = output.with_description(format!(
+                "extracted fields: {}",
+                fields
+                    .iter()
+                    .map(|f| f.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        let mut builder = ToolManifest::builder(self.name)
+            .description(format!(
+                "{} {}",
+                self.config.method.to_uppercase(),
+                self.config.url.as_deref().unwrap_or("<url>")
+            ))
+            .input(
+                ToolPort::new("input").with_schema(
+                    ToolPortSchema::new().with_type("object").with_format("json"),
+                ),
+            )
+            .output(output)
+            .capability("http")
+            .permission("network");
+        if let Some(url) = &self.config.url {
+            builder = builder.resource(url.clone());
+        }
+        builder.build()
+    }
+
+    fn resolve_secret(value: &str) -> Result<String> {
+        if value.starts_with("${") && value.ends_with('}') {
+            EnvConfig::get_env(&value[2..value.len() - 1])
+        } else {
+            Ok(value.to_string())
+        }
+    }
+}
+
+fn origin(url: &reqwest::Url) -> Option<(String, Option<u16>)> {
+    url.host_str()
+        .map(|host| (host.to_ascii_lowercase(), url.port_or_known_default()))
+}
+
+/// 替换字符串中的 `{{field}}` 占位符
+pub fn render_template(template: &str, vars: &Map<String, Value>) -> String {
+    render_with(template, vars, |value| value.to_string())
+}
+
+/// 替换 URL 模板中的占位符，变量值按 URL 组件编码
+pub fn render_url_template(template: &str, vars: &Map<String, Value>) -> String {
+    render_with(template, vars, encode_component)
+}
+
+/// 百分号编码，仅保留 RFC 3986 非保留字符
+fn encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn render_with(
+    template: &str,
+    vars: &Map<String, Value>,
+    encode: impl Fn(&str) -> String,
+) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let key = after[..end].trim();
+                match vars.get(key) {
+                    Some(Value::String(s)) => output.push_str(&encode(s)),
+                    Some(Value::Null) | None => {}
+                    Some(other) => output.push_str(&encode(&other.to_string())),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// 递归替换 JSON 值中的模板；整段为单个占位符时保留原始类型
+fn render_value(value: &Value, vars: &Map<String, Value>) -> Value {
+    match value {
+        Value::String(s) => {
+            let trimmed = s.trim();
+            if let Some(key) = trimmed
+                .strip_prefix("{{")
+                .and_then(|k| k.strip_suffix("}}"))
+                .filter(|k| !k.contains("{{"))
+            {
+                if let Some(found) = vars.get(key.trim()) {
+                    return found.clone();
+                }
+            }
+            Value::String(render_template(s, vars))
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| render_value(v, vars)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), render_value(v, vars)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn string_map(value: &Value) -> HashMap<String, String> {
+    value
+        .as_object()
+        .map(|map| {
+            map.iter()
+                .map(|(k, v)| {
+                    let v = match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    (k.clone(), v)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[async_trait]
+impl Tool for HttpRequestTool {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let vars = invocation.input.as_object().cloned().unwrap_or_default();
+
+        let url_template = match (&self.config.url, vars.get("url")) {
+            (Some(_), Some(_)) => {
+                return Err(AgentFlowError::Other(anyhow::anyhow!(
+                    "Tool `{}` has a configured url; the input may not override it",
+                    self.name
+                )))
+            }
+            (Some(url), None) => url.clone(),
+            (None, Some(Value::String(url))) => url.clone(),
+            (None, _) => return Err(AgentFlowError::Other(anyhow::anyhow!("Missing url"))),
+        };
+        let url = render_url_template(&url_template, &vars);
+        let parsed = reqwest::Url::parse(&url)
+            .map_err(|e| AgentFlowError::Other(anyhow::anyhow!("Invalid url `{}`: {}", url, e)))?;
+
+        let method_name = vars
+            .get("method")
+            .and_then(|v| v.as_str())
+            .unwrap_or(&self.config.method)
+            .to_uppercase();
+        let method = Method::from_bytes(method_name.as_bytes()).map_err(|_| {
+            AgentFlowError::Other(anyhow::anyhow!("Invalid HTTP method `{}`", method_name))
+        })?;
+
+        let mut headers = self.config.headers.clone();
+        headers.extend(string_map(vars.get("headers").unwrap_or(&Value::Null)));
+        let mut query = self.config.query.clone();
+        query.extend(string_map(vars.get("query").unwrap_or(&Value::Null)));
+
+        let mut request = self.client.request(method.clone(), parsed.clone());
+        for (key, value) in &headers {
+            request = request.header(key, render_template(value, &vars));
+        }
+        let query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (k.clone(), render_template(v, &vars)))
+            .collect();
+        if !query.is_empty() {
+            request = request.query(&query);
+        }
+
+        let auth = match &self.config.auth {
+            Some(auth) if self.auth_origin.is_some() && self.auth_origin == origin(&parsed) => {
+                Some(auth)
+            }
+            Some(_) => {
+                tracing::warn!(
+                    tool = self.name,
+                    url = %url,
+                    "Request host differs from the configured url; auth is not attached"
+                );
+                None
+            }
+            None => None,
+        };
+        if let Some(auth) = auth {
+            request = match auth {
+                HttpAuth::Bearer { token } => request.bearer_auth(Self::resolve_secret(token)?),
+                HttpAuth::Basic { username, password } => request.basic_auth(
+                    Self::resolve_secret(username)?,
+                    Some(Self::resolve_secret(password)?),
+                ),
+                HttpAuth::Header { name, value } => {
+                    request.header(name, Self::resolve_secret(value)?)
+                }
+                HttpAuth::Query { name, value } => {
+                    request.query(&[(name.clone(), Self::resolve_secret(value)?)])
+                }
+            };
+        }
+
+        let body = vars
+            .get("body")
+            .cloned()
+            .or_else(|| self.config.body.as_ref().map(|b| render_value(b, &vars)));
+        if let Some(body) = body {
+            request = match body {
+                Value::String(text) => request.body(text),
+                other => request.json(&other),
+            };
+        }
+
+        let response = request.send().await.map_err(|e| {
+            AgentFlowError::Other(anyhow::anyhow!("HTTP {} {} failed: {}", method, url, e))
+        })?;
+
+        let status = response.status();
+        let response_headers: Map<String, Value> = response
+            .headers()
+            .iter()
+            .filter_map(|(k, v)| {
+                v.to_str()
+                    .ok()
+                    .map(|v| (k.as_str().to_string(), Value::String(v.to_string())))
+            })
+            .collect();
+        let bytes = read_body(response, self.config.max_response_bytes).await?;
+        let text = String::from_utf8_lossy(&bytes).into_owned();
+
+        if self.config.fail_on_status && !status.is_success() {
+            return Err(AgentFlowError::Other(anyhow::anyhow!(
+                "HTTP {} {} returned {}: {}",
+                method,
+                url,
+                status,
+                text.chars().take(500).collect::<String>()
+            )));
+        }
+
+        let body = serde_json::from_str::<Value>(&text).unwrap_or(Value::String(text));
+        let extracted: Map<String, Value> = self
+            .extractors
+            .iter()
+            .map(|(field, path)| (field.clone(), path.extract(&body).unwrap_or(Value::Null)))
+            .collect();
+
+        let result = json!({
+            "success": status.is_success(),
+            "status": status.as_u16(),
+            "url": url,
+            "headers": response_headers,
+            "body": body,
+            "extracted": extracted,
+        });
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name.to_string(),
+            to: None,
+            content: result.to_string(),
+            metadata: invocation.metadata,
+            attachments: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryStore;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{mpsc, Arc};
+
+    /// (请求行, 小写请求头)
+    type Request = (String, HashMap<String, String>);
+
+    /// 返回固定响应体的 HTTP 服务，记录收到的每个请求
+    fn server(body: &'static str) -> (String, mpsc::Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut headers = HashMap::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((key, value)) = line.split_once(": ") {
+                        headers.insert(key.to_ascii_lowercase(), value.to_string());
+                    }
+                }
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+                let _ = tx.send((request_line.trim_end().to_string(), headers));
+            }
+        });
+        (url, rx)
+    }
+
+    fn tool(config: Value) -> HttpRequestTool {
+        HttpRequestTool::with_config(serde_json::from_value(config).unwrap()).unwrap()
+    }
+
+    async fn call(tool: &HttpRequestTool, input: Value) -> Result<Value> {
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+        let reply = tool
+            .call(ToolInvocation::new("http.request", input), &ctx)
+            .await?;
+        Ok(serde_json::from_str(&reply.content).unwrap())
+    }
+
+    #[test]
+    fn test_render_templates() {
+        let vars = json!({ "city": "Paris", "days": 3 })
+            .as_object()
+            .cloned()
+            .unwrap();
+        assert_eq!(
+            render_template("https://api.test/weather/{{city}}?d={{ days }}", &vars),
+            "https://api.test/weather/Paris?d=3"
+        );
+        let body = render_value(&json!({ "q": "{{city}}", "n": "{{days}}", "s": "in {{city}}" }), &vars);
+        assert_eq!(body, json!({ "q": "Paris", "n": 3, "s": "in Paris" }));
+    }
+
+    #[test]
+    fn test_invalid_extract_rule() {
+        let config: HttpRequestConfig = serde_json::from_value(json!({
+            "url": "https://api.test",
+            "extract": { "bad": "$.a[" }
+        }))
+        .unwrap();
+        assert!(HttpRequestTool::with_config(config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_configured_url_cannot_be_overridden() {
+        let (url, requests) = server(r#"{"temp":21}"#);
+        let tool = tool(json!({
+            "url": format!("{}/weather/{{{{city}}}}", url),
+            "auth": { "type": "bearer", "token": "secret" },
+            "extract": { "temp": "$.temp" }
+        }));
+
+        let err = call(&tool, json!({ "url": "http://attacker.test/" }))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("may not override"));
+
+        let result = call(&tool, json!({ "city": "São Paulo/../admin?x=1" }))
+            .await
+            .unwrap();
+        assert_eq!(result["extracted"]["temp"], 21);
+        let (request_line, headers) = requests.recv().unwrap();
+        assert_eq!(
+            request_line,
+            "GET /weather/S%C3%A3o%20Paulo%2F..%2Fadmin%3Fx%3D1 HTTP/1.1"
+        );
+        assert_eq!(headers["authorization"], "Bearer secret");
+    }
+
+    #[tokio::test]
+    async fn test_auth_is_scoped_to_configured_host() {
+        let (url, requests) = server("{}");
+        let secret = json!({ "type": "header", "name": "X-Api-Key", "value": "secret" });
+
+        // 未配置 url：输入提供的地址不会收到凭据
+        let open = tool(json!({ "auth": secret }));
+        call(&open, json!({ "url": format!("{}/a", url) }))
+            .await
+            .unwrap();
+        let (_, headers) = requests.recv().unwrap();
+        assert!(!headers.contains_key("x-api-key"));
+
+        // 模板变量经过 URL 编码，不能借此改写主机
+        let templated = tool(json!({ "url": "http://{{host}}/a", "auth": secret }));
+        call(
+            &templated,
+            json!({ "host": url.trim_start_matches("http://") }),
+        )
+        .await
+        .unwrap_err();
+
+        let pinned = tool(json!({ "url": format!("{}/a", url), "auth": secret }));
+        call(&pinned, json!({})).await.unwrap();
+        let (_, headers) = requests.recv().unwrap();
+        assert_eq!(headers["x-api-key"], "secret");
+    }
+
+    #[tokio::test]
+    async fn test_response_body_is_limited() {
+        let (url, _requests) = server(r#"{"data":"0123456789"}"#);
+        let tool = tool(json!({ "url": url, "max_response_bytes": 8 }));
+        let err = call(&tool, json!({})).await.unwrap_err();
+        assert!(err.to_string().contains("exceeds the limit"));
+    }
+}
@@ -1,51 +1,76 @@
 //! 图片生成工具 - 调用通义万相 API
 
 use async_trait::async_trait;
-use reqwest::Client;
-use serde::Deserialize;
-use serde_json::json;
-use tokio::time::{sleep, Duration};
+use serde_json::{json, Value};
 
-use crate::agent::{AgentMessage, MessageRole};
+use crate::agent::{AgentMessage, Attachment, MessageRole};
 use crate::error::{AgentFlowError, Result};
+use crate::llm::{ImageGenConfig, ImageGenRequest};
+use crate::runtime::RunUpdate;
 use crate::state::FlowContext;
 use crate::tools::tool::{Tool, ToolInvocation};
 
 /// 图片生成工具
+///
+/// 输入参数：
+/// - prompt: 提示词
+/// - model: 模型（配置了默认模型时可省略）
+/// - api_key / endpoint / query_endpoint: 可选，覆盖工具配置
+/// - size / style / negative_prompt / n: 可选
+/// - poll_interval_ms / max_wait_secs: 可选，轮询间隔与最长等待时间
+///
+/// 输出：`image_urls` 与 `task_id`，图片地址同时作为消息附件返回；
+/// 上下文挂载运行通道时推送 `task_progress` 事件。
+#[derive(Clone, Default)]
 pub struct ImageGeneratorTool {
-    client: Client,
-}
-
-impl Default for ImageGeneratorTool {
-    fn default() -> Self {
-        Self::new()
-    }
+    config: Option<ImageGenConfig>,
 }
 
 impl ImageGeneratorTool {
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 默认配置，调用参数中的同名字段优先
+    pub fn with_config(config: ImageGenConfig) -> Self {
         Self {
-            client: Client::new(),
+            config: Some(config),
         }
     }
-}
 
-#[derive(Debug, Deserialize)]
-struct WanxiangResponse {
-    output: WanxiangOutput,
-}
-
-#[derive(Debug, Deserialize)]
-struct WanxiangOutput {
-    task_id: String,
-    task_status: String,
-    #[serde(default)]
-    results: Vec<WanxiangResult>,
-}
-
-#[derive(Debug, Deserialize)]
-struct WanxiangResult {
-    url: String,
+    fn resolve_config(&self, input: &Value) -> Result<ImageGenConfig> {
+        let mut config = match input["model"].as_str() {
+            Some(model) => {
+                let mut config = self
+                    .config
+                    .clone()
+                    .unwrap_or_else(|| ImageGenConfig::new(model));
+                config.model = model.to_string();
+                config
+            }
+            None => self
+                .config
+                .clone()
+                .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing model parameter")))?,
+        };
+        let text = |key: &str| input[key].as_str().map(str::to_string);
+        if let Some(api_key) = text("api_key") {
+            config.api_key = Some(api_key);
+        }
+        if let Some(endpoint) = text("endpoint") {
+            config.endpoint = Some(endpoint);
+        }
+        if let Some(task_endpoint) = text("query_endpoint") {
+            config.task_endpoint = Some(task_endpoint);
+        }
+        if let Some(interval) = input["poll_interval_ms"].as_u64() {
+            config.poll_interval_ms = interval;
+        }
+        if let Some(max_wait) = input["max_wait_secs"].as_u64() {
+            config.max_wait_secs = max_wait;
+        }
+        Ok(config)
+    }
 }
 
 #[async_trait]
@@ -54,161 +79,59 @@ impl Tool for ImageGeneratorTool {
         "image_generator"
     }
 
-    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
-        let api_key = invocation.input["api_key"]
-            .as_str()
-            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing api_key")))?;
-        
-        let prompt = invocation.input["prompt"]
+    async fn call(&self, invocation: ToolInvocation, ctx: &FlowContext) -> Result<AgentMessage> {
+        let input = &invocation.input;
+        let prompt = input["prompt"]
             .as_str()
             .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing prompt")))?;
-        
-        let model = invocation.input["model"]
-            .as_str()
-            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing model parameter")))?;
-        
-        let endpoint = invocation.input["endpoint"]
-            .as_str()
-            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing endpoint parameter")))?;
-        
-        let query_endpoint = invocation.input["query_endpoint"]
-            .as_str()
-            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing query_endpoint parameter")))?;
-        
-        let style = invocation.input["style"]
-            .as_str()
-            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing style parameter")))?;
-        
-        let size = invocation.input["size"]
-            .as_str()
-            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing size parameter")))?;
+        let client = self.resolve_config(input)?.build_client()?;
 
-        let req_body = json!({
-            "model": model,
-            "input": {
-                "prompt": prompt
-            },
-            "parameters": {
-                "style": style,
-                "size": size,
-                "n": 1
-            }
-        });
-
-        let response = self.client
-            .post(endpoint)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
-            .header("X-DashScope-Async", "enable")
-            .json(&req_body)
-            .send()
-            .await
-            .map_err(|e| AgentFlowError::Other(anyhow::anyhow!("API request failed: {}", e)))?;
-
-        if !response.status().is_success() {
-            let error_text = response.text().await
-                .map_err(|e| AgentFlowError::Other(anyhow::anyhow!("Failed to read error response: {}", e)))?;
-            return Err(AgentFlowError::Other(anyhow::anyhow!(
-                "API error: {}",
-                error_text
-            )));
+        let mut request = ImageGenRequest::new(prompt);
+        if let Some(size) = input["size"].as_str() {
+            request = request.with_size(size);
         }
-
-        let submit_resp: WanxiangResponse = response
-            .json()
-            .await
-            .map_err(|e| AgentFlowError::Other(anyhow::anyhow!("Parse response failed: {}", e)))?;
-
-        if submit_resp.output.task_status == "SUCCEEDED" && !submit_resp.output.results.is_empty() {
-            let image_urls: Vec<String> = submit_resp
-                .output
-                .results
-                .into_iter()
-                .map(|r| r.url)
-                .collect();
-
-            return Ok(AgentMessage {
-                id: crate::agent::message::uuid(),
-                role: MessageRole::Tool,
-                from: self.name().to_string(),
-                to: None,
-                content: json!({
-                    "success": true,
-                    "image_urls": image_urls,
-                    "task_id": submit_resp.output.task_id
-                })
-                .to_string(),
-                metadata: None,
-            });
+        if let Some(style) = input["style"].as_str() {
+            request = request.with_style(style);
+        }
+        if let Some(negative_prompt) = input["negative_prompt"].as_str() {
+            request = request.with_negative_prompt(negative_prompt);
+        }
+        if let Some(n) = input["n"].as_u64() {
+            request = request.with_count(n as u32);
         }
 
-        let task_id = submit_resp.output.task_id;
-        let query_url = format!("{}/{}", query_endpoint, task_id);
-        let max_attempts = 60;
-
-        for attempt in 1..=max_attempts {
-            sleep(Duration::from_secs(2)).await;
-
-            let query_response = self.client
-                .get(&query_url)
-                .header("Authorization", format!("Bearer {}", api_key))
-                .send()
-                .await;
-
-            if query_response.is_err() {
-                continue;
-            }
-
-            let query_response = query_response.unwrap();
-            if !query_response.status().is_success() {
-                continue;
-            }
-
-            let result: WanxiangResponse = match query_response.json().await {
-                Ok(r) => r,
-                Err(_) => continue,
-            };
-
-            match result.output.task_status.as_str() {
-                "SUCCEEDED" => {
-                    let image_urls: Vec<String> = result
-                        .output
-                        .results
-                        .into_iter()
-                        .map(|r| r.url)
-                        .collect();
-
-                    return Ok(AgentMessage {
-                        id: crate::agent::message::uuid(),
-                        role: MessageRole::Tool,
-                        from: self.name().to_string(),
-                        to: None,
-                        content: json!({
-                            "success": true,
-                            "image_urls": image_urls,
-                            "task_id": task_id,
-                            "attempts": attempt
-                        })
-                        .to_string(),
-                        metadata: None,
+        let node = ctx.node().unwrap_or(self.name()).to_string();
+        let result = client
+            .generate_with_progress(&request, |progress| {
+                if let Some(channel) = ctx.channel() {
+                    channel.publish(RunUpdate::TaskProgress {
+                        node: node.clone(),
+                        task_id: progress.task_id.clone(),
+                        status: progress.status.clone(),
+                        elapsed_ms: progress.elapsed_ms,
                     });
                 }
-                "FAILED" => {
-                    return Err(AgentFlowError::Other(anyhow::anyhow!(
-                        "Image generation failed"
-                    )));
-                }
-                "RUNNING" | "PENDING" => {
-                    continue;
-                }
-                _ => continue,
-            }
-        }
-
-        Err(AgentFlowError::Other(anyhow::anyhow!(
-            "Image generation timeout after {} attempts",
-            max_attempts
-        )))
+            })
+            .await?;
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name().to_string(),
+            to: None,
+            content: json!({
+                "success": true,
+                "image_urls": result.urls,
+                "task_id": result.task_id,
+                "attempts": result.attempts
+            })
+            .to_string(),
+            metadata: None,
+            attachments: result
+                .urls
+                .iter()
+                .map(|url| Attachment::image_url(url.clone()))
+                .collect(),
+        })
     }
 }
-
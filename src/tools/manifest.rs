@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ToolManifest {
@@ -22,6 +22,56 @@ impl ToolManifest {
     pub fn builder(name: impl Into<String>) -> ToolManifestBuilder {
         ToolManifestBuilder::new(name)
     }
+
+    /// 输入参数的 JSON Schema
+    ///
+    /// 只有一个带 `json_schema` 的 `arguments` 输入（如 MCP 工具）时直接使用该 Schema，
+    /// 否则每个输入端口作为对象的一个属性。
+    pub fn parameters_schema(&self) -> Value {
+        if let [port] = self.inputs.as_slice() {
+            if port.name == "arguments" {
+                if let Some(schema) = port.schema.as_ref().and_then(|s| s.json_schema.clone()) {
+                    return schema;
+                }
+            }
+        }
+        let properties: serde_json::Map<String, Value> = self
+            .inputs
+            .iter()
+            .map(|port| {
+                let mut schema = port
+                    .schema
+                    .as_ref()
+                    .and_then(|schema| {
+                        schema.json_schema.clone().or_else(|| {
+                            schema
+                                .type_name
+                                .as_ref()
+                                .map(|type_name| json!({ "type": type_name }))
+                        })
+                    })
+                    .unwrap_or_else(|| json!({}));
+                if let (Some(description), Some(object)) =
+                    (&port.description, schema.as_object_mut())
+                {
+                    object
+                        .entry("description")
+                        .or_insert_with(|| json!(description));
+                }
+                (port.name.clone(), schema)
+            })
+            .collect();
+        json!({ "type": "object", "properties": properties })
+    }
+
+    /// 函数调用格式的工具描述：`{"name", "description", "parameters"}`
+    pub fn function_schema(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description.clone().unwrap_or_default(),
+            "parameters": self.parameters_schema(),
+        })
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -61,7 +111,7 @@ impl ToolPort {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ToolPortSchema {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub type_name: Option<String>,
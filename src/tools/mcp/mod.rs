@@ -0,0 +1,377 @@
+//! MCP (Model Context Protocol) 工具桥接
+//!
+//! 连接 MCP 服务器，枚举其工具并注册到 `ToolRegistry`。
+
+pub mod transport;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::agent::{AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::state::FlowContext;
+use crate::tools::manifest::{ToolManifest, ToolPort, ToolPortSchema};
+use crate::tools::registry::ToolRegistry;
+use crate::tools::tool::{Tool, ToolInvocation};
+
+pub use transport::{McpTransport, SseTransport, StdioTransport};
+
+pub const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// MCP 服务器连接配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    pub name: String,
+    pub transport: McpTransportConfig,
+    /// 注册名前缀，例如 `fs` 会把 `read_file` 注册为 `fs.read_file`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_prefix: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum McpTransportConfig {
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: HashMap<String, String>,
+    },
+    Sse {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+}
+
+/// MCP 服务器声明的工具
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct McpToolDescriptor {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, rename = "inputSchema", skip_serializing_if = "Option::is_none")]
+    pub input_schema: Option<Value>,
+    #[serde(default, rename = "outputSchema", skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+}
+
+impl McpToolDescriptor {
+    /// 由 MCP 工具 schema 生成 ToolManifest
+    pub fn to_manifest(&self, registered_name: &str, server: &str) -> ToolManifest {
+        let mut input_schema = ToolPortSchema::new().with_type("object").with_format("json");
+        if let Some(schema) = &self.input_schema {
+            input_schema = input_schema.with_json_schema(schema.clone());
+        }
+
+        let mut output_schema = ToolPortSchema::new().with_type("object").with_format("json");
+        if let Some(schema) = &self.output_schema {
+            output_schema = output_schema.with_json_schema(schema.clone());
+        }
+
+        let mut builder = ToolManifest::builder(registered_name)
+            .input(ToolPort::new("arguments").with_schema(input_schema))
+            .output(ToolPort::new("result").with_schema(output_schema))
+            .capability("mcp")
+            .resource(format!("mcp:{}", server));
+        if let Some(description) = &self.description {
+            builder = builder.description(description.clone());
+        }
+        builder.build()
+    }
+}
+
+/// MCP 工具提供者
+///
+/// 负责握手、分页枚举工具，并把每个 MCP 工具包装为框架 `Tool`。
+#[derive(Clone)]
+pub struct McpToolProvider {
+    server_name: String,
+    tool_prefix: Option<String>,
+    transport: Arc<dyn McpTransport>,
+    server_info: Value,
+}
+
+impl McpToolProvider {
+    /// 按配置连接 MCP 服务器
+    pub async fn connect(config: &McpServerConfig) -> Result<Self> {
+        let transport: Arc<dyn McpTransport> = match &config.transport {
+            McpTransportConfig::Stdio { command, args, env } => {
+                Arc::new(StdioTransport::spawn(command, args, env).await?)
+            }
+            McpTransportConfig::Sse { url, headers } => {
+                Arc::new(SseTransport::connect(url, headers.clone()).await?)
+            }
+        };
+        let mut provider = Self::with_transport(config.name.clone(), transport).await?;
+        provider.tool_prefix = config.tool_prefix.clone();
+        Ok(provider)
+    }
+
+    /// 启动子进程并通过 stdio 连接
+    pub async fn connect_stdio(
+        name: impl Into<String>,
+        command: &str,
+        args: &[String],
+    ) -> Result<Self> {
+        let transport = StdioTransport::spawn(command, args, &HashMap::new()).await?;
+        Self::with_transport(name, Arc::new(transport)).await
+    }
+
+    /// 通过 SSE 连接
+    pub async fn connect_sse(name: impl Into<String>, url: &str) -> Result<Self> {
+        let transport = SseTransport::connect(url, HashMap::new()).await?;
+        Self::with_transport(name, Arc::new(transport)).await
+    }
+
+    /// 使用已有传输完成 initialize 握手
+    pub async fn with_transport(
+        name: impl Into<String>,
+        transport: Arc<dyn McpTransport>,
+    ) -> Result<Self> {
+        let server_info = transport
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": MCP_PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "agentflow",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    }
+                }),
+            )
+            .await?;
+        transport
+            .notify("notifications/initialized", json!({}))
+            .await?;
+
+        Ok(Self {
+            server_name: name.into(),
+            tool_prefix: None,
+            transport,
+            server_info,
+        })
+    }
+
+    pub fn with_tool_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.tool_prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+
+    /// initialize 返回的服务器信息
+    pub fn server_info(&self) -> &Value {
+        &self.server_info
+    }
+
+    /// 枚举服务器工具（处理 `nextCursor` 分页）
+    pub async fn list_tools(&self) -> Result<Vec<McpToolDescriptor>> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.transport.request("tools/list", params).await?;
+            let page: Vec<McpToolDescriptor> =
+                serde_json::from_value(result["tools"].clone()).map_err(|e| {
+                    AgentFlowError::Other(anyhow!("Invalid MCP tools/list response: {}", e))
+                })?;
+            tools.extend(page);
+            match result["nextCursor"].as_str() {
+                Some(next) if !next.is_empty() => cursor = Some(next.to_string()),
+                _ => break,
+            }
+        }
+        Ok(tools)
+    }
+
+    /// 调用 MCP 工具，返回原始 result
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value> {
+        self.transport
+            .request("tools/call", json!({ "name": name, "arguments": arguments }))
+            .await
+    }
+
+    fn registered_name(&self, tool: &str) -> String {
+        match &self.tool_prefix {
+            Some(prefix) => format!("{}.{}", prefix, tool),
+            None => tool.to_string(),
+        }
+    }
+
+    /// 将服务器工具全部注册到 ToolRegistry，返回注册名列表
+    pub async fn register_tools(&self, registry: &mut ToolRegistry) -> Result<Vec<String>> {
+        let mut registered = Vec::new();
+        for descriptor in self.list_tools().await? {
+            let name = self.registered_name(&descriptor.name);
+            let manifest = descriptor.to_manifest(&name, &self.server_name);
+            let tool = McpTool {
+                name: Box::leak(name.clone().into_boxed_str()),
+                remote_name: descriptor.name.clone(),
+                provider: self.clone(),
+            };
+            registry.register_with_manifest(Arc::new(tool), manifest)?;
+            registered.push(name);
+        }
+        Ok(registered)
+    }
+}
+
+/// 包装单个 MCP 工具
+pub struct McpTool {
+    name: &'static str,
+    remote_name: String,
+    provider: McpToolProvider,
+}
+
+impl McpTool {
+    pub fn remote_name(&self) -> &str {
+        &self.remote_name
+    }
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let arguments = match invocation.input {
+            Value::Null => json!({}),
+            other => other,
+        };
+        let result = self.provider.call_tool(&self.remote_name, arguments).await?;
+
+        let text = result["content"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item["text"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+
+        if result["isError"].as_bool().unwrap_or(false) {
+            return Err(AgentFlowError::Other(anyhow!(
+                "MCP tool `{}` returned error: {}",
+                self.remote_name,
+                text
+            )));
+        }
+
+        let payload = json!({
+            "success": true,
+            "server": self.provider.server_name(),
+            "tool": self.remote_name,
+            "text": text,
+            "content": result["content"],
+            "structured": result.get("structuredContent").cloned().unwrap_or(Value::Null),
+        });
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name.to_string(),
+            to: None,
+            content: payload.to_string(),
+            metadata: invocation.metadata,
+            attachments: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryStore;
+    use parking_lot::Mutex;
+
+    struct FakeTransport {
+        calls: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl McpTransport for FakeTransport {
+        async fn request(&self, method: &str, params: Value) -> Result<Value> {
+            self.calls.lock().push(method.to_string());
+            Ok(match method {
+                "initialize" => json!({ "serverInfo": { "name": "fake" } }),
+                "tools/list" if params.get("cursor").is_none() => json!({
+                    "tools": [{
+                        "name": "add",
+                        "description": "Add numbers",
+                        "inputSchema": { "type": "object", "properties": { "a": { "type": "number" } } }
+                    }],
+                    "nextCursor": "page2"
+                }),
+                "tools/list" => json!({ "tools": [{ "name": "fail" }] }),
+                "tools/call" if params["name"] == "fail" => json!({
+                    "content": [{ "type": "text", "text": "boom" }],
+                    "isError": true
+                }),
+                "tools/call" => json!({
+                    "content": [{ "type": "text", "text": format!("sum={}", params["arguments"]["a"]) }]
+                }),
+                _ => Value::Null,
+            })
+        }
+
+        async fn notify(&self, method: &str, _params: Value) -> Result<()> {
+            self.calls.lock().push(method.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_and_call_mcp_tools() {
+        let transport = Arc::new(FakeTransport {
+            calls: Mutex::new(Vec::new()),
+        });
+        let provider = McpToolProvider::with_transport("fake", transport.clone())
+            .await
+            .unwrap()
+            .with_tool_prefix("math");
+
+        let mut registry = ToolRegistry::new();
+        let names = provider.register_tools(&mut registry).await.unwrap();
+        assert_eq!(names, vec!["math.add".to_string(), "math.fail".to_string()]);
+
+        let manifest = registry.manifest("math.add").unwrap();
+        assert_eq!(manifest.description.as_deref(), Some("Add numbers"));
+        assert!(manifest.inputs[0].schema.as_ref().unwrap().json_schema.is_some());
+
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+        let tool = registry.get("math.add").unwrap();
+        let message = tool
+            .call(ToolInvocation::new("math.add", json!({ "a": 2 })), &ctx)
+            .await
+            .unwrap();
+        let payload: Value = serde_json::from_str(&message.content).unwrap();
+        assert_eq!(payload["text"], "sum=2");
+
+        let failing = registry.get("math.fail").unwrap();
+        assert!(failing
+            .call(ToolInvocation::new("math.fail", json!({})), &ctx)
+            .await
+            .is_err());
+
+        let calls = transport.calls.lock().clone();
+        assert_eq!(calls[0], "initialize");
+        assert_eq!(calls[1], "notifications/initialized");
+    }
+}
@@ -0,0 +1,490 @@
+//! MCP 传输层 - stdio 与 SSE 两种 JSON-RPC 通道
+
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use reqwest::{Client, Url};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{oneshot, Mutex as AsyncMutex};
+use tracing::{debug, warn};
+
+use crate::error::{AgentFlowError, Result};
+
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// MCP JSON-RPC 传输通道
+#[async_trait]
+pub trait McpTransport: Send + Sync {
+    /// 发送请求并等待响应的 `result` 字段
+    async fn request(&self, method: &str, params: Value) -> Result<Value>;
+
+    /// 发送通知（无响应）
+    async fn notify(&self, method: &str, params: Value) -> Result<()>;
+}
+
+fn request_frame(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn notification_frame(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+/// 从 JSON-RPC 响应中取出 result，error 转换为框架错误
+fn into_result(method: &str, response: Value) -> Result<Value> {
+    if let Some(error) = response.get("error") {
+        let message = error["message"].as_str().unwrap_or("unknown error");
+        let code = error["code"].as_i64().unwrap_or_default();
+        return Err(AgentFlowError::Other(anyhow!(
+            "MCP `{}` failed ({}): {}",
+            method,
+            code,
+            message
+        )));
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
+
+struct StdioChannel {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+/// 通过子进程 stdin/stdout 通信的传输（每行一条 JSON-RPC 消息）
+pub struct StdioTransport {
+    channel: AsyncMutex<StdioChannel>,
+    next_id: AtomicU64,
+    timeout: Duration,
+    _child: Child,
+}
+
+impl StdioTransport {
+    /// 启动 MCP 服务器子进程
+    pub async fn spawn(
+        command: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+    ) -> Result<Self> {
+        let mut child = Command::new(command)
+            .args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                AgentFlowError::Other(anyhow!("Failed to spawn MCP server `{}`: {}", command, e))
+            })?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| AgentFlowError::Other(anyhow!("MCP server stdin unavailable")))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| AgentFlowError::Other(anyhow!("MCP server stdout unavailable")))?;
+
+        Ok(Self {
+            channel: AsyncMutex::new(StdioChannel {
+                stdin,
+                stdout: BufReader::new(stdout),
+            }),
+            next_id: AtomicU64::new(1),
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+            _child: child,
+        })
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn write_frame(channel: &mut StdioChannel, frame: &Value) -> Result<()> {
+        let mut line = frame.to_string();
+        line.push('\n');
+        channel
+            .stdin
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("MCP stdio write failed: {}", e)))?;
+        channel
+            .stdin
+            .flush()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("MCP stdio flush failed: {}", e)))
+    }
+
+    async fn read_response(channel: &mut StdioChannel, id: u64) -> Result<Value> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = channel
+                .stdout
+                .read_line(&mut line)
+                .await
+                .map_err(|e| AgentFlowError::Other(anyhow!("MCP stdio read failed: {}", e)))?;
+            if read == 0 {
+                return Err(AgentFlowError::Other(anyhow!("MCP server closed stdout")));
+            }
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let message: Value = match serde_json::from_str(trimmed) {
+                Ok(message) => message,
+                Err(err) => {
+                    debug!(%err, line = trimmed, "ignoring non JSON-RPC line from MCP server");
+                    continue;
+                }
+            };
+            // 跳过服务器主动发送的通知与请求
+            if message.get("method").is_some() {
+                continue;
+            }
+            if message["id"].as_u64() == Some(id) {
+                return Ok(message);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl McpTransport for StdioTransport {
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let frame = request_frame(id, method, params);
+        let mut channel = self.channel.lock().await;
+        Self::write_frame(&mut channel, &frame).await?;
+        let response = tokio::time::timeout(self.timeout, Self::read_response(&mut channel, id))
+            .await
+            .map_err(|_| AgentFlowError::Other(anyhow!("MCP `{}` timed out", method)))??;
+        into_result(method, response)
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        let frame = notification_frame(method, params);
+        let mut channel = self.channel.lock().await;
+        Self::write_frame(&mut channel, &frame).await
+    }
+}
+
+type PendingMap = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+/// 基于 HTTP + SSE 的传输
+///
+/// GET 建立事件流，服务器通过 `endpoint` 事件告知 POST 地址，
+/// 响应通过 `message` 事件返回。事件流断开后等待中的请求立即失败。
+pub struct SseTransport {
+    client: Client,
+    endpoint: Url,
+    headers: HashMap<String, String>,
+    pending: PendingMap,
+    closed: Arc<AtomicBool>,
+    next_id: AtomicU64,
+    timeout: Duration,
+    reader: tokio::task::JoinHandle<()>,
+}
+
+impl SseTransport {
+    /// 连接 SSE 端点并等待服务器下发消息端点
+    pub async fn connect(url: &str, headers: HashMap<String, String>) -> Result<Self> {
+        let base = Url::parse(url)
+            .map_err(|e| AgentFlowError::Other(anyhow!("Invalid MCP url `{}`: {}", url, e)))?;
+        let client = Client::new();
+
+        let mut request = client.get(base.clone()).header("Accept", "text/event-stream");
+        for (key, value) in &headers {
+            request = request.header(key, value);
+        }
+        let mut response = request
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("MCP SSE connect failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "MCP SSE connect failed: HTTP {}",
+                response.status()
+            )));
+        }
+
+        let pending: PendingMap = Arc::new(Mutex::new(HashMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+        let (endpoint_tx, endpoint_rx) = oneshot::channel::<String>();
+        let reader_pending = Arc::clone(&pending);
+        let reader_closed = Arc::clone(&closed);
+
+        let reader = tokio::spawn(async move {
+            let mut parser = SseEventParser::default();
+            let mut endpoint_tx = Some(endpoint_tx);
+            loop {
+                let chunk = match response.chunk().await {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => break,
+                    Err(err) => {
+                        warn!(%err, "MCP SSE stream error");
+                        break;
+                    }
+                };
+                for (event, data) in parser.feed(&chunk) {
+                    match event.as_str() {
+                        "endpoint" => {
+                            if let Some(tx) = endpoint_tx.take() {
+                                let _ = tx.send(data);
+                            }
+                        }
+                        _ => {
+                            let Ok(message) = serde_json::from_str::<Value>(&data) else {
+                                continue;
+                            };
+                            if message.get("method").is_some() {
+                                continue;
+                            }
+                            if let Some(id) = message["id"].as_u64() {
+                                if let Some(tx) = reader_pending.lock().remove(&id) {
+                                    let _ = tx.send(message);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            // 丢弃等待中的请求，使其立即返回而不是等到超时
+            let mut pending = reader_pending.lock();
+            reader_closed.store(true, Ordering::SeqCst);
+            pending.clear();
+        });
+
+        let endpoint = tokio::time::timeout(DEFAULT_REQUEST_TIMEOUT, endpoint_rx)
+            .await
+            .map_err(|_| AgentFlowError::Other(anyhow!("MCP SSE endpoint event timed out")))?
+            .map_err(|_| AgentFlowError::Other(anyhow!("MCP SSE stream closed before endpoint")))?;
+        let endpoint = base.join(endpoint.trim()).map_err(|e| {
+            AgentFlowError::Other(anyhow!("Invalid MCP endpoint `{}`: {}", endpoint, e))
+        })?;
+
+        Ok(Self {
+            client,
+            endpoint,
+            headers,
+            pending,
+            closed,
+            next_id: AtomicU64::new(1),
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+            reader,
+        })
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn post(&self, frame: &Value) -> Result<()> {
+        let mut request = self.client.post(self.endpoint.clone()).json(frame);
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("MCP SSE post failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "MCP SSE post failed: HTTP {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SseTransport {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+#[async_trait]
+impl McpTransport for SseTransport {
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut pending = self.pending.lock();
+            if self.closed.load(Ordering::SeqCst) {
+                return Err(AgentFlowError::Other(anyhow!("MCP SSE stream closed")));
+            }
+            pending.insert(id, tx);
+        }
+
+        if let Err(err) = self.post(&request_frame(id, method, params)).await {
+            self.pending.lock().remove(&id);
+            return Err(err);
+        }
+
+        let response = match tokio::time::timeout(self.timeout, rx).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => {
+                return Err(AgentFlowError::Other(anyhow!("MCP SSE stream closed")));
+            }
+            Err(_) => {
+                self.pending.lock().remove(&id);
+                return Err(AgentFlowError::Other(anyhow!("MCP `{}` timed out", method)));
+            }
+        };
+        into_result(method, response)
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        self.post(&notification_frame(method, params)).await
+    }
+}
+
+/// 最小 SSE 事件解析器，返回 (event, data) 对
+///
+/// 按字节缓存，只解码完整的行，多字节字符或 `\r\n` 被拆到两个块中时不会损坏。
+#[derive(Default)]
+struct SseEventParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseEventParser {
+    fn feed(&mut self, bytes: &[u8]) -> Vec<(String, String)> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < self.buffer.len() {
+            let next = match self.buffer[i] {
+                b'\n' => i + 1,
+                // 末尾的 `\r` 可能是被拆开的 `\r\n`，等下一块再处理
+                b'\r' if i + 1 == self.buffer.len() => break,
+                b'\r' if self.buffer[i + 1] == b'\n' => i + 2,
+                b'\r' => i + 1,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let line = String::from_utf8_lossy(&self.buffer[start..i]).into_owned();
+            self.line(&line, &mut events);
+            start = next;
+            i = next;
+        }
+        self.buffer.drain(..start);
+        events
+    }
+
+    fn line(&mut self, line: &str, events: &mut Vec<(String, String)>) {
+        if line.is_empty() {
+            let event = self.event.take().unwrap_or_else(|| "message".to_string());
+            if !self.data.is_empty() {
+                events.push((event, self.data.join("\n")));
+                self.data.clear();
+            }
+        } else if let Some(value) = line.strip_prefix("event:") {
+            self.event = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            self.data
+                .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_event_parser() {
+        let mut parser = SseEventParser::default();
+        let events = parser.feed(b"event: endpoint\ndata: /messages?session=1\n\nevent: mes");
+        assert_eq!(
+            events,
+            vec![("endpoint".to_string(), "/messages?session=1".to_string())]
+        );
+        let events = parser.feed(b"sage\ndata: {\"id\":1}\r\n\r\n");
+        assert_eq!(events, vec![("message".to_string(), "{\"id\":1}".to_string())]);
+    }
+
+    #[test]
+    fn test_sse_event_parser_split_chunks() {
+        let mut parser = SseEventParser::default();
+        let frame = "data: 你好\r\n\r\n".as_bytes();
+        // 在多字节字符中间和 `\r\n` 中间拆分
+        let mut events = parser.feed(&frame[..7]);
+        events.extend(parser.feed(&frame[7..13]));
+        events.extend(parser.feed(&frame[13..]));
+        assert_eq!(events, vec![("message".to_string(), "你好".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_sse_request_fails_when_stream_closes() {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/sse", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut events, _) = listener.accept().unwrap();
+            events
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\nevent: endpoint\ndata: /messages\n\n",
+                )
+                .unwrap();
+            // 收到请求后只确认，不回响应，随即关闭事件流
+            let (post, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(post.try_clone().unwrap());
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_ascii_lowercase();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(value) = line.strip_prefix("content-length: ") {
+                    length = value.parse().unwrap();
+                }
+            }
+            reader.read_exact(&mut vec![0; length]).unwrap();
+            (&post)
+                .write_all(
+                    b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+            drop(events);
+        });
+
+        let transport = SseTransport::connect(&url, HashMap::new()).await.unwrap();
+        let started = std::time::Instant::now();
+        let error = transport
+            .request("tools/list", json!({}))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("stream closed"), "{error}");
+        assert!(started.elapsed() < Duration::from_secs(5));
+        // 断开后的请求同样立即失败
+        assert!(transport.request("tools/list", json!({})).await.is_err());
+    }
+
+    #[test]
+    fn test_into_result_error() {
+        let response = json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32601, "message": "nope" } });
+        assert!(into_result("tools/list", response).is_err());
+        let response = json!({ "jsonrpc": "2.0", "id": 1, "result": { "tools": [] } });
+        assert_eq!(into_result("tools/list", response).unwrap(), json!({ "tools": [] }));
+    }
+}
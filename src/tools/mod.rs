@@ -1,17 +1,46 @@
+pub mod audio;
 pub mod builtin;
+pub mod code_runner;
 pub mod downloader;
 pub mod factory;
+pub mod http_request;
 pub mod image_generator;
 pub mod manifest;
+pub mod mcp;
 pub mod orchestrator;
 pub mod registry;
 pub mod resources;
+pub mod shell;
 pub mod tool;
+pub mod vector;
+pub mod video;
+pub mod web_crawler;
+pub mod web_search;
 
+pub use audio::{AudioProvider, AudioSpeakTool, AudioToolConfig, AudioTranscribeTool};
+pub use code_runner::{CodeLanguage, CodeRunConfig, CodeRunTool};
 pub use downloader::DownloaderTool;
 pub use factory::{register_builtin_tool_factories, ToolFactory, ToolFactoryRegistry};
+pub use http_request::{HttpAuth, HttpRequestConfig, HttpRequestTool};
 pub use image_generator::ImageGeneratorTool;
 pub use manifest::{ToolManifest, ToolManifestBuilder, ToolPort, ToolPortSchema};
-pub use orchestrator::{ToolOrchestrator, ToolPipeline, ToolStep, ToolStrategy};
+pub use mcp::{McpServerConfig, McpToolProvider};
+pub use orchestrator::{
+    InputBinding, MapStep, ToolOrchestrator, ToolPipeline, ToolStep, ToolStrategy,
+};
 pub use registry::ToolRegistry;
+pub use shell::{ShellExecConfig, ShellExecTool, ShellPolicy};
 pub use tool::{Tool, ToolInvocation};
+pub use vector::{
+    register_vector_store, vector_store, DynVectorStore, Embedder, EmbeddingConfig, HttpEmbedder,
+    HttpReranker, InMemoryVectorStore, LlmEmbedder, QdrantVectorStore, RagRetrieveConfig,
+    RagRetrieveTool, RagRetrieveToolConfig, RerankConfig, RerankFormat, RerankResult, Reranker,
+    VectorMatch, VectorQuery, VectorRecord, VectorStore, VectorStoreConfig,
+};
+#[cfg(feature = "pgvector")]
+pub use vector::PgVectorStore;
+pub use video::VideoGenerateTool;
+pub use web_crawler::{WebCrawlerConfig, WebCrawlerTool};
+pub use web_search::{
+    SearchProvider, SearchResult, WebReadConfig, WebReadTool, WebSearchConfig, WebSearchTool,
+};
@@ -2,22 +2,26 @@ use std::collections::HashMap;
 use std::time::Duration;
 
 use anyhow::anyhow;
-use futures::future;
+use futures::{stream, StreamExt};
 use serde_json::Value;
 use tokio::time::timeout;
 use tracing::{info, warn};
 
 use crate::agent::{AgentMessage, MessageRole};
 use crate::error::{AgentFlowError, Result};
+use crate::schema::{validate_schema, validate_value, Schema};
 use crate::state::FlowContext;
+use crate::utils::json_path::JsonPath;
 
-use super::{ToolInvocation, ToolManifest, ToolRegistry};
+use super::{ToolInvocation, ToolManifest, ToolPort, ToolRegistry};
 
 #[derive(Clone, Debug)]
 pub enum ToolStrategy {
     Sequential(Vec<ToolStep>),
     Parallel(Vec<ToolStep>),
     Fallback(Vec<ToolStep>),
+    /// 对输入中的数组逐项执行同一个步骤
+    Map(MapStep),
 }
 
 #[derive(Clone, Debug)]
@@ -27,6 +31,16 @@ pub struct ToolStep {
     pub timeout: Option<Duration>,
     pub retries: u32,
     pub name: Option<String>,
+    pub bindings: Vec<InputBinding>,
+}
+
+/// 输入绑定：执行前从前序步骤的输出中按 JSONPath 取值写入 `field`
+#[derive(Clone, Debug)]
+pub struct InputBinding {
+    pub field: String,
+    /// 来源步骤的 `name` 或工具名，为空时取上一步的输出
+    pub source: Option<String>,
+    pub path: String,
 }
 
 impl ToolStep {
@@ -37,6 +51,7 @@ impl ToolStep {
             timeout: None,
             retries: 0,
             name: None,
+            bindings: Vec::new(),
         }
     }
 
@@ -54,6 +69,65 @@ impl ToolStep {
         self.name = Some(name.into());
         self
     }
+
+    /// 把上一步输出中 `path` 处的值写入输入字段 `field`（仅 Sequential 策略）
+    pub fn with_input_from(mut self, field: impl Into<String>, path: impl Into<String>) -> Self {
+        self.bindings.push(InputBinding {
+            field: field.into(),
+            source: None,
+            path: path.into(),
+        });
+        self
+    }
+
+    /// 把指定前序步骤输出中 `path` 处的值写入输入字段 `field`
+    pub fn with_input_from_step(
+        mut self,
+        field: impl Into<String>,
+        source: impl Into<String>,
+        path: impl Into<String>,
+    ) -> Self {
+        self.bindings.push(InputBinding {
+            field: field.into(),
+            source: Some(source.into()),
+            path: path.into(),
+        });
+        self
+    }
+}
+
+/// Map 策略：从合并后的输入中按 `items` 路径取出数组，每个元素写入 `item_key` 后调用 `step`
+///
+/// 结果按原顺序汇总为 JSON 数组，工具输出能解析为 JSON 时保留结构。
+#[derive(Clone, Debug)]
+pub struct MapStep {
+    pub step: ToolStep,
+    /// 数组在输入中的路径，支持 `a.b` 形式
+    pub items: String,
+    pub item_key: String,
+    /// 最大并发数，未设置时使用编排器的 `max_concurrency`
+    pub concurrency: Option<usize>,
+}
+
+impl MapStep {
+    pub fn new(step: ToolStep, items: impl Into<String>) -> Self {
+        Self {
+            step,
+            items: items.into(),
+            item_key: "item".to_string(),
+            concurrency: None,
+        }
+    }
+
+    pub fn with_item_key(mut self, key: impl Into<String>) -> Self {
+        self.item_key = key.into();
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -82,6 +156,8 @@ impl ToolPipeline {
 pub struct ToolOrchestrator {
     registry: ToolRegistry,
     pipelines: HashMap<String, ToolPipeline>,
+    validate_outputs: bool,
+    max_concurrency: Option<usize>,
 }
 
 impl ToolOrchestrator {
@@ -89,9 +165,25 @@ impl ToolOrchestrator {
         Self {
             registry,
             pipelines: HashMap::new(),
+            validate_outputs: false,
+            max_concurrency: None,
         }
     }
 
+    /// 限制 Parallel / Map 策略同时执行的步骤数，默认不限制
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = Some(max_concurrency.max(1));
+        self
+    }
+
+    /// 按 `ToolManifest` 输出端口的 schema 校验每个步骤的输出
+    ///
+    /// 校验失败视为步骤失败：顺序/并行策略立即返回错误，Fallback 策略切换到下一个步骤。
+    pub fn with_output_validation(mut self, enabled: bool) -> Self {
+        self.validate_outputs = enabled;
+        self
+    }
+
     pub fn register_pipeline(&mut self, pipeline: ToolPipeline) -> Result<()> {
         self.pipelines.insert(pipeline.name.clone(), pipeline);
         Ok(())
@@ -147,25 +239,23 @@ impl ToolOrchestrator {
         match strategy {
             ToolStrategy::Sequential(steps) => {
                 let mut last_message = AgentMessage::system("tool.pipeline.start");
+                let mut outputs: Vec<(&ToolStep, Value)> = Vec::new();
                 for step in steps {
-                    let mut merged_input = step.input.clone();
-                    if let Some(obj) = merged_input.as_object_mut() {
-                        if let Some(params_obj) = params.as_object() {
-                            for (k, v) in params_obj {
-                                obj.entry(k.clone()).or_insert(v.clone());
-                            }
-                        }
-                    }
-                    
-                    let mut merged_step = step.clone();
-                    merged_step.input = merged_input;
+                    let mut merged_step = merge_params(step, &params);
+                    apply_bindings(&mut merged_step, &outputs)?;
                     last_message = self.execute_step(&merged_step, ctx).await?;
+                    outputs.push((step, parse_output(&last_message.content)));
                 }
                 Ok(last_message)
             }
             ToolStrategy::Parallel(steps) => {
-                let futures = steps.iter().map(|step| self.execute_step(step, ctx));
-                let results = future::join_all(futures).await;
+                let steps: Vec<ToolStep> = steps
+                    .iter()
+                    .map(|step| merge_params(step, &params))
+                    .collect();
+                let results = self
+                    .execute_concurrent(&steps, self.max_concurrency, ctx)
+                    .await;
                 let mut messages = Vec::new();
                 for result in results {
                     messages.push(result?);
@@ -174,6 +264,7 @@ impl ToolOrchestrator {
                     .map_err(|e| AgentFlowError::Serialization(e.to_string()))?;
                 Ok(AgentMessage::system(aggregated))
             }
+            ToolStrategy::Map(map) => self.execute_map(map, &params, ctx).await,
             ToolStrategy::Fallback(steps) => {
                 let mut last_error: Option<AgentFlowError> = None;
                 for step in steps {
@@ -190,7 +281,79 @@ impl ToolOrchestrator {
         }
     }
 
+    async fn execute_map(
+        &self,
+        map: &MapStep,
+        params: &Value,
+        ctx: &FlowContext,
+    ) -> Result<AgentMessage> {
+        let base = merge_params(&map.step, params);
+        let items = map
+            .items
+            .split('.')
+            .try_fold(&base.input, |value, key| value.get(key))
+            .and_then(Value::as_array)
+            .ok_or_else(|| {
+                AgentFlowError::Other(anyhow!(
+                    "map step `{}` expects an array at `{}`",
+                    map.step.tool,
+                    map.items
+                ))
+            })?;
+        let steps: Vec<ToolStep> = items
+            .iter()
+            .map(|item| {
+                let mut step = base.clone();
+                if let Some(obj) = step.input.as_object_mut() {
+                    obj.insert(map.item_key.clone(), item.clone());
+                }
+                step
+            })
+            .collect();
+
+        let results = self
+            .execute_concurrent(&steps, map.concurrency.or(self.max_concurrency), ctx)
+            .await;
+        let mut outputs = Vec::with_capacity(results.len());
+        for result in results {
+            outputs.push(parse_output(&result?.content));
+        }
+        let mut message = AgentMessage::system(Value::Array(outputs).to_string());
+        message.role = MessageRole::Tool;
+        message.from = map
+            .step
+            .name
+            .clone()
+            .unwrap_or_else(|| map.step.tool.clone());
+        Ok(message)
+    }
+
+    /// 并发执行步骤，结果保持输入顺序
+    async fn execute_concurrent(
+        &self,
+        steps: &[ToolStep],
+        limit: Option<usize>,
+        ctx: &FlowContext,
+    ) -> Vec<Result<AgentMessage>> {
+        let limit = limit.unwrap_or(steps.len()).max(1);
+        let tasks: Vec<_> = steps
+            .iter()
+            .map(|step| self.execute_step(step, ctx))
+            .collect();
+        stream::iter(tasks).buffered(limit).collect().await
+    }
+
     async fn execute_step(&self, step: &ToolStep, ctx: &FlowContext) -> Result<AgentMessage> {
+        let message = self.invoke_step(step, ctx).await?;
+        if self.validate_outputs {
+            if let Some(manifest) = self.registry.manifest(&step.tool) {
+                self.validate_output(&manifest, &message)?;
+            }
+        }
+        Ok(message)
+    }
+
+    async fn invoke_step(&self, step: &ToolStep, ctx: &FlowContext) -> Result<AgentMessage> {
         let tool = self
             .registry
             .get(&step.tool)
@@ -271,6 +434,293 @@ impl ToolOrchestrator {
                 "pipeline output role mismatch; expected tool message"
             );
         }
+        if !self.validate_outputs {
+            return Ok(());
+        }
+        let value = parse_output(&message.content);
+        for port in &manifest.outputs {
+            validate_port(port, &value).map_err(|reason| {
+                AgentFlowError::Other(anyhow!(
+                    "tool `{}` output `{}` does not match schema: {}",
+                    manifest.name,
+                    port.name,
+                    reason
+                ))
+            })?;
+        }
         Ok(())
     }
 }
+
+/// 把 pipeline 参数合并到步骤输入中，步骤自身的字段优先
+fn merge_params(step: &ToolStep, params: &Value) -> ToolStep {
+    let mut merged = step.clone();
+    if let (Some(obj), Some(params_obj)) = (merged.input.as_object_mut(), params.as_object()) {
+        for (k, v) in params_obj {
+            obj.entry(k.clone()).or_insert(v.clone());
+        }
+    }
+    merged
+}
+
+/// 工具输出能解析为 JSON 时保留结构，否则作为字符串
+fn parse_output(content: &str) -> Value {
+    serde_json::from_str(content).unwrap_or_else(|_| Value::String(content.to_string()))
+}
+
+/// 按输入绑定从前序步骤的输出中取值，绑定的值覆盖静态输入
+fn apply_bindings(step: &mut ToolStep, outputs: &[(&ToolStep, Value)]) -> Result<()> {
+    for binding in &step.bindings {
+        let source = match &binding.source {
+            None => outputs.last(),
+            Some(source) => outputs.iter().rev().find(|(prev, _)| {
+                prev.name.as_deref() == Some(source.as_str()) || prev.tool == *source
+            }),
+        };
+        let (_, output) = source.ok_or_else(|| {
+            AgentFlowError::Other(anyhow!(
+                "tool `{}` input `{}` is bound to `{}`, which has not run",
+                step.tool,
+                binding.field,
+                binding.source.as_deref().unwrap_or("the previous step")
+            ))
+        })?;
+        let value = JsonPath::parse(&binding.path)?
+            .extract(output)
+            .ok_or_else(|| {
+                AgentFlowError::Other(anyhow!(
+                    "tool `{}` input `{}`: `{}` matched nothing in {}",
+                    step.tool,
+                    binding.field,
+                    binding.path,
+                    output
+                ))
+            })?;
+        if !step.input.is_object() {
+            step.input = Value::Object(Default::default());
+        }
+        if let Some(obj) = step.input.as_object_mut() {
+            obj.insert(binding.field.clone(), value);
+        }
+    }
+    Ok(())
+}
+
+/// 校验端口：`json_schema` 为字符串时按已注册的 Schema 名称校验，为对象时按内联 Schema 校验；
+/// 未配置 `json_schema` 时只检查 `type_name`
+fn validate_port(port: &ToolPort, value: &Value) -> std::result::Result<(), String> {
+    let Some(schema) = &port.schema else {
+        return Ok(());
+    };
+    match &schema.json_schema {
+        Some(Value::String(name)) => validate_schema(name, value).map_err(|e| e.to_string()),
+        Some(inline) => {
+            let schema: Schema = serde_json::from_value(inline.clone())
+                .map_err(|e| format!("invalid json_schema: {}", e))?;
+            validate_value(&schema, value, &mut Vec::new()).map_err(|e| match e {
+                crate::schema::SchemaError::Validation { message, path } if !path.is_empty() => {
+                    format!("{} at `{}`", message, path.join("."))
+                }
+                other => other.to_string(),
+            })
+        }
+        None => match schema.type_name.as_deref() {
+            Some(type_name) if !matches_type(type_name, value) => {
+                Err(format!("expected {}", type_name))
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+fn matches_type(type_name: &str, value: &Value) -> bool {
+    match type_name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryStore;
+    use crate::tools::{Tool, ToolPortSchema};
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    /// 原样返回输入中 `reply` 字段的测试工具
+    struct EchoTool(&'static str);
+
+    #[async_trait]
+    impl Tool for EchoTool {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        async fn call(
+            &self,
+            invocation: ToolInvocation,
+            _ctx: &FlowContext,
+        ) -> Result<AgentMessage> {
+            let mut message = AgentMessage::system(invocation.input["reply"].to_string());
+            message.role = MessageRole::Tool;
+            Ok(message)
+        }
+    }
+
+    fn manifest(name: &str) -> ToolManifest {
+        ToolManifest::builder(name)
+            .output(
+                ToolPort::new("result").with_schema(ToolPortSchema::new().with_json_schema(
+                    json!({
+                        "type": "object",
+                        "properties": { "answer": { "type": "string" } },
+                        "required": ["answer"]
+                    }),
+                )),
+            )
+            .build()
+    }
+
+    fn orchestrator(validate: bool) -> ToolOrchestrator {
+        let mut registry = ToolRegistry::new();
+        for name in ["primary", "backup"] {
+            registry
+                .register_with_manifest(Arc::new(EchoTool(name)), manifest(name))
+                .unwrap();
+        }
+        ToolOrchestrator::new(registry).with_output_validation(validate)
+    }
+
+    #[tokio::test]
+    async fn test_output_validation_fails_fast_and_falls_back() {
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+        let bad = ToolStep::new("primary", json!({ "reply": { "wrong": 1 } }));
+        let good = ToolStep::new("backup", json!({ "reply": { "answer": "ok" } }));
+
+        let unchecked = orchestrator(false)
+            .execute_strategy(&ToolStrategy::Sequential(vec![bad.clone()]), &ctx)
+            .await;
+        assert!(unchecked.is_ok());
+
+        let orchestrator = orchestrator(true);
+        let err = orchestrator
+            .execute_strategy(&ToolStrategy::Sequential(vec![bad.clone()]), &ctx)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("missing required property `answer`"));
+
+        let message = orchestrator
+            .execute_strategy(&ToolStrategy::Fallback(vec![bad, good]), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(message.content, r#"{"answer":"ok"}"#);
+    }
+
+    /// 记录最大并发数、返回 `item * 2` 的测试工具
+    #[derive(Default)]
+    struct DoubleTool {
+        running: std::sync::atomic::AtomicUsize,
+        peak: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Tool for DoubleTool {
+        fn name(&self) -> &'static str {
+            "double"
+        }
+
+        async fn call(
+            &self,
+            invocation: ToolInvocation,
+            _ctx: &FlowContext,
+        ) -> Result<AgentMessage> {
+            use std::sync::atomic::Ordering;
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            let value = invocation.input["chunk"].as_i64().unwrap_or_default();
+            Ok(AgentMessage::system(
+                json!({ "value": value * 2 }).to_string(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_map_strategy_respects_concurrency() {
+        let tool = Arc::new(DoubleTool::default());
+        let mut registry = ToolRegistry::new();
+        registry.register(tool.clone());
+        let orchestrator = ToolOrchestrator::new(registry);
+        let strategy = ToolStrategy::Map(
+            MapStep::new(ToolStep::new("double", json!({})), "data.chunks")
+                .with_item_key("chunk")
+                .with_concurrency(2),
+        );
+
+        let message = orchestrator
+            .execute_strategy_with_params(
+                &strategy,
+                json!({ "data": { "chunks": [1, 2, 3, 4, 5] } }),
+                &FlowContext::new(Arc::new(MemoryStore::new())),
+            )
+            .await
+            .unwrap();
+        let output: Value = serde_json::from_str(&message.content).unwrap();
+        assert_eq!(output.as_array().unwrap().len(), 5);
+        assert_eq!(output[4]["value"], 10);
+        assert_eq!(message.role, MessageRole::Tool);
+        assert_eq!(tool.peak.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let err = orchestrator
+            .execute_strategy(&strategy, &FlowContext::new(Arc::new(MemoryStore::new())))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("expects an array"));
+    }
+
+    #[tokio::test]
+    async fn test_sequential_steps_pipe_outputs() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoTool("primary")));
+        registry.register(Arc::new(EchoTool("backup")));
+        let orchestrator = ToolOrchestrator::new(registry);
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+
+        let strategy = ToolStrategy::Sequential(vec![
+            ToolStep::new(
+                "primary",
+                json!({ "reply": { "payload": { "total_calories": 520 } } }),
+            )
+            .with_name("macros"),
+            ToolStep::new("backup", json!({ "reply": "static" }))
+                .with_input_from("reply", "$.payload.total_calories"),
+            ToolStep::new("backup", json!({})).with_input_from_step("reply", "macros", "$.payload"),
+        ]);
+        let message = orchestrator
+            .execute_strategy(&strategy, &ctx)
+            .await
+            .unwrap();
+        assert_eq!(message.content, r#"{"total_calories":520}"#);
+
+        let broken = ToolStrategy::Sequential(vec![
+            ToolStep::new("primary", json!({ "reply": {} })),
+            ToolStep::new("backup", json!({})).with_input_from("reply", "$.missing"),
+        ]);
+        let err = orchestrator
+            .execute_strategy(&broken, &ctx)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("matched nothing"));
+    }
+}
@@ -54,6 +54,17 @@ impl ToolRegistry {
             .and_then(|entry| entry.manifest.as_ref().map(Arc::clone))
     }
 
+    /// 已登记的工具清单，按名称排序
+    pub fn manifests(&self) -> Vec<Arc<ToolManifest>> {
+        let mut manifests: Vec<_> = self
+            .tools
+            .values()
+            .filter_map(|entry| entry.manifest.as_ref().map(Arc::clone))
+            .collect();
+        manifests.sort_by(|a, b| a.name.cmp(&b.name));
+        manifests
+    }
+
     fn insert(&mut self, tool: Arc<dyn Tool>, manifest: Option<ToolManifest>) -> Result<()> {
         if let Some(ref manifest) = manifest {
             if manifest.name != tool.name() {
@@ -0,0 +1,603 @@
+//! 命令执行工具 - 带沙箱策略的 shell 命令执行（内置工具）
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+
+use crate::agent::{AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::state::FlowContext;
+use crate::tools::tool::{Tool, ToolInvocation};
+
+/// 命令执行策略
+///
+/// 执行前检查；`allowed_commands` 为空时拒绝所有命令。
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ShellPolicy {
+    /// 允许执行的程序（与命令完全一致才放行，如 `python3`、`/usr/bin/cargo`）
+    #[serde(default)]
+    pub allowed_commands: Vec<String>,
+    /// 命令行中出现即拒绝的片段（如 `rm -rf`、`sudo`）
+    #[serde(default)]
+    pub denied_patterns: Vec<String>,
+    /// 是否允许通过 `sh -c` 执行脚本
+    #[serde(default)]
+    pub allow_shell: bool,
+    /// 允许的工作目录前缀（为空时不限制；比较前解析符号链接）
+    #[serde(default)]
+    pub allowed_dirs: Vec<String>,
+}
+
+impl ShellPolicy {
+    /// 检查命令是否允许执行
+    pub fn check(&self, program: &str, args: &[String], cwd: Option<&Path>) -> Result<()> {
+        let deny = |reason: String| {
+            Err(AgentFlowError::Other(anyhow::anyhow!(
+                "shell.exec denied by policy: {}",
+                reason
+            )))
+        };
+
+        // 只做精确匹配：按文件名匹配时 `/tmp/evil/echo` 会冒充 `echo`
+        if !self
+            .allowed_commands
+            .iter()
+            .any(|allowed| allowed == program)
+        {
+            return deny(format!("command `{}` is not allowed", program));
+        }
+
+        let command_line = std::iter::once(program)
+            .chain(args.iter().map(|a| a.as_str()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if let Some(pattern) = self
+            .denied_patterns
+            .iter()
+            .find(|pattern| command_line.contains(pattern.as_str()))
+        {
+            return deny(format!("command contains denied pattern `{}`", pattern));
+        }
+
+        if let Some(cwd) = cwd {
+            if !self.allowed_dirs.is_empty() {
+                // 解析符号链接后再比较，避免允许目录中的链接指向目录之外
+                let cwd = resolve_path(cwd);
+                let inside = self
+                    .allowed_dirs
+                    .iter()
+                    .any(|dir| cwd.starts_with(resolve_path(Path::new(dir))));
+                if !inside {
+                    return deny(format!("working dir `{}` is not allowed", cwd.display()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 解析路径：已存在的最长前缀取真实路径（解析符号链接），其余部分按词法规范化
+fn resolve_path(path: &Path) -> PathBuf {
+    let absolute = absolute_path(path);
+    let mut existing = absolute.as_path();
+    loop {
+        if let Ok(real) = existing.canonicalize() {
+            let rest = absolute.strip_prefix(existing).unwrap_or(Path::new(""));
+            return normalize_path(&real.join(rest));
+        }
+        match existing.parent() {
+            Some(parent) => existing = parent,
+            None => return normalize_path(&absolute),
+        }
+    }
+}
+
+fn absolute_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("/"))
+            .join(path)
+    }
+}
+
+/// 词法规范化路径（处理 `.` 与 `..`，不访问文件系统）
+fn normalize_path(path: &Path) -> PathBuf {
+    let absolute = absolute_path(path);
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// 命令执行工具配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShellExecConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    /// 允许从当前进程继承的环境变量
+    #[serde(default = "ShellExecConfig::default_env_whitelist")]
+    pub env_whitelist: Vec<String>,
+    /// 额外设置的环境变量
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default = "ShellExecConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// stdout / stderr 各自保留的最大字节数
+    #[serde(default = "ShellExecConfig::default_max_output_bytes")]
+    pub max_output_bytes: usize,
+    #[serde(default)]
+    pub policy: ShellPolicy,
+}
+
+impl ShellExecConfig {
+    fn default_env_whitelist() -> Vec<String> {
+        vec!["PATH".to_string(), "HOME".to_string(), "LANG".to_string()]
+    }
+
+    fn default_timeout_secs() -> u64 {
+        30
+    }
+
+    fn default_max_output_bytes() -> usize {
+        64 * 1024
+    }
+}
+
+impl Default for ShellExecConfig {
+    fn default() -> Self {
+        Self {
+            working_dir: None,
+            env_whitelist: Self::default_env_whitelist(),
+            env: HashMap::new(),
+            timeout_secs: Self::default_timeout_secs(),
+            max_output_bytes: Self::default_max_output_bytes(),
+            policy: ShellPolicy::default(),
+        }
+    }
+}
+
+/// 命令执行工具
+///
+/// 输入参数：
+/// - command: 程序名；args: 参数数组
+/// - 或 script: 脚本文本（需策略允许 `allow_shell`，以 `sh -c` 执行）
+/// - cwd: 工作目录（可选，覆盖配置）
+/// - stdin: 标准输入（可选）
+/// - timeout_secs: 超时（可选，不超过配置值）
+///
+/// 输出：`exit_code`、`stdout`、`stderr`、`timed_out`、`duration_ms`。
+#[derive(Clone, Default)]
+pub struct ShellExecTool {
+    config: ShellExecConfig,
+}
+
+impl ShellExecTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: ShellExecConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &ShellExecConfig {
+        &self.config
+    }
+
+    fn parse_command(&self, input: &Value) -> Result<(String, Vec<String>)> {
+        if let Some(script) = input["script"].as_str() {
+            if !self.config.policy.allow_shell {
+                return Err(AgentFlowError::Other(anyhow::anyhow!(
+                    "shell.exec denied by policy: shell scripts are not allowed"
+                )));
+            }
+            return Ok(("sh".to_string(), vec!["-c".to_string(), script.to_string()]));
+        }
+
+        let command = input["command"]
+            .as_str()
+            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing command")))?;
+        let args = input["args"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .map(|v| match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok((command.to_string(), args))
+    }
+}
+
+fn truncate_output(bytes: &[u8], max: usize) -> (String, bool) {
+    if bytes.len() > max {
+        (String::from_utf8_lossy(&bytes[..max]).into_owned(), true)
+    } else {
+        (String::from_utf8_lossy(bytes).into_owned(), false)
+    }
+}
+
+/// 最多保留 `max + 1` 字节（多出的 1 字节用于判断截断），其余输出读出后丢弃，
+/// 避免子进程写满管道阻塞
+async fn read_bounded<R: AsyncRead + Unpin>(pipe: &mut R, max: usize) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let _ = (&mut *pipe)
+        .take(max as u64 + 1)
+        .read_to_end(&mut buf)
+        .await;
+    let _ = tokio::io::copy(pipe, &mut tokio::io::sink()).await;
+    buf
+}
+
+/// 子进程执行结果
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ProcessOutput {
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub stdout_truncated: bool,
+    pub stderr_truncated: bool,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+}
+
+impl ProcessOutput {
+    pub fn success(&self) -> bool {
+        !self.timed_out && self.exit_code == Some(0)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "success": self.success(),
+            "exit_code": self.exit_code,
+            "stdout": self.stdout,
+            "stderr": self.stderr,
+            "stdout_truncated": self.stdout_truncated,
+            "stderr_truncated": self.stderr_truncated,
+            "timed_out": self.timed_out,
+            "duration_ms": self.duration_ms,
+        })
+    }
+}
+
+/// 清空环境变量，只保留白名单中的变量与额外设置的变量
+pub(crate) fn apply_env(
+    command: &mut Command,
+    whitelist: &[String],
+    env: &HashMap<String, String>,
+) {
+    command.env_clear();
+    for key in whitelist {
+        if let Ok(value) = std::env::var(key) {
+            command.env(key, value);
+        }
+    }
+    command.envs(env);
+}
+
+/// 结束时终止子进程所在的整个进程组，`sh -c` 启动的孙进程不会遗留
+#[cfg(unix)]
+struct ProcessGroupGuard(Option<u32>);
+
+#[cfg(unix)]
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            let _ = std::process::Command::new("kill")
+                .args(["-KILL", "--", &format!("-{pid}")])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+    }
+}
+
+/// 运行子进程并收集输出，超时后终止进程
+///
+/// Unix 下子进程在新的进程组中运行，返回或超时后整组终止。
+pub(crate) async fn run_process(
+    mut command: Command,
+    program: &str,
+    stdin_data: Option<String>,
+    timeout: Duration,
+    max_output_bytes: usize,
+) -> Result<ProcessOutput> {
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let started = Instant::now();
+    let mut child = command.spawn().map_err(|e| {
+        AgentFlowError::Other(anyhow::anyhow!("Failed to spawn `{}`: {}", program, e))
+    })?;
+    #[cfg(unix)]
+    let _group = ProcessGroupGuard(child.id());
+
+    let mut stdin = child.stdin.take();
+    let mut stdout = child.stdout.take();
+    let mut stderr = child.stderr.take();
+
+    let io = async {
+        if let (Some(pipe), Some(data)) = (stdin.as_mut(), stdin_data) {
+            let _ = pipe.write_all(data.as_bytes()).await;
+        }
+        drop(stdin.take());
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let read_out = async {
+            if let Some(pipe) = stdout.as_mut() {
+                out = read_bounded(pipe, max_output_bytes).await;
+            }
+        };
+        let read_err = async {
+            if let Some(pipe) = stderr.as_mut() {
+                err = read_bounded(pipe, max_output_bytes).await;
+            }
+        };
+        tokio::join!(read_out, read_err);
+        let status = child.wait().await;
+        (status, out, err)
+    };
+
+    let (exit_code, stdout_bytes, stderr_bytes, timed_out) = match tokio::time::timeout(timeout, io)
+        .await
+    {
+        Ok((status, out, err)) => {
+            let status = status.map_err(|e| {
+                AgentFlowError::Other(anyhow::anyhow!("Failed to wait for `{}`: {}", program, e))
+            })?;
+            (status.code(), out, err, false)
+        }
+        // 超时后 child 随 future 一起被 drop，kill_on_drop 终止进程，进程组由 guard 终止
+        Err(_) => (None, Vec::new(), Vec::new(), true),
+    };
+
+    let (stdout, stdout_truncated) = truncate_output(&stdout_bytes, max_output_bytes);
+    let (stderr, stderr_truncated) = truncate_output(&stderr_bytes, max_output_bytes);
+
+    Ok(ProcessOutput {
+        exit_code,
+        stdout,
+        stderr,
+        stdout_truncated,
+        stderr_truncated,
+        timed_out,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+#[async_trait]
+impl Tool for ShellExecTool {
+    fn name(&self) -> &'static str {
+        "shell.exec"
+    }
+
+    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let input = &invocation.input;
+        let (program, args) = self.parse_command(input)?;
+        let cwd = input["cwd"]
+            .as_str()
+            .map(|s| s.to_string())
+            .or_else(|| self.config.working_dir.clone())
+            .map(PathBuf::from);
+
+        // `sh -c` 时检查的是 sh 本身，脚本内容仍经过 denied_patterns 过滤
+        self.config.policy.check(&program, &args, cwd.as_deref())?;
+
+        let timeout = Duration::from_secs(
+            input["timeout_secs"]
+                .as_u64()
+                .map(|t| t.min(self.config.timeout_secs))
+                .unwrap_or(self.config.timeout_secs)
+                .max(1),
+        );
+
+        let mut command = Command::new(&program);
+        command.args(&args);
+        apply_env(&mut command, &self.config.env_whitelist, &self.config.env);
+        if let Some(cwd) = &cwd {
+            command.current_dir(cwd);
+        }
+
+        let output = run_process(
+            command,
+            &program,
+            input["stdin"].as_str().map(|s| s.to_string()),
+            timeout,
+            self.config.max_output_bytes,
+        )
+        .await?;
+
+        let mut result = output.to_json();
+        result["command"] = json!(program);
+        result["args"] = json!(args);
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name().to_string(),
+            to: None,
+            content: result.to_string(),
+            metadata: invocation.metadata,
+            attachments: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryStore;
+    use std::sync::Arc;
+
+    fn policy() -> ShellPolicy {
+        ShellPolicy {
+            allowed_commands: vec!["echo".to_string(), "sh".to_string()],
+            denied_patterns: vec!["rm -rf".to_string()],
+            allow_shell: true,
+            allowed_dirs: vec!["/tmp".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_policy_checks() {
+        let policy = policy();
+        assert!(policy.check("echo", &["hi".to_string()], None).is_ok());
+        assert!(policy
+            .check("echo", &[], Some(Path::new("/tmp/work")))
+            .is_ok());
+        assert!(policy.check("/bin/echo", &[], None).is_err());
+        assert!(policy.check("/tmp/evil/echo", &[], None).is_err());
+        assert!(policy.check("./echo", &[], None).is_err());
+        assert!(policy.check("curl", &[], None).is_err());
+        assert!(policy
+            .check("sh", &["-c".to_string(), "rm -rf /".to_string()], None)
+            .is_err());
+        assert!(policy
+            .check("echo", &[], Some(Path::new("/tmp/../etc")))
+            .is_err());
+        assert!(ShellPolicy::default().check("echo", &[], None).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_allowed_dirs_resolve_symlinks() {
+        let root = tempfile::tempdir().unwrap();
+        let allowed = root.path().join("work");
+        let outside = root.path().join("outside");
+        std::fs::create_dir_all(allowed.join("sub")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, allowed.join("escape")).unwrap();
+
+        let policy = ShellPolicy {
+            allowed_dirs: vec![allowed.display().to_string()],
+            ..policy()
+        };
+        assert!(policy
+            .check("echo", &[], Some(&allowed.join("sub")))
+            .is_ok());
+        assert!(policy
+            .check("echo", &[], Some(&allowed.join("new")))
+            .is_ok());
+        assert!(policy
+            .check("echo", &[], Some(&allowed.join("escape")))
+            .is_err());
+        assert!(policy
+            .check("echo", &[], Some(&allowed.join("escape/../..")))
+            .is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout_kills_grandchildren() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+        let tool = ShellExecTool::with_config(ShellExecConfig {
+            policy: policy(),
+            ..ShellExecConfig::default()
+        });
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+
+        // 后台进程在超时后才写入标记文件
+        let script = format!(
+            "(sleep 2; touch {}) > /dev/null 2>&1 & sleep 5",
+            marker.display()
+        );
+        let message = tool
+            .call(
+                ToolInvocation::new("shell.exec", json!({ "script": script, "timeout_secs": 1 })),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        let result: Value = serde_json::from_str(&message.content).unwrap();
+        assert_eq!(result["timed_out"], true);
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_exec_and_timeout() {
+        let tool = ShellExecTool::with_config(ShellExecConfig {
+            policy: policy(),
+            ..ShellExecConfig::default()
+        });
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+
+        let message = tool
+            .call(
+                ToolInvocation::new(
+                    "shell.exec",
+                    json!({ "script": "echo out; echo err 1>&2; exit 3" }),
+                ),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        let result: Value = serde_json::from_str(&message.content).unwrap();
+        assert_eq!(result["exit_code"], 3);
+        assert_eq!(result["stdout"], "out\n");
+        assert_eq!(result["stderr"], "err\n");
+        assert_eq!(result["success"], false);
+
+        let message = tool
+            .call(
+                ToolInvocation::new(
+                    "shell.exec",
+                    json!({ "script": "sleep 5", "timeout_secs": 1 }),
+                ),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        let result: Value = serde_json::from_str(&message.content).unwrap();
+        assert_eq!(result["timed_out"], true);
+    }
+
+    #[tokio::test]
+    async fn test_output_is_bounded() {
+        let tool = ShellExecTool::with_config(ShellExecConfig {
+            policy: policy(),
+            max_output_bytes: 16,
+            ..ShellExecConfig::default()
+        });
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+
+        let message = tool
+            .call(
+                ToolInvocation::new("shell.exec", json!({ "script": "yes | head -c 1000000" })),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        let result: Value = serde_json::from_str(&message.content).unwrap();
+        assert_eq!(result["exit_code"], 0);
+        assert_eq!(result["stdout"].as_str().unwrap().len(), 16);
+        assert_eq!(result["stdout_truncated"], true);
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::EnvConfig;
+use crate::error::{AgentFlowError, Result};
+use crate::llm::embedding::{embedding_request_body, parse_embeddings};
+use crate::llm::DynLlmClient;
+
+/// 文本向量化
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Embedding 接口配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmbeddingConfig {
+    /// 完整的 `text_embedding` 端点地址，例如 `https://api.openai.com/v1/embeddings`
+    pub endpoint: String,
+    pub model: String,
+    /// API Key（支持 `${VAR}`），为空时读取 `OPENAI_API_KEY`
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default = "EmbeddingConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl EmbeddingConfig {
+    pub fn new(endpoint: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            model: model.into(),
+            api_key: None,
+            timeout_secs: Self::default_timeout_secs(),
+        }
+    }
+
+    /// 从 `ApiEndpointConfig` 的 `text_embedding` 端点构建
+    #[cfg(feature = "openai-client")]
+    pub fn from_endpoint_config(
+        config: &crate::llm::ApiEndpointConfig,
+        model: impl Into<String>,
+    ) -> Option<Self> {
+        Some(Self::new(config.get_endpoint("text_embedding")?, model))
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    fn default_timeout_secs() -> u64 {
+        30
+    }
+}
+
+/// 基于 HTTP 的 Embedding 客户端（OpenAI 兼容格式，兼容 DashScope 原生响应）
+#[derive(Clone)]
+pub struct HttpEmbedder {
+    config: EmbeddingConfig,
+    client: Client,
+}
+
+impl HttpEmbedder {
+    pub fn new(config: EmbeddingConfig) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build()
+            .unwrap_or_default();
+        Self { config, client }
+    }
+
+    pub fn config(&self) -> &EmbeddingConfig {
+        &self.config
+    }
+}
+
+#[async_trait]
+impl Embedder for HttpEmbedder {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let api_key = EnvConfig::get_api_key(
+            self.config.api_key.as_deref().unwrap_or(""),
+            "OPENAI_API_KEY",
+        )?;
+        let expected = texts.len();
+        let response = self
+            .client
+            .post(&self.config.endpoint)
+            .bearer_auth(api_key)
+            .json(&embedding_request_body(&self.config.model, &texts, false))
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Embedding request failed: {}", e)))?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Embedding endpoint returned {}: {}",
+                status,
+                body
+            )));
+        }
+        let vectors = parse_embeddings(&body)
+            .filter(|vectors| vectors.len() == expected)
+            .ok_or_else(|| {
+                AgentFlowError::Other(anyhow!("Invalid embedding response: {}", body))
+            })?;
+        Ok(vectors)
+    }
+}
+
+/// 使用 `LlmClient::embed` 的 Embedder
+#[derive(Clone)]
+pub struct LlmEmbedder {
+    client: DynLlmClient,
+}
+
+impl LlmEmbedder {
+    pub fn new(client: DynLlmClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Embedder for LlmEmbedder {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.client.embed(texts).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{LlmClient, LlmRequest, LlmResponse};
+    use std::sync::Arc;
+
+    #[derive(Clone)]
+    struct LengthClient;
+
+    #[async_trait]
+    impl LlmClient for LengthClient {
+        async fn complete(&self, _request: LlmRequest) -> Result<LlmResponse> {
+            unreachable!()
+        }
+
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|t| vec![t.len() as f32]).collect())
+        }
+
+        fn clone_dyn(&self) -> DynLlmClient {
+            Arc::new(self.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_llm_embedder_uses_client() {
+        let embedder = LlmEmbedder::new(Arc::new(LengthClient));
+        let vectors = embedder
+            .embed(vec!["ab".into(), "abc".into()])
+            .await
+            .unwrap();
+        assert_eq!(vectors, vec![vec![2.0], vec![3.0]]);
+
+        let echo = LlmEmbedder::new(Arc::new(crate::llm::LocalEchoClient));
+        assert!(echo.embed(vec!["x".into()]).await.is_err());
+    }
+}
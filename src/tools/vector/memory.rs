@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use parking_lot::RwLock;
+
+use super::{
+    cosine_similarity, matches_filter, VectorMatch, VectorQuery, VectorRecord, VectorStore,
+};
+use crate::error::Result;
+
+/// 进程内向量存储（暴力检索，适合测试和小规模知识库）
+pub struct InMemoryVectorStore {
+    name: String,
+    records: RwLock<HashMap<String, VectorRecord>>,
+}
+
+impl InMemoryVectorStore {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            records: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.read().is_empty()
+    }
+}
+
+impl Default for InMemoryVectorStore {
+    fn default() -> Self {
+        Self::new("memory")
+    }
+}
+
+#[async_trait]
+impl VectorStore for InMemoryVectorStore {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<()> {
+        let mut guard = self.records.write();
+        for record in records {
+            guard.insert(record.id.clone(), record);
+        }
+        Ok(())
+    }
+
+    async fn query(&self, query: VectorQuery) -> Result<Vec<VectorMatch>> {
+        let guard = self.records.read();
+        let mut matches: Vec<VectorMatch> = guard
+            .values()
+            .filter(|record| matches_filter(&record.metadata, &query.filter))
+            .map(|record| VectorMatch {
+                id: record.id.clone(),
+                score: cosine_similarity(&query.vector, &record.vector),
+                content: record.content.clone(),
+                metadata: record.metadata.clone(),
+            })
+            .filter(|m| query.min_score.is_none_or(|min| m.score >= min))
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(query.top_k);
+        Ok(matches)
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<()> {
+        let mut guard = self.records.write();
+        for id in ids {
+            guard.remove(id);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_memory_store_query_and_delete() {
+        let store = InMemoryVectorStore::default();
+        store
+            .upsert(vec![
+                VectorRecord::new("a", vec![1.0, 0.0], "alpha")
+                    .with_metadata(json!({"lang": "en"})),
+                VectorRecord::new("b", vec![0.0, 1.0], "beta").with_metadata(json!({"lang": "en"})),
+                VectorRecord::new("c", vec![0.9, 0.1], "gamma")
+                    .with_metadata(json!({"lang": "zh"})),
+            ])
+            .await
+            .unwrap();
+
+        let hits = store
+            .query(VectorQuery::new(vec![1.0, 0.0], 2))
+            .await
+            .unwrap();
+        assert_eq!(
+            hits.iter().map(|h| h.id.as_str()).collect::<Vec<_>>(),
+            ["a", "c"]
+        );
+
+        let hits = store
+            .query(
+                VectorQuery::new(vec![1.0, 0.0], 5)
+                    .with_filter("lang", json!("en"))
+                    .with_min_score(0.5),
+            )
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].content, "alpha");
+
+        store.delete(&["a".to_string()]).await.unwrap();
+        assert_eq!(store.len(), 2);
+    }
+}
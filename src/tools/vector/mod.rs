@@ -0,0 +1,225 @@
+//! 向量存储抽象与检索工具
+//!
+//! `VectorStore` 统一内存、Qdrant、pgvector 等后端，`rag.retrieve` 工具基于它完成 RAG 检索。
+
+pub mod embedding;
+pub mod memory;
+#[cfg(feature = "pgvector")]
+pub mod pgvector;
+pub mod qdrant;
+pub mod rerank;
+pub mod retrieve;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{AgentFlowError, Result};
+
+pub use embedding::{Embedder, EmbeddingConfig, HttpEmbedder, LlmEmbedder};
+pub use memory::InMemoryVectorStore;
+#[cfg(feature = "pgvector")]
+pub use pgvector::PgVectorStore;
+pub use qdrant::QdrantVectorStore;
+pub use rerank::{HttpReranker, RerankConfig, RerankFormat, RerankResult, Reranker};
+pub use retrieve::{RagRetrieveConfig, RagRetrieveTool, RagRetrieveToolConfig};
+
+/// 向量记录（一个文本块及其向量）
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VectorRecord {
+    pub id: String,
+    pub vector: Vec<f32>,
+    pub content: String,
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+impl VectorRecord {
+    pub fn new(id: impl Into<String>, vector: Vec<f32>, content: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            vector,
+            content: content.into(),
+            metadata: Value::Object(Default::default()),
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+/// 向量检索条件
+#[derive(Clone, Debug, Default)]
+pub struct VectorQuery {
+    pub vector: Vec<f32>,
+    pub top_k: usize,
+    /// 元数据等值过滤
+    pub filter: HashMap<String, Value>,
+    /// 最低相似度
+    pub min_score: Option<f32>,
+}
+
+impl VectorQuery {
+    pub fn new(vector: Vec<f32>, top_k: usize) -> Self {
+        Self {
+            vector,
+            top_k,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_filter(mut self, key: impl Into<String>, value: Value) -> Self {
+        self.filter.insert(key.into(), value);
+        self
+    }
+
+    pub fn with_min_score(mut self, min_score: f32) -> Self {
+        self.min_score = Some(min_score);
+        self
+    }
+}
+
+/// 检索命中结果
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VectorMatch {
+    pub id: String,
+    pub score: f32,
+    pub content: String,
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+/// 向量存储
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// 写入或覆盖记录
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<()>;
+
+    /// 按相似度返回 top-k 记录（分数从高到低）
+    async fn query(&self, query: VectorQuery) -> Result<Vec<VectorMatch>>;
+
+    /// 按 ID 删除记录
+    async fn delete(&self, ids: &[String]) -> Result<()>;
+}
+
+pub type DynVectorStore = Arc<dyn VectorStore>;
+
+/// 向量存储后端配置（用于 JSON 配置的工具）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum VectorStoreConfig {
+    /// 进程内存储，同名配置共享同一实例
+    Memory {
+        #[serde(default = "VectorStoreConfig::default_name")]
+        name: String,
+    },
+    /// 通过 `register_vector_store` 注册的存储
+    Registered { name: String },
+    Qdrant {
+        url: String,
+        collection: String,
+        #[serde(default)]
+        api_key: Option<String>,
+    },
+    #[cfg(feature = "pgvector")]
+    Pgvector { url: String, table: String },
+}
+
+impl Default for VectorStoreConfig {
+    fn default() -> Self {
+        Self::Memory {
+            name: Self::default_name(),
+        }
+    }
+}
+
+impl VectorStoreConfig {
+    fn default_name() -> String {
+        "default".to_string()
+    }
+
+    /// 根据配置构建存储
+    pub fn build(&self) -> Result<DynVectorStore> {
+        match self {
+            Self::Memory { name } => {
+                let mut stores = registry().lock().map_err(|_| {
+                    AgentFlowError::Other(anyhow!("Vector store registry poisoned"))
+                })?;
+                let store = stores
+                    .entry(name.clone())
+                    .or_insert_with(|| Arc::new(InMemoryVectorStore::new(name.clone())));
+                Ok(store.clone())
+            }
+            Self::Registered { name } => vector_store(name).ok_or_else(|| {
+                AgentFlowError::Other(anyhow!("Vector store `{}` is not registered", name))
+            }),
+            Self::Qdrant {
+                url,
+                collection,
+                api_key,
+            } => {
+                let mut store = QdrantVectorStore::new(url, collection);
+                if let Some(api_key) = api_key {
+                    store = store.with_api_key(api_key);
+                }
+                Ok(Arc::new(store))
+            }
+            #[cfg(feature = "pgvector")]
+            Self::Pgvector { url, table } => Ok(Arc::new(PgVectorStore::new(url, table)?)),
+        }
+    }
+}
+
+static REGISTRY: OnceLock<Mutex<HashMap<String, DynVectorStore>>> = OnceLock::new();
+
+fn registry() -> &'static Mutex<HashMap<String, DynVectorStore>> {
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 注册具名向量存储，供 `rag.retrieve` 等工具按名称引用
+pub fn register_vector_store(name: impl Into<String>, store: DynVectorStore) {
+    if let Ok(mut guard) = registry().lock() {
+        guard.insert(name.into(), store);
+    } else {
+        tracing::warn!("failed to acquire vector store registry lock");
+    }
+}
+
+/// 获取具名向量存储
+pub fn vector_store(name: &str) -> Option<DynVectorStore> {
+    registry().lock().ok()?.get(name).cloned()
+}
+
+/// 余弦相似度
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let mut dot = 0.0;
+    let mut norm_a = 0.0;
+    let mut norm_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// 检查元数据是否满足等值过滤
+pub(crate) fn matches_filter(metadata: &Value, filter: &HashMap<String, Value>) -> bool {
+    filter
+        .iter()
+        .all(|(key, expected)| metadata.get(key) == Some(expected))
+}
@@ -0,0 +1,183 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::OnceCell;
+use tokio_postgres::{Client, NoTls};
+
+use super::{VectorMatch, VectorQuery, VectorRecord, VectorStore};
+use crate::error::{AgentFlowError, Result};
+
+/// PostgreSQL + pgvector 向量存储
+///
+/// 首次使用时建立连接。表结构：
+///
+/// ```sql
+/// CREATE TABLE {table} (
+///     id TEXT PRIMARY KEY,
+///     embedding VECTOR(<dim>),
+///     content TEXT NOT NULL,
+///     metadata JSONB NOT NULL DEFAULT '{}'
+/// );
+/// ```
+pub struct PgVectorStore {
+    url: String,
+    table: String,
+    client: OnceCell<Client>,
+}
+
+impl PgVectorStore {
+    pub fn new(url: impl Into<String>, table: impl Into<String>) -> Result<Self> {
+        let table = table.into();
+        if table.is_empty()
+            || !table
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Invalid pgvector table name: {}",
+                table
+            )));
+        }
+        Ok(Self {
+            url: url.into(),
+            table,
+            client: OnceCell::new(),
+        })
+    }
+
+    /// 创建 pgvector 扩展和数据表（已存在时忽略）
+    pub async fn ensure_table(&self, dimension: usize) -> Result<()> {
+        let sql = format!(
+            "CREATE EXTENSION IF NOT EXISTS vector; \
+             CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, embedding VECTOR({}), \
+             content TEXT NOT NULL, metadata JSONB NOT NULL DEFAULT '{{}}')",
+            self.table, dimension
+        );
+        self.client()
+            .await?
+            .batch_execute(&sql)
+            .await
+            .map_err(pg_error)
+    }
+
+    async fn client(&self) -> Result<&Client> {
+        self.client
+            .get_or_try_init(|| async {
+                let (client, connection) = tokio_postgres::connect(&self.url, NoTls)
+                    .await
+                    .map_err(pg_error)?;
+                tokio::spawn(async move {
+                    if let Err(e) = connection.await {
+                        tracing::warn!(error = %e, "pgvector connection closed");
+                    }
+                });
+                Ok(client)
+            })
+            .await
+    }
+}
+
+fn pg_error(e: tokio_postgres::Error) -> AgentFlowError {
+    AgentFlowError::Other(anyhow!("pgvector error: {}", e))
+}
+
+/// pgvector 的文本表示：`[1,2,3]`
+fn vector_literal(vector: &[f32]) -> String {
+    let items: Vec<String> = vector.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", items.join(","))
+}
+
+#[async_trait]
+impl VectorStore for PgVectorStore {
+    fn name(&self) -> &str {
+        &self.table
+    }
+
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<()> {
+        let client = self.client().await?;
+        let sql = format!(
+            "INSERT INTO {} (id, embedding, content, metadata) \
+             VALUES ($1, $2::text::vector, $3, $4::text::jsonb) \
+             ON CONFLICT (id) DO UPDATE SET embedding = EXCLUDED.embedding, \
+             content = EXCLUDED.content, metadata = EXCLUDED.metadata",
+            self.table
+        );
+        let statement = client.prepare(&sql).await.map_err(pg_error)?;
+        for record in records {
+            client
+                .execute(
+                    &statement,
+                    &[
+                        &record.id,
+                        &vector_literal(&record.vector),
+                        &record.content,
+                        &record.metadata.to_string(),
+                    ],
+                )
+                .await
+                .map_err(pg_error)?;
+        }
+        Ok(())
+    }
+
+    async fn query(&self, query: VectorQuery) -> Result<Vec<VectorMatch>> {
+        let client = self.client().await?;
+        let sql = format!(
+            "SELECT id, content, metadata::text, \
+             (1 - (embedding <=> $1::text::vector))::float8 AS score \
+             FROM {} WHERE metadata @> $2::text::jsonb \
+             ORDER BY embedding <=> $1::text::vector LIMIT $3",
+            self.table
+        );
+        let filter = Value::Object(query.filter.into_iter().collect()).to_string();
+        let rows = client
+            .query(
+                &sql,
+                &[
+                    &vector_literal(&query.vector),
+                    &filter,
+                    &(query.top_k as i64),
+                ],
+            )
+            .await
+            .map_err(pg_error)?;
+
+        let mut matches = Vec::with_capacity(rows.len());
+        for row in rows {
+            let score = row.get::<_, f64>(3) as f32;
+            if query.min_score.is_some_and(|min| score < min) {
+                continue;
+            }
+            let metadata: String = row.get(2);
+            matches.push(VectorMatch {
+                id: row.get(0),
+                score,
+                content: row.get(1),
+                metadata: serde_json::from_str(&metadata).unwrap_or(Value::Null),
+            });
+        }
+        Ok(matches)
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<()> {
+        let sql = format!("DELETE FROM {} WHERE id = ANY($1)", self.table);
+        self.client()
+            .await?
+            .execute(&sql, &[&ids])
+            .await
+            .map(|_| ())
+            .map_err(pg_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_name_and_vector_literal() {
+        assert!(PgVectorStore::new("postgres://localhost/db", "kb_chunks").is_ok());
+        assert!(PgVectorStore::new("postgres://localhost/db", "kb; DROP TABLE x").is_err());
+        assert_eq!(vector_literal(&[1.0, 0.5]), "[1,0.5]");
+    }
+}
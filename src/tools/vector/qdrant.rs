@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+
+use super::{VectorMatch, VectorQuery, VectorRecord, VectorStore};
+use crate::config::EnvConfig;
+use crate::error::{AgentFlowError, Result};
+
+/// Qdrant 向量存储（REST API）
+///
+/// Qdrant 的点 ID 只接受整数或 UUID，记录 ID 会被映射为确定性的 UUID，
+/// 原始 ID 保存在 payload 的 `id` 字段中。
+#[derive(Clone)]
+pub struct QdrantVectorStore {
+    url: String,
+    collection: String,
+    api_key: Option<String>,
+    client: Client,
+}
+
+impl QdrantVectorStore {
+    pub fn new(url: impl Into<String>, collection: impl Into<String>) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap_or_default();
+        Self {
+            url: url.into().trim_end_matches('/').to_string(),
+            collection: collection.into(),
+            api_key: None,
+            client,
+        }
+    }
+
+    /// 设置 API Key（支持 `${VAR}` 引用环境变量）
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// 创建集合（已存在时忽略）
+    pub async fn ensure_collection(&self, dimension: usize) -> Result<()> {
+        let exists = self
+            .request(self.client.get(self.collection_url("")))?
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Qdrant request failed: {}", e)))?
+            .status()
+            .is_success();
+        if exists {
+            return Ok(());
+        }
+        self.send(self.client.put(self.collection_url("")).json(&json!({
+            "vectors": { "size": dimension, "distance": "Cosine" }
+        })))
+        .await
+        .map(|_| ())
+    }
+
+    fn collection_url(&self, path: &str) -> String {
+        format!("{}/collections/{}{}", self.url, self.collection, path)
+    }
+
+    fn request(&self, builder: RequestBuilder) -> Result<RequestBuilder> {
+        Ok(match &self.api_key {
+            Some(key) => builder.header("api-key", EnvConfig::get_api_key(key, "QDRANT_API_KEY")?),
+            None => builder,
+        })
+    }
+
+    async fn send(&self, builder: RequestBuilder) -> Result<Value> {
+        let response = self
+            .request(builder)?
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Qdrant request failed: {}", e)))?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Qdrant returned {}: {}",
+                status,
+                body
+            )));
+        }
+        Ok(body)
+    }
+}
+
+/// 把任意字符串 ID 映射为 UUID 格式（FNV-1a，结果稳定）
+fn point_id(id: &str) -> String {
+    fn fnv1a(seed: u64, bytes: &[u8]) -> u64 {
+        bytes.iter().fold(seed, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+    let high = fnv1a(0xcbf2_9ce4_8422_2325, id.as_bytes());
+    let low = fnv1a(0x8422_2325_cbf2_9ce4, id.as_bytes());
+    let hex = format!("{:016x}{:016x}", high, low);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[async_trait]
+impl VectorStore for QdrantVectorStore {
+    fn name(&self) -> &str {
+        &self.collection
+    }
+
+    async fn upsert(&self, records: Vec<VectorRecord>) -> Result<()> {
+        let points: Vec<Value> = records
+            .into_iter()
+            .map(|record| {
+                json!({
+                    "id": point_id(&record.id),
+                    "vector": record.vector,
+                    "payload": {
+                        "id": record.id,
+                        "content": record.content,
+                        "metadata": record.metadata,
+                    },
+                })
+            })
+            .collect();
+        self.send(
+            self.client
+                .put(self.collection_url("/points?wait=true"))
+                .json(&json!({ "points": points })),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn query(&self, query: VectorQuery) -> Result<Vec<VectorMatch>> {
+        let mut body = json!({
+            "vector": query.vector,
+            "limit": query.top_k,
+            "with_payload": true,
+        });
+        if !query.filter.is_empty() {
+            let must: Vec<Value> = query
+                .filter
+                .iter()
+                .map(|(key, value)| {
+                    json!({ "key": format!("metadata.{}", key), "match": { "value": value } })
+                })
+                .collect();
+            body["filter"] = json!({ "must": must });
+        }
+        if let Some(min_score) = query.min_score {
+            body["score_threshold"] = json!(min_score);
+        }
+
+        let response = self
+            .send(
+                self.client
+                    .post(self.collection_url("/points/search"))
+                    .json(&body),
+            )
+            .await?;
+        let hits = response["result"].as_array().cloned().unwrap_or_default();
+        Ok(hits
+            .into_iter()
+            .map(|hit| {
+                let payload = &hit["payload"];
+                VectorMatch {
+                    id: payload["id"]
+                        .as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| hit["id"].to_string()),
+                    score: hit["score"].as_f64().unwrap_or_default() as f32,
+                    content: payload["content"].as_str().unwrap_or_default().to_string(),
+                    metadata: payload["metadata"].clone(),
+                }
+            })
+            .collect())
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<()> {
+        let points: Vec<String> = ids.iter().map(|id| point_id(id)).collect();
+        self.send(
+            self.client
+                .post(self.collection_url("/points/delete?wait=true"))
+                .json(&json!({ "points": points })),
+        )
+        .await
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_id_is_stable_uuid() {
+        let id = point_id("doc-1#0");
+        assert_eq!(id, point_id("doc-1#0"));
+        assert_ne!(id, point_id("doc-1#1"));
+        assert_eq!(id.len(), 36);
+        assert_eq!(id.matches('-').count(), 4);
+    }
+}
@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::EnvConfig;
+use crate::error::{AgentFlowError, Result};
+
+/// 单个文档的重排序结果
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RerankResult {
+    /// 文档在输入列表中的下标
+    pub index: usize,
+    pub score: f32,
+}
+
+/// 文档重排序
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// 返回按相关度降序排列的前 `top_n` 个结果
+    async fn rerank(
+        &self,
+        query: &str,
+        documents: &[String],
+        top_n: usize,
+    ) -> Result<Vec<RerankResult>>;
+}
+
+/// Rerank 接口格式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RerankFormat {
+    /// Cohere 兼容格式（Jina、vLLM、Xinference 等也兼容）
+    #[default]
+    Cohere,
+    /// DashScope `text-rerank` 原生格式
+    Dashscope,
+}
+
+impl RerankFormat {
+    fn default_env_key(&self) -> &'static str {
+        match self {
+            RerankFormat::Cohere => "COHERE_API_KEY",
+            RerankFormat::Dashscope => "QWEN_API_KEY",
+        }
+    }
+}
+
+/// Rerank 接口配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RerankConfig {
+    /// 完整的 rerank 端点地址，例如 `https://api.cohere.com/v2/rerank`
+    pub endpoint: String,
+    pub model: String,
+    #[serde(default)]
+    pub format: RerankFormat,
+    /// API Key（支持 `${VAR}`），为空时按格式读取 `COHERE_API_KEY` / `QWEN_API_KEY`
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default = "RerankConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl RerankConfig {
+    pub fn new(endpoint: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            model: model.into(),
+            format: RerankFormat::default(),
+            api_key: None,
+            timeout_secs: Self::default_timeout_secs(),
+        }
+    }
+
+    pub fn with_format(mut self, format: RerankFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    fn default_timeout_secs() -> u64 {
+        30
+    }
+}
+
+/// 基于 HTTP 的 Rerank 客户端
+#[derive(Clone)]
+pub struct HttpReranker {
+    config: RerankConfig,
+    client: Client,
+}
+
+impl HttpReranker {
+    pub fn new(config: RerankConfig) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build()
+            .unwrap_or_default();
+        Self { config, client }
+    }
+
+    pub fn config(&self) -> &RerankConfig {
+        &self.config
+    }
+
+    fn request_body(&self, query: &str, documents: &[String], top_n: usize) -> Value {
+        match self.config.format {
+            RerankFormat::Cohere => json!({
+                "model": self.config.model,
+                "query": query,
+                "documents": documents,
+                "top_n": top_n,
+            }),
+            RerankFormat::Dashscope => json!({
+                "model": self.config.model,
+                "input": { "query": query, "documents": documents },
+                "parameters": { "top_n": top_n, "return_documents": false },
+            }),
+        }
+    }
+}
+
+/// 解析 Rerank 响应：Cohere `results[]` 或 DashScope `output.results[]`，按分数降序
+pub(crate) fn parse_rerank(body: &Value) -> Option<Vec<RerankResult>> {
+    let items = body["results"]
+        .as_array()
+        .or_else(|| body["output"]["results"].as_array())?;
+    let mut results: Vec<RerankResult> = items
+        .iter()
+        .filter_map(|item| {
+            Some(RerankResult {
+                index: item["index"].as_u64()? as usize,
+                score: item["relevance_score"]
+                    .as_f64()
+                    .or_else(|| item["score"].as_f64())? as f32,
+            })
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Some(results)
+}
+
+#[async_trait]
+impl Reranker for HttpReranker {
+    async fn rerank(
+        &self,
+        query: &str,
+        documents: &[String],
+        top_n: usize,
+    ) -> Result<Vec<RerankResult>> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+        let api_key = EnvConfig::get_api_key(
+            self.config.api_key.as_deref().unwrap_or(""),
+            self.config.format.default_env_key(),
+        )?;
+        let response = self
+            .client
+            .post(&self.config.endpoint)
+            .bearer_auth(api_key)
+            .json(&self.request_body(query, documents, top_n))
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow!("Rerank request failed: {}", e)))?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(AgentFlowError::Other(anyhow!(
+                "Rerank endpoint returned {}: {}",
+                status,
+                body
+            )));
+        }
+        let mut results = parse_rerank(&body)
+            .ok_or_else(|| AgentFlowError::Other(anyhow!("Invalid rerank response: {}", body)))?;
+        results.retain(|result| result.index < documents.len());
+        results.truncate(top_n);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rerank_formats() {
+        let cohere = json!({ "results": [
+            { "index": 0, "relevance_score": 0.1 },
+            { "index": 2, "relevance_score": 0.9 }
+        ] });
+        assert_eq!(
+            parse_rerank(&cohere).unwrap(),
+            vec![
+                RerankResult {
+                    index: 2,
+                    score: 0.9
+                },
+                RerankResult {
+                    index: 0,
+                    score: 0.1
+                }
+            ]
+        );
+
+        let dashscope = json!({ "output": { "results": [
+            { "index": 1, "relevance_score": 0.5 }
+        ] } });
+        assert_eq!(parse_rerank(&dashscope).unwrap()[0].index, 1);
+        assert!(parse_rerank(&json!({ "message": "bad" })).is_none());
+    }
+
+    #[test]
+    fn test_dashscope_request_body() {
+        let reranker = HttpReranker::new(
+            RerankConfig::new("http://localhost", "gte-rerank")
+                .with_format(RerankFormat::Dashscope),
+        );
+        let body = reranker.request_body("q", &["a".to_string()], 3);
+        assert_eq!(body["input"]["documents"], json!(["a"]));
+        assert_eq!(body["parameters"]["top_n"], 3);
+    }
+}
@@ -0,0 +1,308 @@
+//! RAG 检索工具 - `rag.retrieve`（内置工具）
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{DynVectorStore, Embedder, Reranker, VectorQuery};
+use crate::agent::{AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::state::FlowContext;
+use crate::tools::tool::{Tool, ToolInvocation};
+
+/// 检索参数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RagRetrieveConfig {
+    #[serde(default = "RagRetrieveConfig::default_top_k")]
+    pub top_k: usize,
+    #[serde(default)]
+    pub min_score: Option<f32>,
+    /// 配置重排序时先召回的候选数量（默认 `top_k * 4`）
+    #[serde(default)]
+    pub rerank_candidates: Option<usize>,
+}
+
+impl Default for RagRetrieveConfig {
+    fn default() -> Self {
+        Self {
+            top_k: Self::default_top_k(),
+            min_score: None,
+            rerank_candidates: None,
+        }
+    }
+}
+
+impl RagRetrieveConfig {
+    fn default_top_k() -> usize {
+        4
+    }
+}
+
+/// RAG 检索工具
+///
+/// 输入参数：
+/// - query: 查询文本
+/// - top_k: 返回数量（可选）
+/// - filter: 元数据等值过滤（可选）
+///
+/// 输出：`chunks` 数组，每项包含 `id`、`score`、`content`、`metadata`。
+/// 配置重排序后先按向量召回候选，再用 rerank 分数排序截取 top_k，`score` 为 rerank 分数。
+#[derive(Clone)]
+pub struct RagRetrieveTool {
+    store: DynVectorStore,
+    embedder: Arc<dyn Embedder>,
+    reranker: Option<Arc<dyn Reranker>>,
+    config: RagRetrieveConfig,
+}
+
+impl RagRetrieveTool {
+    pub fn new(store: DynVectorStore, embedder: Arc<dyn Embedder>) -> Self {
+        Self::with_config(store, embedder, RagRetrieveConfig::default())
+    }
+
+    pub fn with_config(
+        store: DynVectorStore,
+        embedder: Arc<dyn Embedder>,
+        config: RagRetrieveConfig,
+    ) -> Self {
+        Self {
+            store,
+            embedder,
+            reranker: None,
+            config,
+        }
+    }
+
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
+    pub fn config(&self) -> &RagRetrieveConfig {
+        &self.config
+    }
+}
+
+#[async_trait]
+impl Tool for RagRetrieveTool {
+    fn name(&self) -> &'static str {
+        "rag.retrieve"
+    }
+
+    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let input = &invocation.input;
+        let query = input["query"]
+            .as_str()
+            .or_else(|| input["content"].as_str())
+            .filter(|q| !q.trim().is_empty())
+            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing query")))?;
+        let top_k = input["top_k"]
+            .as_u64()
+            .map(|n| n as usize)
+            .unwrap_or(self.config.top_k)
+            .max(1);
+
+        let vector = self
+            .embedder
+            .embed(vec![query.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Empty query embedding")))?;
+
+        let candidates = match &self.reranker {
+            Some(_) => self
+                .config
+                .rerank_candidates
+                .unwrap_or(top_k * 4)
+                .max(top_k),
+            None => top_k,
+        };
+        let mut vector_query = VectorQuery::new(vector, candidates);
+        vector_query.min_score = self.config.min_score;
+        if let Some(filter) = input["filter"].as_object() {
+            vector_query.filter = filter
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect::<HashMap<_, _>>();
+        }
+        let mut chunks = self.store.query(vector_query).await?;
+
+        if let Some(reranker) = &self.reranker {
+            let documents: Vec<String> = chunks.iter().map(|c| c.content.clone()).collect();
+            let ranked = reranker.rerank(query, &documents, top_k).await?;
+            chunks = ranked
+                .into_iter()
+                .filter_map(|result| {
+                    let mut chunk = chunks.get(result.index)?.clone();
+                    chunk.score = result.score;
+                    Some(chunk)
+                })
+                .collect();
+        }
+
+        let result = json!({
+            "success": true,
+            "store": self.store.name(),
+            "query": query,
+            "reranked": self.reranker.is_some(),
+            "chunks": chunks,
+        });
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name().to_string(),
+            to: None,
+            content: result.to_string(),
+            metadata: invocation.metadata,
+            attachments: Vec::new(),
+        })
+    }
+}
+
+/// 检索工具配置：`{"store": {...}, "embedding": {...}, "rerank": {...}, "top_k": 4}`
+#[derive(Clone, Debug, Deserialize)]
+pub struct RagRetrieveToolConfig {
+    #[serde(default)]
+    pub store: super::VectorStoreConfig,
+    /// Embedding 接口，未配置时需在构建时提供 Embedder
+    #[serde(default)]
+    pub embedding: Option<super::EmbeddingConfig>,
+    /// 重排序接口（可选）
+    #[serde(default)]
+    pub rerank: Option<super::RerankConfig>,
+    #[serde(flatten)]
+    pub retrieve: RagRetrieveConfig,
+}
+
+impl RagRetrieveToolConfig {
+    pub fn build(self) -> Result<RagRetrieveTool> {
+        self.build_with(None)
+    }
+
+    /// 构建检索工具，未配置 `embedding` 时使用 `fallback`（如 `LlmEmbedder`）
+    pub fn build_with(self, fallback: Option<Arc<dyn Embedder>>) -> Result<RagRetrieveTool> {
+        let embedder: Arc<dyn Embedder> = match (self.embedding, fallback) {
+            (Some(embedding), _) => Arc::new(super::HttpEmbedder::new(embedding)),
+            (None, Some(embedder)) => embedder,
+            (None, None) => {
+                return Err(AgentFlowError::Other(anyhow::anyhow!(
+                    "rag.retrieve requires an `embedding` config"
+                )))
+            }
+        };
+        let tool = RagRetrieveTool::with_config(self.store.build()?, embedder, self.retrieve);
+        Ok(match self.rerank {
+            Some(rerank) => tool.with_reranker(Arc::new(super::HttpReranker::new(rerank))),
+            None => tool,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::vector::{InMemoryVectorStore, VectorRecord, VectorStore};
+    use serde_json::Value;
+
+    /// 按关键词构造二维向量的测试 Embedder
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl Embedder for KeywordEmbedder {
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    vec![
+                        t.matches("rust").count() as f32,
+                        t.matches("python").count() as f32,
+                    ]
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rag_retrieve_returns_top_chunks() {
+        let store = Arc::new(InMemoryVectorStore::default());
+        store
+            .upsert(vec![
+                VectorRecord::new("r", vec![1.0, 0.0], "rust ownership"),
+                VectorRecord::new("p", vec![0.0, 1.0], "python generators"),
+            ])
+            .await
+            .unwrap();
+        let tool = RagRetrieveTool::new(store, Arc::new(KeywordEmbedder));
+
+        let message = tool
+            .call(
+                ToolInvocation::new(
+                    "rag.retrieve",
+                    json!({ "query": "rust borrow", "top_k": 1 }),
+                ),
+                &FlowContext::new(Arc::new(crate::state::MemoryStore::new())),
+            )
+            .await
+            .unwrap();
+        let output: Value = serde_json::from_str(&message.content).unwrap();
+        assert_eq!(output["chunks"].as_array().unwrap().len(), 1);
+        assert_eq!(output["chunks"][0]["content"], "rust ownership");
+    }
+
+    /// 偏好包含 "generators" 的文档的测试 Reranker
+    struct KeywordReranker;
+
+    #[async_trait]
+    impl Reranker for KeywordReranker {
+        async fn rerank(
+            &self,
+            _query: &str,
+            documents: &[String],
+            top_n: usize,
+        ) -> Result<Vec<crate::tools::vector::RerankResult>> {
+            let mut results: Vec<_> = documents
+                .iter()
+                .enumerate()
+                .map(|(index, doc)| crate::tools::vector::RerankResult {
+                    index,
+                    score: if doc.contains("generators") { 0.9 } else { 0.2 },
+                })
+                .collect();
+            results.sort_by(|a, b| b.score.total_cmp(&a.score));
+            results.truncate(top_n);
+            Ok(results)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rag_retrieve_reranks_candidates() {
+        let store = Arc::new(InMemoryVectorStore::default());
+        store
+            .upsert(vec![
+                VectorRecord::new("r", vec![1.0, 0.0], "rust ownership"),
+                VectorRecord::new("g", vec![1.0, 1.0], "rust generators"),
+            ])
+            .await
+            .unwrap();
+        let tool = RagRetrieveTool::new(store, Arc::new(KeywordEmbedder))
+            .with_reranker(Arc::new(KeywordReranker));
+
+        let message = tool
+            .call(
+                ToolInvocation::new("rag.retrieve", json!({ "query": "rust", "top_k": 1 })),
+                &FlowContext::new(Arc::new(crate::state::MemoryStore::new())),
+            )
+            .await
+            .unwrap();
+        let output: Value = serde_json::from_str(&message.content).unwrap();
+        assert_eq!(output["reranked"], true);
+        assert_eq!(output["chunks"].as_array().unwrap().len(), 1);
+        assert_eq!(output["chunks"][0]["id"], "g");
+        assert!((output["chunks"][0]["score"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,89 @@
+//! 视频生成工具 - `video.generate`（内置工具）
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::agent::{AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::media::{VideoGenClient, VideoGenRequest};
+use crate::runtime::RunUpdate;
+use crate::state::FlowContext;
+use crate::tools::tool::{Tool, ToolInvocation};
+
+/// 视频生成工具
+///
+/// 输入参数：
+/// - prompt: 提示词
+/// - image_url: 可选，首帧图片（图生视频，仅 DashScope）
+/// - size / duration: 可选，分辨率与时长（秒）
+///
+/// 输出：`task_id`、`video_url`、`bytes` 与 `blob`，视频作为消息附件返回；
+/// 上下文挂载运行通道时推送 `task_progress` 事件。
+#[derive(Clone)]
+pub struct VideoGenerateTool {
+    client: VideoGenClient,
+}
+
+impl VideoGenerateTool {
+    pub fn new(client: VideoGenClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Tool for VideoGenerateTool {
+    fn name(&self) -> &'static str {
+        "video.generate"
+    }
+
+    async fn call(&self, invocation: ToolInvocation, ctx: &FlowContext) -> Result<AgentMessage> {
+        let input = &invocation.input;
+        let prompt = input["prompt"]
+            .as_str()
+            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing prompt")))?;
+        let mut request = VideoGenRequest::new(prompt);
+        if let Some(url) = input["image_url"].as_str() {
+            request = request.with_image_url(url);
+        }
+        if let Some(size) = input["size"].as_str() {
+            request = request.with_size(size);
+        }
+        if let Some(duration) = input["duration"].as_u64() {
+            request = request.with_duration(duration as u32);
+        }
+
+        let node = ctx.node().unwrap_or(self.name()).to_string();
+        let result = self
+            .client
+            .generate_with_progress(&request, |progress| {
+                if let Some(channel) = ctx.channel() {
+                    channel.publish(RunUpdate::TaskProgress {
+                        node: node.clone(),
+                        task_id: progress.task_id.clone(),
+                        status: progress.status.clone(),
+                        elapsed_ms: progress.elapsed_ms,
+                    });
+                }
+            })
+            .await?;
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name().to_string(),
+            to: None,
+            content: json!({
+                "success": true,
+                "task_id": result.task_id,
+                "video_url": result.video_url,
+                "mime_type": "video/mp4",
+                "bytes": result.bytes,
+                "blob": result.blob(),
+                "attempts": result.attempts
+            })
+            .to_string(),
+            metadata: None,
+            attachments: vec![result.attachment],
+        })
+    }
+}
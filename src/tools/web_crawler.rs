@@ -0,0 +1,831 @@
+//! 网页爬取工具 - 限速、遵守 robots.txt（内置工具）
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::time::{sleep, Instant};
+use tracing::{debug, warn};
+
+use crate::agent::{AgentMessage, MessageRole};
+use crate::error::{AgentFlowError, Result};
+use crate::llm::http::body::read_body;
+use crate::state::FlowContext;
+use crate::tools::tool::{Tool, ToolInvocation};
+
+const DEFAULT_USER_AGENT: &str = "agentflow-crawler/0.1";
+
+/// 爬虫配置
+///
+/// 工厂配置同时是上限：单次调用的输入只能收紧 `max_depth`、`max_pages`
+/// 和 `allowed_domains`，不能扩大爬取范围。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebCrawlerConfig {
+    #[serde(default = "WebCrawlerConfig::default_max_depth")]
+    pub max_depth: usize,
+    #[serde(default = "WebCrawlerConfig::default_max_pages")]
+    pub max_pages: usize,
+    /// 允许访问的域名（为空时只允许起始 URL 所在域名）
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    #[serde(default = "WebCrawlerConfig::default_respect_robots")]
+    pub respect_robots: bool,
+    /// 同一主机两次请求之间的最小间隔（毫秒）
+    #[serde(default = "WebCrawlerConfig::default_delay_ms")]
+    pub delay_ms: u64,
+    #[serde(default = "WebCrawlerConfig::default_user_agent")]
+    pub user_agent: String,
+    #[serde(default = "WebCrawlerConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// 单个页面保留的最大文本长度（字符）
+    #[serde(default = "WebCrawlerConfig::default_max_content_chars")]
+    pub max_content_chars: usize,
+    /// 单个响应体的大小上限（字节），超过时跳过该页面
+    #[serde(default = "WebCrawlerConfig::default_max_response_bytes")]
+    pub max_response_bytes: usize,
+}
+
+impl WebCrawlerConfig {
+    fn default_max_depth() -> usize {
+        1
+    }
+
+    fn default_max_pages() -> usize {
+        20
+    }
+
+    fn default_respect_robots() -> bool {
+        true
+    }
+
+    fn default_delay_ms() -> u64 {
+        1000
+    }
+
+    fn default_user_agent() -> String {
+        DEFAULT_USER_AGENT.to_string()
+    }
+
+    fn default_timeout_secs() -> u64 {
+        15
+    }
+
+    fn default_max_content_chars() -> usize {
+        20_000
+    }
+
+    fn default_max_response_bytes() -> usize {
+        5 * 1024 * 1024
+    }
+}
+
+impl Default for WebCrawlerConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: Self::default_max_depth(),
+            max_pages: Self::default_max_pages(),
+            allowed_domains: Vec::new(),
+            respect_robots: Self::default_respect_robots(),
+            delay_ms: Self::default_delay_ms(),
+            user_agent: Self::default_user_agent(),
+            timeout_secs: Self::default_timeout_secs(),
+            max_content_chars: Self::default_max_content_chars(),
+            max_response_bytes: Self::default_max_response_bytes(),
+        }
+    }
+}
+
+/// 爬取得到的页面文档
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrawledDocument {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub depth: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    pub content: String,
+    pub links_found: usize,
+}
+
+/// 网页爬取工具
+///
+/// 从起始 URL 开始广度优先爬取，按深度、页数和域名白名单约束范围，
+/// 每个主机遵守 robots.txt 规则与请求间隔。
+///
+/// 输入参数：
+/// - url: 起始 URL（必填）
+/// - max_depth / max_pages / allowed_domains: 收紧工厂配置（可选，不超过配置值）
+///
+/// 输出为 JSON，`documents` 数组包含每个页面的 URL、标题和纯文本内容。
+#[derive(Clone)]
+pub struct WebCrawlerTool {
+    client: Client,
+    config: WebCrawlerConfig,
+}
+
+impl Default for WebCrawlerTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebCrawlerTool {
+    pub fn new() -> Self {
+        Self::with_config(WebCrawlerConfig::default())
+    }
+
+    pub fn with_config(config: WebCrawlerConfig) -> Self {
+        let client = Client::builder()
+            .user_agent(config.user_agent.clone())
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+        Self { client, config }
+    }
+
+    pub fn config(&self) -> &WebCrawlerConfig {
+        &self.config
+    }
+
+    /// 合并调用输入与工厂配置；`allowed_domains` 为空时使用起始 URL 所在域名
+    fn effective_config(&self, input: &Value, start_host: &str) -> WebCrawlerConfig {
+        let mut config = self.config.clone();
+        if config.allowed_domains.is_empty() {
+            config.allowed_domains.push(start_host.to_string());
+        }
+        if let Some(depth) = input["max_depth"].as_u64() {
+            config.max_depth = (depth as usize).min(self.config.max_depth);
+        }
+        if let Some(pages) = input["max_pages"].as_u64() {
+            config.max_pages = (pages as usize).min(self.config.max_pages);
+        }
+        if let Some(domains) = input["allowed_domains"].as_array() {
+            // 只保留落在配置白名单内的域名
+            config.allowed_domains = domains
+                .iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_lowercase()))
+                .filter(|domain| {
+                    domain_allowed(domain.trim_start_matches("*."), &config.allowed_domains)
+                })
+                .collect();
+        }
+        config
+    }
+
+    async fn fetch_robots(&self, origin: &Url) -> RobotsRules {
+        let robots_url = match origin.join("/robots.txt") {
+            Ok(url) => url,
+            Err(_) => return RobotsRules::allow_all(),
+        };
+        match self.client.get(robots_url.clone()).send().await {
+            Ok(response) if response.status().is_success() => {
+                match read_body(response, self.config.max_response_bytes).await {
+                    Ok(body) => {
+                        RobotsRules::parse(&String::from_utf8_lossy(&body), &self.config.user_agent)
+                    }
+                    Err(err) => {
+                        debug!(url = %robots_url, %err, "failed to read robots.txt");
+                        RobotsRules::allow_all()
+                    }
+                }
+            }
+            Ok(response) => {
+                debug!(url = %robots_url, status = %response.status(), "robots.txt unavailable");
+                RobotsRules::allow_all()
+            }
+            Err(err) => {
+                debug!(url = %robots_url, %err, "robots.txt request failed");
+                RobotsRules::allow_all()
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for WebCrawlerTool {
+    fn name(&self) -> &'static str {
+        "web.crawl"
+    }
+
+    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let start = invocation.input["url"]
+            .as_str()
+            .or_else(|| invocation.input.as_str())
+            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing url")))?;
+        let start_url = Url::parse(start)
+            .map_err(|e| AgentFlowError::Other(anyhow::anyhow!("Invalid url `{}`: {}", start, e)))?;
+        if !matches!(start_url.scheme(), "http" | "https") {
+            return Err(AgentFlowError::Other(anyhow::anyhow!(
+                "Unsupported url scheme `{}`",
+                start_url.scheme()
+            )));
+        }
+
+        let start_host = start_url.host_str().unwrap_or_default().to_lowercase();
+        let config = self.effective_config(&invocation.input, &start_host);
+        let allowed_domains = &config.allowed_domains;
+
+        let delay = Duration::from_millis(config.delay_ms);
+        let mut robots: HashMap<String, RobotsRules> = HashMap::new();
+        let mut last_request: HashMap<String, Instant> = HashMap::new();
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut queue: VecDeque<(Url, usize)> = VecDeque::new();
+        let mut documents: Vec<CrawledDocument> = Vec::new();
+        let mut skipped: Vec<Value> = Vec::new();
+
+        queue.push_back((start_url.clone(), 0));
+
+        while let Some((url, depth)) = queue.pop_front() {
+            if documents.len() >= config.max_pages {
+                break;
+            }
+            let key = normalize_url(&url);
+            if !visited.insert(key) {
+                continue;
+            }
+            let host = match url.host_str() {
+                Some(host) => host.to_lowercase(),
+                None => continue,
+            };
+            if !domain_allowed(&host, allowed_domains) {
+                continue;
+            }
+
+            if config.respect_robots {
+                let origin = origin_key(&url);
+                if !robots.contains_key(&origin) {
+                    let rules = self.fetch_robots(&url).await;
+                    robots.insert(origin.clone(), rules);
+                }
+                if let Some(rules) = robots.get(&origin) {
+                    if !rules.is_allowed(&robots_path(&url)) {
+                        skipped.push(json!({ "url": url.as_str(), "reason": "robots" }));
+                        continue;
+                    }
+                }
+            }
+
+            let host_delay = robots
+                .get(&origin_key(&url))
+                .and_then(|rules| rules.crawl_delay)
+                .map(|d| d.max(delay))
+                .unwrap_or(delay);
+            if let Some(previous) = last_request.get(&host) {
+                let elapsed = previous.elapsed();
+                if elapsed < host_delay {
+                    sleep(host_delay - elapsed).await;
+                }
+            }
+            last_request.insert(host.clone(), Instant::now());
+
+            let response = match self.client.get(url.clone()).send().await {
+                Ok(response) => response,
+                Err(err) => {
+                    warn!(url = %url, %err, "crawl request failed");
+                    skipped.push(json!({ "url": url.as_str(), "reason": err.to_string() }));
+                    continue;
+                }
+            };
+            if !response.status().is_success() {
+                skipped.push(json!({
+                    "url": url.as_str(),
+                    "reason": format!("HTTP {}", response.status())
+                }));
+                continue;
+            }
+
+            let final_url = response.url().clone();
+            let final_allowed = final_url
+                .host_str()
+                .is_some_and(|host| domain_allowed(&host.to_lowercase(), allowed_domains));
+            if !final_allowed {
+                skipped.push(json!({
+                    "url": url.as_str(),
+                    "reason": format!("redirected outside allowed domains to {}", final_url)
+                }));
+                continue;
+            }
+            visited.insert(normalize_url(&final_url));
+            let content_type = response
+                .headers()
+                .get("content-type")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string());
+            let is_html = content_type
+                .as_deref()
+                .map(|ct| ct.contains("text/html"))
+                .unwrap_or(true);
+            let is_text = content_type
+                .as_deref()
+                .map(|ct| ct.starts_with("text/") || ct.contains("json") || ct.contains("xml"))
+                .unwrap_or(true);
+            if !is_text {
+                skipped.push(json!({ "url": url.as_str(), "reason": "non-text content" }));
+                continue;
+            }
+
+            let body = match read_body(response, config.max_response_bytes).await {
+                Ok(body) => String::from_utf8_lossy(&body).into_owned(),
+                Err(err) => {
+                    skipped.push(json!({ "url": url.as_str(), "reason": err.to_string() }));
+                    continue;
+                }
+            };
+
+            let (title, text, links) = if is_html {
+                let links = extract_links(&body, &final_url);
+                (extract_title(&body), html_to_text(&body), links)
+            } else {
+                (None, body.clone(), Vec::new())
+            };
+
+            if depth < config.max_depth {
+                for link in &links {
+                    if !visited.contains(&normalize_url(link)) {
+                        queue.push_back((link.clone(), depth + 1));
+                    }
+                }
+            }
+
+            documents.push(CrawledDocument {
+                url: final_url.to_string(),
+                title,
+                depth,
+                content_type,
+                content: truncate_chars(&text, config.max_content_chars),
+                links_found: links.len(),
+            });
+        }
+
+        let result = json!({
+            "success": true,
+            "start_url": start_url.as_str(),
+            "pages_crawled": documents.len(),
+            "documents": documents,
+            "skipped": skipped,
+        });
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name().to_string(),
+            to: None,
+            content: result.to_string(),
+            metadata: invocation.metadata,
+            attachments: Vec::new(),
+        })
+    }
+}
+
+/// robots.txt 规则（仅保留与当前 user-agent 匹配的分组）
+#[derive(Clone, Debug, Default)]
+pub struct RobotsRules {
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// 解析 robots.txt
+    ///
+    /// 优先使用名称匹配 `user_agent` 的分组，否则回退到 `*` 分组。
+    pub fn parse(body: &str, user_agent: &str) -> Self {
+        let agent_token = user_agent
+            .split('/')
+            .next()
+            .unwrap_or(user_agent)
+            .to_lowercase();
+
+        let mut specific: Option<RobotsRules> = None;
+        let mut wildcard: Option<RobotsRules> = None;
+        let mut group_agents: Vec<String> = Vec::new();
+        let mut current = RobotsRules::default();
+        let mut in_rules = false;
+
+        let mut flush = |agents: &mut Vec<String>, rules: &mut RobotsRules| {
+            for agent in agents.iter() {
+                if agent == "*" {
+                    if wildcard.is_none() {
+                        wildcard = Some(rules.clone());
+                    }
+                } else if !agent_token.is_empty() && agent_token.contains(agent.as_str())
+                    && specific.is_none()
+                {
+                    specific = Some(rules.clone());
+                }
+            }
+            agents.clear();
+            *rules = RobotsRules::default();
+        };
+
+        for raw_line in body.lines() {
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            let field = field.trim().to_lowercase();
+            let value = value.trim();
+            match field.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        flush(&mut group_agents, &mut current);
+                        in_rules = false;
+                    }
+                    group_agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    if !value.is_empty() {
+                        current.rules.push((field == "allow", value.to_string()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    if let Ok(secs) = value.parse::<f64>() {
+                        if secs >= 0.0 {
+                            current.crawl_delay = Some(Duration::from_secs_f64(secs));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        flush(&mut group_agents, &mut current);
+
+        specific.or(wildcard).unwrap_or_default()
+    }
+
+    /// 判断路径是否允许访问（最长匹配优先，长度相同时 Allow 优先）
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let mut best: Option<(usize, bool)> = None;
+        for (allow, pattern) in &self.rules {
+            if robots_pattern_matches(pattern, path) {
+                let len = pattern.len();
+                best = match best {
+                    Some((best_len, best_allow))
+                        if best_len > len || (best_len == len && best_allow) =>
+                    {
+                        Some((best_len, best_allow))
+                    }
+                    _ => Some((len, *allow)),
+                };
+            }
+        }
+        best.map(|(_, allow)| allow).unwrap_or(true)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+fn robots_pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(stripped) => (stripped, true),
+        None => (pattern, false),
+    };
+    wildcard_match(pattern.as_bytes(), path.as_bytes(), anchored)
+}
+
+/// `*` 匹配任意字符序列；未锚定时模式只需匹配路径前缀
+fn wildcard_match(pattern: &[u8], path: &[u8], anchored: bool) -> bool {
+    match pattern.split_first() {
+        None => !anchored || path.is_empty(),
+        Some((b'*', rest)) => (0..=path.len()).any(|i| wildcard_match(rest, &path[i..], anchored)),
+        Some((c, rest)) => path
+            .split_first()
+            .map(|(p, tail)| p == c && wildcard_match(rest, tail, anchored))
+            .unwrap_or(false),
+    }
+}
+
+/// robots.txt 规则匹配的目标：路径加查询串
+fn robots_path(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+fn origin_key(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+fn normalize_url(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url.to_string()
+}
+
+fn domain_allowed(host: &str, allowed: &[String]) -> bool {
+    allowed.iter().any(|domain| {
+        let domain = domain.trim_start_matches("*.");
+        host == domain || host.ends_with(&format!(".{domain}"))
+    })
+}
+
+/// 提取页面中的 http(s) 链接
+pub fn extract_links(html: &str, base: &Url) -> Vec<Url> {
+    // 只做 ASCII 小写，保证偏移量可以直接用于切片原文
+    let lower = html.to_ascii_lowercase();
+    let mut links = Vec::new();
+    let mut seen = HashSet::new();
+    let mut search_from = 0usize;
+    while let Some(found) = lower[search_from..].find("href") {
+        let attr_start = search_from + found + 4;
+        search_from = attr_start;
+        let rest = &html[attr_start..];
+        let trimmed = rest.trim_start();
+        let Some(after_eq) = trimmed.strip_prefix('=') else {
+            continue;
+        };
+        let after_eq = after_eq.trim_start();
+        let value = match after_eq.chars().next() {
+            Some(quote @ ('"' | '\'')) => after_eq[1..].split(quote).next().unwrap_or(""),
+            Some(_) => after_eq
+                .split(|c: char| c.is_whitespace() || c == '>')
+                .next()
+                .unwrap_or(""),
+            None => "",
+        };
+        let value = value.trim();
+        if value.is_empty()
+            || value.starts_with('#')
+            || value.starts_with("javascript:")
+            || value.starts_with("mailto:")
+        {
+            continue;
+        }
+        if let Ok(mut url) = base.join(&decode_entities(value)) {
+            if !matches!(url.scheme(), "http" | "https") {
+                continue;
+            }
+            url.set_fragment(None);
+            if seen.insert(url.to_string()) {
+                links.push(url);
+            }
+        }
+    }
+    links
+}
+
+/// 提取 `<title>` 内容
+pub fn extract_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let content_start = open + lower[open..].find('>')? + 1;
+    let content_end = content_start + lower[content_start..].find("</title")?;
+    let title = decode_entities(html[content_start..content_end].trim());
+    if title.is_empty() {
+        None
+    } else {
+        Some(collapse_whitespace(&title))
+    }
+}
+
+/// 将 HTML 转为纯文本（去除标签、脚本与样式）
+pub fn html_to_text(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut output = String::with_capacity(html.len() / 2);
+    let mut pos = 0usize;
+    while pos < html.len() {
+        let Some(tag_start) = lower[pos..].find('<').map(|i| pos + i) else {
+            output.push_str(&html[pos..]);
+            break;
+        };
+        output.push_str(&html[pos..tag_start]);
+        let skip_block = ["script", "style", "noscript"]
+            .iter()
+            .find(|tag| lower[tag_start + 1..].starts_with(*tag));
+        if let Some(tag) = skip_block {
+            let closing = format!("</{tag}");
+            match lower[tag_start..].find(&closing) {
+                Some(close) => {
+                    let close_start = tag_start + close;
+                    pos = lower[close_start..]
+                        .find('>')
+                        .map(|i| close_start + i + 1)
+                        .unwrap_or(html.len());
+                }
+                None => break,
+            }
+            continue;
+        }
+        match lower[tag_start..].find('>') {
+            Some(end) => {
+                output.push(' ');
+                pos = tag_start + end + 1;
+            }
+            None => break,
+        }
+    }
+    collapse_whitespace(&decode_entities(&output))
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub(crate) fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => text[..idx].to_string(),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryStore;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    /// 按路径返回固定响应的 HTTP 服务：`/start` 重定向到 `localhost`，`/big` 返回大页面
+    fn site() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                }
+                let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+                let big = format!("<p>{}</p>", "x".repeat(4096));
+                let (status, location, body) = match path {
+                    "/start" => ("302 Found", format!("http://localhost:{port}/landing"), ""),
+                    "/landing" => ("200 OK", String::new(), "<title>Landing</title>"),
+                    "/big" => ("200 OK", String::new(), big.as_str()),
+                    _ => ("404 Not Found", String::new(), ""),
+                };
+                let location = if location.is_empty() {
+                    String::new()
+                } else {
+                    format!("Location: {location}\r\n")
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\n{location}Content-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        port
+    }
+
+    async fn crawl(config: WebCrawlerConfig, url: String) -> Value {
+        let ctx = FlowContext::new(Arc::new(MemoryStore::new()));
+        let reply = WebCrawlerTool::with_config(config)
+            .call(
+                ToolInvocation::new("web.crawl", json!({ "url": url })),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        serde_json::from_str(&reply.content).unwrap()
+    }
+
+    #[test]
+    fn test_robots_rules() {
+        let body = "User-agent: *\nDisallow: /private\nAllow: /private/public\n\nUser-agent: other\nDisallow: /\n";
+        let rules = RobotsRules::parse(body, DEFAULT_USER_AGENT);
+        assert!(rules.is_allowed("/index.html"));
+        assert!(!rules.is_allowed("/private/secret"));
+        assert!(rules.is_allowed("/private/public/page"));
+    }
+
+    #[test]
+    fn test_robots_wildcards() {
+        let body = "User-agent: *\nDisallow: /*.pdf$\nDisallow: /search*q=\n";
+        let rules = RobotsRules::parse(body, DEFAULT_USER_AGENT);
+        assert!(!rules.is_allowed("/files/report.pdf"));
+        assert!(rules.is_allowed("/files/report.pdf.html"));
+        assert!(!rules.is_allowed("/search?q=rust"));
+        assert!(rules.is_allowed("/search"));
+    }
+
+    #[test]
+    fn test_robots_specific_agent_and_delay() {
+        let body = "User-agent: agentflow-crawler\nDisallow: /tmp\nCrawl-delay: 2\n\nUser-agent: *\nDisallow: /\n";
+        let rules = RobotsRules::parse(body, DEFAULT_USER_AGENT);
+        assert!(rules.is_allowed("/docs"));
+        assert!(!rules.is_allowed("/tmp/file"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_input_cannot_widen_config() {
+        let tool = WebCrawlerTool::with_config(WebCrawlerConfig {
+            max_depth: 2,
+            max_pages: 10,
+            allowed_domains: vec!["example.com".into()],
+            ..WebCrawlerConfig::default()
+        });
+        let config = tool.effective_config(
+            &json!({
+                "max_depth": 50,
+                "max_pages": 5,
+                "allowed_domains": ["docs.example.com", "*.example.com", "evil.com", "example.com.evil.com"]
+            }),
+            "example.com",
+        );
+        assert_eq!(config.max_depth, 2);
+        assert_eq!(config.max_pages, 5);
+        assert_eq!(
+            config.allowed_domains,
+            vec!["docs.example.com", "*.example.com"]
+        );
+
+        let tool = WebCrawlerTool::new();
+        let config = tool.effective_config(
+            &json!({ "max_pages": 1000, "allowed_domains": ["other.org"] }),
+            "example.com",
+        );
+        assert_eq!(config.max_pages, 20);
+        assert!(config.allowed_domains.is_empty());
+        assert!(!domain_allowed("example.com", &config.allowed_domains));
+    }
+
+    #[test]
+    fn test_extract_links_and_text() {
+        let base = Url::parse("https://example.com/docs/").unwrap();
+        let html = r#"<html><head><title> Docs &amp; Guides </title><script>var a = "<a href='x'>";</script></head>
+            <body><a href="intro.html#top">Intro</a> <a href='/about'>About</a>
+            <a href="mailto:a@b.c">Mail</a><p>Hello&nbsp;world</p></body></html>"#;
+        let links = extract_links(html, &base);
+        let links: Vec<String> = links.iter().map(|u| u.to_string()).collect();
+        assert!(links.contains(&"https://example.com/docs/intro.html".to_string()));
+        assert!(links.contains(&"https://example.com/about".to_string()));
+        assert_eq!(extract_title(html).as_deref(), Some("Docs & Guides"));
+        let text = html_to_text(html);
+        assert!(text.contains("Hello world"));
+        assert!(!text.contains("var a"));
+    }
+
+    #[test]
+    fn test_robots_rules_match_query_string() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /search?q=\n", DEFAULT_USER_AGENT);
+        let url = Url::parse("https://example.com/search?q=rust").unwrap();
+        assert!(!rules.is_allowed(&robots_path(&url)));
+        let url = Url::parse("https://example.com/search").unwrap();
+        assert!(rules.is_allowed(&robots_path(&url)));
+    }
+
+    #[test]
+    fn test_non_ascii_case_folding_keeps_offsets() {
+        // 'İ' 经 Unicode 小写后字节长度会改变
+        let html = "<TITLE>İİİ</TITLE><p>İstanbul</p><A HREF=\"/İ\">x</A><SCRIPT>x</SCRIPT>";
+        let base = Url::parse("https://example.com/").unwrap();
+        assert_eq!(extract_title(html).as_deref(), Some("İİİ"));
+        assert_eq!(extract_links(html, &base).len(), 1);
+        assert_eq!(html_to_text(html), "İİİ İstanbul x");
+    }
+
+    #[tokio::test]
+    async fn test_off_domain_redirects_and_large_pages_are_skipped() {
+        let port = site();
+        let config = WebCrawlerConfig {
+            delay_ms: 0,
+            max_response_bytes: 1024,
+            ..WebCrawlerConfig::default()
+        };
+
+        let result = crawl(config.clone(), format!("http://127.0.0.1:{port}/start")).await;
+        assert_eq!(result["pages_crawled"], 0);
+        let reason = result["skipped"][0]["reason"].as_str().unwrap();
+        assert!(reason.starts_with("redirected outside allowed domains"));
+
+        let result = crawl(config, format!("http://127.0.0.1:{port}/big")).await;
+        assert_eq!(result["pages_crawled"], 0);
+        let reason = result["skipped"][0]["reason"].as_str().unwrap();
+        assert!(reason.contains("exceeds the limit"));
+    }
+}
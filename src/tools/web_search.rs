@@ -0,0 +1,434 @@
+//! 网页搜索与阅读工具 - `web.search` / `web.read`（内置工具）
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::agent::{AgentMessage, MessageRole};
+use crate::config::EnvConfig;
+use crate::error::{AgentFlowError, Result};
+use crate::state::FlowContext;
+use crate::tools::tool::{Tool, ToolInvocation};
+use crate::tools::web_crawler::{extract_title, html_to_text, truncate_chars};
+
+const DEFAULT_USER_AGENT: &str = "agentflow-reader/0.1";
+
+/// 搜索服务提供商
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchProvider {
+    Bing,
+    Serper,
+    Tavily,
+}
+
+impl SearchProvider {
+    fn default_endpoint(&self) -> &'static str {
+        match self {
+            SearchProvider::Bing => "https://api.bing.microsoft.com/v7.0/search",
+            SearchProvider::Serper => "https://google.serper.dev/search",
+            SearchProvider::Tavily => "https://api.tavily.com/search",
+        }
+    }
+
+    /// 未配置 api_key 时读取的环境变量
+    pub fn api_key_env(&self) -> &'static str {
+        match self {
+            SearchProvider::Bing => "BING_API_KEY",
+            SearchProvider::Serper => "SERPER_API_KEY",
+            SearchProvider::Tavily => "TAVILY_API_KEY",
+        }
+    }
+
+    /// 解析提供商响应
+    pub fn parse_results(&self, body: &Value) -> Vec<SearchResult> {
+        let (items, title, url, snippet) = match self {
+            SearchProvider::Bing => (body.pointer("/webPages/value"), "name", "url", "snippet"),
+            SearchProvider::Serper => (body.get("organic"), "title", "link", "snippet"),
+            SearchProvider::Tavily => (body.get("results"), "title", "url", "content"),
+        };
+        items
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| {
+                        Some(SearchResult {
+                            title: item[title].as_str().unwrap_or_default().to_string(),
+                            url: item[url].as_str()?.to_string(),
+                            snippet: item[snippet].as_str().unwrap_or_default().to_string(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// 搜索结果
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// 搜索工具配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebSearchConfig {
+    pub provider: SearchProvider,
+    /// API Key，支持 `${VAR_NAME}`；为空时读取提供商对应的环境变量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// 自定义接口地址（代理或私有部署）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    #[serde(default = "WebSearchConfig::default_max_results")]
+    pub max_results: usize,
+    #[serde(default = "WebSearchConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl WebSearchConfig {
+    fn default_max_results() -> usize {
+        5
+    }
+
+    fn default_timeout_secs() -> u64 {
+        15
+    }
+
+    pub fn new(provider: SearchProvider) -> Self {
+        Self {
+            provider,
+            api_key: None,
+            endpoint: None,
+            max_results: Self::default_max_results(),
+            timeout_secs: Self::default_timeout_secs(),
+        }
+    }
+
+    /// 按 Tavily / Serper / Bing 顺序选择已配置 API Key 的提供商
+    pub fn from_env() -> Option<Self> {
+        [
+            SearchProvider::Tavily,
+            SearchProvider::Serper,
+            SearchProvider::Bing,
+        ]
+        .into_iter()
+        .find(|provider| EnvConfig::get_env_optional(provider.api_key_env()).is_some())
+        .map(Self::new)
+    }
+
+    fn resolve_api_key(&self) -> Result<String> {
+        match &self.api_key {
+            Some(value) if value.starts_with("${") && value.ends_with('}') => {
+                EnvConfig::get_env(&value[2..value.len() - 1])
+            }
+            Some(value) => Ok(value.clone()),
+            None => EnvConfig::get_env(self.provider.api_key_env()),
+        }
+    }
+}
+
+/// 网页搜索工具
+///
+/// 输入参数：
+/// - query: 搜索词
+/// - max_results: 结果数量（可选）
+///
+/// 输出：`results` 数组，每项包含 `title`、`url`、`snippet`。
+#[derive(Clone)]
+pub struct WebSearchTool {
+    config: WebSearchConfig,
+    client: Client,
+}
+
+impl WebSearchTool {
+    pub fn new(config: WebSearchConfig) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build()
+            .unwrap_or_default();
+        Self { config, client }
+    }
+
+    pub fn config(&self) -> &WebSearchConfig {
+        &self.config
+    }
+
+    async fn search(&self, query: &str, max_results: usize) -> Result<Value> {
+        let api_key = self.config.resolve_api_key()?;
+        let endpoint = self
+            .config
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| self.config.provider.default_endpoint().to_string());
+
+        let request = match self.config.provider {
+            SearchProvider::Bing => self
+                .client
+                .get(&endpoint)
+                .header("Ocp-Apim-Subscription-Key", api_key)
+                .query(&[("q", query), ("count", &max_results.to_string())]),
+            SearchProvider::Serper => self
+                .client
+                .post(&endpoint)
+                .header("X-API-KEY", api_key)
+                .json(&json!({ "q": query, "num": max_results })),
+            SearchProvider::Tavily => self.client.post(&endpoint).json(&json!({
+                "api_key": api_key,
+                "query": query,
+                "max_results": max_results,
+            })),
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow::anyhow!("Search request failed: {}", e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(AgentFlowError::Other(anyhow::anyhow!(
+                "Search provider returned {}: {}",
+                status,
+                truncate_chars(&body, 500)
+            )));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow::anyhow!("Invalid search response: {}", e)))
+    }
+}
+
+#[async_trait]
+impl Tool for WebSearchTool {
+    fn name(&self) -> &'static str {
+        "web.search"
+    }
+
+    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let input = &invocation.input;
+        let query = input["query"]
+            .as_str()
+            .or_else(|| input["content"].as_str())
+            .filter(|q| !q.trim().is_empty())
+            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing query")))?;
+        let max_results = input["max_results"]
+            .as_u64()
+            .map(|n| n as usize)
+            .unwrap_or(self.config.max_results)
+            .max(1);
+
+        let body = self.search(query, max_results).await?;
+        let mut results = self.config.provider.parse_results(&body);
+        results.truncate(max_results);
+
+        let result = json!({
+            "success": true,
+            "provider": self.config.provider,
+            "query": query,
+            "results": results,
+        });
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name().to_string(),
+            to: None,
+            content: result.to_string(),
+            metadata: invocation.metadata,
+            attachments: Vec::new(),
+        })
+    }
+}
+
+/// 网页阅读工具配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebReadConfig {
+    #[serde(default = "WebReadConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "WebReadConfig::default_max_content_chars")]
+    pub max_content_chars: usize,
+    #[serde(default = "WebReadConfig::default_user_agent")]
+    pub user_agent: String,
+}
+
+impl WebReadConfig {
+    fn default_timeout_secs() -> u64 {
+        15
+    }
+
+    fn default_max_content_chars() -> usize {
+        20_000
+    }
+
+    fn default_user_agent() -> String {
+        DEFAULT_USER_AGENT.to_string()
+    }
+}
+
+impl Default for WebReadConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: Self::default_timeout_secs(),
+            max_content_chars: Self::default_max_content_chars(),
+            user_agent: Self::default_user_agent(),
+        }
+    }
+}
+
+/// 网页阅读工具
+///
+/// 输入参数：
+/// - url: 页面地址
+/// - max_chars: 最大文本长度（可选）
+///
+/// 输出：页面标题与纯文本内容（HTML 会被转换为文本）。
+#[derive(Clone)]
+pub struct WebReadTool {
+    config: WebReadConfig,
+    client: Client,
+}
+
+impl Default for WebReadTool {
+    fn default() -> Self {
+        Self::with_config(WebReadConfig::default())
+    }
+}
+
+impl WebReadTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: WebReadConfig) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .user_agent(config.user_agent.clone())
+            .build()
+            .unwrap_or_default();
+        Self { config, client }
+    }
+
+    pub fn config(&self) -> &WebReadConfig {
+        &self.config
+    }
+}
+
+#[async_trait]
+impl Tool for WebReadTool {
+    fn name(&self) -> &'static str {
+        "web.read"
+    }
+
+    async fn call(&self, invocation: ToolInvocation, _ctx: &FlowContext) -> Result<AgentMessage> {
+        let input = &invocation.input;
+        let raw_url = input["url"]
+            .as_str()
+            .ok_or_else(|| AgentFlowError::Other(anyhow::anyhow!("Missing url")))?;
+        let url = Url::parse(raw_url)
+            .map_err(|e| AgentFlowError::Other(anyhow::anyhow!("Invalid url: {}", e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(AgentFlowError::Other(anyhow::anyhow!(
+                "Unsupported url scheme: {}",
+                url.scheme()
+            )));
+        }
+        let max_chars = input["max_chars"]
+            .as_u64()
+            .map(|n| n as usize)
+            .unwrap_or(self.config.max_content_chars);
+
+        let response =
+            self.client.get(url).send().await.map_err(|e| {
+                AgentFlowError::Other(anyhow::anyhow!("Failed to fetch page: {}", e))
+            })?;
+        let status = response.status();
+        let final_url = response.url().to_string();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| AgentFlowError::Other(anyhow::anyhow!("Failed to read page: {}", e)))?;
+
+        let is_html = content_type.contains("html") || body.trim_start().starts_with('<');
+        let (title, text) = if is_html {
+            (extract_title(&body), html_to_text(&body))
+        } else {
+            (None, body)
+        };
+        let truncated = text.chars().count() > max_chars;
+
+        let result = json!({
+            "success": status.is_success(),
+            "status": status.as_u16(),
+            "url": final_url,
+            "title": title,
+            "content_type": content_type,
+            "content": truncate_chars(&text, max_chars),
+            "truncated": truncated,
+        });
+
+        Ok(AgentMessage {
+            id: crate::agent::message::uuid(),
+            role: MessageRole::Tool,
+            from: self.name().to_string(),
+            to: None,
+            content: result.to_string(),
+            metadata: invocation.metadata,
+            attachments: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_provider_results() {
+        let bing = json!({ "webPages": { "value": [
+            { "name": "Rust", "url": "https://rust-lang.org", "snippet": "A language" }
+        ] } });
+        let serper = json!({ "organic": [
+            { "title": "Rust", "link": "https://rust-lang.org", "snippet": "A language" },
+            { "title": "No link" }
+        ] });
+        let tavily = json!({ "results": [
+            { "title": "Rust", "url": "https://rust-lang.org", "content": "A language" }
+        ] });
+
+        let expected = vec![SearchResult {
+            title: "Rust".to_string(),
+            url: "https://rust-lang.org".to_string(),
+            snippet: "A language".to_string(),
+        }];
+        assert_eq!(SearchProvider::Bing.parse_results(&bing), expected);
+        assert_eq!(SearchProvider::Serper.parse_results(&serper), expected);
+        assert_eq!(SearchProvider::Tavily.parse_results(&tavily), expected);
+        assert!(SearchProvider::Bing.parse_results(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_search_config_from_json() {
+        let config: WebSearchConfig = serde_json::from_value(json!({
+            "provider": "serper",
+            "api_key": "secret"
+        }))
+        .unwrap();
+        assert_eq!(config.provider, SearchProvider::Serper);
+        assert_eq!(config.max_results, 5);
+        assert_eq!(config.resolve_api_key().unwrap(), "secret");
+    }
+}
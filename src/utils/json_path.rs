@@ -0,0 +1,168 @@
+use crate::error::{AgentFlowError, Result};
+use anyhow::anyhow;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// JSONPath 片段
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(i64),
+    Wildcard,
+}
+
+/// 简化版 JSONPath
+///
+/// 支持 `$`、`.key`、`['key']`、`[0]`、`[-1]`、`[*]` 和 `.*`，
+/// 用于从工具响应中提取字段。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+    wildcard: bool,
+}
+
+impl JsonPath {
+    /// 解析路径表达式（`$` 前缀可省略）
+    pub fn parse(expr: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            AgentFlowError::Other(anyhow!("Invalid JSONPath `{}`: {}", expr, reason))
+        };
+
+        let trimmed = expr.trim();
+        let mut rest = trimmed.strip_prefix('$').unwrap_or(trimmed);
+        let mut segments = Vec::new();
+
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                let key = &after[..end];
+                if key.is_empty() {
+                    return Err(invalid("empty key"));
+                }
+                segments.push(if key == "*" {
+                    Segment::Wildcard
+                } else {
+                    Segment::Key(key.to_string())
+                });
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| invalid("missing `]`"))?;
+                let inner = after[..end].trim();
+                let segment = if inner == "*" {
+                    Segment::Wildcard
+                } else if let Some(quoted) = inner
+                    .strip_prefix('\'')
+                    .and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')))
+                {
+                    Segment::Key(quoted.to_string())
+                } else {
+                    Segment::Index(inner.parse().map_err(|_| invalid("bad index"))?)
+                };
+                segments.push(segment);
+                rest = &after[end + 1..];
+            } else if segments.is_empty() {
+                // 允许省略前导 `$.`，例如 `data.items[0]`
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                segments.push(Segment::Key(rest[..end].to_string()));
+                rest = &rest[end..];
+            } else {
+                return Err(invalid("unexpected character"));
+            }
+        }
+
+        let wildcard = segments.iter().any(|s| matches!(s, Segment::Wildcard));
+        Ok(Self { segments, wildcard })
+    }
+
+    /// 返回所有匹配的值
+    pub fn query<'a>(&self, value: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![value];
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            for item in current {
+                match segment {
+                    Segment::Key(key) => {
+                        if let Some(found) = item.get(key) {
+                            next.push(found);
+                        }
+                    }
+                    Segment::Index(index) => {
+                        if let Some(array) = item.as_array() {
+                            let idx = if *index < 0 {
+                                array.len() as i64 + index
+                            } else {
+                                *index
+                            };
+                            if let Some(found) = usize::try_from(idx).ok().and_then(|i| array.get(i))
+                            {
+                                next.push(found);
+                            }
+                        }
+                    }
+                    Segment::Wildcard => match item {
+                        Value::Array(array) => next.extend(array.iter()),
+                        Value::Object(map) => next.extend(map.values()),
+                        _ => {}
+                    },
+                }
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// 提取值：含通配符时返回数组，否则返回首个匹配（无匹配返回 None）
+    pub fn extract(&self, value: &Value) -> Option<Value> {
+        let matches = self.query(value);
+        if self.wildcard {
+            Some(Value::Array(matches.into_iter().cloned().collect()))
+        } else {
+            matches.first().map(|v| (*v).clone())
+        }
+    }
+}
+
+impl TryFrom<String> for JsonPath {
+    type Error = AgentFlowError;
+
+    fn try_from(expr: String) -> Result<Self> {
+        Self::parse(&expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_path_extract() {
+        let data = json!({
+            "data": {
+                "items": [
+                    { "id": 1, "name": "a" },
+                    { "id": 2, "name": "b" }
+                ],
+                "meta key": "x"
+            }
+        });
+
+        let path = JsonPath::parse("$.data.items[0].name").unwrap();
+        assert_eq!(path.extract(&data), Some(json!("a")));
+
+        let path = JsonPath::parse("$.data.items[*].id").unwrap();
+        assert_eq!(path.extract(&data), Some(json!([1, 2])));
+
+        let path = JsonPath::parse("data.items[-1]['name']").unwrap();
+        assert_eq!(path.extract(&data), Some(json!("b")));
+
+        let path = JsonPath::parse("$.data['meta key']").unwrap();
+        assert_eq!(path.extract(&data), Some(json!("x")));
+
+        assert_eq!(JsonPath::parse("$.missing").unwrap().extract(&data), None);
+        assert_eq!(JsonPath::parse("$").unwrap().extract(&data), Some(data.clone()));
+        assert!(JsonPath::parse("$.data[").is_err());
+    }
+}
@@ -1,6 +1,12 @@
 use std::env;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
+use crate::guardrails::PiiRedactor;
+
 /// 日志配置
 pub struct LoggingConfig;
 
@@ -68,12 +74,78 @@ impl LoggingConfig {
             .init();
     }
 
+    /// 初始化日志系统，输出前对每条日志做 PII 脱敏
+    pub fn init_with_redactor(redactor: Arc<PiiRedactor>) {
+        let env_filter = EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new("agentflow=info,warn"));
+
+        tracing_subscriber::registry()
+            .with(env_filter)
+            .with(fmt::layer().with_writer(RedactingWriter::new(redactor, io::stderr)))
+            .init();
+    }
+
     /// 检查是否启用调试模式
     pub fn is_debug() -> bool {
         env::var("AGENTFLOW_DEBUG").is_ok()
     }
 }
 
+/// 脱敏日志输出：缓存一条日志的全部内容，写出前替换 PII
+#[derive(Clone)]
+pub struct RedactingWriter<M> {
+    redactor: Arc<PiiRedactor>,
+    inner: M,
+}
+
+impl<M> RedactingWriter<M> {
+    pub fn new(redactor: Arc<PiiRedactor>, inner: M) -> Self {
+        Self { redactor, inner }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingWriter<M> {
+    type Writer = RedactingLine<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingLine {
+            redactor: Arc::clone(&self.redactor),
+            buffer: Vec::new(),
+            inner: self.inner.make_writer(),
+        }
+    }
+}
+
+/// 单条日志的缓冲，drop 时脱敏并写入底层输出
+pub struct RedactingLine<W: Write> {
+    redactor: Arc<PiiRedactor>,
+    buffer: Vec<u8>,
+    inner: W,
+}
+
+impl<W: Write> Write for RedactingLine<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let buffer = std::mem::take(&mut self.buffer);
+        if !buffer.is_empty() {
+            let text = String::from_utf8_lossy(&buffer);
+            self.inner
+                .write_all(self.redactor.redact(&text).as_bytes())?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for RedactingLine<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
 /// 便捷宏：记录带上下文的错误
 #[macro_export]
 macro_rules! log_error {
@@ -110,4 +182,36 @@ mod tests {
 
         env::remove_var("AGENTFLOW_DEBUG");
     }
+
+    #[test]
+    fn test_redacting_writer() {
+        #[derive(Clone, Default)]
+        struct Sink(Arc<parking_lot::Mutex<Vec<u8>>>);
+
+        impl Write for Sink {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let sink = Sink::default();
+        let output = sink.clone();
+        let writer = RedactingWriter::new(Arc::new(PiiRedactor::builtin()), move || sink.clone());
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(writer)
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(user = "alice@example.com", "callback 13812345678");
+        });
+        let logged = String::from_utf8(output.0.lock().clone()).unwrap();
+        assert!(logged.contains("callback [PHONE]"));
+        assert!(logged.contains("user=\"[EMAIL]\""));
+        assert!(!logged.contains("alice@"));
+    }
 }
@@ -1,6 +1,14 @@
 /// 工具模块 - 提供通用工具函数
+pub mod json_path;
 pub mod logging;
+pub mod sigv4;
 pub mod validation;
+#[cfg(feature = "hot-reload")]
+pub mod watch;
 
-pub use logging::LoggingConfig;
+pub use json_path::JsonPath;
+pub use logging::{LoggingConfig, RedactingWriter};
+pub use sigv4::AwsCredentials;
 pub use validation::ConfigValidator;
+#[cfg(feature = "hot-reload")]
+pub use watch::ReloadHandle;
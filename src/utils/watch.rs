@@ -0,0 +1,89 @@
+//! 文件监听与热加载（需启用 `hot-reload` feature）
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::RwLock;
+use tokio::sync::watch;
+
+use crate::error::{AgentFlowError, Result};
+
+/// 合并连续文件事件的等待时间
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// 热加载句柄
+///
+/// 持有被监听的注册表，文件变化时在后台线程中重新加载。Drop 后停止监听。
+pub struct ReloadHandle<T> {
+    target: Arc<RwLock<T>>,
+    version: watch::Receiver<u64>,
+    _watcher: RecommendedWatcher,
+}
+
+impl<T> ReloadHandle<T> {
+    /// 共享的注册表，每次读取都能拿到最新版本
+    pub fn registry(&self) -> Arc<RwLock<T>> {
+        Arc::clone(&self.target)
+    }
+
+    /// 订阅重新加载事件，值为成功加载的次数
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.version.clone()
+    }
+}
+
+/// 监听 `path`，变化时调用 `reload`
+///
+/// `reload` 返回新值时替换注册表内容；返回错误时保留旧版本并记录日志。
+pub(crate) fn watch_path<T, F>(path: &Path, initial: T, reload: F) -> Result<ReloadHandle<T>>
+where
+    T: Send + Sync + 'static,
+    F: Fn(&Path) -> Result<T> + Send + 'static,
+{
+    let path: PathBuf = path.to_path_buf();
+    let target = Arc::new(RwLock::new(initial));
+    let (version_tx, version) = watch::channel(0u64);
+    let (event_tx, event_rx) = mpsc::channel();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|event| !event.kind.is_access()) {
+            let _ = event_tx.send(());
+        }
+    })
+    .map_err(|e| AgentFlowError::Other(anyhow!("Failed to create watcher: {}", e)))?;
+    watcher
+        .watch(&path, RecursiveMode::Recursive)
+        .map_err(|e| {
+            AgentFlowError::Other(anyhow!("Failed to watch `{}`: {}", path.display(), e))
+        })?;
+
+    let shared = Arc::clone(&target);
+    thread::spawn(move || {
+        // watcher 被 drop 后发送端关闭，recv 返回错误，线程退出
+        while event_rx.recv().is_ok() {
+            thread::sleep(DEBOUNCE);
+            while event_rx.try_recv().is_ok() {}
+            match reload(&path) {
+                Ok(value) => {
+                    *shared.write() = value;
+                    version_tx.send_modify(|v| *v += 1);
+                    tracing::info!(path = %path.display(), "hot reload applied");
+                }
+                Err(err) => {
+                    tracing::warn!(path = %path.display(), error = %err, "hot reload failed; keeping previous version");
+                }
+            }
+        }
+    });
+
+    Ok(ReloadHandle {
+        target,
+        version,
+        _watcher: watcher,
+    })
+}